@@ -114,7 +114,7 @@ impl Display for LogLevel {
 	}
 }
 
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(version)]
 pub struct CliOpts {
 	/// Path to the yaml configuration file
@@ -147,6 +147,9 @@ pub struct CliOpts {
 	/// Enable websocket transport
 	#[arg(long, value_name = "ws_transport_enable")]
 	pub ws_transport_enable: bool,
+	/// Run without the full node, receiving headers from peers and fetching data from DHT only
+	#[arg(long, value_name = "dht_only")]
+	pub dht_only: bool,
 	/// Log level
 	#[arg(long)]
 	pub verbosity: Option<LogLevel>,
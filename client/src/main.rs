@@ -2,15 +2,20 @@
 
 use crate::cli::{CliOpts, Network};
 use avail_light_core::{
+	api::v2::admin::{self, LogLevelReload},
 	data::{ClientIdKey, Database, LatestHeaderKey, P2PKeypairKey, RocksDB},
+	ledger::{self, NodeEvent},
 	network::{p2p, rpc},
+	proof,
 	shutdown::Controller,
 	telemetry::{self, otlp::MetricAttributes, MetricCounter, Metrics},
 	types::{
-		load_or_init_suri, IdentifyConfig, IdentityConfig, KademliaMode, LibP2PConfig,
-		MaintenanceConfig, MultiaddrConfig, OtelConfig, RuntimeConfig, SecretKey, Uuid,
+		load_or_init_suri, BootstrapConfig, IdentifyConfig, IdentityConfig, KademliaMode,
+		LibP2PConfig, MaintenanceConfig, MultiaddrConfig, OtelConfig, RuntimeConfig, SecretKey,
+		Uuid,
 	},
-	utils::spawn_in_span,
+	utils::{override_with_vars, parse_config, spawn_in_span},
+	validation,
 };
 use clap::Parser;
 use color_eyre::{
@@ -23,10 +28,15 @@ use libp2p::{
 	multiaddr::Protocol,
 	Multiaddr, PeerId,
 };
-use std::{fs, net::Ipv4Addr, path::Path, str::FromStr, sync::Arc};
-use tokio::sync::{broadcast, mpsc};
+use opentelemetry_sdk::trace::Tracer;
+use std::{
+	collections::HashMap, fs, net::Ipv4Addr, path::Path, str::FromStr, sync::Arc, time::Duration,
+};
+use tokio::sync::{broadcast, Notify};
 use tracing::{error, info, metadata::ParseLevelError, span, warn, Level, Subscriber};
-use tracing_subscriber::{fmt::format, EnvFilter, FmtSubscriber};
+use tracing_subscriber::{
+	filter::Directive, fmt::format, layer::SubscriberExt, reload, EnvFilter, FmtSubscriber,
+};
 
 #[cfg(not(feature = "crawl"))]
 use avail_core::AppId;
@@ -37,8 +47,10 @@ use avail_light_core::{
 	consts::EXPECTED_SYSTEM_VERSION,
 	data::{IsFinalitySyncedKey, IsSyncedKey},
 	network,
+	sampling::Sampler,
 	sync_client::SyncClient,
 	sync_finality::SyncFinality,
+	types::SyncClientConfig,
 };
 
 #[cfg(not(feature = "crawl"))]
@@ -58,21 +70,75 @@ use tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+/// Prefix of the environment variables which override the configuration parameters
+const CONFIG_VARS_PREFIX: &str = "AVAIL_LC_";
+
 /// Light Client for Avail Blockchain
 
-fn json_subscriber(log_level: Level) -> impl Subscriber + Send + Sync {
-	FmtSubscriber::builder()
+/// Filters the logs by the log level, overridden by the per-module log levels
+fn env_filter(log_level: Level, module_levels: &[Directive]) -> EnvFilter {
+	module_levels.iter().cloned().fold(
+		EnvFilter::new(format!("avail_light={log_level}")),
+		EnvFilter::add_directive,
+	)
+}
+
+/// Parses the per-module log levels into filter directives, returning the invalid ones separately
+fn parse_module_levels(module_levels: &HashMap<String, String>) -> (Vec<Directive>, Vec<String>) {
+	let mut directives = vec![];
+	let mut invalid = vec![];
+	for (module, level) in module_levels {
+		match format!("{module}={level}").parse::<Directive>() {
+			Ok(directive) => directives.push(directive),
+			Err(error) => invalid.push(format!("{module} = {level:?} ({error})")),
+		}
+	}
+	(directives, invalid)
+}
+
+fn log_level_reload<S: 'static>(
+	handle: reload::Handle<EnvFilter, S>,
+	module_levels: Vec<Directive>,
+) -> LogLevelReload {
+	Arc::new(move |log_level| -> Result<()> {
+		handle.reload(env_filter(log_level, &module_levels))?;
+		Ok(())
+	})
+}
+
+/// Layer which exports the spans with the tracer, if traces are enabled
+fn traces_layer<S>(tracer: Option<Tracer>) -> impl tracing_subscriber::Layer<S>
+where
+	S: Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+	tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+fn json_subscriber(
+	log_level: Level,
+	module_levels: Vec<Directive>,
+	tracer: Option<Tracer>,
+) -> (impl Subscriber + Send + Sync, LogLevelReload) {
+	let builder = FmtSubscriber::builder()
 		.json()
-		.with_env_filter(EnvFilter::new(format!("avail_light={log_level}")))
+		.with_env_filter(env_filter(log_level, &module_levels))
 		.with_span_events(format::FmtSpan::CLOSE)
-		.finish()
+		.with_filter_reloading();
+	let reload = log_level_reload(builder.reload_handle(), module_levels);
+	(builder.finish().with(traces_layer(tracer)), reload)
 }
 
-fn default_subscriber(log_level: Level) -> impl Subscriber + Send + Sync {
-	FmtSubscriber::builder()
-		.with_env_filter(EnvFilter::new(format!("avail_light={log_level}")))
+fn default_subscriber(
+	log_level: Level,
+	module_levels: Vec<Directive>,
+	tracer: Option<Tracer>,
+) -> (impl Subscriber + Send + Sync, LogLevelReload) {
+	let builder = FmtSubscriber::builder()
+		.with_env_filter(env_filter(log_level, &module_levels))
 		.with_span_events(format::FmtSpan::CLOSE)
-		.finish()
+		.with_filter_reloading();
+	let reload = log_level_reload(builder.reload_handle(), module_levels);
+	(builder.finish().with(traces_layer(tracer)), reload)
 }
 
 fn parse_log_level(log_level: &str, default: Level) -> (Level, Option<ParseLevelError>) {
@@ -106,6 +172,9 @@ async fn run(
 	shutdown: Controller<String>,
 	client_id: Uuid,
 	execution_id: Uuid,
+	log_level_reload: LogLevelReload,
+	reload_config: impl Fn() -> Result<RuntimeConfig> + Send + 'static,
+	hangup: Arc<Notify>,
 ) -> Result<()> {
 	let version = clap::crate_version!();
 	info!("Running Avail Light Client version: {version}.");
@@ -120,7 +189,7 @@ async fn run(
 	}
 
 	let identify = IdentifyConfig::new(version.to_string());
-	let cfg_libp2p: LibP2PConfig = (&cfg, identify).into();
+	let cfg_libp2p = LibP2PConfig::try_from((&cfg, identify))?;
 	let id_keys = get_or_init_p2p_keypair(&cfg_libp2p, db.clone())?;
 	let peer_id = PeerId::from(id_keys.public()).to_string();
 
@@ -154,7 +223,7 @@ async fn run(
 	);
 
 	// Create sender channel for P2P event loop commands
-	let (p2p_event_loop_sender, p2p_event_loop_receiver) = mpsc::unbounded_channel();
+	let (p2p_event_loop_sender, p2p_event_loop_receiver) = p2p::command_channel();
 
 	let p2p_event_loop = p2p::EventLoop::new(
 		cfg_libp2p,
@@ -163,38 +232,60 @@ async fn run(
 		cfg.ws_transport_enable,
 		shutdown.clone(),
 		cfg.operation_mode,
-		#[cfg(feature = "kademlia-rocksdb")]
 		db.inner(),
 	);
 
+	// event loop drains the pending queries on shutdown, instead of being cancelled
 	spawn_in_span(
-		shutdown.with_cancel(
-			p2p_event_loop
-				.await
-				.run(ot_metrics.clone(), p2p_event_loop_receiver),
-		),
+		p2p_event_loop
+			.await
+			.run(ot_metrics.clone(), p2p_event_loop_receiver),
 	);
 
 	let p2p_client = p2p::Client::new(
 		p2p_event_loop_sender,
 		cfg.dht_parallelization_limit,
+		cfg.dht_max_parallelization_limit(),
 		cfg.kad_record_ttl,
+		cfg.row_record_ttl(),
+		cfg.dht_put_quorum.into(),
+		cfg.dht_get_quorum.into(),
+		cfg.network_prefix.clone(),
+		cfg.dht_cell_cache_size,
+		cfg.sign_dht_records.then(|| id_keys.clone()),
+		cfg.require_signed_dht_records,
 	);
+	shutdown_network_on_trigger(shutdown.clone(), p2p_client.clone());
 
-	// Start listening on provided port
 	p2p_client
-		.start_listening(construct_multiaddress(cfg.ws_transport_enable, cfg.port))
+		.restore_blocked_peers(db.clone())
 		.await
-		.wrap_err("Listening on TCP not to fail.")?;
-	info!("TCP listener started on port {}", cfg.port);
+		.wrap_err("Restoring blocked peers not to fail.")?;
+
+	// Start listening on configured addresses, or on provided port
+	for address in listen_addresses(&cfg) {
+		p2p_client
+			.start_listening(address.clone())
+			.await
+			.wrap_err("Listening on TCP not to fail.")?;
+		info!("Listener started on {address}");
+	}
 
 	let p2p_clone = p2p_client.to_owned();
 	let cfg_clone = cfg.to_owned();
+	let db_clone = db.clone();
 	spawn_in_span(shutdown.with_cancel(async move {
 		info!("Bootstraping the DHT with bootstrap nodes...");
+		let bootstraps: Vec<Multiaddr> = cfg_clone.bootstraps.iter().map(Into::into).collect();
 		let bs_result = p2p_clone
-			.bootstrap_on_startup(cfg_clone.bootstraps.iter().map(Into::into).collect())
+			.bootstrap_on_startup(p2p::resolve_bootstraps(&bootstraps).await)
 			.await;
+		ledger::record(
+			&db_clone,
+			NodeEvent::Bootstrap {
+				error: bs_result.as_ref().err().map(|error| format!("{error:#}")),
+			},
+		);
 		match bs_result {
 			Ok(_) => {
 				info!("Bootstrap done.");
@@ -203,6 +294,8 @@ async fn run(
 				warn!("Bootstrap process: {e:?}.");
 			},
 		}
+		let refresh_interval = Duration::from_secs(cfg_clone.bootstrap_dns_refresh_interval);
+		p2p::refresh_bootstraps(p2p_clone, bootstraps, refresh_interval).await;
 	}));
 
 	#[cfg(feature = "network-analysis")]
@@ -214,32 +307,87 @@ async fn run(
 	let public_params_len = hex::encode(raw_pp).len();
 	trace!("Public params ({public_params_len}): hash: {public_params_hash}");
 
-	let (rpc_client, rpc_events, rpc_subscriptions) = rpc::init(
-		db.clone(),
-		&cfg.full_node_ws,
-		&cfg.genesis_hash,
-		cfg.retry_config.clone(),
-		shutdown.clone(),
-	)
-	.await?;
+	// In the DHT-only mode, the node is never contacted and headers are received from peers
+	let (rpc, rpc_events) = if cfg.dht_only {
+		let (rpc_events, _) = broadcast::channel(1000);
+		(None, rpc_events)
+	} else {
+		let (rpc_client, rpc_events, mut rpc_subscriptions) = rpc::init(
+			db.clone(),
+			&cfg.full_node_ws,
+			&cfg.genesis_hash,
+			cfg.retry_config.clone(),
+			Duration::from_secs(cfg.rpc_health_check_interval),
+			cfg.rpc_cross_check,
+			cfg.rpc_rate_limit.clone(),
+			shutdown.clone(),
+		)
+		.await?;
+
+		// Missed blocks exceeding the backfill limit are processed by the historical sync, if it is enabled,
+		// otherwise all missed blocks are backfilled by the subscriptions
+		let missed_blocks_receiver = cfg
+			.sync_start_block
+			.is_some()
+			.then(|| rpc_subscriptions.historical_sync(cfg.max_backfill_blocks));
+		(
+			Some((rpc_client, rpc_subscriptions, missed_blocks_receiver)),
+			rpc_events,
+		)
+	};
 
 	// Subscribing to RPC events before first event is published
 	let publish_rpc_event_receiver = rpc_events.subscribe();
 	let first_header_rpc_event_receiver = rpc_events.subscribe();
 	let client_rpc_event_receiver = rpc_events.subscribe();
+	let announce_rpc_event_receiver = rpc_events.subscribe();
+	#[cfg(feature = "network-analysis")]
+	spawn_in_span(shutdown.with_cancel(analyzer::start_propagation_analyzer(
+		p2p_client.clone(),
+		rpc_events.subscribe(),
+		Default::default(),
+	)));
 
-	// spawn the RPC Network task for Event Loop to run in the background
-	// and shut it down, without delays
-	let rpc_subscriptions_handle = spawn_in_span(shutdown.with_cancel(shutdown.with_trigger(
-		"Subscription loop failure triggered shutdown".to_string(),
-		async {
-			let result = rpc_subscriptions.run().await;
-			if let Err(ref err) = result {
-				error!(%err, "Subscription loop ended with error");
-			};
-			result
+	let (rpc_client, missed_blocks_receiver, rpc_subscriptions_handle) = match rpc {
+		Some((rpc_client, rpc_subscriptions, missed_blocks_receiver)) => {
+			// spawn the RPC Network task for Event Loop to run in the background
+			// and shut it down, without delays
+			let handle = spawn_in_span(shutdown.with_cancel(shutdown.with_trigger(
+				"Subscription loop failure triggered shutdown".to_string(),
+				async move {
+					let result = rpc_subscriptions.run().await;
+					if let Err(ref err) = result {
+						error!(%err, "Subscription loop ended with error");
+					};
+					result
+				},
+			)));
+			(Some(rpc_client), missed_blocks_receiver, handle)
 		},
-	)));
+		None => {
+			let (p2p_client, db, rpc_events) = (p2p_client.clone(), db.clone(), rpc_events.clone());
+			let (finality_checkpoint, max_gap) =
+				(cfg.finality_checkpoint.clone(), cfg.max_backfill_blocks);
+			let handle = spawn_in_span(shutdown.with_cancel(shutdown.with_trigger(
+				"Block announcements failure triggered shutdown".to_string(),
+				async move {
+					let result = p2p::receive_block_announcements(
+						p2p_client,
+						db,
+						rpc_events,
+						finality_checkpoint,
+						max_gap,
+					)
+					.await;
+					if let Err(ref err) = result {
+						error!(%err, "Receiving block announcements ended with error");
+					};
+					result
+				},
+			)));
+			(None, None, handle)
+		},
+	};
 
 	info!("Waiting for first finalized header...");
 	let block_header = match shutdown
@@ -267,6 +415,23 @@ async fn run(
 	let sync_range = cfg.sync_range(block_header.number);
 
 	let ws_clients = api::v2::types::WsClients::default();
+	let maintenance_stats = avail_light_core::maintenance::Stats::default();
+	let (confidence_sender, confidence_receiver) = tokio::sync::watch::channel(cfg.confidence);
+	let static_config_params: MaintenanceConfig = (&cfg).into();
+	let (server_mode_thresholds_sender, server_mode_thresholds_receiver) =
+		tokio::sync::watch::channel((&static_config_params).into());
+
+	let admin = admin::Admin::new(
+		&cfg,
+		db.clone(),
+		p2p_client.clone(),
+		rpc_client.clone(),
+		log_level_reload,
+		confidence_sender,
+		server_mode_thresholds_sender,
+	);
+	let submissions = api::v2::submissions::Submissions::new(db.clone());
+	let (block_tx, block_rx) = broadcast::channel::<avail_light_core::types::BlockVerified>(1 << 7);
 
 	// Spawn tokio task which runs one http server for handling RPC
 	let server = api::server::Server {
@@ -279,19 +444,32 @@ async fn run(
 		ws_clients: ws_clients.clone(),
 		shutdown: shutdown.clone(),
 		p2p_client: p2p_client.clone(),
+		maintenance_stats: maintenance_stats.clone(),
+		admin: admin.clone(),
+		submissions: submissions.clone(),
+		block_sender: block_tx.clone(),
 	};
 	spawn_in_span(shutdown.with_cancel(server.bind()));
 
-	let (block_tx, block_rx) = broadcast::channel::<avail_light_core::types::BlockVerified>(1 << 7);
+	// headers received from peers are announced only by the nodes which received them from the full node
+	if rpc_client.is_some() {
+		spawn_in_span(shutdown.with_cancel(p2p::announce_blocks(
+			p2p_client.clone(),
+			announce_rpc_event_receiver,
+		)));
+	}
+
+	let (reorg_tx, reorg_rx) = broadcast::channel::<avail_light_core::types::Reorg>(1 << 7);
 
-	let data_rx = cfg.app_id.map(AppId).map(|app_id| {
-		let (data_tx, data_rx) = broadcast::channel::<(u32, AppData)>(1 << 7);
+	let app_ids = cfg.app_ids().into_iter().map(AppId).collect::<Vec<_>>();
+	let data_rx = (!app_ids.is_empty()).then(|| {
+		let (data_tx, data_rx) = broadcast::channel::<(u32, AppId, AppData)>(1 << 7);
 		spawn_in_span(shutdown.with_cancel(avail_light_core::app_client::run(
 			(&cfg).into(),
 			db.clone(),
 			p2p_client.clone(),
 			rpc_client.clone(),
-			app_id,
+			app_ids,
 			block_tx.subscribe(),
 			pp.clone(),
 			sync_range.clone(),
@@ -313,6 +491,33 @@ async fn run(
 		ws_clients.clone(),
 	)));
 
+	spawn_in_span(shutdown.with_cancel(api::v2::publish(
+		api::v2::types::Topic::Reorg,
+		reorg_rx,
+		ws_clients.clone(),
+	)));
+
+	spawn_in_span(shutdown.with_cancel(api::v2::publish(
+		api::v2::types::Topic::SubmissionUpdated,
+		submissions.subscribe(),
+		ws_clients.clone(),
+	)));
+
+	spawn_in_span(shutdown.with_cancel(submissions.run(block_tx.subscribe())));
+
+	#[cfg(feature = "grpc")]
+	if let Some(grpc_server) = (api::grpc::Server {
+		db: db.clone(),
+		cfg: cfg.clone(),
+		p2p_client: p2p_client.clone(),
+		block_sender: block_tx.clone(),
+		shutdown: shutdown.clone(),
+	})
+	.bind()
+	{
+		spawn_in_span(shutdown.with_cancel(grpc_server));
+	}
+
 	if let Some(data_rx) = data_rx {
 		spawn_in_span(shutdown.with_cancel(api::v2::publish(
 			api::v2::types::Topic::DataVerified,
@@ -321,32 +526,78 @@ async fn run(
 		)));
 	}
 
-	let sync_client = SyncClient::new(db.clone(), rpc_client.clone());
+	// historical and finality sync need the node, so they are disabled in the DHT-only mode
+	if let Some(rpc_client) = rpc_client.clone() {
+		let sync_client = SyncClient::new(db.clone(), rpc_client.clone());
 
-	let sync_network_client = network::new(
-		p2p_client.clone(),
-		rpc_client.clone(),
-		pp.clone(),
-		cfg.disable_rpc,
-	);
+		let sync_network_client = network::new(
+			p2p_client.clone(),
+			Some(rpc_client.clone()),
+			pp.clone(),
+			cfg.disable_rpc,
+			cfg.dht_provider_mode,
+			None,
+			cfg.fetch_strategy,
+		);
 
-	if cfg.sync_start_block.is_some() {
-		db.put(IsSyncedKey, false);
-		spawn_in_span(shutdown.with_cancel(avail_light_core::sync_client::run(
-			sync_client,
-			sync_network_client,
-			(&cfg).into(),
-			sync_range,
-			block_tx.clone(),
-		)));
+		if cfg.sync_start_block.is_some() {
+			db.put(IsSyncedKey, false);
+			spawn_in_span(shutdown.with_cancel(avail_light_core::sync_client::run(
+				sync_client,
+				sync_network_client,
+				(&cfg).into(),
+				sync_range,
+				block_tx.clone(),
+			)));
+		}
+
+		// missed blocks are handed over only if the historical sync is enabled
+		if let Some(mut missed_blocks_receiver) = missed_blocks_receiver {
+			let missed_blocks_sync_client = SyncClient::new(db.clone(), rpc_client.clone());
+			// filling the gaps doesn't complete the historical sync, so the synced flag is not set
+			let missed_blocks_sync_config = SyncClientConfig {
+				is_last_step: false,
+				..(&cfg).into()
+			};
+			let (p2p_client_clone, rpc_client_clone, pp_clone, block_tx_clone) = (
+				p2p_client.clone(),
+				rpc_client.clone(),
+				pp.clone(),
+				block_tx.clone(),
+			);
+			let (disable_rpc, dht_provider_mode, fetch_strategy) =
+				(cfg.disable_rpc, cfg.dht_provider_mode, cfg.fetch_strategy);
+			spawn_in_span(shutdown.with_cancel(async move {
+				while let Some(missed_blocks) = missed_blocks_receiver.recv().await {
+					let network_client = network::new(
+						p2p_client_clone.clone(),
+						Some(rpc_client_clone.clone()),
+						pp_clone.clone(),
+						disable_rpc,
+						dht_provider_mode,
+						None,
+						fetch_strategy,
+					);
+					avail_light_core::sync_client::run(
+						missed_blocks_sync_client.clone(),
+						network_client,
+						missed_blocks_sync_config.clone(),
+						missed_blocks,
+						block_tx_clone.clone(),
+					)
+					.await;
+				}
+			}));
+		}
 	}
 
-	if cfg.sync_finality_enable {
-		let sync_finality = SyncFinality::new(db.clone(), rpc_client.clone());
+	if let Some(rpc_client) = rpc_client.clone().filter(|_| cfg.sync_finality_enable) {
+		let sync_finality = SyncFinality::new(db.clone(), rpc_client);
 		spawn_in_span(shutdown.with_cancel(avail_light_core::sync_finality::run(
 			sync_finality,
 			shutdown.clone(),
 			block_header.clone(),
+			cfg.finality_checkpoint.clone(),
 		)));
 	} else {
 		warn!("Finality sync is disabled! Implicitly, blocks before LC startup will be considered verified as final");
@@ -354,22 +605,45 @@ async fn run(
 		db.put(IsFinalitySyncedKey, true);
 	}
 
-	let static_config_params: MaintenanceConfig = (&cfg).into();
 	spawn_in_span(shutdown.with_cancel(avail_light_core::maintenance::run(
 		p2p_client.clone(),
 		ot_metrics.clone(),
+		db.clone(),
 		block_rx,
 		static_config_params,
+		server_mode_thresholds_receiver,
+		maintenance_stats,
 		shutdown.clone(),
 	)));
 
+	spawn_in_span(shutdown.with_cancel(reload_on_hangup(
+		cfg.clone(),
+		admin.clone(),
+		reload_config,
+		hangup,
+	)));
+
+	// sampling results are consumed by the subscribers created before the sampling starts
+	let sampler = Sampler::new((&cfg).into()).with_target_confidence(confidence_receiver);
+
+	if let Some(alerts) = cfg.alerts.clone() {
+		spawn_in_span(shutdown.with_cancel(avail_light_core::alerts::run(
+			alerts,
+			db.clone(),
+			p2p_client.clone(),
+			rpc_client.clone(),
+			sampler.subscribe(),
+		)));
+	}
+
 	let channels = avail_light_core::types::ClientChannels {
 		block_sender: block_tx,
 		rpc_event_receiver: client_rpc_event_receiver,
+		reorg_sender: reorg_tx,
 	};
 
-	if let Some(partition) = cfg.block_matrix_partition {
-		let fat_client = avail_light_core::fat_client::new(p2p_client.clone(), rpc_client.clone());
+	if let Some((partition, rpc_client)) = cfg.block_matrix_partition.zip(rpc_client.clone()) {
+		let fat_client = avail_light_core::fat_client::new(p2p_client.clone(), rpc_client);
 
 		spawn_in_span(shutdown.with_cancel(avail_light_core::fat_client::run(
 			fat_client,
@@ -381,11 +655,20 @@ async fn run(
 			shutdown.clone(),
 		)));
 	} else {
-		let light_network_client = network::new(p2p_client, rpc_client, pp, cfg.disable_rpc);
+		let light_network_client = network::new(
+			p2p_client,
+			rpc_client,
+			pp,
+			cfg.disable_rpc,
+			cfg.dht_provider_mode,
+			(cfg.dht_fetch_timeout > 0).then(|| Duration::from_secs(cfg.dht_fetch_timeout)),
+			cfg.fetch_strategy,
+		);
 
 		spawn_in_span(shutdown.with_cancel(avail_light_core::light_client::run(
 			db.clone(),
 			light_network_client,
+			sampler,
 			(&cfg).into(),
 			ot_metrics.clone(),
 			channels,
@@ -420,7 +703,7 @@ async fn run_crawl(
 	}
 
 	let identify = IdentifyConfig::new(version.to_string());
-	let cfg_libp2p: LibP2PConfig = (&cfg, identify).into();
+	let cfg_libp2p = LibP2PConfig::try_from((&cfg, identify))?;
 	let id_keys = get_or_init_p2p_keypair(&cfg_libp2p, db.clone())?;
 	let peer_id = PeerId::from(id_keys.public()).to_string();
 
@@ -455,7 +738,7 @@ async fn run_crawl(
 	);
 
 	// Create sender channel for P2P event loop commands
-	let (p2p_event_loop_sender, p2p_event_loop_receiver) = mpsc::unbounded_channel();
+	let (p2p_event_loop_sender, p2p_event_loop_receiver) = p2p::command_channel();
 
 	let p2p_event_loop = p2p::EventLoop::new(
 		cfg_libp2p,
@@ -464,38 +747,60 @@ async fn run_crawl(
 		cfg.ws_transport_enable,
 		shutdown.clone(),
 		KademliaMode::Client,
-		#[cfg(feature = "kademlia-rocksdb")]
 		db.inner(),
 	);
 
+	// event loop drains the pending queries on shutdown, instead of being cancelled
 	spawn_in_span(
-		shutdown.with_cancel(
-			p2p_event_loop
-				.await
-				.run(ot_metrics.clone(), p2p_event_loop_receiver),
-		),
+		p2p_event_loop
+			.await
+			.run(ot_metrics.clone(), p2p_event_loop_receiver),
 	);
 
 	let p2p_client = p2p::Client::new(
 		p2p_event_loop_sender,
 		cfg.dht_parallelization_limit,
+		cfg.dht_max_parallelization_limit(),
 		cfg.kad_record_ttl,
+		cfg.row_record_ttl(),
+		cfg.dht_put_quorum.into(),
+		cfg.dht_get_quorum.into(),
+		cfg.network_prefix.clone(),
+		cfg.dht_cell_cache_size,
+		cfg.sign_dht_records.then(|| id_keys.clone()),
+		cfg.require_signed_dht_records,
 	);
+	shutdown_network_on_trigger(shutdown.clone(), p2p_client.clone());
 
-	// Start listening on provided port
 	p2p_client
-		.start_listening(construct_multiaddress(cfg.ws_transport_enable, cfg.port))
+		.restore_blocked_peers(db.clone())
 		.await
-		.wrap_err("Listening on TCP not to fail.")?;
-	info!("TCP listener started on port {}", cfg.port);
+		.wrap_err("Restoring blocked peers not to fail.")?;
+
+	// Start listening on configured addresses, or on provided port
+	for address in listen_addresses(&cfg) {
+		p2p_client
+			.start_listening(address.clone())
+			.await
+			.wrap_err("Listening on TCP not to fail.")?;
+		info!("Listener started on {address}");
+	}
 
 	let p2p_clone = p2p_client.to_owned();
 	let cfg_clone = cfg.to_owned();
+	let db_clone = db.clone();
 	spawn_in_span(shutdown.with_cancel(async move {
 		info!("Bootstraping the DHT with bootstrap nodes...");
+		let bootstraps: Vec<Multiaddr> = cfg_clone.bootstraps.iter().map(Into::into).collect();
 		let bs_result = p2p_clone
-			.bootstrap_on_startup(cfg_clone.bootstraps.iter().map(Into::into).collect())
+			.bootstrap_on_startup(p2p::resolve_bootstraps(&bootstraps).await)
 			.await;
+		ledger::record(
+			&db_clone,
+			NodeEvent::Bootstrap {
+				error: bs_result.as_ref().err().map(|error| format!("{error:#}")),
+			},
+		);
 		match bs_result {
 			Ok(_) => {
 				info!("Bootstrap done.");
@@ -504,6 +809,8 @@ async fn run_crawl(
 				warn!("Bootstrap process: {e:?}.");
 			},
 		}
+		let refresh_interval = Duration::from_secs(cfg_clone.bootstrap_dns_refresh_interval);
+		p2p::refresh_bootstraps(p2p_clone, bootstraps, refresh_interval).await;
 	}));
 
 	let (_, rpc_events, rpc_subscriptions) = rpc::init(
@@ -511,6 +818,9 @@ async fn run_crawl(
 		&cfg.full_node_ws,
 		&cfg.genesis_hash,
 		cfg.retry_config.clone(),
+		Duration::from_secs(cfg.rpc_health_check_interval),
+		cfg.rpc_cross_check,
+		cfg.rpc_rate_limit.clone(),
 		shutdown.clone(),
 	)
 	.await?;
@@ -571,12 +881,27 @@ async fn run_crawl(
 		)));
 	}
 
+	if cfg.crawl.crawl_dht {
+		spawn_in_span(shutdown.with_cancel(p2p::crawler::run(
+			p2p_client.clone(),
+			ot_metrics.clone(),
+			Duration::from_secs(cfg.crawl.crawl_dht_interval),
+			cfg.crawl.crawl_dht_max_lookups,
+			cfg.crawl.crawl_dht_topology_path.clone(),
+		)));
+	}
+
 	let static_config_params: MaintenanceConfig = (&cfg).into();
+	// thresholds are not changed at runtime
+	let server_mode_thresholds = (&static_config_params).into();
 	spawn_in_span(shutdown.with_cancel(avail_light_core::maintenance::run(
 		p2p_client.clone(),
 		ot_metrics.clone(),
+		db.clone(),
 		block_rx,
 		static_config_params,
+		tokio::sync::watch::channel(server_mode_thresholds).1,
+		Default::default(),
 		shutdown.clone(),
 	)));
 
@@ -605,7 +930,7 @@ async fn run_fat(
 	}
 
 	let identify = IdentifyConfig::new(version.to_string());
-	let cfg_libp2p: LibP2PConfig = (&cfg, identify).into();
+	let cfg_libp2p = LibP2PConfig::try_from((&cfg, identify))?;
 	let id_keys = get_or_init_p2p_keypair(&cfg_libp2p, db.clone())?;
 	let peer_id = PeerId::from(id_keys.public()).to_string();
 
@@ -639,47 +964,69 @@ async fn run_fat(
 	);
 
 	// Create sender channel for P2P event loop commands
-	let (p2p_event_loop_sender, p2p_event_loop_receiver) = mpsc::unbounded_channel();
+	let (p2p_event_loop_sender, p2p_event_loop_receiver) = p2p::command_channel();
 
-	let p2p_event_loop = p2p::EventLoop::new(
+	let mut p2p_event_loop = p2p::EventLoop::new(
 		cfg_libp2p,
 		&id_keys,
 		cfg.is_fat_client(),
 		cfg.ws_transport_enable,
 		shutdown.clone(),
 		KademliaMode::Client,
-		#[cfg(feature = "kademlia-rocksdb")]
 		db.inner(),
-	);
+	)
+	.await;
+	let unverified_cells_receiver = cfg
+		.verify_inbound_cell_proofs
+		.then(|| p2p_event_loop.verify_inbound_cells());
 
-	spawn_in_span(
-		shutdown.with_cancel(
-			p2p_event_loop
-				.await
-				.run(ot_metrics.clone(), p2p_event_loop_receiver),
-		),
-	);
+	// event loop drains the pending queries on shutdown, instead of being cancelled
+	spawn_in_span(p2p_event_loop.run(ot_metrics.clone(), p2p_event_loop_receiver));
 
 	let p2p_client = p2p::Client::new(
 		p2p_event_loop_sender,
 		cfg.dht_parallelization_limit,
+		cfg.dht_max_parallelization_limit(),
 		cfg.kad_record_ttl,
+		cfg.row_record_ttl(),
+		cfg.dht_put_quorum.into(),
+		cfg.dht_get_quorum.into(),
+		cfg.network_prefix.clone(),
+		cfg.dht_cell_cache_size,
+		cfg.sign_dht_records.then(|| id_keys.clone()),
+		cfg.require_signed_dht_records,
 	);
+	shutdown_network_on_trigger(shutdown.clone(), p2p_client.clone());
 
-	// Start listening on provided port
 	p2p_client
-		.start_listening(construct_multiaddress(cfg.ws_transport_enable, cfg.port))
+		.restore_blocked_peers(db.clone())
 		.await
-		.wrap_err("Listening on TCP not to fail.")?;
-	info!("TCP listener started on port {}", cfg.port);
+		.wrap_err("Restoring blocked peers not to fail.")?;
+
+	// Start listening on configured addresses, or on provided port
+	for address in listen_addresses(&cfg) {
+		p2p_client
+			.start_listening(address.clone())
+			.await
+			.wrap_err("Listening on TCP not to fail.")?;
+		info!("Listener started on {address}");
+	}
 
 	let p2p_clone = p2p_client.to_owned();
 	let cfg_clone = cfg.to_owned();
+	let db_clone = db.clone();
 	spawn_in_span(shutdown.with_cancel(async move {
 		info!("Bootstraping the DHT with bootstrap nodes...");
+		let bootstraps: Vec<Multiaddr> = cfg_clone.bootstraps.iter().map(Into::into).collect();
 		let bs_result = p2p_clone
-			.bootstrap_on_startup(cfg_clone.bootstraps.iter().map(Into::into).collect())
+			.bootstrap_on_startup(p2p::resolve_bootstraps(&bootstraps).await)
 			.await;
+		ledger::record(
+			&db_clone,
+			NodeEvent::Bootstrap {
+				error: bs_result.as_ref().err().map(|error| format!("{error:#}")),
+			},
+		);
 		match bs_result {
 			Ok(_) => {
 				info!("Bootstrap done.");
@@ -688,6 +1035,8 @@ async fn run_fat(
 				warn!("Bootstrap process: {e:?}.");
 			},
 		}
+		let refresh_interval = Duration::from_secs(cfg_clone.bootstrap_dns_refresh_interval);
+		p2p::refresh_bootstraps(p2p_clone, bootstraps, refresh_interval).await;
 	}));
 
 	let (rpc_client, rpc_events, rpc_subscriptions) = rpc::init(
@@ -695,6 +1044,9 @@ async fn run_fat(
 		&cfg.full_node_ws,
 		&cfg.genesis_hash,
 		cfg.retry_config.clone(),
+		Duration::from_secs(cfg.rpc_health_check_interval),
+		cfg.rpc_cross_check,
+		cfg.rpc_rate_limit.clone(),
 		shutdown.clone(),
 	)
 	.await?;
@@ -703,6 +1055,16 @@ async fn run_fat(
 	let first_header_rpc_event_receiver = rpc_events.subscribe();
 	let client_rpc_event_receiver = rpc_events.subscribe();
 
+	if let Some(receiver) = unverified_cells_receiver {
+		spawn_in_span(shutdown.with_cancel(p2p::record_verification::run(
+			p2p_client.clone(),
+			db.clone(),
+			Arc::new(kate_recovery::couscous::public_params()),
+			rpc_events.subscribe(),
+			receiver,
+		)));
+	}
+
 	// spawn the RPC Network task for Event Loop to run in the background
 	// and shut it down, without delays
 	let rpc_subscriptions_handle = spawn_in_span(shutdown.with_cancel(shutdown.with_trigger(
@@ -748,6 +1110,7 @@ async fn run_fat(
 			sync_finality,
 			shutdown.clone(),
 			block_header.clone(),
+			cfg.finality_checkpoint.clone(),
 		)));
 	} else {
 		warn!("Finality sync is disabled! Implicitly, blocks before LC startup will be considered verified as final");
@@ -756,17 +1119,23 @@ async fn run_fat(
 	}
 
 	let static_config_params: MaintenanceConfig = (&cfg).into();
+	// thresholds are not changed at runtime
+	let server_mode_thresholds = (&static_config_params).into();
 	spawn_in_span(shutdown.with_cancel(avail_light_core::maintenance::run(
 		p2p_client.clone(),
 		ot_metrics.clone(),
+		db.clone(),
 		block_rx,
 		static_config_params,
+		tokio::sync::watch::channel(server_mode_thresholds).1,
+		Default::default(),
 		shutdown.clone(),
 	)));
 
 	let channels = avail_light_core::types::ClientChannels {
 		block_sender: block_tx,
 		rpc_event_receiver: client_rpc_event_receiver,
+		reorg_sender: broadcast::channel(1 << 7).0,
 	};
 
 	if let Some(partition) = cfg.block_matrix_partition {
@@ -800,6 +1169,13 @@ fn construct_multiaddress(is_websocket: bool, port: u16) -> Multiaddr {
 	tcp_multiaddress
 }
 
+fn listen_addresses(cfg: &RuntimeConfig) -> Vec<Multiaddr> {
+	if cfg.listen_addresses.is_empty() {
+		return vec![construct_multiaddress(cfg.ws_transport_enable, cfg.port)];
+	}
+	cfg.listen_addresses.clone()
+}
+
 fn install_panic_hooks(shutdown: Controller<String>) -> Result<()> {
 	// initialize color-eyre hooks
 	let (panic_hook, eyre_hook) = color_eyre::config::HookBuilder::default()
@@ -872,17 +1248,85 @@ async fn user_signal() {
 	}
 }
 
+/// Shuts the network down gracefully once the shutdown is triggered,
+/// so the pending DHT queries are drained and the record store is flushed before the shutdown completes.
+fn shutdown_network_on_trigger(shutdown: Controller<String>, p2p_client: p2p::Client) {
+	spawn_in_span(async move {
+		shutdown.triggered_shutdown().await;
+		match p2p_client.shutdown().await {
+			Ok(()) => info!("Network is shut down"),
+			Err(error) => warn!("Network is not shut down gracefully: {error:#}"),
+		}
+	});
+}
+
+/// Installs the SIGHUP handler, so the signal doesn't terminate the process in any mode or during the startup.
+/// Each SIGHUP notifies the configuration reload, signals received before the reload is running are kept as a single permit.
+fn handle_hangup() -> Arc<Notify> {
+	let hangup = Arc::new(Notify::new());
+
+	#[cfg(unix)]
+	{
+		use tokio::signal::unix::{signal, SignalKind};
+
+		match signal(SignalKind::hangup()) {
+			Ok(mut signal) => {
+				let hangup = hangup.clone();
+				tokio::spawn(async move {
+					while signal.recv().await.is_some() {
+						hangup.notify_one();
+					}
+				});
+			},
+			Err(error) => warn!("Configuration cannot be reloaded on SIGHUP: {error}"),
+		}
+	}
+
+	hangup
+}
+
+/// Reloads the configuration on each SIGHUP, applying the changed runtime parameters.
+/// Reload is rejected if any parameter which requires the restart is changed.
+#[cfg(not(feature = "crawl"))]
+async fn reload_on_hangup(
+	cfg: RuntimeConfig,
+	admin: admin::Admin<RocksDB>,
+	reload_config: impl Fn() -> Result<RuntimeConfig>,
+	hangup: Arc<Notify>,
+) {
+	loop {
+		hangup.notified().await;
+		info!("Reloading configuration...");
+		let result = match reload_config() {
+			Ok(reloaded) => admin.reload(&cfg, reloaded).await,
+			Err(error) => Err(error),
+		};
+		match result {
+			Ok(parameters) => info!(?parameters, "Configuration reloaded"),
+			Err(error) => error!("Configuration is not reloaded: {error:#}"),
+		}
+	}
+}
+
 mod cli;
 
 pub fn load_runtime_config(opts: &CliOpts) -> Result<RuntimeConfig> {
 	let mut cfg = if let Some(config_path) = &opts.config {
 		fs::metadata(config_path).map_err(|_| eyre!("Provided config file doesn't exist."))?;
-		confy::load_path(config_path)
+		let content = fs::read_to_string(config_path)
+			.wrap_err(format!("Failed to read configuration from {}", config_path))?;
+		parse_config(&content)
 			.wrap_err(format!("Failed to load configuration from {}", config_path))?
 	} else {
 		RuntimeConfig::default()
 	};
 
+	// Environment variables override the config file parameters
+	let vars = std::env::vars_os()
+		.filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+	cfg = override_with_vars(cfg, CONFIG_VARS_PREFIX, vars)?;
+	cfg.convert_deprecated_intervals();
+
 	// Flags override the config parameters
 	if let Some(network) = &opts.network {
 		let bootstrap: (PeerId, Multiaddr) = (
@@ -892,7 +1336,9 @@ pub fn load_runtime_config(opts: &CliOpts) -> Result<RuntimeConfig> {
 				.wrap_err("unable to parse default bootstrap multi-address")?,
 		);
 		cfg.full_node_ws = network.full_node_ws();
-		cfg.bootstraps = vec![MultiaddrConfig::PeerIdAndMultiaddr(bootstrap)];
+		cfg.bootstraps = vec![BootstrapConfig::Peer(MultiaddrConfig::PeerIdAndMultiaddr(
+			bootstrap,
+		))];
 		cfg.ot_collector_endpoint = network.ot_collector_endpoint().to_string();
 		cfg.genesis_hash = network.genesis_hash().to_string();
 	}
@@ -913,6 +1359,7 @@ pub fn load_runtime_config(opts: &CliOpts) -> Result<RuntimeConfig> {
 	cfg.sync_finality_enable |= opts.finality_sync_enable;
 	cfg.app_id = opts.app_id.or(cfg.app_id);
 	cfg.ws_transport_enable |= opts.ws_transport_enable;
+	cfg.dht_only |= opts.dht_only;
 	if let Some(secret_key) = &opts.private_key {
 		cfg.secret_key = Some(SecretKey::Key {
 			key: secret_key.to_string(),
@@ -933,6 +1380,18 @@ pub fn load_runtime_config(opts: &CliOpts) -> Result<RuntimeConfig> {
 		cfg.client_alias = Some(client_alias.clone())
 	}
 
+	if cfg.dht_only {
+		if cfg.is_fat_client() {
+			Err(eyre!(
+				"Fat client fetches cells from the full node and can't run in the DHT-only mode"
+			))?
+		}
+		// historical and finality sync need the node, while the data is fetched only from DHT
+		cfg.disable_rpc = true;
+		cfg.sync_start_block = None;
+		cfg.sync_finality_enable = false;
+	}
+
 	Ok(cfg)
 }
 
@@ -945,17 +1404,42 @@ pub async fn main() -> Result<()> {
 
 	let opts = CliOpts::parse();
 
-	let cfg = load_runtime_config(&opts).expect("runtime configuration is loaded");
+	let mut cfg = load_runtime_config(&opts)?;
+	validation::validate(&cfg)?;
 
 	let (log_level, parse_error) = parse_log_level(&cfg.log_level, Level::INFO);
+	let (module_levels, invalid_module_levels) = parse_module_levels(&cfg.log_module_levels);
+
+	// tracer is initialized before the subscriber, so the initialization error is logged later
+	let tracer = cfg
+		.ot_traces
+		.then(|| telemetry::otlp::tracer(cfg.ot_collector_endpoint.clone(), (&cfg).into()));
+	let (tracer, tracer_error) = match tracer {
+		Some(Ok(tracer)) => (Some(tracer), None),
+		Some(Err(error)) => (None, Some(error)),
+		None => (None, None),
+	};
 
 	let logs_json = opts.logs_json || cfg.log_format_json;
-	if logs_json {
-		tracing::subscriber::set_global_default(json_subscriber(log_level))
-			.expect("global json subscriber is set");
+	let log_level_reload = if logs_json {
+		let (subscriber, reload) = json_subscriber(log_level, module_levels, tracer);
+		tracing::subscriber::set_global_default(subscriber).expect("global json subscriber is set");
+		reload
 	} else {
-		tracing::subscriber::set_global_default(default_subscriber(log_level))
+		let (subscriber, reload) = default_subscriber(log_level, module_levels, tracer);
+		tracing::subscriber::set_global_default(subscriber)
 			.expect("global default subscriber is set");
+		reload
+	};
+
+	// SIGHUP is handled from the start, it reloads the configuration only in the light client mode
+	#[cfg_attr(feature = "crawl", allow(unused_variables))]
+	let hangup = handle_hangup();
+
+	#[cfg(not(feature = "crawl"))]
+	let reload_config = {
+		let opts = opts.clone();
+		move || load_runtime_config(&opts)
 	};
 
 	let suri = match opts.avail_suri {
@@ -969,7 +1453,17 @@ pub async fn main() -> Result<()> {
 		fs::remove_dir_all(&cfg.avail_path).wrap_err("Failed to remove local state directory")?;
 	}
 
-	let db = RocksDB::open(&cfg.avail_path).expect("Avail Light could not initialize database");
+	let db = RocksDB::open(&cfg.avail_path, cfg.kad_record_ttl, cfg.row_record_ttl())
+		.expect("Avail Light could not initialize database");
+
+	admin::apply_persisted(&db, &mut cfg);
+	let (persisted_log_level, _) = parse_log_level(&cfg.log_level, log_level);
+	if persisted_log_level != log_level {
+		log_level_reload(persisted_log_level)?;
+	}
+
+	proof::init_thread_pool(cfg.proof_verification_threads)?;
+	proof::init_commitment_cache(cfg.commitment_cache_size)?;
 
 	let client_id = db.get(ClientIdKey).unwrap_or_else(|| {
 		let client_id = Uuid::new_v4();
@@ -993,6 +1487,14 @@ pub async fn main() -> Result<()> {
 		warn!("Using default log level: {}", error);
 	}
 
+	for invalid in invalid_module_levels {
+		warn!("Ignoring invalid module log level: {invalid}");
+	}
+
+	if let Some(error) = tracer_error {
+		warn!("Block processing traces are not exported: {error:#}");
+	}
+
 	// spawn a task to watch for ctrl-c signals from user to trigger the shutdown
 	spawn_in_span(shutdown.with_trigger("user signaled shutdown".to_string(), user_signal()));
 
@@ -1030,6 +1532,9 @@ pub async fn main() -> Result<()> {
 			shutdown.clone(),
 			client_id,
 			execution_id,
+			log_level_reload,
+			reload_config,
+			hangup,
 		)
 		.await
 	} {
@@ -1038,6 +1543,7 @@ pub async fn main() -> Result<()> {
 	};
 
 	let reason = shutdown.completed_shutdown().await;
+	telemetry::otlp::shutdown_tracer();
 
 	// we are not logging error here since expectation is
 	// to log terminating condition before sending message to this channel
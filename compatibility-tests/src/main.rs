@@ -2,11 +2,12 @@ use avail_light_core::{
 	data::RocksDB,
 	network::rpc,
 	shutdown::Controller,
-	types::{ExponentialConfig, RetryConfig},
+	types::{ExponentialConfig, RetryConfig, RuntimeConfig},
 };
 use clap::Parser;
 use color_eyre::{eyre::Context, Result};
 use kate_recovery::matrix::Position;
+use std::time::Duration;
 
 #[derive(Parser)]
 struct CommandArgs {
@@ -21,8 +22,13 @@ async fn main() -> Result<()> {
 	let command_args = CommandArgs::parse();
 	println!("Using URL: {}", command_args.url);
 	println!("Using Path: {}", command_args.avail_path);
-	let db = RocksDB::open(&command_args.avail_path)
-		.wrap_err("API Compatibility Test could not initialize database")?;
+	let cfg = RuntimeConfig::default();
+	let db = RocksDB::open(
+		&command_args.avail_path,
+		cfg.kad_record_ttl,
+		cfg.row_record_ttl(),
+	)
+	.wrap_err("API Compatibility Test could not initialize database")?;
 
 	let retry_cfg = RetryConfig::Exponential(ExponentialConfig {
 		base: 10,
@@ -31,8 +37,17 @@ async fn main() -> Result<()> {
 	});
 
 	let shutdown = Controller::new();
-	let (rpc_client, _, subscriptions) =
-		rpc::init(db, &[command_args.url], "DEV", retry_cfg, shutdown).await?;
+	let (rpc_client, _, subscriptions) = rpc::init(
+		db,
+		&[command_args.url],
+		"DEV",
+		retry_cfg,
+		Duration::from_secs(60),
+		false,
+		None,
+		shutdown,
+	)
+	.await?;
 	tokio::spawn(subscriptions.run());
 
 	let mut correct: bool = true;
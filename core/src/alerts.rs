@@ -0,0 +1,363 @@
+//! Webhook alerts on the availability failures.
+//!
+//! Alerts are posted as JSON to the configured webhook when the sampled blocks stay below
+//! the confidence threshold, when the routing table is depleted, or when the RPC node is unreachable.
+//! Alert is sent once when the condition occurs, and again only after the condition is cleared.
+//! If the secret is configured, payload is signed with HMAC-SHA256 in the `X-Avail-Signature` header.
+
+use color_eyre::{eyre::eyre, Result};
+use hmac::{Hmac, Mac};
+use hyper::{client::HttpConnector, header::CONTENT_TYPE, Body, Client, Request, Uri};
+use hyper_rustls::HttpsConnector;
+use serde::Serialize;
+use sha2::Sha256;
+use std::{
+	str::FromStr,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::broadcast, time};
+use tokio_retry::{strategy::ExponentialBackoff, Retry};
+use tracing::{debug, info, warn};
+
+use crate::{
+	data::{Database, RpcNodeKey},
+	network::{p2p::Client as P2pClient, rpc},
+	sampling::SamplingResult,
+	types::AlertsConfig,
+	utils::spawn_in_span,
+};
+
+/// Header with the hex encoded HMAC-SHA256 signature of the payload
+const SIGNATURE_HEADER: &str = "X-Avail-Signature";
+/// Timeout of the RPC node health check
+const RPC_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "alert", rename_all = "snake_case")]
+pub enum Alert {
+	/// Number of the consecutive blocks are below the confidence threshold
+	LowConfidence {
+		block_number: u32,
+		confidence: f64,
+		blocks: u32,
+	},
+	/// Number of the peers in the routing table is below the minimum
+	LowDhtPeers { peers: usize, min_peers: usize },
+	/// RPC node is unreachable longer than the timeout (in seconds)
+	RpcUnreachable {
+		host: Option<String>,
+		unreachable_for: u64,
+	},
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+	#[serde(flatten)]
+	alert: &'a Alert,
+	/// Unix timestamp of the alert (in seconds)
+	timestamp: u64,
+}
+
+fn signature(secret: &str, payload: &[u8]) -> String {
+	let mut mac =
+		Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+	mac.update(payload);
+	format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Clone)]
+struct Webhook {
+	client: Client<HttpsConnector<HttpConnector>>,
+	url: Uri,
+	secret: Option<String>,
+	retries: usize,
+}
+
+impl Webhook {
+	fn new(config: &AlertsConfig) -> Result<Self> {
+		let connector = hyper_rustls::HttpsConnectorBuilder::new()
+			.with_native_roots()
+			.https_or_http()
+			.enable_http1()
+			.build();
+		Ok(Webhook {
+			client: Client::builder().build(connector),
+			url: Uri::from_str(&config.webhook_url)?,
+			secret: config.secret.clone(),
+			retries: config.retries,
+		})
+	}
+
+	async fn post(&self, payload: &[u8]) -> Result<()> {
+		let mut request = Request::post(&self.url).header(CONTENT_TYPE, "application/json");
+		if let Some(secret) = &self.secret {
+			request = request.header(SIGNATURE_HEADER, signature(secret, payload));
+		}
+		let response = self
+			.client
+			.request(request.body(Body::from(payload.to_vec()))?)
+			.await?;
+		if !response.status().is_success() {
+			return Err(eyre!("Webhook responded with {}", response.status()));
+		}
+		Ok(())
+	}
+
+	/// Posts the alert in the background, retrying the failed requests with the exponential backoff
+	fn send(&self, alert: Alert) {
+		let webhook = self.clone();
+		spawn_in_span(async move {
+			let timestamp = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|duration| duration.as_secs())
+				.unwrap_or_default();
+			let payload = Payload {
+				alert: &alert,
+				timestamp,
+			};
+			let payload = serde_json::to_vec(&payload).expect("Alert is serializable");
+
+			let backoff = ExponentialBackoff::from_millis(2)
+				.factor(500)
+				.max_delay(Duration::from_secs(60))
+				.take(webhook.retries);
+			match Retry::spawn(backoff, || webhook.post(&payload)).await {
+				Ok(()) => info!(?alert, "Alert sent"),
+				Err(error) => warn!(?alert, "Failed to send alert: {error:#}"),
+			}
+		});
+	}
+}
+
+/// Tracks the alert conditions, returning the alert once the condition occurs
+struct Conditions {
+	config: AlertsConfig,
+	low_confidence_blocks: u32,
+	low_dht_peers: bool,
+	rpc_unreachable_since: Option<Instant>,
+	rpc_alerted: bool,
+}
+
+impl Conditions {
+	fn new(config: AlertsConfig) -> Self {
+		Conditions {
+			config,
+			low_confidence_blocks: 0,
+			low_dht_peers: false,
+			rpc_unreachable_since: None,
+			rpc_alerted: false,
+		}
+	}
+
+	fn block_processed(&mut self, block_number: u32, confidence: f64) -> Option<Alert> {
+		if self.config.low_confidence_blocks == 0 {
+			return None;
+		}
+		if confidence >= self.config.confidence_threshold {
+			self.low_confidence_blocks = 0;
+			return None;
+		}
+		self.low_confidence_blocks += 1;
+		(self.low_confidence_blocks == self.config.low_confidence_blocks).then_some(
+			Alert::LowConfidence {
+				block_number,
+				confidence,
+				blocks: self.low_confidence_blocks,
+			},
+		)
+	}
+
+	fn dht_peers_counted(&mut self, peers: usize) -> Option<Alert> {
+		let min_peers = self.config.min_dht_peers;
+		let was_low = self.low_dht_peers;
+		self.low_dht_peers = peers < min_peers;
+		(self.low_dht_peers && !was_low).then_some(Alert::LowDhtPeers { peers, min_peers })
+	}
+
+	fn rpc_checked(
+		&mut self,
+		is_reachable: bool,
+		host: Option<String>,
+		now: Instant,
+	) -> Option<Alert> {
+		if is_reachable || self.config.rpc_unreachable_timeout == 0 {
+			self.rpc_unreachable_since = None;
+			self.rpc_alerted = false;
+			return None;
+		}
+		let unreachable_since = *self.rpc_unreachable_since.get_or_insert(now);
+		let unreachable_for = now.duration_since(unreachable_since).as_secs();
+		if self.rpc_alerted || unreachable_for < self.config.rpc_unreachable_timeout {
+			return None;
+		}
+		self.rpc_alerted = true;
+		Some(Alert::RpcUnreachable {
+			host,
+			unreachable_for,
+		})
+	}
+}
+
+/// Checks the alert conditions on the sampling results and periodically,
+/// posting the alerts to the configured webhook.
+pub async fn run<T: Database + Clone + Send + Sync + 'static>(
+	config: AlertsConfig,
+	db: T,
+	p2p_client: P2pClient,
+	rpc_client: Option<rpc::Client<T>>,
+	mut sampling_results: broadcast::Receiver<SamplingResult>,
+) {
+	let webhook = match Webhook::new(&config) {
+		Ok(webhook) => webhook,
+		Err(error) => {
+			warn!("Alerts are disabled, webhook URL is not valid: {error:#}");
+			return;
+		},
+	};
+	info!("Starting alerts...");
+
+	let mut interval = time::interval(Duration::from_secs(config.check_interval.max(1)));
+	// first tick completes immediately, while the routing table is still being bootstrapped
+	interval.tick().await;
+	let mut conditions = Conditions::new(config);
+	// blocks are not sampled in the fat client mode
+	let mut is_sampling = true;
+
+	loop {
+		let alert = tokio::select! {
+			result = sampling_results.recv(), if is_sampling => {
+				let result = match result {
+					Ok(result) => result,
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						warn!(skipped, "Alerts skipped sampling results");
+						continue;
+					},
+					Err(broadcast::error::RecvError::Closed) => {
+						is_sampling = false;
+						continue;
+					},
+				};
+				conditions.block_processed(result.block_number, result.confidence())
+			},
+			_ = interval.tick() => {
+				match p2p_client.count_dht_entries().await {
+					Ok((peers, _)) => {
+						if let Some(alert) = conditions.dht_peers_counted(peers) {
+							webhook.send(alert);
+						}
+					},
+					Err(error) => debug!("Unable to count routing table peers: {error:#}"),
+				}
+
+				let Some(rpc_client) = &rpc_client else {
+					continue;
+				};
+				let is_reachable = time::timeout(RPC_CHECK_TIMEOUT, rpc_client.current_health())
+					.await
+					.is_ok_and(|health| health.is_ok());
+				let host = db.get(RpcNodeKey).map(|node| node.host);
+				conditions.rpc_checked(is_reachable, host, Instant::now())
+			},
+		};
+
+		if let Some(alert) = alert {
+			webhook.send(alert);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	fn config() -> AlertsConfig {
+		AlertsConfig {
+			webhook_url: "http://127.0.0.1:8080/alerts".to_string(),
+			secret: None,
+			confidence_threshold: 90.0,
+			low_confidence_blocks: 3,
+			min_dht_peers: 10,
+			rpc_unreachable_timeout: 60,
+			check_interval: 30,
+			retries: 3,
+		}
+	}
+
+	#[test_case(&[50.0, 50.0, 50.0] => vec![3] ; "consecutive low confidence")]
+	#[test_case(&[50.0, 50.0, 99.0, 50.0, 50.0] => Vec::<u32>::new() ; "interrupted by high confidence")]
+	#[test_case(&[50.0, 50.0, 50.0, 50.0, 99.0, 50.0, 50.0, 50.0] => vec![3, 8] ; "alerted again after recovery")]
+	fn low_confidence(confidences: &[f64]) -> Vec<u32> {
+		let mut conditions = Conditions::new(config());
+		(1..)
+			.zip(confidences)
+			.filter_map(|(block_number, &confidence)| {
+				conditions.block_processed(block_number, confidence)
+			})
+			.map(|alert| match alert {
+				Alert::LowConfidence { block_number, .. } => block_number,
+				_ => unreachable!(),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn low_dht_peers() {
+		let mut conditions = Conditions::new(config());
+		assert_eq!(conditions.dht_peers_counted(20), None);
+		assert_eq!(
+			conditions.dht_peers_counted(5),
+			Some(Alert::LowDhtPeers {
+				peers: 5,
+				min_peers: 10
+			})
+		);
+		assert_eq!(conditions.dht_peers_counted(3), None);
+		assert_eq!(conditions.dht_peers_counted(10), None);
+		assert!(conditions.dht_peers_counted(9).is_some());
+	}
+
+	#[test]
+	fn rpc_unreachable() {
+		let mut conditions = Conditions::new(config());
+		let host = || Some("ws://127.0.0.1:9944".to_string());
+		let started = Instant::now();
+		let at = |seconds| started + Duration::from_secs(seconds);
+
+		assert_eq!(conditions.rpc_checked(false, host(), at(0)), None);
+		assert_eq!(conditions.rpc_checked(false, host(), at(30)), None);
+		assert_eq!(
+			conditions.rpc_checked(false, host(), at(60)),
+			Some(Alert::RpcUnreachable {
+				host: host(),
+				unreachable_for: 60
+			})
+		);
+		assert_eq!(conditions.rpc_checked(false, host(), at(90)), None);
+
+		assert_eq!(conditions.rpc_checked(true, host(), at(120)), None);
+		assert_eq!(conditions.rpc_checked(false, host(), at(150)), None);
+		assert!(conditions.rpc_checked(false, host(), at(210)).is_some());
+	}
+
+	#[test]
+	fn payload_signature() {
+		let alert = Alert::LowDhtPeers {
+			peers: 5,
+			min_peers: 10,
+		};
+		let payload = serde_json::to_string(&Payload {
+			alert: &alert,
+			timestamp: 1700000000,
+		})
+		.unwrap();
+		assert_eq!(
+			payload,
+			r#"{"alert":"low_dht_peers","peers":5,"min_peers":10,"timestamp":1700000000}"#
+		);
+
+		let signature = signature("secret", payload.as_bytes());
+		assert!(signature.starts_with("sha256="));
+		assert_eq!(signature.len(), "sha256=".len() + 64);
+	}
+}
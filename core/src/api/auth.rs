@@ -0,0 +1,272 @@
+//! Authentication and authorization of the API requests.
+//!
+//! Requests are authenticated with a bearer token, which is either one of the configured API keys,
+//! or a HS256 signed JWT with the `role` claim and the optional `exp` claim:
+//!
+//! ```json
+//! { "role": "read_only", "exp": 1735689600 }
+//! ```
+//!
+//! Authentication is disabled if neither API keys nor JWT secret are configured,
+//! in which case the read-only endpoints are public, and the admin endpoints are not available.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::{eyre::eyre, Result};
+use hmac::{Hmac, Mac};
+use hyper::StatusCode;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{
+	sync::Arc,
+	time::{SystemTime, UNIX_EPOCH},
+};
+use warp::{Filter, Rejection, Reply};
+
+use crate::types::{ApiKey, Role, RuntimeConfig};
+
+#[derive(Debug)]
+pub struct Unauthorized {}
+
+impl warp::reject::Reject for Unauthorized {}
+
+#[derive(Debug)]
+pub struct Forbidden {}
+
+impl warp::reject::Reject for Forbidden {}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+	alg: String,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+	role: Role,
+	/// Expiration time (Unix timestamp in seconds)
+	exp: Option<u64>,
+}
+
+#[derive(Clone, Default)]
+pub struct Auth {
+	api_keys: Arc<Vec<ApiKey>>,
+	jwt_secret: Option<Arc<String>>,
+}
+
+impl Auth {
+	pub fn new(cfg: &RuntimeConfig) -> Self {
+		Auth {
+			api_keys: Arc::new(cfg.api_keys.clone()),
+			jwt_secret: cfg.api_jwt_secret.clone().map(Arc::new),
+		}
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		!self.api_keys.is_empty() || self.jwt_secret.is_some()
+	}
+
+	/// Returns `true` if admin can be authenticated, with an API key or a JWT
+	pub fn is_admin_enabled(&self) -> bool {
+		self.jwt_secret.is_some() || self.api_keys.iter().any(|key| key.role == Role::Admin)
+	}
+
+	/// Returns the role of the bearer token, if the token is valid
+	pub fn role(&self, token: &str) -> Option<Role> {
+		if let Some(api_key) = self
+			.api_keys
+			.iter()
+			.find(|api_key| is_api_key(&api_key.key, token))
+		{
+			return Some(api_key.role);
+		}
+		let secret = self.jwt_secret.as_ref()?;
+		verify_jwt(secret, token, now()).ok()
+	}
+}
+
+/// Compares the token with the API key in constant time, by verifying the HMACs of both,
+/// so the comparison time doesn't depend on the length of the matching prefix
+fn is_api_key(api_key: &str, token: &str) -> bool {
+	let mac = |value: &str| {
+		let mut mac =
+			Hmac::<Sha256>::new_from_slice(b"api-key").expect("HMAC can take key of any size");
+		mac.update(value.as_bytes());
+		mac
+	};
+	mac(token)
+		.verify_slice(&mac(api_key).finalize().into_bytes())
+		.is_ok()
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or_default()
+}
+
+fn verify_jwt(secret: &str, token: &str, now: u64) -> Result<Role> {
+	let mut parts = token.split('.');
+	let (Some(header), Some(claims), Some(signature), None) =
+		(parts.next(), parts.next(), parts.next(), parts.next())
+	else {
+		return Err(eyre!("Malformed token"));
+	};
+
+	let header: JwtHeader = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header)?)?;
+	if header.alg != "HS256" {
+		return Err(eyre!("Unsupported algorithm {}", header.alg));
+	}
+
+	let mut mac =
+		Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+	mac.update(token[..token.rfind('.').expect("Token has three parts")].as_bytes());
+	mac.verify_slice(&URL_SAFE_NO_PAD.decode(signature)?)
+		.map_err(|_| eyre!("Invalid signature"))?;
+
+	let claims: Claims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims)?)?;
+	if claims.exp.is_some_and(|exp| exp <= now) {
+		return Err(eyre!("Token expired"));
+	}
+	Ok(claims.role)
+}
+
+/// Extracts the role of the request bearer token, rejecting the request if the token is not valid.
+/// If the authentication is disabled, requests are not checked and have the admin role.
+pub fn authenticate(auth: Auth) -> impl Filter<Extract = (Role,), Error = Rejection> + Clone {
+	warp::header::optional::<String>("authorization").and_then(
+		move |authorization: Option<String>| {
+			let auth = auth.clone();
+			async move {
+				if !auth.is_enabled() {
+					return Ok(Role::Admin);
+				}
+				authorization
+					.as_deref()
+					.and_then(|header| header.strip_prefix("Bearer "))
+					.and_then(|token| auth.role(token))
+					.ok_or_else(|| warp::reject::custom(Unauthorized {}))
+			}
+		},
+	)
+}
+
+/// Rejects the request if it is not authorized with the bearer token of the required role
+pub fn authorize(
+	auth: Auth,
+	required: Role,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+	authenticate(auth)
+		.and_then(move |role: Role| async move {
+			if role < required {
+				return Err(warp::reject::custom(Forbidden {}));
+			}
+			Ok(())
+		})
+		.untuple_one()
+}
+
+pub async fn handle_rejection(error: Rejection) -> Result<impl Reply, Rejection> {
+	if error.find::<Unauthorized>().is_some() {
+		return Ok(StatusCode::UNAUTHORIZED.into_response());
+	}
+	if error.find::<Forbidden>().is_some() {
+		return Ok(StatusCode::FORBIDDEN.into_response());
+	}
+	Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use test_case::test_case;
+
+	const SECRET: &str = "secret";
+
+	fn jwt(secret: &str, header: &str, claims: &str) -> String {
+		let message = format!(
+			"{}.{}",
+			URL_SAFE_NO_PAD.encode(header),
+			URL_SAFE_NO_PAD.encode(claims)
+		);
+		let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+		mac.update(message.as_bytes());
+		let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+		format!("{message}.{signature}")
+	}
+
+	#[test_case(SECRET, r#"{"alg":"HS256"}"#, r#"{"role":"admin"}"# => Some(Role::Admin) ; "admin")]
+	#[test_case(SECRET, r#"{"alg":"HS256"}"#, r#"{"role":"read_only","exp":2000}"# => Some(Role::ReadOnly) ; "not expired")]
+	#[test_case(SECRET, r#"{"alg":"HS256"}"#, r#"{"role":"admin","exp":1000}"# => None ; "expired")]
+	#[test_case("wrong", r#"{"alg":"HS256"}"#, r#"{"role":"admin"}"# => None ; "invalid signature")]
+	#[test_case(SECRET, r#"{"alg":"none"}"#, r#"{"role":"admin"}"# => None ; "unsupported algorithm")]
+	#[test_case(SECRET, r#"{"alg":"HS256"}"#, r#"{"role":"root"}"# => None ; "unknown role")]
+	fn verify(secret: &str, header: &str, claims: &str) -> Option<Role> {
+		verify_jwt(SECRET, &jwt(secret, header, claims), 1000).ok()
+	}
+
+	#[test_case("reader", "reader" => true ; "equal")]
+	#[test_case("reader", "read" => false ; "prefix")]
+	#[test_case("reader", "readers" => false ; "longer")]
+	#[test_case("reader", "" => false ; "empty")]
+	fn api_key(api_key: &str, token: &str) -> bool {
+		is_api_key(api_key, token)
+	}
+
+	fn auth(api_keys: &[(&str, Role)], jwt_secret: Option<&str>) -> Auth {
+		let cfg = RuntimeConfig {
+			api_keys: api_keys
+				.iter()
+				.map(|&(key, role)| ApiKey {
+					key: key.to_string(),
+					role,
+				})
+				.collect(),
+			api_jwt_secret: jwt_secret.map(String::from),
+			..Default::default()
+		};
+		Auth::new(&cfg)
+	}
+
+	#[test_case(false, None, Role::ReadOnly => StatusCode::OK ; "disabled")]
+	#[test_case(true, None, Role::ReadOnly => StatusCode::UNAUTHORIZED ; "missing token")]
+	#[test_case(true, Some("Bearer wrong"), Role::ReadOnly => StatusCode::UNAUTHORIZED ; "unknown key")]
+	#[test_case(true, Some("reader"), Role::ReadOnly => StatusCode::UNAUTHORIZED ; "missing bearer scheme")]
+	#[test_case(true, Some("Bearer reader"), Role::ReadOnly => StatusCode::OK ; "read-only key")]
+	#[test_case(true, Some("Bearer reader"), Role::Admin => StatusCode::FORBIDDEN ; "read-only key on admin endpoint")]
+	#[test_case(true, Some("Bearer admin"), Role::ReadOnly => StatusCode::OK ; "admin key on read-only endpoint")]
+	#[test_case(true, Some("Bearer admin"), Role::Admin => StatusCode::OK ; "admin key")]
+	#[tokio::test]
+	async fn authorize_filter(
+		enabled: bool,
+		authorization: Option<&str>,
+		required: Role,
+	) -> StatusCode {
+		let auth = match enabled {
+			true => auth(&[("reader", Role::ReadOnly), ("admin", Role::Admin)], None),
+			false => Auth::default(),
+		};
+		let route = warp::path!("endpoint")
+			.and(authorize(auth, required))
+			.map(warp::reply)
+			.recover(handle_rejection);
+
+		let mut request = warp::test::request().method("GET").path("/endpoint");
+		if let Some(authorization) = authorization {
+			request = request.header("authorization", authorization);
+		}
+		request.reply(&route).await.status()
+	}
+
+	#[test]
+	fn jwt_bearer_token() {
+		let auth = auth(&[], Some(SECRET));
+		assert!(auth.is_admin_enabled());
+		let token = jwt(SECRET, r#"{"alg":"HS256"}"#, r#"{"role":"read_only"}"#);
+		assert_eq!(auth.role(&token), Some(Role::ReadOnly));
+		assert_eq!(auth.role("admin"), None);
+
+		let auth = Auth::new(&RuntimeConfig::default());
+		assert!(!auth.is_enabled());
+		assert!(!auth.is_admin_enabled());
+	}
+}
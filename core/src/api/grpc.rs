@@ -0,0 +1,423 @@
+//! gRPC server mirroring the read-only v2 REST API, for integrators which prefer typed streaming RPC.
+//!
+//! Protobuf definitions are shipped in the `proto/light_client.proto`, and the server is available with the `grpc` feature.
+//!
+//! # Methods
+//!
+//! * `GetBlockStatus` - returns block status, confidence and the outcome of the block processing
+//! * `SubscribeConfidence` - streams the confidence of the verified blocks
+//! * `GetAppData` - returns verified data of the followed application in the block range
+//! * `GetLocalInfo`, `GetPeers`, `GetDhtInfo` - return P2P diagnostics
+//!
+//! If the API keys or the JWT secret are configured, requests require the `authorization` metadata with the bearer token.
+
+use futures::{Future, Stream};
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, pin::Pin, str::FromStr};
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+
+use crate::{
+	api::{
+		auth::Auth,
+		v2::{
+			handlers::{self, p2p as p2p_handlers},
+			types::{self as v2_types, AppData, AppDataQuery, Error, ErrorCode},
+		},
+	},
+	data::Database,
+	light_client::{self, BlockProcessing},
+	network::p2p,
+	shutdown::Controller,
+	types::{BlockVerified, RuntimeConfig},
+};
+
+pub mod proto {
+	tonic::include_proto!("avail_light.v2");
+}
+
+use proto::light_client_server::{LightClient, LightClientServer};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct GrpcConfig {
+	/// gRPC server port, server is started on the `http_server_host` if the port is set (default: None)
+	pub grpc_server_port: Option<u16>,
+}
+
+impl From<Error> for Status {
+	fn from(error: Error) -> Self {
+		match error.error_code {
+			ErrorCode::NotFound => Status::not_found(error.message),
+			ErrorCode::BadRequest => Status::invalid_argument(error.message),
+			ErrorCode::InternalServerError => {
+				if let Some(cause) = &error.cause {
+					error!("{}: {cause:#}", error.message);
+				}
+				Status::internal(error.message)
+			},
+		}
+	}
+}
+
+impl From<v2_types::BlockStatus> for proto::BlockStatus {
+	fn from(status: v2_types::BlockStatus) -> Self {
+		match status {
+			v2_types::BlockStatus::Unavailable => proto::BlockStatus::Unavailable,
+			v2_types::BlockStatus::Pending => proto::BlockStatus::Pending,
+			v2_types::BlockStatus::VerifyingHeader => proto::BlockStatus::VerifyingHeader,
+			v2_types::BlockStatus::VerifyingConfidence => proto::BlockStatus::VerifyingConfidence,
+			v2_types::BlockStatus::VerifyingData => proto::BlockStatus::VerifyingData,
+			v2_types::BlockStatus::Incomplete => proto::BlockStatus::Incomplete,
+			v2_types::BlockStatus::Finished => proto::BlockStatus::Finished,
+		}
+	}
+}
+
+impl From<BlockProcessing> for proto::BlockProcessing {
+	fn from(processing: BlockProcessing) -> Self {
+		let outcome = match processing.outcome {
+			light_client::ProcessingOutcome::Verified => proto::ProcessingOutcome::Verified,
+			light_client::ProcessingOutcome::Unavailable => proto::ProcessingOutcome::Unavailable,
+			light_client::ProcessingOutcome::Skipped => proto::ProcessingOutcome::Skipped,
+		};
+		proto::BlockProcessing {
+			outcome: outcome.into(),
+			required: processing.required,
+			verified: processing.verified,
+			dht_fetched: processing.dht_fetched,
+			rpc_fetched: processing.rpc_fetched,
+			duration: processing.duration,
+			processed_at: processing.processed_at,
+			dht_average_latency: processing
+				.dht_stats
+				.average_latency()
+				.map(|latency| latency.as_millis() as u64),
+			dht_failures: processing
+				.dht_stats
+				.failures
+				.by_reason()
+				.into_iter()
+				.map(|(reason, count)| (reason.to_string(), count))
+				.collect(),
+		}
+	}
+}
+
+impl From<AppData> for Vec<u8> {
+	fn from(data: AppData) -> Self {
+		match data {
+			AppData::Base64(data) => data.0,
+			AppData::Json(value) => value.to_string().into_bytes(),
+		}
+	}
+}
+
+async fn block_status(
+	block_number: u32,
+	config: RuntimeConfig,
+	db: impl Database + Clone,
+) -> Result<proto::BlockStatusResponse, Error> {
+	let block = handlers::block(block_number, config, db.clone()).await?;
+	let processing = handlers::block_processing_status(block_number, db)
+		.await
+		.ok()
+		.map(|status| status.processing.into());
+
+	Ok(proto::BlockStatusResponse {
+		block_number,
+		status: proto::BlockStatus::from(block.status).into(),
+		confidence: block.confidence,
+		processing,
+	})
+}
+
+async fn app_data(
+	request: proto::AppDataRequest,
+	config: RuntimeConfig,
+	db: impl Database + Clone,
+) -> Result<proto::AppDataResponse, Error> {
+	let format = match request.format() {
+		proto::AppDataFormat::Data => v2_types::AppDataFormat::Base64,
+		proto::AppDataFormat::Extrinsic => v2_types::AppDataFormat::Raw,
+	};
+	let query = AppDataQuery {
+		from_block: request.from_block,
+		to_block: request.to_block,
+		limit: request.limit.map(|limit| limit as usize),
+		format,
+	};
+	let response = handlers::app_data(request.app_id, query, config, db).await?;
+
+	Ok(proto::AppDataResponse {
+		app_id: response.app_id,
+		blocks: response
+			.blocks
+			.into_iter()
+			.map(|block| proto::AppDataBlock {
+				block_number: block.block_number,
+				data: block.data.into_iter().map(Into::into).collect(),
+			})
+			.collect(),
+		next_block: response.next_block,
+	})
+}
+
+struct Service<T: Database> {
+	config: RuntimeConfig,
+	db: T,
+	p2p_client: p2p::Client,
+	block_sender: broadcast::Sender<BlockVerified>,
+}
+
+type ConfidenceStream = Pin<Box<dyn Stream<Item = Result<proto::ConfidenceEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl<T: Database + Clone + Send + Sync + 'static> LightClient for Service<T> {
+	async fn get_block_status(
+		&self,
+		request: Request<proto::BlockStatusRequest>,
+	) -> Result<Response<proto::BlockStatusResponse>, Status> {
+		let block_number = request.into_inner().block_number;
+		let response = block_status(block_number, self.config.clone(), self.db.clone()).await?;
+		Ok(Response::new(response))
+	}
+
+	type SubscribeConfidenceStream = ConfidenceStream;
+
+	async fn subscribe_confidence(
+		&self,
+		request: Request<proto::SubscribeConfidenceRequest>,
+	) -> Result<Response<Self::SubscribeConfidenceStream>, Status> {
+		let min_confidence = request.into_inner().min_confidence;
+		// lagging subscribers skip the missed blocks
+		let stream = BroadcastStream::new(self.block_sender.subscribe()).filter_map(move |block| {
+			let block = block.ok()?;
+			if min_confidence.is_some_and(|min| block.confidence.map_or(true, |value| value < min))
+			{
+				return None;
+			}
+			Some(Ok(proto::ConfidenceEvent {
+				block_number: block.block_num,
+				confidence: block.confidence,
+			}))
+		});
+		Ok(Response::new(Box::pin(stream)))
+	}
+
+	async fn get_app_data(
+		&self,
+		request: Request<proto::AppDataRequest>,
+	) -> Result<Response<proto::AppDataResponse>, Status> {
+		let response = app_data(request.into_inner(), self.config.clone(), self.db.clone()).await?;
+		Ok(Response::new(response))
+	}
+
+	async fn get_local_info(
+		&self,
+		_: Request<proto::LocalInfoRequest>,
+	) -> Result<Response<proto::LocalInfoResponse>, Status> {
+		let info = p2p_handlers::get_peer_info(self.p2p_client.clone()).await?;
+		Ok(Response::new(proto::LocalInfoResponse {
+			peer_id: info.peer_id,
+			operation_mode: info.operation_mode,
+			local_listeners: info.listeners.local,
+			external_listeners: info.listeners.external,
+			routing_table_peers_count: info.routing_table_peers_count as u64,
+			routing_table_external_peers_count: info.routing_table_external_peers_count as u64,
+		}))
+	}
+
+	async fn get_peers(
+		&self,
+		_: Request<proto::PeersRequest>,
+	) -> Result<Response<proto::PeersResponse>, Status> {
+		let response = p2p_handlers::get_connected_peers(self.p2p_client.clone()).await?;
+		let peers = response
+			.peers
+			.into_iter()
+			.map(|peer| proto::ConnectedPeer {
+				peer_id: peer.peer_id,
+				latest_latency: peer.latest_latency,
+				average_latency: peer.average_latency,
+			})
+			.collect();
+		Ok(Response::new(proto::PeersResponse { peers }))
+	}
+
+	async fn get_dht_info(
+		&self,
+		_: Request<proto::DhtInfoRequest>,
+	) -> Result<Response<proto::DhtInfoResponse>, Status> {
+		let info = p2p_handlers::get_dht_info(self.p2p_client.clone()).await?;
+		Ok(Response::new(proto::DhtInfoResponse {
+			routing_table_peers_count: info.routing_table_peers_count as u64,
+			routing_table_external_peers_count: info.routing_table_external_peers_count as u64,
+			kademlia_map_size: info.kademlia_map_size as u64,
+		}))
+	}
+}
+
+/// Rejects the request without a valid bearer token, if the authentication is enabled.
+/// All methods are read-only, so any of the roles is authorized.
+fn authorize(auth: Auth) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Clone {
+	move |request: Request<()>| {
+		if !auth.is_enabled() {
+			return Ok(request);
+		}
+		let is_authorized = request
+			.metadata()
+			.get("authorization")
+			.and_then(|value| value.to_str().ok())
+			.and_then(|header| header.strip_prefix("Bearer "))
+			.and_then(|token| auth.role(token))
+			.is_some();
+		if !is_authorized {
+			return Err(Status::unauthenticated("Invalid or missing bearer token"));
+		}
+		Ok(request)
+	}
+}
+
+pub struct Server<T: Database> {
+	pub db: T,
+	pub cfg: RuntimeConfig,
+	pub p2p_client: p2p::Client,
+	pub block_sender: broadcast::Sender<BlockVerified>,
+	pub shutdown: Controller<String>,
+}
+
+impl<T: Database + Clone + Send + Sync + 'static> Server<T> {
+	/// Creates a gRPC server that needs to be spawned into a runtime, returns `None` if the port is not configured
+	pub fn bind(self) -> Option<impl Future<Output = ()>> {
+		let host = &self.cfg.http_server_host;
+		let port = self.cfg.grpc.grpc_server_port?;
+		let addr = SocketAddr::from_str(&format!("{host}:{port}"))
+			.expect("Unable to parse host address from config");
+
+		let auth = Auth::new(&self.cfg);
+		let service = Service {
+			config: self.cfg,
+			db: self.db,
+			p2p_client: self.p2p_client,
+			block_sender: self.block_sender,
+		};
+		let shutdown_signal = self.shutdown.triggered_shutdown();
+
+		info!("gRPC running on http://{addr}");
+		Some(async move {
+			let result = tonic::transport::Server::builder()
+				.add_service(LightClientServer::with_interceptor(
+					service,
+					authorize(auth),
+				))
+				.serve_with_shutdown(addr, async move {
+					let _ = shutdown_signal.await;
+				})
+				.await;
+
+			if let Err(error) = result {
+				error!("gRPC server failed: {error}");
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		data::{BlockHeaderKey, BlockProcessingKey, LatestHeaderKey, MemoryDB},
+		types::{ApiKey, Role},
+	};
+	use avail_subxt::{
+		api::runtime_types::avail_core::{
+			data_lookup::compact::CompactDataLookup,
+			header::extension::{v3, HeaderExtension},
+			kate_commitment::v3::KateCommitment,
+		},
+		primitives::Header,
+	};
+	use sp_core::H256;
+	use subxt::config::substrate::Digest;
+	use test_case::test_case;
+
+	#[tokio::test]
+	async fn block_status_response() {
+		let db = MemoryDB::default();
+		db.put(LatestHeaderKey, 10);
+		let status = block_status(11, RuntimeConfig::default(), db.clone())
+			.await
+			.map_err(Status::from)
+			.unwrap_err();
+		assert_eq!(status.code(), tonic::Code::NotFound);
+
+		db.put(
+			BlockHeaderKey(10),
+			Header {
+				parent_hash: H256::default(),
+				number: 10,
+				state_root: H256::default(),
+				extrinsics_root: H256::default(),
+				digest: Digest { logs: vec![] },
+				extension: HeaderExtension::V3(v3::HeaderExtension {
+					commitment: KateCommitment::default(),
+					app_lookup: CompactDataLookup {
+						size: 0,
+						index: vec![],
+					},
+				}),
+			},
+		);
+		db.put(
+			BlockProcessingKey(10),
+			BlockProcessing {
+				outcome: light_client::ProcessingOutcome::Verified,
+				required: 10,
+				verified: 10,
+				dht_fetched: 6,
+				rpc_fetched: 4,
+				dht_stats: Default::default(),
+				duration: 250,
+				processed_at: 1700000000,
+				total_cells: 64,
+				withheld_cells: 32,
+			},
+		);
+		let response = block_status(10, RuntimeConfig::default(), db)
+			.await
+			.unwrap();
+		assert_eq!(response.status(), proto::BlockStatus::VerifyingHeader);
+		let processing = response.processing.unwrap();
+		assert_eq!(processing.outcome(), proto::ProcessingOutcome::Verified);
+		assert_eq!(processing.dht_fetched, 6);
+	}
+
+	#[test_case(false, None => tonic::Code::Ok ; "disabled")]
+	#[test_case(true, None => tonic::Code::Unauthenticated ; "missing token")]
+	#[test_case(true, Some("Bearer wrong") => tonic::Code::Unauthenticated ; "unknown key")]
+	#[test_case(true, Some("Bearer reader") => tonic::Code::Ok ; "read-only key")]
+	fn authorize_interceptor(enabled: bool, authorization: Option<&str>) -> tonic::Code {
+		let cfg = RuntimeConfig {
+			api_keys: enabled
+				.then(|| ApiKey {
+					key: "reader".to_string(),
+					role: Role::ReadOnly,
+				})
+				.into_iter()
+				.collect(),
+			..Default::default()
+		};
+		let mut request = Request::new(());
+		if let Some(authorization) = authorization {
+			request
+				.metadata_mut()
+				.insert("authorization", authorization.parse().unwrap());
+		}
+		authorize(Auth::new(&cfg))(request)
+			.err()
+			.map_or(tonic::Code::Ok, |status| status.code())
+	}
+}
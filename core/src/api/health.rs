@@ -0,0 +1,243 @@
+//! Health and readiness checks, intended for the orchestrator probes and load balancers.
+//!
+//! * `/health` - liveness, responds with `200 OK` and the local block lag while the server is running,
+//!   without calling the node or the swarm, so a slow dependency doesn't restart a healthy process
+//! * `/ready` - readiness, responds with `503 Service Unavailable` if any of the checks fails
+
+use serde::Serialize;
+use std::time::Duration;
+use tokio::time::timeout;
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+use crate::{
+	data::{AchievedConfidenceKey, Database, LatestHeaderKey, RpcNodeKey},
+	network::{p2p, rpc},
+	types::{BlockRange, RuntimeConfig},
+};
+
+/// Maximum duration of a single check, so the probes don't time out while waiting on the node or the swarm
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PeersCheck {
+	pub ready: bool,
+	/// Number of peers in the routing table
+	pub peers: Option<usize>,
+	pub min_peers: usize,
+	pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RpcCheck {
+	pub ready: bool,
+	pub host: Option<String>,
+	/// Round trip time of the health RPC call (in milliseconds)
+	pub latency: Option<u64>,
+	pub is_syncing: Option<bool>,
+	pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct BlockLagCheck {
+	pub ready: bool,
+	/// Latest block received from the node or peers
+	pub latest: Option<u32>,
+	/// Latest block with the achieved confidence
+	pub verified: Option<u32>,
+	pub lag: Option<u32>,
+	pub max_lag: u32,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Report {
+	pub ready: bool,
+	pub p2p: PeersCheck,
+	/// Not checked in the DHT-only mode, since the node is not used
+	pub rpc: Option<RpcCheck>,
+	/// Not checked in the fat client mode, since the blocks are not verified
+	pub block_lag: Option<BlockLagCheck>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Liveness {
+	/// Not checked in the fat client mode, since the blocks are not verified
+	pub block_lag: Option<BlockLagCheck>,
+}
+
+#[derive(Clone)]
+pub struct Checks<T: Database> {
+	pub db: T,
+	pub p2p_client: p2p::Client,
+	pub rpc_client: Option<rpc::Client<T>>,
+	pub min_peers: usize,
+	pub max_lag: u32,
+	pub check_lag: bool,
+}
+
+impl<T: Database + Clone> Checks<T> {
+	pub fn new(
+		cfg: &RuntimeConfig,
+		db: T,
+		p2p_client: p2p::Client,
+		rpc_client: Option<rpc::Client<T>>,
+	) -> Self {
+		Checks {
+			db,
+			p2p_client,
+			rpc_client,
+			min_peers: cfg.ready_min_peers,
+			max_lag: cfg.head_lag_threshold,
+			check_lag: cfg.block_matrix_partition.is_none(),
+		}
+	}
+
+	async fn peers_check(&self) -> PeersCheck {
+		let peers = timeout(CHECK_TIMEOUT, self.p2p_client.count_dht_entries())
+			.await
+			.map_err(|_| "Counting routing table peers timed out".to_string())
+			.and_then(|result| result.map_err(|error| format!("{error:#}")));
+
+		match peers {
+			Ok((peers, _)) => PeersCheck {
+				ready: peers >= self.min_peers,
+				peers: Some(peers),
+				min_peers: self.min_peers,
+				error: None,
+			},
+			Err(error) => PeersCheck {
+				ready: false,
+				peers: None,
+				min_peers: self.min_peers,
+				error: Some(error),
+			},
+		}
+	}
+
+	async fn rpc_check(&self, rpc_client: &rpc::Client<T>) -> RpcCheck {
+		let host = self.db.get(RpcNodeKey).map(|node| node.host);
+		let health = timeout(CHECK_TIMEOUT, rpc_client.current_health())
+			.await
+			.map_err(|_| "Health check timed out".to_string())
+			.and_then(|result| result.map_err(|error| format!("{error:#}")));
+
+		match health {
+			Ok(health) => RpcCheck {
+				ready: !health.is_syncing,
+				host,
+				latency: Some(health.latency.as_millis() as u64),
+				is_syncing: Some(health.is_syncing),
+				error: None,
+			},
+			Err(error) => RpcCheck {
+				ready: false,
+				host,
+				latency: None,
+				is_syncing: None,
+				error: Some(error),
+			},
+		}
+	}
+
+	/// Reports only the checks of the local state
+	pub fn liveness(&self) -> Liveness {
+		Liveness {
+			block_lag: self
+				.check_lag
+				.then(|| block_lag_check(&self.db, self.max_lag)),
+		}
+	}
+
+	pub async fn report(&self) -> Report {
+		let p2p = self.peers_check().await;
+		let rpc = match &self.rpc_client {
+			Some(rpc_client) => Some(self.rpc_check(rpc_client).await),
+			None => None,
+		};
+		let block_lag = self
+			.check_lag
+			.then(|| block_lag_check(&self.db, self.max_lag));
+
+		Report {
+			ready: p2p.ready
+				&& rpc.as_ref().map_or(true, |check| check.ready)
+				&& block_lag.as_ref().map_or(true, |check| check.ready),
+			p2p,
+			rpc,
+			block_lag,
+		}
+	}
+}
+
+fn block_lag_check(db: &impl Database, max_lag: u32) -> BlockLagCheck {
+	let latest = db.get(LatestHeaderKey);
+	let verified = db
+		.get(AchievedConfidenceKey)
+		.map(|range: BlockRange| range.last);
+	let lag = latest
+		.zip(verified)
+		.map(|(latest, verified)| latest.saturating_sub(verified));
+
+	BlockLagCheck {
+		ready: lag.is_some_and(|lag| lag <= max_lag),
+		latest,
+		verified,
+		lag,
+		max_lag,
+	}
+}
+
+async fn health(checks: Checks<impl Database + Clone>) -> impl Reply {
+	warp::reply::json(&checks.liveness())
+}
+
+async fn ready(checks: Checks<impl Database + Clone>) -> impl Reply {
+	let report = checks.report().await;
+	let status = if report.ready {
+		StatusCode::OK
+	} else {
+		StatusCode::SERVICE_UNAVAILABLE
+	};
+	warp::reply::with_status(warp::reply::json(&report), status)
+}
+
+pub fn routes<T: Database + Clone + Send + Sync + 'static>(
+	checks: Checks<T>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	let with_checks = warp::any().map(move || checks.clone());
+
+	let health_route = warp::path!("health")
+		.and(warp::head().or(warp::get()).unify())
+		.and(with_checks.clone())
+		.then(health);
+
+	let ready_route = warp::path!("ready")
+		.and(warp::head().or(warp::get()).unify())
+		.and(with_checks)
+		.then(ready);
+
+	health_route.or(ready_route)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::MemoryDB;
+	use test_case::test_case;
+
+	#[test_case(None, None => (false, None) ; "no blocks")]
+	#[test_case(Some(10), None => (false, None) ; "no verified blocks")]
+	#[test_case(Some(10), Some(5) => (true, Some(5)) ; "lag within threshold")]
+	#[test_case(Some(10), Some(4) => (false, Some(6)) ; "lag above threshold")]
+	fn block_lag(latest: Option<u32>, verified: Option<u32>) -> (bool, Option<u32>) {
+		let db = MemoryDB::default();
+		if let Some(latest) = latest {
+			db.put(LatestHeaderKey, latest);
+		}
+		if let Some(verified) = verified {
+			db.put(AchievedConfidenceKey, BlockRange::init(verified));
+		}
+
+		let check = block_lag_check(&db, 5);
+		(check.ready, check.lag)
+	}
+}
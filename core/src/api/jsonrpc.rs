@@ -0,0 +1,694 @@
+//! JSON-RPC 2.0 server, compatible with the substrate-style tooling and dashboards.
+//!
+//! Requests are sent with `POST /rpc`, or over the web socket connection upgraded on `/rpc`, on the same port as the HTTP API.
+//! Batch requests are supported, while subscriptions are only available over the web socket.
+//! Parameters can be passed either by position or by name.
+//! Size of the requests, number of the requests in the batch and number of the subscriptions per web socket connection are limited.
+//!
+//! # Methods
+//!
+//! * `rpc_methods` - returns the list of supported methods
+//! * `lc_version` - returns the version of the light client and the supported network
+//! * `lc_status` - returns the status of the light client, same as `/v2/status`
+//! * `lc_blockConfidence(block_number)` - returns block status and confidence, or `null` if block is not known
+//! * `lc_blockStatus(block_number)` - returns the outcome of the block processing, or `null` if block is not processed
+//! * `lc_appData(app_id, from_block, to_block?, limit?, format?)` - returns verified application data, same as `/v2/apps/{app_id}/data`
+//! * `lc_subscribeConfidence` - subscribes to the `lc_confidence` notifications of the verified blocks, returns the subscription ID
+//! * `lc_unsubscribeConfidence(subscription_id)` - cancels the subscription, returns `true` if the subscription existed
+
+use futures::{FutureExt, StreamExt};
+use hyper::{body::Bytes, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::{
+	sync::{broadcast, mpsc},
+	task::JoinHandle,
+};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{error, warn};
+use uuid::Uuid;
+use warp::{
+	ws::{Message, WebSocket, Ws},
+	Filter, Rejection, Reply,
+};
+
+use crate::{
+	api::v2::{
+		handlers,
+		types::{AppDataQuery, Error, ErrorCode, Status, Version},
+	},
+	data::Database,
+	types::{BlockVerified, RuntimeConfig},
+	utils::spawn_in_span,
+};
+
+const JSONRPC_VERSION: &str = "2.0";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+/// Server error, returned if the requested resource is not available (e.g. application is not followed)
+const NOT_FOUND: i64 = -32001;
+/// Server error, returned if the request exceeds the limits (e.g. batch is too large)
+const LIMIT_EXCEEDED: i64 = -32005;
+
+/// Maximum size of the HTTP request body or the web socket message, in bytes
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+/// Maximum number of the requests in the batch
+const MAX_BATCH_SIZE: usize = 32;
+/// Maximum number of the subscriptions per web socket connection
+const MAX_SUBSCRIPTIONS: usize = 16;
+
+const METHODS: [&str; 8] = [
+	"rpc_methods",
+	"lc_version",
+	"lc_status",
+	"lc_blockConfidence",
+	"lc_blockStatus",
+	"lc_appData",
+	"lc_subscribeConfidence",
+	"lc_unsubscribeConfidence",
+];
+
+#[derive(Deserialize)]
+struct Request {
+	jsonrpc: String,
+	/// Requests without the ID are notifications, and are not answered
+	id: Option<Value>,
+	method: String,
+	#[serde(default)]
+	params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ErrorObject {
+	pub code: i64,
+	pub message: String,
+}
+
+impl ErrorObject {
+	fn new(code: i64, message: &str) -> Self {
+		ErrorObject {
+			code,
+			message: message.to_string(),
+		}
+	}
+
+	fn invalid_params(message: &str) -> Self {
+		Self::new(INVALID_PARAMS, &format!("Invalid params: {message}"))
+	}
+}
+
+impl From<Error> for ErrorObject {
+	fn from(error: Error) -> Self {
+		match error.error_code {
+			ErrorCode::NotFound => ErrorObject::new(NOT_FOUND, &error.message),
+			ErrorCode::BadRequest => ErrorObject::invalid_params(&error.message),
+			ErrorCode::InternalServerError => {
+				if let Some(cause) = &error.cause {
+					error!("{}: {cause:#}", error.message);
+				}
+				ErrorObject::new(INTERNAL_ERROR, &error.message)
+			},
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+	Result(Value),
+	Error(ErrorObject),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Response {
+	pub jsonrpc: String,
+	#[serde(flatten)]
+	pub outcome: Outcome,
+	pub id: Value,
+}
+
+impl Response {
+	fn new(id: Value, result: Result<Value, ErrorObject>) -> Self {
+		Response {
+			jsonrpc: JSONRPC_VERSION.to_string(),
+			outcome: match result {
+				Ok(value) => Outcome::Result(value),
+				Err(error) => Outcome::Error(error),
+			},
+			id,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Responses {
+	Single(Response),
+	Batch(Vec<Response>),
+}
+
+#[derive(Serialize)]
+struct SubscriptionResult<T> {
+	subscription: String,
+	result: T,
+}
+
+#[derive(Serialize)]
+struct Notification<T> {
+	jsonrpc: &'static str,
+	method: &'static str,
+	params: SubscriptionResult<T>,
+}
+
+#[derive(Serialize)]
+struct Confidence {
+	block_number: u32,
+	confidence: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct BlockParams {
+	block_number: u32,
+}
+
+#[derive(Deserialize)]
+struct AppDataParams {
+	app_id: u32,
+	#[serde(flatten)]
+	query: AppDataQuery,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeParams {
+	subscription_id: String,
+}
+
+/// Parses positional or named parameters, positional parameters are named in the given order
+fn parse_params<P: DeserializeOwned>(params: Value, names: &[&str]) -> Result<P, ErrorObject> {
+	let params = match params {
+		Value::Null => Value::Object(Default::default()),
+		Value::Array(values) if values.len() > names.len() => {
+			return Err(ErrorObject::invalid_params("Too many parameters"));
+		},
+		Value::Array(values) => names
+			.iter()
+			.map(|name| name.to_string())
+			.zip(values)
+			.collect(),
+		params => params,
+	};
+	serde_json::from_value(params).map_err(|error| ErrorObject::invalid_params(&error.to_string()))
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, ErrorObject> {
+	serde_json::to_value(value).map_err(|error| {
+		error!("Cannot serialize JSON-RPC result: {error}");
+		ErrorObject::new(INTERNAL_ERROR, "Internal Server Error")
+	})
+}
+
+/// Converts the missing resource into the `null` result
+fn optional(result: Result<impl Serialize, Error>) -> Result<Value, ErrorObject> {
+	match result {
+		Ok(value) => to_value(value),
+		Err(Error {
+			error_code: ErrorCode::NotFound,
+			..
+		}) => Ok(Value::Null),
+		Err(error) => Err(error.into()),
+	}
+}
+
+type Sender = mpsc::UnboundedSender<Result<Message, warp::Error>>;
+
+/// Confidence subscriptions of the web socket connection, cancelled when the connection is closed
+struct Subscriptions {
+	sender: Sender,
+	tasks: HashMap<String, JoinHandle<()>>,
+}
+
+impl Subscriptions {
+	fn subscribe(
+		&mut self,
+		block_sender: &broadcast::Sender<BlockVerified>,
+	) -> Result<String, ErrorObject> {
+		if self.tasks.len() >= MAX_SUBSCRIPTIONS {
+			let message = format!("Connection has more than {MAX_SUBSCRIPTIONS} subscriptions");
+			return Err(ErrorObject::new(LIMIT_EXCEEDED, &message));
+		}
+		let subscription_id = Uuid::new_v4().to_string();
+		let mut receiver = block_sender.subscribe();
+		let sender = self.sender.clone();
+		let subscription = subscription_id.clone();
+
+		let task = spawn_in_span(async move {
+			loop {
+				let block = match receiver.recv().await {
+					Ok(block) => block,
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						warn!(
+							subscription,
+							"Confidence subscription skipped {skipped} blocks"
+						);
+						continue;
+					},
+					Err(broadcast::error::RecvError::Closed) => return,
+				};
+				let notification = Notification {
+					jsonrpc: JSONRPC_VERSION,
+					method: "lc_confidence",
+					params: SubscriptionResult {
+						subscription: subscription.clone(),
+						result: Confidence {
+							block_number: block.block_num,
+							confidence: block.confidence,
+						},
+					},
+				};
+				let Ok(message) = serde_json::to_string(&notification) else {
+					continue;
+				};
+				if sender.send(Ok(Message::text(message))).is_err() {
+					return;
+				}
+			}
+		});
+
+		self.tasks.insert(subscription_id.clone(), task);
+		Ok(subscription_id)
+	}
+
+	fn unsubscribe(&mut self, subscription_id: &str) -> bool {
+		self.tasks
+			.remove(subscription_id)
+			.map(|task| task.abort())
+			.is_some()
+	}
+}
+
+impl Drop for Subscriptions {
+	fn drop(&mut self) {
+		for task in self.tasks.values() {
+			task.abort();
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct Context<T: Database> {
+	version: Version,
+	config: RuntimeConfig,
+	db: T,
+	block_sender: broadcast::Sender<BlockVerified>,
+}
+
+impl<T: Database + Clone + Send + Sync + 'static> Context<T> {
+	pub fn new(
+		version: String,
+		network_version: String,
+		config: RuntimeConfig,
+		db: T,
+		block_sender: broadcast::Sender<BlockVerified>,
+	) -> Self {
+		Context {
+			version: Version {
+				version,
+				network_version,
+			},
+			config,
+			db,
+			block_sender,
+		}
+	}
+
+	async fn call(
+		&self,
+		method: &str,
+		params: Value,
+		subscriptions: Option<&mut Subscriptions>,
+	) -> Result<Value, ErrorObject> {
+		match method {
+			"rpc_methods" => to_value(serde_json::json!({ "methods": METHODS })),
+			"lc_version" => to_value(&self.version),
+			"lc_status" => to_value(Status::new(&self.config, self.db.clone())),
+			"lc_blockConfidence" => {
+				let BlockParams { block_number } = parse_params(params, &["block_number"])?;
+				optional(handlers::block(block_number, self.config.clone(), self.db.clone()).await)
+			},
+			"lc_blockStatus" => {
+				let BlockParams { block_number } = parse_params(params, &["block_number"])?;
+				optional(handlers::block_processing_status(block_number, self.db.clone()).await)
+			},
+			"lc_appData" => {
+				let names = ["app_id", "from_block", "to_block", "limit", "format"];
+				let AppDataParams { app_id, query } = parse_params(params, &names)?;
+				handlers::app_data(app_id, query, self.config.clone(), self.db.clone())
+					.await
+					.map_err(ErrorObject::from)
+					.and_then(to_value)
+			},
+			"lc_subscribeConfidence" | "lc_unsubscribeConfidence" => {
+				let Some(subscriptions) = subscriptions else {
+					return Err(ErrorObject::new(
+						METHOD_NOT_FOUND,
+						"Subscriptions are only supported over the web socket",
+					));
+				};
+				if method == "lc_subscribeConfidence" {
+					return subscriptions
+						.subscribe(&self.block_sender)
+						.and_then(to_value);
+				}
+				let UnsubscribeParams { subscription_id } =
+					parse_params(params, &["subscription_id"])?;
+				to_value(subscriptions.unsubscribe(&subscription_id))
+			},
+			_ => Err(ErrorObject::new(METHOD_NOT_FOUND, "Method not found")),
+		}
+	}
+
+	async fn handle_request(
+		&self,
+		request: Value,
+		subscriptions: Option<&mut Subscriptions>,
+	) -> Option<Response> {
+		let request = match serde_json::from_value::<Request>(request) {
+			Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+			Ok(request) => {
+				let error = ErrorObject::new(INVALID_REQUEST, "Invalid request");
+				return Some(Response::new(request.id.unwrap_or_default(), Err(error)));
+			},
+			Err(_) => {
+				let error = ErrorObject::new(INVALID_REQUEST, "Invalid request");
+				return Some(Response::new(Value::Null, Err(error)));
+			},
+		};
+
+		let result = self
+			.call(&request.method, request.params, subscriptions)
+			.await;
+		Some(Response::new(request.id?, result))
+	}
+
+	/// Handles the single or batch request, returns `None` if there is nothing to respond (e.g. notifications only)
+	async fn handle(
+		&self,
+		message: &[u8],
+		mut subscriptions: Option<&mut Subscriptions>,
+	) -> Option<Responses> {
+		let message = match serde_json::from_slice::<Value>(message) {
+			Ok(message) => message,
+			Err(_) => {
+				let error = ErrorObject::new(PARSE_ERROR, "Parse error");
+				return Some(Responses::Single(Response::new(Value::Null, Err(error))));
+			},
+		};
+
+		let Value::Array(requests) = message else {
+			return self
+				.handle_request(message, subscriptions)
+				.await
+				.map(Responses::Single);
+		};
+
+		if requests.is_empty() {
+			let error = ErrorObject::new(INVALID_REQUEST, "Invalid request");
+			return Some(Responses::Single(Response::new(Value::Null, Err(error))));
+		}
+		if requests.len() > MAX_BATCH_SIZE {
+			let message = format!("Batch has more than {MAX_BATCH_SIZE} requests");
+			let error = ErrorObject::new(LIMIT_EXCEEDED, &message);
+			return Some(Responses::Single(Response::new(Value::Null, Err(error))));
+		}
+
+		let mut responses = vec![];
+		for request in requests {
+			let response = self
+				.handle_request(request, subscriptions.as_deref_mut())
+				.await;
+			responses.extend(response);
+		}
+		(!responses.is_empty()).then_some(Responses::Batch(responses))
+	}
+}
+
+async fn http(
+	body: Bytes,
+	context: Context<impl Database + Clone + Send + Sync + 'static>,
+) -> warp::reply::Response {
+	match context.handle(&body, None).await {
+		Some(responses) => warp::reply::json(&responses).into_response(),
+		None => StatusCode::NO_CONTENT.into_response(),
+	}
+}
+
+async fn connect(
+	web_socket: WebSocket,
+	context: Context<impl Database + Clone + Send + Sync + 'static>,
+) {
+	let (web_socket_sender, mut web_socket_receiver) = web_socket.split();
+	let (sender, receiver) = mpsc::unbounded_channel();
+
+	spawn_in_span(
+		UnboundedReceiverStream::new(receiver)
+			.forward(web_socket_sender)
+			.map(|result| {
+				if let Err(error) = result {
+					error!("Error sending web socket message: {error}");
+				}
+			}),
+	);
+
+	let mut subscriptions = Subscriptions {
+		sender: sender.clone(),
+		tasks: HashMap::new(),
+	};
+
+	while let Some(result) = web_socket_receiver.next().await {
+		let message = match result {
+			Err(error) => {
+				error!("Error receiving client message: {error}");
+				continue;
+			},
+			Ok(message) if !message.is_text() => continue,
+			Ok(message) => message,
+		};
+
+		let Some(responses) = context
+			.handle(message.as_bytes(), Some(&mut subscriptions))
+			.await
+		else {
+			continue;
+		};
+
+		let send_result = serde_json::to_string(&responses)
+			.map_err(|error| error.to_string())
+			.and_then(|message| {
+				sender
+					.send(Ok(Message::text(message)))
+					.map_err(|error| error.to_string())
+			});
+		if let Err(error) = send_result {
+			warn!("Error sending JSON-RPC response: {error}");
+		}
+	}
+}
+
+pub fn routes<T: Database + Clone + Send + Sync + 'static>(
+	context: Context<T>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	let with_context = warp::any().map(move || context.clone());
+
+	let http_route = warp::path!("rpc")
+		.and(warp::post())
+		.and(warp::body::content_length_limit(MAX_REQUEST_SIZE))
+		.and(warp::body::bytes())
+		.and(with_context.clone())
+		.then(http);
+
+	let ws_route =
+		warp::path!("rpc")
+			.and(warp::ws())
+			.and(with_context)
+			.map(|ws: Ws, context: Context<T>| {
+				ws.max_message_size(MAX_REQUEST_SIZE as usize)
+					.on_upgrade(move |web_socket| connect(web_socket, context))
+			});
+
+	http_route.or(ws_route)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		data::{BlockProcessingKey, MemoryDB},
+		light_client::{BlockProcessing, ProcessingOutcome},
+	};
+	use test_case::test_case;
+
+	fn context() -> Context<MemoryDB> {
+		let db = MemoryDB::default();
+		db.put(
+			BlockProcessingKey(10),
+			BlockProcessing {
+				outcome: ProcessingOutcome::Verified,
+				required: 10,
+				verified: 10,
+				dht_fetched: 10,
+				rpc_fetched: 0,
+				dht_stats: Default::default(),
+				duration: 250,
+				processed_at: 1700000000,
+				total_cells: 64,
+				withheld_cells: 32,
+			},
+		);
+		Context::new(
+			"v1.0.0".to_string(),
+			"nv1.0.0".to_string(),
+			RuntimeConfig::default(),
+			db,
+			broadcast::channel(1).0,
+		)
+	}
+
+	async fn request(body: &str) -> (StatusCode, String) {
+		let route = routes(context());
+		let response = warp::test::request()
+			.method("POST")
+			.path("/rpc")
+			.body(body)
+			.reply(&route)
+			.await;
+		let body = String::from_utf8(response.body().to_vec()).unwrap();
+		(response.status(), body)
+	}
+
+	#[test_case(r#"{"jsonrpc":"2.0","id":1,"method":"lc_version"}"#, r#"{"jsonrpc":"2.0","result":{"version":"v1.0.0","network_version":"nv1.0.0"},"id":1}"# ; "Version")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":"a","method":"lc_blockStatus","params":[11]}"#, r#"{"jsonrpc":"2.0","result":null,"id":"a"}"# ; "Block not processed")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":1,"method":"lc_blockStatus","params":{"block_number":"ten"}}"#, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: invalid type: string \"ten\", expected u32"},"id":1}"# ; "Invalid named params")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":1,"method":"lc_blockStatus","params":[10,11]}"#, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: Too many parameters"},"id":1}"# ; "Too many params")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":1,"method":"lc_appData","params":[1,5]}"#, r#"{"jsonrpc":"2.0","error":{"code":-32001,"message":"Not Found"},"id":1}"# ; "Application not followed")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":1,"method":"lc_subscribeConfidence"}"#, r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Subscriptions are only supported over the web socket"},"id":1}"# ; "Subscription over HTTP")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":1,"method":"chain_getHeader"}"#, r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"# ; "Unknown method")]
+	#[test_case(r#"{"jsonrpc":"1.0","id":1,"method":"lc_version"}"#, r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":1}"# ; "Invalid version")]
+	#[test_case(r#"{"jsonrpc":"2.0","id":1"#, r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"# ; "Parse error")]
+	#[test_case("[]", r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Invalid request"},"id":null}"# ; "Empty batch")]
+	#[tokio::test]
+	async fn http_request(body: &str, expected: &str) {
+		assert_eq!(request(body).await, (StatusCode::OK, expected.to_string()));
+	}
+
+	#[tokio::test]
+	async fn http_batch_request() {
+		let (status, body) = request(
+			r#"[
+				{"jsonrpc":"2.0","id":1,"method":"lc_blockStatus","params":[10]},
+				{"jsonrpc":"2.0","method":"lc_version"},
+				{"jsonrpc":"2.0","id":2,"method":"rpc_methods"}
+			]"#,
+		)
+		.await;
+		assert_eq!(status, StatusCode::OK);
+
+		let Responses::Batch(responses) = serde_json::from_str(&body).unwrap() else {
+			panic!("Batch response expected");
+		};
+		assert_eq!(responses.len(), 2);
+		let Outcome::Result(status) = &responses[0].outcome else {
+			panic!("Result expected");
+		};
+		assert_eq!(status["outcome"], "verified");
+		assert_eq!(responses[1].id, 2);
+
+		let notification = r#"{"jsonrpc":"2.0","method":"lc_version"}"#;
+		assert_eq!(
+			request(notification).await,
+			(StatusCode::NO_CONTENT, "".to_string())
+		);
+	}
+
+	#[tokio::test]
+	async fn http_request_limits() {
+		let batch = vec![r#"{"jsonrpc":"2.0","id":1,"method":"lc_version"}"#; MAX_BATCH_SIZE + 1];
+		assert_eq!(
+			request(&format!("[{}]", batch.join(","))).await,
+			(
+				StatusCode::OK,
+				r#"{"jsonrpc":"2.0","error":{"code":-32005,"message":"Batch has more than 32 requests"},"id":null}"#.to_string()
+			)
+		);
+
+		let body = " ".repeat(MAX_REQUEST_SIZE as usize + 1);
+		assert_eq!(request(&body).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+	}
+
+	#[tokio::test]
+	async fn subscriptions_limit() {
+		let (sender, _receiver) = mpsc::unbounded_channel();
+		let mut subscriptions = Subscriptions {
+			sender,
+			tasks: HashMap::new(),
+		};
+		let block_sender = broadcast::channel(1).0;
+		for _ in 0..MAX_SUBSCRIPTIONS {
+			assert!(subscriptions.subscribe(&block_sender).is_ok());
+		}
+		let error = subscriptions.subscribe(&block_sender).unwrap_err();
+		assert_eq!(error.code, LIMIT_EXCEEDED);
+
+		let subscription_id = subscriptions.tasks.keys().next().unwrap().clone();
+		assert!(subscriptions.unsubscribe(&subscription_id));
+		assert!(subscriptions.subscribe(&block_sender).is_ok());
+	}
+
+	#[tokio::test]
+	async fn ws_confidence_subscription() {
+		let context = context();
+		let block_sender = context.block_sender.clone();
+		let route = routes(context);
+		let mut client = warp::test::ws()
+			.path("/rpc")
+			.handshake(route)
+			.await
+			.unwrap();
+
+		client
+			.send_text(r#"{"jsonrpc":"2.0","id":1,"method":"lc_subscribeConfidence"}"#)
+			.await;
+		let response: Response =
+			serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+		let Outcome::Result(Value::String(subscription_id)) = response.outcome else {
+			panic!("Subscription ID expected");
+		};
+
+		block_sender
+			.send(BlockVerified {
+				header_hash: Default::default(),
+				block_num: 10,
+				extension: None,
+				confidence: Some(99.9),
+			})
+			.unwrap();
+		let notification: Value =
+			serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+		assert_eq!(notification["method"], "lc_confidence");
+		assert_eq!(notification["params"]["subscription"], subscription_id);
+		assert_eq!(notification["params"]["result"]["block_number"], 10);
+
+		client
+			.send_text(format!(
+				r#"{{"jsonrpc":"2.0","id":2,"method":"lc_unsubscribeConfidence","params":["{subscription_id}"]}}"#
+			))
+			.await;
+		let response: Response =
+			serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+		assert_eq!(response.outcome, Outcome::Result(Value::Bool(true)));
+	}
+}
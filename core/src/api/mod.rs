@@ -1,3 +1,8 @@
+pub mod auth;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod health;
+pub mod jsonrpc;
 pub mod server;
 mod v1;
 pub mod v2;
@@ -2,17 +2,27 @@
 //!
 //! # Endpoints
 //!
+//! * `/health` - returns health check details of the light client
+//! * `/ready` - returns health check details, with `503` status code if the client is not ready
 //! * `/v1/mode` - returns client mode (light or light+app client)
 //! * `/v1/status` - returns status of a latest processed block
 //! * `/v1/latest_block` - returns latest processed block
 //! * `/v1/confidence/{block_number}` - returns calculated confidence for a given block number
 //! * `/v1/appdata/{block_number}` - returns decoded extrinsic data for configured app_id and given block number
+//! * `/v2/admin/*` - changes the runtime parameters and the peer blocklist, if the admin authentication is configured
+//! * `/rpc` - JSON-RPC 2.0 server, over HTTP and web socket
+//!
+//! If the API keys or the JWT secret are configured, all endpoints except `/health` and `/ready` require authentication.
 
-use crate::api::v2;
+use crate::api::{
+	auth::{self, Auth},
+	health, jsonrpc, v2,
+};
 use crate::data::Database;
+use crate::maintenance;
 use crate::network::p2p;
 use crate::shutdown::Controller;
-use crate::types::IdentityConfig;
+use crate::types::{BlockVerified, IdentityConfig, Role};
 use crate::{
 	api::v1,
 	network::rpc::{self},
@@ -21,8 +31,9 @@ use crate::{
 use color_eyre::eyre::WrapErr;
 use futures::{Future, FutureExt};
 use std::{net::SocketAddr, str::FromStr};
+use tokio::sync::broadcast;
 use tracing::info;
-use warp::{Filter, Reply};
+use warp::Filter;
 
 pub struct Server<T: Database> {
 	pub db: T,
@@ -30,17 +41,17 @@ pub struct Server<T: Database> {
 	pub identity_cfg: IdentityConfig,
 	pub version: String,
 	pub network_version: String,
-	pub node_client: rpc::Client<T>,
+	/// Node's RPC client, not set in the DHT-only mode
+	pub node_client: Option<rpc::Client<T>>,
 	pub ws_clients: v2::types::WsClients,
 	pub shutdown: Controller<String>,
 	pub p2p_client: p2p::Client,
-}
-
-fn health_route() -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
-	warp::head()
-		.or(warp::get())
-		.and(warp::path("health"))
-		.map(|_| warp::reply::with_status("", warp::http::StatusCode::OK))
+	pub maintenance_stats: maintenance::Stats,
+	/// Admin API, enabled if the admin API key or the JWT secret is configured
+	pub admin: v2::admin::Admin<T>,
+	pub submissions: v2::submissions::Submissions<T>,
+	/// Sender of the verified blocks, used for the JSON-RPC subscriptions
+	pub block_sender: broadcast::Sender<BlockVerified>,
 }
 
 impl<T: Database + Clone + Send + Sync + 'static> Server<T> {
@@ -53,7 +64,26 @@ impl<T: Database + Clone + Send + Sync + 'static> Server<T> {
 			..
 		} = self.cfg.clone();
 
-		let v1_api = v1::routes(self.db.clone(), app_id, self.cfg.clone());
+		let health_checks = health::Checks::new(
+			&self.cfg,
+			self.db.clone(),
+			self.p2p_client.clone(),
+			self.node_client.clone(),
+		);
+		let v1_api = auth::authorize(Auth::new(&self.cfg), Role::ReadOnly).and(v1::routes(
+			self.db.clone(),
+			app_id,
+			self.cfg.clone(),
+		));
+		let jsonrpc_api = auth::authorize(Auth::new(&self.cfg), Role::ReadOnly).and(
+			jsonrpc::routes(jsonrpc::Context::new(
+				self.version.clone(),
+				self.network_version.clone(),
+				self.cfg.clone(),
+				self.db.clone(),
+				self.block_sender,
+			)),
+		);
 		let v2_api = v2::routes(
 			self.version.clone(),
 			self.network_version.clone(),
@@ -63,14 +93,22 @@ impl<T: Database + Clone + Send + Sync + 'static> Server<T> {
 			self.ws_clients.clone(),
 			self.db.clone(),
 			self.p2p_client.clone(),
+			self.maintenance_stats.clone(),
+			self.admin,
+			self.submissions,
 		);
 
 		let cors = warp::cors()
 			.allow_any_origin()
-			.allow_header("content-type")
-			.allow_methods(vec!["GET", "POST", "DELETE"]);
+			.allow_headers(vec!["content-type", "authorization"])
+			.allow_methods(vec!["GET", "POST", "PUT", "DELETE"]);
 
-		let routes = health_route().or(v1_api).or(v2_api).with(cors);
+		let routes = health::routes(health_checks)
+			.or(v1_api)
+			.or(v2_api)
+			.or(jsonrpc_api)
+			.recover(auth::handle_rejection)
+			.with(cors);
 
 		let addr = SocketAddr::from_str(format!("{host}:{port}").as_str())
 			.wrap_err("Unable to parse host address from config")
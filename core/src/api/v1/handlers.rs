@@ -1,10 +1,9 @@
 use super::types::{AppDataQuery, ClientResponse, ConfidenceResponse, LatestBlockResponse, Status};
 use crate::{
 	api::v1::types::{Extrinsics, ExtrinsicsDataResponse},
-	data::{AchievedConfidenceKey, AppDataKey, Database, VerifiedCellCountKey},
-	network::rpc::cell_count_for_confidence,
+	data::{AchievedConfidenceKey, AppDataKey, Database},
+	sampling::block_confidence,
 	types::{BlockRange, Mode, RuntimeConfig},
-	utils::calculate_confidence,
 };
 use avail_subxt::{
 	api::runtime_types::{da_control::pallet::Call, da_runtime::RuntimeCall},
@@ -42,13 +41,12 @@ pub fn confidence(
 
 	info!("Got request for confidence for block {block_num}");
 
-	let count = match db.get(VerifiedCellCountKey(block_num)) {
-		Some(count) => count,
-		None if is_synced(block_num, db) => cell_count_for_confidence(cfg.confidence),
+	let confidence = match block_confidence(&db, block_num) {
+		Some(confidence) => confidence,
+		None if is_synced(block_num, db) => cfg.confidence,
 		None => return ClientResponse::NotFinalized,
 	};
 
-	let confidence = calculate_confidence(count);
 	let serialised_confidence = serialised_confidence(block_num, confidence);
 
 	let response = ClientResponse::Normal(ConfidenceResponse {
@@ -64,15 +62,12 @@ pub fn status(app_id: Option<u32>, db: impl Database) -> ClientResponse<Status>
 	let Some(BlockRange { last, .. }) = get_achived_confidence(&db) else {
 		return ClientResponse::NotFound;
 	};
-	let res = match db.get(VerifiedCellCountKey(last)) {
-		Some(count) => {
-			let confidence = calculate_confidence(count);
-			ClientResponse::Normal(Status {
-				block_num: last,
-				confidence,
-				app_id,
-			})
-		},
+	let res = match block_confidence(&db, last) {
+		Some(confidence) => ClientResponse::Normal(Status {
+			block_num: last,
+			confidence,
+			app_id,
+		}),
 		None => ClientResponse::NotFound,
 	};
 	info!("Returning status: {res:?}");
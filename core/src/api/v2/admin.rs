@@ -0,0 +1,265 @@
+use color_eyre::{eyre::eyre, Result};
+use libp2p::PeerId;
+use std::{
+	str::FromStr,
+	sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+use tracing::{info, warn, Level};
+
+use crate::{
+	data::{Database, RuntimeParametersKey},
+	maintenance::ServerModeThresholds,
+	network::{p2p, rpc},
+	types::{RuntimeConfig, RuntimeParameters},
+};
+
+/// Changes the log level of the installed tracing subscriber
+pub type LogLevelReload = Arc<dyn Fn(Level) -> Result<()> + Send + Sync>;
+
+/// Applies the runtime parameter changes and manages the peer blocklist
+#[derive(Clone)]
+pub struct Admin<T: Database> {
+	db: T,
+	p2p_client: p2p::Client,
+	rpc_client: Option<rpc::Client<T>>,
+	log_level: LogLevelReload,
+	confidence: Arc<watch::Sender<f64>>,
+	server_mode_thresholds: Arc<watch::Sender<ServerModeThresholds>>,
+	parameters: Arc<Mutex<RuntimeParameters>>,
+}
+
+impl<T: Database + Clone> Admin<T> {
+	pub fn new(
+		cfg: &RuntimeConfig,
+		db: T,
+		p2p_client: p2p::Client,
+		rpc_client: Option<rpc::Client<T>>,
+		log_level: LogLevelReload,
+		confidence: watch::Sender<f64>,
+		server_mode_thresholds: watch::Sender<ServerModeThresholds>,
+	) -> Self {
+		Admin {
+			db,
+			p2p_client,
+			rpc_client,
+			log_level,
+			confidence: Arc::new(confidence),
+			server_mode_thresholds: Arc::new(server_mode_thresholds),
+			parameters: Arc::new(Mutex::new(cfg.into())),
+		}
+	}
+
+	/// Returns current values of the runtime parameters
+	pub fn parameters(&self) -> RuntimeParameters {
+		self.parameters
+			.lock()
+			.expect("Lock is not poisoned")
+			.clone()
+	}
+
+	/// Checks whether the parameters can be applied
+	pub fn validate(&self, parameters: &RuntimeParameters) -> Result<()> {
+		if let Some(log_level) = &parameters.log_level {
+			Level::from_str(log_level).map_err(|error| eyre!("Invalid log level: {error}"))?;
+		}
+		if parameters.dht_parallelization_limit == Some(0) {
+			return Err(eyre!("DHT parallelization limit must be greater than 0"));
+		}
+		if let Some(confidence) = parameters.confidence {
+			if !(confidence > 0.0 && confidence < 100.0) {
+				return Err(eyre!("Confidence must be between 0 and 100"));
+			}
+		}
+		if let Some(full_node_ws) = &parameters.full_node_ws {
+			if self.rpc_client.is_none() {
+				return Err(eyre!("Full nodes cannot be changed while RPC is not used"));
+			}
+			if full_node_ws.is_empty() {
+				return Err(eyre!("List of the full nodes must not be empty"));
+			}
+		}
+		if let Some(threshold) = parameters.total_memory_gb_threshold {
+			if !(threshold > 0.0 && threshold.is_finite()) {
+				return Err(eyre!("Total memory threshold must be greater than 0"));
+			}
+		}
+		if parameters.num_cpus_threshold == Some(0) {
+			return Err(eyre!("Number of CPUs threshold must be greater than 0"));
+		}
+		Ok(())
+	}
+
+	/// Applies the set parameters, and persists them to be applied on restart if `persist` is set.
+	/// Parameters are validated before any of them is applied.
+	pub async fn reconfigure(
+		&self,
+		parameters: RuntimeParameters,
+		persist: bool,
+	) -> Result<RuntimeParameters> {
+		self.validate(&parameters)?;
+
+		if let Some(log_level) = &parameters.log_level {
+			(self.log_level)(Level::from_str(log_level)?)?;
+		}
+		if let Some(limit) = parameters.dht_parallelization_limit {
+			self.p2p_client.set_dht_parallelization_limit(limit);
+		}
+		if let Some(confidence) = parameters.confidence {
+			self.confidence.send_replace(confidence);
+		}
+		if let (Some(full_node_ws), Some(rpc_client)) = (&parameters.full_node_ws, &self.rpc_client)
+		{
+			rpc_client.set_nodes(full_node_ws).await?;
+		}
+		self.server_mode_thresholds.send_modify(|thresholds| {
+			if let Some(total_memory_gb) = parameters.total_memory_gb_threshold {
+				thresholds.total_memory_gb = total_memory_gb;
+			}
+			if let Some(num_cpus) = parameters.num_cpus_threshold {
+				thresholds.num_cpus = num_cpus;
+			}
+		});
+		info!(?parameters, persist, "Runtime parameters changed");
+
+		if persist {
+			let mut persisted = self
+				.db
+				.get(RuntimeParametersKey)
+				.and_then(|json| serde_json::from_str::<RuntimeParameters>(&json).ok())
+				.unwrap_or_default();
+			persisted.merge(parameters.clone());
+			self.db
+				.put(RuntimeParametersKey, serde_json::to_string(&persisted)?);
+		}
+
+		let mut current = self.parameters.lock().expect("Lock is not poisoned");
+		current.merge(parameters);
+		Ok(current.clone())
+	}
+
+	/// Applies the runtime parameters changed in the reloaded configuration, without persisting them.
+	/// Persisted parameters take precedence over the reloaded ones, same as on the startup.
+	/// Reload is rejected if any parameter which requires the restart is changed.
+	pub async fn reload(
+		&self,
+		cfg: &RuntimeConfig,
+		mut reloaded: RuntimeConfig,
+	) -> Result<RuntimeParameters> {
+		apply_persisted(&self.db, &mut reloaded);
+		let non_reloadable = cfg.non_reloadable_changes(&reloaded)?;
+		if !non_reloadable.is_empty() {
+			return Err(eyre!(
+				"Changes require the restart: {}",
+				non_reloadable.join(", ")
+			));
+		}
+		let changed = RuntimeParameters::from(&reloaded).changed(&self.parameters());
+		if changed == RuntimeParameters::default() {
+			return Ok(self.parameters());
+		}
+		self.reconfigure(changed, false).await
+	}
+
+	pub async fn blocked_peers(&self) -> Result<Vec<PeerId>> {
+		self.p2p_client.list_blocked_peers().await
+	}
+
+	pub async fn block_peer(&self, peer_id: PeerId) -> Result<()> {
+		self.p2p_client.block_peer(self.db.clone(), peer_id).await
+	}
+
+	pub async fn unblock_peer(&self, peer_id: PeerId) -> Result<()> {
+		self.p2p_client.unblock_peer(self.db.clone(), peer_id).await
+	}
+}
+
+/// Overrides the configuration with the runtime parameters persisted through the admin API
+pub fn apply_persisted(db: &impl Database, cfg: &mut RuntimeConfig) {
+	let Some(json) = db.get(RuntimeParametersKey) else {
+		return;
+	};
+	match serde_json::from_str::<RuntimeParameters>(&json) {
+		Ok(parameters) => {
+			info!(?parameters, "Applying persisted runtime parameters");
+			parameters.apply(cfg);
+		},
+		Err(error) => warn!("Cannot parse persisted runtime parameters: {error}"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{data::MemoryDB, types::SecretKey};
+
+	#[test]
+	fn apply_persisted_parameters() {
+		let db = MemoryDB::default();
+		let mut cfg = RuntimeConfig::default();
+		apply_persisted(&db, &mut cfg);
+		assert_eq!(cfg.confidence, RuntimeConfig::default().confidence);
+
+		let mut persisted = RuntimeParameters {
+			confidence: Some(99.9),
+			..Default::default()
+		};
+		persisted.merge(RuntimeParameters {
+			log_level: Some("debug".to_string()),
+			..Default::default()
+		});
+		db.put(
+			RuntimeParametersKey,
+			serde_json::to_string(&persisted).unwrap(),
+		);
+
+		apply_persisted(&db, &mut cfg);
+		assert_eq!(cfg.confidence, 99.9);
+		assert_eq!(cfg.log_level, "debug");
+		assert_eq!(
+			cfg.dht_parallelization_limit,
+			RuntimeConfig::default().dht_parallelization_limit
+		);
+	}
+
+	#[test]
+	fn non_reloadable_changes() {
+		let cfg = RuntimeConfig::default();
+		let mut reloaded = cfg.clone();
+		reloaded.confidence = 99.9;
+		reloaded.full_node_ws = vec!["ws://127.0.0.1:9945".to_string()];
+		reloaded.num_cpus_threshold = 8;
+		assert!(cfg.non_reloadable_changes(&reloaded).unwrap().is_empty());
+
+		reloaded.port = 37001;
+		reloaded.secret_key = Some(SecretKey::Seed {
+			seed: "avail".to_string(),
+		});
+		assert_eq!(
+			cfg.non_reloadable_changes(&reloaded).unwrap(),
+			vec!["port", "secret_key"]
+		);
+	}
+
+	#[test]
+	fn changed_parameters() {
+		let cfg = RuntimeConfig::default();
+		let mut reloaded = cfg.clone();
+		reloaded.log_level = "debug".to_string();
+		reloaded.total_memory_gb_threshold = 32.0;
+
+		let changed = RuntimeParameters::from(&reloaded).changed(&(&cfg).into());
+		assert_eq!(
+			changed,
+			RuntimeParameters {
+				log_level: Some("debug".to_string()),
+				total_memory_gb_threshold: Some(32.0),
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			RuntimeParameters::from(&cfg).changed(&(&cfg).into()),
+			RuntimeParameters::default()
+		);
+	}
+}
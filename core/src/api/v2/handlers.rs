@@ -1,17 +1,21 @@
 use super::{
+	submissions::Submissions,
 	transactions,
 	types::{
-		block_status, filter_fields, Block, BlockStatus, DataQuery, DataResponse, DataTransaction,
-		Error, FieldsQueryParameter, Header, Status, SubmitResponse, Subscription, SubscriptionId,
+		block_status, filter_fields, AppData, AppDataBlock, AppDataQuery, AppDataResponse, Block,
+		BlockProcessingStatus, BlockRangeQuery, BlockStatus, BlocksStatusResponse, DataQuery,
+		DataResponse, DataTransaction, Error, FieldsQueryParameter, Header, LedgerQuery,
+		LedgerResponse, Status, Submission, SubmitResponse, Subscription, SubscriptionId,
 		Transaction, Version, WsClients,
 	},
 	ws,
 };
 use crate::{
 	api::v2::types::{ErrorCode, InternalServerError},
-	data::{AppDataKey, BlockHeaderKey, Database, VerifiedCellCountKey},
+	data::{AppDataKey, BlockHeaderKey, BlockProcessingKey, Database},
+	ledger, maintenance,
+	sampling::block_confidence,
 	types::RuntimeConfig,
-	utils::calculate_confidence,
 };
 use avail_subxt::primitives;
 use color_eyre::{eyre::eyre, Result};
@@ -20,8 +24,20 @@ use std::{convert::Infallible, sync::Arc};
 use tracing::error;
 use uuid::Uuid;
 use warp::{ws::Ws, Rejection, Reply};
+pub mod admin;
 pub mod p2p;
 
+/// Maximum number of the blocks in the block status and application data range queries
+const MAX_BLOCK_RANGE: u32 = 1000;
+/// Default number of the data transactions in the application data response
+const DEFAULT_APP_DATA_LIMIT: usize = 100;
+/// Maximum number of the data transactions in the application data response
+const MAX_APP_DATA_LIMIT: usize = 1000;
+/// Default number of the entries in the ledger response
+const DEFAULT_LEDGER_LIMIT: usize = 100;
+/// Maximum number of the entries in the ledger response
+const MAX_LEDGER_LIMIT: usize = 1000;
+
 pub async fn subscriptions(
 	subscription: Subscription,
 	clients: WsClients,
@@ -41,6 +57,24 @@ pub async fn submit(
 		.map_err(Error::internal_server_error)
 }
 
+pub async fn submission(
+	submitter: Arc<impl transactions::Submit + Send + Sync + 'static>,
+	transaction: Transaction,
+	submissions: Submissions<impl Database + Clone + Send + Sync + 'static>,
+) -> Result<Submission, Error> {
+	if transaction.is_empty() {
+		return Err(Error::bad_request_unknown("Transaction is empty"));
+	}
+	Ok(submissions.submit(submitter, transaction))
+}
+
+pub async fn submission_status(
+	submission_id: Uuid,
+	submissions: Submissions<impl Database + Clone + Send + Sync + 'static>,
+) -> Result<Submission, Error> {
+	submissions.get(&submission_id).ok_or_else(Error::not_found)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn ws(
 	subscription_id: String,
@@ -72,6 +106,21 @@ pub fn status(config: RuntimeConfig, db: impl Database) -> impl Reply {
 	Status::new(&config, db)
 }
 
+pub fn maintenance(stats: maintenance::Stats) -> impl Reply {
+	warp::reply::json(&stats.get())
+}
+
+pub async fn ledger(query: LedgerQuery, db: impl Database) -> Result<LedgerResponse, Error> {
+	let limit = query.limit.unwrap_or(DEFAULT_LEDGER_LIMIT);
+	if limit == 0 || limit > MAX_LEDGER_LIMIT {
+		return Err(Error::bad_request_unknown(&format!(
+			"Limit must be between 1 and {MAX_LEDGER_LIMIT}"
+		)));
+	}
+	let entries = ledger::entries(&db, query.before, limit);
+	Ok(LedgerResponse { entries })
+}
+
 pub fn log_internal_server_error(result: Result<impl Reply, Error>) -> Result<impl Reply, Error> {
 	if let Err(Error {
 		error_code: ErrorCode::InternalServerError,
@@ -89,7 +138,7 @@ pub async fn block(
 	block_number: u32,
 	config: RuntimeConfig,
 	db: impl Database + Clone,
-) -> Result<impl Reply, Error> {
+) -> Result<Block, Error> {
 	let sync_start_block = &config.sync_start_block;
 
 	let block_status = db
@@ -98,13 +147,132 @@ pub async fn block(
 		.and_then(|extension| block_status(sync_start_block, db.clone(), block_number, extension))
 		.ok_or(Error::not_found())?;
 
-	let confidence = db
-		.get(VerifiedCellCountKey(block_number))
-		.map(calculate_confidence);
+	let confidence = block_confidence(&db, block_number);
 
 	Ok(Block::new(block_status, confidence))
 }
 
+pub async fn block_processing_status(
+	block_number: u32,
+	db: impl Database,
+) -> Result<BlockProcessingStatus, Error> {
+	db.get(BlockProcessingKey(block_number))
+		.map(|processing| BlockProcessingStatus::new(block_number, processing))
+		.ok_or(Error::not_found())
+}
+
+pub async fn blocks_processing_status(
+	query: BlockRangeQuery,
+	db: impl Database,
+) -> Result<BlocksStatusResponse, Error> {
+	if query.from > query.to {
+		return Err(Error::bad_request_unknown(
+			"Range start must not be greater than range end",
+		));
+	}
+	if query.to - query.from >= MAX_BLOCK_RANGE {
+		return Err(Error::bad_request_unknown(&format!(
+			"Range must not contain more than {MAX_BLOCK_RANGE} blocks"
+		)));
+	}
+
+	let blocks = (query.from..=query.to)
+		.filter_map(|block_number| {
+			db.get(BlockProcessingKey(block_number))
+				.map(|processing| BlockProcessingStatus::new(block_number, processing))
+		})
+		.collect();
+
+	Ok(BlocksStatusResponse { blocks })
+}
+
+pub async fn app_data(
+	app_id: u32,
+	query: AppDataQuery,
+	config: RuntimeConfig,
+	db: impl Database + Clone,
+) -> Result<AppDataResponse, Error> {
+	if !config.app_ids().contains(&app_id) {
+		return Err(Error::not_found());
+	}
+
+	let from_block = query.from_block;
+	let to_block = query
+		.to_block
+		.unwrap_or_else(|| from_block.saturating_add(MAX_BLOCK_RANGE - 1));
+	if from_block > to_block {
+		return Err(Error::bad_request_unknown(
+			"Range start must not be greater than range end",
+		));
+	}
+	if to_block - from_block >= MAX_BLOCK_RANGE {
+		return Err(Error::bad_request_unknown(&format!(
+			"Range must not contain more than {MAX_BLOCK_RANGE} blocks"
+		)));
+	}
+	let limit = query.limit.unwrap_or(DEFAULT_APP_DATA_LIMIT);
+	if limit == 0 || limit > MAX_APP_DATA_LIMIT {
+		return Err(Error::bad_request_unknown(&format!(
+			"Limit must be between 1 and {MAX_APP_DATA_LIMIT}"
+		)));
+	}
+
+	let mut blocks = vec![];
+	let mut count = 0;
+	let mut next_block = None;
+	for block_number in from_block..=to_block {
+		if count >= limit {
+			next_block = Some(block_number);
+			break;
+		}
+
+		// status of the blocks without the stored header is checked as well,
+		// so the blocks preceding the sync are skipped instead of ending the page
+		let extension = db
+			.get(BlockHeaderKey(block_number))
+			.map(|primitives::Header { extension, .. }| extension);
+		let has_header = extension.is_some();
+		let status = block_status(
+			&config.sync_start_block,
+			db.clone(),
+			block_number,
+			extension,
+		);
+		match status {
+			Some(BlockStatus::Finished) => {},
+			// blocks which will not have the verified data
+			Some(BlockStatus::Unavailable) => continue,
+			Some(BlockStatus::Incomplete) if has_header => continue,
+			// page ends at the first block which is above the latest or still being processed,
+			// so the next page starts with it
+			_ => {
+				next_block = Some(block_number);
+				break;
+			},
+		}
+
+		let data = db
+			.get(AppDataKey(app_id, block_number))
+			.unwrap_or_default()
+			.into_iter()
+			.filter_map(|extrinsic| AppData::new(extrinsic, query.format).transpose())
+			.collect::<Result<Vec<_>>>()
+			.map_err(Error::internal_server_error)?;
+		if data.is_empty() {
+			continue;
+		}
+
+		count += data.len();
+		blocks.push(AppDataBlock { block_number, data });
+	}
+
+	Ok(AppDataResponse {
+		app_id,
+		blocks,
+		next_block,
+	})
+}
+
 pub async fn block_header(
 	block_number: u32,
 	config: RuntimeConfig,
@@ -137,7 +305,11 @@ pub async fn block_data(
 	config: RuntimeConfig,
 	db: impl Database + Clone,
 ) -> Result<DataResponse, Error> {
-	let app_id = config.app_id.ok_or(Error::not_found())?;
+	let app_id = query
+		.app_id
+		.or(config.app_id)
+		.filter(|app_id| config.app_ids().contains(app_id))
+		.ok_or(Error::not_found())?;
 	let sync_start_block = &config.sync_start_block;
 
 	let block_status = db
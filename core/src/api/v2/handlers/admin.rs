@@ -0,0 +1,88 @@
+use crate::{
+	api::v2::{admin::Admin, types::Error},
+	data::Database,
+	types::RuntimeParameters,
+};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use warp::reply::Reply;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ParametersQuery {
+	/// Persist the parameters, so they are applied after the restart
+	#[serde(default)]
+	pub persist: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlocklistResponse {
+	peers: Vec<String>,
+}
+
+impl Reply for BlocklistResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+pub async fn get_parameters(admin: Admin<impl Database + Clone>) -> impl Reply {
+	warp::reply::json(&admin.parameters())
+}
+
+pub async fn put_parameters(
+	query: ParametersQuery,
+	admin: Admin<impl Database + Clone>,
+	parameters: RuntimeParameters,
+) -> Result<impl Reply, Error> {
+	admin
+		.validate(&parameters)
+		.map_err(|error| Error::bad_request_unknown(&error.to_string()))?;
+
+	admin
+		.reconfigure(parameters, query.persist)
+		.await
+		.map(|parameters| warp::reply::json(&parameters))
+		.map_err(Error::internal_server_error)
+}
+
+pub async fn get_blocklist(
+	admin: Admin<impl Database + Clone>,
+) -> Result<BlocklistResponse, Error> {
+	let peers = admin
+		.blocked_peers()
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	Ok(BlocklistResponse {
+		peers: peers.iter().map(PeerId::to_string).collect(),
+	})
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, Error> {
+	PeerId::from_str(peer_id).map_err(|error| Error::bad_request_unknown(&error.to_string()))
+}
+
+pub async fn block_peer(
+	peer_id: String,
+	admin: Admin<impl Database + Clone>,
+) -> Result<BlocklistResponse, Error> {
+	admin
+		.block_peer(parse_peer_id(&peer_id)?)
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	get_blocklist(admin).await
+}
+
+pub async fn unblock_peer(
+	peer_id: String,
+	admin: Admin<impl Database + Clone>,
+) -> Result<BlocklistResponse, Error> {
+	admin
+		.unblock_peer(parse_peer_id(&peer_id)?)
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	get_blocklist(admin).await
+}
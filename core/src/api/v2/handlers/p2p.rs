@@ -4,6 +4,7 @@ use crate::{
 };
 use libp2p::{swarm::DialError, Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use warp::reply::Reply;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -15,11 +16,11 @@ pub struct Listeners {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PeerInfoResponse {
-	peer_id: String,
-	listeners: Listeners,
-	operation_mode: String,
-	routing_table_peers_count: usize,
-	routing_table_external_peers_count: usize,
+	pub peer_id: String,
+	pub listeners: Listeners,
+	pub operation_mode: String,
+	pub routing_table_peers_count: usize,
+	pub routing_table_external_peers_count: usize,
 }
 
 impl Reply for PeerInfoResponse {
@@ -28,6 +29,40 @@ impl Reply for PeerInfoResponse {
 	}
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectedPeer {
+	pub peer_id: String,
+	/// Latest ping round-trip time (in milliseconds)
+	pub latest_latency: Option<u64>,
+	/// Average ping round-trip time (in milliseconds)
+	pub average_latency: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConnectedPeersResponse {
+	pub peers: Vec<ConnectedPeer>,
+}
+
+impl Reply for ConnectedPeersResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DhtInfoResponse {
+	pub routing_table_peers_count: usize,
+	pub routing_table_external_peers_count: usize,
+	/// Number of records in the local Kademlia store
+	pub kademlia_map_size: usize,
+}
+
+impl Reply for DhtInfoResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct ExternalPeerDialError {
 	pub error: String,
@@ -89,7 +124,12 @@ pub async fn get_peer_info(p2p_client: p2p::Client) -> Result<PeerInfoResponse,
 		peer_id: local_info.peer_id,
 		operation_mode: local_info.operation_mode,
 		listeners: Listeners {
-			local: local_info.local_listeners,
+			local: local_info
+				.local_listeners
+				.iter()
+				.flat_map(|listener| &listener.listen_addresses)
+				.map(ToString::to_string)
+				.collect(),
 			external: local_info.external_listeners,
 			public: vec![],
 		},
@@ -98,6 +138,43 @@ pub async fn get_peer_info(p2p_client: p2p::Client) -> Result<PeerInfoResponse,
 	})
 }
 
+pub async fn get_connected_peers(p2p_client: p2p::Client) -> Result<ConnectedPeersResponse, Error> {
+	let peers = p2p_client
+		.list_connected_peers_with_latency()
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	let as_millis = |latency: Duration| latency.as_millis() as u64;
+	let peers = peers
+		.into_iter()
+		.map(|(peer_id, latency)| ConnectedPeer {
+			peer_id: peer_id.to_string(),
+			latest_latency: latency.map(|latency| as_millis(latency.latest)),
+			average_latency: latency.map(|latency| as_millis(latency.average)),
+		})
+		.collect();
+
+	Ok(ConnectedPeersResponse { peers })
+}
+
+pub async fn get_dht_info(p2p_client: p2p::Client) -> Result<DhtInfoResponse, Error> {
+	let (routing_table_peers_count, routing_table_external_peers_count) = p2p_client
+		.count_dht_entries()
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	let kademlia_map_size = p2p_client
+		.get_kademlia_map_size()
+		.await
+		.map_err(Error::internal_server_error)?;
+
+	Ok(DhtInfoResponse {
+		routing_table_peers_count,
+		routing_table_external_peers_count,
+		kademlia_map_size,
+	})
+}
+
 pub async fn get_peer_multiaddr(
 	p2p_client: p2p::Client,
 	query: PeerInfoQuery,
@@ -1,21 +1,30 @@
 use std::{convert::Infallible, fmt::Display, sync::Arc};
 use tokio::sync::broadcast;
 use tracing::{debug, error, info};
+use uuid::Uuid;
 use warp::{Filter, Rejection, Reply};
 
 use self::{
 	handlers::{handle_rejection, log_internal_server_error},
-	types::{DataQuery, PublishMessage, Version, WsClients},
+	types::{
+		AppDataQuery, BlockRangeQuery, DataQuery, LedgerQuery, PublishMessage, Version, WsClients,
+	},
 };
 
 use crate::{
-	api::v2::types::Topic,
+	api::{
+		auth::{self, Auth},
+		v2::types::Topic,
+	},
 	data::Database,
+	maintenance,
 	network::{p2p, rpc::Client},
-	types::{IdentityConfig, RuntimeConfig},
+	types::{IdentityConfig, Role, RuntimeConfig},
 };
 
-mod handlers;
+pub mod admin;
+pub(crate) mod handlers;
+pub mod submissions;
 mod transactions;
 pub mod types;
 mod ws;
@@ -70,6 +79,40 @@ fn block_route(
 		.map(log_internal_server_error)
 }
 
+fn app_data_route(
+	config: RuntimeConfig,
+	db: impl Database + Clone + Send,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "apps" / u32 / "data")
+		.and(warp::get())
+		.and(warp::query::<AppDataQuery>())
+		.and(warp::any().map(move || config.clone()))
+		.and(with_db(db))
+		.then(handlers::app_data)
+		.map(log_internal_server_error)
+}
+
+fn block_processing_status_route(
+	db: impl Database + Clone + Send,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / u32 / "status")
+		.and(warp::get())
+		.and(with_db(db))
+		.then(handlers::block_processing_status)
+		.map(log_internal_server_error)
+}
+
+fn blocks_processing_status_route(
+	db: impl Database + Clone + Send,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "blocks" / "status")
+		.and(warp::get())
+		.and(warp::query::<BlockRangeQuery>())
+		.and(with_db(db))
+		.then(handlers::blocks_processing_status)
+		.map(log_internal_server_error)
+}
+
 fn block_header_route(
 	config: RuntimeConfig,
 	db: impl Database + Clone + Send,
@@ -95,17 +138,64 @@ fn block_data_route(
 		.map(log_internal_server_error)
 }
 
+fn maintenance_route(
+	stats: maintenance::Stats,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "maintenance")
+		.and(warp::get())
+		.and(warp::any().map(move || stats.clone()))
+		.map(handlers::maintenance)
+}
+
+fn ledger_route(
+	db: impl Database + Clone + Send,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "ledger")
+		.and(warp::get())
+		.and(warp::query::<LedgerQuery>())
+		.and(with_db(db))
+		.then(handlers::ledger)
+		.map(log_internal_server_error)
+}
+
 fn submit_route(
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync>>,
+	auth: Auth,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	warp::path!("v2" / "submit")
 		.and(warp::post())
+		.and(auth::authorize(auth, Role::Admin))
 		.and_then(move || optionally(submitter.clone()))
 		.and(warp::body::json())
 		.then(handlers::submit)
 		.map(log_internal_server_error)
 }
 
+fn submission_route<T: Database + Clone + Send + Sync + 'static>(
+	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync + 'static>>,
+	submissions: submissions::Submissions<T>,
+	auth: Auth,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "submissions")
+		.and(warp::post())
+		.and(auth::authorize(auth, Role::Admin))
+		.and_then(move || optionally(submitter.clone()))
+		.and(warp::body::json())
+		.and(warp::any().map(move || submissions.clone()))
+		.then(handlers::submission)
+		.map(log_internal_server_error)
+}
+
+fn submission_status_route<T: Database + Clone + Send + Sync + 'static>(
+	submissions: submissions::Submissions<T>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "submissions" / Uuid)
+		.and(warp::get())
+		.and(warp::any().map(move || submissions.clone()))
+		.then(handlers::submission_status)
+		.map(log_internal_server_error)
+}
+
 fn p2p_local_info_route(
 	p2p_client: p2p::Client,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
@@ -116,11 +206,33 @@ fn p2p_local_info_route(
 		.map(log_internal_server_error)
 }
 
+fn p2p_peers_route(
+	p2p_client: p2p::Client,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "peers")
+		.and(warp::get())
+		.and(warp::any().map(move || p2p_client.clone()))
+		.then(handlers::p2p::get_connected_peers)
+		.map(log_internal_server_error)
+}
+
+fn p2p_dht_info_route(
+	p2p_client: p2p::Client,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "p2p" / "dht" / "info")
+		.and(warp::get())
+		.and(warp::any().map(move || p2p_client.clone()))
+		.then(handlers::p2p::get_dht_info)
+		.map(log_internal_server_error)
+}
+
 fn p2p_peers_dial_route(
 	p2p_client: p2p::Client,
+	auth: Auth,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	warp::path!("v2" / "p2p" / "peers" / "dial")
 		.and(warp::post())
+		.and(auth::authorize(auth, Role::Admin))
 		.and(warp::any().map(move || p2p_client.clone()))
 		.and(warp::body::json())
 		.then(handlers::p2p::dial_external_peer)
@@ -138,6 +250,67 @@ fn p2p_peer_multiaddr_route(
 		.map(log_internal_server_error)
 }
 
+fn admin_parameters_route<T: Database + Clone + Send + Sync + 'static>(
+	admin: Option<admin::Admin<T>>,
+	auth: Auth,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "admin" / "parameters")
+		.and(warp::get())
+		.and(auth::authorize(auth, Role::Admin))
+		.and_then(move || optionally(admin.clone()))
+		.then(handlers::admin::get_parameters)
+}
+
+fn admin_reconfigure_route<T: Database + Clone + Send + Sync + 'static>(
+	admin: Option<admin::Admin<T>>,
+	auth: Auth,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "admin" / "parameters")
+		.and(warp::put())
+		.and(warp::query::<handlers::admin::ParametersQuery>())
+		.and(auth::authorize(auth, Role::Admin))
+		.and_then(move || optionally(admin.clone()))
+		.and(warp::body::json())
+		.then(handlers::admin::put_parameters)
+		.map(log_internal_server_error)
+}
+
+fn admin_blocklist_route<T: Database + Clone + Send + Sync + 'static>(
+	admin: Option<admin::Admin<T>>,
+	auth: Auth,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "admin" / "blocklist")
+		.and(warp::get())
+		.and(auth::authorize(auth, Role::Admin))
+		.and_then(move || optionally(admin.clone()))
+		.then(handlers::admin::get_blocklist)
+		.map(log_internal_server_error)
+}
+
+fn admin_block_peer_route<T: Database + Clone + Send + Sync + 'static>(
+	admin: Option<admin::Admin<T>>,
+	auth: Auth,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "admin" / "blocklist" / String)
+		.and(warp::put())
+		.and(auth::authorize(auth, Role::Admin))
+		.and_then(move || optionally(admin.clone()))
+		.then(handlers::admin::block_peer)
+		.map(log_internal_server_error)
+}
+
+fn admin_unblock_peer_route<T: Database + Clone + Send + Sync + 'static>(
+	admin: Option<admin::Admin<T>>,
+	auth: Auth,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+	warp::path!("v2" / "admin" / "blocklist" / String)
+		.and(warp::delete())
+		.and(auth::authorize(auth, Role::Admin))
+		.and_then(move || optionally(admin.clone()))
+		.then(handlers::admin::unblock_peer)
+		.map(log_internal_server_error)
+}
+
 fn subscriptions_route(
 	clients: WsClients,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
@@ -154,13 +327,18 @@ fn ws_route(
 	config: RuntimeConfig,
 	submitter: Option<Arc<impl transactions::Submit + Clone + Send + Sync + 'static>>,
 	db: impl Database + Clone + Send + 'static,
+	auth: Auth,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	warp::path!("v2" / "ws" / String)
 		.and(warp::ws())
 		.and(with_ws_clients(clients))
 		.and(warp::any().map(move || version.clone()))
 		.and(warp::any().map(move || config.clone()))
-		.and(warp::any().map(move || submitter.clone()))
+		// same as over HTTP, only admin can submit transactions
+		.and(
+			auth::authenticate(auth)
+				.map(move |role| submitter.clone().filter(|_| role == Role::Admin)),
+		)
 		.and(with_db(db))
 		.and_then(handlers::ws)
 }
@@ -204,15 +382,18 @@ pub async fn publish<T: Clone + TryInto<PublishMessage>>(
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn routes(
+pub fn routes<T: Database + Clone + Send + Sync + 'static>(
 	version: String,
 	network_version: String,
 	config: RuntimeConfig,
 	identity_config: IdentityConfig,
-	rpc_client: Client<impl Database + Send + Sync + Clone + 'static>,
+	rpc_client: Option<Client<impl Database + Send + Sync + Clone + 'static>>,
 	ws_clients: WsClients,
 	db: impl Database + Clone + Send + 'static,
 	p2p_client: p2p::Client,
+	maintenance_stats: maintenance::Stats,
+	admin: admin::Admin<T>,
+	submissions: submissions::Submissions<T>,
 ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
 	let version = Version {
 		version,
@@ -220,8 +401,11 @@ pub fn routes(
 	};
 
 	let app_id = config.app_id.as_ref();
+	let auth = Auth::new(&config);
+	let admin = auth.is_admin_enabled().then_some(admin);
 
-	let submitter = app_id.map(|&app_id| {
+	// transactions can't be submitted without the node
+	let submitter = app_id.zip(rpc_client).map(|(&app_id, rpc_client)| {
 		Arc::new(transactions::Submitter {
 			rpc_client,
 			app_id,
@@ -229,34 +413,66 @@ pub fn routes(
 		})
 	});
 
-	version_route(version.clone())
+	let read_only_routes = version_route(version.clone())
 		.or(status_route(config.clone(), db.clone()))
 		.or(block_route(config.clone(), db.clone()))
 		.or(block_header_route(config.clone(), db.clone()))
 		.or(block_data_route(config.clone(), db.clone()))
+		.or(app_data_route(config.clone(), db.clone()))
+		.or(block_processing_status_route(db.clone()))
+		.or(blocks_processing_status_route(db.clone()))
 		.or(subscriptions_route(ws_clients.clone()))
-		.or(submit_route(submitter.clone()))
-		.or(ws_route(ws_clients, version, config, submitter, db.clone()))
 		.or(p2p_local_info_route(p2p_client.clone()))
-		.or(p2p_peers_dial_route(p2p_client.clone()))
+		.or(p2p_peers_route(p2p_client.clone()))
+		.or(p2p_dht_info_route(p2p_client.clone()))
 		.or(p2p_peer_multiaddr_route(p2p_client.clone()))
+		.or(maintenance_route(maintenance_stats))
+		.or(ledger_route(db.clone()))
+		.or(submission_status_route(submissions.clone()));
+
+	auth::authorize(auth.clone(), Role::ReadOnly)
+		.and(read_only_routes)
+		.or(submit_route(submitter.clone(), auth.clone()))
+		.or(submission_route(
+			submitter.clone(),
+			submissions,
+			auth.clone(),
+		))
+		.or(ws_route(
+			ws_clients,
+			version,
+			config,
+			submitter,
+			db.clone(),
+			auth.clone(),
+		))
+		.or(p2p_peers_dial_route(p2p_client, auth.clone()))
+		.or(admin_parameters_route(admin.clone(), auth.clone()))
+		.or(admin_reconfigure_route(admin.clone(), auth.clone()))
+		.or(admin_blocklist_route(admin.clone(), auth.clone()))
+		.or(admin_block_peer_route(admin.clone(), auth.clone()))
+		.or(admin_unblock_peer_route(admin, auth))
 		.recover(handle_rejection)
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{transactions, types::Transaction};
+	use super::{submissions::Submissions, transactions, types::Transaction, Auth};
 	use crate::{
 		api::v2::types::{
-			DataField, ErrorCode, SubmitResponse, Subscription, SubscriptionId, Topic, Version,
-			WsClients, WsError, WsResponse,
+			AppDataResponse, BlocksStatusResponse, DataField, ErrorCode, LedgerResponse,
+			Submission, SubmissionStatus, SubmitResponse, Subscription, SubscriptionId, Topic,
+			Version, WsClients, WsError, WsResponse,
 		},
 		data::{
 			self, AchievedConfidenceKey, AchievedSyncConfidenceKey, AppDataKey, BlockHeaderKey,
-			Database, IsSyncedKey, LatestHeaderKey, LatestSyncKey, MemoryDB, VerifiedCellCountKey,
-			VerifiedDataKey, VerifiedHeaderKey, VerifiedSyncDataKey,
+			BlockProcessingKey, Database, IsSyncedKey, LatestHeaderKey, LatestSyncKey, MemoryDB,
+			SamplingResultKey, VerifiedDataKey, VerifiedHeaderKey, VerifiedSyncDataKey,
 		},
-		types::{BlockRange, RuntimeConfig},
+		ledger::{self, NodeEvent},
+		light_client::{BlockProcessing, ProcessingOutcome},
+		sampling::SamplingResult,
+		types::{ApiKey, BlockRange, Role, RuntimeConfig},
 	};
 	use async_trait::async_trait;
 	use avail_subxt::{api::runtime_types::avail_core::AppId, utils::H256};
@@ -274,6 +490,7 @@ mod tests {
 	use subxt::config::substrate::Digest;
 	use test_case::test_case;
 	use uuid::Uuid;
+	use warp::Filter;
 
 	fn v1() -> Version {
 		Version {
@@ -358,7 +575,7 @@ mod tests {
 
 		let gen_hash = H256::default();
 		let expected = format!(
-			r#"{{"modes":["light","app","partition"],"app_id":1,"genesis_hash":"{:#x}","network":"{NETWORK}","blocks":{{"latest":30,"available":{{"first":20,"last":29}},"app_data":{{"first":20,"last":29}},"historical_sync":{{"synced":false,"available":{{"first":10,"last":19}},"app_data":{{"first":10,"last":18}}}}}},"partition":"1/10"}}"#,
+			r#"{{"modes":["light","app","partition"],"app_id":1,"genesis_hash":"{:#x}","network":"{NETWORK}","blocks":{{"latest":30,"available":{{"first":20,"last":29}},"head_lag":1,"app_data":{{"first":20,"last":29}},"historical_sync":{{"synced":false,"available":{{"first":10,"last":19}},"app_data":{{"first":10,"last":18}}}}}},"partition":"1/10"}}"#,
 			gen_hash
 		);
 		assert_eq!(response.body(), &expected);
@@ -411,7 +628,18 @@ mod tests {
 		db.put(LatestHeaderKey, 10);
 		db.put(VerifiedHeaderKey, BlockRange::init(10));
 		db.put(VerifiedDataKey, BlockRange::init(10));
-		db.put(VerifiedCellCountKey(10), 4);
+		db.put(
+			SamplingResultKey(10),
+			SamplingResult {
+				block_number: 10,
+				required: 4,
+				sampled: 4,
+				verified: 4,
+				rounds: 1,
+				total_cells: 8,
+				withheld_cells: 1,
+			},
+		);
 		db.put(BlockHeaderKey(10), header());
 		let route = super::block_route(config, db);
 		let response = warp::test::request()
@@ -423,7 +651,154 @@ mod tests {
 		assert_eq!(response.status(), StatusCode::OK);
 		assert_eq!(
 			response.body(),
-			r#"{"status":"finished","confidence":93.75}"#
+			r#"{"status":"finished","confidence":50.0}"#
+		);
+	}
+
+	fn block_processing(outcome: ProcessingOutcome, verified: u32) -> BlockProcessing {
+		BlockProcessing {
+			outcome,
+			required: 10,
+			verified,
+			dht_fetched: verified,
+			rpc_fetched: 0,
+			dht_stats: Default::default(),
+			duration: 250,
+			processed_at: 1700000000,
+			total_cells: 8,
+			withheld_cells: 1,
+		}
+	}
+
+	#[tokio::test]
+	async fn block_processing_status_route() {
+		let db = data::MemoryDB::default();
+		let route = super::block_processing_status_route(db.clone());
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/10/status")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+		db.put(
+			BlockProcessingKey(10),
+			block_processing(ProcessingOutcome::Verified, 4),
+		);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/10/status")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			r#"{"block_number":10,"outcome":"verified","required":10,"verified":4,"dht_fetched":4,"rpc_fetched":0,"dht_stats":{"cached":0,"fetched":0,"queries":0,"queries_duration":0,"failures":{"not_found":0,"timeout":0,"quorum_failed":0,"invalid_record":0,"other":0,"invalid_proof":0,"cancelled":0}},"duration":250,"processed_at":1700000000,"total_cells":8,"withheld_cells":1,"confidence":50.0,"dht_average_latency":null}"#
+		);
+	}
+
+	#[test_case("from=10&to=9", "Range start must not be greater than range end" ; "Reversed range")]
+	#[test_case("from=0&to=1000", "Range must not contain more than 1000 blocks" ; "Range too large")]
+	#[tokio::test]
+	async fn blocks_processing_status_route_bad_request(query: &str, expected: &str) {
+		let route = super::blocks_processing_status_route(data::MemoryDB::default());
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/blocks/status?{query}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		assert!(String::from_utf8_lossy(response.body()).contains(expected));
+	}
+
+	#[tokio::test]
+	async fn blocks_processing_status_route() {
+		let db = data::MemoryDB::default();
+		db.put(
+			BlockProcessingKey(10),
+			block_processing(ProcessingOutcome::Verified, 10),
+		);
+		db.put(
+			BlockProcessingKey(12),
+			block_processing(ProcessingOutcome::Skipped, 0),
+		);
+		db.put(
+			BlockProcessingKey(20),
+			block_processing(ProcessingOutcome::Unavailable, 2),
+		);
+		let route = super::blocks_processing_status_route(db);
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/blocks/status?from=10&to=19")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let response: BlocksStatusResponse = serde_json::from_slice(response.body()).unwrap();
+		let blocks = response
+			.blocks
+			.iter()
+			.map(|block| {
+				(
+					block.block_number,
+					block.processing.outcome,
+					block.confidence,
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			blocks,
+			vec![
+				(10, ProcessingOutcome::Verified, Some(100.0)),
+				(12, ProcessingOutcome::Skipped, None),
+			]
+		);
+	}
+
+	#[test_case("" => vec![2, 1, 0] ; "All entries")]
+	#[test_case("?before=2" => vec![1, 0] ; "Before sequence")]
+	#[test_case("?limit=1" => vec![2] ; "Limited")]
+	#[tokio::test]
+	async fn ledger_route(query: &str) -> Vec<u64> {
+		let db = data::MemoryDB::default();
+		for block_number in 1..=3 {
+			ledger::record(
+				&db,
+				NodeEvent::Reorg {
+					block_number,
+					orphaned_blocks: 1,
+				},
+			);
+		}
+		let route = super::ledger_route(db);
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/ledger{query}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let response: LedgerResponse = serde_json::from_slice(response.body()).unwrap();
+		response
+			.entries
+			.iter()
+			.map(|entry| entry.sequence)
+			.collect()
+	}
+
+	#[test_case("limit=0" ; "Zero limit")]
+	#[test_case("limit=1001" ; "Limit too large")]
+	#[tokio::test]
+	async fn ledger_route_bad_request(query: &str) {
+		let route = super::ledger_route(data::MemoryDB::default());
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/ledger?{query}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+		assert!(
+			String::from_utf8_lossy(response.body()).contains("Limit must be between 1 and 1000")
 		);
 	}
 
@@ -626,11 +1001,143 @@ mod tests {
 		);
 	}
 
+	fn app_data_db() -> data::MemoryDB {
+		let extrinsic = vec![
+			189, 1, 132, 0, 212, 53, 147, 199, 21, 253, 211, 28, 97, 20, 26, 189, 4, 169, 159, 214,
+			130, 44, 133, 88, 133, 76, 205, 227, 154, 86, 132, 231, 165, 109, 162, 125, 1, 50, 12,
+			43, 176, 19, 42, 23, 73, 70, 223, 198, 180, 103, 34, 60, 246, 184, 49, 140, 113, 174,
+			234, 229, 95, 71, 18, 92, 158, 185, 168, 140, 126, 12, 191, 156, 50, 234, 8, 4, 68,
+			137, 5, 156, 94, 209, 7, 169, 105, 62, 63, 1, 122, 253, 195, 112, 173, 239, 21, 73,
+			163, 240, 106, 109, 131, 0, 4, 0, 4, 29, 1, 20, 116, 101, 115, 116, 10,
+		];
+		let db = data::MemoryDB::default();
+		db.put(LatestHeaderKey, 10);
+		db.put(VerifiedHeaderKey, BlockRange { first: 5, last: 9 });
+		db.put(AchievedConfidenceKey, BlockRange { first: 5, last: 9 });
+		db.put(VerifiedDataKey, BlockRange { first: 5, last: 8 });
+		for block_number in 5..=9 {
+			db.put(BlockHeaderKey(block_number), header());
+			// block 7 has no data, and data of block 9 is not verified yet
+			if block_number != 7 {
+				db.put(AppDataKey(1, block_number), vec![extrinsic.clone()]);
+			}
+		}
+		db
+	}
+
+	#[test_case("from_block=5&to_block=8", StatusCode::OK, &[5, 6, 8], None ; "Whole range")]
+	#[test_case("from_block=5&to_block=9&limit=2", StatusCode::OK, &[5, 6], Some(7) ; "First page")]
+	#[test_case("from_block=7&to_block=9&limit=2", StatusCode::OK, &[8], Some(9) ; "Unfinished block")]
+	#[test_case("from_block=5", StatusCode::OK, &[5, 6, 8], Some(9) ; "Default range")]
+	#[test_case("from_block=10&to_block=11", StatusCode::OK, &[], Some(10) ; "Unprocessed block")]
+	#[test_case("from_block=9&to_block=5", StatusCode::BAD_REQUEST, &[], None ; "Reversed range")]
+	#[test_case("from_block=0&to_block=1000", StatusCode::BAD_REQUEST, &[], None ; "Range too large")]
+	#[test_case("from_block=5&limit=0", StatusCode::BAD_REQUEST, &[], None ; "Zero limit")]
+	#[tokio::test]
+	async fn app_data_route(
+		query: &str,
+		status: StatusCode,
+		block_numbers: &[u32],
+		next_block: Option<u32>,
+	) {
+		let config = RuntimeConfig {
+			app_id: Some(1),
+			..Default::default()
+		};
+		let route = super::app_data_route(config, app_data_db());
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/apps/1/data?{query}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), status);
+		if status != StatusCode::OK {
+			return;
+		}
+
+		let response: AppDataResponse = serde_json::from_slice(response.body()).unwrap();
+		let blocks = response
+			.blocks
+			.iter()
+			.map(|block| block.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, block_numbers);
+		assert_eq!(response.next_block, next_block);
+	}
+
+	#[test_case(None, "from_block=0&to_block=6", &[5, 6], None ; "Range before the first header")]
+	#[test_case(None, "from_block=0&to_block=4", &[], None ; "Range of unavailable blocks")]
+	#[test_case(Some(3), "from_block=0&to_block=6", &[], Some(3) ; "Range of unsynced blocks")]
+	#[tokio::test]
+	async fn app_data_route_before_first_header(
+		sync_start_block: Option<u32>,
+		query: &str,
+		block_numbers: &[u32],
+		next_block: Option<u32>,
+	) {
+		let config = RuntimeConfig {
+			app_id: Some(1),
+			sync_start_block,
+			..Default::default()
+		};
+		let route = super::app_data_route(config, app_data_db());
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/apps/1/data?{query}"))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+
+		let response: AppDataResponse = serde_json::from_slice(response.body()).unwrap();
+		let blocks = response
+			.blocks
+			.iter()
+			.map(|block| block.block_number)
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, block_numbers);
+		assert_eq!(response.next_block, next_block);
+	}
+
+	#[test_case("base64", r#""dGVzdAo=""# ; "Base64 format")]
+	#[test_case("json", "null" ; "JSON format with invalid JSON")]
+	#[test_case("raw", r#""vQGEANQ1k8cV/dMcYRQavQSpn9aCLIVYhUzN45pWhOelbaJ9ATIMK7ATKhdJRt/GtGciPPa4MYxxrurlX0cSXJ65qIx+DL+cMuoIBESJBZxe0QepaT4/AXr9w3Ct7xVJo/BqbYMABAAEHQEUdGVzdAo=""# ; "Raw format")]
+	#[tokio::test]
+	async fn app_data_route_format(format: &str, expected: &str) {
+		let config = RuntimeConfig {
+			app_id: Some(1),
+			..Default::default()
+		};
+		let route = super::app_data_route(config, app_data_db());
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!(
+				"/v2/apps/1/data?from_block=5&to_block=5&format={format}"
+			))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(
+			response.body(),
+			&format!(
+				r#"{{"app_id":1,"blocks":[{{"block_number":5,"data":[{expected}]}}],"next_block":null}}"#
+			)
+		);
+
+		let route = super::app_data_route(RuntimeConfig::default(), app_data_db());
+		let response = warp::test::request()
+			.method("GET")
+			.path("/v2/apps/1/data?from_block=5")
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+
 	fn all_topics() -> HashSet<Topic> {
 		vec![
 			Topic::HeaderVerified,
 			Topic::ConfidenceAchieved,
 			Topic::DataVerified,
+			Topic::Reorg,
 		]
 		.into_iter()
 		.collect()
@@ -661,7 +1168,7 @@ mod tests {
 	#[test_case(r#"{"data":"dHJhbnooNhY3Rpb24:"}"#, b"Request body deserialize error: Invalid byte" ; "Invalid base64 value")]
 	#[tokio::test]
 	async fn submit_route_bad_request(json: &str, message: &[u8]) {
-		let route = super::submit_route(Some(Arc::new(MockSubmitter {})));
+		let route = super::submit_route(Some(Arc::new(MockSubmitter {})), Auth::default());
 		let response = warp::test::request()
 			.method("POST")
 			.path("/v2/submit")
@@ -676,7 +1183,7 @@ mod tests {
 	#[test_case(r#"{"extrinsic":"dHJhbnNhY3Rpb24K"}"# ; "No errors in case of submitted extrinsic")]
 	#[tokio::test]
 	async fn submit_route_extrinsic(body: &str) {
-		let route = super::submit_route(Some(Arc::new(MockSubmitter {})));
+		let route = super::submit_route(Some(Arc::new(MockSubmitter {})), Auth::default());
 		let response = warp::test::request()
 			.method("POST")
 			.path("/v2/submit")
@@ -688,6 +1195,43 @@ mod tests {
 		let _ = serde_json::to_string(&response).unwrap();
 	}
 
+	#[tokio::test]
+	async fn submission_routes() {
+		let submissions = Submissions::new(MemoryDB::default());
+		let route = super::submission_route(
+			Some(Arc::new(MockSubmitter {})),
+			submissions.clone(),
+			Auth::default(),
+		)
+		.or(super::submission_status_route(submissions));
+
+		let response = warp::test::request()
+			.method("POST")
+			.path("/v2/submissions")
+			.body(r#"{"data":"dHJhbnNhY3Rpb24K"}"#)
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		let submission: Submission = serde_json::from_slice(response.body()).unwrap();
+		assert_eq!(submission.status, SubmissionStatus::Submitted);
+
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/submissions/{}", submission.submission_id))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::OK);
+		let tracked: Submission = serde_json::from_slice(response.body()).unwrap();
+		assert_eq!(tracked.submission_id, submission.submission_id);
+
+		let response = warp::test::request()
+			.method("GET")
+			.path(&format!("/v2/submissions/{}", Uuid::new_v4()))
+			.reply(&route)
+			.await;
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	}
+
 	#[tokio::test]
 	async fn subscriptions_route() {
 		let clients = WsClients::default();
@@ -710,6 +1254,7 @@ mod tests {
 		let expected = Subscription {
 			topics: all_topics(),
 			data_fields: all_data_fields(),
+			..Default::default()
 		};
 		assert!(client.subscription == expected);
 	}
@@ -721,6 +1266,14 @@ mod tests {
 
 	impl MockSetup {
 		async fn new(config: RuntimeConfig, submitter: Option<MockSubmitter>) -> Self {
+			Self::with_authorization(config, submitter, None).await
+		}
+
+		async fn with_authorization(
+			config: RuntimeConfig,
+			submitter: Option<MockSubmitter>,
+			authorization: Option<&str>,
+		) -> Self {
 			let client_uuid = uuid::Uuid::new_v4().to_string();
 			let clients = WsClients::default();
 			clients
@@ -734,12 +1287,13 @@ mod tests {
 				config.clone(),
 				submitter.map(Arc::new),
 				db.clone(),
+				Auth::new(&config),
 			);
-			let ws_client = warp::test::ws()
-				.path(&format!("/v2/ws/{client_uuid}"))
-				.handshake(route)
-				.await
-				.expect("handshake");
+			let mut request = warp::test::ws().path(&format!("/v2/ws/{client_uuid}"));
+			if let Some(authorization) = authorization {
+				request = request.header("authorization", authorization);
+			}
+			let ws_client = request.handshake(route).await.expect("handshake");
 
 			MockSetup { ws_client, db }
 		}
@@ -798,7 +1352,7 @@ mod tests {
 
 		let gen_hash = H256::default();
 		let expected = format!(
-			r#"{{"topic":"status","request_id":"363c71fc-90f7-4276-a5b6-bec688bf01e2","message":{{"modes":["light","app","partition"],"app_id":1,"genesis_hash":"{:x?}","network":"{NETWORK}","blocks":{{"latest":30,"available":{{"first":20,"last":29}},"app_data":{{"first":20,"last":29}},"historical_sync":{{"synced":false,"available":{{"first":10,"last":19}},"app_data":{{"first":10,"last":18}}}}}},"partition":"1/10"}}}}"#,
+			r#"{{"topic":"status","request_id":"363c71fc-90f7-4276-a5b6-bec688bf01e2","message":{{"modes":["light","app","partition"],"app_id":1,"genesis_hash":"{:x?}","network":"{NETWORK}","blocks":{{"latest":30,"available":{{"first":20,"last":29}},"head_lag":1,"app_data":{{"first":20,"last":29}},"historical_sync":{{"synced":false,"available":{{"first":10,"last":19}},"app_data":{{"first":10,"last":18}}}}}},"partition":"1/10"}}}}"#,
 			gen_hash
 		);
 
@@ -879,4 +1433,25 @@ mod tests {
 		assert_eq!(response.request_id, expected_request_id);
 		assert_eq!(response.message.index, 0);
 	}
+
+	#[tokio::test]
+	async fn ws_route_submit_read_only() {
+		let config = RuntimeConfig {
+			api_keys: vec![ApiKey {
+				key: "reader".to_string(),
+				role: Role::ReadOnly,
+			}],
+			..Default::default()
+		};
+		let submitter = Some(MockSubmitter {});
+		let mut test =
+			MockSetup::with_authorization(config, submitter, Some("Bearer reader")).await;
+
+		let request = r#"{"type":"submit","request_id":"16b24956-2e01-4ba8-bad5-456c561c87d7","message":{"data":"dHJhbnNhY3Rpb24K"}}"#;
+		let response = test.ws_send_text(request).await;
+
+		let WsError::Error(error) = serde_json::from_str(&response).unwrap();
+		assert_eq!(error.error_code, ErrorCode::BadRequest);
+		assert!(error.message.contains("Submit is not configured"));
+	}
 }
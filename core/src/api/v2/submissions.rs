@@ -0,0 +1,290 @@
+use lru::LruCache;
+use std::{
+	num::NonZeroUsize,
+	sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::{
+	transactions::Submit,
+	types::{Submission, SubmissionStatus, Transaction},
+};
+use crate::{
+	data::Database, sampling::block_confidence, types::BlockVerified, utils::spawn_in_span,
+};
+
+/// Number of the most recent submissions which can be queried
+const TRACKED_SUBMISSIONS: usize = 1024;
+
+/// Submits the transactions in the background, and tracks their inclusion and block confidence
+#[derive(Clone)]
+pub struct Submissions<T: Database> {
+	db: T,
+	submissions: Arc<Mutex<LruCache<Uuid, Submission>>>,
+	sender: broadcast::Sender<Submission>,
+}
+
+impl<T: Database + Clone + Send + Sync + 'static> Submissions<T> {
+	pub fn new(db: T) -> Self {
+		let capacity = NonZeroUsize::new(TRACKED_SUBMISSIONS).expect("Capacity is not zero");
+		let (sender, _) = broadcast::channel(1 << 7);
+		Submissions {
+			db,
+			submissions: Arc::new(Mutex::new(LruCache::new(capacity))),
+			sender,
+		}
+	}
+
+	/// Receiver of the submission status changes
+	pub fn subscribe(&self) -> broadcast::Receiver<Submission> {
+		self.sender.subscribe()
+	}
+
+	fn update(&self, submission: Submission) {
+		debug!(
+			submission_id = %submission.submission_id,
+			status = ?submission.status,
+			"Submission updated"
+		);
+		self.submissions
+			.lock()
+			.expect("Lock is not poisoned")
+			.put(submission.submission_id, submission.clone());
+		// there are no receivers if nobody is subscribed
+		let _ = self.sender.send(submission);
+	}
+
+	/// Sets the confidence of the included submission, if the block is already verified
+	fn verify(&self, mut submission: Submission) -> Submission {
+		let confidence = submission
+			.inclusion
+			.as_ref()
+			.and_then(|inclusion| block_confidence(&self.db, inclusion.block_number));
+
+		if submission.status == SubmissionStatus::Included && confidence.is_some() {
+			submission.status = SubmissionStatus::Verified;
+			submission.confidence = confidence;
+			self.update(submission.clone());
+		}
+		submission
+	}
+
+	/// Submits the transaction in the background, returning the submission to track
+	pub fn submit(
+		&self,
+		submitter: Arc<impl Submit + Send + Sync + 'static>,
+		transaction: Transaction,
+	) -> Submission {
+		let submission = Submission {
+			submission_id: Uuid::new_v4(),
+			status: SubmissionStatus::Submitted,
+			inclusion: None,
+			confidence: None,
+			error: None,
+		};
+		self.update(submission.clone());
+
+		let submissions = self.clone();
+		let mut submitted = submission.clone();
+		spawn_in_span(async move {
+			match submitter.submit(transaction).await {
+				Ok(inclusion) => {
+					submitted.status = SubmissionStatus::Included;
+					submitted.inclusion = Some(inclusion);
+				},
+				Err(error) => {
+					warn!(submission_id = %submitted.submission_id, "Submission failed: {error:#}");
+					submitted.status = SubmissionStatus::Failed;
+					submitted.error = Some(format!("{error:#}"));
+				},
+			}
+			submissions.update(submitted.clone());
+			submissions.verify(submitted);
+		});
+
+		submission
+	}
+
+	/// Returns the submission, if it is still tracked
+	pub fn get(&self, submission_id: &Uuid) -> Option<Submission> {
+		let submission = self
+			.submissions
+			.lock()
+			.expect("Lock is not poisoned")
+			.get(submission_id)
+			.cloned()?;
+		Some(self.verify(submission))
+	}
+
+	/// Updates the submissions included in the verified blocks
+	pub async fn run(self, mut block_receiver: broadcast::Receiver<BlockVerified>) {
+		loop {
+			let block = match block_receiver.recv().await {
+				Ok(block) => block,
+				Err(broadcast::error::RecvError::Lagged(skipped)) => {
+					warn!("Submissions tracking skipped {skipped} verified blocks");
+					continue;
+				},
+				Err(broadcast::error::RecvError::Closed) => return,
+			};
+
+			let included = self
+				.submissions
+				.lock()
+				.expect("Lock is not poisoned")
+				.iter()
+				.filter(|(_, submission)| {
+					submission.status == SubmissionStatus::Included
+						&& submission
+							.inclusion
+							.as_ref()
+							.is_some_and(|inclusion| inclusion.block_number == block.block_num)
+				})
+				.map(|(_, submission)| submission.clone())
+				.collect::<Vec<_>>();
+
+			for mut submission in included {
+				submission.status = SubmissionStatus::Verified;
+				submission.confidence = block.confidence;
+				self.update(submission);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		api::v2::types::{Base64, SubmitResponse},
+		data::{MemoryDB, SamplingResultKey},
+		sampling::SamplingResult,
+	};
+	use async_trait::async_trait;
+	use color_eyre::{eyre::eyre, Result};
+	use sp_core::H256;
+	use std::time::Duration;
+
+	#[derive(Clone)]
+	struct MockSubmitter {
+		fail: bool,
+	}
+
+	#[async_trait]
+	impl Submit for MockSubmitter {
+		async fn submit(&self, _: Transaction) -> Result<SubmitResponse> {
+			if self.fail {
+				return Err(eyre!("Transaction is invalid"));
+			}
+			Ok(SubmitResponse {
+				block_number: 10,
+				block_hash: H256::repeat_byte(1),
+				hash: H256::repeat_byte(2),
+				index: 1,
+			})
+		}
+	}
+
+	async fn updates(
+		receiver: &mut broadcast::Receiver<Submission>,
+		count: usize,
+	) -> Vec<SubmissionStatus> {
+		let mut statuses = vec![];
+		for _ in 0..count {
+			let submission = tokio::time::timeout(Duration::from_secs(1), receiver.recv())
+				.await
+				.unwrap()
+				.unwrap();
+			statuses.push(submission.status);
+		}
+		statuses
+	}
+
+	fn transaction() -> Transaction {
+		Transaction::Data(Base64(b"data".to_vec()))
+	}
+
+	#[tokio::test]
+	async fn submission_verified_after_inclusion() {
+		let submissions = Submissions::new(MemoryDB::default());
+		let mut receiver = submissions.subscribe();
+		let (block_sender, block_receiver) = broadcast::channel(1);
+		tokio::spawn(submissions.clone().run(block_receiver));
+
+		let submitter = Arc::new(MockSubmitter { fail: false });
+		let submission = submissions.submit(submitter, transaction());
+		assert_eq!(submission.status, SubmissionStatus::Submitted);
+		assert_eq!(
+			updates(&mut receiver, 2).await,
+			vec![SubmissionStatus::Submitted, SubmissionStatus::Included]
+		);
+
+		block_sender
+			.send(BlockVerified {
+				header_hash: H256::zero(),
+				block_num: 10,
+				extension: None,
+				confidence: Some(99.9),
+			})
+			.unwrap();
+		assert_eq!(
+			updates(&mut receiver, 1).await,
+			vec![SubmissionStatus::Verified]
+		);
+
+		let submission = submissions.get(&submission.submission_id).unwrap();
+		assert_eq!(submission.status, SubmissionStatus::Verified);
+		assert_eq!(submission.confidence, Some(99.9));
+		assert_eq!(submission.inclusion.unwrap().block_number, 10);
+	}
+
+	#[tokio::test]
+	async fn submission_verified_before_inclusion() {
+		let db = MemoryDB::default();
+		let result = SamplingResult {
+			block_number: 10,
+			required: 10,
+			sampled: 10,
+			verified: 10,
+			rounds: 1,
+			total_cells: 64,
+			withheld_cells: 32,
+		};
+		db.put(SamplingResultKey(10), result.clone());
+		let submissions = Submissions::new(db);
+		let mut receiver = submissions.subscribe();
+
+		let submitter = Arc::new(MockSubmitter { fail: false });
+		let submission = submissions.submit(submitter, transaction());
+		assert_eq!(
+			updates(&mut receiver, 3).await,
+			vec![
+				SubmissionStatus::Submitted,
+				SubmissionStatus::Included,
+				SubmissionStatus::Verified
+			]
+		);
+
+		let submission = submissions.get(&submission.submission_id).unwrap();
+		assert_eq!(submission.confidence, Some(result.confidence()));
+	}
+
+	#[tokio::test]
+	async fn submission_failed() {
+		let submissions = Submissions::new(MemoryDB::default());
+		let mut receiver = submissions.subscribe();
+
+		let submitter = Arc::new(MockSubmitter { fail: true });
+		let submission = submissions.submit(submitter, transaction());
+		assert_eq!(
+			updates(&mut receiver, 2).await,
+			vec![SubmissionStatus::Submitted, SubmissionStatus::Failed]
+		);
+
+		let submission = submissions.get(&submission.submission_id).unwrap();
+		assert_eq!(submission.error, Some("Transaction is invalid".to_string()));
+		assert!(submissions.get(&Uuid::new_v4()).is_none());
+	}
+}
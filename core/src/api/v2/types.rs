@@ -1,3 +1,4 @@
+use avail_core::AppId;
 use avail_subxt::api::runtime_types::{
 	avail_core::{data_lookup::compact::CompactDataLookup, header::extension::HeaderExtension},
 	bounded_collections::bounded_vec::BoundedVec,
@@ -30,8 +31,10 @@ use crate::{
 		LatestSyncKey, RpcNodeKey, VerifiedDataKey, VerifiedHeaderKey, VerifiedSyncDataKey,
 		VerifiedSyncHeaderKey,
 	},
+	ledger::LedgerEntry,
+	light_client::BlockProcessing,
 	network::rpc::Event as RpcEvent,
-	types::{self, block_matrix_partition_format, BlockVerified, RuntimeConfig},
+	types::{self, block_matrix_partition_format, BlockVerified, Reorg, RuntimeConfig},
 	utils::{decode_app_data, OptionalExtension},
 };
 
@@ -82,6 +85,8 @@ pub struct Blocks {
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub available: Option<BlockRange>,
 	#[serde(skip_serializing_if = "Option::is_none")]
+	pub head_lag: Option<u32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
 	pub app_data: Option<BlockRange>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub historical_sync: Option<HistoricalSync>,
@@ -168,6 +173,40 @@ impl Reply for SubmitResponse {
 	}
 }
 
+/// Status of the tracked transaction submission
+///
+/// * `submitted` - transaction is submitted, waiting for the finalized block
+/// * `included` - transaction is included in the finalized block, waiting for the confidence
+/// * `verified` - confidence of the block with the transaction is achieved
+/// * `failed` - transaction is not submitted or included
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SubmissionStatus {
+	Submitted,
+	Included,
+	Verified,
+	Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Submission {
+	pub submission_id: Uuid,
+	pub status: SubmissionStatus,
+	/// Block and the index of the included transaction
+	#[serde(flatten)]
+	pub inclusion: Option<SubmitResponse>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub confidence: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl Reply for Submission {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 impl Status {
 	pub fn new(config: &RuntimeConfig, db: impl Database) -> Self {
 		let historical_sync = db.get(IsSyncedKey).map(|synced| HistoricalSync {
@@ -176,9 +215,16 @@ impl Status {
 			app_data: db.get(VerifiedSyncDataKey).map(From::from),
 		});
 
+		let latest = db.get(LatestHeaderKey).unwrap_or_default();
+		let available: Option<BlockRange> = db.get(AchievedConfidenceKey).map(From::from);
+		let head_lag = available
+			.as_ref()
+			.map(|available| latest.saturating_sub(available.last));
+
 		let blocks = Blocks {
-			latest: db.get(LatestHeaderKey).unwrap_or_default(),
-			available: db.get(AchievedConfidenceKey).map(From::from),
+			latest,
+			available,
+			head_lag,
 			app_data: db.get(VerifiedDataKey).map(From::from),
 			historical_sync,
 		};
@@ -208,7 +254,7 @@ impl From<&RuntimeConfig> for Vec<Mode> {
 	fn from(value: &RuntimeConfig) -> Self {
 		let mut result: Vec<Mode> = vec![];
 		result.push(Mode::Light);
-		if value.app_id.is_some() {
+		if !value.app_ids().is_empty() {
 			result.push(Mode::App);
 		}
 		if value.block_matrix_partition.is_some() {
@@ -230,6 +276,8 @@ pub enum Topic {
 	HeaderVerified,
 	ConfidenceAchieved,
 	DataVerified,
+	Reorg,
+	SubmissionUpdated,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -243,6 +291,30 @@ pub enum DataField {
 pub struct Subscription {
 	pub topics: HashSet<Topic>,
 	pub data_fields: HashSet<DataField>,
+	/// Application IDs of the published data, data of all applications is published if empty
+	#[serde(default)]
+	pub app_ids: HashSet<u32>,
+	/// Minimum confidence of the published confidence messages
+	#[serde(default)]
+	pub min_confidence: Option<f64>,
+}
+
+impl Subscription {
+	fn accepts(&self, message: &PublishMessage) -> bool {
+		match message {
+			PublishMessage::ConfidenceAchieved(ConfidenceMessage { confidence, .. }) => {
+				self.min_confidence.map_or(true, |min| {
+					confidence.is_some_and(|confidence| confidence >= min)
+				})
+			},
+			PublishMessage::DataVerified(DataMessage { app_id, .. }) => {
+				self.app_ids.is_empty() || self.app_ids.contains(app_id)
+			},
+			PublishMessage::HeaderVerified(_)
+			| PublishMessage::Reorg(_)
+			| PublishMessage::SubmissionUpdated(_) => true,
+		}
+	}
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -368,6 +440,77 @@ impl Reply for Block {
 	}
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BlockProcessingStatus {
+	pub block_number: u32,
+	#[serde(flatten)]
+	pub processing: BlockProcessing,
+	pub confidence: Option<f64>,
+	/// Average duration of the DHT GET queries (in milliseconds)
+	pub dht_average_latency: Option<u64>,
+}
+
+impl BlockProcessingStatus {
+	pub fn new(block_number: u32, processing: BlockProcessing) -> Self {
+		let confidence = processing.confidence();
+		let dht_average_latency = processing
+			.dht_stats
+			.average_latency()
+			.map(|latency| latency.as_millis() as u64);
+		Self {
+			block_number,
+			processing,
+			confidence,
+			dht_average_latency,
+		}
+	}
+}
+
+impl Reply for BlockProcessingStatus {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlockRangeQuery {
+	pub from: u32,
+	/// Last block of the range (inclusive)
+	pub to: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BlocksStatusResponse {
+	/// Processed blocks in the range, blocks without the stored outcome are omitted
+	pub blocks: Vec<BlockProcessingStatus>,
+}
+
+impl Reply for BlocksStatusResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LedgerQuery {
+	/// Only the entries with lower sequence numbers are returned, defaults to the latest entry
+	pub before: Option<u64>,
+	/// Maximum number of the entries in the response
+	pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LedgerResponse {
+	/// Ledger entries, latest first
+	pub entries: Vec<LedgerEntry>,
+}
+
+impl Reply for LedgerResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 impl TryFrom<avail_subxt::primitives::Header> for HeaderMessage {
 	type Error = Report;
 
@@ -513,6 +656,35 @@ impl TryFrom<BlockVerified> for PublishMessage {
 	}
 }
 
+impl TryFrom<Submission> for PublishMessage {
+	type Error = Report;
+
+	fn try_from(value: Submission) -> Result<Self, Self::Error> {
+		Ok(PublishMessage::SubmissionUpdated(value))
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReorgMessage {
+	block_number: u32,
+	orphaned_hash: H256,
+	block_hash: H256,
+	orphaned_blocks: Vec<u32>,
+}
+
+impl TryFrom<Reorg> for PublishMessage {
+	type Error = Report;
+
+	fn try_from(value: Reorg) -> Result<Self, Self::Error> {
+		Ok(PublishMessage::Reorg(ReorgMessage {
+			block_number: value.block_number,
+			orphaned_hash: value.orphaned_hash,
+			block_hash: value.block_hash,
+			orphaned_blocks: value.orphaned_blocks,
+		}))
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(try_from = "String")]
 pub struct FieldsQueryParameter(pub HashSet<DataField>);
@@ -533,6 +705,8 @@ impl TryFrom<String> for FieldsQueryParameter {
 #[derive(Serialize, Deserialize)]
 pub struct DataQuery {
 	pub fields: Option<FieldsQueryParameter>,
+	/// Application ID of the data, defaults to the configured `app_id`
+	pub app_id: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -547,9 +721,79 @@ impl Reply for DataResponse {
 	}
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AppDataFormat {
+	/// Encoded extrinsic, in base64 format
+	Raw,
+	/// Submitted data, in base64 format
+	#[default]
+	Base64,
+	/// Submitted data, parsed as JSON (`null` if data is not valid JSON)
+	Json,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AppDataQuery {
+	pub from_block: u32,
+	/// Last block of the range (inclusive), defaults to the maximum range
+	pub to_block: Option<u32>,
+	/// Maximum number of the data transactions in the response, complete blocks are always returned
+	pub limit: Option<usize>,
+	#[serde(default)]
+	pub format: AppDataFormat,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum AppData {
+	Base64(Base64),
+	Json(serde_json::Value),
+}
+
+impl AppData {
+	/// Encodes the extrinsic in the requested format, returns `None` if extrinsic doesn't contain data
+	pub fn new(extrinsic: Vec<u8>, format: AppDataFormat) -> Result<Option<Self>> {
+		if format == AppDataFormat::Raw {
+			return Ok(Some(AppData::Base64(Base64(extrinsic))));
+		}
+		let Some(data) = decode_app_data(&extrinsic)? else {
+			return Ok(None);
+		};
+		Ok(Some(match format {
+			AppDataFormat::Json => {
+				AppData::Json(serde_json::from_slice(&data).unwrap_or(serde_json::Value::Null))
+			},
+			_ => AppData::Base64(Base64(data)),
+		}))
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppDataBlock {
+	pub block_number: u32,
+	pub data: Vec<AppData>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AppDataResponse {
+	pub app_id: u32,
+	/// Blocks with the verified application data, blocks without the data are omitted
+	pub blocks: Vec<AppDataBlock>,
+	/// First block of the next page, if the limit is reached or the block is not processed yet
+	pub next_block: Option<u32>,
+}
+
+impl Reply for AppDataResponse {
+	fn into_response(self) -> warp::reply::Response {
+		warp::reply::json(&self).into_response()
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DataMessage {
 	block_number: u32,
+	app_id: u32,
 	data_transactions: Vec<DataTransaction>,
 }
 
@@ -585,16 +829,19 @@ pub fn filter_fields(data_transactions: &mut [DataTransaction], fields: &HashSet
 	}
 }
 
-impl TryFrom<(u32, AppData)> for PublishMessage {
+impl TryFrom<(u32, AppId, AppData)> for PublishMessage {
 	type Error = Report;
 
-	fn try_from((block_number, app_data): (u32, AppData)) -> Result<Self, Self::Error> {
+	fn try_from(
+		(block_number, app_id, app_data): (u32, AppId, AppData),
+	) -> Result<Self, Self::Error> {
 		let data_transactions = app_data
 			.into_iter()
 			.map(TryFrom::try_from)
 			.collect::<Result<Vec<_>>>()?;
 		Ok(PublishMessage::DataVerified(DataMessage {
 			block_number,
+			app_id: app_id.0,
 			data_transactions,
 		}))
 	}
@@ -606,6 +853,8 @@ pub enum PublishMessage {
 	HeaderVerified(Box<HeaderMessage>),
 	ConfidenceAchieved(ConfidenceMessage),
 	DataVerified(DataMessage),
+	Reorg(ReorgMessage),
+	SubmissionUpdated(Submission),
 }
 
 impl PublishMessage {
@@ -616,6 +865,8 @@ impl PublishMessage {
 			PublishMessage::DataVerified(data) => {
 				filter_fields(&mut data.data_transactions, fields)
 			},
+			PublishMessage::Reorg(_) => (),
+			PublishMessage::SubmissionUpdated(_) => (),
 		}
 	}
 }
@@ -644,8 +895,8 @@ impl WsClient {
 		}
 	}
 
-	fn is_subscribed(&self, topic: &Topic) -> bool {
-		self.subscription.topics.contains(topic)
+	fn is_subscribed(&self, topic: &Topic, message: &PublishMessage) -> bool {
+		self.subscription.topics.contains(topic) && self.subscription.accepts(message)
 	}
 
 	fn sender_with_data_fields(&self) -> Option<(&Sender, &HashSet<DataField>)> {
@@ -681,7 +932,7 @@ impl WsClients {
 		let clients = self.0.read().await;
 		Ok(clients
 			.iter()
-			.filter(|(_, client)| client.is_subscribed(topic))
+			.filter(|(_, client)| client.is_subscribed(topic, &message))
 			.flat_map(|(_, client)| client.sender_with_data_fields())
 			.map(|(sender, data_fields)| {
 				let mut message = message.clone();
@@ -873,6 +1124,7 @@ mod tests {
 		Subscription {
 			topics: topics.into_iter().collect(),
 			data_fields: fields.into_iter().collect(),
+			..Default::default()
 		}
 	}
 
@@ -913,6 +1165,7 @@ mod tests {
 	fn data_verified() -> PublishMessage {
 		PublishMessage::DataVerified(DataMessage {
 			block_number: 1,
+			app_id: 1,
 			data_transactions: vec![DataTransaction {
 				data: transaction_data(),
 				extrinsic: transaction_data(),
@@ -986,6 +1239,60 @@ mod tests {
 		};
 	}
 
+	#[tokio::test]
+	async fn clients_publish_filtered() {
+		let clients = WsClients::default();
+		let subscription = Subscription {
+			app_ids: [2].into(),
+			min_confidence: Some(99.0),
+			..subscription(
+				vec![Topic::ConfidenceAchieved, Topic::DataVerified],
+				vec![DataField::Data],
+			)
+		};
+		let (sender, mut receiver) = mpsc::unbounded_channel();
+		clients.subscribe("1", subscription).await;
+		clients.set_sender("1", sender).await.unwrap();
+
+		let confidence = |confidence| {
+			PublishMessage::ConfidenceAchieved(ConfidenceMessage {
+				block_number: 1,
+				confidence,
+			})
+		};
+		let data_verified_2 = PublishMessage::DataVerified(DataMessage {
+			block_number: 2,
+			app_id: 2,
+			data_transactions: vec![],
+		});
+
+		for (topic, message) in [
+			(Topic::ConfidenceAchieved, confidence(None)),
+			(Topic::ConfidenceAchieved, confidence(Some(90.0))),
+			(Topic::ConfidenceAchieved, confidence(Some(99.5))),
+			(Topic::DataVerified, data_verified()),
+			(Topic::DataVerified, data_verified_2),
+		] {
+			clients.publish(&topic, message).await.unwrap();
+		}
+
+		let mut received: Vec<PublishMessage> = vec![];
+		while let Ok(message) = receiver.try_recv() {
+			received.push(serde_json::from_slice(message.unwrap().as_bytes()).unwrap());
+		}
+
+		assert!(matches!(
+			received[..],
+			[
+				PublishMessage::ConfidenceAchieved(ConfidenceMessage {
+					confidence: Some(confidence),
+					..
+				}),
+				PublishMessage::DataVerified(DataMessage { app_id: 2, .. }),
+			] if confidence == 99.5
+		));
+	}
+
 	struct ExtensionNone;
 
 	impl OptionalExtension for ExtensionNone {
@@ -1,18 +1,19 @@
 //! Application client for data fetching and reconstruction.
 //!
-//! App client is enabled when app_id is configured and greater than 0 in avail-light configuration. [`Light client`](super::light_client) triggers application client if block is verified with high enough confidence. Currently [`run`] function is separate task and doesn't block main thread.
+//! App client is enabled when app_id or app_ids are configured in avail-light configuration. [`Light client`](super::light_client) triggers application client if block is verified with high enough confidence. Currently [`run`] function is separate task and doesn't block main thread.
 //!
 //! # Flow
 //!
 //! Get app data rows from node
 //! Verify commitment equality for each row
-//! Decode app data and store it into local database under the `app_id:block_number` key
+//! Reconstruct missing rows from the columns fetched from DHT, if less than half of each column cells is missing
+//! Decode app data of each followed application and store it into local database under the `app_id:block_number` key
 //!
 //! # Notes
 //!
 //! If application client fails to run or stops its execution, error is logged, and other tasks continue with execution.
 use async_trait::async_trait;
-use avail_core::AppId;
+use avail_core::{AppId, DataLookup};
 use avail_subxt::utils::H256;
 use color_eyre::{
 	eyre::{eyre, WrapErr},
@@ -20,17 +21,14 @@ use color_eyre::{
 };
 use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
 use kate_recovery::{
-	com::{
-		app_specific_rows, columns_positions, decode_app_extrinsics, reconstruct_columns, AppData,
-		Percent,
-	},
+	com::{app_specific_rows, decode_app_extrinsics, reconstruct_columns, AppData},
 	commitments,
 	config::{self, CHUNK_SIZE},
 	data::{Cell, DataCell},
 	matrix::{Dimensions, Position},
 };
 use mockall::automock;
-use rand::SeedableRng as _;
+use rand::{seq::IteratorRandom, Rng, SeedableRng as _};
 use rand_chacha::ChaChaRng;
 use std::{
 	collections::{HashMap, HashSet},
@@ -78,7 +76,7 @@ trait Client {
 #[derive(Clone)]
 struct AppClient<T: Database> {
 	p2p_client: P2pClient,
-	rpc_client: RpcClient<T>,
+	rpc_client: Option<RpcClient<T>>,
 }
 
 #[async_trait]
@@ -119,20 +117,32 @@ impl<T: Database + Sync> Client for AppClient<T> {
 		);
 
 		let mut rng = ChaChaRng::from_seed(Default::default());
-		let missing_cells =
-			columns_positions(dimensions, &unfetched, Percent::from_percent(66), &mut rng);
+		let mut columns = ColumnCells::new(dimensions, &fetched, &unfetched);
 
-		let (missing_fetched, _) = fetch_verified(
-			pp,
-			&self.p2p_client,
-			block_number,
-			dimensions,
-			commitments,
-			&missing_cells,
-		)
-		.await?;
+		loop {
+			let positions = columns.missing_positions(&mut rng)?;
+			if positions.is_empty() {
+				break;
+			}
 
-		let reconstructed = reconstruct_columns(dimensions, &missing_fetched)?;
+			debug!(
+				block_number,
+				"Fetching {} column cells from DHT",
+				positions.len()
+			);
+			let (column_fetched, _) = fetch_verified(
+				pp.clone(),
+				&self.p2p_client,
+				block_number,
+				dimensions,
+				commitments,
+				&positions,
+			)
+			.await?;
+			columns.insert(&positions, column_fetched);
+		}
+
+		let reconstructed = reconstruct_columns(dimensions, &columns.cells())?;
 
 		debug!(
 			block_number,
@@ -195,10 +205,13 @@ impl<T: Database + Sync> Client for AppClient<T> {
 		dimensions: Dimensions,
 		block_hash: H256,
 	) -> Result<Vec<Option<Vec<u8>>>> {
+		let Some(rpc_client) = &self.rpc_client else {
+			return Err(eyre!("RPC client is not available in the DHT-only mode"));
+		};
 		let rows = rows
 			.clone()
 			.into_iter()
-			.zip(self.rpc_client.request_kate_rows(rows, block_hash).await?);
+			.zip(rpc_client.request_kate_rows(rows, block_hash).await?);
 		let mut result = vec![None; dimensions.extended_rows() as usize];
 		for (i, row) in rows {
 			result[i as usize] = Some(row);
@@ -207,6 +220,90 @@ impl<T: Database + Sync> Client for AppClient<T> {
 	}
 }
 
+/// Cells of the columns which need to be reconstructed, with the rows which were already fetched
+struct ColumnCells {
+	dimensions: Dimensions,
+	cells: HashMap<u16, Vec<Cell>>,
+	fetched_rows: HashMap<u16, HashSet<u32>>,
+}
+
+impl ColumnCells {
+	/// Columns of the unfetched positions, with the already fetched cells of those columns
+	fn new(dimensions: Dimensions, fetched: &[Cell], unfetched: &[Position]) -> Self {
+		let mut columns = ColumnCells {
+			dimensions,
+			cells: HashMap::new(),
+			fetched_rows: HashMap::new(),
+		};
+		for position in unfetched {
+			columns.cells.entry(position.col).or_default();
+			columns
+				.fetched_rows
+				.entry(position.col)
+				.or_default()
+				.insert(position.row);
+		}
+		for cell in fetched {
+			let Some(cells) = columns.cells.get_mut(&cell.position.col) else {
+				continue;
+			};
+			cells.push(cell.clone());
+			columns
+				.fetched_rows
+				.entry(cell.position.col)
+				.or_default()
+				.insert(cell.position.row);
+		}
+		columns
+	}
+
+	/// Random positions which are not fetched yet, and are needed to reconstruct the columns.
+	/// Column is reconstructed from any half of its extended cells, so it fails if more than half of the column cells is missing.
+	fn missing_positions(&self, rng: &mut impl Rng) -> Result<Vec<Position>> {
+		let extended_rows = self.dimensions.extended_rows();
+		let required = extended_rows as usize / config::EXTENSION_FACTOR;
+
+		let mut columns = self.cells.keys().collect::<Vec<_>>();
+		columns.sort();
+
+		let mut positions = vec![];
+		for &col in columns {
+			let available = self.cells[&col].len();
+			let needed = required.saturating_sub(available);
+			if needed == 0 {
+				continue;
+			}
+			let rows = (0..extended_rows)
+				.filter(|row| !self.fetched_rows[&col].contains(row))
+				.choose_multiple(rng, needed);
+			if rows.len() < needed {
+				return Err(eyre!(
+					"Column {col} cannot be reconstructed, {} of {required} required cells can be available",
+					available + rows.len()
+				));
+			}
+			positions.extend(rows.into_iter().map(|row| Position { row, col }));
+		}
+		Ok(positions)
+	}
+
+	fn insert(&mut self, positions: &[Position], fetched: Vec<Cell>) {
+		for position in positions {
+			self.fetched_rows
+				.entry(position.col)
+				.or_default()
+				.insert(position.row);
+		}
+		for cell in fetched {
+			self.cells.entry(cell.position.col).or_default().push(cell);
+		}
+	}
+
+	fn cells(&self) -> Vec<Cell> {
+		self.cells.values().flatten().cloned().collect()
+	}
+}
+
 fn new_data_cell(row: usize, col: usize, data: &[u8]) -> Result<DataCell> {
 	Ok(DataCell {
 		position: Position {
@@ -237,6 +334,64 @@ fn data_cells_from_rows(rows: Vec<Option<Vec<u8>>>) -> Result<Vec<DataCell>> {
 		.collect::<Vec<_>>())
 }
 
+/// Verifies the rows of all applications, returns verified and missing application rows
+fn verify_rows(
+	pp: &PublicParameters,
+	commitments: &[[u8; config::COMMITMENT_SIZE]],
+	rows: &[Option<Vec<u8>>],
+	lookup: &DataLookup,
+	dimensions: Dimensions,
+	app_ids: &[AppId],
+) -> Result<(Vec<u32>, Vec<u32>)> {
+	let mut verified = HashSet::new();
+	let mut missing = HashSet::new();
+	for &app_id in app_ids {
+		let (app_verified, app_missing) =
+			commitments::verify_equality(pp, commitments, rows, lookup, dimensions, app_id)?;
+		verified.extend(app_verified);
+		missing.extend(app_missing);
+	}
+	let mut missing = missing.difference(&verified).cloned().collect::<Vec<_>>();
+	let mut verified = verified.into_iter().collect::<Vec<_>>();
+	missing.sort();
+	verified.sort();
+	Ok((verified, missing))
+}
+
+/// Decodes the data availability extrinsics of each application from the verified rows,
+/// and stores decoded data into database, indexed by application ID and block number
+fn decode_app_data(
+	db: &impl Database,
+	block_number: u32,
+	lookup: &DataLookup,
+	dimensions: Dimensions,
+	rows: Vec<Option<Vec<u8>>>,
+	app_ids: &[AppId],
+) -> Result<Vec<(AppId, AppData)>> {
+	let data_cells = data_cells_from_rows(rows)
+		.wrap_err("Failed to create data cells from rows got from RPC")?;
+
+	app_ids
+		.iter()
+		.map(|&app_id| {
+			let data = decode_app_extrinsics(lookup, dimensions, data_cells.clone(), app_id)
+				.wrap_err("Failed to decode app extrinsics")?;
+
+			debug!(block_number, "Storing data of app {app_id} into database");
+
+			// store encoded App Data into the database
+			db.put(AppDataKey(app_id.0, block_number), data.clone());
+
+			let bytes_count = data.iter().fold(0usize, |acc, x| acc + x.len());
+			debug!(
+				block_number,
+				"Stored {bytes_count} bytes of app {app_id} into database"
+			);
+			Ok((app_id, data))
+		})
+		.collect()
+}
+
 fn data_cell(
 	position: Position,
 	reconstructed: &HashMap<u16, Vec<[u8; config::CHUNK_SIZE]>>,
@@ -258,8 +413,8 @@ async fn fetch_verified(
 	commitments: &[[u8; config::COMMITMENT_SIZE]],
 	positions: &[Position],
 ) -> Result<(Vec<Cell>, Vec<Position>)> {
-	let (mut fetched, mut unfetched) = p2p_client
-		.fetch_cells_from_dht(block_number, positions)
+	let (mut fetched, mut unfetched, _) = p2p_client
+		.fetch_cells_from_dht(block_number, positions, None)
 		.await;
 
 	let (verified, mut unverified) =
@@ -269,6 +424,7 @@ async fn fetch_verified(
 
 	fetched.retain(|cell| verified.contains(&cell.position));
 	unfetched.append(&mut unverified);
+	p2p_client.cache_cells(block_number, &fetched);
 
 	Ok((fetched, unfetched))
 }
@@ -278,10 +434,10 @@ async fn process_block(
 	client: impl Client,
 	db: impl Database,
 	cfg: &AppClientConfig,
-	app_id: AppId,
+	app_ids: &[AppId],
 	block: &BlockVerified,
 	pp: Arc<PublicParameters>,
-) -> Result<AppData> {
+) -> Result<Vec<(AppId, AppData)>> {
 	let Some(extension) = &block.extension else {
 		return Err(eyre!("Missing header extension"));
 	};
@@ -290,7 +446,12 @@ async fn process_block(
 	let dimensions = extension.dimensions;
 	let commitments = &extension.commitments;
 
-	let app_rows = app_specific_rows(lookup, dimensions, app_id);
+	let mut app_rows = app_ids
+		.iter()
+		.flat_map(|&app_id| app_specific_rows(lookup, dimensions, app_id))
+		.collect::<Vec<_>>();
+	app_rows.sort();
+	app_rows.dedup();
 
 	debug!(
 		block_number,
@@ -306,7 +467,7 @@ async fn process_block(
 	debug!(block_number, "Fetched {dht_rows_count} app rows from DHT");
 
 	let (dht_verified_rows, dht_missing_rows) =
-		commitments::verify_equality(&pp, commitments, &dht_rows, lookup, dimensions, app_id)?;
+		verify_rows(&pp, commitments, &dht_rows, lookup, dimensions, app_ids)?;
 	debug!(
 		block_number,
 		"Verified {} app rows from DHT, missing {}",
@@ -327,7 +488,7 @@ async fn process_block(
 	};
 
 	let (rpc_verified_rows, mut missing_rows) =
-		commitments::verify_equality(&pp, commitments, &rpc_rows, lookup, dimensions, app_id)?;
+		verify_rows(&pp, commitments, &rpc_rows, lookup, dimensions, app_ids)?;
 	// Since verify_equality returns all missing rows, exclude DHT rows that are already verified
 	missing_rows.retain(|row| !dht_verified_rows.contains(row));
 
@@ -386,21 +547,7 @@ async fn process_block(
 		rows[i] = Some(row);
 	}
 
-	let data_cells = data_cells_from_rows(rows)
-		.wrap_err("Failed to create data cells from rows got from RPC")?;
-
-	let data = decode_app_extrinsics(lookup, dimensions, data_cells, app_id)
-		.wrap_err("Failed to decode app extrinsics")?;
-
-	debug!(block_number, "Storing data into database");
-
-	// store encoded App Data into the database
-	db.put(AppDataKey(app_id.0, block_number), data.clone());
-
-	let bytes_count = data.iter().fold(0usize, |acc, x| acc + x.len());
-	debug!(block_number, "Stored {bytes_count} bytes into database");
-
-	Ok(data)
+	decode_app_data(&db, block_number, lookup, dimensions, rows, app_ids)
 }
 
 /// Runs application client.
@@ -410,8 +557,8 @@ async fn process_block(
 /// * `cfg` - Application client configuration
 /// * `db` - Database to store data inot DB
 /// * `network_client` - Reference to a libp2p custom network client
-/// * `rpc_client` - Node's RPC subxt client for fetching data unavailable in DHT (if configured)
-/// * `app_id` - Application ID
+/// * `rpc_client` - Node's RPC subxt client for fetching data unavailable in DHT (if configured, not set in the DHT-only mode)
+/// * `app_ids` - Followed application IDs
 /// * `block_receive` - Channel used to receive header of verified block
 /// * `pp` - Public parameters (i.e. SRS) needed for proof verification
 #[allow(clippy::too_many_arguments)]
@@ -419,15 +566,15 @@ pub async fn run(
 	cfg: AppClientConfig,
 	db: impl Database + Clone,
 	network_client: P2pClient,
-	rpc_client: RpcClient<impl Database + Clone + Sync>,
-	app_id: AppId,
+	rpc_client: Option<RpcClient<impl Database + Clone + Sync>>,
+	app_ids: Vec<AppId>,
 	mut block_receive: broadcast::Receiver<BlockVerified>,
 	pp: Arc<PublicParameters>,
 	sync_range: Range<u32>,
-	data_verified_sender: broadcast::Sender<(u32, AppData)>,
+	data_verified_sender: broadcast::Sender<(u32, AppId, AppData)>,
 	shutdown: Controller<String>,
 ) {
-	info!("Starting for app {app_id}...");
+	info!("Starting for apps {app_ids:?}...");
 
 	fn update_range(
 		db: &impl Database,
@@ -475,10 +622,15 @@ pub async fn run(
 
 		info!(block_number, "Block available: {dimensions:?}");
 
-		if extension.lookup.range_of(app_id).is_none() {
+		let block_app_ids = app_ids
+			.iter()
+			.filter(|&&app_id| extension.lookup.range_of(app_id).is_some())
+			.cloned()
+			.collect::<Vec<_>>();
+		if block_app_ids.is_empty() {
 			info!(
 				block_number,
-				"Skipping block with no cells for app {app_id}"
+				"Skipping block with no cells for apps {app_ids:?}"
 			);
 			set_data_verified_state(db.clone(), &sync_range, block_number);
 			continue;
@@ -488,21 +640,31 @@ pub async fn run(
 			p2p_client: network_client.clone(),
 			rpc_client: rpc_client.clone(),
 		};
-		let data =
-			match process_block(app_client, db.clone(), &cfg, app_id, &block, pp.clone()).await {
-				Ok(data) => data,
-				Err(error) => {
-					error!(block_number, "Cannot process block: {error}");
-					let _ = shutdown.trigger_shutdown(format!("Cannot process block: {error:#}"));
-					return;
-				},
-			};
+		let app_data = match process_block(
+			app_client,
+			db.clone(),
+			&cfg,
+			&block_app_ids,
+			&block,
+			pp.clone(),
+		)
+		.await
+		{
+			Ok(app_data) => app_data,
+			Err(error) => {
+				error!(block_number, "Cannot process block: {error}");
+				let _ = shutdown.trigger_shutdown(format!("Cannot process block: {error:#}"));
+				return;
+			},
+		};
 		set_data_verified_state(db.clone(), &sync_range, block_number);
-		if let Err(error) = data_verified_sender.send((block_number, data)) {
-			error!("Cannot send data verified message: {error}");
-			let _ =
-				shutdown.trigger_shutdown(format!("Cannot send data verified message: {error:#}"));
-			return;
+		for (app_id, data) in app_data {
+			if let Err(error) = data_verified_sender.send((block_number, app_id, data)) {
+				error!("Cannot send data verified message: {error}");
+				let _ = shutdown
+					.trigger_shutdown(format!("Cannot send data verified message: {error:#}"));
+				return;
+			}
 		}
 		debug!(block_number, "Block processed");
 	}
@@ -519,6 +681,46 @@ mod tests {
 	use hex_literal::hex;
 	use kate_recovery::{matrix::Dimensions, testnet};
 
+	fn cell(row: u32, col: u16) -> Cell {
+		Cell {
+			position: Position { row, col },
+			content: [0; 80],
+		}
+	}
+
+	#[test]
+	fn test_column_cells_missing_positions() {
+		let dimensions = Dimensions::new(4, 2).unwrap();
+		let mut rng = ChaChaRng::from_seed(Default::default());
+		let unfetched = [Position { row: 0, col: 0 }, Position { row: 2, col: 1 }];
+		let fetched = [cell(0, 1), cell(1, 1), cell(1, 0)];
+		let mut columns = ColumnCells::new(dimensions, &fetched, &unfetched);
+
+		// Half of the 8 extended column cells is needed, fetched cells are not fetched again
+		let positions = columns.missing_positions(&mut rng).unwrap();
+		assert_eq!(positions.iter().filter(|p| p.col == 0).count(), 3);
+		assert_eq!(positions.iter().filter(|p| p.col == 1).count(), 2);
+		assert!(positions
+			.iter()
+			.all(|p| !unfetched.contains(p) && fetched.iter().all(|cell| cell.position != *p)));
+
+		// Column 0 has enough cells after fetch, column 1 has only unfetched cells left
+		let fetched = positions
+			.iter()
+			.filter(|p| p.col == 0)
+			.map(|p| cell(p.row, p.col))
+			.collect();
+		columns.insert(&positions, fetched);
+		assert_eq!(columns.cells().len(), 6);
+		let positions = columns.missing_positions(&mut rng).unwrap();
+		assert_eq!(positions.len(), 2);
+		assert!(positions.iter().all(|p| p.col == 1));
+
+		// More than half of the column cells is missing
+		columns.insert(&positions, vec![]);
+		assert!(columns.missing_positions(&mut rng).is_err());
+	}
+
 	#[tokio::test]
 	async fn test_process_blocks_without_rpc() {
 		let mut cfg = AppClientConfig::from(&RuntimeConfig::default());
@@ -571,9 +773,20 @@ mod tests {
 			.expect_reconstruct_rows_from_dht()
 			.returning(|_, _, _, _, _| Box::pin(async move { Ok(vec![]) }));
 
-		process_block(mock_client, db, &cfg, AppId(1), &block, pp)
-			.await
-			.unwrap();
+		let app_data = process_block(
+			mock_client,
+			db.clone(),
+			&cfg,
+			&[AppId(0), AppId(1)],
+			&block,
+			pp,
+		)
+		.await
+		.unwrap();
+		// Data of both apps in the same row is decoded and stored
+		assert_eq!(app_data.len(), 2);
+		assert!(db.get(AppDataKey(0, 270)).is_some());
+		assert!(db.get(AppDataKey(1, 270)).is_some());
 	}
 
 	#[tokio::test]
@@ -635,7 +848,7 @@ mod tests {
 			.expect_reconstruct_rows_from_dht()
 			.returning(|_, _, _, _, _| Box::pin(async move { Ok(vec![]) }));
 
-		process_block(mock_client, db, &cfg, AppId(1), &block, pp)
+		process_block(mock_client, db, &cfg, &[AppId(1)], &block, pp)
 			.await
 			.unwrap();
 	}
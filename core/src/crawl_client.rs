@@ -40,6 +40,14 @@ pub struct CrawlConfig {
 	/// Fraction and number of the block matrix part to crawl (e.g. 2/20 means second 1/20 part of a matrix) (default: None)
 	#[serde(with = "block_matrix_partition_format")]
 	pub crawl_block_matrix_partition: Option<Partition>,
+	/// Crawl DHT periodically to measure its size and health. (default: false)
+	pub crawl_dht: bool,
+	/// DHT crawl interval in seconds (default: 3600)
+	pub crawl_dht_interval: u64,
+	/// Maximum number of closest peers lookups in a single DHT crawl (default: 256)
+	pub crawl_dht_max_lookups: usize,
+	/// Path of the file in which the crawled DHT topology is dumped as JSON (default: None)
+	pub crawl_dht_topology_path: Option<String>,
 }
 
 impl Default for CrawlConfig {
@@ -49,6 +57,10 @@ impl Default for CrawlConfig {
 			crawl_block_delay: 20,
 			crawl_block_mode: CrawlMode::Cells,
 			crawl_block_matrix_partition: None,
+			crawl_dht: false,
+			crawl_dht_interval: 3600,
+			crawl_dht_max_lookups: 256,
+			crawl_dht_topology_path: None,
 		}
 	}
 }
@@ -109,6 +121,7 @@ pub async fn run(
 	while let Ok(rpc::Event::HeaderUpdate {
 		header,
 		received_at,
+		..
 	}) = message_rx.recv().await
 	{
 		let block = match types::BlockVerified::try_from((header, None)) {
@@ -144,7 +157,7 @@ pub async fn run(
 
 			let total = positions.len();
 			let fetched = network_client
-				.fetch_cells_from_dht(block_number, &positions)
+				.fetch_cells_from_dht(block_number, &positions, None)
 				.await
 				.0
 				.len();
@@ -1,9 +1,12 @@
 use self::rocks_db::RocksDBKey;
 use crate::{
+	ledger::LedgerEntry,
+	light_client::{header_store::StoredHeader, BlockProcessing},
 	network::rpc::Node as RpcNode,
+	sampling::SamplingResult,
 	types::{BlockRange, Uuid},
 };
-use avail_subxt::primitives::Header;
+use avail_subxt::{primitives::Header, utils::H256};
 use codec::{Decode, Encode};
 #[cfg(test)]
 use mem_db::HashMapKey;
@@ -25,6 +28,9 @@ pub const APP_STATE_CF: &str = "app_state_cf";
 /// Column family for Kademlia store
 pub const KADEMLIA_STORE_CF: &str = "kademlia_store_cf";
 
+/// Column family for the ledger of node events
+pub const LEDGER_CF: &str = "ledger_cf";
+
 #[cfg(not(test))]
 /// Type of the database key which we can get from the custom key.
 pub trait RecordKey: Into<RocksDBKey> {
@@ -159,3 +165,69 @@ pub struct P2PKeypairKey;
 impl RecordKey for P2PKeypairKey {
 	type Type = Vec<u8>;
 }
+
+pub struct BlockedPeersKey;
+
+impl RecordKey for BlockedPeersKey {
+	type Type = Vec<String>;
+}
+
+pub struct CanonicalHeaderKey(pub u32);
+
+impl RecordKey for CanonicalHeaderKey {
+	type Type = StoredHeader;
+}
+
+pub struct LatestCanonicalHeaderKey;
+
+impl RecordKey for LatestCanonicalHeaderKey {
+	type Type = u32;
+}
+
+pub struct SamplingResultKey(pub u32);
+
+impl RecordKey for SamplingResultKey {
+	type Type = SamplingResult;
+}
+
+/// Hash of the processed block, by block number
+pub struct BlockHashKey(pub u32);
+
+impl RecordKey for BlockHashKey {
+	type Type = H256;
+}
+
+/// Outcome of the block processing, by block number
+pub struct BlockProcessingKey(pub u32);
+
+impl RecordKey for BlockProcessingKey {
+	type Type = BlockProcessing;
+}
+
+/// Runtime parameters persisted through the admin API, serialized to JSON
+pub struct RuntimeParametersKey;
+
+impl RecordKey for RuntimeParametersKey {
+	type Type = String;
+}
+
+/// Ledger entry, by sequence number
+pub struct LedgerEntryKey(pub u64);
+
+impl RecordKey for LedgerEntryKey {
+	type Type = LedgerEntry;
+}
+
+/// Sequence number of the oldest ledger entry which is not pruned
+pub struct LedgerFirstSequenceKey;
+
+impl RecordKey for LedgerFirstSequenceKey {
+	type Type = u64;
+}
+
+/// Sequence number of the latest ledger entry
+pub struct LedgerLatestSequenceKey;
+
+impl RecordKey for LedgerLatestSequenceKey {
+	type Type = u64;
+}
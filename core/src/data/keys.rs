@@ -33,3 +33,23 @@ pub const IS_SYNCED_KEY: &str = "is_synced";
 pub const CLIENT_ID_KEY: &str = "client_id";
 /// Key for storing P2P keypair
 pub const P2P_KEYPAIR_KEY: &str = "p2p_keypair";
+/// Key for storing blocked P2P peers
+pub const BLOCKED_PEERS_KEY: &str = "blocked_peers";
+/// Prefix used with Canonical Header key
+pub const CANONICAL_HEADER_KEY_PREFIX: &str = "canonical_header";
+/// Key for storing Latest Canonical Header number
+pub const LATEST_CANONICAL_HEADER_KEY: &str = "latest_canonical_header";
+/// Prefix used with Sampling Result key
+pub const SAMPLING_RESULT_KEY_PREFIX: &str = "sampling_result";
+/// Prefix used with Block Hash key
+pub const BLOCK_HASH_KEY_PREFIX: &str = "block_hash";
+/// Prefix used with Block Processing key
+pub const BLOCK_PROCESSING_KEY_PREFIX: &str = "block_processing";
+/// Key for storing runtime parameters persisted through the admin API
+pub const RUNTIME_PARAMETERS_KEY: &str = "runtime_parameters";
+/// Prefix used with Ledger Entry key
+pub const LEDGER_ENTRY_KEY_PREFIX: &str = "ledger_entry";
+/// Key for storing the sequence number of the oldest ledger entry
+pub const LEDGER_FIRST_SEQUENCE_KEY: &str = "ledger_first_sequence";
+/// Key for storing the sequence number of the latest ledger entry
+pub const LEDGER_LATEST_SEQUENCE_KEY: &str = "ledger_latest_sequence";
@@ -153,3 +153,76 @@ impl From<P2PKeypairKey> for HashMapKey {
 		HashMapKey(P2P_KEYPAIR_KEY.to_string())
 	}
 }
+
+impl From<BlockedPeersKey> for HashMapKey {
+	fn from(_: BlockedPeersKey) -> Self {
+		HashMapKey(BLOCKED_PEERS_KEY.to_string())
+	}
+}
+
+impl From<CanonicalHeaderKey> for HashMapKey {
+	fn from(value: CanonicalHeaderKey) -> Self {
+		let CanonicalHeaderKey(block_num) = value;
+		HashMapKey(format!(
+			"{APP_STATE_CF}:{CANONICAL_HEADER_KEY_PREFIX}:{block_num}"
+		))
+	}
+}
+
+impl From<LatestCanonicalHeaderKey> for HashMapKey {
+	fn from(_: LatestCanonicalHeaderKey) -> Self {
+		HashMapKey(format!("{APP_STATE_CF}:{LATEST_CANONICAL_HEADER_KEY}"))
+	}
+}
+
+impl From<SamplingResultKey> for HashMapKey {
+	fn from(value: SamplingResultKey) -> Self {
+		let SamplingResultKey(block_num) = value;
+		HashMapKey(format!(
+			"{APP_STATE_CF}:{SAMPLING_RESULT_KEY_PREFIX}:{block_num}"
+		))
+	}
+}
+
+impl From<BlockProcessingKey> for HashMapKey {
+	fn from(value: BlockProcessingKey) -> Self {
+		let BlockProcessingKey(block_num) = value;
+		HashMapKey(format!(
+			"{APP_STATE_CF}:{BLOCK_PROCESSING_KEY_PREFIX}:{block_num}"
+		))
+	}
+}
+
+impl From<RuntimeParametersKey> for HashMapKey {
+	fn from(_: RuntimeParametersKey) -> Self {
+		HashMapKey(RUNTIME_PARAMETERS_KEY.to_string())
+	}
+}
+
+impl From<BlockHashKey> for HashMapKey {
+	fn from(value: BlockHashKey) -> Self {
+		let BlockHashKey(block_num) = value;
+		HashMapKey(format!(
+			"{APP_STATE_CF}:{BLOCK_HASH_KEY_PREFIX}:{block_num}"
+		))
+	}
+}
+
+impl From<LedgerEntryKey> for HashMapKey {
+	fn from(value: LedgerEntryKey) -> Self {
+		let LedgerEntryKey(sequence) = value;
+		HashMapKey(format!("{LEDGER_CF}:{LEDGER_ENTRY_KEY_PREFIX}:{sequence}"))
+	}
+}
+
+impl From<LedgerFirstSequenceKey> for HashMapKey {
+	fn from(_: LedgerFirstSequenceKey) -> Self {
+		HashMapKey(LEDGER_FIRST_SEQUENCE_KEY.to_string())
+	}
+}
+
+impl From<LedgerLatestSequenceKey> for HashMapKey {
+	fn from(_: LedgerLatestSequenceKey) -> Self {
+		HashMapKey(LEDGER_LATEST_SEQUENCE_KEY.to_string())
+	}
+}
@@ -1,6 +1,6 @@
 use super::{keys::*, *};
 use crate::{
-	data::{self, APP_STATE_CF, KADEMLIA_STORE_CF},
+	data::{self, APP_STATE_CF, KADEMLIA_STORE_CF, LEDGER_CF},
 	network::p2p::ExpirationCompactionFilterFactory,
 };
 use codec::{Decode, Encode};
@@ -23,13 +23,16 @@ impl RocksDBKey {
 }
 
 impl RocksDB {
-	pub fn open(path: &str) -> Result<RocksDB> {
+	/// Opens the database, Kademlia records are compacted based on the given cell and row TTLs (in seconds)
+	pub fn open(path: &str, kad_cell_ttl: u64, kad_row_ttl: u64) -> Result<RocksDB> {
 		let mut kademlia_store_cf_opts = Options::default();
-		kademlia_store_cf_opts
-			.set_compaction_filter_factory(ExpirationCompactionFilterFactory::default());
+		kademlia_store_cf_opts.set_compaction_filter_factory(
+			ExpirationCompactionFilterFactory::new(kad_cell_ttl, kad_row_ttl),
+		);
 		let cf_opts = vec![
 			ColumnFamilyDescriptor::new(APP_STATE_CF, Options::default()),
 			ColumnFamilyDescriptor::new(KADEMLIA_STORE_CF, kademlia_store_cf_opts),
+			ColumnFamilyDescriptor::new(LEDGER_CF, Options::default()),
 		];
 
 		let mut db_opts = Options::default();
@@ -216,3 +219,69 @@ impl From<P2PKeypairKey> for RocksDBKey {
 		RocksDBKey::app_state(P2P_KEYPAIR_KEY)
 	}
 }
+
+impl From<BlockedPeersKey> for RocksDBKey {
+	fn from(_: BlockedPeersKey) -> Self {
+		RocksDBKey::app_state(BLOCKED_PEERS_KEY)
+	}
+}
+
+impl From<CanonicalHeaderKey> for RocksDBKey {
+	fn from(value: CanonicalHeaderKey) -> Self {
+		let CanonicalHeaderKey(block_num) = value;
+		RocksDBKey::app_state(&format!("{CANONICAL_HEADER_KEY_PREFIX}:{block_num}"))
+	}
+}
+
+impl From<LatestCanonicalHeaderKey> for RocksDBKey {
+	fn from(_: LatestCanonicalHeaderKey) -> Self {
+		RocksDBKey::app_state(LATEST_CANONICAL_HEADER_KEY)
+	}
+}
+
+impl From<SamplingResultKey> for RocksDBKey {
+	fn from(value: SamplingResultKey) -> Self {
+		let SamplingResultKey(block_num) = value;
+		RocksDBKey::app_state(&format!("{SAMPLING_RESULT_KEY_PREFIX}:{block_num}"))
+	}
+}
+
+impl From<BlockProcessingKey> for RocksDBKey {
+	fn from(value: BlockProcessingKey) -> Self {
+		let BlockProcessingKey(block_num) = value;
+		RocksDBKey::app_state(&format!("{BLOCK_PROCESSING_KEY_PREFIX}:{block_num}"))
+	}
+}
+
+impl From<RuntimeParametersKey> for RocksDBKey {
+	fn from(_: RuntimeParametersKey) -> Self {
+		RocksDBKey::app_state(RUNTIME_PARAMETERS_KEY)
+	}
+}
+
+impl From<BlockHashKey> for RocksDBKey {
+	fn from(value: BlockHashKey) -> Self {
+		let BlockHashKey(block_num) = value;
+		RocksDBKey::app_state(&format!("{BLOCK_HASH_KEY_PREFIX}:{block_num}"))
+	}
+}
+
+impl From<LedgerEntryKey> for RocksDBKey {
+	fn from(value: LedgerEntryKey) -> Self {
+		let LedgerEntryKey(sequence) = value;
+		// big-endian sequence numbers keep the entries ordered in the column family
+		RocksDBKey(Some(LEDGER_CF), sequence.to_be_bytes().to_vec())
+	}
+}
+
+impl From<LedgerFirstSequenceKey> for RocksDBKey {
+	fn from(_: LedgerFirstSequenceKey) -> Self {
+		RocksDBKey::app_state(LEDGER_FIRST_SEQUENCE_KEY)
+	}
+}
+
+impl From<LedgerLatestSequenceKey> for RocksDBKey {
+	fn from(_: LedgerLatestSequenceKey) -> Self {
+		RocksDBKey::app_state(LEDGER_LATEST_SEQUENCE_KEY)
+	}
+}
@@ -26,20 +26,22 @@ use tracing::{debug, error, info, warn};
 
 use crate::{
 	data::{BlockHeaderKey, Database},
+	light_client::header_store::HeaderStore,
 	network::{
 		p2p::Client as P2pClient,
 		rpc::{Client as RpcClient, Event},
 	},
+	proof,
 	shutdown::Controller,
 	telemetry::{MetricCounter, MetricValue, Metrics},
 	types::{BlockVerified, ClientChannels, FatClientConfig},
-	utils::extract_kate,
 };
 
 #[async_trait]
 #[automock]
 pub trait Client {
 	async fn insert_cells_into_dht(&self, block: u32, cells: Vec<Cell>) -> Result<()>;
+	async fn provide_cells(&self, block: u32, cells: Vec<Cell>) -> Result<()>;
 	async fn insert_rows_into_dht(&self, block: u32, rows: Vec<(RowIndex, Vec<u8>)>) -> Result<()>;
 	async fn get_kate_proof(&self, hash: H256, positions: &[Position]) -> Result<Vec<Cell>>;
 }
@@ -63,7 +65,13 @@ pub fn new(
 #[async_trait]
 impl<T: Database + Sync> Client for FatClient<T> {
 	async fn insert_cells_into_dht(&self, block: u32, cells: Vec<Cell>) -> Result<()> {
-		self.p2p_client.insert_cells_into_dht(block, cells).await
+		self.p2p_client
+			.insert_cells_into_dht(block, cells, None)
+			.await
+	}
+
+	async fn provide_cells(&self, block: u32, cells: Vec<Cell>) -> Result<()> {
+		self.p2p_client.provide_cells(block, cells).await
 	}
 
 	async fn insert_rows_into_dht(&self, block: u32, rows: Vec<(RowIndex, Vec<u8>)>) -> Result<()> {
@@ -94,17 +102,21 @@ pub async fn process_block(
 	let block_delay = received_at.elapsed().as_secs();
 	info!(block_number, block_delay, "Processing finalized block",);
 
-	let Some((rows, cols, _, _)) = extract_kate(&header.extension) else {
-		info!(block_number, "Skipping block without header extension");
-		return Ok(());
-	};
-	let Some(dimensions) = Dimensions::new(rows, cols) else {
-		info!(
-			block_number,
-			"Skipping block with invalid dimensions {rows}x{cols}",
-		);
-		return Ok(());
+	let block_commitments = match proof::block_commitments(header_hash, &header.extension) {
+		Ok(Some(block_commitments)) => block_commitments,
+		Ok(None) => {
+			info!(block_number, "Skipping block without header extension");
+			return Ok(());
+		},
+		Err(error) => {
+			info!(
+				block_number,
+				"Skipping block with invalid commitments: {error:#}"
+			);
+			return Ok(());
+		},
 	};
+	let dimensions = block_commitments.dimensions;
 
 	if dimensions.cols().get() <= 2 {
 		error!(block_number, "More than 2 columns are required");
@@ -147,10 +159,16 @@ pub async fn process_block(
 			let batch_rpc_fetched =
 				result.wrap_err(format!("Failed to fetch cells from node RPC at batch {i}"))?;
 
-			if let Err(e) = client
-				.insert_cells_into_dht(block_number, batch_rpc_fetched.clone())
-				.await
-			{
+			let inserted = if cfg.dht_provider_mode {
+				client
+					.provide_cells(block_number, batch_rpc_fetched.clone())
+					.await
+			} else {
+				client
+					.insert_cells_into_dht(block_number, batch_rpc_fetched.clone())
+					.await
+			};
+			if let Err(e) = inserted {
 				debug!("Error inserting cells into DHT: {e}");
 			}
 
@@ -210,6 +228,7 @@ pub async fn run(
 	shutdown: Controller<String>,
 ) {
 	info!("Starting fat client...");
+	let header_store = HeaderStore::new(db.clone(), cfg.header_retention);
 
 	loop {
 		let (header, received_at) = match channels.rpc_event_receiver.recv().await {
@@ -217,6 +236,7 @@ pub async fn run(
 				Event::HeaderUpdate {
 					header,
 					received_at,
+					..
 				} => (header, received_at),
 			},
 			Err(error) => {
@@ -249,6 +269,13 @@ pub async fn run(
 			return;
 		};
 
+		if let Err(error) = header_store.insert(header.clone()) {
+			warn!(
+				block_number = header.number,
+				"Cannot store header: {error:#}"
+			);
+		}
+
 		let Ok(client_msg) = BlockVerified::try_from((header, None)) else {
 			error!("Cannot create message from header");
 			continue;
@@ -266,7 +293,7 @@ pub async fn run(
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::{data, telemetry::metric::tests, types::RuntimeConfig};
+	use crate::{data, telemetry::metric::tests, types::RuntimeConfig, utils::extract_kate};
 	use avail_subxt::{
 		api::runtime_types::avail_core::{
 			data_lookup::compact::CompactDataLookup,
@@ -276,6 +303,7 @@ mod tests {
 		config::substrate::Digest,
 	};
 	use hex_literal::hex;
+	use std::{collections::HashSet, sync::Mutex};
 
 	fn default_header() -> Header {
 		Header {
@@ -365,6 +393,69 @@ mod tests {
 		}
 	}
 
+	#[tokio::test]
+	async fn process_block_fetches_and_inserts_assigned_partition() {
+		let partition = Partition {
+			number: 2,
+			fraction: 4,
+		};
+		let header = default_header();
+		let (rows, cols, _, _) = extract_kate(&header.extension).unwrap();
+		let expected = Dimensions::new(rows, cols)
+			.unwrap()
+			.iter_extended_partition_positions(&partition)
+			.collect::<HashSet<_>>();
+		assert!(!expected.is_empty());
+
+		let requested = Arc::new(Mutex::new(HashSet::new()));
+		let inserted = Arc::new(Mutex::new(HashSet::new()));
+
+		let mut mock_client = MockClient::new();
+		let requested_clone = requested.clone();
+		mock_client
+			.expect_get_kate_proof()
+			.returning(move |_, positions| {
+				requested_clone
+					.lock()
+					.unwrap()
+					.extend(positions.iter().copied());
+				let cells = positions
+					.iter()
+					.map(|&position| Cell {
+						position,
+						content: [0u8; 80],
+					})
+					.collect::<Vec<_>>();
+				Box::pin(async move { Ok(cells) })
+			});
+		let inserted_clone = inserted.clone();
+		mock_client
+			.expect_insert_cells_into_dht()
+			.returning(move |_, cells| {
+				let positions = cells.iter().map(|cell| cell.position);
+				inserted_clone.lock().unwrap().extend(positions);
+				Box::pin(async move { Ok(()) })
+			});
+		mock_client
+			.expect_insert_rows_into_dht()
+			.returning(|_, _| Box::pin(async move { Ok(()) }));
+
+		process_block(
+			&mock_client,
+			data::MemoryDB::default(),
+			&Arc::new(tests::MockMetrics {}),
+			&FatClientConfig::from(&RuntimeConfig::default()),
+			&header,
+			Instant::now(),
+			partition,
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(*requested.lock().unwrap(), expected);
+		assert_eq!(*inserted.lock().unwrap(), expected);
+	}
+
 	#[tokio::test]
 	async fn process_block_successful() {
 		let db = data::MemoryDB::default();
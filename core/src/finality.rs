@@ -1,6 +1,13 @@
 use std::collections::HashMap;
 
-use codec::Encode;
+use avail_subxt::{
+	config::substrate::DigestItem,
+	primitives::{
+		grandpa::{AuthorityId, ConsensusLog},
+		Header as DaHeader,
+	},
+};
+use codec::{Decode, Encode};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use sp_core::{
@@ -8,7 +15,7 @@ use sp_core::{
 	ed25519::{self, Public},
 	Pair, H256,
 };
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 use crate::types::{GrandpaJustification, SignerMessage};
 use color_eyre::{eyre::eyre, Result};
@@ -19,6 +26,132 @@ pub struct ValidatorSet {
 	pub validator_set: Vec<Public>,
 }
 
+/// Validator set change announced in the header digest
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatorSetChange {
+	pub validator_set: Vec<Public>,
+	/// Last block finalized by the current validator set, the new set finalizes the blocks after it
+	pub enacted_at: u32,
+	/// Forced changes are enacted without the finality of the announcing block, replacing the pending changes
+	pub is_forced: bool,
+}
+
+fn validator_set(authorities: Vec<(AuthorityId, u64)>) -> Vec<Public> {
+	authorities
+		.into_iter()
+		.map(|(authority, _)| ed25519::Public::from_raw(authority.0 .0 .0))
+		.collect()
+}
+
+/// Scheduled and forced validator set changes announced in the header digest
+pub fn validator_set_changes(header: &DaHeader) -> Vec<ValidatorSetChange> {
+	header
+		.digest
+		.logs
+		.iter()
+		.filter_map(|item| match item {
+			DigestItem::Consensus([b'F', b'R', b'N', b'K'], data) => {
+				match ConsensusLog::<u32>::decode(&mut data.as_slice()) {
+					Ok(ConsensusLog::ScheduledChange(change)) => Some(ValidatorSetChange {
+						validator_set: validator_set(change.next_authorities),
+						enacted_at: header.number.saturating_add(change.delay),
+						is_forced: false,
+					}),
+					Ok(ConsensusLog::ForcedChange(_, change)) => Some(ValidatorSetChange {
+						validator_set: validator_set(change.next_authorities),
+						enacted_at: header.number.saturating_add(change.delay),
+						is_forced: true,
+					}),
+					_ => None,
+				}
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+/// Tracks the validator set which signs the justifications, applying the changes announced in the headers
+#[derive(Clone)]
+pub struct ValidatorSetTracker {
+	current: ValidatorSet,
+	pending: Option<ValidatorSetChange>,
+}
+
+impl ValidatorSetTracker {
+	pub fn new(current: ValidatorSet) -> Self {
+		Self {
+			current,
+			pending: None,
+		}
+	}
+
+	pub fn current(&self) -> &ValidatorSet {
+		&self.current
+	}
+
+	/// Schedules the validator set change, only forced changes can replace the pending change
+	pub fn schedule(&mut self, change: ValidatorSetChange) {
+		if self.pending.is_some() && !change.is_forced {
+			warn!(
+				enacted_at = change.enacted_at,
+				"Validator set change is already pending, ignoring the scheduled change"
+			);
+			return;
+		}
+		debug!("Validator set change: {change:?}");
+		self.pending = Some(change);
+	}
+
+	/// Schedules the validator set changes announced in the header
+	pub fn import(&mut self, header: &DaHeader) {
+		for change in validator_set_changes(header) {
+			self.schedule(change);
+		}
+	}
+
+	/// Validator set which signs the justification of the given block, enacting the pending change if it is due
+	pub fn validator_set_at(&mut self, block_number: u32) -> &ValidatorSet {
+		let is_due = self
+			.pending
+			.as_ref()
+			.is_some_and(|change| block_number > change.enacted_at);
+		if let Some(change) = is_due.then(|| self.pending.take()).flatten() {
+			self.current = ValidatorSet {
+				set_id: self.current.set_id + 1,
+				validator_set: change.validator_set,
+			};
+			info!(
+				set_id = self.current.set_id,
+				is_forced = change.is_forced,
+				"Validator set changed at block {}",
+				change.enacted_at
+			);
+		}
+		&self.current
+	}
+}
+
+/// Verifies that the headers, in the ascending order, form the chain from the ancestor to the descendant header
+pub fn verify_ancestry(
+	ancestor: &DaHeader,
+	headers: &[DaHeader],
+	descendant: &DaHeader,
+) -> Result<()> {
+	let mut parent = ancestor;
+	for header in headers.iter().chain([descendant]) {
+		let parent_hash = Encode::using_encoded(parent, blake2_256);
+		if header.parent_hash.0 != parent_hash {
+			return Err(eyre!(
+				"Header {} does not link to the header {}",
+				header.number,
+				parent.number
+			));
+		}
+		parent = header;
+	}
+	Ok(())
+}
+
 pub fn check_finality(
 	validator_set: &ValidatorSet,
 	justification: &GrandpaJustification,
@@ -124,9 +257,19 @@ fn confirm_ancestry(
 
 #[cfg(test)]
 mod tests {
-	use super::{check_finality, ValidatorSet};
+	use super::{
+		check_finality, verify_ancestry, ValidatorSet, ValidatorSetChange, ValidatorSetTracker,
+	};
 	use crate::types::{Commit, GrandpaJustification, Precommit, SignerMessage};
-	use avail_subxt::primitives::Header as DaHeader;
+	use avail_subxt::{
+		api::runtime_types::avail_core::{
+			data_lookup::compact::CompactDataLookup,
+			header::extension::{v3::HeaderExtension, HeaderExtension::V3},
+			kate_commitment::v3::KateCommitment,
+		},
+		config::substrate::Digest,
+		primitives::Header as DaHeader,
+	};
 	use codec::Encode;
 	use hex::FromHex;
 	use serde::{Deserialize, Serialize};
@@ -209,6 +352,83 @@ mod tests {
 		<ed25519::Pair as PairT>::verify(&sig, signed_message, &id)
 	}
 
+	#[test]
+	fn validator_set_changes() {
+		let validator = |byte: u8| Public::from_raw([byte; 32]);
+		let mut tracker = ValidatorSetTracker::new(ValidatorSet {
+			set_id: 1,
+			validator_set: vec![validator(1)],
+		});
+
+		tracker.schedule(ValidatorSetChange {
+			validator_set: vec![validator(2)],
+			enacted_at: 10,
+			is_forced: false,
+		});
+		// Only forced change can replace the pending change
+		tracker.schedule(ValidatorSetChange {
+			validator_set: vec![validator(3)],
+			enacted_at: 10,
+			is_forced: false,
+		});
+		assert_eq!(tracker.validator_set_at(10).set_id, 1);
+		let current = tracker.validator_set_at(11);
+		assert_eq!(
+			(current.set_id, current.validator_set.clone()),
+			(2, vec![validator(2)])
+		);
+
+		tracker.schedule(ValidatorSetChange {
+			validator_set: vec![validator(3)],
+			enacted_at: 20,
+			is_forced: false,
+		});
+		tracker.schedule(ValidatorSetChange {
+			validator_set: vec![validator(4)],
+			enacted_at: 15,
+			is_forced: true,
+		});
+		let current = tracker.validator_set_at(16);
+		assert_eq!(
+			(current.set_id, current.validator_set.clone()),
+			(3, vec![validator(4)])
+		);
+		assert_eq!(tracker.validator_set_at(30).set_id, 3);
+	}
+
+	#[test]
+	fn header_ancestry() {
+		let header = |number: u32, parent_hash: [u8; 32]| DaHeader {
+			parent_hash: parent_hash.into(),
+			number,
+			state_root: Default::default(),
+			extrinsics_root: Default::default(),
+			digest: Digest { logs: vec![] },
+			extension: V3(HeaderExtension {
+				commitment: KateCommitment {
+					rows: 0,
+					cols: 0,
+					data_root: Default::default(),
+					commitment: vec![],
+				},
+				app_lookup: CompactDataLookup {
+					size: 0,
+					index: vec![],
+				},
+			}),
+		};
+		let hash = |header: &DaHeader| Encode::using_encoded(header, sp_core::blake2_256);
+
+		let first = header(1, [0; 32]);
+		let second = header(2, hash(&first));
+		let third = header(3, hash(&second));
+
+		assert!(verify_ancestry(&first, &[second.clone()], &third).is_ok());
+		assert!(verify_ancestry(&first, &[], &second).is_ok());
+		assert!(verify_ancestry(&first, &[], &third).is_err());
+		assert!(verify_ancestry(&second, &[first.clone()], &third).is_err());
+	}
+
 	#[test_case("src/test_assets/ancestry.json" => true; "Complex ancestry")]
 	#[test_case("src/test_assets/ancestry_missing_link_no_majority.json" => false; "Missing ancestor negative case")]
 	#[test_case("src/test_assets/ancestry_missing_link_works.json" => true; "Missing ancestor")]
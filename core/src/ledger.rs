@@ -0,0 +1,187 @@
+//! Append-only ledger of the significant node events.
+//!
+//! Events are stored with the sequence number and the timestamp, so the operators can look into
+//! the past bootstraps, Kademlia mode changes, reorgs, RPC failovers and unavailable blocks
+//! after the logs are gone. Entries older than the retention period are pruned from the oldest one.
+
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use std::{
+	sync::Mutex,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::debug;
+
+use crate::data::{Database, LedgerEntryKey, LedgerFirstSequenceKey, LedgerLatestSequenceKey};
+
+/// Serializes appending and pruning, so the concurrent writers don't reuse the sequence numbers
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Significant node event
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NodeEvent {
+	/// Kademlia bootstrap is finished, successfully if there is no error
+	Bootstrap { error: Option<String> },
+	/// Kademlia mode is switched to `client` or `server`
+	KademliaModeChanged { mode: String },
+	/// Chain reorganization is detected at the block
+	Reorg {
+		block_number: u32,
+		orphaned_blocks: u32,
+	},
+	/// RPC client is switched to another node
+	RpcFailover { from: Option<String>, to: String },
+	/// Block is processed without reaching the confidence target
+	BelowConfidenceTarget {
+		block_number: u32,
+		verified: u32,
+		required: u32,
+	},
+}
+
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug, PartialEq)]
+pub struct LedgerEntry {
+	pub sequence: u64,
+	/// Unix timestamp of the event (in seconds)
+	pub timestamp: u64,
+	#[serde(flatten)]
+	pub event: NodeEvent,
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or_default()
+}
+
+fn append(db: &impl Database, timestamp: u64, event: NodeEvent) -> u64 {
+	let _lock = LOCK.lock().expect("Lock is not poisoned");
+	let sequence = db
+		.get(LedgerLatestSequenceKey)
+		.map_or(0, |latest| latest + 1);
+	db.put(
+		LedgerEntryKey(sequence),
+		LedgerEntry {
+			sequence,
+			timestamp,
+			event,
+		},
+	);
+	db.put(LedgerLatestSequenceKey, sequence);
+	sequence
+}
+
+/// Appends the event to the ledger
+pub fn record(db: &impl Database, event: NodeEvent) {
+	debug!(?event, "Recording node event");
+	append(db, now(), event);
+}
+
+/// Returns up to `limit` entries, latest first, with sequence numbers lower than `before` if given
+pub fn entries(db: &impl Database, before: Option<u64>, limit: usize) -> Vec<LedgerEntry> {
+	let Some(latest) = db.get(LedgerLatestSequenceKey) else {
+		return vec![];
+	};
+	let last = match before {
+		Some(0) => return vec![],
+		Some(before) => latest.min(before - 1),
+		None => latest,
+	};
+	// pruned entries are removed from the oldest one, so the first missing entry ends the ledger
+	(0..=last)
+		.rev()
+		.map_while(|sequence| db.get(LedgerEntryKey(sequence)))
+		.take(limit)
+		.collect()
+}
+
+/// Removes the entries older than the retention period, returning the number of the removed entries
+pub fn prune(db: &impl Database, retention: Duration) -> u64 {
+	prune_before(db, now().saturating_sub(retention.as_secs()))
+}
+
+fn prune_before(db: &impl Database, timestamp: u64) -> u64 {
+	let _lock = LOCK.lock().expect("Lock is not poisoned");
+	let first = db.get(LedgerFirstSequenceKey).unwrap_or_default();
+	let mut sequence = first;
+	while db
+		.get(LedgerEntryKey(sequence))
+		.is_some_and(|entry| entry.timestamp < timestamp)
+	{
+		db.delete(LedgerEntryKey(sequence));
+		sequence += 1;
+	}
+	if sequence != first {
+		db.put(LedgerFirstSequenceKey, sequence);
+	}
+	sequence - first
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::MemoryDB;
+
+	fn reorg(block_number: u32) -> NodeEvent {
+		NodeEvent::Reorg {
+			block_number,
+			orphaned_blocks: 1,
+		}
+	}
+
+	fn sequences(entries: Vec<LedgerEntry>) -> Vec<u64> {
+		entries.into_iter().map(|entry| entry.sequence).collect()
+	}
+
+	#[test]
+	fn entries_latest_first() {
+		let db = MemoryDB::default();
+		assert!(entries(&db, None, 10).is_empty());
+
+		for block_number in 0..5 {
+			record(&db, reorg(block_number));
+		}
+
+		assert_eq!(sequences(entries(&db, None, 10)), vec![4, 3, 2, 1, 0]);
+		assert_eq!(sequences(entries(&db, None, 2)), vec![4, 3]);
+		assert_eq!(sequences(entries(&db, Some(3), 10)), vec![2, 1, 0]);
+		assert_eq!(sequences(entries(&db, Some(100), 1)), vec![4]);
+		assert!(entries(&db, Some(0), 10).is_empty());
+		assert_eq!(entries(&db, None, 1)[0].event, reorg(4));
+	}
+
+	#[test]
+	fn prune_old_entries() {
+		let db = MemoryDB::default();
+		for timestamp in [100, 200, 300, 400] {
+			append(&db, timestamp, reorg(timestamp as u32));
+		}
+
+		assert_eq!(prune_before(&db, 250), 2);
+		assert_eq!(prune_before(&db, 250), 0);
+		assert_eq!(sequences(entries(&db, None, 10)), vec![3, 2]);
+
+		record(&db, reorg(500));
+		assert_eq!(sequences(entries(&db, None, 10)), vec![4, 3, 2]);
+		assert_eq!(prune_before(&db, u64::MAX), 3);
+		assert!(entries(&db, None, 10).is_empty());
+	}
+
+	#[test]
+	fn entry_json() {
+		let entry = LedgerEntry {
+			sequence: 1,
+			timestamp: 1700000000,
+			event: NodeEvent::RpcFailover {
+				from: Some("ws://127.0.0.1:9944".to_string()),
+				to: "ws://127.0.0.1:9945".to_string(),
+			},
+		};
+		assert_eq!(
+			serde_json::to_string(&entry).unwrap(),
+			r#"{"sequence":1,"timestamp":1700000000,"event":"rpc_failover","from":"ws://127.0.0.1:9944","to":"ws://127.0.0.1:9945"}"#
+		);
+	}
+}
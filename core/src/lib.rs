@@ -1,3 +1,4 @@
+pub mod alerts;
 pub mod api;
 pub mod app_client;
 pub mod consts;
@@ -6,13 +7,16 @@ pub mod crawl_client;
 pub mod data;
 pub mod fat_client;
 pub mod finality;
+pub mod ledger;
 pub mod light_client;
 pub mod maintenance;
 pub mod network;
 pub mod proof;
+pub mod sampling;
 pub mod shutdown;
 pub mod sync_client;
 pub mod sync_finality;
 pub mod telemetry;
 pub mod types;
 pub mod utils;
+pub mod validation;
@@ -5,11 +5,12 @@
 //! # Flow
 //!
 //! * Connect to the Avail node WebSocket stream and start listening to finalized headers
-//! * Generate random cells for random data sampling (8 cells currently)
+//! * Sample random cells in rounds, until the configured confidence is reached (see [`crate::sampling`])
 //! * Retrieve cell proofs from a) DHT and/or b) via RPC call from the node, in that order
 //! * Verify proof using the received cells
 //! * Calculate block confidence and store it in RocksDB
 //! * Insert cells to to DHT for remote fetch
+//! * Optionally re-insert verified cells into DHT (replication), when in Kademlia server mode
 //! * Notify the consumer (app client) a new block has been verified
 //!
 //! # Notes
@@ -18,28 +19,120 @@
 //! In case RPC is disabled, RPC calls will be skipped.
 
 use avail_subxt::{primitives::Header, utils::H256};
-use codec::Encode;
+use codec::{Decode, Encode};
 use color_eyre::Result;
-use kate_recovery::{commitments, matrix::Dimensions};
+use serde::{Deserialize, Serialize};
 use sp_core::blake2_256;
-use std::{sync::Arc, time::Instant};
-use tracing::{error, info};
+use std::{
+	sync::Arc,
+	time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::{error, info, info_span, warn, Instrument};
 
 use crate::{
-	data::{AchievedConfidenceKey, BlockHeaderKey, Database, VerifiedCellCountKey},
-	network::{
-		self,
-		rpc::{self, Event},
+	data::{
+		AchievedConfidenceKey, BlockHeaderKey, BlockProcessingKey, Database, LatestHeaderKey,
+		VerifiedCellCountKey,
 	},
+	ledger::{self, NodeEvent},
+	network::{self, p2p::DHTFetchStats, rpc::Event},
+	proof,
+	sampling::{self, Sample, Sampler},
 	shutdown::Controller,
 	telemetry::{MetricCounter, MetricValue, Metrics},
 	types::{self, BlockRange, ClientChannels, LightClientConfig},
-	utils::{calculate_confidence, extract_kate},
 };
 
+pub mod header_store;
+pub mod reorg;
+
+use header_store::HeaderStore;
+
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProcessingOutcome {
+	/// Target confidence is achieved
+	Verified,
+	/// Not enough cells are fetched for the target confidence
+	Unavailable,
+	/// Block without the header extension, or with invalid commitments
+	Skipped,
+}
+
+/// Outcome of the block processing, persisted so the availability history can be queried
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug, PartialEq)]
+pub struct BlockProcessing {
+	pub outcome: ProcessingOutcome,
+	/// Number of the verified cells needed for the target confidence
+	pub required: u32,
+	pub verified: u32,
+	/// Number of the cells fetched from the DHT
+	pub dht_fetched: u32,
+	/// Number of the cells fetched from the RPC
+	pub rpc_fetched: u32,
+	/// Outcomes and durations of the DHT GET queries
+	pub dht_stats: DHTFetchStats,
+	/// Duration of the block sampling and verification (in milliseconds)
+	pub duration: u64,
+	/// Unix timestamp of the block processing (in seconds)
+	pub processed_at: u64,
+	/// Number of the extended matrix cells
+	pub total_cells: u32,
+	/// Minimal number of the withheld cells which makes the block unavailable
+	pub withheld_cells: u32,
+}
+
+impl BlockProcessing {
+	fn new(outcome: ProcessingOutcome, sample: Option<&Sample>, started_at: Instant) -> Self {
+		let (required, verified, total_cells, withheld_cells) = sample
+			.map(|Sample { result, .. }| {
+				(
+					result.required,
+					result.verified,
+					result.total_cells,
+					result.withheld_cells,
+				)
+			})
+			.unwrap_or_default();
+		let fetch_stats = sample
+			.map(|sample| &sample.fetch_stats[..])
+			.unwrap_or_default();
+
+		BlockProcessing {
+			outcome,
+			required,
+			verified,
+			dht_fetched: fetch_stats
+				.iter()
+				.map(|stats| stats.dht_fetched as u32)
+				.sum(),
+			rpc_fetched: fetch_stats
+				.iter()
+				.filter_map(|stats| stats.rpc_fetched)
+				.map(|fetched| fetched as u32)
+				.sum(),
+			dht_stats: fetch_stats.iter().map(|stats| stats.dht_stats).sum(),
+			duration: started_at.elapsed().as_millis() as u64,
+			processed_at: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|duration| duration.as_secs())
+				.unwrap_or_default(),
+			total_cells,
+			withheld_cells,
+		}
+	}
+
+	/// Confidence of the block, if any of the cells is verified
+	pub fn confidence(&self) -> Option<f64> {
+		(self.verified > 0)
+			.then(|| sampling::confidence(self.total_cells, self.withheld_cells, self.verified))
+	}
+}
+
 pub async fn process_block(
 	db: impl Database,
 	network_client: &impl network::Client,
+	sampler: &Sampler,
 	metrics: &Arc<impl Metrics>,
 	cfg: &LightClientConfig,
 	header: Header,
@@ -52,13 +145,33 @@ pub async fn process_block(
 
 	let block_number = header.number;
 	let header_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+	let started_at = Instant::now();
+	let skipped = || BlockProcessing::new(ProcessingOutcome::Skipped, None, started_at);
 
 	info!(
 		{ block_number, block_delay = received_at.elapsed().as_secs()},
 		"Processing finalized block",
 	);
 
-	let (required, verified, unverified) = match extract_kate(&header.extension) {
+	let block_commitments = match proof::block_commitments(header_hash, &header.extension) {
+		Ok(block_commitments) => block_commitments,
+		Err(error) => {
+			info!(
+				block_number,
+				"Skipping block with invalid commitments: {error:#}"
+			);
+			db.put(BlockProcessingKey(block_number), skipped());
+			return Ok(None);
+		},
+	};
+
+	if let Some(hit_rate) = proof::commitment_cache_hit_rate() {
+		metrics
+			.record(MetricValue::CommitmentCacheHitRate(hit_rate))
+			.await;
+	}
+
+	let (required, verified, unverified, confidence) = match block_commitments {
 		None => {
 			info!("Skipping block without header extension");
 			// get current currently stored Achieved Confidence
@@ -69,69 +182,108 @@ pub async fn process_block(
 			achieved_confidence.last = block_number;
 			db.put(AchievedConfidenceKey, achieved_confidence);
 			db.put(BlockHeaderKey(block_number), header);
+			db.put(BlockProcessingKey(block_number), skipped());
 
 			return Ok(None);
 		},
-		Some((rows, cols, _, commitment)) => {
-			let Some(dimensions) = Dimensions::new(rows, cols) else {
-				info!(
-					block_number,
-					"Skipping block with invalid dimensions {rows}x{cols}",
-				);
-				return Ok(None);
-			};
-
+		Some(block_commitments) => {
+			let dimensions = block_commitments.dimensions;
 			if dimensions.cols().get() <= 2 {
 				error!(block_number, "more than 2 columns is required");
+				db.put(BlockProcessingKey(block_number), skipped());
 				return Ok(None);
 			}
 
-			let commitments = commitments::from_slice(&commitment)?;
-			let cell_count = rpc::cell_count_for_confidence(cfg.confidence);
-			let positions = rpc::generate_random_cells(dimensions, cell_count);
-			info!(
-				block_number,
-				"cells_requested" = positions.len(),
-				"Random cells generated: {}",
-				positions.len()
-			);
-
-			let (fetched, unfetched, fetch_stats) = network_client
-				.fetch_verified(
+			let commitments = &block_commitments.commitments;
+			let sample = sampler
+				.sample(
+					&db,
+					network_client,
 					block_number,
 					header_hash,
 					dimensions,
-					&commitments,
-					&positions,
+					commitments,
 				)
 				.await?;
 
-			metrics
-				.record(MetricValue::DHTFetched(fetch_stats.dht_fetched))
-				.await;
+			let outcome = match sample.result.is_target_reached() {
+				true => ProcessingOutcome::Verified,
+				false => ProcessingOutcome::Unavailable,
+			};
+			let processing = BlockProcessing::new(outcome, Some(&sample), started_at);
+			let dht_stats = processing.dht_stats;
+			if outcome == ProcessingOutcome::Unavailable {
+				ledger::record(
+					&db,
+					NodeEvent::BelowConfidenceTarget {
+						block_number,
+						verified: processing.verified,
+						required: processing.required,
+					},
+				);
+			}
+			db.put(BlockProcessingKey(block_number), processing);
 
-			metrics
-				.record(MetricValue::DHTFetchedPercentage(
-					fetch_stats.dht_fetched_percentage,
-				))
-				.await;
+			if let Some(latency) = dht_stats.average_latency() {
+				metrics
+					.record(MetricValue::DHTGetLatency(latency.as_millis() as f64))
+					.await;
+			}
+			for (reason, failures) in dht_stats.failures.by_reason() {
+				metrics
+					.record(MetricValue::DHTGetFailures(reason, failures as f64))
+					.await;
+			}
 
-			metrics
-				.record(MetricValue::DHTFetchDuration(
-					fetch_stats.dht_fetch_duration,
-				))
-				.await;
+			for fetch_stats in sample.fetch_stats {
+				metrics
+					.record(MetricValue::DHTFetched(fetch_stats.dht_fetched))
+					.await;
 
-			if let Some(rpc_fetched) = fetch_stats.rpc_fetched {
-				metrics.record(MetricValue::RPCFetched(rpc_fetched)).await;
-			}
+				metrics
+					.record(MetricValue::DHTFetchedPercentage(
+						fetch_stats.dht_fetched_percentage,
+					))
+					.await;
 
-			if let Some(rpc_fetch_duration) = fetch_stats.rpc_fetch_duration {
 				metrics
-					.record(MetricValue::RPCFetchDuration(rpc_fetch_duration))
+					.record(MetricValue::DHTFetchDuration(
+						fetch_stats.dht_fetch_duration,
+					))
 					.await;
+
+				if let Some(rpc_fetched) = fetch_stats.rpc_fetched {
+					metrics.record(MetricValue::RPCFetched(rpc_fetched)).await;
+				}
+
+				if let Some(rpc_fetch_duration) = fetch_stats.rpc_fetch_duration {
+					metrics
+						.record(MetricValue::RPCFetchDuration(rpc_fetch_duration))
+						.await;
+				}
+
+				if let Some(rate) = fetch_stats.proof_verification_rate {
+					metrics
+						.record(MetricValue::ProofVerificationRate(rate))
+						.await;
+				}
 			}
-			(positions.len(), fetched.len(), unfetched.len())
+
+			let result = sample.result;
+			if cfg.replication && !sample.cells.is_empty() {
+				if let Err(error) = network_client
+					.replicate_cells(block_number, sample.cells)
+					.await
+				{
+					warn!(block_number, "Cannot replicate verified cells: {error:#}");
+				}
+			}
+			(
+				result.required as usize,
+				result.verified as usize,
+				result.sampled.saturating_sub(result.verified) as usize,
+				result.confidence(),
+			)
 		},
 	};
 
@@ -152,13 +304,16 @@ pub async fn process_block(
 
 	db.put(AchievedConfidenceKey, achieved_confidence);
 
-	let confidence = calculate_confidence(verified as u32);
-	info!(
-		block_number,
-		"confidence" = confidence,
-		"Confidence factor: {}",
-		confidence
-	);
+	track_head_lag(&db, metrics, cfg, block_number).await;
+
+	info_span!("confidence_computation", block_number, verified).in_scope(|| {
+		info!(
+			block_number,
+			"confidence" = confidence,
+			"Confidence factor: {}",
+			confidence
+		);
+	});
 	metrics
 		.record(MetricValue::BlockConfidence(confidence))
 		.await;
@@ -176,11 +331,36 @@ pub async fn process_block(
 	Ok(Some(confidence))
 }
 
+/// Compares the latest verified block with the chain head received from the node,
+/// records the lag and emits a warning if it exceeds the configured threshold.
+async fn track_head_lag(
+	db: &impl Database,
+	metrics: &Arc<impl Metrics>,
+	cfg: &LightClientConfig,
+	block_number: u32,
+) {
+	let chain_head = db.get(LatestHeaderKey).unwrap_or(block_number);
+	let head_lag = chain_head.saturating_sub(block_number);
+
+	metrics
+		.record(MetricValue::ChainHeadHeight(chain_head))
+		.await;
+	metrics.record(MetricValue::BlockHeadLag(head_lag)).await;
+
+	if head_lag > cfg.head_lag_threshold {
+		warn!(
+			block_number,
+			chain_head, head_lag, "Latest verified block is lagging behind the chain head"
+		);
+	}
+}
+
 /// Runs light client.
 ///
 /// # Arguments
 ///
 /// * `light_client` - Light client implementation
+/// * `sampler` - Block sampler
 /// * `cfg` - Light client configuration
 /// * `metrics` - Metrics registry
 /// * `state` - Processed blocks state
@@ -189,20 +369,24 @@ pub async fn process_block(
 pub async fn run(
 	db: impl Database + Clone,
 	network_client: impl network::Client,
+	sampler: Sampler,
 	cfg: LightClientConfig,
 	metrics: Arc<impl Metrics>,
 	mut channels: ClientChannels,
 	shutdown: Controller<String>,
 ) {
 	info!("Starting light client...");
+	let header_store = HeaderStore::new(db.clone(), cfg.header_retention);
 
 	loop {
-		let (header, received_at) = match channels.rpc_event_receiver.recv().await {
+		let (header, received_at, span) = match channels.rpc_event_receiver.recv().await {
 			Ok(event) => match event {
 				Event::HeaderUpdate {
 					header,
 					received_at,
-				} => (header, received_at),
+					span,
+					..
+				} => (header, received_at, span),
 			},
 			Err(error) => {
 				error!("Cannot receive message: {error}");
@@ -218,14 +402,38 @@ pub async fn run(
 			tokio::time::sleep(seconds).await;
 		}
 
+		let header_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+		if let Some(reorg) =
+			reorg::detect(&db, &header_store, &cfg.app_ids, header.number, header_hash)
+		{
+			warn!(
+				block_number = reorg.block_number,
+				orphaned_hash = ?reorg.orphaned_hash,
+				block_hash = ?reorg.block_hash,
+				"Chain reorganization detected, invalidated {} orphaned blocks",
+				reorg.orphaned_blocks.len()
+			);
+			ledger::record(
+				&db,
+				NodeEvent::Reorg {
+					block_number: reorg.block_number,
+					orphaned_blocks: reorg.orphaned_blocks.len() as u32,
+				},
+			);
+			// there are no subscribers if sending fails
+			let _ = channels.reorg_sender.send(reorg);
+		}
+
 		let process_block_result = process_block(
 			db.clone(),
 			&network_client,
+			&sampler,
 			&metrics,
 			&cfg,
 			header.clone(),
 			received_at,
 		)
+		.instrument(info_span!(parent: &span, "process_block"))
 		.await;
 		let confidence = match process_block_result {
 			Ok(confidence) => confidence,
@@ -236,6 +444,13 @@ pub async fn run(
 			},
 		};
 
+		if let Err(error) = header_store.insert(header.clone()) {
+			warn!(
+				block_number = header.number,
+				"Cannot store header: {error:#}"
+			);
+		}
+
 		let Ok(client_msg) = types::BlockVerified::try_from((header, confidence)) else {
 			error!("Cannot create message from header");
 			continue;
@@ -351,6 +566,7 @@ mod tests {
 		process_block(
 			db,
 			&mock_network_client,
+			&Sampler::new((&RuntimeConfig::default()).into()),
 			&Arc::new(tests::MockMetrics {}),
 			&cfg,
 			header,
@@ -0,0 +1,243 @@
+//! Persistent store of the finalized block headers, which form the canonical chain.
+//!
+//! Headers are stored with their hashes by block number. Header is accepted only if it links to the stored parent and child headers,
+//! and it does not conflict with the already stored header of the same block, so stored headers always form the canonical chain.
+//! Chain can have gaps, which are filled once the missing headers are inserted.
+//! Only the headers within the retention window below the latest header are kept, older headers are pruned as the chain advances.
+
+use crate::{
+	data::{BlockHashKey, CanonicalHeaderKey, Database, LatestCanonicalHeaderKey},
+	types::{BlockVerified, Extension},
+};
+use avail_subxt::{primitives::Header, utils::H256};
+use codec::{Decode, Encode};
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use sp_core::blake2_256;
+use std::ops::RangeInclusive;
+
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug)]
+pub struct StoredHeader {
+	pub hash: H256,
+	pub header: Header,
+}
+
+impl StoredHeader {
+	pub fn new(header: Header) -> Self {
+		let hash = Encode::using_encoded(&header, blake2_256).into();
+		Self { hash, header }
+	}
+
+	pub fn number(&self) -> u32 {
+		self.header.number
+	}
+
+	/// Dimensions, lookup and commitments of the block, `None` if the block has no data
+	pub fn extension(&self) -> Result<Option<Extension>> {
+		BlockVerified::try_from((self.header.clone(), None)).map(|block| block.extension)
+	}
+}
+
+#[derive(Clone)]
+pub struct HeaderStore<T: Database> {
+	db: T,
+	retention: u32,
+}
+
+impl<T: Database> HeaderStore<T> {
+	/// Creates the header store keeping the given number of the latest headers, 0 keeps all headers
+	pub fn new(db: T, retention: u32) -> Self {
+		Self { db, retention }
+	}
+
+	pub fn get_header(&self, number: u32) -> Option<StoredHeader> {
+		self.db.get(CanonicalHeaderKey(number))
+	}
+
+	/// Header of the latest block in the canonical chain
+	pub fn latest_verified(&self) -> Option<StoredHeader> {
+		let number = self.db.get(LatestCanonicalHeaderKey)?;
+		self.get_header(number)
+	}
+
+	/// Stored headers in the given block range, missing headers are skipped
+	pub fn range(&self, range: RangeInclusive<u32>) -> impl Iterator<Item = StoredHeader> + '_ {
+		range.filter_map(move |number| self.get_header(number))
+	}
+
+	/// Inserts the finalized header into the canonical chain.
+	/// Fails if the header conflicts with the stored header of the same block, does not link to its stored neighbours,
+	/// or is older than the retained headers.
+	pub fn insert(&self, header: Header) -> Result<StoredHeader> {
+		let stored = StoredHeader::new(header);
+		let number = stored.number();
+		let latest = self.db.get(LatestCanonicalHeaderKey);
+
+		if latest.is_some_and(|latest| number < self.retained_from(latest)) {
+			return Err(eyre!("Header {number} is older than the retained headers"));
+		}
+
+		if let Some(existing) = self.get_header(number) {
+			if existing.hash != stored.hash {
+				return Err(eyre!(
+					"Header {number} conflicts with the canonical header {:?}",
+					existing.hash
+				));
+			}
+			return Ok(existing);
+		}
+
+		let parent = number.checked_sub(1).and_then(|n| self.get_header(n));
+		if let Some(parent) = parent {
+			if parent.hash != stored.header.parent_hash {
+				return Err(eyre!(
+					"Header {number} does not link to the canonical parent {:?}",
+					parent.hash
+				));
+			}
+		}
+
+		let child = number.checked_add(1).and_then(|n| self.get_header(n));
+		if let Some(child) = child {
+			if child.header.parent_hash != stored.hash {
+				return Err(eyre!(
+					"Header {number} is not the parent of the canonical header {:?}",
+					child.hash
+				));
+			}
+		}
+
+		self.db.put(CanonicalHeaderKey(number), stored.clone());
+		if latest.map_or(true, |latest| number > latest) {
+			self.db.put(LatestCanonicalHeaderKey, number);
+			if let Some(latest) = latest {
+				self.prune(latest, number);
+			}
+		}
+		Ok(stored)
+	}
+
+	/// First block number within the retention window of the given latest block
+	fn retained_from(&self, latest: u32) -> u32 {
+		match self.retention {
+			0 => 0,
+			retention => (latest + 1).saturating_sub(retention),
+		}
+	}
+
+	/// Removes the headers which fall out of the retention window once the latest header advances,
+	/// together with the processed block hashes used for the reorg detection
+	fn prune(&self, previous_latest: u32, latest: u32) {
+		let from = self.retained_from(previous_latest);
+		let to = self.retained_from(latest).min(previous_latest + 1);
+		for number in from..to {
+			self.db.delete(CanonicalHeaderKey(number));
+			self.db.delete(BlockHashKey(number));
+		}
+	}
+
+	/// Removes the headers starting from the given block, once they are no longer in the canonical chain
+	pub fn truncate(&self, from: u32) {
+		let Some(latest) = self.db.get(LatestCanonicalHeaderKey) else {
+			return;
+		};
+		for number in from..=latest {
+			self.db.delete(CanonicalHeaderKey(number));
+		}
+		match from
+			.checked_sub(1)
+			.filter(|&n| self.get_header(n).is_some())
+		{
+			Some(number) if from <= latest => self.db.put(LatestCanonicalHeaderKey, number),
+			None if from <= latest => self.db.delete(LatestCanonicalHeaderKey),
+			_ => (),
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+	use crate::data::MemoryDB;
+	use avail_subxt::{
+		api::runtime_types::avail_core::{
+			data_lookup::compact::CompactDataLookup,
+			header::extension::{v3::HeaderExtension, HeaderExtension::V3},
+			kate_commitment::v3::KateCommitment,
+		},
+		config::substrate::Digest,
+	};
+
+	pub fn header(number: u32, parent_hash: H256) -> Header {
+		Header {
+			parent_hash,
+			number,
+			state_root: H256::zero(),
+			extrinsics_root: H256::zero(),
+			digest: Digest { logs: vec![] },
+			extension: V3(HeaderExtension {
+				commitment: KateCommitment {
+					rows: 0,
+					cols: 0,
+					data_root: H256::zero(),
+					commitment: vec![],
+				},
+				app_lookup: CompactDataLookup {
+					size: 0,
+					index: vec![],
+				},
+			}),
+		}
+	}
+
+	#[test]
+	fn canonical_chain() {
+		let store = HeaderStore::new(MemoryDB::default(), 0);
+		assert!(store.latest_verified().is_none());
+
+		let first = store.insert(header(1, H256::zero())).unwrap();
+		let third = StoredHeader::new(header(3, H256::repeat_byte(1)));
+
+		// Gap is allowed, until the missing header is inserted
+		store.insert(third.header.clone()).unwrap();
+		assert_eq!(store.latest_verified().unwrap().hash, third.hash);
+		assert!(store.insert(header(2, H256::repeat_byte(2))).is_err());
+		assert!(store.insert(header(2, first.hash)).is_err());
+
+		// Conflicting header of the stored block is rejected
+		assert!(store.insert(header(1, H256::repeat_byte(2))).is_err());
+		assert_eq!(store.insert(first.header.clone()).unwrap().hash, first.hash);
+
+		let numbers: Vec<_> = store.range(0..=5).map(|header| header.number()).collect();
+		assert_eq!(numbers, vec![1, 3]);
+		assert_eq!(store.latest_verified().unwrap().number(), 3);
+
+		// Truncated chain accepts a different header
+		store.truncate(2);
+		assert_eq!(store.latest_verified().unwrap().hash, first.hash);
+		store.insert(header(2, first.hash)).unwrap();
+		assert_eq!(store.latest_verified().unwrap().number(), 2);
+	}
+
+	#[test]
+	fn retention() {
+		let db = MemoryDB::default();
+		let store = HeaderStore::new(db.clone(), 2);
+
+		let first = store.insert(header(1, H256::zero())).unwrap();
+		db.put(BlockHashKey(1), first.hash);
+		let second = store.insert(header(2, first.hash)).unwrap();
+		store.insert(header(3, second.hash)).unwrap();
+		let numbers: Vec<_> = store.range(0..=3).map(|header| header.number()).collect();
+		assert_eq!(numbers, vec![2, 3]);
+		assert_eq!(db.get(BlockHashKey(1)), None);
+
+		// Pruned header is not inserted again
+		assert!(store.insert(first.header.clone()).is_err());
+
+		// Headers below the window are pruned when the chain advances over a gap
+		store.insert(header(10, H256::repeat_byte(1))).unwrap();
+		let numbers: Vec<_> = store.range(0..=10).map(|header| header.number()).collect();
+		assert_eq!(numbers, vec![10]);
+	}
+}
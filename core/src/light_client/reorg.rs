@@ -0,0 +1,111 @@
+//! Detection of the followed chain reorganizations.
+//!
+//! Hashes of the processed blocks are stored by block number. If a block with a different hash is received
+//! for the already processed block number, the previously processed block and its processed descendants are orphaned.
+//! Stored confidence, sampling results, application data and headers of the orphaned blocks are invalidated,
+//! so they are not served as the data of the followed chain.
+
+use super::header_store::HeaderStore;
+use crate::{
+	data::{
+		AppDataKey, BlockHashKey, BlockHeaderKey, BlockProcessingKey, Database, SamplingResultKey,
+		VerifiedCellCountKey,
+	},
+	types::Reorg,
+};
+use avail_subxt::utils::H256;
+use codec::Encode;
+use sp_core::blake2_256;
+
+/// Hash of the processed block, falls back to the hash of the stored header
+fn stored_hash(db: &impl Database, block_number: u32) -> Option<H256> {
+	db.get(BlockHashKey(block_number)).or_else(|| {
+		db.get(BlockHeaderKey(block_number))
+			.map(|header| Encode::using_encoded(&header, blake2_256).into())
+	})
+}
+
+fn invalidate(db: &impl Database, app_ids: &[u32], block_number: u32) {
+	db.delete(BlockHashKey(block_number));
+	db.delete(BlockHeaderKey(block_number));
+	db.delete(VerifiedCellCountKey(block_number));
+	db.delete(SamplingResultKey(block_number));
+	db.delete(BlockProcessingKey(block_number));
+	for &app_id in app_ids {
+		db.delete(AppDataKey(app_id, block_number));
+	}
+}
+
+/// Stores the hash of the received block, and invalidates the orphaned blocks if the followed chain is reorganized
+pub fn detect<T: Database>(
+	db: &T,
+	header_store: &HeaderStore<T>,
+	app_ids: &[u32],
+	block_number: u32,
+	block_hash: H256,
+) -> Option<Reorg> {
+	let orphaned_hash = stored_hash(db, block_number).filter(|&hash| hash != block_hash);
+
+	let reorg = orphaned_hash.map(|orphaned_hash| {
+		let mut orphaned_blocks = vec![];
+		let mut number = block_number;
+		while stored_hash(db, number).is_some() {
+			invalidate(db, app_ids, number);
+			orphaned_blocks.push(number);
+			number += 1;
+		}
+		header_store.truncate(block_number);
+
+		Reorg {
+			block_number,
+			orphaned_hash,
+			block_hash,
+			orphaned_blocks,
+		}
+	});
+
+	db.put(BlockHashKey(block_number), block_hash);
+	reorg
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{data::MemoryDB, light_client::header_store::tests::header};
+
+	#[test]
+	fn invalidate_orphaned_blocks() {
+		let db = MemoryDB::default();
+		let header_store = HeaderStore::new(db.clone(), 0);
+
+		let mut parent_hash = H256::zero();
+		for number in 1..=3 {
+			let stored = header_store.insert(header(number, parent_hash)).unwrap();
+			assert!(detect(&db, &header_store, &[1], number, stored.hash).is_none());
+			db.put(VerifiedCellCountKey(number), 10);
+			db.put(AppDataKey(1, number), vec![vec![number as u8]]);
+			parent_hash = stored.hash;
+		}
+		let first_hash = header_store.get_header(1).unwrap().hash;
+		let orphaned_hash = header_store.get_header(2).unwrap().hash;
+
+		// Same block is not a reorg
+		assert!(detect(&db, &header_store, &[1], 3, parent_hash).is_none());
+
+		let fork = header(2, first_hash);
+		let fork_hash = Encode::using_encoded(&fork, blake2_256).into();
+		let reorg = detect(&db, &header_store, &[1], 2, fork_hash).unwrap();
+		assert_eq!(reorg.orphaned_blocks, vec![2, 3]);
+		assert_eq!(reorg.orphaned_hash, orphaned_hash);
+		assert_eq!(reorg.block_hash, fork_hash);
+
+		for number in 2..=3 {
+			assert!(db.get(VerifiedCellCountKey(number)).is_none());
+			assert!(db.get(AppDataKey(1, number)).is_none());
+		}
+		assert_eq!(db.get(AppDataKey(1, 1)), Some(vec![vec![1]]));
+		assert_eq!(db.get(BlockHashKey(2)), Some(fork_hash));
+		assert_eq!(header_store.latest_verified().unwrap().number(), 1);
+		header_store.insert(fork).unwrap();
+	}
+}
@@ -1,66 +1,185 @@
 use color_eyre::{eyre::WrapErr, Result};
-use std::sync::Arc;
-use tokio::sync::broadcast;
-use tracing::{debug, error, info};
+use rand::Rng;
+use serde::Serialize;
+use std::{
+	collections::BTreeMap,
+	fs,
+	path::PathBuf,
+	sync::{Arc, RwLock},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use sysinfo::{Disks, System};
+use tokio::{
+	sync::{broadcast, watch},
+	time::Instant,
+};
+use tracing::{debug, error, info, warn};
 
 use crate::{
+	data::Database,
+	ledger::{self, NodeEvent},
 	network::p2p::Client as P2pClient,
 	shutdown::Controller,
 	telemetry::{MetricCounter, MetricValue, Metrics},
 	types::{BlockVerified, MaintenanceConfig},
 };
 
-pub async fn process_block(
-	block_number: u32,
-	p2p_client: &P2pClient,
-	maintenance_config: MaintenanceConfig,
-	metrics: &Arc<impl Metrics>,
-) -> Result<()> {
-	#[cfg(not(feature = "kademlia-rocksdb"))]
-	if block_number % maintenance_config.pruning_interval == 0 {
-		info!(block_number, "Pruning...");
-		match p2p_client.prune_expired_records().await {
-			Ok(pruned) => info!(block_number, pruned, "Pruning finished"),
-			Err(error) => error!(block_number, "Pruning failed: {error:#}"),
+/// Periodic maintenance task, run by the maintenance scheduler
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Task {
+	/// Removes the expired records from the Kademlia store
+	PruneRecords,
+	/// Releases the unused capacity of the Kademlia store
+	ShrinkKademliaMap,
+	/// Switches the Kademlia mode based on the available resources
+	ReconfigureKademliaMode,
+	/// Flushes the metrics to the OpenTelemetry collector
+	FlushTelemetry,
+	/// Repeats the Kademlia bootstrap if the period expired or the routing table is depleted
+	Bootstrap,
+	/// Removes the ledger entries older than the retention period
+	PruneLedger,
+}
+
+/// Statistics of the maintenance task runs
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct TaskStats {
+	pub runs: u64,
+	pub failures: u64,
+	/// Unix timestamp of the last run (in seconds)
+	pub last_run: Option<u64>,
+	/// Duration of the last run (in milliseconds)
+	pub last_duration: Option<u64>,
+	pub last_error: Option<String>,
+}
+
+/// System resources above which the Kademlia server mode is enabled automatically
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerModeThresholds {
+	pub total_memory_gb: f64,
+	pub num_cpus: usize,
+}
+
+impl From<&MaintenanceConfig> for ServerModeThresholds {
+	fn from(config: &MaintenanceConfig) -> Self {
+		ServerModeThresholds {
+			total_memory_gb: config.total_memory_gb_threshold,
+			num_cpus: config.num_cpus_threshold,
 		}
 	}
+}
+
+/// Maintenance task statistics, shared with the API
+#[derive(Clone, Default)]
+pub struct Stats(Arc<RwLock<BTreeMap<Task, TaskStats>>>);
 
-	if block_number % maintenance_config.telemetry_flush_interval == 0 {
-		info!(block_number, "Flushing metrics...");
-		match metrics.flush().await {
-			Ok(()) => info!(block_number, "Flushing metrics finished"),
-			Err(error) => error!(block_number, "Flushing metrics failed: {error:#}"),
+impl Stats {
+	fn record(&self, task: Task, started: Instant, result: &Result<()>) {
+		let mut stats = self.0.write().expect("Lock is not poisoned");
+		let stats = stats.entry(task).or_default();
+		stats.runs += 1;
+		stats.last_run = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.ok()
+			.map(|since_epoch| since_epoch.as_secs());
+		stats.last_duration = Some(started.elapsed().as_millis() as u64);
+		stats.last_error = result.as_ref().err().map(|error| format!("{error:#}"));
+		if result.is_err() {
+			stats.failures += 1;
 		}
 	}
 
+	pub fn get(&self) -> BTreeMap<Task, TaskStats> {
+		self.0.read().expect("Lock is not poisoned").clone()
+	}
+}
+
+fn percentage(used: u64, total: u64) -> f64 {
+	if total == 0 {
+		return 0.0;
+	}
+	used as f64 / total as f64 * 100.0
+}
+
+/// Checks system memory, disk holding the database and the Kademlia store usage.
+/// Returns the cause of the resource pressure, if any of the thresholds is exceeded.
+fn resource_pressure(config: &MaintenanceConfig, map_size: usize) -> Option<String> {
+	let mut system = System::new();
+	system.refresh_memory();
+	let memory_usage = percentage(system.used_memory(), system.total_memory());
+	if memory_usage > config.memory_pressure_threshold {
+		return Some(format!("memory usage is {memory_usage:.1}%"));
+	}
+
+	let avail_path =
+		fs::canonicalize(&config.avail_path).unwrap_or_else(|_| PathBuf::from(&config.avail_path));
+	let disks = Disks::new_with_refreshed_list();
+	// Disk with the longest mount point prefix is the one holding the database
+	let disk = disks
+		.list()
+		.iter()
+		.filter(|disk| avail_path.starts_with(disk.mount_point()))
+		.max_by_key(|disk| disk.mount_point().as_os_str().len());
+
+	if let Some(disk) = disk {
+		let used_space = disk.total_space().saturating_sub(disk.available_space());
+		let disk_usage = percentage(used_space, disk.total_space());
+		if disk_usage > config.disk_pressure_threshold {
+			return Some(format!("disk usage is {disk_usage:.1}%"));
+		}
+	}
+
+	let store_usage = percentage(map_size as u64, config.max_kad_record_number);
+	if store_usage > config.store_pressure_threshold {
+		return Some(format!("Kademlia store usage is {store_usage:.1}%"));
+	}
+
+	None
+}
+
+/// Per-block bookkeeping, run on each verified block
+pub async fn process_block(
+	block_number: u32,
+	p2p_client: &P2pClient,
+	maintenance_config: &MaintenanceConfig,
+	metrics: &Arc<impl Metrics>,
+) -> Result<()> {
 	p2p_client
-		.shrink_kademlia_map()
+		.set_latest_block(block_number)
 		.await
-		.wrap_err("Unable to perform Kademlia map shrink")?;
+		.wrap_err("Unable to set the latest block")?;
 
 	let map_size = p2p_client
 		.get_kademlia_map_size()
 		.await
 		.wrap_err("Unable to get Kademlia map size")?;
 
+	// Pause publishing to the DHT while the host is under resource pressure,
+	// verification is not affected since fetching is not paused
+	let pressure = resource_pressure(maintenance_config, map_size);
+	let is_under_pressure = pressure.is_some();
+	if p2p_client.set_publication_paused(is_under_pressure) != is_under_pressure {
+		match pressure {
+			Some(cause) => {
+				warn!(
+					block_number,
+					"Resource pressure detected ({cause}), pausing DHT publication"
+				)
+			},
+			None => info!(
+				block_number,
+				"Resource pressure cleared, resuming DHT publication"
+			),
+		}
+	}
+
 	let (peers_num, pub_peers_num) = p2p_client.count_dht_entries().await?;
 	info!("Number of peers in the routing table: {peers_num}. Number of peers with public IPs: {pub_peers_num}.");
 
 	let connected_peers = p2p_client.list_connected_peers().await?;
 	debug!("Connected peers: {:?}", connected_peers);
 
-	// Reconfigure Kademlia mode if needed
-	if maintenance_config.automatic_server_mode {
-		let new_mode = p2p_client
-			.reconfigure_kademlia_mode(
-				maintenance_config.total_memory_gb_threshold,
-				maintenance_config.num_cpus_threshold,
-			)
-			.await
-			.wrap_err("Unable to reconfigure kademlia mode")?;
-		metrics.update_operating_mode(new_mode).await;
-	}
-
 	let peers_num_metric = MetricValue::DHTConnectedPeers(peers_num);
 	metrics.record(peers_num_metric).await;
 
@@ -85,26 +204,302 @@ pub async fn process_block(
 	Ok(())
 }
 
+/// Maximum exponent of the backoff between the failed bootstraps triggered by the depleted routing table
+const MAX_BOOTSTRAP_BACKOFF_EXPONENT: u32 = 6;
+
+// Doubles the routing table check interval with each consecutive failed bootstrap, up to the maximum exponent
+fn bootstrap_backoff(check_interval: Duration, failures: u32) -> Duration {
+	check_interval.saturating_mul(1 << failures.min(MAX_BOOTSTRAP_BACKOFF_EXPONENT))
+}
+
+// Prolongs the interval by a random delay, so the nodes started together don't run the tasks at the same time
+fn with_jitter(interval: Duration, jitter: f64) -> Duration {
+	if jitter <= 0.0 {
+		return interval;
+	}
+	interval + interval.mul_f64(rand::thread_rng().gen_range(0.0..jitter))
+}
+
+/// Schedule of the periodic maintenance tasks, tasks with zero interval are not scheduled
+struct Schedule {
+	tasks: Vec<(Task, Duration, Instant)>,
+	jitter: f64,
+}
+
+impl Schedule {
+	fn new(config: &MaintenanceConfig) -> Self {
+		let intervals = [
+			(Task::PruneRecords, config.prune_interval),
+			(Task::ShrinkKademliaMap, config.kademlia_shrink_interval),
+			(
+				Task::ReconfigureKademliaMode,
+				if config.automatic_server_mode {
+					config.kademlia_mode_interval
+				} else {
+					0
+				},
+			),
+			(Task::FlushTelemetry, config.telemetry_flush_interval),
+			(Task::Bootstrap, config.routing_table_check_interval),
+			(
+				Task::PruneLedger,
+				if config.ledger_retention > 0 {
+					config.prune_interval
+				} else {
+					0
+				},
+			),
+		];
+
+		let now = Instant::now();
+		let tasks = intervals
+			.into_iter()
+			.filter(|&(_, interval)| interval > 0)
+			.map(|(task, interval)| {
+				let interval = Duration::from_secs(interval);
+				(task, interval, now + with_jitter(interval, config.jitter))
+			})
+			.collect();
+
+		Schedule {
+			tasks,
+			jitter: config.jitter,
+		}
+	}
+
+	/// Waits until the next task is due, and schedules its next run
+	async fn next(&mut self) -> Option<Task> {
+		let (task, interval, next_run) = self
+			.tasks
+			.iter_mut()
+			.min_by_key(|(_, _, next_run)| *next_run)?;
+
+		tokio::time::sleep_until(*next_run).await;
+		*next_run = Instant::now() + with_jitter(*interval, self.jitter);
+		Some(*task)
+	}
+}
+
+struct Scheduler<M: Metrics, D: Database> {
+	p2p_client: P2pClient,
+	metrics: Arc<M>,
+	db: D,
+	config: MaintenanceConfig,
+	server_mode_thresholds: watch::Receiver<ServerModeThresholds>,
+	next_bootstrap: Instant,
+	// Consecutive failed bootstraps, which back off the bootstraps triggered by the depleted routing table
+	bootstrap_failures: u32,
+	next_bootstrap_retry: Instant,
+}
+
+impl<M: Metrics, D: Database> Scheduler<M, D> {
+	async fn run_task(&mut self, task: Task) -> Result<()> {
+		match task {
+			Task::PruneRecords => {
+				let pruned = self.p2p_client.prune_expired_records().await?;
+				info!(pruned, "Pruning finished");
+			},
+			Task::ShrinkKademliaMap => self
+				.p2p_client
+				.shrink_kademlia_map()
+				.await
+				.wrap_err("Unable to perform Kademlia map shrink")?,
+			Task::ReconfigureKademliaMode => {
+				let mode = self
+					.p2p_client
+					.get_kademlia_mode()
+					.await
+					.wrap_err("Unable to get kademlia mode")?;
+				let thresholds = *self.server_mode_thresholds.borrow();
+				let new_mode = self
+					.p2p_client
+					.reconfigure_kademlia_mode(thresholds.total_memory_gb, thresholds.num_cpus)
+					.await
+					.wrap_err("Unable to reconfigure kademlia mode")?;
+				if new_mode != mode {
+					ledger::record(
+						&self.db,
+						NodeEvent::KademliaModeChanged {
+							mode: new_mode.to_string(),
+						},
+					);
+				}
+				self.metrics.update_operating_mode(new_mode).await;
+			},
+			Task::FlushTelemetry => self.metrics.flush().await?,
+			Task::Bootstrap => self.refresh_routing_table().await?,
+			Task::PruneLedger => {
+				let retention = Duration::from_secs(self.config.ledger_retention);
+				let pruned = ledger::prune(&self.db, retention);
+				info!(pruned, "Ledger pruning finished");
+			},
+		};
+		Ok(())
+	}
+
+	/// Repeats the Kademlia bootstrap once the bootstrap period expires,
+	/// or sooner if the number of peers in the routing table drops below the threshold.
+	/// Bootstraps triggered by the depleted routing table are backed off after the failures.
+	async fn refresh_routing_table(&mut self) -> Result<()> {
+		let (peers_num, _) = self
+			.p2p_client
+			.count_dht_entries()
+			.await
+			.wrap_err("Unable to count routing table peers")?;
+
+		let now = Instant::now();
+		if now >= self.next_bootstrap {
+			debug!("Bootstrap period expired, bootstrapping...");
+		} else if peers_num >= self.config.routing_table_min_peers {
+			return Ok(());
+		} else if now < self.next_bootstrap_retry {
+			debug!(
+				failures = self.bootstrap_failures,
+				"Number of peers in the routing table ({peers_num}) is below the threshold, bootstrap is backed off"
+			);
+			return Ok(());
+		} else {
+			info!("Number of peers in the routing table ({peers_num}) is below the threshold, bootstrapping...");
+		}
+
+		let bootstrap_period = Duration::from_secs(self.config.bootstrap_period);
+		self.next_bootstrap = now + with_jitter(bootstrap_period, self.config.jitter);
+		let result = self.p2p_client.bootstrap().await;
+		ledger::record(
+			&self.db,
+			NodeEvent::Bootstrap {
+				error: result.as_ref().err().map(|error| format!("{error:#}")),
+			},
+		);
+		if result.is_err() {
+			let check_interval = Duration::from_secs(self.config.routing_table_check_interval);
+			self.next_bootstrap_retry =
+				Instant::now() + bootstrap_backoff(check_interval, self.bootstrap_failures);
+			self.bootstrap_failures += 1;
+		} else {
+			self.bootstrap_failures = 0;
+		}
+		result?;
+		debug!("Bootstrap done");
+		Ok(())
+	}
+}
+
+/// Runs the per-block bookkeeping on verified blocks, and the periodic maintenance tasks on their schedule.
+/// Failed periodic tasks are retried on the next run, while the per-block failures trigger the shutdown.
 pub async fn run(
 	p2p_client: P2pClient,
 	metrics: Arc<impl Metrics>,
+	db: impl Database,
 	mut block_receiver: broadcast::Receiver<BlockVerified>,
-	static_config_params: MaintenanceConfig,
+	config: MaintenanceConfig,
+	server_mode_thresholds: watch::Receiver<ServerModeThresholds>,
+	stats: Stats,
 	shutdown: Controller<String>,
 ) {
 	info!("Starting maintenance...");
 
+	let mut schedule = Schedule::new(&config);
+	let bootstrap_period = Duration::from_secs(config.bootstrap_period);
+	let mut scheduler = Scheduler {
+		p2p_client: p2p_client.clone(),
+		metrics: metrics.clone(),
+		db,
+		next_bootstrap: Instant::now() + with_jitter(bootstrap_period, config.jitter),
+		bootstrap_failures: 0,
+		next_bootstrap_retry: Instant::now(),
+		config: config.clone(),
+		server_mode_thresholds,
+	};
+
 	loop {
-		let result = match block_receiver.recv().await {
-			Ok(block) => {
-				process_block(block.block_num, &p2p_client, static_config_params, &metrics).await
-			},
-			Err(error) => Err(error.into()),
-		};
+		tokio::select! {
+			block = block_receiver.recv() => {
+				let result = match block {
+					Ok(block) => process_block(block.block_num, &p2p_client, &config, &metrics).await,
+					// Blocks may be skipped while a long running task is in progress
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						warn!(skipped, "Maintenance skipped verified blocks");
+						continue;
+					},
+					Err(error) => Err(error.into()),
+				};
 
-		if let Err(error) = result {
-			let _ = shutdown.trigger_shutdown(format!("{error:#}"));
-			break;
+				if let Err(error) = result {
+					let _ = shutdown.trigger_shutdown(format!("{error:#}"));
+					break;
+				}
+			},
+			Some(task) = schedule.next() => {
+				debug!(?task, "Running maintenance task...");
+				let started = Instant::now();
+				let result = scheduler.run_task(task).await;
+				if let Err(error) = &result {
+					error!(?task, "Maintenance task failed: {error:#}");
+				}
+				stats.record(task, started, &result);
+			},
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::RuntimeConfig;
+
+	#[test]
+	fn schedule_skips_disabled_tasks() {
+		let mut config: MaintenanceConfig = (&RuntimeConfig::default()).into();
+		config.telemetry_flush_interval = 0;
+		config.automatic_server_mode = false;
+		config.jitter = 0.0;
+
+		let schedule = Schedule::new(&config);
+		let tasks = schedule
+			.tasks
+			.iter()
+			.map(|&(task, interval, _)| (task, interval.as_secs()))
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			tasks,
+			vec![
+				(Task::PruneRecords, config.prune_interval),
+				(Task::ShrinkKademliaMap, config.kademlia_shrink_interval),
+				(Task::Bootstrap, config.routing_table_check_interval),
+				(Task::PruneLedger, config.prune_interval),
+			]
+		);
+	}
+
+	#[test]
+	fn stats_record_runs_and_failures() {
+		let stats = Stats::default();
+		stats.record(Task::PruneRecords, Instant::now(), &Ok(()));
+		stats.record(
+			Task::PruneRecords,
+			Instant::now(),
+			&Err(color_eyre::eyre::eyre!("Store is gone")),
+		);
+
+		let stats = stats.get();
+		let prune_stats = &stats[&Task::PruneRecords];
+		assert_eq!(prune_stats.runs, 2);
+		assert_eq!(prune_stats.failures, 1);
+		assert_eq!(prune_stats.last_error.as_deref(), Some("Store is gone"));
+		assert!(prune_stats.last_run.is_some());
+		assert!(!stats.contains_key(&Task::Bootstrap));
+	}
+
+	#[test]
+	fn bootstrap_backoff_is_capped() {
+		let interval = Duration::from_secs(60);
+		assert_eq!(bootstrap_backoff(interval, 0), interval);
+		assert_eq!(bootstrap_backoff(interval, 2), Duration::from_secs(240));
+		assert_eq!(
+			bootstrap_backoff(interval, 100),
+			bootstrap_backoff(interval, MAX_BOOTSTRAP_BACKOFF_EXPONENT)
+		);
+	}
+}
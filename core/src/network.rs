@@ -1,22 +1,30 @@
 use async_trait::async_trait;
-use color_eyre::{eyre::WrapErr, Result};
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
 use dusk_plonk::prelude::PublicParameters;
+use futures::future::{self, Either};
 use kate_recovery::{
 	config,
 	data::Cell,
 	matrix::{Dimensions, Position},
 };
+use libp2p::kad::Mode;
 use mockall::automock;
 use sp_core::H256;
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashSet, future::Future, sync::Arc, time::Duration};
 use tokio::time::Instant;
-use tracing::{debug, info};
+use tracing::{debug, info, instrument, warn};
 
-use crate::{data::Database, proof};
+use crate::{data::Database, network::p2p::DHTFetchStats, proof};
 
+pub mod fetch;
 pub mod p2p;
 pub mod rpc;
 
+pub use fetch::FetchStrategy;
+
 #[async_trait]
 #[automock]
 pub trait Client {
@@ -28,6 +36,20 @@ pub trait Client {
 		commitments: &[[u8; config::COMMITMENT_SIZE]],
 		positions: &[Position],
 	) -> Result<(Vec<Cell>, Vec<Position>, FetchStats)>;
+
+	/// Fetches verified cells using the given strategy, instead of the configured one
+	async fn fetch_verified_with(
+		&self,
+		strategy: FetchStrategy,
+		block_number: u32,
+		block_hash: H256,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+		positions: &[Position],
+	) -> Result<(Vec<Cell>, Vec<Position>, FetchStats)>;
+
+	/// Re-inserts verified cells into the DHT, if the node is in Kademlia server mode
+	async fn replicate_cells(&self, block_number: u32, cells: Vec<Cell>) -> Result<()>;
 }
 
 pub struct FetchStats {
@@ -36,10 +58,48 @@ pub struct FetchStats {
 	pub dht_fetch_duration: f64,
 	pub rpc_fetched: Option<f64>,
 	pub rpc_fetch_duration: Option<f64>,
+	/// Number of the cell proofs verified per second
+	pub proof_verification_rate: Option<f64>,
+	/// Outcomes and durations of the DHT GET queries
+	pub dht_stats: DHTFetchStats,
 }
 
 type RPCFetchStats = (usize, Duration);
 
+/// Number of the cells with verified proofs, and the verification duration
+type VerificationStats = (usize, Duration);
+
+/// Verified cells, unfetched positions, fetch duration, verification stats and DHT GET stats (empty for RPC)
+type FetchResult = (
+	Vec<Cell>,
+	Vec<Position>,
+	Duration,
+	VerificationStats,
+	DHTFetchStats,
+);
+
+fn empty_fetch(positions: &[Position]) -> FetchResult {
+	(
+		vec![],
+		positions.to_vec(),
+		Duration::ZERO,
+		(0, Duration::ZERO),
+		DHTFetchStats::default(),
+	)
+}
+
+fn unfetched_positions(positions: &[Position], fetched: &[Cell]) -> Vec<Position> {
+	let fetched = fetched
+		.iter()
+		.map(|cell| cell.position)
+		.collect::<HashSet<_>>();
+	positions
+		.iter()
+		.filter(|position| !fetched.contains(position))
+		.copied()
+		.collect()
+}
+
 impl FetchStats {
 	pub fn new(
 		total: usize,
@@ -53,33 +113,75 @@ impl FetchStats {
 			dht_fetch_duration: dht_fetch_duration.as_secs_f64(),
 			rpc_fetched: rpc_fetch_stats.map(|(rpc_fetched, _)| rpc_fetched as f64),
 			rpc_fetch_duration: rpc_fetch_stats.map(|(_, duration)| duration.as_secs_f64()),
+			proof_verification_rate: None,
+			dht_stats: DHTFetchStats::default(),
 		}
 	}
+
+	fn with_dht_stats(mut self, dht_stats: DHTFetchStats) -> Self {
+		self.dht_stats = dht_stats;
+		self
+	}
+
+	/// Sets the proof verification rate from the verification stats, if any proof is verified
+	fn with_verification_stats(mut self, stats: &[VerificationStats]) -> Self {
+		let (cells, duration) = stats
+			.iter()
+			.fold((0, Duration::ZERO), |(cells, duration), stats| {
+				(cells + stats.0, duration + stats.1)
+			});
+		self.proof_verification_rate =
+			(cells > 0 && !duration.is_zero()).then(|| cells as f64 / duration.as_secs_f64());
+		self
+	}
 }
 
 struct DHTWithRPCFallbackClient<T: Database> {
 	p2p_client: p2p::Client,
-	rpc_client: rpc::Client<T>,
+	/// Node's RPC client, not set in the DHT-only mode
+	rpc_client: Option<rpc::Client<T>>,
 	pp: Arc<PublicParameters>,
 	disable_rpc: bool,
+	/// Fetch cells from block providers instead of DHT records
+	provider_mode: bool,
+	/// Maximum duration of the DHT fetch, not bounded if not set
+	fetch_timeout: Option<Duration>,
+	strategy: FetchStrategy,
 }
 
 type Commitments = [[u8; config::COMMITMENT_SIZE]];
 
 impl<T: Database> DHTWithRPCFallbackClient<T> {
+	#[instrument(
+		skip_all,
+		name = "dht_fetch",
+		fields(block_number = block_number, cells = positions.len()),
+	)]
 	async fn fetch_verified_from_dht(
 		&self,
 		block_number: u32,
 		dimensions: Dimensions,
 		commitments: &Commitments,
 		positions: &[Position],
-	) -> Result<(Vec<Cell>, Vec<Position>, Duration)> {
+	) -> Result<FetchResult> {
 		let begin = Instant::now();
 
-		let (mut dht_fetched, mut unfetched) = self
-			.p2p_client
-			.fetch_cells_from_dht(block_number, positions)
-			.await;
+		let (mut dht_fetched, mut unfetched, mut dht_stats) = if self.provider_mode {
+			let (fetched, unfetched) = self
+				.p2p_client
+				.fetch_cells_from_providers(block_number, positions)
+				.await;
+			(fetched, unfetched, DHTFetchStats::default())
+		} else if let Some(fetch_timeout) = self.fetch_timeout {
+			let deadline = (begin + fetch_timeout).into_std();
+			self.p2p_client
+				.fetch_cells_from_dht_with_deadline(block_number, positions, deadline)
+				.await
+		} else {
+			self.p2p_client
+				.fetch_cells_from_dht(block_number, positions, None)
+				.await
+		};
 
 		let fetch_elapsed = begin.elapsed();
 
@@ -103,12 +205,30 @@ impl<T: Database> DHTWithRPCFallbackClient<T> {
 			"Cells fetched from DHT"
 		);
 
+		let unverified_count = unverified.len();
+		dht_stats.failures.invalid_proof += unverified_count as u32;
 		dht_fetched.retain(|cell| verified.contains(&cell.position));
 		unfetched.append(&mut unverified);
+		self.p2p_client.cache_cells(block_number, &dht_fetched);
 
-		Ok((dht_fetched, unfetched, fetch_elapsed))
+		let verification_stats = (
+			dht_fetched.len() + unverified_count,
+			begin.elapsed() - fetch_elapsed,
+		);
+		Ok((
+			dht_fetched,
+			unfetched,
+			fetch_elapsed,
+			verification_stats,
+			dht_stats,
+		))
 	}
 
+	#[instrument(
+		skip_all,
+		name = "rpc_fetch",
+		fields(block_number = block_number, cells = positions.len()),
+	)]
 	async fn fetch_verified_from_rpc(
 		&self,
 		block_number: u32,
@@ -116,17 +236,17 @@ impl<T: Database> DHTWithRPCFallbackClient<T> {
 		dimensions: Dimensions,
 		commitments: &Commitments,
 		positions: &[Position],
-	) -> Result<(Vec<Cell>, Vec<Position>, Duration)> {
+	) -> Result<FetchResult> {
 		let begin = Instant::now();
 
-		let mut fetched = self
-			.rpc_client
-			.request_kate_proof(block_hash, positions)
-			.await?;
+		let Some(rpc_client) = &self.rpc_client else {
+			return Err(eyre!("RPC client is not available in the DHT-only mode"));
+		};
+		let mut fetched = rpc_client.request_kate_proof(block_hash, positions).await?;
 
 		let fetch_elapsed = begin.elapsed();
 
-		let (verified, unverified) = proof::verify(
+		let (verified, _) = proof::verify(
 			block_number,
 			dimensions,
 			&fetched,
@@ -146,8 +266,119 @@ impl<T: Database> DHTWithRPCFallbackClient<T> {
 			"Cells fetched from RPC"
 		);
 
+		let verification_stats = (fetched.len(), begin.elapsed() - fetch_elapsed);
 		fetched.retain(|cell| verified.contains(&cell.position));
-		Ok((fetched, unverified, fetch_elapsed))
+		self.p2p_client.cache_cells(block_number, &fetched);
+		// cells not returned by RPC are unfetched as well as the unverified ones
+		let unfetched = unfetched_positions(positions, &fetched);
+		Ok((
+			fetched,
+			unfetched,
+			fetch_elapsed,
+			verification_stats,
+			DHTFetchStats::default(),
+		))
+	}
+}
+
+/// Returns the strategy used for the fetch, cells are fetched only from DHT if RPC is not available
+fn effective_strategy(strategy: FetchStrategy, is_rpc_available: bool) -> FetchStrategy {
+	if is_rpc_available {
+		strategy
+	} else {
+		FetchStrategy::DhtOnly
+	}
+}
+
+/// Fetches the cells with the given strategy, using the DHT and RPC fetch functions.
+/// Returns the DHT fetch result, and the RPC fetch result if the strategy uses RPC.
+async fn fetch_with_strategy<DHT, RPC>(
+	strategy: FetchStrategy,
+	block_number: u32,
+	positions: &[Position],
+	fetch_from_dht: impl Fn(Vec<Position>) -> DHT,
+	fetch_from_rpc: impl Fn(Vec<Position>) -> RPC,
+) -> Result<(FetchResult, Option<FetchResult>)>
+where
+	DHT: Future<Output = Result<FetchResult>>,
+	RPC: Future<Output = Result<FetchResult>>,
+{
+	match strategy {
+		FetchStrategy::DhtOnly => {
+			let dht = fetch_from_dht(positions.to_vec()).await?;
+			Ok((dht, None))
+		},
+		FetchStrategy::DhtFirst => {
+			let dht = fetch_from_dht(positions.to_vec()).await?;
+			let rpc = fetch_from_rpc(dht.1.clone()).await?;
+			Ok((dht, Some(rpc)))
+		},
+		FetchStrategy::RpcFirst => {
+			let rpc = fetch_from_rpc(positions.to_vec())
+				.await
+				.unwrap_or_else(|error| {
+					warn!(
+						block_number,
+						"Cannot fetch cells from RPC, falling back to DHT: {error:#}"
+					);
+					empty_fetch(positions)
+				});
+			let dht = if rpc.1.is_empty() {
+				empty_fetch(&[])
+			} else {
+				fetch_from_dht(rpc.1.clone()).await?
+			};
+			Ok((dht, Some(rpc)))
+		},
+		FetchStrategy::Race => {
+			fetch_race(
+				block_number,
+				positions,
+				fetch_from_dht(positions.to_vec()),
+				fetch_from_rpc(positions.to_vec()),
+			)
+			.await
+		},
+	}
+}
+
+/// Fetches cells from DHT and RPC simultaneously, taking the first complete result.
+/// If the first result is not complete, the other one is awaited as well.
+async fn fetch_race(
+	block_number: u32,
+	positions: &[Position],
+	dht: impl Future<Output = Result<FetchResult>>,
+	rpc: impl Future<Output = Result<FetchResult>>,
+) -> Result<(FetchResult, Option<FetchResult>)> {
+	futures::pin_mut!(dht, rpc);
+	let is_complete = |result: &Result<FetchResult>| {
+		result
+			.as_ref()
+			.is_ok_and(|(_, unfetched, ..)| unfetched.is_empty())
+	};
+
+	let (dht, rpc) = match future::select(dht, rpc).await {
+		Either::Left((dht, _)) if is_complete(&dht) => (dht, Ok(empty_fetch(&[]))),
+		Either::Left((dht, rpc)) => (dht, rpc.await),
+		Either::Right((rpc, _)) if is_complete(&rpc) => (Ok(empty_fetch(&[])), rpc),
+		Either::Right((rpc, dht)) => (dht.await, rpc),
+	};
+
+	match (dht, rpc) {
+		(Err(dht_error), Err(rpc_error)) => {
+			Err(dht_error.wrap_err(format!("Cannot fetch cells from RPC: {rpc_error:#}")))
+		},
+		(dht, rpc) => {
+			let dht = dht.unwrap_or_else(|error| {
+				warn!(block_number, "Cannot fetch cells from DHT: {error:#}");
+				empty_fetch(positions)
+			});
+			let rpc = rpc.unwrap_or_else(|error| {
+				warn!(block_number, "Cannot fetch cells from RPC: {error:#}");
+				empty_fetch(positions)
+			});
+			Ok((dht, Some(rpc)))
+		},
 	}
 }
 
@@ -161,31 +392,72 @@ impl<T: Database + Sync> Client for DHTWithRPCFallbackClient<T> {
 		commitments: &Commitments,
 		positions: &[Position],
 	) -> Result<(Vec<Cell>, Vec<Position>, FetchStats)> {
-		let (dht_fetched, unfetched, dht_fetch_duration) = self
-			.fetch_verified_from_dht(block_number, dimensions, commitments, positions)
-			.await?;
+		self.fetch_verified_with(
+			self.strategy,
+			block_number,
+			block_hash,
+			dimensions,
+			commitments,
+			positions,
+		)
+		.await
+	}
 
-		if self.disable_rpc {
-			let stats =
-				FetchStats::new(positions.len(), dht_fetched.len(), dht_fetch_duration, None);
-			return Ok((dht_fetched, unfetched, stats));
-		};
+	async fn fetch_verified_with(
+		&self,
+		strategy: FetchStrategy,
+		block_number: u32,
+		block_hash: H256,
+		dimensions: Dimensions,
+		commitments: &Commitments,
+		positions: &[Position],
+	) -> Result<(Vec<Cell>, Vec<Position>, FetchStats)> {
+		let is_rpc_available = !self.disable_rpc && self.rpc_client.is_some();
+		let strategy = effective_strategy(strategy, is_rpc_available);
 
-		let (rpc_fetched, unfetched, rpc_fetch_duration) = self
-			.fetch_verified_from_rpc(
+		let fetch_from_dht = |positions: Vec<Position>| async move {
+			self.fetch_verified_from_dht(block_number, dimensions, commitments, &positions)
+				.await
+		};
+		let fetch_from_rpc = |positions: Vec<Position>| async move {
+			self.fetch_verified_from_rpc(
 				block_number,
 				block_hash,
 				dimensions,
 				commitments,
-				&unfetched,
+				&positions,
 			)
-			.await?;
-
-		if let Err(error) = self
-			.p2p_client
-			.insert_cells_into_dht(block_number, rpc_fetched.clone())
 			.await
-		{
+		};
+		let (dht, rpc) = fetch_with_strategy(
+			strategy,
+			block_number,
+			positions,
+			fetch_from_dht,
+			fetch_from_rpc,
+		)
+		.await?;
+
+		let (dht_fetched, _, dht_fetch_duration, dht_verification_stats, dht_stats) = dht;
+		let Some((rpc_fetched, _, rpc_fetch_duration, rpc_verification_stats, _)) = rpc else {
+			let unfetched = unfetched_positions(positions, &dht_fetched);
+			let stats =
+				FetchStats::new(positions.len(), dht_fetched.len(), dht_fetch_duration, None)
+					.with_verification_stats(&[dht_verification_stats])
+					.with_dht_stats(dht_stats);
+			return Ok((dht_fetched, unfetched, stats));
+		};
+
+		let inserted = if self.provider_mode {
+			self.p2p_client
+				.provide_cells(block_number, rpc_fetched.clone())
+				.await
+		} else {
+			self.p2p_client
+				.insert_cells_into_dht(block_number, rpc_fetched.clone(), None)
+				.await
+		};
+		if let Err(error) = inserted {
 			debug!("Error inserting cells into DHT: {error}");
 		}
 
@@ -194,26 +466,211 @@ impl<T: Database + Sync> Client for DHTWithRPCFallbackClient<T> {
 			dht_fetched.len(),
 			dht_fetch_duration,
 			Some((rpc_fetched.len(), rpc_fetch_duration)),
-		);
+		)
+		.with_verification_stats(&[dht_verification_stats, rpc_verification_stats])
+		.with_dht_stats(dht_stats);
 
-		let mut fetched = vec![];
-		fetched.extend(dht_fetched);
-		fetched.extend(rpc_fetched);
+		// cells fetched from both sources when racing are taken from DHT
+		let dht_positions = dht_fetched
+			.iter()
+			.map(|cell| cell.position)
+			.collect::<HashSet<_>>();
+		let mut fetched = dht_fetched;
+		fetched.extend(
+			rpc_fetched
+				.into_iter()
+				.filter(|cell| !dht_positions.contains(&cell.position)),
+		);
+		let unfetched = unfetched_positions(positions, &fetched);
 
 		Ok((fetched, unfetched, stats))
 	}
+
+	async fn replicate_cells(&self, block_number: u32, cells: Vec<Cell>) -> Result<()> {
+		let mode = self.p2p_client.get_kademlia_mode().await?;
+		if !matches!(mode, Mode::Server) {
+			debug!(
+				block_number,
+				"Skipping cells replication in Kademlia {mode} mode"
+			);
+			return Ok(());
+		}
+
+		self.p2p_client
+			.insert_cells_into_dht(block_number, cells, None)
+			.await
+	}
 }
 
 pub fn new(
 	p2p_client: p2p::Client,
-	rpc_client: rpc::Client<impl Database + Sync>,
+	rpc_client: Option<rpc::Client<impl Database + Sync>>,
 	pp: Arc<PublicParameters>,
 	disable_rpc: bool,
+	provider_mode: bool,
+	fetch_timeout: Option<Duration>,
+	strategy: FetchStrategy,
 ) -> impl Client {
 	DHTWithRPCFallbackClient {
 		p2p_client,
 		rpc_client,
 		pp,
 		disable_rpc,
+		provider_mode,
+		fetch_timeout,
+		strategy,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Mutex;
+	use test_case::test_case;
+
+	fn position(col: u16) -> Position {
+		Position { row: 0, col }
+	}
+
+	/// Fetches the requested positions which are available
+	fn fetch(requested: Vec<Position>, available: &[Position]) -> Result<FetchResult> {
+		let fetched = requested
+			.iter()
+			.filter(|position| available.contains(position))
+			.map(|&position| Cell {
+				position,
+				content: [0; 80],
+			})
+			.collect::<Vec<_>>();
+		let unfetched = unfetched_positions(&requested, &fetched);
+		Ok((
+			fetched,
+			unfetched,
+			Duration::ZERO,
+			(0, Duration::ZERO),
+			DHTFetchStats::default(),
+		))
+	}
+
+	/// Fetch results as the positions fetched from DHT and RPC, and the positions requested from DHT and RPC
+	type Fetched = (Vec<u16>, Option<Vec<u16>>, Vec<Vec<u16>>, Vec<Vec<u16>>);
+
+	async fn fetch_with(
+		strategy: FetchStrategy,
+		dht_available: &[Position],
+		rpc_available: Option<&[Position]>,
+	) -> Fetched {
+		let positions = (0..4).map(position).collect::<Vec<_>>();
+		let dht_requests = Mutex::new(vec![]);
+		let rpc_requests = Mutex::new(vec![]);
+		let cols = |positions: &[Position]| positions.iter().map(|p| p.col).collect::<Vec<_>>();
+
+		let fetch_from_dht = |requested: Vec<Position>| {
+			dht_requests.lock().unwrap().push(cols(&requested));
+			async move { fetch(requested, dht_available) }
+		};
+		let fetch_from_rpc = |requested: Vec<Position>| {
+			rpc_requests.lock().unwrap().push(cols(&requested));
+			async move {
+				match rpc_available {
+					Some(available) => fetch(requested, available),
+					None => Err(eyre!("RPC is not available")),
+				}
+			}
+		};
+		let (dht, rpc) =
+			fetch_with_strategy(strategy, 1, &positions, fetch_from_dht, fetch_from_rpc)
+				.await
+				.unwrap();
+
+		let fetched = |cells: &[Cell]| cells.iter().map(|cell| cell.position.col).collect();
+		(
+			fetched(&dht.0),
+			rpc.map(|rpc| fetched(&rpc.0)),
+			dht_requests.into_inner().unwrap(),
+			rpc_requests.into_inner().unwrap(),
+		)
+	}
+
+	#[test_case(FetchStrategy::RpcFirst, true => FetchStrategy::RpcFirst ; "Per call strategy is used")]
+	#[test_case(FetchStrategy::Race, true => FetchStrategy::Race ; "Per call race is used")]
+	#[test_case(FetchStrategy::RpcFirst, false => FetchStrategy::DhtOnly ; "DHT only without RPC")]
+	#[test_case(FetchStrategy::DhtFirst, false => FetchStrategy::DhtOnly ; "DHT first without RPC")]
+	fn strategy_override(strategy: FetchStrategy, is_rpc_available: bool) -> FetchStrategy {
+		effective_strategy(strategy, is_rpc_available)
+	}
+
+	#[tokio::test]
+	async fn dht_only() {
+		let (dht, rpc, dht_requests, rpc_requests) =
+			fetch_with(FetchStrategy::DhtOnly, &[position(0)], Some(&[position(1)])).await;
+		assert_eq!(dht, vec![0]);
+		assert_eq!(rpc, None);
+		assert_eq!(dht_requests, vec![vec![0, 1, 2, 3]]);
+		assert!(rpc_requests.is_empty());
+	}
+
+	#[tokio::test]
+	async fn dht_first() {
+		let all = (0..4).map(position).collect::<Vec<_>>();
+		let (dht, rpc, dht_requests, rpc_requests) =
+			fetch_with(FetchStrategy::DhtFirst, &all[..2], Some(&all[..])).await;
+		assert_eq!(dht, vec![0, 1]);
+		assert_eq!(rpc, Some(vec![2, 3]));
+		assert_eq!(dht_requests, vec![vec![0, 1, 2, 3]]);
+		// only the cells missing from DHT are fetched from RPC
+		assert_eq!(rpc_requests, vec![vec![2, 3]]);
+	}
+
+	#[tokio::test]
+	async fn rpc_first() {
+		let all = (0..4).map(position).collect::<Vec<_>>();
+		let (dht, rpc, dht_requests, _) =
+			fetch_with(FetchStrategy::RpcFirst, &all, Some(&all[..])).await;
+		assert!(dht.is_empty());
+		assert_eq!(rpc, Some(vec![0, 1, 2, 3]));
+		// DHT is not used if RPC returns all cells
+		assert!(dht_requests.is_empty());
+
+		let (dht, rpc, dht_requests, _) =
+			fetch_with(FetchStrategy::RpcFirst, &all, Some(&all[..1])).await;
+		assert_eq!(dht, vec![1, 2, 3]);
+		assert_eq!(rpc, Some(vec![0]));
+		assert_eq!(dht_requests, vec![vec![1, 2, 3]]);
+	}
+
+	#[tokio::test]
+	async fn rpc_first_fallback() {
+		let all = (0..4).map(position).collect::<Vec<_>>();
+		let (dht, rpc, dht_requests, _) = fetch_with(FetchStrategy::RpcFirst, &all, None).await;
+		assert_eq!(dht, vec![0, 1, 2, 3]);
+		assert_eq!(rpc, Some(vec![]));
+		assert_eq!(dht_requests, vec![vec![0, 1, 2, 3]]);
+	}
+
+	#[tokio::test]
+	async fn race() {
+		let all = (0..4).map(position).collect::<Vec<_>>();
+		let (dht, rpc, dht_requests, rpc_requests) =
+			fetch_with(FetchStrategy::Race, &all[..2], Some(&all[2..])).await;
+		// both sources are awaited if the first result is not complete
+		assert_eq!(dht, vec![0, 1]);
+		assert_eq!(rpc, Some(vec![2, 3]));
+		assert_eq!(dht_requests, vec![vec![0, 1, 2, 3]]);
+		assert_eq!(rpc_requests, vec![vec![0, 1, 2, 3]]);
+
+		// complete DHT result is taken without waiting for RPC
+		let available = all.as_slice();
+		let (dht, rpc) = fetch_with_strategy(
+			FetchStrategy::Race,
+			1,
+			&all,
+			|requested| async move { fetch(requested, available) },
+			|_| std::future::pending(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(dht.0.len(), 4);
+		assert!(rpc.is_some_and(|rpc| rpc.0.is_empty()));
 	}
 }
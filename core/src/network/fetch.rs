@@ -0,0 +1,52 @@
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Strategy of fetching the cells from the network
+///
+/// * `DhtFirst` - fetch cells from DHT, and the ones missing from DHT from RPC
+/// * `RpcFirst` - fetch cells from RPC, and the ones missing from RPC from DHT
+/// * `Race` - fetch cells from DHT and RPC simultaneously, take the first complete response
+/// * `DhtOnly` - fetch cells from DHT only, never contacting the full node
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(try_from = "String", into = "String")]
+pub enum FetchStrategy {
+	#[default]
+	DhtFirst,
+	RpcFirst,
+	Race,
+	DhtOnly,
+}
+
+impl Display for FetchStrategy {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			FetchStrategy::DhtFirst => write!(f, "dht_first"),
+			FetchStrategy::RpcFirst => write!(f, "rpc_first"),
+			FetchStrategy::Race => write!(f, "race"),
+			FetchStrategy::DhtOnly => write!(f, "dht_only"),
+		}
+	}
+}
+
+impl From<FetchStrategy> for String {
+	fn from(value: FetchStrategy) -> Self {
+		value.to_string()
+	}
+}
+
+impl TryFrom<String> for FetchStrategy {
+	type Error = color_eyre::Report;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		match value.to_lowercase().as_str() {
+			"dht_first" => Ok(FetchStrategy::DhtFirst),
+			"rpc_first" => Ok(FetchStrategy::RpcFirst),
+			"race" => Ok(FetchStrategy::Race),
+			"dht_only" => Ok(FetchStrategy::DhtOnly),
+			_ => Err(eyre!(
+				"Wrong fetch strategy. Expecting 'dht_first', 'rpc_first', 'race' or 'dht_only'."
+			)),
+		}
+	}
+}
@@ -1,76 +1,485 @@
 use allow_block_list::BlockedPeers;
-use color_eyre::{eyre::WrapErr, Report, Result};
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Report, Result,
+};
 use libp2p::{
-	autonat, dcutr, identify, identity,
-	kad::{self, Mode, PeerRecord, QueryId},
-	mdns, noise, ping, relay,
+	autonat, connection_limits,
+	core::transport::ListenerId,
+	core::upgrade,
+	dcutr, dns, gossipsub, identify, identity,
+	kad::{self, Mode, PeerRecord, QueryId, Quorum, Record},
+	mdns,
+	multiaddr::Protocol,
+	noise, ping,
+	pnet::{PnetConfig, PreSharedKey},
+	relay,
+	request_response::{self, OutboundRequestId, ProtocolSupport},
 	swarm::NetworkBehaviour,
-	tcp, upnp, yamux, Multiaddr, PeerId, Swarm, SwarmBuilder,
+	tcp, upnp, websocket, yamux, Multiaddr, PeerId, Swarm, SwarmBuilder, Transport,
 };
+use lru::LruCache;
 use multihash::{self, Hasher};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, net::Ipv4Addr};
-use tokio::sync::{
-	mpsc::{self},
-	oneshot,
+use std::{
+	collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet, VecDeque},
+	fs,
+	hash::{Hash, Hasher as _},
+	net::{Ipv4Addr, Ipv6Addr},
+	num::NonZeroUsize,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+	time::Duration,
+};
+use tokio::{
+	sync::{
+		broadcast,
+		mpsc::{self},
+		oneshot,
+	},
+	time::Instant,
 };
-use tracing::info;
+use tokio_stream::StreamExt;
+use tracing::{debug, error, info};
 
 #[cfg(feature = "network-analysis")]
 pub mod analyzer;
+mod announcements;
+mod bandwidth;
+mod cell_fetch;
+mod churn;
 mod client;
+#[cfg(feature = "crawl")]
+pub mod crawler;
+mod dial_backoff;
+mod dnsaddr;
 mod event_loop;
+mod fetch_stats;
 mod kad_mem_providers;
-#[cfg(not(feature = "kademlia-rocksdb"))]
 mod kad_mem_store;
 mod kad_rocksdb_store;
+mod kad_store;
+mod mdns_toggle;
+mod parallelization;
+mod port_mapping;
+mod query_stats;
+mod rate_limit;
+mod record_snapshot;
+mod record_validation;
+pub mod record_verification;
+mod routing_table;
+mod security;
+mod signed_record;
 
-use crate::types::{LibP2PConfig, SecretKey};
+use super::rpc;
+use crate::{
+	data::{Database, FinalitySyncCheckpoint, FinalitySyncCheckpointKey, VerifiedHeaderKey},
+	telemetry::block_span,
+	types::{BlockRange, FinalityCheckpointConfig, LibP2PConfig, RetryConfig, SecretKey},
+};
+pub use announcements::{AnnouncedHeaders, BlockAnnouncement};
+pub use bandwidth::{BandwidthStats, Traffic};
+pub use churn::{PeerChurnReport, PeerChurnStats};
 pub use client::Client;
+pub use dial_backoff::DialBackoffConfig;
+pub use dnsaddr::{is_dns_address, resolve_bootstraps};
 pub use event_loop::EventLoop;
+pub use fetch_stats::{DHTFetchStats, GetFailures};
 pub use kad_mem_providers::ProvidersConfig;
-#[cfg(not(feature = "kademlia-rocksdb"))]
 pub use kad_mem_store::MemoryStoreConfig;
 pub use kad_rocksdb_store::ExpirationCompactionFilterFactory;
 pub use kad_rocksdb_store::RocksDBStoreConfig;
+pub use parallelization::ParallelizationStats;
+pub use port_mapping::{PortMapping, PortMappingProtocol};
+pub use query_stats::{DHTQueryStats, Histogram, QueryKindStats};
+pub use record_validation::RecordValidationConfig;
+pub use routing_table::RoutingTableEntry;
 
-use self::{client::BlockStat, event_loop::ConnectionEstablishedInfo};
+use self::{
+	bandwidth::Bandwidth,
+	churn::PeerChurn,
+	client::BlockStat,
+	event_loop::{ConnectionEstablishedInfo, ShutdownRequest},
+	security::SecurityUpgrade,
+};
 use libp2p_allow_block_list as allow_block_list;
 
+/// Reason of a failed DHT PUT operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DHTPutError {
+	/// Record was not stored on the required number of peers
+	QuorumFailed,
+	/// Query timed out before the record was stored on the required number of peers
+	Timeout,
+	/// Query was dropped before completion (e.g. on shutdown)
+	Dropped,
+	/// Record was rejected by the local store (e.g. when the store is full)
+	Store,
+}
+
+/// Kademlia record store statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StoreStats {
+	/// Number of stored records
+	pub records: usize,
+	/// Total size of stored record values, in bytes
+	pub bytes: usize,
+	/// Number of records evicted to make room for the new ones
+	pub evicted: u64,
+}
+
+/// DHT PUT statistics of a block
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockPutStats {
+	pub total_count: usize,
+	pub success_count: usize,
+	pub error_count: usize,
+	/// Number of records without the PUT result, which were dropped from the stats on timeout if completed
+	pub remaining_count: usize,
+	/// Time since the first PUT of the block until the completion
+	pub duration: Duration,
+	pub is_completed: bool,
+}
+
+impl BlockPutStats {
+	pub fn success_ratio(&self) -> f64 {
+		if self.total_count == 0 {
+			return 0.0;
+		}
+		self.success_count as f64 / self.total_count as f64
+	}
+}
+
+type PutResultSender = oneshot::Sender<std::result::Result<(), DHTPutError>>;
+
+/// DHT PUT query, record is kept so the query can be retried on failure
+#[derive(Debug)]
+pub struct PutQuery {
+	record: Record,
+	quorum: Quorum,
+	result_sender: Option<PutResultSender>,
+	/// Number of already made retries
+	retries: usize,
+	/// Republished records are not counted in the block PUT stats
+	is_republish: bool,
+}
+
+impl PutQuery {
+	pub fn key(&self) -> &kad::RecordKey {
+		&self.record.key
+	}
+
+	pub fn is_republish(&self) -> bool {
+		self.is_republish
+	}
+
+	pub fn send_result(self, result: std::result::Result<(), DHTPutError>) {
+		if let Some(result_sender) = self.result_sender {
+			_ = result_sender.send(result);
+		}
+	}
+}
+
+/// DHT PUT records queued until the number of PUT queries in flight drops below the limit,
+/// and failed PUT queries waiting for a retry
+pub struct PendingPuts {
+	queue: VecDeque<PutQuery>,
+	/// Failed queries by the retry time
+	retries: BTreeMap<Instant, Vec<PutQuery>>,
+	/// Maximum number of PUT queries in flight, not limited if not set
+	in_flight_limit: Option<usize>,
+	/// Delays between the retries of failed queries, queries are not retried if not set
+	retry_config: Option<RetryConfig>,
+	/// Keys of the records rejected by the local store, not yet counted in the block stats
+	rejected: Vec<kad::RecordKey>,
+}
+
+impl PendingPuts {
+	pub fn new(in_flight_limit: Option<usize>, retry_config: Option<RetryConfig>) -> Self {
+		Self {
+			queue: VecDeque::new(),
+			retries: BTreeMap::new(),
+			in_flight_limit,
+			retry_config,
+			rejected: vec![],
+		}
+	}
+
+	pub fn push(&mut self, record: Record, quorum: Quorum, result_sender: Option<PutResultSender>) {
+		self.queue.push_back(PutQuery {
+			record,
+			quorum,
+			result_sender,
+			retries: 0,
+			is_republish: false,
+		});
+	}
+
+	/// Queues the locally held record for republishing
+	pub fn push_republish(&mut self, record: Record) {
+		self.queue.push_back(PutQuery {
+			record,
+			quorum: Quorum::One,
+			result_sender: None,
+			retries: 0,
+			is_republish: true,
+		});
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.queue.is_empty() && self.retries.is_empty()
+	}
+
+	/// Schedules retry of the failed query with the next backoff delay.
+	/// Query is returned back if there are no retries left.
+	pub fn retry(&mut self, mut query: PutQuery) -> Option<PutQuery> {
+		let Some(delay) = self
+			.retry_config
+			.clone()
+			.and_then(|retry_config| retry_config.into_iter().nth(query.retries))
+		else {
+			return Some(query);
+		};
+		query.retries += 1;
+		self.retries
+			.entry(Instant::now() + delay)
+			.or_default()
+			.push(query);
+		None
+	}
+
+	/// Time of the earliest scheduled retry
+	pub fn next_retry(&self) -> Option<Instant> {
+		self.retries.keys().next().copied()
+	}
+
+	/// Takes keys of the records rejected by the local store since the last call
+	pub fn take_rejected(&mut self) -> Vec<kad::RecordKey> {
+		std::mem::take(&mut self.rejected)
+	}
+
+	/// Moves queries due for retry to the queue
+	pub fn queue_due_retries(&mut self) {
+		let pending = self.retries.split_off(&Instant::now());
+		let due = std::mem::replace(&mut self.retries, pending);
+		self.queue.extend(due.into_values().flatten());
+	}
+
+	/// Starts queued PUT queries until the in-flight limit is reached
+	pub fn start(
+		&mut self,
+		kademlia: &mut kad::Behaviour<Store>,
+		pending_kad_queries: &mut HashMap<QueryId, QueryChannel>,
+	) {
+		let mut in_flight = pending_kad_queries
+			.values()
+			.filter(|channel| matches!(channel, QueryChannel::PutRecord(_)))
+			.count();
+
+		while !self
+			.in_flight_limit
+			.is_some_and(|in_flight_limit| in_flight >= in_flight_limit)
+		{
+			let Some(query) = self.queue.pop_front() else {
+				break;
+			};
+			match kademlia.put_record(query.record.clone(), query.quorum) {
+				Ok(query_id) => {
+					pending_kad_queries.insert(query_id, QueryChannel::PutRecord(query));
+					in_flight += 1;
+				},
+				Err(error) => {
+					error!("Unable to perform Kademlia PUT operation: {error}");
+					self.rejected.push(query.key().clone());
+					query.send_result(Err(DHTPutError::Store));
+				},
+			}
+		}
+	}
+}
+
+/// Result of the DHT GET query, with the query duration
+type GetRecordResult = (Result<PeerRecord>, Duration);
+
+type GetRecordsResult = Result<Vec<GetRecordResult>>;
+
+/// Results of the batched DHT GET queries, sent back at once when all queries are finished
+#[derive(Debug)]
+pub struct GetRecordBatch {
+	results: Vec<Option<GetRecordResult>>,
+	remaining: usize,
+	started_at: Instant,
+	response_sender: Option<oneshot::Sender<GetRecordsResult>>,
+}
+
+impl GetRecordBatch {
+	fn new(size: usize, response_sender: oneshot::Sender<GetRecordsResult>) -> Self {
+		Self {
+			results: (0..size).map(|_| None).collect(),
+			remaining: size,
+			started_at: Instant::now(),
+			response_sender: Some(response_sender),
+		}
+	}
+
+	fn set(&mut self, index: usize, result: Result<PeerRecord>) {
+		let result = (result, self.started_at.elapsed());
+		if self.results[index].replace(result).is_none() {
+			self.remaining -= 1;
+		}
+		if self.remaining > 0 {
+			return;
+		}
+		if let Some(response_sender) = self.response_sender.take() {
+			let results = self.results.drain(..).flatten().collect();
+			_ = response_sender.send(Ok(results));
+		}
+	}
+
+	fn is_closed(&self) -> bool {
+		self.response_sender
+			.as_ref()
+			.map_or(true, oneshot::Sender::is_closed)
+	}
+}
+
+/// Receiver of the DHT GET query result
+#[derive(Debug)]
+pub enum GetRecordSender {
+	Single(oneshot::Sender<Result<PeerRecord>>),
+	/// Query is a part of the batch, at the given index
+	Batch {
+		batch: Arc<Mutex<GetRecordBatch>>,
+		index: usize,
+	},
+}
+
+impl GetRecordSender {
+	fn send(self, result: Result<PeerRecord>) {
+		match self {
+			GetRecordSender::Single(response_sender) => _ = response_sender.send(result),
+			GetRecordSender::Batch { batch, index } => batch
+				.lock()
+				.expect("GET record batch lock is poisoned")
+				.set(index, result),
+		}
+	}
+
+	fn is_closed(&self) -> bool {
+		match self {
+			GetRecordSender::Single(response_sender) => response_sender.is_closed(),
+			GetRecordSender::Batch { batch, .. } => batch
+				.lock()
+				.expect("GET record batch lock is poisoned")
+				.is_closed(),
+		}
+	}
+}
+
 #[derive(Debug)]
 pub enum QueryChannel {
-	GetRecord(oneshot::Sender<Result<PeerRecord>>),
-	PutRecord,
+	GetRecord {
+		response_sender: GetRecordSender,
+		quorum: Quorum,
+		/// Number of records found so far
+		found: usize,
+		/// Records from the local store are not counted, so the record is retrieved from remote peers
+		remote_only: bool,
+	},
+	PutRecord(PutQuery),
 	Bootstrap(oneshot::Sender<Result<()>>),
+	GetClosestPeers(oneshot::Sender<Result<Vec<(PeerId, Vec<Multiaddr>)>>>),
+	GetProviders(oneshot::Sender<Result<HashSet<PeerId>>>),
+}
+
+impl QueryChannel {
+	/// GET query whose caller is no longer waiting for the result, so the query can be cancelled
+	fn is_abandoned(&self) -> bool {
+		matches!(self, QueryChannel::GetRecord { response_sender, .. } if response_sender.is_closed())
+	}
 }
 
 pub struct EventLoopEntries<'a> {
 	swarm: &'a mut Swarm<Behaviour>,
 	pending_kad_queries: &'a mut HashMap<QueryId, QueryChannel>,
+	pending_kad_puts: &'a mut PendingPuts,
 	pending_swarm_events:
 		&'a mut HashMap<PeerId, oneshot::Sender<Result<ConnectionEstablishedInfo>>>,
 	/// <block_num, (total_cells, result_cell_counter, time_stat)>
 	active_blocks: &'a mut HashMap<u32, BlockStat>,
+	completed_blocks: &'a LruCache<u32, BlockPutStats>,
 	kad_mode: &'a mut Mode,
+	is_kad_mode_pinned: &'a mut bool,
+	listeners: &'a mut HashMap<ListenerId, ListenerInfo>,
+	pending_cell_fetches:
+		&'a mut HashMap<OutboundRequestId, oneshot::Sender<Result<cell_fetch::Response>>>,
+	block_announcement_sender: &'a broadcast::Sender<BlockAnnouncement>,
+	blocked_peers: &'a mut HashSet<PeerId>,
+	shutdown_request: &'a mut Option<ShutdownRequest>,
+	bandwidth: &'a Bandwidth,
+	peer_latencies: &'a HashMap<PeerId, PeerLatency>,
+	peer_identities: &'a LruCache<PeerId, PeerIdentity>,
+	holepunch_stats: &'a HolePunchStats,
+	query_stats: &'a DHTQueryStats,
+	peer_churn: &'a PeerChurn,
+	nat_servers: &'a HashSet<PeerId>,
+	port_mapping: &'a Option<PortMapping>,
 }
 
 impl<'a> EventLoopEntries<'a> {
 	pub fn new(
 		swarm: &'a mut Swarm<Behaviour>,
 		pending_kad_queries: &'a mut HashMap<QueryId, QueryChannel>,
+		pending_kad_puts: &'a mut PendingPuts,
 		pending_swarm_events: &'a mut HashMap<
 			PeerId,
 			oneshot::Sender<Result<ConnectionEstablishedInfo>>,
 		>,
 		active_blocks: &'a mut HashMap<u32, BlockStat>,
+		completed_blocks: &'a LruCache<u32, BlockPutStats>,
 		kad_mode: &'a mut Mode,
+		is_kad_mode_pinned: &'a mut bool,
+		listeners: &'a mut HashMap<ListenerId, ListenerInfo>,
+		pending_cell_fetches: &'a mut HashMap<
+			OutboundRequestId,
+			oneshot::Sender<Result<cell_fetch::Response>>,
+		>,
+		block_announcement_sender: &'a broadcast::Sender<BlockAnnouncement>,
+		blocked_peers: &'a mut HashSet<PeerId>,
+		shutdown_request: &'a mut Option<ShutdownRequest>,
+		bandwidth: &'a Bandwidth,
+		peer_latencies: &'a HashMap<PeerId, PeerLatency>,
+		peer_identities: &'a LruCache<PeerId, PeerIdentity>,
+		holepunch_stats: &'a HolePunchStats,
+		query_stats: &'a DHTQueryStats,
+		peer_churn: &'a PeerChurn,
+		nat_servers: &'a HashSet<PeerId>,
+		port_mapping: &'a Option<PortMapping>,
 	) -> Self {
 		Self {
 			swarm,
 			pending_kad_queries,
+			pending_kad_puts,
 			pending_swarm_events,
 			active_blocks,
+			completed_blocks,
 			kad_mode,
+			is_kad_mode_pinned,
+			listeners,
+			pending_cell_fetches,
+			block_announcement_sender,
+			blocked_peers,
+			shutdown_request,
+			bandwidth,
+			peer_latencies,
+			peer_identities,
+			holepunch_stats,
+			query_stats,
+			peer_churn,
+			nat_servers,
+			port_mapping,
 		}
 	}
 
@@ -78,8 +487,8 @@ impl<'a> EventLoopEntries<'a> {
 		self.swarm.local_peer_id()
 	}
 
-	pub fn listeners(&self) -> Vec<String> {
-		self.swarm.listeners().map(ToString::to_string).collect()
+	pub fn listeners(&self) -> Vec<ListenerInfo> {
+		self.listeners.values().cloned().collect()
 	}
 
 	pub fn external_address(&self) -> Vec<String> {
@@ -93,6 +502,32 @@ impl<'a> EventLoopEntries<'a> {
 		self.pending_kad_queries.insert(query_id, result_sender);
 	}
 
+	/// Queues locally held records for republishing and starts the queries allowed by the in-flight limit
+	pub fn insert_republish_puts(&mut self, records: Vec<Record>) {
+		for record in records {
+			self.pending_kad_puts.push_republish(record);
+		}
+		self.pending_kad_puts.start(
+			&mut self.swarm.behaviour_mut().kademlia,
+			self.pending_kad_queries,
+		);
+	}
+
+	/// Queues records for DHT PUT and starts the queries allowed by the in-flight limit
+	pub fn insert_puts(
+		&mut self,
+		records: impl IntoIterator<Item = (Record, Option<PutResultSender>)>,
+		quorum: Quorum,
+	) {
+		for (record, result_sender) in records {
+			self.pending_kad_puts.push(record, quorum, result_sender);
+		}
+		self.pending_kad_puts.start(
+			&mut self.swarm.behaviour_mut().kademlia,
+			self.pending_kad_queries,
+		);
+	}
+
 	pub fn insert_swarm_event(
 		&mut self,
 		peer_id: PeerId,
@@ -101,6 +536,102 @@ impl<'a> EventLoopEntries<'a> {
 		self.pending_swarm_events.insert(peer_id, result_sender);
 	}
 
+	pub fn insert_cell_fetch(
+		&mut self,
+		request_id: OutboundRequestId,
+		result_sender: oneshot::Sender<Result<cell_fetch::Response>>,
+	) {
+		self.pending_cell_fetches.insert(request_id, result_sender);
+	}
+
+	pub fn subscribe_block_announcements(&self) -> broadcast::Receiver<BlockAnnouncement> {
+		self.block_announcement_sender.subscribe()
+	}
+
+	pub fn block_peer(&mut self, peer_id: PeerId) {
+		self.swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+		self.blocked_peers.insert(peer_id);
+	}
+
+	pub fn unblock_peer(&mut self, peer_id: PeerId) {
+		self.swarm
+			.behaviour_mut()
+			.blocked_peers
+			.unblock_peer(peer_id);
+		self.blocked_peers.remove(&peer_id);
+	}
+
+	pub fn blocked_peers(&self) -> Vec<PeerId> {
+		self.blocked_peers.iter().cloned().collect()
+	}
+
+	pub fn bandwidth_stats(&self) -> BandwidthStats {
+		self.bandwidth.stats()
+	}
+
+	pub fn holepunch_stats(&self) -> HolePunchStats {
+		self.holepunch_stats.clone()
+	}
+
+	pub fn query_stats(&self) -> DHTQueryStats {
+		self.query_stats.clone()
+	}
+
+	/// Returns churn statistics of the routing table peers
+	pub fn peer_churn_report(&mut self) -> PeerChurnReport {
+		let peer_ids: Vec<PeerId> = self
+			.swarm
+			.behaviour_mut()
+			.kademlia
+			.kbuckets()
+			.flat_map(|bucket| {
+				bucket
+					.iter()
+					.map(|entry| *entry.node.key.preimage())
+					.collect::<Vec<_>>()
+			})
+			.collect();
+		self.peer_churn
+			.report(&peer_ids, tokio::time::Instant::now())
+	}
+
+	pub fn block_put_stats(&self, block_num: u32) -> Option<BlockPutStats> {
+		self.active_blocks
+			.get(&block_num)
+			.map(|block| block.put_stats(false))
+			.or_else(|| self.completed_blocks.peek(&block_num).cloned())
+	}
+
+	pub fn port_mapping(&self) -> Option<PortMapping> {
+		self.port_mapping.clone()
+	}
+
+	pub fn nat_info(&self) -> NatInfo {
+		NatInfo {
+			status: self.swarm.behaviour().auto_nat.nat_status(),
+			confirmed_by: self.nat_servers.iter().cloned().collect(),
+		}
+	}
+
+	/// Returns connected peers with their ping round-trip times, if any
+	pub fn connected_peers_with_latency(&self) -> Vec<(PeerId, Option<PeerLatency>)> {
+		self.swarm
+			.connected_peers()
+			.map(|peer_id| (*peer_id, self.peer_latencies.get(peer_id).copied()))
+			.collect()
+	}
+
+	/// Requests the graceful shutdown, or waits for the one already in progress
+	pub fn request_shutdown(&mut self, response_sender: oneshot::Sender<Result<()>>) {
+		self.shutdown_request
+			.get_or_insert_with(|| ShutdownRequest {
+				requested_at: tokio::time::Instant::now(),
+				response_senders: vec![],
+			})
+			.response_senders
+			.push(response_sender);
+	}
+
 	pub fn behavior_mut(&mut self) -> &mut Behaviour {
 		self.swarm.behaviour_mut()
 	}
@@ -110,19 +641,119 @@ impl<'a> EventLoopEntries<'a> {
 	}
 }
 
+/// Gossipsub topic used for announcing new finalized block headers
+pub const BLOCK_ANNOUNCEMENTS_TOPIC: &str = "avail/blocks";
+
+/// Maximum size of the encoded block announcement
+const MAX_BLOCK_ANNOUNCEMENT_SIZE: usize = 512 * 1024;
+
+pub fn block_announcements_topic() -> gossipsub::IdentTopic {
+	gossipsub::IdentTopic::new(BLOCK_ANNOUNCEMENTS_TOPIC)
+}
+
 pub trait Command {
 	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report>;
 	fn abort(&mut self, error: Report);
+
+	/// Name of the command type, used in the command traces and metrics
+	fn name(&self) -> &'static str {
+		let type_name = std::any::type_name::<Self>();
+		type_name.rsplit("::").next().unwrap_or(type_name)
+	}
+
+	/// Commands are rejected while the event loop is shutting down, unless allowed
+	fn is_allowed_on_shutdown(&self) -> bool {
+		false
+	}
 }
 
 type SendableCommand = Box<dyn Command + Send + Sync>;
-type CommandSender = mpsc::UnboundedSender<SendableCommand>;
-type CommandReceiver = mpsc::UnboundedReceiver<SendableCommand>;
 
-#[cfg(not(feature = "kademlia-rocksdb"))]
-type Store = kad_mem_store::MemoryStore;
-#[cfg(feature = "kademlia-rocksdb")]
-type Store = kad_rocksdb_store::RocksDBStore;
+static NEXT_COMMAND_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Priority of the command in the event loop queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandPriority {
+	/// Interactive operations, like cell fetches for sampling of the latest block
+	High,
+	/// Other operations, including bulk DHT PUTs
+	Normal,
+}
+
+/// Command with the time it was sent at, for measuring how long it waits for the event loop
+pub struct TracedCommand {
+	id: u64,
+	sent_at: Instant,
+	priority: CommandPriority,
+	command: SendableCommand,
+}
+
+impl TracedCommand {
+	fn new(command: SendableCommand) -> Self {
+		Self::with_priority(command, CommandPriority::Normal)
+	}
+
+	fn with_priority(command: SendableCommand, priority: CommandPriority) -> Self {
+		Self {
+			id: NEXT_COMMAND_ID.fetch_add(1, Ordering::Relaxed),
+			sent_at: Instant::now(),
+			priority,
+			command,
+		}
+	}
+}
+
+/// Sender of the event loop commands, with a channel per priority,
+/// so the high priority commands are not queued behind the bulk operations
+#[derive(Clone)]
+pub struct CommandSender {
+	high: mpsc::UnboundedSender<TracedCommand>,
+	normal: mpsc::UnboundedSender<TracedCommand>,
+}
+
+impl CommandSender {
+	fn send(&self, command: TracedCommand) -> Result<(), mpsc::error::SendError<TracedCommand>> {
+		match command.priority {
+			CommandPriority::High => self.high.send(command),
+			CommandPriority::Normal => self.normal.send(command),
+		}
+	}
+}
+
+/// Receiver of the event loop commands, high priority commands are received first
+pub struct CommandReceiver {
+	high: mpsc::UnboundedReceiver<TracedCommand>,
+	normal: mpsc::UnboundedReceiver<TracedCommand>,
+}
+
+impl CommandReceiver {
+	/// Receives the next command, returns `None` once all senders are dropped
+	async fn recv(&mut self) -> Option<TracedCommand> {
+		tokio::select! {
+			biased;
+			Some(command) = self.high.recv() => Some(command),
+			Some(command) = self.normal.recv() => Some(command),
+			else => None,
+		}
+	}
+}
+
+/// Creates the channel of the event loop commands
+pub fn command_channel() -> (CommandSender, CommandReceiver) {
+	let (high_sender, high_receiver) = mpsc::unbounded_channel();
+	let (normal_sender, normal_receiver) = mpsc::unbounded_channel();
+	let sender = CommandSender {
+		high: high_sender,
+		normal: normal_sender,
+	};
+	let receiver = CommandReceiver {
+		high: high_receiver,
+		normal: normal_receiver,
+	};
+	(sender, receiver)
+}
+
+use kad_store::Store;
 
 // Behaviour struct is used to derive delegated Libp2p behaviour implementation
 #[derive(NetworkBehaviour)]
@@ -131,12 +762,16 @@ pub struct Behaviour {
 	kademlia: kad::Behaviour<Store>,
 	identify: identify::Behaviour,
 	ping: ping::Behaviour,
-	mdns: mdns::tokio::Behaviour,
+	mdns: mdns_toggle::Behaviour,
 	auto_nat: autonat::Behaviour,
 	relay_client: relay::client::Behaviour,
 	dcutr: dcutr::Behaviour,
 	upnp: upnp::tokio::Behaviour,
 	blocked_peers: allow_block_list::Behaviour<BlockedPeers>,
+	connection_limits: connection_limits::Behaviour,
+	dial_backoff: dial_backoff::Behaviour,
+	cell_fetch: request_response::Behaviour<cell_fetch::Codec>,
+	gossipsub: gossipsub::Behaviour,
 }
 
 #[derive(Debug)]
@@ -144,15 +779,143 @@ pub struct PeerInfo {
 	pub peer_id: String,
 	pub operation_mode: String,
 	pub peer_multiaddr: Option<Vec<String>>,
-	pub local_listeners: Vec<String>,
+	pub local_listeners: Vec<ListenerInfo>,
 	pub external_listeners: Vec<String>,
 	pub public_listeners: Vec<String>,
+	/// External address mapped on the gateway with UPnP, PCP or NAT-PMP
+	pub port_mapping: Option<PortMapping>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenerStatus {
+	/// Listener is started, but the transport has not reported any listen address yet
+	Pending,
+	Listening,
+	/// Listener is closed by the transport, with the error if it was closed on failure
+	Closed(Option<String>),
+}
+
+/// Listener started on the requested address, with the addresses reported by the transport
+#[derive(Debug, Clone)]
+pub struct ListenerInfo {
+	pub address: Multiaddr,
+	pub listen_addresses: Vec<Multiaddr>,
+	pub status: ListenerStatus,
+}
+
+impl ListenerInfo {
+	fn new(address: Multiaddr) -> Self {
+		Self {
+			address,
+			listen_addresses: vec![],
+			status: ListenerStatus::Pending,
+		}
+	}
+}
+
+/// Notable network events, broadcast to the subscribers of the event loop
+#[derive(Debug, Clone)]
+pub enum P2pEvent {
+	/// First connection with the peer is established
+	PeerConnected {
+		peer_id: PeerId,
+		address: Multiaddr,
+	},
+	/// Last connection with the peer is closed
+	PeerDisconnected {
+		peer_id: PeerId,
+	},
+	ExternalAddressConfirmed {
+		address: Multiaddr,
+	},
+	KademliaModeChanged {
+		mode: Mode,
+	},
+	/// All DHT PUT queries of the block cells are finished
+	PutCompleted {
+		block_number: u32,
+		success_count: usize,
+		total_count: usize,
+		duration: Duration,
+	},
+	/// DHT GET query is finished
+	GetCompleted {
+		is_success: bool,
+		duration: Option<Duration>,
+	},
+}
+
+/// NAT status detected by AutoNAT, with the servers which confirmed the public address
+#[derive(Debug, Clone)]
+pub struct NatInfo {
+	pub status: autonat::NatStatus,
+	pub confirmed_by: Vec<PeerId>,
+}
+
+/// Weight of the latest round-trip time in the average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Ping round-trip times of a connected peer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeerLatency {
+	pub latest: Duration,
+	/// Exponentially weighted moving average of the round-trip times
+	pub average: Duration,
+}
+
+impl PeerLatency {
+	fn new(rtt: Duration) -> Self {
+		Self {
+			latest: rtt,
+			average: rtt,
+		}
+	}
+
+	fn update(&mut self, rtt: Duration) {
+		self.latest = rtt;
+		self.average =
+			self.average.mul_f64(1.0 - LATENCY_SMOOTHING) + rtt.mul_f64(LATENCY_SMOOTHING);
+	}
+}
+
+/// Outcomes of the DCUtR direct connection upgrades of the relayed connections
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HolePunchStats {
+	pub attempts: u64,
+	pub successes: u64,
+	/// Failed upgrades after which the peer stayed connected over the relay
+	pub relay_fallbacks: u64,
+}
+
+/// Peer info received over the identify protocol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerIdentity {
+	pub agent_version: String,
+	pub protocol_version: String,
+	pub protocols: Vec<String>,
+	/// Address of the local node, as observed by the peer
+	pub observed_address: Multiaddr,
+}
+
+impl From<&identify::Info> for PeerIdentity {
+	fn from(info: &identify::Info) -> Self {
+		Self {
+			agent_version: info.agent_version.clone(),
+			protocol_version: info.protocol_version.clone(),
+			protocols: info.protocols.iter().map(ToString::to_string).collect(),
+			observed_address: info.observed_addr.clone(),
+		}
+	}
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MultiAddressInfo {
 	multiaddresses: Vec<String>,
 	peer_id: String,
+	/// Number of consecutive failed dials to the peer
+	dial_failures: u32,
+	/// Identify info, if the peer was identified recently
+	identity: Option<PeerIdentity>,
 }
 
 fn generate_config(config: libp2p::swarm::Config, cfg: &LibP2PConfig) -> libp2p::swarm::Config {
@@ -164,11 +927,32 @@ fn generate_config(config: libp2p::swarm::Config, cfg: &LibP2PConfig) -> libp2p:
 		.with_per_connection_event_buffer_size(cfg.per_connection_event_buffer_size)
 }
 
+/// Minimal yamux receive window size of a stream, smaller windows are rejected by the yamux configuration
+pub const MIN_YAMUX_RECEIVE_WINDOW_SIZE: u32 = 256 * 1024;
+
+// Receive window and buffer size settings are deprecated in libp2p, but there is no replacement yet
+#[allow(deprecated)]
+fn yamux_config(cfg: &LibP2PConfig) -> yamux::Config {
+	let mut config = yamux::Config::default();
+	if let Some(receive_window_size) = cfg.yamux_receive_window_size {
+		// yamux panics on the smaller windows
+		config.set_receive_window_size(receive_window_size.max(MIN_YAMUX_RECEIVE_WINDOW_SIZE));
+	}
+	if let Some(max_buffer_size) = cfg.yamux_max_buffer_size {
+		config.set_max_buffer_size(max_buffer_size);
+	}
+	if let Some(max_num_streams) = cfg.yamux_max_num_streams {
+		config.set_max_num_streams(max_num_streams);
+	}
+	config
+}
+
 async fn build_swarm(
 	cfg: &LibP2PConfig,
 	id_keys: &libp2p::identity::Keypair,
 	kad_store: Store,
 	is_ws_transport: bool,
+	bandwidth: &Bandwidth,
 ) -> Result<Swarm<Behaviour>> {
 	// create Identify Protocol Config
 	let identify_cfg =
@@ -185,6 +969,28 @@ async fn build_swarm(
 		..Default::default()
 	};
 
+	// create Cell Fetch Protocol Config
+	let cell_fetch_cfg =
+		request_response::Config::default().with_request_timeout(cfg.kademlia.query_timeout);
+
+	// create Connection Limits Config
+	let connection_limits_cfg = connection_limits::ConnectionLimits::default()
+		.with_max_established_incoming(cfg.max_established_incoming)
+		.with_max_established_outgoing(cfg.max_established_outgoing)
+		.with_max_pending_outgoing(cfg.max_pending_outgoing);
+
+	// create Gossipsub Config
+	// Message ID is derived from the content, so the same header announced by different peers is deduplicated
+	// Announcements carry the justifications, which are signed by each validator
+	let gossipsub_cfg = gossipsub::ConfigBuilder::default()
+		.max_transmit_size(MAX_BLOCK_ANNOUNCEMENT_SIZE)
+		.message_id_fn(|message: &gossipsub::Message| {
+			let mut hasher = DefaultHasher::new();
+			message.data.hash(&mut hasher);
+			gossipsub::MessageId::from(hasher.finish().to_string())
+		})
+		.build()?;
+
 	// build the Swarm, connecting the lower transport logic with the
 	// higher layer network behaviour logic
 	let tokio_swarm = SwarmBuilder::with_existing_identity(id_keys.clone()).with_tokio();
@@ -199,27 +1005,92 @@ async fn build_swarm(
 			dcutr: dcutr::Behaviour::new(key.public().to_peer_id()),
 			kademlia: kad::Behaviour::with_config(key.public().to_peer_id(), kad_store, cfg.into()),
 			auto_nat: autonat::Behaviour::new(key.public().to_peer_id(), autonat_cfg),
-			mdns: mdns::Behaviour::new(mdns::Config::default(), key.public().to_peer_id())?,
+			mdns: mdns_toggle::Behaviour::new(
+				mdns::Config::default(),
+				key.public().to_peer_id(),
+				cfg.mdns_enabled,
+			)?,
 			upnp: upnp::tokio::Behaviour::default(),
 			blocked_peers: allow_block_list::Behaviour::default(),
+			connection_limits: connection_limits::Behaviour::new(connection_limits_cfg),
+			dial_backoff: dial_backoff::Behaviour::new(
+				cfg.dial_backoff.clone(),
+				cfg.relays.iter().map(|(peer_id, _)| *peer_id),
+			),
+			cell_fetch: request_response::Behaviour::new(
+				[(cell_fetch::PROTOCOL_NAME, ProtocolSupport::Full)],
+				cell_fetch_cfg,
+			),
+			gossipsub: gossipsub::Behaviour::new(
+				gossipsub::MessageAuthenticity::Signed(key.clone()),
+				gossipsub_cfg,
+			)?,
 		})
 	};
 
-	if is_ws_transport {
+	let tcp_cfg = tcp::Config::default().port_reuse(false).nodelay(false);
+
+	if let Some(psk) = &cfg.pnet_psk {
+		if is_ws_transport {
+			return Err(eyre!(
+				"Private network is supported only with TCP transport"
+			));
+		}
+
+		let psk = pre_shared_key(psk)?;
+		info!(
+			"Private network enabled, PSK fingerprint: {}",
+			psk.fingerprint()
+		);
+
+		swarm = tokio_swarm
+			.with_other_transport(|key| {
+				let security_upgrade = SecurityUpgrade::new(key, cfg.transport_security)?;
+				Ok::<_, Box<dyn std::error::Error + Send + Sync>>(
+					tcp::tokio::Transport::new(tcp_cfg)
+						.and_then(move |socket, _| PnetConfig::new(psk).handshake(socket))
+						.upgrade(upgrade::Version::V1Lazy)
+						.authenticate(security_upgrade)
+						.multiplex(yamux_config(cfg))
+						.map(bandwidth.meter()),
+				)
+			})?
+			.with_dns()?
+			.with_relay_client(noise::Config::new, yamux::Config::default)?
+			.with_behaviour(behaviour)?
+			.with_swarm_config(|c| generate_config(c, cfg))
+			.build();
+	} else if is_ws_transport {
 		swarm = tokio_swarm
-			.with_websocket(noise::Config::new, yamux::Config::default)
-			.await?
+			.with_other_transport(|key| {
+				let security_upgrade = SecurityUpgrade::new(key, cfg.transport_security)?;
+				let tcp_transport = dns::tokio::Transport::system(tcp::tokio::Transport::new(
+					tcp::Config::default(),
+				))?;
+				Ok::<_, Box<dyn std::error::Error + Send + Sync>>(
+					websocket::WsConfig::new(tcp_transport)
+						.upgrade(upgrade::Version::V1Lazy)
+						.authenticate(security_upgrade)
+						.multiplex(yamux_config(cfg))
+						.map(bandwidth.meter()),
+				)
+			})?
 			.with_relay_client(noise::Config::new, yamux::Config::default)?
 			.with_behaviour(behaviour)?
 			.with_swarm_config(|c| generate_config(c, cfg))
 			.build();
 	} else {
 		swarm = tokio_swarm
-			.with_tcp(
-				tcp::Config::default().port_reuse(false).nodelay(false),
-				noise::Config::new,
-				yamux::Config::default,
-			)?
+			.with_other_transport(|key| {
+				let security_upgrade = SecurityUpgrade::new(key, cfg.transport_security)?;
+				Ok::<_, Box<dyn std::error::Error + Send + Sync>>(
+					tcp::tokio::Transport::new(tcp_cfg)
+						.upgrade(upgrade::Version::V1Lazy)
+						.authenticate(security_upgrade)
+						.multiplex(yamux_config(cfg))
+						.map(bandwidth.meter()),
+				)
+			})?
 			.with_dns()?
 			.with_relay_client(noise::Config::new, yamux::Config::default)?
 			.with_behaviour(behaviour)?
@@ -229,6 +1100,11 @@ async fn build_swarm(
 
 	info!("Local peerID: {}", swarm.local_peer_id());
 
+	for address in &cfg.external_addresses {
+		info!("Adding configured external address: {address}");
+		swarm.add_external_address(address.clone());
+	}
+
 	// Setting the mode this way disables automatic mode changes.
 	//
 	// Because the identify protocol doesn't allow us to change
@@ -239,9 +1115,149 @@ async fn build_swarm(
 		.kademlia
 		.set_mode(Some(cfg.kademlia.kademlia_mode.into()));
 
+	swarm
+		.behaviour_mut()
+		.gossipsub
+		.subscribe(&block_announcements_topic())?;
+
 	Ok(swarm)
 }
 
+/// Periodically re-resolves DNS bootstrap addresses and adds the resolved addresses to the routing table,
+/// so the node can reach the bootstrap nodes after their IP addresses are rotated.
+pub async fn refresh_bootstraps(
+	p2p_client: Client,
+	bootstraps: Vec<Multiaddr>,
+	interval: Duration,
+) {
+	if interval.is_zero() || !bootstraps.iter().any(dnsaddr::is_dns_address) {
+		return;
+	}
+	let mut interval = tokio::time::interval_at(Instant::now() + interval, interval);
+	loop {
+		interval.tick().await;
+		for (peer_id, address) in resolve_bootstraps(&bootstraps).await {
+			if let Err(error) = p2p_client.exempt_from_dial_backoff(peer_id).await {
+				debug!("Cannot exempt resolved bootstrap {peer_id} from the dial backoff: {error}");
+			}
+			if let Err(error) = p2p_client.add_address(peer_id, address).await {
+				debug!("Cannot add resolved bootstrap address of {peer_id}: {error}");
+			}
+		}
+	}
+}
+
+/// Announces finalized headers received from the node, with their justifications, to peers subscribed to block announcements.
+pub async fn announce_blocks(
+	p2p_client: Client,
+	mut rpc_event_receiver: broadcast::Receiver<rpc::Event>,
+) {
+	loop {
+		let announcement = match rpc_event_receiver.recv().await {
+			Ok(rpc::Event::HeaderUpdate {
+				header,
+				justification,
+				..
+			}) => BlockAnnouncement {
+				header,
+				justification,
+			},
+			Err(error) => {
+				error!("Cannot receive message: {error}");
+				return;
+			},
+		};
+
+		if let Err(error) = p2p_client.publish_block_announcement(&announcement).await {
+			debug!(
+				block_number = announcement.header.number,
+				"Cannot announce block: {error}"
+			);
+		}
+	}
+}
+
+/// Forwards headers announced by peers as header events, in place of the RPC subscriptions in the DHT-only mode.
+/// Headers are forwarded only if they are finalized by the validator set tracked from the finality checkpoint,
+/// or if they link to the finalized header, and at most `max_gap` blocks ahead of the last verified header.
+pub async fn receive_block_announcements(
+	p2p_client: Client,
+	db: impl Database,
+	event_sender: broadcast::Sender<rpc::Event>,
+	trusted_checkpoint: Option<FinalityCheckpointConfig>,
+	max_gap: u32,
+) -> Result<()> {
+	let mut announced_headers = AnnouncedHeaders::from_checkpoint(
+		db.get(FinalitySyncCheckpointKey),
+		trusted_checkpoint,
+		max_gap,
+	)?;
+	info!(
+		"Verifying announced headers from block {}",
+		announced_headers.last_verified_number()
+	);
+	let announcements = p2p_client.subscribe_block_announcements().await?;
+	futures::pin_mut!(announcements);
+
+	while let Some(announcement) = announcements.next().await {
+		let block_number = announcement.header.number;
+		let verified = match announced_headers.verify(announcement) {
+			Ok(verified) if verified.is_empty() => continue,
+			Ok(verified) => verified,
+			Err(error) => {
+				debug!(block_number, "Announced block is not verified: {error:#}");
+				continue;
+			},
+		};
+
+		let validator_set = announced_headers.validator_set();
+		db.put(
+			FinalitySyncCheckpointKey,
+			FinalitySyncCheckpoint {
+				number: block_number,
+				set_id: validator_set.set_id,
+				validator_set: validator_set.validator_set.clone(),
+			},
+		);
+		let mut verified_header = db
+			.get(VerifiedHeaderKey)
+			.unwrap_or_else(|| BlockRange::init(block_number));
+		verified_header.last = block_number;
+		db.put(VerifiedHeaderKey, verified_header);
+
+		for BlockAnnouncement {
+			header,
+			justification,
+		} in verified
+		{
+			debug!(block_number = header.number, "Received announced block");
+			let span = block_span(header.number);
+			// Sending fails only when there are no subscribers
+			_ = event_sender.send(rpc::Event::HeaderUpdate {
+				header,
+				received_at: Instant::now().into_std(),
+				span,
+				justification,
+			});
+		}
+	}
+
+	Ok(())
+}
+
+// Parses pre-shared key for private network, from either hex encoded key or PSK file
+pub fn pre_shared_key(psk: &str) -> Result<PreSharedKey> {
+	let mut key = [0u8; 32];
+	if hex::decode_to_slice(psk.trim_start_matches("0x"), &mut key).is_ok() {
+		return Ok(PreSharedKey::new(key));
+	}
+
+	fs::read_to_string(psk)
+		.wrap_err("error reading PSK file")?
+		.parse()
+		.wrap_err("error parsing PSK file")
+}
+
 // Keypair function creates identity Keypair for a local node.
 // From such generated keypair it derives multihash identifier of the local peer.
 pub fn keypair(secret_key: &SecretKey) -> Result<identity::Keypair> {
@@ -281,22 +1297,255 @@ pub fn is_global(ip: Ipv4Addr) -> bool {
 		|| ip.is_broadcast())
 }
 
+// Returns [`true`] if the IPv6 address appears to be globally reachable
+// Take from the unstable std::net implementation
+pub fn is_global_ipv6(ip: Ipv6Addr) -> bool {
+	let segments = ip.segments();
+	!(ip.is_unspecified()
+		|| ip.is_loopback()
+		// IPv4-mapped addresses (`::ffff:0:0/96`)
+		|| matches!(segments, [0, 0, 0, 0, 0, 0xffff, _, _])
+		// IPv4-IPv6 translation addresses (`64:ff9b:1::/48`)
+		|| matches!(segments, [0x64, 0xff9b, 1, _, _, _, _, _])
+		// discard-only address block (`100::/64`)
+		|| matches!(segments, [0x100, 0, 0, 0, _, _, _, _])
+		// IETF protocol assignments (`2001::/23`)
+		// AMT (`2001:3::/32`), AS112-v6 (`2001:4:112::/48`) and ORCHIDv2 (`2001:20::/28`)
+		// are documented as globally reachable so they're excluded
+		|| (matches!(segments, [0x2001, b, _, _, _, _, _, _] if b < 0x200)
+			&& !(matches!(segments, [0x2001, 3, _, _, _, _, _, _])
+				|| matches!(segments, [0x2001, 4, 0x112, _, _, _, _, _])
+				|| matches!(segments, [0x2001, 0x20..=0x2f, _, _, _, _, _, _])))
+		// documentation addresses (`2001:db8::/32`)
+		|| matches!(segments, [0x2001, 0xdb8, _, _, _, _, _, _])
+		// unique local addresses (`fc00::/7`)
+		|| (segments[0] & 0xfe00) == 0xfc00
+		// unicast link-local addresses (`fe80::/10`)
+		|| (segments[0] & 0xffc0) == 0xfe80)
+}
+
 // Returns [`true`] if the multi-address IP appears to be globally reachable
 pub fn is_multiaddr_global(address: &Multiaddr) -> bool {
-	address
-		.iter()
-		.any(|protocol| matches!(protocol, libp2p::multiaddr::Protocol::Ip4(ip) if is_global(ip)))
+	address.iter().any(|protocol| match protocol {
+		Protocol::Ip4(ip) => is_global(ip),
+		Protocol::Ip6(ip) => is_global_ipv6(ip),
+		_ => false,
+	})
+}
+
+/// Prepends the network prefix to the DHT record reference
+pub fn prefixed_reference(network_prefix: Option<&str>, reference: String) -> String {
+	match network_prefix {
+		Some(prefix) => format!("{prefix}/{reference}"),
+		None => reference,
+	}
+}
+
+/// Returns [`true`] if the DHT record key belongs to the network with the given prefix.
+/// All keys are accepted if there is no network prefix configured.
+pub fn has_network_prefix(key: &kad::RecordKey, network_prefix: Option<&str>) -> bool {
+	let Some(prefix) = network_prefix else {
+		return true;
+	};
+	key.as_ref()
+		.strip_prefix(prefix.as_bytes())
+		.is_some_and(|reference| reference.first() == Some(&b'/'))
+}
+
+/// Returns the number of peers required to satisfy the quorum.
+/// Majority and all are calculated from the replication factor.
+pub fn quorum_size(quorum: Quorum, replication_factor: NonZeroUsize) -> usize {
+	match quorum {
+		Quorum::One => 1,
+		Quorum::Majority => replication_factor.get() / 2 + 1,
+		Quorum::All => replication_factor.get(),
+		Quorum::N(n) => n.get().min(replication_factor.get()),
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::types::ExponentialConfig;
 	use test_case::test_case;
 
+	fn get_record_channel(response_sender: GetRecordSender) -> QueryChannel {
+		QueryChannel::GetRecord {
+			response_sender,
+			quorum: Quorum::One,
+			found: 0,
+			remote_only: false,
+		}
+	}
+
+	#[test]
+	fn abandoned_queries() {
+		let (sender, receiver) = oneshot::channel();
+		let waiting = get_record_channel(GetRecordSender::Single(sender));
+		let (sender, _) = oneshot::channel();
+		let abandoned = get_record_channel(GetRecordSender::Single(sender));
+		assert!(!waiting.is_abandoned());
+		assert!(abandoned.is_abandoned());
+
+		// Batched queries are abandoned once the batch receiver is dropped
+		let (sender, batch_receiver) = oneshot::channel();
+		let batch = Arc::new(Mutex::new(GetRecordBatch::new(2, sender)));
+		let batched = (0..2)
+			.map(|index| {
+				get_record_channel(GetRecordSender::Batch {
+					batch: batch.clone(),
+					index,
+				})
+			})
+			.collect::<Vec<_>>();
+		assert!(batched.iter().all(|channel| !channel.is_abandoned()));
+		drop(batch_receiver);
+		assert!(batched.iter().all(QueryChannel::is_abandoned));
+
+		// Only GET queries are cancelled
+		let (sender, _) = oneshot::channel();
+		assert!(!QueryChannel::Bootstrap(sender).is_abandoned());
+		drop(receiver);
+		assert!(waiting.is_abandoned());
+	}
+
+	#[test]
+	fn pending_puts_retry() {
+		let retry_config = RetryConfig::Exponential(ExponentialConfig {
+			base: 1,
+			max_delay: 1000,
+			retries: 2,
+		});
+		let mut pending_puts = PendingPuts::new(None, Some(retry_config));
+		let record = Record::new(kad::RecordKey::new(&"1:0:0"), vec![]);
+		pending_puts.push(record, Quorum::One, None);
+
+		let mut query = pending_puts.queue.pop_front().unwrap();
+		for _ in 0..2 {
+			assert!(pending_puts.retry(query).is_none());
+			assert!(pending_puts.next_retry().is_some());
+			query = pending_puts.retries.pop_first().unwrap().1.pop().unwrap();
+		}
+		assert!(pending_puts.retry(query).is_some());
+		assert!(pending_puts.is_empty());
+
+		let mut pending_puts = PendingPuts::new(None, None);
+		pending_puts.push(
+			Record::new(kad::RecordKey::new(&"1:0:1"), vec![]),
+			Quorum::One,
+			None,
+		);
+		let query = pending_puts.queue.pop_front().unwrap();
+		assert!(pending_puts.retry(query).is_some());
+	}
+
+	#[test]
+	fn pending_puts_republish() {
+		let mut pending_puts = PendingPuts::new(Some(1), None);
+		pending_puts.push(
+			Record::new(kad::RecordKey::new(&"1:0:0"), vec![]),
+			Quorum::All,
+			None,
+		);
+		pending_puts.push_republish(Record::new(kad::RecordKey::new(&"1:0:1"), vec![]));
+
+		// republished records are queued behind the pending PUTs
+		let query = pending_puts.queue.pop_front().unwrap();
+		assert!(!query.is_republish());
+		let query = pending_puts.queue.pop_front().unwrap();
+		assert!(query.is_republish());
+		assert_eq!(query.quorum, Quorum::One);
+	}
+
+	struct NoopCommand;
+
+	impl Command for NoopCommand {
+		fn run(&mut self, _: EventLoopEntries) -> Result<(), Report> {
+			Ok(())
+		}
+
+		fn abort(&mut self, _: Report) {}
+	}
+
+	#[test]
+	fn get_record_batch() {
+		let record = |key: &str| PeerRecord {
+			peer: None,
+			record: Record::new(kad::RecordKey::new(&key), vec![]),
+		};
+		let (response_sender, mut response_receiver) = oneshot::channel();
+		let batch = Arc::new(Mutex::new(GetRecordBatch::new(2, response_sender)));
+		let first = GetRecordSender::Batch {
+			batch: batch.clone(),
+			index: 0,
+		};
+		let second = GetRecordSender::Batch { batch, index: 1 };
+		assert!(!first.is_closed());
+
+		second.send(Err(eyre!("Not found")));
+		assert!(response_receiver.try_recv().is_err());
+		first.send(Ok(record("1:0:0")));
+
+		let results = response_receiver.try_recv().unwrap().unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(
+			results[0].0.as_ref().unwrap().record.key,
+			kad::RecordKey::new(&"1:0:0")
+		);
+		assert!(results[1].0.is_err());
+		assert!(results[1].1 <= results[0].1);
+	}
+
+	#[tokio::test]
+	async fn command_priority_order() {
+		let (sender, mut receiver) = command_channel();
+		let normal = TracedCommand::new(Box::new(NoopCommand));
+		let normal_id = normal.id;
+		sender.send(normal).unwrap();
+		sender
+			.send(TracedCommand::with_priority(
+				Box::new(NoopCommand),
+				CommandPriority::High,
+			))
+			.unwrap();
+		sender
+			.send(TracedCommand::new(Box::new(NoopCommand)))
+			.unwrap();
+		drop(sender);
+
+		assert_eq!(
+			receiver.recv().await.unwrap().priority,
+			CommandPriority::High
+		);
+		assert_eq!(receiver.recv().await.unwrap().id, normal_id);
+		assert_eq!(
+			receiver.recv().await.unwrap().priority,
+			CommandPriority::Normal
+		);
+		assert!(receiver.recv().await.is_none());
+	}
+
+	#[test]
+	fn peer_latency_average() {
+		let mut latency = PeerLatency::new(Duration::from_millis(100));
+		latency.update(Duration::from_millis(200));
+		assert_eq!(latency.latest, Duration::from_millis(200));
+		assert_eq!(latency.average, Duration::from_millis(120));
+	}
+
 	#[test_case("/ip4/159.73.143.3/tcp/37000" => true ; "Global IPv4")]
 	#[test_case("/ip4/192.168.0.1/tcp/37000" => false ; "Local (192.168) IPv4")]
 	#[test_case("/ip4/172.16.10.11/tcp/37000" => false ; "Local (172.16) IPv4")]
 	#[test_case("/ip4/127.0.0.1/tcp/37000" => false ; "Loopback IPv4")]
+	#[test_case("/ip6/2a01:4f8:c17:1::1/tcp/37000" => true ; "Global IPv6")]
+	#[test_case("/ip6/::1/tcp/37000" => false ; "Loopback IPv6")]
+	#[test_case("/ip6/::/tcp/37000" => false ; "Unspecified IPv6")]
+	#[test_case("/ip6/fe80::1/tcp/37000" => false ; "Link-local IPv6")]
+	#[test_case("/ip6/fd00::1/tcp/37000" => false ; "Unique local IPv6")]
+	#[test_case("/ip6/2001:db8::1/tcp/37000" => false ; "Documentation IPv6")]
+	#[test_case("/ip6/::ffff:159.73.143.3/tcp/37000" => false ; "IPv4-mapped IPv6")]
+	#[test_case("/ip4/192.168.0.1/tcp/37000/p2p-circuit/ip6/2a01:4f8:c17:1::1" => true ; "Mixed IPv4 and IPv6")]
+	#[test_case("/dns/example.com/tcp/37000" => false ; "DNS multiaddr")]
 	#[test_case("" => false ; "Empty multiaddr")]
 	fn test_is_multiaddr_global(addr_str: &str) -> bool {
 		let addr = if addr_str.is_empty() {
@@ -306,4 +1555,32 @@ mod tests {
 		};
 		is_multiaddr_global(&addr)
 	}
+
+	#[test_case("8f3c4a6e2b1d9f0a7c5e3b1d9f0a7c5e3b1d9f0a7c5e3b1d9f0a7c5e3b1d9f0a" => true ; "Hex key")]
+	#[test_case("0x8f3c4a6e2b1d9f0a7c5e3b1d9f0a7c5e3b1d9f0a7c5e3b1d9f0a7c5e3b1d9f0a" => true ; "Prefixed hex key")]
+	#[test_case("8f3c4a6e" => false ; "Short hex key")]
+	#[test_case("/nonexistent/swarm.key" => false ; "Missing PSK file")]
+	fn test_pre_shared_key(psk: &str) -> bool {
+		pre_shared_key(psk).is_ok()
+	}
+
+	#[test_case("1:2:3", None => true ; "Unprefixed key without network prefix")]
+	#[test_case("turing/1:2:3", Some("turing") => true ; "Prefixed key with matching network prefix")]
+	#[test_case("mainnet/1:2:3", Some("turing") => false ; "Prefixed key with other network prefix")]
+	#[test_case("turingx/1:2:3", Some("turing") => false ; "Prefixed key with extended network prefix")]
+	#[test_case("1:2:3", Some("turing") => false ; "Unprefixed key with network prefix")]
+	#[test_case("turing/1:2:3", None => true ; "Prefixed key without network prefix")]
+	fn test_has_network_prefix(key: &str, network_prefix: Option<&str>) -> bool {
+		let key = kad::RecordKey::from(key.as_bytes().to_vec());
+		has_network_prefix(&key, network_prefix)
+	}
+
+	#[test_case(Quorum::One => 1 ; "One")]
+	#[test_case(Quorum::Majority => 3 ; "Majority")]
+	#[test_case(Quorum::All => 5 ; "All")]
+	#[test_case(Quorum::N(NonZeroUsize::new(2).unwrap()) => 2 ; "N")]
+	#[test_case(Quorum::N(NonZeroUsize::new(8).unwrap()) => 5 ; "N above replication factor")]
+	fn test_quorum_size(quorum: Quorum) -> usize {
+		quorum_size(quorum, NonZeroUsize::new(5).unwrap())
+	}
 }
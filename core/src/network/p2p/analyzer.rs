@@ -1,15 +1,52 @@
+use super::{Client, Histogram};
+use crate::{
+	network::rpc::{self, Event},
+	types::BlockVerified,
+};
 use color_eyre::Result;
+use kate_recovery::matrix::Position;
 use pcap::{Active, Capture, ConnectionStatus, Device};
 use std::{
 	sync::{
 		atomic::{AtomicU32, Ordering},
 		Arc,
 	},
-	time::Duration,
+	time::{Duration, Instant},
+};
+use tokio::{
+	sync::{broadcast, OwnedSemaphorePermit, Semaphore},
+	time,
 };
-use tokio::time;
 use tracing::{debug, error, info, warn};
 
+/// Upper bounds of the cell propagation latency buckets, in milliseconds
+const PROPAGATION_BOUNDS: &[u64] = &[
+	1_000, 2_000, 5_000, 10_000, 20_000, 30_000, 60_000, 120_000, 300_000,
+];
+
+/// Maximum number of blocks whose cell propagation is measured concurrently
+const MAX_MEASURED_BLOCKS: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct PropagationConfig {
+	/// Number of the random cells probed in each block
+	pub sample_size: u32,
+	/// Interval between the probes of the cells which are not yet retrievable
+	pub probe_interval: Duration,
+	/// Time after the finalization, after which the remaining cells are considered not propagated
+	pub max_duration: Duration,
+}
+
+impl Default for PropagationConfig {
+	fn default() -> Self {
+		Self {
+			sample_size: 20,
+			probe_interval: Duration::from_secs(5),
+			max_duration: Duration::from_secs(300),
+		}
+	}
+}
+
 // TODO: implement graceful shutdown for all async spawned functions
 pub async fn start_traffic_analyzer(port: u16, sampling_interval: u64) {
 	let mut is_one_capture_active = false;
@@ -62,6 +99,97 @@ pub async fn start_traffic_analyzer(port: u16, sampling_interval: u64) {
 	}
 }
 
+/// Measures the delay between the block finalization and its cells becoming retrievable from the DHT
+pub async fn start_propagation_analyzer(
+	client: Client,
+	mut message_rx: broadcast::Receiver<Event>,
+	cfg: PropagationConfig,
+) {
+	info!("Starting cell propagation analyzer.");
+	let measured_blocks = Arc::new(Semaphore::new(MAX_MEASURED_BLOCKS));
+	loop {
+		let (header, received_at) = match message_rx.recv().await {
+			Ok(Event::HeaderUpdate {
+				header,
+				received_at,
+				..
+			}) => (header, received_at),
+			Err(broadcast::error::RecvError::Lagged(skipped)) => {
+				warn!("Cell propagation analyzer skipped {skipped} blocks");
+				continue;
+			},
+			Err(broadcast::error::RecvError::Closed) => return,
+		};
+
+		let block = match BlockVerified::try_from((header, None)) {
+			Ok(block) => block,
+			Err(error) => {
+				error!("Header is not valid: {error}");
+				continue;
+			},
+		};
+		let Some(extension) = block.extension else {
+			continue;
+		};
+
+		let Ok(permit) = measured_blocks.clone().try_acquire_owned() else {
+			debug!(
+				block_number = block.block_num,
+				"Too many blocks are measured, skipping cell propagation measurement"
+			);
+			continue;
+		};
+
+		let positions = rpc::generate_random_cells(extension.dimensions, cfg.sample_size);
+		tokio::spawn(measure_propagation(
+			client.clone(),
+			block.block_num,
+			positions,
+			received_at,
+			cfg.clone(),
+			permit,
+		));
+	}
+}
+
+/// Probes the cells until all of them are retrieved, returns the histogram of the latencies in milliseconds.
+/// Permit is held until the measurement is finished.
+async fn measure_propagation(
+	client: Client,
+	block_number: u32,
+	positions: Vec<Position>,
+	finalized_at: Instant,
+	cfg: PropagationConfig,
+	_permit: OwnedSemaphorePermit,
+) -> Histogram {
+	let total = positions.len();
+	let mut latencies = Histogram::new(PROPAGATION_BOUNDS);
+	let mut remaining = positions;
+	let mut interval = time::interval(cfg.probe_interval);
+
+	while !remaining.is_empty() && finalized_at.elapsed() < cfg.max_duration {
+		interval.tick().await;
+		// Cells were retrievable at least since the probe has started
+		let latency = finalized_at.elapsed().as_millis() as u64;
+		// Local cache and store are bypassed, since they would hide the propagation delay
+		let (fetched, unfetched) = client.probe_cells_in_dht(block_number, &remaining).await;
+		for _ in &fetched {
+			latencies.observe(latency);
+		}
+		remaining = unfetched;
+	}
+
+	info!(
+		block_number,
+		total,
+		unfetched = remaining.len(),
+		mean_latency = ?latencies.mean(),
+		"Cell propagation latency histogram: {:?}",
+		latencies.counts
+	);
+	latencies
+}
+
 fn start_listening_on_device(
 	device_name: String,
 	port: u16,
@@ -0,0 +1,378 @@
+//! Finality verification of the block headers announced by peers over gossipsub.
+//!
+//! Finalized headers are announced with their GRANDPA justifications, which are checked against
+//! the validator set tracked from the trusted checkpoint. Headers skipped by the announcing node
+//! are announced without justification, and are buffered until the finalized descendant links to them.
+
+use avail_subxt::{primitives::Header, utils::H256};
+use codec::{Decode, Encode};
+use color_eyre::{eyre::eyre, Result};
+use sp_core::blake2_256;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+use crate::{
+	data::FinalitySyncCheckpoint,
+	finality::{check_finality, ValidatorSet, ValidatorSetTracker},
+	types::{FinalityCheckpointConfig, GrandpaJustification},
+};
+
+/// Block header announced over gossipsub
+#[derive(Clone, Debug, Encode, Decode)]
+pub struct BlockAnnouncement {
+	pub header: Header,
+	/// Justification of the finalized header, skipped headers are announced without it
+	pub justification: Option<GrandpaJustification>,
+}
+
+fn hash(header: &Header) -> H256 {
+	Encode::using_encoded(header, blake2_256).into()
+}
+
+/// Verifies announced headers, starting from the trusted checkpoint
+pub struct AnnouncedHeaders {
+	validator_sets: ValidatorSetTracker,
+	last_verified_number: u32,
+	/// Hash of the last verified header, unknown until the first header after the checkpoint is verified
+	last_verified_hash: Option<H256>,
+	/// Maximum number of blocks the verified header can be ahead of the last verified one
+	max_gap: u32,
+	unverified: HashMap<H256, Header>,
+}
+
+impl AnnouncedHeaders {
+	pub fn new(
+		validator_set: ValidatorSet,
+		checkpoint_number: u32,
+		checkpoint_hash: Option<H256>,
+		max_gap: u32,
+	) -> Self {
+		Self {
+			validator_sets: ValidatorSetTracker::new(validator_set),
+			last_verified_number: checkpoint_number,
+			last_verified_hash: checkpoint_hash,
+			max_gap,
+			unverified: HashMap::new(),
+		}
+	}
+
+	/// Starts from the stored checkpoint, or from the trusted one if it is newer
+	pub fn from_checkpoint(
+		stored: Option<FinalitySyncCheckpoint>,
+		trusted: Option<FinalityCheckpointConfig>,
+		max_gap: u32,
+	) -> Result<Self> {
+		let trusted_number = trusted.as_ref().map(|trusted| trusted.block_number);
+		match (stored, trusted) {
+			(Some(stored), _) if !trusted_number.is_some_and(|number| stored.number <= number) => {
+				let validator_set = ValidatorSet {
+					set_id: stored.set_id,
+					validator_set: stored.validator_set,
+				};
+				Ok(Self::new(validator_set, stored.number, None, max_gap))
+			},
+			(_, Some(trusted)) => {
+				let validator_set = ValidatorSet {
+					set_id: trusted.set_id,
+					validator_set: trusted.validator_set,
+				};
+				let checkpoint_hash = Some(trusted.block_hash);
+				Ok(Self::new(
+					validator_set,
+					trusted.block_number,
+					checkpoint_hash,
+					max_gap,
+				))
+			},
+			_ => Err(eyre!(
+				"Announced headers cannot be verified without the trusted `finality_checkpoint`"
+			)),
+		}
+	}
+
+	pub fn last_verified_number(&self) -> u32 {
+		self.last_verified_number
+	}
+
+	/// Validator set which finalized the last verified header
+	pub fn validator_set(&self) -> &ValidatorSet {
+		self.validator_sets.current()
+	}
+
+	/// Verifies the announced header, returning the newly verified announcements in the ascending order.
+	/// Skipped headers are returned with the finalized descendant, only if they link to the last verified header.
+	pub fn verify(&mut self, announcement: BlockAnnouncement) -> Result<Vec<BlockAnnouncement>> {
+		let BlockAnnouncement {
+			header,
+			justification,
+		} = announcement;
+
+		if header.number <= self.last_verified_number {
+			return Ok(vec![]);
+		}
+		if header.number - self.last_verified_number > self.max_gap {
+			return Err(eyre!(
+				"Announced block {} is more than {} blocks ahead of the last verified block {}",
+				header.number,
+				self.max_gap,
+				self.last_verified_number
+			));
+		}
+
+		let header_hash = hash(&header);
+		let Some(justification) = justification else {
+			if self.unverified.len() >= self.max_gap as usize {
+				return Err(eyre!(
+					"Too many unverified headers, dropping block {}",
+					header.number
+				));
+			}
+			self.unverified.insert(header_hash, header);
+			return Ok(vec![]);
+		};
+
+		if justification.commit.target_hash != header_hash
+			|| justification.commit.target_number != header.number
+		{
+			return Err(eyre!(
+				"Justification of block {} does not match the announced header",
+				header.number
+			));
+		}
+
+		// skipped headers between the last verified and the finalized header, in the ascending order
+		let mut skipped = vec![];
+		let mut parent_hash = H256(header.parent_hash.0);
+		while let Some(parent) = self
+			.unverified
+			.get(&parent_hash)
+			.filter(|parent| parent.number > self.last_verified_number)
+		{
+			parent_hash = H256(parent.parent_hash.0);
+			skipped.push(parent.clone());
+		}
+		skipped.reverse();
+		// validator set changes are imported only from the headers linked to the verified header
+		if self.last_verified_hash != Some(parent_hash)
+			|| skipped.len() as u32 != header.number - self.last_verified_number - 1
+		{
+			if !skipped.is_empty() {
+				warn!(
+					block_number = header.number,
+					"Skipped headers do not link to the last verified header"
+				);
+			}
+			skipped.clear();
+		}
+
+		let mut validator_sets = self.validator_sets.clone();
+		for header in &skipped {
+			validator_sets.validator_set_at(header.number);
+			validator_sets.import(header);
+		}
+		let validator_set = validator_sets.validator_set_at(header.number).clone();
+		check_finality(&validator_set, &justification)?;
+		validator_sets.import(&header);
+		debug!(
+			block_number = header.number,
+			skipped = skipped.len(),
+			"Announced block is finalized"
+		);
+
+		self.validator_sets = validator_sets;
+		self.last_verified_number = header.number;
+		self.last_verified_hash = Some(header_hash);
+		self.unverified
+			.retain(|_, unverified| unverified.number > header.number);
+
+		let mut verified = skipped
+			.into_iter()
+			.map(|header| BlockAnnouncement {
+				header,
+				justification: None,
+			})
+			.collect::<Vec<_>>();
+		verified.push(BlockAnnouncement {
+			header,
+			justification: Some(justification),
+		});
+		Ok(verified)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::{Commit, Precommit, SignedPrecommit, SignerMessage};
+	use avail_subxt::{
+		api::runtime_types::avail_core::{
+			data_lookup::compact::CompactDataLookup,
+			header::extension::{v3::HeaderExtension, HeaderExtension::V3},
+			kate_commitment::v3::KateCommitment,
+		},
+		config::substrate::Digest,
+	};
+	use sp_core::{ed25519, Pair};
+
+	const SET_ID: u64 = 1;
+
+	fn header(number: u32, parent: Option<&Header>) -> Header {
+		Header {
+			parent_hash: parent
+				.map(|parent| hash(parent).0)
+				.unwrap_or([0; 32])
+				.into(),
+			number,
+			state_root: Default::default(),
+			extrinsics_root: Default::default(),
+			digest: Digest { logs: vec![] },
+			extension: V3(HeaderExtension {
+				commitment: KateCommitment {
+					rows: 0,
+					cols: 0,
+					data_root: Default::default(),
+					commitment: vec![],
+				},
+				app_lookup: CompactDataLookup {
+					size: 0,
+					index: vec![],
+				},
+			}),
+		}
+	}
+
+	fn validators() -> Vec<ed25519::Pair> {
+		(1..=4)
+			.map(|seed| ed25519::Pair::from_seed(&[seed; 32]))
+			.collect()
+	}
+
+	fn finalized(header: &Header, validators: &[ed25519::Pair]) -> BlockAnnouncement {
+		let precommit = Precommit {
+			target_hash: hash(header),
+			target_number: header.number,
+		};
+		let message = Encode::encode(&(
+			&SignerMessage::PrecommitMessage(precommit.clone()),
+			&1u64,
+			&SET_ID,
+		));
+		let precommits = validators
+			.iter()
+			.map(|validator| SignedPrecommit {
+				precommit: precommit.clone(),
+				signature: validator.sign(&message),
+				id: validator.public(),
+			})
+			.collect();
+		BlockAnnouncement {
+			header: header.clone(),
+			justification: Some(GrandpaJustification {
+				round: 1,
+				commit: Commit {
+					target_hash: hash(header),
+					target_number: header.number,
+					precommits,
+				},
+				votes_ancestries: vec![],
+			}),
+		}
+	}
+
+	fn skipped(header: &Header) -> BlockAnnouncement {
+		BlockAnnouncement {
+			header: header.clone(),
+			justification: None,
+		}
+	}
+
+	fn announced_headers(checkpoint: &Header) -> AnnouncedHeaders {
+		let validator_set = ValidatorSet {
+			set_id: SET_ID,
+			validator_set: validators()
+				.iter()
+				.map(|validator| validator.public())
+				.collect(),
+		};
+		AnnouncedHeaders::new(validator_set, checkpoint.number, Some(hash(checkpoint)), 10)
+	}
+
+	fn numbers(announcements: Vec<BlockAnnouncement>) -> Vec<u32> {
+		announcements
+			.iter()
+			.map(|announcement| announcement.header.number)
+			.collect()
+	}
+
+	#[test]
+	fn finalized_with_skipped_headers() {
+		let first = header(1, None);
+		let second = header(2, Some(&first));
+		let third = header(3, Some(&second));
+		let fourth = header(4, Some(&third));
+		let mut headers = announced_headers(&first);
+
+		assert!(headers.verify(skipped(&second)).unwrap().is_empty());
+		assert!(headers.verify(skipped(&third)).unwrap().is_empty());
+		let verified = headers.verify(finalized(&fourth, &validators())).unwrap();
+		assert_eq!(numbers(verified), vec![2, 3, 4]);
+		assert_eq!(headers.last_verified_number(), 4);
+
+		// already verified headers are ignored
+		assert!(headers
+			.verify(finalized(&third, &validators()))
+			.unwrap()
+			.is_empty());
+	}
+
+	#[test]
+	fn unlinked_skipped_headers() {
+		let first = header(1, None);
+		let second = header(2, Some(&first));
+		let forged = header(2, None);
+		let third = header(3, Some(&second));
+		let mut headers = announced_headers(&first);
+
+		headers.verify(skipped(&forged)).unwrap();
+		let verified = headers.verify(finalized(&third, &validators())).unwrap();
+		assert_eq!(numbers(verified), vec![3]);
+	}
+
+	#[test]
+	fn not_finalized_by_validator_set() {
+		let first = header(1, None);
+		let second = header(2, Some(&first));
+		let mut headers = announced_headers(&first);
+
+		// supermajority of 4 validators is 3
+		assert!(headers
+			.verify(finalized(&second, &validators()[..2]))
+			.is_err());
+		let impostors = (5..=8)
+			.map(|seed| ed25519::Pair::from_seed(&[seed; 32]))
+			.collect::<Vec<_>>();
+		assert!(headers.verify(finalized(&second, &impostors)).is_err());
+
+		let mut mismatched = finalized(&second, &validators());
+		mismatched.header = header(2, None);
+		assert!(headers.verify(mismatched).is_err());
+		assert_eq!(headers.last_verified_number(), 1);
+	}
+
+	#[test]
+	fn bounded_jump() {
+		let first = header(1, None);
+		let far_ahead = header(u32::MAX, None);
+		let mut headers = announced_headers(&first);
+
+		assert!(headers
+			.verify(finalized(&far_ahead, &validators()))
+			.is_err());
+		assert!(headers.verify(skipped(&header(12, None))).is_err());
+
+		// far ahead announcement does not stall the verification
+		let second = header(2, Some(&first));
+		let verified = headers.verify(finalized(&second, &validators())).unwrap();
+		assert_eq!(numbers(verified), vec![2]);
+	}
+}
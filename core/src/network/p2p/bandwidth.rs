@@ -0,0 +1,389 @@
+//! Bandwidth accounting per protocol and per peer.
+//!
+//! Transport connections are wrapped with a metering muxer, which counts bytes read from and written to each substream.
+//! Substream protocol is taken from the multistream-select negotiation at the start of the substream,
+//! as the first protocol proposed by the dialer. Bytes are counted above the multiplexer, so the noise and yamux framing is not included.
+//! Relayed connections are counted on the connection to the relay, under the relay protocol.
+
+use futures::{ready, AsyncRead, AsyncWrite};
+use libp2p::{
+	core::{
+		muxing::{StreamMuxer, StreamMuxerBox, StreamMuxerEvent, SubstreamBox},
+		ConnectedPoint,
+	},
+	PeerId,
+};
+use std::{
+	collections::HashMap,
+	io,
+	pin::Pin,
+	sync::{Arc, Mutex},
+	task::{Context, Poll},
+};
+
+/// Protocol of the substreams without recognized multistream-select negotiation
+const UNKNOWN_PROTOCOL: &str = "unknown";
+/// Protocol under which the traffic of the untracked protocols is counted
+const OTHER_PROTOCOL: &str = "other";
+/// Maximum number of tracked protocols, since the proposed protocol names are chosen by the remote peers
+const MAX_TRACKED_PROTOCOLS: usize = 64;
+/// Maximum number of bytes inspected for the negotiated protocol
+const MAX_NEGOTIATION_BYTES: usize = 256;
+/// Number of tracked peers above which the peers with the lowest traffic are dropped
+const MAX_TRACKED_PEERS: usize = 2048;
+/// Number of peers returned in the bandwidth stats
+const TOP_PEERS: usize = 10;
+
+const MULTISTREAM_HEADER: &[u8] = b"/multistream/1.0.0\n";
+
+/// Number of bytes received and sent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Traffic {
+	pub inbound: u64,
+	pub outbound: u64,
+}
+
+impl Traffic {
+	pub fn total(&self) -> u64 {
+		self.inbound + self.outbound
+	}
+
+	fn add(&mut self, direction: Direction, bytes: u64) {
+		match direction {
+			Direction::Inbound => self.inbound += bytes,
+			Direction::Outbound => self.outbound += bytes,
+		}
+	}
+}
+
+/// Bytes received and sent since the start, by protocol and for the peers with the most traffic
+#[derive(Debug, Clone)]
+pub struct BandwidthStats {
+	pub total: Traffic,
+	pub protocols: HashMap<String, Traffic>,
+	/// Peers with the most traffic, sorted by the total number of bytes
+	pub top_peers: Vec<(PeerId, Traffic)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+	Inbound,
+	Outbound,
+}
+
+#[derive(Default)]
+struct Counters {
+	total: Traffic,
+	protocols: HashMap<String, Traffic>,
+	peers: HashMap<PeerId, Traffic>,
+}
+
+/// Shared bandwidth counters of all metered connections
+#[derive(Clone, Default)]
+pub struct Bandwidth(Arc<Mutex<Counters>>);
+
+impl Bandwidth {
+	/// Returns transport output mapping, which wraps the connection muxer so its substreams are metered
+	pub fn meter<M>(&self) -> impl Fn((PeerId, M), ConnectedPoint) -> (PeerId, Muxer) + Clone
+	where
+		M: StreamMuxer + Send + 'static,
+		M::Substream: Send + 'static,
+		M::Error: Send + Sync + 'static,
+	{
+		let bandwidth = self.clone();
+		move |(peer_id, muxer), _| {
+			let muxer = Muxer {
+				inner: StreamMuxerBox::new(muxer),
+				peer_id,
+				bandwidth: bandwidth.clone(),
+			};
+			(peer_id, muxer)
+		}
+	}
+
+	pub fn stats(&self) -> BandwidthStats {
+		let counters = self.0.lock().unwrap();
+		let mut top_peers = counters
+			.peers
+			.iter()
+			.map(|(peer_id, traffic)| (*peer_id, *traffic))
+			.collect::<Vec<_>>();
+		top_peers.sort_by_key(|(_, traffic)| std::cmp::Reverse(traffic.total()));
+		top_peers.truncate(TOP_PEERS);
+
+		BandwidthStats {
+			total: counters.total,
+			protocols: counters.protocols.clone(),
+			top_peers,
+		}
+	}
+
+	fn record_peer(&self, peer_id: PeerId, direction: Direction, bytes: u64) {
+		let mut counters = self.0.lock().unwrap();
+		counters.total.add(direction, bytes);
+		counters
+			.peers
+			.entry(peer_id)
+			.or_default()
+			.add(direction, bytes);
+
+		if counters.peers.len() > MAX_TRACKED_PEERS {
+			let mut peers = counters.peers.drain().collect::<Vec<_>>();
+			peers.sort_by_key(|(_, traffic)| std::cmp::Reverse(traffic.total()));
+			peers.truncate(MAX_TRACKED_PEERS / 2);
+			counters.peers.extend(peers);
+		}
+	}
+
+	fn record_protocol(&self, protocol: &str, traffic: Traffic) {
+		let mut counters = self.0.lock().unwrap();
+		let protocol = if counters.protocols.contains_key(protocol)
+			|| counters.protocols.len() < MAX_TRACKED_PROTOCOLS
+		{
+			protocol
+		} else {
+			OTHER_PROTOCOL
+		};
+		let protocol_traffic = counters.protocols.entry(protocol.to_string()).or_default();
+		protocol_traffic.inbound += traffic.inbound;
+		protocol_traffic.outbound += traffic.outbound;
+	}
+}
+
+/// Connection muxer with metered substreams
+pub struct Muxer {
+	inner: StreamMuxerBox,
+	peer_id: PeerId,
+	bandwidth: Bandwidth,
+}
+
+impl Muxer {
+	fn substream(&self, inner: SubstreamBox, negotiation: Direction) -> Substream {
+		Substream {
+			inner,
+			peer_id: self.peer_id,
+			bandwidth: self.bandwidth.clone(),
+			negotiation,
+			negotiation_bytes: Vec::new(),
+			protocol: None,
+			pending: Traffic::default(),
+		}
+	}
+}
+
+impl StreamMuxer for Muxer {
+	type Substream = Substream;
+	type Error = io::Error;
+
+	fn poll_inbound(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<Self::Substream, Self::Error>> {
+		let this = self.get_mut();
+		let inner = ready!(Pin::new(&mut this.inner).poll_inbound(cx))?;
+		// Dialer proposes the protocol, so it is read from the inbound substreams
+		Poll::Ready(Ok(this.substream(inner, Direction::Inbound)))
+	}
+
+	fn poll_outbound(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<Self::Substream, Self::Error>> {
+		let this = self.get_mut();
+		let inner = ready!(Pin::new(&mut this.inner).poll_outbound(cx))?;
+		Poll::Ready(Ok(this.substream(inner, Direction::Outbound)))
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		Pin::new(&mut self.get_mut().inner).poll_close(cx)
+	}
+
+	fn poll(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+		Pin::new(&mut self.get_mut().inner).poll(cx)
+	}
+}
+
+/// Metered substream, traffic is counted for the protocol once it is negotiated
+pub struct Substream {
+	inner: SubstreamBox,
+	peer_id: PeerId,
+	bandwidth: Bandwidth,
+	/// Direction in which the dialer proposes the protocol
+	negotiation: Direction,
+	negotiation_bytes: Vec<u8>,
+	protocol: Option<String>,
+	/// Traffic before the protocol is known
+	pending: Traffic,
+}
+
+impl Substream {
+	fn record(&mut self, direction: Direction, bytes: &[u8]) {
+		if bytes.is_empty() {
+			return;
+		}
+		let length = bytes.len() as u64;
+		self.bandwidth.record_peer(self.peer_id, direction, length);
+
+		if let Some(protocol) = &self.protocol {
+			let mut traffic = Traffic::default();
+			traffic.add(direction, length);
+			self.bandwidth.record_protocol(protocol, traffic);
+			return;
+		}
+
+		self.pending.add(direction, length);
+		if direction != self.negotiation {
+			return;
+		}
+		let remaining = MAX_NEGOTIATION_BYTES.saturating_sub(self.negotiation_bytes.len());
+		self.negotiation_bytes
+			.extend_from_slice(&bytes[..bytes.len().min(remaining)]);
+
+		let protocol = match proposed_protocol(&self.negotiation_bytes) {
+			Negotiation::Incomplete if remaining > bytes.len() => return,
+			Negotiation::Protocol(protocol) => protocol,
+			_ => UNKNOWN_PROTOCOL.to_string(),
+		};
+		self.bandwidth
+			.record_protocol(&protocol, std::mem::take(&mut self.pending));
+		self.negotiation_bytes = Vec::new();
+		self.protocol = Some(protocol);
+	}
+}
+
+impl Drop for Substream {
+	fn drop(&mut self) {
+		if self.protocol.is_none() && self.pending != Traffic::default() {
+			self.bandwidth
+				.record_protocol(UNKNOWN_PROTOCOL, self.pending);
+		}
+	}
+}
+
+impl AsyncRead for Substream {
+	fn poll_read(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		let this = self.get_mut();
+		let read = ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+		this.record(Direction::Inbound, &buf[..read]);
+		Poll::Ready(Ok(read))
+	}
+}
+
+impl AsyncWrite for Substream {
+	fn poll_write(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let this = self.get_mut();
+		let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+		this.record(Direction::Outbound, &buf[..written]);
+		Poll::Ready(Ok(written))
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.get_mut().inner).poll_close(cx)
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Negotiation {
+	Incomplete,
+	Protocol(String),
+	Unknown,
+}
+
+/// Reads unsigned varint length prefixed message
+fn read_message(bytes: &[u8]) -> Option<(&[u8], &[u8])> {
+	let mut length = 0usize;
+	for (index, byte) in bytes.iter().enumerate().take(2) {
+		length |= usize::from(byte & 0x7f) << (7 * index);
+		if byte & 0x80 == 0 {
+			let rest = &bytes[index + 1..];
+			return (rest.len() >= length).then(|| rest.split_at(length));
+		}
+	}
+	None
+}
+
+/// Parses the multistream-select header and the first proposed protocol
+fn proposed_protocol(bytes: &[u8]) -> Negotiation {
+	let Some((header, rest)) = read_message(bytes) else {
+		return Negotiation::Incomplete;
+	};
+	if header != MULTISTREAM_HEADER {
+		return Negotiation::Unknown;
+	}
+	let Some((protocol, _)) = read_message(rest) else {
+		return Negotiation::Incomplete;
+	};
+	match protocol
+		.strip_suffix(b"\n")
+		.and_then(|protocol| std::str::from_utf8(protocol).ok())
+	{
+		Some(protocol) => Negotiation::Protocol(protocol.to_string()),
+		None => Negotiation::Unknown,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn message(content: &[u8]) -> Vec<u8> {
+		[&[content.len() as u8], content].concat()
+	}
+
+	#[test]
+	fn parse_proposed_protocol() {
+		let header = message(MULTISTREAM_HEADER);
+		let protocol = message(b"/ipfs/ping/1.0.0\n");
+		let negotiation = [header.clone(), protocol].concat();
+
+		assert_eq!(
+			proposed_protocol(&negotiation),
+			Negotiation::Protocol("/ipfs/ping/1.0.0".to_string())
+		);
+		assert_eq!(proposed_protocol(&header), Negotiation::Incomplete);
+		assert_eq!(
+			proposed_protocol(&negotiation[..negotiation.len() - 1]),
+			Negotiation::Incomplete
+		);
+		assert_eq!(
+			proposed_protocol(&message(b"/other/1.0.0\n")),
+			Negotiation::Unknown
+		);
+	}
+
+	#[test]
+	fn bounded_protocols() {
+		let bandwidth = Bandwidth::default();
+		let traffic = Traffic {
+			inbound: 1,
+			outbound: 2,
+		};
+		for index in 0..MAX_TRACKED_PROTOCOLS * 2 {
+			bandwidth.record_protocol(&format!("/protocol/{index}"), traffic);
+		}
+		bandwidth.record_protocol("/protocol/0", traffic);
+
+		let protocols = bandwidth.stats().protocols;
+		assert_eq!(protocols.len(), MAX_TRACKED_PROTOCOLS + 1);
+		assert_eq!(protocols["/protocol/0"].total(), 6);
+		assert_eq!(
+			protocols[OTHER_PROTOCOL].total(),
+			3 * MAX_TRACKED_PROTOCOLS as u64
+		);
+	}
+}
@@ -0,0 +1,230 @@
+//! Request-response protocol for fetching cells directly from a known peer.
+//!
+//! Requests and responses are SCALE encoded, read until the remote side closes the stream.
+//! Signed cell records are sent with the envelope, and are verified like the cells fetched from the DHT.
+//! In provider mode, peers holding the block cells are found through the block provider key.
+
+use async_trait::async_trait;
+use codec::{Decode, Encode};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use kate_recovery::{data::Cell, matrix::Position};
+use libp2p::{kad::RecordKey, request_response, StreamProtocol};
+use std::io;
+
+use super::{prefixed_reference, signed_record};
+
+pub const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/avail/cell-fetch/1");
+
+/// Maximum size of the encoded request, (4 + 2) bytes per position
+const REQUEST_SIZE_MAXIMUM: u64 = 1024 * 1024;
+/// Maximum size of the encoded response, (4 + 2 + 80) bytes per unsigned cell
+const RESPONSE_SIZE_MAXIMUM: u64 = 16 * 1024 * 1024;
+
+/// DHT key which peers holding the block cells are providing
+pub fn provider_key(block_number: u32, network_prefix: Option<&str>) -> RecordKey {
+	let reference = prefixed_reference(network_prefix, format!("cells:{block_number}"));
+	RecordKey::from(reference.into_bytes())
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Request {
+	pub block_number: u32,
+	/// Cell positions as (row, column) pairs
+	pub positions: Vec<(u32, u16)>,
+}
+
+impl Request {
+	pub fn new(block_number: u32, positions: &[Position]) -> Self {
+		Self {
+			block_number,
+			positions: positions
+				.iter()
+				.map(|position| (position.row, position.col))
+				.collect(),
+		}
+	}
+
+	pub fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+		self.positions
+			.iter()
+			.map(|&(row, col)| Position { row, col })
+	}
+}
+
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct Response {
+	/// Available cells as (row, column, record value) triples, unavailable cells are omitted
+	pub cells: Vec<(u32, u16, Vec<u8>)>,
+}
+
+impl Response {
+	pub fn push(&mut self, position: Position, content: Vec<u8>) {
+		self.cells.push((position.row, position.col, content));
+	}
+
+	/// Converts response into cells, skipping the ones with invalid signature or content size.
+	/// Unsigned cells are skipped if signatures are required.
+	/// Returns the cells and the number of the invalid ones, unsigned cells are not invalid.
+	pub fn into_cells(
+		self,
+		block_number: u32,
+		network_prefix: Option<&str>,
+		require_signed_records: bool,
+	) -> (Vec<Cell>, usize) {
+		let mut invalid = 0;
+		let cells = self
+			.cells
+			.into_iter()
+			.filter_map(|(row, col, value)| {
+				let position = Position { row, col };
+				let reference =
+					prefixed_reference(network_prefix, position.reference(block_number));
+				let Ok(opened) = signed_record::open(reference.as_bytes(), value, false) else {
+					invalid += 1;
+					return None;
+				};
+				if require_signed_records && opened.publisher.is_none() {
+					return None;
+				}
+				let Ok(content) = opened.value.try_into() else {
+					invalid += 1;
+					return None;
+				};
+				Some(Cell { position, content })
+			})
+			.collect();
+		(cells, invalid)
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Codec;
+
+async fn read<T, M>(io: &mut T, limit: u64) -> io::Result<M>
+where
+	T: AsyncRead + Unpin + Send,
+	M: Decode,
+{
+	let mut bytes = Vec::new();
+	io.take(limit).read_to_end(&mut bytes).await?;
+	M::decode(&mut &bytes[..]).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+async fn write<T, M>(io: &mut T, message: M) -> io::Result<()>
+where
+	T: AsyncWrite + Unpin + Send,
+	M: Encode,
+{
+	io.write_all(&message.encode()).await?;
+	io.close().await
+}
+
+#[async_trait]
+impl request_response::Codec for Codec {
+	type Protocol = StreamProtocol;
+	type Request = Request;
+	type Response = Response;
+
+	async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Request>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read(io, REQUEST_SIZE_MAXIMUM).await
+	}
+
+	async fn read_response<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Response>
+	where
+		T: AsyncRead + Unpin + Send,
+	{
+		read(io, RESPONSE_SIZE_MAXIMUM).await
+	}
+
+	async fn write_request<T>(
+		&mut self,
+		_: &Self::Protocol,
+		io: &mut T,
+		request: Request,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write(io, request).await
+	}
+
+	async fn write_response<T>(
+		&mut self,
+		_: &Self::Protocol,
+		io: &mut T,
+		response: Response,
+	) -> io::Result<()>
+	where
+		T: AsyncWrite + Unpin + Send,
+	{
+		write(io, response).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn response_into_cells_skips_invalid_content() {
+		let mut response = Response::default();
+		response.push(Position { row: 1, col: 2 }, vec![7; 80]);
+		response.push(Position { row: 3, col: 4 }, vec![7; 79]);
+
+		let (cells, invalid) = response.into_cells(5, None, false);
+		assert_eq!(invalid, 1);
+		assert_eq!(cells.len(), 1);
+		assert_eq!(cells[0].position, Position { row: 1, col: 2 });
+	}
+
+	#[test]
+	fn response_into_cells_verifies_signatures() {
+		let keypair = libp2p::identity::Keypair::generate_ed25519();
+		let sign = |position: Position, block_number: u32| {
+			let reference = prefixed_reference(Some("test"), position.reference(block_number));
+			signed_record::sign(&keypair, reference.as_bytes(), vec![7; 80]).unwrap()
+		};
+		let mut response = Response::default();
+		response.push(
+			Position { row: 1, col: 2 },
+			sign(Position { row: 1, col: 2 }, 5),
+		);
+		// Signed for a different block
+		response.push(
+			Position { row: 3, col: 4 },
+			sign(Position { row: 3, col: 4 }, 6),
+		);
+		response.push(Position { row: 5, col: 6 }, vec![7; 80]);
+
+		let positions = |(cells, invalid): (Vec<Cell>, usize)| {
+			let positions = cells
+				.into_iter()
+				.map(|cell| cell.position)
+				.collect::<Vec<_>>();
+			(positions, invalid)
+		};
+		// Unsigned cell is skipped, but it is not invalid
+		assert_eq!(
+			positions(response.clone().into_cells(5, Some("test"), true)),
+			(vec![Position { row: 1, col: 2 }], 1)
+		);
+		assert_eq!(
+			positions(response.into_cells(5, Some("test"), false)),
+			(
+				vec![Position { row: 1, col: 2 }, Position { row: 5, col: 6 }],
+				1
+			)
+		);
+	}
+
+	#[test]
+	fn request_positions_roundtrip() {
+		let positions = [Position { row: 0, col: 1 }, Position { row: 2, col: 3 }];
+		let request = Request::decode(&mut &Request::new(5, &positions).encode()[..]).unwrap();
+		assert_eq!(request.block_number, 5);
+		assert_eq!(request.positions().collect::<Vec<_>>(), positions);
+	}
+}
@@ -0,0 +1,181 @@
+//! Churn and uptime tracking of the connected peers.
+//!
+//! Peer session lasts from the first established connection until the last connection with the peer is closed.
+//! Only the recently seen peers are kept, so the tracking is bounded on the networks with high churn.
+
+use libp2p::PeerId;
+use lru::LruCache;
+use std::{num::NonZeroUsize, time::Duration};
+use tokio::time::Instant;
+
+const TRACKED_PEERS_CAPACITY: NonZeroUsize = match NonZeroUsize::new(4096) {
+	Some(capacity) => capacity,
+	None => panic!("Capacity must not be zero"),
+};
+
+struct PeerSessions {
+	first_seen: Instant,
+	last_seen: Instant,
+	/// Number of the started sessions, including the current one
+	sessions: u32,
+	/// Total duration of the closed sessions
+	closed_sessions_duration: Duration,
+	/// Start of the current session, if the peer is connected
+	connected_since: Option<Instant>,
+}
+
+impl PeerSessions {
+	fn uptime(&self, now: Instant) -> Duration {
+		let current = self
+			.connected_since
+			.map(|since| now.saturating_duration_since(since))
+			.unwrap_or_default();
+		self.closed_sessions_duration + current
+	}
+}
+
+/// Churn statistics of a peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerChurnStats {
+	pub peer_id: PeerId,
+	pub is_connected: bool,
+	/// Number of the connection sessions with the peer, including the current one
+	pub sessions: u32,
+	/// Total time the peer was connected
+	pub uptime: Duration,
+	pub average_session_duration: Duration,
+	pub since_first_seen: Duration,
+	/// Time since the last connection or disconnection of the peer
+	pub since_last_seen: Duration,
+}
+
+impl PeerChurnStats {
+	/// Share of the time since the peer was first seen, in which the peer was connected
+	pub fn uptime_ratio(&self) -> f64 {
+		if self.since_first_seen.is_zero() {
+			return 1.0;
+		}
+		self.uptime.as_secs_f64() / self.since_first_seen.as_secs_f64()
+	}
+}
+
+/// Churn report of the routing table peers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerChurnReport {
+	pub peers: Vec<PeerChurnStats>,
+	/// Number of the routing table peers which were not connected since the tracking has started
+	pub unseen_peers: usize,
+}
+
+impl PeerChurnReport {
+	pub fn average_session_duration(&self) -> Option<Duration> {
+		let sessions: u32 = self.peers.iter().map(|peer| peer.sessions).sum();
+		let uptime: Duration = self.peers.iter().map(|peer| peer.uptime).sum();
+		(sessions > 0).then(|| uptime / sessions)
+	}
+}
+
+pub struct PeerChurn {
+	peers: LruCache<PeerId, PeerSessions>,
+}
+
+impl Default for PeerChurn {
+	fn default() -> Self {
+		Self {
+			peers: LruCache::new(TRACKED_PEERS_CAPACITY),
+		}
+	}
+}
+
+impl PeerChurn {
+	/// Starts the session of the peer, called when the first connection is established
+	pub fn connected(&mut self, peer_id: PeerId, now: Instant) {
+		let peer = self.peers.get_or_insert_mut(peer_id, || PeerSessions {
+			first_seen: now,
+			last_seen: now,
+			sessions: 0,
+			closed_sessions_duration: Duration::ZERO,
+			connected_since: None,
+		});
+		peer.last_seen = now;
+		if peer.connected_since.is_none() {
+			peer.sessions += 1;
+			peer.connected_since = Some(now);
+		}
+	}
+
+	/// Closes the session of the peer, called when the last connection is closed
+	pub fn disconnected(&mut self, peer_id: PeerId, now: Instant) {
+		let Some(peer) = self.peers.get_mut(&peer_id) else {
+			return;
+		};
+		peer.last_seen = now;
+		if let Some(since) = peer.connected_since.take() {
+			peer.closed_sessions_duration += now.saturating_duration_since(since);
+		}
+	}
+
+	pub fn report<'a>(
+		&self,
+		peer_ids: impl IntoIterator<Item = &'a PeerId>,
+		now: Instant,
+	) -> PeerChurnReport {
+		let mut report = PeerChurnReport::default();
+		for peer_id in peer_ids {
+			let Some(peer) = self.peers.peek(peer_id) else {
+				report.unseen_peers += 1;
+				continue;
+			};
+			let uptime = peer.uptime(now);
+			report.peers.push(PeerChurnStats {
+				peer_id: *peer_id,
+				is_connected: peer.connected_since.is_some(),
+				sessions: peer.sessions,
+				uptime,
+				average_session_duration: uptime / peer.sessions.max(1),
+				since_first_seen: now.saturating_duration_since(peer.first_seen),
+				since_last_seen: now.saturating_duration_since(peer.last_seen),
+			});
+		}
+		report
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn peer_sessions() {
+		let mut churn = PeerChurn::default();
+		let peer_id = PeerId::random();
+		let unseen_peer_id = PeerId::random();
+		let start = Instant::now();
+
+		churn.connected(peer_id, start);
+		// Additional connection does not start a new session
+		churn.connected(peer_id, start + Duration::from_secs(5));
+		churn.disconnected(peer_id, start + Duration::from_secs(10));
+		churn.connected(peer_id, start + Duration::from_secs(30));
+
+		let report = churn.report([&peer_id, &unseen_peer_id], start + Duration::from_secs(40));
+		assert_eq!(report.unseen_peers, 1);
+		assert_eq!(
+			report.peers,
+			vec![PeerChurnStats {
+				peer_id,
+				is_connected: true,
+				sessions: 2,
+				uptime: Duration::from_secs(20),
+				average_session_duration: Duration::from_secs(10),
+				since_first_seen: Duration::from_secs(40),
+				since_last_seen: Duration::from_secs(10),
+			}]
+		);
+		assert_eq!(report.peers[0].uptime_ratio(), 0.5);
+		assert_eq!(
+			report.average_session_duration(),
+			Some(Duration::from_secs(10))
+		);
+	}
+}
@@ -1,7 +1,22 @@
 use super::{
-	event_loop::ConnectionEstablishedInfo, is_global, is_multiaddr_global, Command, CommandSender,
-	EventLoopEntries, MultiAddressInfo, PeerInfo, QueryChannel, SendableCommand,
+	block_announcements_topic, cell_fetch,
+	event_loop::{records_to_republish, ConnectionEstablishedInfo, DHTKey},
+	fetch_stats::GetFailure,
+	is_multiaddr_global,
+	parallelization::{ParallelizationLimit, ParallelizationStats},
+	prefixed_reference, record_snapshot,
+	record_verification::InvalidRecords,
+	routing_table, signed_record, BandwidthStats, BlockAnnouncement, BlockPutStats, Command,
+	CommandPriority, CommandSender, DHTFetchStats, DHTPutError, DHTQueryStats, EventLoopEntries,
+	GetFailures, GetRecordBatch, GetRecordSender, HolePunchStats, ListenerInfo, MultiAddressInfo,
+	NatInfo, PeerChurnReport, PeerInfo, PeerLatency, PutResultSender, QueryChannel,
+	RoutingTableEntry, SendableCommand, StoreStats, TracedCommand,
 };
+use crate::{
+	data::{BlockedPeersKey, Database},
+	types::{BlockRange, TimeToLive},
+};
+use codec::Encode;
 use color_eyre::{
 	eyre::{eyre, WrapErr},
 	Report, Result,
@@ -13,35 +28,140 @@ use kate_recovery::{
 	matrix::{Dimensions, Position, RowIndex},
 };
 use libp2p::{
-	kad::{store::RecordStore, Mode, PeerRecord, Quorum, Record, RecordKey},
+	identity::Keypair,
+	kad::{
+		store::{self, RecordStore},
+		Mode, PeerRecord, Quorum, Record, RecordKey,
+	},
 	swarm::dial_opts::DialOpts,
 	Multiaddr, PeerId,
 };
-use std::time::{Duration, Instant};
+use lru::LruCache;
+use std::{
+	collections::HashSet,
+	future::Future,
+	num::NonZeroUsize,
+	pin::pin,
+	str::FromStr,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, Instant},
+};
 use sysinfo::System;
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, trace};
 
+/// Maximum duration of the command execution, including the time it is queued in the event loop
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Converts the fetched DHT record into the cell
+/// Converts the fetched record into the cell.
+/// Peers which sent the records with invalid signature or content are added to the invalid senders.
+fn cell_from_record(
+	reference: &str,
+	position: Position,
+	result: Result<PeerRecord>,
+	require_signed_records: bool,
+	invalid_senders: &mut Vec<PeerId>,
+) -> Result<Cell, GetFailure> {
+	match result {
+		Ok(peer_record) => {
+			trace!("Fetched cell {reference} from the DHT");
+
+			let value =
+				match signed_record::open(reference.as_bytes(), peer_record.record.value, false) {
+					Ok(opened) => match opened.publisher {
+						Some(publisher) => {
+							trace!("Cell {reference} is signed by {publisher}");
+							opened.value
+						},
+						None if require_signed_records => {
+							debug!("Cell {reference} record rejected: Record is not signed");
+							return Err(GetFailure::InvalidRecord);
+						},
+						None => opened.value,
+					},
+					Err(error) => {
+						debug!("Cell {reference} record rejected: {error:#}");
+						invalid_senders.extend(peer_record.peer);
+						return Err(GetFailure::InvalidRecord);
+					},
+				};
+
+			let try_content: Result<[u8; config::COMMITMENT_SIZE + config::CHUNK_SIZE], _> =
+				value.try_into();
+
+			let Ok(content) = try_content else {
+				debug!("Cannot convert cell {reference} into 80 bytes");
+				invalid_senders.extend(peer_record.peer);
+				return Err(GetFailure::InvalidRecord);
+			};
+
+			Ok(Cell { position, content })
+		},
+		Err(error) => {
+			trace!("Cell {reference} not found in the DHT: {error}");
+			Err(GetFailure::from(&error))
+		},
+	}
+}
+
+/// Wraps the record value into the signed envelope, if the signing keypair is set
+fn signed_value(reference: &str, value: Vec<u8>, keypair: Option<&Keypair>) -> Vec<u8> {
+	let Some(keypair) = keypair else {
+		return value;
+	};
+	match signed_record::sign(keypair, reference.as_bytes(), value.clone()) {
+		Ok(signed) => signed,
+		Err(error) => {
+			debug!("Publishing unsigned record {reference}: {error:#}");
+			value
+		},
+	}
+}
+
 #[derive(Clone)]
 pub struct Client {
 	command_sender: CommandSender,
-	/// Number of cells to fetch in parallel
-	dht_parallelization_limit: usize,
+	/// Number of cells to fetch in parallel, adapted to the query latencies and failures if enabled
+	dht_parallelization: Arc<Mutex<ParallelizationLimit>>,
 	/// Cell time to live in DHT (in seconds)
-	ttl: u64,
+	cell_ttl: u64,
+	/// Row time to live in DHT (in seconds)
+	row_ttl: u64,
+	/// Default quorum for DHT PUT operations
+	put_quorum: Quorum,
+	/// Default quorum for DHT GET operations
+	get_quorum: Quorum,
+	/// Network prefix of the DHT record keys
+	network_prefix: Option<String>,
+	/// Set when publishing to the DHT is paused due to resource pressure
+	publication_paused: Arc<AtomicBool>,
+	/// Verified cells by their reference, consulted before fetching cells from the DHT
+	cell_cache: Option<Arc<Mutex<LruCache<String, Cell>>>>,
+	/// Keypair signing the published DHT records, records are published unsigned if not set
+	signing_keypair: Option<Keypair>,
+	/// Reject fetched DHT records which are not signed by their publishers
+	require_signed_records: bool,
+	/// Invalid records received from the peers, which are blocked after sending too many of them
+	invalid_records: Arc<Mutex<InvalidRecords>>,
 }
 
 struct DHTCell(Cell);
 
 impl DHTCell {
-	fn reference(&self, block: u32) -> String {
-		self.0.reference(block)
+	fn reference(&self, block: u32, network_prefix: Option<&str>) -> String {
+		prefixed_reference(network_prefix, self.0.reference(block))
 	}
 
-	fn dht_record(&self, block: u32, ttl: u64) -> Record {
+	fn dht_record(&self, reference: &str, ttl: u64, keypair: Option<&Keypair>) -> Record {
 		Record {
-			key: self.0.reference(block).as_bytes().to_vec().into(),
-			value: self.0.content.to_vec(),
+			key: reference.as_bytes().to_vec().into(),
+			value: signed_value(reference, self.0.content.to_vec(), keypair),
 			publisher: None,
 			expires: Instant::now().checked_add(Duration::from_secs(ttl)),
 		}
@@ -50,14 +170,14 @@ impl DHTCell {
 struct DHTRow((RowIndex, Vec<u8>));
 
 impl DHTRow {
-	fn reference(&self, block: u32) -> String {
-		self.0 .0.reference(block)
+	fn reference(&self, block: u32, network_prefix: Option<&str>) -> String {
+		prefixed_reference(network_prefix, self.0 .0.reference(block))
 	}
 
-	fn dht_record(&self, block: u32, ttl: u64) -> Record {
+	fn dht_record(&self, reference: &str, ttl: u64, keypair: Option<&Keypair>) -> Record {
 		Record {
-			key: self.0 .0.reference(block).as_bytes().to_vec().into(),
-			value: self.0 .1.clone(),
+			key: reference.as_bytes().to_vec().into(),
+			value: signed_value(reference, self.0 .1.clone(), keypair),
 			publisher: None,
 			expires: Instant::now().checked_add(Duration::from_secs(ttl)),
 		}
@@ -71,6 +191,8 @@ pub struct BlockStat {
 	pub success_counter: usize,
 	pub error_counter: usize,
 	pub time_stat: u64,
+	/// Time of the first PUT of the block
+	pub started_at: Instant,
 }
 
 impl BlockStat {
@@ -78,28 +200,33 @@ impl BlockStat {
 		self.total_count += cell_number;
 		self.remaining_counter += cell_number;
 	}
+
+	pub fn put_stats(&self, is_completed: bool) -> BlockPutStats {
+		BlockPutStats {
+			total_count: self.total_count,
+			success_count: self.success_counter,
+			error_count: self.error_counter,
+			remaining_count: self.remaining_counter,
+			duration: self.started_at.elapsed(),
+			is_completed,
+		}
+	}
 }
 
 struct PruneExpiredRecords {
-	#[allow(dead_code)]
 	now: Instant,
 	response_sender: Option<oneshot::Sender<Result<usize>>>,
 }
 
-#[cfg(not(feature = "kademlia-rocksdb"))]
 impl Command for PruneExpiredRecords {
 	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
-		let store = entries.behavior_mut().kademlia.store_mut();
-
-		let before = store.records().count();
-		store.retain(|_, record| !record.is_expired(self.now));
-		let after = store.records().count();
+		let pruned = entries
+			.behavior_mut()
+			.kademlia
+			.store_mut()
+			.prune_expired_records(self.now);
 
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Ok(before - after))
-			.expect("PruneExpiredRecords receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Ok(pruned));
 
 		Ok(())
 	}
@@ -107,48 +234,60 @@ impl Command for PruneExpiredRecords {
 	fn abort(&mut self, _: Report) {}
 }
 
-#[cfg(feature = "kademlia-rocksdb")]
-impl Command for PruneExpiredRecords {
-	fn run(&mut self, _: EventLoopEntries) -> Result<(), Report> {
-		// Skip iterating all records from RocksDB, since TTL will be handled during compaction phase
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Ok(0))
-			.expect("PruneExpiredRecords receiver dropped");
+struct StartListening {
+	addr: Multiaddr,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for StartListening {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let listener_id = entries.swarm().listen_on(self.addr.clone())?;
+		entries
+			.listeners
+			.insert(listener_id, ListenerInfo::new(self.addr.clone()));
 
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(()));
 		Ok(())
 	}
 
-	fn abort(&mut self, _: Report) {}
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
 }
 
-struct StartListening {
+struct StopListening {
 	addr: Multiaddr,
 	response_sender: Option<oneshot::Sender<Result<()>>>,
 }
 
-impl Command for StartListening {
+impl Command for StopListening {
 	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
-		_ = entries.swarm().listen_on(self.addr.clone())?;
+		let listener_ids = entries
+			.listeners
+			.iter()
+			.filter(|(_, listener)| listener.address == self.addr)
+			.map(|(listener_id, _)| *listener_id)
+			.collect::<Vec<_>>();
+		if listener_ids.is_empty() {
+			return Err(eyre!("No listener started on {}", self.addr));
+		}
+		for listener_id in listener_ids {
+			entries.listeners.remove(&listener_id);
+			entries.swarm().remove_listener(listener_id);
+		}
 
 		// send result back
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Ok(()))
-			.expect("StartListening receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Ok(()));
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Err(error))
-			.expect("StartListening receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -170,6 +309,20 @@ impl Command for AddAddress {
 	fn abort(&mut self, _error: Report) {}
 }
 
+struct ExemptFromDialBackoff {
+	peer_id: PeerId,
+}
+
+impl Command for ExemptFromDialBackoff {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		entries.behavior_mut().dial_backoff.exempt(self.peer_id);
+
+		Ok(())
+	}
+
+	fn abort(&mut self, _error: Report) {}
+}
+
 struct Bootstrap {
 	response_sender: Option<oneshot::Sender<Result<()>>>,
 }
@@ -186,16 +339,13 @@ impl Command for Bootstrap {
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Err(error))
-			.expect("Bootstrap receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
 struct GetKadRecord {
 	key: RecordKey,
+	quorum: Quorum,
 	response_sender: Option<oneshot::Sender<Result<PeerRecord>>>,
 }
 
@@ -205,22 +355,200 @@ impl Command for GetKadRecord {
 
 		// insert response channel into KAD Queries pending map
 		let response_sender = self.response_sender.take().unwrap();
-		entries.insert_query(query_id, super::QueryChannel::GetRecord(response_sender));
+		entries.insert_query(
+			query_id,
+			super::QueryChannel::GetRecord {
+				response_sender: GetRecordSender::Single(response_sender),
+				quorum: self.quorum,
+				found: 0,
+				remote_only: false,
+			},
+		);
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct GetKadRecords {
+	keys: Vec<RecordKey>,
+	quorum: Quorum,
+	remote_only: bool,
+	response_sender: Option<oneshot::Sender<Result<Vec<Result<PeerRecord>>>>>,
+}
+
+impl Command for GetKadRecords {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let response_sender = self.response_sender.take().unwrap();
+		if self.keys.is_empty() {
+			_ = response_sender.send(Ok(vec![]));
+			return Ok(());
+		}
+
+		// results of all queries are sent back through the single batch response channel
+		let batch = Arc::new(Mutex::new(GetRecordBatch::new(
+			self.keys.len(),
+			response_sender,
+		)));
+		for (index, key) in self.keys.drain(..).enumerate() {
+			let query_id = entries.behavior_mut().kademlia.get_record(key);
+			entries.insert_query(
+				query_id,
+				QueryChannel::GetRecord {
+					response_sender: GetRecordSender::Batch {
+						batch: batch.clone(),
+						index,
+					},
+					quorum: self.quorum,
+					found: 0,
+					remote_only: self.remote_only,
+				},
+			);
+		}
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+/// Cancels DHT GET queries whose callers are no longer waiting for the result
+struct CancelQuery {
+	response_sender: Option<oneshot::Sender<Result<usize>>>,
+}
+
+impl Command for CancelQuery {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let query_ids = entries
+			.pending_kad_queries
+			.iter()
+			.filter(|(_, channel)| channel.is_abandoned())
+			.map(|(query_id, _)| *query_id)
+			.collect::<Vec<_>>();
+
+		for query_id in &query_ids {
+			entries.pending_kad_queries.remove(query_id);
+			if let Some(mut query) = entries.behavior_mut().kademlia.query_mut(query_id) {
+				query.finish();
+			}
+		}
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Err(error))
-			.expect("GetKadRecord receiver dropped");
+			.send(Ok(query_ids.len()));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct GetClosestPeers {
+	key: RecordKey,
+	response_sender: Option<oneshot::Sender<Result<Vec<(PeerId, Vec<Multiaddr>)>>>>,
+}
+
+impl Command for GetClosestPeers {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let query_id = entries
+			.behavior_mut()
+			.kademlia
+			.get_closest_peers(self.key.to_vec());
+
+		// insert response channel into KAD Queries pending map
+		let response_sender = self.response_sender.take().unwrap();
+		entries.insert_query(query_id, QueryChannel::GetClosestPeers(response_sender));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct GetProviders {
+	key: RecordKey,
+	response_sender: Option<oneshot::Sender<Result<HashSet<PeerId>>>>,
+}
+
+impl Command for GetProviders {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let query_id = entries
+			.behavior_mut()
+			.kademlia
+			.get_providers(self.key.clone());
+
+		// insert response channel into KAD Queries pending map
+		let response_sender = self.response_sender.take().unwrap();
+		entries.insert_query(query_id, QueryChannel::GetProviders(response_sender));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct ProvideCells {
+	key: RecordKey,
+	/// Cell contents mapped by the cell DHT keys
+	cells: Vec<(RecordKey, Vec<u8>)>,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for ProvideCells {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let kademlia = &mut entries.behavior_mut().kademlia;
+
+		kademlia.store_mut().prune_expired_providers(Instant::now());
+		match kademlia.start_providing(self.key.clone()) {
+			// the oldest provided block is replaced, so the new blocks are still provided
+			Err(store::Error::MaxProvidedKeys) => {
+				if let Some(oldest) = kademlia.store_mut().oldest_provided_key() {
+					debug!(
+						"Maximum number of provided keys reached, stopped providing the oldest key"
+					);
+					kademlia.stop_providing(&oldest);
+				}
+				kademlia.start_providing(self.key.clone())?;
+			},
+			result => {
+				result?;
+			},
+		}
+		// cells are served from the provided content, so they are not replicated to the DHT
+		kademlia
+			.store_mut()
+			.add_provided_content(&self.key, std::mem::take(&mut self.cells));
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
 struct PutKadRecord {
-	records: Vec<Record>,
+	/// Records with optional per-record result channels
+	records: Vec<(Record, Option<PutResultSender>)>,
 	quorum: Quorum,
 	block_num: u32,
 }
@@ -240,16 +568,10 @@ impl Command for PutKadRecord {
 				success_counter: 0,
 				error_counter: 0,
 				time_stat: 0,
+				started_at: Instant::now(),
 			});
 
-		for record in self.records.clone() {
-			let query_id = entries
-				.behavior_mut()
-				.kademlia
-				.put_record(record, self.quorum)
-				.expect("Unable to perform Kademlia PUT operation.");
-			entries.insert_query(query_id, QueryChannel::PutRecord);
-		}
+		entries.insert_puts(self.records.drain(..), self.quorum);
 		Ok(())
 	}
 
@@ -276,21 +598,80 @@ impl Command for CountKademliaPeers {
 				total_peers += 1;
 			}
 		}
-		self.response_sender
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Ok((total_peers, peers_with_non_pvt_addr)))
-			.expect("CountKademliaPeers receiver dropped");
+			.send(Ok((total_peers, peers_with_non_pvt_addr)));
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct AddExternalAddress {
+	address: Multiaddr,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for AddExternalAddress {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
+		entries.swarm().add_external_address(self.address.clone());
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for AddExternalAddress");
+	}
+}
+
+struct GetNatStatus {
+	response_sender: Option<oneshot::Sender<Result<NatInfo>>>,
+}
+
+impl Command for GetNatStatus {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.nat_info()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetNatStatus");
+	}
+}
+
+struct GetKademliaMode {
+	response_sender: Option<oneshot::Sender<Result<Mode>>>,
+}
+
+impl Command for GetKademliaMode {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report> {
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Err(error))
-			.expect("CountDHTPeers receiver dropped");
+			.send(Ok(*entries.kad_mode));
+
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -303,36 +684,25 @@ impl Command for GetLocalInfo {
 		let public_listeners: Vec<String> = entries
 			.swarm
 			.external_addresses()
-			.filter(|multiaddr| {
-				multiaddr.iter().any(
-					|protocol| matches!(protocol, libp2p::multiaddr::Protocol::Ip4(ip) if is_global(ip)),
-				)
-			})
+			.filter(|multiaddr| is_multiaddr_global(multiaddr))
 			.map(ToString::to_string)
 			.collect();
 
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Ok(PeerInfo {
-				peer_id: entries.peer_id().to_string(),
-				operation_mode: entries.kad_mode.to_string(),
-				peer_multiaddr: None,
-				local_listeners: entries.listeners(),
-				external_listeners: entries.external_address(),
-				public_listeners,
-			}))
-			.expect("GetLocalInfo receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Ok(PeerInfo {
+			peer_id: entries.peer_id().to_string(),
+			operation_mode: entries.kad_mode.to_string(),
+			peer_multiaddr: None,
+			local_listeners: entries.listeners(),
+			external_listeners: entries.external_address(),
+			public_listeners,
+			port_mapping: entries.port_mapping(),
+		}));
 
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Err(error))
-			.expect("GetLocalInfo receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -355,25 +725,51 @@ impl Command for GetExternalPeerInfo {
 			}
 		}
 
-		self.response_sender
+		let dial_failures = entries
+			.swarm
+			.behaviour()
+			.dial_backoff
+			.failures(&self.peer_id);
+
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
 			.send(Ok(MultiAddressInfo {
 				multiaddresses,
 				peer_id: self.peer_id.to_string(),
-			}))
-			.expect("GetExternalPeerInfo receiver dropped");
+				dial_failures,
+				identity: entries.peer_identities.peek(&self.peer_id).cloned(),
+			}));
 
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct ListConnectedPeersWithLatency {
+	response_sender: Option<oneshot::Sender<Result<Vec<(PeerId, Option<PeerLatency>)>>>>,
+}
+
+impl Command for ListConnectedPeersWithLatency {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<()> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Err(error))
-			.expect("GetExternalPeerInfo receiver dropped");
+			.send(Ok(entries.connected_peers_with_latency()));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -391,21 +787,17 @@ impl Command for ListConnectedPeers {
 
 		// send result back
 		// TODO: consider what to do if this results with None
-		self.response_sender
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Ok(connected_peer_list))
-			.expect("CountDHTPeers receiver dropped");
+			.send(Ok(connected_peer_list));
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Err(error))
-			.expect("CountDHTPeers receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -417,7 +809,10 @@ struct ReconfigureKademliaMode {
 
 impl Command for ReconfigureKademliaMode {
 	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
-		if matches!(entries.kad_mode, Mode::Client) && !entries.external_address().is_empty() {
+		if *entries.is_kad_mode_pinned {
+			trace!("Kademlia mode is set explicitly, skipping reconfiguration");
+		} else if matches!(entries.kad_mode, Mode::Client) && !entries.external_address().is_empty()
+		{
 			const BYTES_IN_GB: usize = 1024 * 1024 * 1024;
 
 			let system = System::new_all();
@@ -439,21 +834,66 @@ impl Command for ReconfigureKademliaMode {
 
 		// send result back
 		// TODO: consider what to do if this results with None
-		self.response_sender
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Ok(*entries.kad_mode))
-			.expect("ReconfigureKademliaMode receiver dropped");
+			.send(Ok(*entries.kad_mode));
 		Ok(())
 	}
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Err(error))
-			.expect("ReconfigureKademliaMode receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct SetMdnsEnabled {
+	is_enabled: bool,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for SetMdnsEnabled {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		entries
+			.behavior_mut()
+			.mdns
+			.set_enabled(self.is_enabled)
+			.wrap_err("Unable to toggle mDNS")?;
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct SetKademliaMode {
+	mode: Mode,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for SetKademliaMode {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		info!("Setting Kademlia mode to {}", self.mode);
+		entries.behavior_mut().kademlia.set_mode(Some(self.mode));
+		*entries.kad_mode = self.mode;
+		*entries.is_kad_mode_pinned = true;
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -467,11 +907,7 @@ impl Command for ReduceKademliaMapSize {
 
 		// send result back
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Ok(()))
-			.expect("ReduceKademliaMapSize receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Ok(()));
 		Ok(())
 	}
 
@@ -494,11 +930,7 @@ impl Command for GetKademliaMapSize {
 			.records()
 			.count();
 
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Ok(size))
-			.expect("GetKademliaMapSize receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Ok(size));
 		Ok(())
 	}
 
@@ -508,6 +940,208 @@ impl Command for GetKademliaMapSize {
 	}
 }
 
+struct SetLatestBlock {
+	block_num: u32,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for SetLatestBlock {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
+		entries
+			.behavior_mut()
+			.kademlia
+			.store_mut()
+			.set_latest_block(self.block_num);
+
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for SetLatestBlock");
+	}
+}
+
+struct StoreRecord {
+	record: Option<Record>,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for StoreRecord {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
+		let record = self.record.take().unwrap();
+		let result = entries
+			.behavior_mut()
+			.kademlia
+			.store_mut()
+			.put(record)
+			.map_err(|error| eyre!("Unable to store record: {error}"));
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(result);
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct PruneBlock {
+	block_num: u32,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for PruneBlock {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
+		entries
+			.behavior_mut()
+			.kademlia
+			.store_mut()
+			.prune_block(self.block_num);
+
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for PruneBlock");
+	}
+}
+
+struct GetKademliaStoreStats {
+	response_sender: Option<oneshot::Sender<Result<StoreStats>>>,
+}
+
+impl Command for GetKademliaStoreStats {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
+		let stats = entries.behavior_mut().kademlia.store_mut().stats();
+
+		_ = self.response_sender.take().unwrap().send(Ok(stats));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetKademliaStoreStats");
+	}
+}
+
+struct GetBandwidthStats {
+	response_sender: Option<oneshot::Sender<Result<BandwidthStats>>>,
+}
+
+impl Command for GetBandwidthStats {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.bandwidth_stats()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetBandwidthStats");
+	}
+}
+
+struct GetHolePunchStats {
+	response_sender: Option<oneshot::Sender<Result<HolePunchStats>>>,
+}
+
+impl Command for GetHolePunchStats {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.holepunch_stats()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetHolePunchStats");
+	}
+}
+
+struct GetBlockPutStats {
+	block_num: u32,
+	response_sender: Option<oneshot::Sender<Result<Option<BlockPutStats>>>>,
+}
+
+impl Command for GetBlockPutStats {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.block_put_stats(self.block_num)));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetBlockPutStats");
+	}
+}
+
+struct GetQueryStats {
+	response_sender: Option<oneshot::Sender<Result<DHTQueryStats>>>,
+}
+
+impl Command for GetQueryStats {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<(), Report> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.query_stats()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetQueryStats");
+	}
+}
+
+struct GetPeerChurnReport {
+	response_sender: Option<oneshot::Sender<Result<PeerChurnReport>>>,
+}
+
+impl Command for GetPeerChurnReport {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<(), Report> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.peer_churn_report()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for GetPeerChurnReport");
+	}
+}
+
 struct DialPeer {
 	peer_id: PeerId,
 	peer_address: Vec<Multiaddr>,
@@ -520,6 +1154,8 @@ impl Command for DialPeer {
 			.addresses(self.peer_address.clone())
 			.build();
 
+		// explicit dials are not denied by the backoff of the previous failures
+		entries.behavior_mut().dial_backoff.reset(&self.peer_id);
 		entries.swarm().dial(opts)?;
 
 		// insert response channel into Swarm Events pending map
@@ -529,11 +1165,7 @@ impl Command for DialPeer {
 
 	fn abort(&mut self, error: Report) {
 		// TODO: consider what to do if this results with None
-		self.response_sender
-			.take()
-			.unwrap()
-			.send(Err(error))
-			.expect("DialPeer receiver dropped");
+		_ = self.response_sender.take().unwrap().send(Err(error));
 	}
 }
 
@@ -552,40 +1184,441 @@ impl Command for AddAutonatServer {
 
 		// send result back
 		// TODO: consider what to do if this results with None
-		self.response_sender
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for AddAutonatServer command");
+	}
+}
+
+struct FetchCellsFromPeer {
+	peer_id: PeerId,
+	request: Option<cell_fetch::Request>,
+	response_sender: Option<oneshot::Sender<Result<cell_fetch::Response>>>,
+}
+
+impl Command for FetchCellsFromPeer {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let request = self.request.take().unwrap();
+		let request_id = entries
+			.behavior_mut()
+			.cell_fetch
+			.send_request(&self.peer_id, request);
+
+		// insert response channel into Cell Fetch pending map
+		entries.insert_cell_fetch(request_id, self.response_sender.take().unwrap());
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct SubscribeBlockAnnouncements {
+	response_sender: Option<oneshot::Sender<Result<broadcast::Receiver<BlockAnnouncement>>>>,
+}
+
+impl Command for SubscribeBlockAnnouncements {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<()> {
+		let receiver = entries.subscribe_block_announcements();
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(receiver));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for SubscribeBlockAnnouncements command");
+	}
+}
+
+struct PublishBlockAnnouncement {
+	announcement: Vec<u8>,
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for PublishBlockAnnouncement {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		entries
+			.behavior_mut()
+			.gossipsub
+			.publish(block_announcements_topic(), self.announcement.clone())?;
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(()));
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+}
+
+struct BlockPeers {
+	peer_ids: Vec<PeerId>,
+	response_sender: Option<oneshot::Sender<Result<Vec<PeerId>>>>,
+}
+
+impl Command for BlockPeers {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		for peer_id in self.peer_ids.drain(..) {
+			entries.block_peer(peer_id);
+		}
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
 			.take()
 			.unwrap()
-			.send(Ok(()))
-			.expect("AddAutonatServer receiver dropped");
+			.send(Ok(entries.blocked_peers()));
 		Ok(())
 	}
 
-	fn abort(&mut self, _: Report) {
-		// theres should be no errors from running this Command
-		debug!("No possible errors for AddAutonatServer command");
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for BlockPeers command");
+	}
+}
+
+struct UnblockPeer {
+	peer_id: PeerId,
+	response_sender: Option<oneshot::Sender<Result<Vec<PeerId>>>>,
+}
+
+impl Command for UnblockPeer {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		entries.unblock_peer(self.peer_id);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.blocked_peers()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for UnblockPeer command");
+	}
+}
+
+struct ListBlockedPeers {
+	response_sender: Option<oneshot::Sender<Result<Vec<PeerId>>>>,
+}
+
+impl Command for ListBlockedPeers {
+	fn run(&mut self, entries: EventLoopEntries) -> Result<()> {
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self
+			.response_sender
+			.take()
+			.unwrap()
+			.send(Ok(entries.blocked_peers()));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ListBlockedPeers command");
+	}
+}
+
+struct ExportRoutingTable {
+	response_sender: Option<oneshot::Sender<Result<Vec<RoutingTableEntry>>>>,
+}
+
+impl Command for ExportRoutingTable {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let routing_table = routing_table::export(&mut entries.behavior_mut().kademlia);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(routing_table));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ExportRoutingTable command");
+	}
+}
+
+struct ImportRoutingTable {
+	routing_table: Vec<RoutingTableEntry>,
+	response_sender: Option<oneshot::Sender<Result<usize>>>,
+}
+
+impl Command for ImportRoutingTable {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let imported = routing_table::import(
+			&mut entries.behavior_mut().kademlia,
+			std::mem::take(&mut self.routing_table),
+		);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(imported));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ImportRoutingTable command");
+	}
+}
+
+struct ExportRecords {
+	path: String,
+	response_sender: Option<oneshot::Sender<Result<usize>>>,
+}
+
+impl Command for ExportRecords {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let result =
+			record_snapshot::export(entries.behavior_mut().kademlia.store_mut(), &self.path);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(result);
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ExportRecords command");
+	}
+}
+
+struct ImportRecords {
+	path: String,
+	response_sender: Option<oneshot::Sender<Result<usize>>>,
+}
+
+impl Command for ImportRecords {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let result =
+			record_snapshot::import(entries.behavior_mut().kademlia.store_mut(), &self.path);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(result);
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for ImportRecords command");
+	}
+}
+
+struct RepublishRecords {
+	block_range: BlockRange,
+	cell_ttl: TimeToLive,
+	row_ttl: TimeToLive,
+	response_sender: Option<oneshot::Sender<Result<usize>>>,
+}
+
+impl Command for RepublishRecords {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		let block_range = &self.block_range;
+		let records = records_to_republish(
+			&mut entries.behavior_mut().kademlia,
+			&self.cell_ttl,
+			&self.row_ttl,
+			|record| match DHTKey::try_from(record.key.clone()) {
+				Ok(DHTKey::Cell(block_num, _, _)) | Ok(DHTKey::Row(block_num, _)) => {
+					block_range.contains(block_num)
+				},
+				Err(_) => false,
+			},
+			None,
+		);
+		let republished = records.len();
+		entries.insert_republish_puts(records);
+
+		// send result back
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Ok(republished));
+		Ok(())
+	}
+
+	fn abort(&mut self, _: Report) {
+		// theres should be no errors from running this Command
+		debug!("No possible errors for RepublishRecords command");
+	}
+}
+
+struct Shutdown {
+	response_sender: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Command for Shutdown {
+	fn run(&mut self, mut entries: EventLoopEntries) -> Result<()> {
+		// response is sent once the event loop drains pending queries and exits
+		entries.request_shutdown(self.response_sender.take().unwrap());
+		Ok(())
+	}
+
+	fn abort(&mut self, error: Report) {
+		// TODO: consider what to do if this results with None
+		_ = self.response_sender.take().unwrap().send(Err(error));
+	}
+
+	// shutdown already in progress is awaited
+	fn is_allowed_on_shutdown(&self) -> bool {
+		true
+	}
+}
+
+impl Client {
+	pub fn new(
+		sender: CommandSender,
+		dht_parallelization_limit: usize,
+		dht_max_parallelization_limit: Option<usize>,
+		cell_ttl: u64,
+		row_ttl: u64,
+		put_quorum: Quorum,
+		get_quorum: Quorum,
+		network_prefix: Option<String>,
+		cell_cache_size: usize,
+		signing_keypair: Option<Keypair>,
+		require_signed_records: bool,
+	) -> Self {
+		Self {
+			command_sender: sender,
+			dht_parallelization: Arc::new(Mutex::new(ParallelizationLimit::new(
+				dht_parallelization_limit,
+				dht_max_parallelization_limit,
+			))),
+			cell_ttl,
+			row_ttl,
+			put_quorum,
+			get_quorum,
+			network_prefix,
+			publication_paused: Arc::new(AtomicBool::new(false)),
+			cell_cache: NonZeroUsize::new(cell_cache_size)
+				.map(|size| Arc::new(Mutex::new(LruCache::new(size)))),
+			signing_keypair,
+			require_signed_records,
+			invalid_records: Default::default(),
+		}
+	}
+
+	/// Counts the invalid records received from the peers, and blocks the peers which sent too many of them
+	pub(crate) async fn report_invalid_records(&self, peer_ids: Vec<PeerId>) {
+		let blocked = {
+			let mut invalid_records = self.invalid_records.lock().unwrap();
+			let now = Instant::now();
+			peer_ids
+				.into_iter()
+				.filter_map(|peer_id| {
+					invalid_records
+						.count(peer_id, now)
+						.map(|count| (peer_id, count))
+				})
+				.collect::<Vec<_>>()
+		};
+		for (peer_id, count) in blocked {
+			warn!("Blocking peer {peer_id} for sending {count} invalid records");
+			if let Err(error) = self.block_peers(vec![peer_id]).await {
+				warn!("Unable to block peer {peer_id}: {error:#}");
+			}
+		}
+	}
+
+	/// Adds verified cells to the local cell cache, if enabled
+	pub fn cache_cells(&self, block_number: u32, cells: &[Cell]) {
+		let Some(cell_cache) = &self.cell_cache else {
+			return;
+		};
+		let mut cell_cache = cell_cache.lock().unwrap();
+		for cell in cells {
+			let reference = prefixed_reference(self.network_prefix(), cell.reference(block_number));
+			cell_cache.put(reference, cell.clone());
+		}
+	}
+
+	fn cached_cell(&self, reference: &str) -> Option<Cell> {
+		let cell_cache = self.cell_cache.as_ref()?;
+		cell_cache.lock().unwrap().get(reference).cloned()
+	}
+
+	fn dht_parallelization_limit(&self) -> usize {
+		self.dht_parallelization.lock().unwrap().limit()
+	}
+
+	/// Sets the number of cells fetched from the DHT in parallel
+	pub fn set_dht_parallelization_limit(&self, limit: usize) {
+		self.dht_parallelization.lock().unwrap().set_limit(limit);
+	}
+
+	/// Returns current number of cells fetched from the DHT in parallel, and the average duration of the fetch batches
+	pub fn get_dht_parallelization_stats(&self) -> ParallelizationStats {
+		self.dht_parallelization.lock().unwrap().stats()
+	}
+
+	/// Pauses or resumes publishing to the DHT.
+	/// Returns previous publication state, so callers can detect state changes.
+	pub fn set_publication_paused(&self, paused: bool) -> bool {
+		self.publication_paused.swap(paused, Ordering::Relaxed)
+	}
+
+	pub fn is_publication_paused(&self) -> bool {
+		self.publication_paused.load(Ordering::Relaxed)
 	}
-}
 
-impl Client {
-	pub fn new(sender: CommandSender, dht_parallelization_limit: usize, ttl: u64) -> Self {
-		Self {
-			command_sender: sender,
-			dht_parallelization_limit,
-			ttl,
-		}
+	fn network_prefix(&self) -> Option<&str> {
+		self.network_prefix.as_deref()
 	}
 
 	async fn execute_sync<F, T>(&self, command_with_sender: F) -> Result<T>
+	where
+		F: FnOnce(oneshot::Sender<Result<T>>) -> SendableCommand,
+	{
+		self.execute_sync_with_priority(CommandPriority::Normal, command_with_sender)
+			.await
+	}
+
+	/// Executes the command ahead of the queued normal priority commands,
+	/// used for the interactive operations like sampling cell fetches
+	async fn execute_sync_interactive<F, T>(&self, command_with_sender: F) -> Result<T>
+	where
+		F: FnOnce(oneshot::Sender<Result<T>>) -> SendableCommand,
+	{
+		self.execute_sync_with_priority(CommandPriority::High, command_with_sender)
+			.await
+	}
+
+	async fn execute_sync_with_priority<F, T>(
+		&self,
+		priority: CommandPriority,
+		command_with_sender: F,
+	) -> Result<T>
 	where
 		F: FnOnce(oneshot::Sender<Result<T>>) -> SendableCommand,
 	{
 		let (response_sender, response_receiver) = oneshot::channel();
 		let command = command_with_sender(response_sender);
 		self.command_sender
-			.send(command)
+			.send(TracedCommand::with_priority(command, priority))
 			.wrap_err("receiver should not be dropped")?;
-		response_receiver
+		tokio::time::timeout(COMMAND_TIMEOUT, response_receiver)
 			.await
+			.map_err(|_| eyre!("Command timed out after {COMMAND_TIMEOUT:?}"))?
 			.wrap_err("sender should not be dropped")?
 	}
 
@@ -599,12 +1632,35 @@ impl Client {
 		.await
 	}
 
+	/// Closes the listeners started on the address
+	pub async fn stop_listening(&self, addr: Multiaddr) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(StopListening {
+				addr,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
 	pub async fn add_address(&self, peer_id: PeerId, peer_addr: Multiaddr) -> Result<()> {
 		self.command_sender
-			.send(Box::new(AddAddress { peer_id, peer_addr }))
+			.send(TracedCommand::new(Box::new(AddAddress {
+				peer_id,
+				peer_addr,
+			})))
 			.context("failed to add address to the routing table")
 	}
 
+	/// Exempts the peer from the dial backoff, so it is dialed regardless of the previous failures
+	pub async fn exempt_from_dial_backoff(&self, peer_id: PeerId) -> Result<()> {
+		self.command_sender
+			.send(TracedCommand::new(Box::new(ExemptFromDialBackoff {
+				peer_id,
+			})))
+			.context("failed to exempt peer from the dial backoff")
+	}
+
 	pub async fn dial_peer(
 		&self,
 		peer_id: PeerId,
@@ -642,6 +1698,7 @@ impl Client {
 
 	pub async fn bootstrap_on_startup(&self, nodes: Vec<(PeerId, Multiaddr)>) -> Result<()> {
 		for (peer, addr) in nodes {
+			self.exempt_from_dial_backoff(peer).await?;
 			self.dial_peer(peer, vec![addr.clone()])
 				.await
 				.wrap_err("Dialing Bootstrap peer failed.")?;
@@ -652,96 +1709,444 @@ impl Client {
 		self.bootstrap().await
 	}
 
-	async fn get_kad_record(&self, key: RecordKey) -> Result<PeerRecord> {
+	/// Gets the batch of DHT records with a single command, results are in the order of the keys.
+	/// Each result is returned with the duration of its query.
+	pub async fn get_kad_records(
+		&self,
+		keys: Vec<RecordKey>,
+		quorum: Quorum,
+	) -> Result<Vec<(Result<PeerRecord>, Duration)>> {
+		self.get_records(keys, quorum, false).await
+	}
+
+	async fn get_records(
+		&self,
+		keys: Vec<RecordKey>,
+		quorum: Quorum,
+		remote_only: bool,
+	) -> Result<Vec<(Result<PeerRecord>, Duration)>> {
+		self.execute_sync_interactive(|response_sender| {
+			Box::new(GetKadRecords {
+				keys,
+				quorum,
+				remote_only,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	async fn get_kad_record(&self, key: RecordKey, quorum: Quorum) -> Result<PeerRecord> {
+		self.execute_sync_interactive(|response_sender| {
+			Box::new(GetKadRecord {
+				key,
+				quorum,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	async fn put_kad_record(
+		&self,
+		records: Vec<Record>,
+		quorum: Quorum,
+		block_num: u32,
+	) -> Result<()> {
+		self.command_sender
+			.send(TracedCommand::new(Box::new(PutKadRecord {
+				records: records.into_iter().map(|record| (record, None)).collect(),
+				quorum,
+				block_num,
+			})))
+			.context("receiver should not be dropped")
+	}
+
+	pub async fn count_dht_entries(&self) -> Result<(usize, usize)> {
+		self.execute_sync(|response_sender| {
+			Box::new(CountKademliaPeers {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn list_connected_peers(&self) -> Result<Vec<String>> {
+		self.execute_sync(|response_sender| {
+			Box::new(ListConnectedPeers {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Returns connected peers with the latest and average ping round-trip times.
+	/// Latency is not set for the peers which were not pinged yet.
+	pub async fn list_connected_peers_with_latency(
+		&self,
+	) -> Result<Vec<(PeerId, Option<PeerLatency>)>> {
+		self.execute_sync(|response_sender| {
+			Box::new(ListConnectedPeersWithLatency {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn reconfigure_kademlia_mode(
+		&self,
+		memory_gb_threshold: f64,
+		cpus_threshold: usize,
+	) -> Result<Mode> {
+		self.execute_sync(|response_sender| {
+			Box::new(ReconfigureKademliaMode {
+				response_sender: Some(response_sender),
+				memory_gb_threshold,
+				cpus_threshold,
+			})
+		})
+		.await
+	}
+
+	/// Enables or disables mDNS discovery of the peers in the local network
+	pub async fn set_mdns_enabled(&self, is_enabled: bool) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(SetMdnsEnabled {
+				is_enabled,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Sets the Kademlia mode explicitly, disabling the automatic mode reconfiguration
+	pub async fn set_kademlia_mode(&self, mode: Mode) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(SetKademliaMode {
+				mode,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Registers a known public address of the node with the swarm, without waiting for AutoNAT or UPnP confirmation.
+	/// Kademlia server mode is enabled on the next mode reconfiguration, if automatic server mode is set.
+	pub async fn add_external_address(&self, address: Multiaddr) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(AddExternalAddress {
+				address,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Returns NAT status detected by AutoNAT (public, private or unknown), with the servers which confirmed it
+	pub async fn get_nat_status(&self) -> Result<NatInfo> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetNatStatus {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn get_kademlia_mode(&self) -> Result<Mode> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetKademliaMode {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn get_local_info(&self) -> Result<PeerInfo> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetLocalInfo {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn get_external_peer_info(&self, peer_id: PeerId) -> Result<MultiAddressInfo> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetExternalPeerInfo {
+				peer_id,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub(crate) async fn block_peers(&self, peer_ids: Vec<PeerId>) -> Result<Vec<PeerId>> {
+		self.execute_sync(|response_sender| {
+			Box::new(BlockPeers {
+				peer_ids,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Blocks connections to the given peer, closing the active ones.
+	/// Updated blocklist is persisted, so it survives restarts.
+	pub async fn block_peer(&self, db: impl Database, peer_id: PeerId) -> Result<()> {
+		let blocked_peers = self.block_peers(vec![peer_id]).await?;
+		db.put(
+			BlockedPeersKey,
+			blocked_peers.iter().map(ToString::to_string).collect(),
+		);
+		Ok(())
+	}
+
+	/// Unblocks connections to the given peer and persists updated blocklist.
+	pub async fn unblock_peer(&self, db: impl Database, peer_id: PeerId) -> Result<()> {
+		let blocked_peers = self
+			.execute_sync(|response_sender| {
+				Box::new(UnblockPeer {
+					peer_id,
+					response_sender: Some(response_sender),
+				})
+			})
+			.await?;
+		db.put(
+			BlockedPeersKey,
+			blocked_peers.iter().map(ToString::to_string).collect(),
+		);
+		Ok(())
+	}
+
+	pub async fn list_blocked_peers(&self) -> Result<Vec<PeerId>> {
+		self.execute_sync(|response_sender| {
+			Box::new(ListBlockedPeers {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Blocks peers from the persisted blocklist, skipping the invalid entries.
+	pub async fn restore_blocked_peers(&self, db: impl Database) -> Result<()> {
+		let peer_ids = db
+			.get(BlockedPeersKey)
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|peer_id| match PeerId::from_str(peer_id) {
+				Ok(peer_id) => Some(peer_id),
+				Err(error) => {
+					debug!("Skipping invalid blocked peer {peer_id}: {error}");
+					None
+				},
+			})
+			.collect::<Vec<_>>();
+
+		if peer_ids.is_empty() {
+			return Ok(());
+		}
+
+		info!("Restoring {} blocked peers", peer_ids.len());
+		self.block_peers(peer_ids).await?;
+		Ok(())
+	}
+
+	/// Returns peers and their addresses from the Kademlia routing table.
+	pub async fn export_routing_table(&self) -> Result<Vec<RoutingTableEntry>> {
+		self.execute_sync(|response_sender| {
+			Box::new(ExportRoutingTable {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Adds peers to the Kademlia routing table.
+	/// Returns number of peers added to the routing table.
+	pub async fn import_routing_table(
+		&self,
+		routing_table: Vec<RoutingTableEntry>,
+	) -> Result<usize> {
+		self.execute_sync(|response_sender| {
+			Box::new(ImportRoutingTable {
+				routing_table,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Writes records from the Kademlia store into the snapshot file.
+	/// Returns number of exported records.
+	pub async fn export_records(&self, path: &str) -> Result<usize> {
+		self.execute_sync(|response_sender| {
+			Box::new(ExportRecords {
+				path: path.to_string(),
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Puts records from the snapshot file into the Kademlia store, skipping the expired ones.
+	/// Returns number of imported records.
+	pub async fn import_records(&self, path: &str) -> Result<usize> {
+		self.execute_sync(|response_sender| {
+			Box::new(ImportRecords {
+				path: path.to_string(),
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Finds peers closest to the given key, which are responsible for storing its record.
+	/// Returns peer IDs with their known addresses, addresses are empty for peers outside of the routing table.
+	pub async fn get_closest_peers(&self, key: RecordKey) -> Result<Vec<(PeerId, Vec<Multiaddr>)>> {
+		self.execute_sync(|response_sender| {
+			Box::new(GetClosestPeers {
+				key,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Republishes locally held cells and rows of the given blocks, refreshing their expiry.
+	/// Records are queued behind the pending PUTs, within the in-flight PUT limit.
+	/// Returns number of queued records.
+	pub async fn republish_records(&self, block_range: BlockRange) -> Result<usize> {
+		self.execute_sync(|response_sender| {
+			Box::new(RepublishRecords {
+				block_range,
+				cell_ttl: TimeToLive(Duration::from_secs(self.cell_ttl)),
+				row_ttl: TimeToLive(Duration::from_secs(self.row_ttl)),
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Gracefully shuts down the network event loop, or waits for the shutdown already in progress.
+	/// New commands are rejected, while pending Kademlia queries are given time to resolve
+	/// (up to the query timeout), before the record store is flushed and peers are disconnected.
+	pub async fn shutdown(&self) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(Shutdown {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	// Reduces the size of Kademlias underlying hashmap
+	pub async fn shrink_kademlia_map(&self) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(ReduceKademliaMapSize {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	pub async fn get_kademlia_map_size(&self) -> Result<usize> {
 		self.execute_sync(|response_sender| {
-			Box::new(GetKadRecord {
-				key,
+			Box::new(GetKademliaMapSize {
 				response_sender: Some(response_sender),
 			})
 		})
 		.await
 	}
 
-	async fn put_kad_record(
-		&self,
-		records: Vec<Record>,
-		quorum: Quorum,
-		block_num: u32,
-	) -> Result<()> {
-		self.command_sender
-			.send(Box::new(PutKadRecord {
-				records,
-				quorum,
+	/// Puts the record into the local Kademlia store, without publishing it to the DHT
+	pub async fn store_record(&self, record: Record) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(StoreRecord {
+				record: Some(record),
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Sets the latest finalized block, records outside of the recency window are not stored
+	pub async fn set_latest_block(&self, block_num: u32) -> Result<()> {
+		self.execute_sync(|response_sender| {
+			Box::new(SetLatestBlock {
 				block_num,
-			}))
-			.context("receiver should not be dropped")
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
 	}
 
-	pub async fn count_dht_entries(&self) -> Result<(usize, usize)> {
+	/// Removes all cell and row records of the given block from the local Kademlia store,
+	/// without waiting for the records to expire.
+	pub async fn prune_block(&self, block_num: u32) -> Result<()> {
 		self.execute_sync(|response_sender| {
-			Box::new(CountKademliaPeers {
+			Box::new(PruneBlock {
+				block_num,
 				response_sender: Some(response_sender),
 			})
 		})
 		.await
 	}
 
-	pub async fn list_connected_peers(&self) -> Result<Vec<String>> {
+	/// Returns number of records in the Kademlia store, their total size and number of evicted records
+	/// Returns bytes received and sent by protocol, and for the peers with the most traffic
+	pub async fn get_bandwidth_stats(&self) -> Result<BandwidthStats> {
 		self.execute_sync(|response_sender| {
-			Box::new(ListConnectedPeers {
+			Box::new(GetBandwidthStats {
 				response_sender: Some(response_sender),
 			})
 		})
 		.await
 	}
 
-	pub async fn reconfigure_kademlia_mode(
-		&self,
-		memory_gb_threshold: f64,
-		cpus_threshold: usize,
-	) -> Result<Mode> {
+	pub async fn get_holepunch_stats(&self) -> Result<HolePunchStats> {
 		self.execute_sync(|response_sender| {
-			Box::new(ReconfigureKademliaMode {
+			Box::new(GetHolePunchStats {
 				response_sender: Some(response_sender),
-				memory_gb_threshold,
-				cpus_threshold,
 			})
 		})
 		.await
 	}
 
-	pub async fn get_local_info(&self) -> Result<PeerInfo> {
+	/// Returns DHT PUT statistics of the block, if the block was published recently
+	pub async fn get_block_put_stats(&self, block_num: u32) -> Result<Option<BlockPutStats>> {
 		self.execute_sync(|response_sender| {
-			Box::new(GetLocalInfo {
+			Box::new(GetBlockPutStats {
+				block_num,
 				response_sender: Some(response_sender),
 			})
 		})
 		.await
 	}
 
-	pub async fn get_external_peer_info(&self, peer_id: PeerId) -> Result<MultiAddressInfo> {
+	/// Returns session counts and uptime of the routing table peers,
+	/// which can be used to choose the reliable peers for direct cell fetches and relay reservations
+	pub async fn get_peer_churn_report(&self) -> Result<PeerChurnReport> {
 		self.execute_sync(|response_sender| {
-			Box::new(GetExternalPeerInfo {
-				peer_id,
+			Box::new(GetPeerChurnReport {
 				response_sender: Some(response_sender),
 			})
 		})
 		.await
 	}
 
-	// Reduces the size of Kademlias underlying hashmap
-	pub async fn shrink_kademlia_map(&self) -> Result<()> {
+	/// Returns the duration and contacted peers statistics of the completed DHT GET and PUT queries
+	pub async fn get_query_stats(&self) -> Result<DHTQueryStats> {
 		self.execute_sync(|response_sender| {
-			Box::new(ReduceKademliaMapSize {
+			Box::new(GetQueryStats {
 				response_sender: Some(response_sender),
 			})
 		})
 		.await
 	}
 
-	pub async fn get_kademlia_map_size(&self) -> Result<usize> {
+	pub async fn get_kademlia_store_stats(&self) -> Result<StoreStats> {
 		self.execute_sync(|response_sender| {
-			Box::new(GetKademliaMapSize {
+			Box::new(GetKademliaStoreStats {
 				response_sender: Some(response_sender),
 			})
 		})
@@ -759,32 +2164,98 @@ impl Client {
 	}
 
 	// Since callers ignores DHT errors, debug logs are used to observe DHT behavior.
-	// Return type assumes that cell is not found in case when error is present.
-	async fn fetch_cell_from_dht(&self, block_number: u32, position: Position) -> Option<Cell> {
-		let reference = position.reference(block_number);
+	// Return type assumes that cell is not found in case when error is present,
+	// and the failure reason is counted in the fetch stats.
+	async fn fetch_cell_from_dht(
+		&self,
+		block_number: u32,
+		position: Position,
+		quorum: Quorum,
+	) -> (Option<Cell>, DHTFetchStats) {
+		let reference = prefixed_reference(self.network_prefix(), position.reference(block_number));
+		if let Some(cell) = self.cached_cell(&reference) {
+			trace!("Cell {reference} found in the local cache");
+			return (Some(cell), DHTFetchStats::cached());
+		}
 		let record_key = RecordKey::from(reference.as_bytes().to_vec());
 
 		trace!("Getting DHT record for reference {}", reference);
 
-		match self.get_kad_record(record_key).await {
-			Ok(peer_record) => {
-				trace!("Fetched cell {reference} from the DHT");
-
-				let try_content: Result<[u8; config::COMMITMENT_SIZE + config::CHUNK_SIZE], _> =
-					peer_record.record.value.try_into();
+		let started = Instant::now();
+		let result = self.get_kad_record(record_key, quorum).await;
+		let duration = started.elapsed();
+		let mut invalid_senders = vec![];
+		let cell = cell_from_record(
+			&reference,
+			position,
+			result,
+			self.require_signed_records,
+			&mut invalid_senders,
+		);
+		self.report_invalid_records(invalid_senders).await;
+		let stats = DHTFetchStats::query(&cell, duration);
+		(cell.ok(), stats)
+	}
 
-				let Ok(content) = try_content else {
-					debug!("Cannot convert cell {reference} into 80 bytes");
-					return None;
-				};
+	/// Fetches cells from DHT with a single batched GET command.
+	/// Returned cells are in the order of the positions, unfetched cells are `None`.
+	async fn fetch_cells_batch_from_dht(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+		quorum: Quorum,
+	) -> (Vec<Option<Cell>>, DHTFetchStats) {
+		let mut cells = positions.iter().map(|_| None).collect::<Vec<_>>();
+		let mut stats = DHTFetchStats::default();
+		let mut references = vec![];
+		for (index, position) in positions.iter().enumerate() {
+			let reference =
+				prefixed_reference(self.network_prefix(), position.reference(block_number));
+			match self.cached_cell(&reference) {
+				Some(cell) => {
+					cells[index] = Some(cell);
+					stats += DHTFetchStats::cached();
+				},
+				None => references.push((index, reference)),
+			}
+		}
+		if references.is_empty() {
+			return (cells, stats);
+		}
 
-				Some(Cell { position, content })
+		let keys = references
+			.iter()
+			.map(|(_, reference)| RecordKey::from(reference.as_bytes().to_vec()))
+			.collect();
+		match self.get_kad_records(keys, quorum).await {
+			Ok(results) => {
+				let mut invalid_senders = vec![];
+				for ((index, reference), (result, duration)) in references.into_iter().zip(results)
+				{
+					let cell = cell_from_record(
+						&reference,
+						positions[index],
+						result,
+						self.require_signed_records,
+						&mut invalid_senders,
+					);
+					stats += DHTFetchStats::query(&cell, duration);
+					cells[index] = cell.ok();
+				}
+				self.report_invalid_records(invalid_senders).await;
 			},
 			Err(error) => {
-				trace!("Cell {reference} not found in the DHT: {error}");
-				None
+				debug!("Cannot fetch cells of block {block_number} from the DHT: {error}");
+				stats += DHTFetchStats {
+					failures: GetFailures {
+						other: references.len() as u32,
+						..Default::default()
+					},
+					..Default::default()
+				};
 			},
 		}
+		(cells, stats)
 	}
 
 	async fn fetch_row_from_dht(
@@ -793,13 +2264,24 @@ impl Client {
 		row_index: u32,
 	) -> Option<(u32, Vec<u8>)> {
 		let row_index = RowIndex(row_index);
-		let reference = row_index.reference(block_number);
+		let reference =
+			prefixed_reference(self.network_prefix(), row_index.reference(block_number));
 		let record_key = RecordKey::from(reference.as_bytes().to_vec());
 
 		trace!("Getting DHT record for reference {}", reference);
 
-		match self.get_kad_record(record_key).await {
-			Ok(peer_record) => Some((row_index.0, peer_record.record.value)),
+		match self.get_kad_record(record_key, self.get_quorum).await {
+			Ok(peer_record) => match signed_record::open(
+				reference.as_bytes(),
+				peer_record.record.value,
+				self.require_signed_records,
+			) {
+				Ok(opened) => Some((row_index.0, opened.value)),
+				Err(error) => {
+					debug!("Row {reference} record rejected: {error:#}");
+					None
+				},
+			},
 			Err(error) => {
 				debug!("Row {reference} not found in the DHT: {error}");
 				None
@@ -808,37 +2290,359 @@ impl Client {
 	}
 
 	/// Fetches cells from DHT.
-	/// Returns fetched cells and unfetched positions (so we can try RPC fetch).
+	/// Returns fetched cells, unfetched positions (so we can try RPC fetch) and the fetch stats.
 	///
 	/// # Arguments
 	///
 	/// * `block_number` - Block number
 	/// * `positions` - Cell positions to fetch
+	/// * `quorum` - Number of peers that must return the cell, configured GET quorum is used if not set
 	pub async fn fetch_cells_from_dht(
 		&self,
 		block_number: u32,
 		positions: &[Position],
+		quorum: Option<Quorum>,
+	) -> (Vec<Cell>, Vec<Position>, DHTFetchStats) {
+		let quorum = quorum.unwrap_or(self.get_quorum);
+		self.fetch_cells_until(block_number, positions, quorum, std::future::pending())
+			.await
+	}
+
+	/// Probes the cells in the DHT, bypassing the local cell cache and records store,
+	/// so only the cells retrievable from the remote peers are returned.
+	/// Returns fetched cells and unfetched positions.
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to probe
+	pub async fn probe_cells_in_dht(
+		&self,
+		block_number: u32,
+		positions: &[Position],
 	) -> (Vec<Cell>, Vec<Position>) {
+		let references = positions
+			.iter()
+			.map(|position| {
+				prefixed_reference(self.network_prefix(), position.reference(block_number))
+			})
+			.collect::<Vec<_>>();
+		let keys = references
+			.iter()
+			.map(|reference| RecordKey::from(reference.as_bytes().to_vec()))
+			.collect();
+		let results = match self.get_records(keys, self.get_quorum, true).await {
+			Ok(results) => results,
+			Err(error) => {
+				debug!("Cannot probe cells of block {block_number} in the DHT: {error}");
+				return (vec![], positions.to_vec());
+			},
+		};
+
+		let mut fetched = vec![];
+		let mut unfetched = vec![];
+		let mut invalid_senders = vec![];
+		for ((reference, position), (result, _)) in references.iter().zip(positions).zip(results) {
+			match cell_from_record(
+				reference,
+				*position,
+				result,
+				self.require_signed_records,
+				&mut invalid_senders,
+			) {
+				Ok(cell) => fetched.push(cell),
+				Err(_) => unfetched.push(*position),
+			}
+		}
+		self.report_invalid_records(invalid_senders).await;
+		(fetched, unfetched)
+	}
+
+	/// Fetches cells from DHT, yielding each position with its cell as soon as the GET query resolves,
+	/// so verification can start on the first fetched cells.
+	/// Up to current parallelization limit of queries are in flight, unfetched cells are yielded as `None`.
+	/// Each cell is yielded with the stats of its fetch.
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	pub fn fetch_cells_stream(
+		&self,
+		block_number: u32,
+		positions: Vec<Position>,
+	) -> impl Stream<Item = (Position, Option<Cell>, DHTFetchStats)> + '_ {
+		let quorum = self.get_quorum;
+		let fetches = positions.into_iter().map(move |position| async move {
+			let (cell, stats) = self
+				.fetch_cell_from_dht(block_number, position, quorum)
+				.await;
+			(position, cell, stats)
+		});
+		futures::StreamExt::buffer_unordered(
+			futures::stream::iter(fetches),
+			self.dht_parallelization_limit(),
+		)
+	}
+
+	/// Fetches cells from DHT until all GET queries resolve or the token is cancelled.
+	/// On cancellation, queries still in flight are aborted, so they stop consuming bandwidth
+	/// (e.g. once the confidence target for the block is reached).
+	/// Returns fetched cells, unfetched positions and the fetch stats.
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	/// * `cancel_token` - Token cancelling the remaining fetches
+	pub async fn fetch_cells_with_cancel(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+		cancel_token: CancellationToken,
+	) -> (Vec<Cell>, Vec<Position>, DHTFetchStats) {
+		self.fetch_cells_until(
+			block_number,
+			positions,
+			self.get_quorum,
+			cancel_token.cancelled(),
+		)
+		.await
+	}
+
+	/// Fetches cells from DHT until all GET queries resolve or the deadline is reached,
+	/// so the sampling of the latest block can be bounded by the block time.
+	/// Returns cells fetched before the deadline, unfetched positions and the fetch stats.
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	/// * `deadline` - Time after which the remaining fetches are aborted
+	pub async fn fetch_cells_from_dht_with_deadline(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+		deadline: Instant,
+	) -> (Vec<Cell>, Vec<Position>, DHTFetchStats) {
+		let deadline = tokio::time::sleep_until(deadline.into());
+		self.fetch_cells_until(block_number, positions, self.get_quorum, deadline)
+			.await
+	}
+
+	/// Fetches cells from DHT in batched GET commands of up to current parallelization limit of cells,
+	/// until all batches are finished or the stop future completes.
+	async fn fetch_cells_until(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+		quorum: Quorum,
+		stop: impl Future<Output = ()>,
+	) -> (Vec<Cell>, Vec<Position>, DHTFetchStats) {
 		let mut cells = Vec::<Option<Cell>>::with_capacity(positions.len());
+		let mut stats = DHTFetchStats::default();
+
+		let is_stopped = {
+			let mut stop = pin!(stop);
+			let mut remaining = positions;
+			loop {
+				if remaining.is_empty() {
+					break false;
+				}
+				let limit = self.dht_parallelization_limit().min(remaining.len());
+				let (batch, rest) = remaining.split_at(limit);
+
+				let started = Instant::now();
+				let (results, batch_stats) = tokio::select! {
+					_ = &mut stop => break true,
+					fetched = self.fetch_cells_batch_from_dht(block_number, batch, quorum) => fetched,
+				};
+				remaining = rest;
+				stats += batch_stats;
+				let failures = results.iter().filter(|cell| cell.is_none()).count();
+				self.dht_parallelization.lock().unwrap().update(
+					started.elapsed(),
+					results.len(),
+					failures,
+				);
+				cells.extend(results);
+			}
+		};
 
-		for positions in positions.chunks(self.dht_parallelization_limit) {
-			let fetch = |&position| self.fetch_cell_from_dht(block_number, position);
-			let results = join_all(positions.iter().map(fetch)).await;
-			cells.extend(results.into_iter().collect::<Vec<_>>());
+		if is_stopped {
+			match self.cancel_abandoned_queries().await {
+				Ok(cancelled) => trace!("Cancelled {cancelled} DHT queries"),
+				Err(error) => debug!("Cannot cancel DHT queries: {error}"),
+			}
 		}
 
-		let unfetched = cells
+		// queries which are not finished are aborted
+		stats += DHTFetchStats::cancelled(positions.len() - cells.len());
+
+		let unfetched = positions
 			.iter()
-			.zip(positions)
-			.filter(|(cell, _)| cell.is_none())
+			.enumerate()
+			.filter(|&(index, _)| cells.get(index).map_or(true, Option::is_none))
 			.map(|(_, &position)| position)
 			.collect::<Vec<_>>();
 
 		let fetched = cells.into_iter().flatten().collect();
 
+		(fetched, unfetched, stats)
+	}
+
+	/// Collects the fetched items until the stream ends or the stop future completes.
+	/// GET queries left without a receiver after the stop are cancelled.
+	async fn collect_until<T>(
+		&self,
+		fetches: impl Stream<Item = Option<T>>,
+		stop: impl Future<Output = ()>,
+	) -> Vec<T> {
+		let mut fetched = vec![];
+		let is_stopped = {
+			let mut fetches = pin!(fetches);
+			let mut stop = pin!(stop);
+			loop {
+				tokio::select! {
+					_ = &mut stop => break true,
+					next = fetches.next() => match next {
+						Some(item) => fetched.extend(item),
+						None => break false,
+					},
+				}
+			}
+		};
+
+		if is_stopped {
+			match self.cancel_abandoned_queries().await {
+				Ok(cancelled) => trace!("Cancelled {cancelled} DHT queries"),
+				Err(error) => debug!("Cannot cancel DHT queries: {error}"),
+			}
+		}
+
+		fetched
+	}
+
+	async fn cancel_abandoned_queries(&self) -> Result<usize> {
+		self.execute_sync_interactive(|response_sender| {
+			Box::new(CancelQuery {
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
+	/// Fetches cells directly from the given peer, bypassing the DHT lookup.
+	/// Returns only the cells peer has, unavailable positions are omitted.
+	///
+	/// # Arguments
+	///
+	/// * `peer_id` - Peer to fetch cells from
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	pub async fn fetch_cells_from_peer(
+		&self,
+		peer_id: PeerId,
+		block_number: u32,
+		positions: &[Position],
+	) -> Result<Vec<Cell>> {
+		let request = cell_fetch::Request::new(block_number, positions);
+		let response = self
+			.execute_sync_interactive(|response_sender| {
+				Box::new(FetchCellsFromPeer {
+					peer_id,
+					request: Some(request),
+					response_sender: Some(response_sender),
+				})
+			})
+			.await?;
+		let (cells, invalid) = response.into_cells(
+			block_number,
+			self.network_prefix(),
+			self.require_signed_records,
+		);
+		self.report_invalid_records(vec![peer_id; invalid]).await;
+		Ok(cells)
+	}
+
+	/// Fetches cells from the peers providing the block, instead of fetching cell records from the DHT.
+	/// Returns fetched cells and unfetched positions (so we can try RPC fetch).
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `positions` - Cell positions to fetch
+	pub async fn fetch_cells_from_providers(
+		&self,
+		block_number: u32,
+		positions: &[Position],
+	) -> (Vec<Cell>, Vec<Position>) {
+		let mut unfetched = positions.to_vec();
+		let providers = match self
+			.execute_sync_interactive(|response_sender| {
+				Box::new(GetProviders {
+					key: cell_fetch::provider_key(block_number, self.network_prefix()),
+					response_sender: Some(response_sender),
+				})
+			})
+			.await
+		{
+			Ok(providers) => providers,
+			Err(error) => {
+				debug!("Cannot find providers for block {block_number}: {error}");
+				return (vec![], unfetched);
+			},
+		};
+
+		let mut fetched = Vec::with_capacity(positions.len());
+		for peer_id in providers {
+			if unfetched.is_empty() {
+				break;
+			}
+			match self
+				.fetch_cells_from_peer(peer_id, block_number, &unfetched)
+				.await
+			{
+				Ok(mut cells) => {
+					cells.retain(|cell| unfetched.contains(&cell.position));
+					unfetched
+						.retain(|position| !cells.iter().any(|cell| &cell.position == position));
+					fetched.extend(cells);
+				},
+				Err(error) => debug!("Cannot fetch cells from provider {peer_id}: {error}"),
+			}
+		}
+
 		(fetched, unfetched)
 	}
 
+	/// Subscribes to block headers announced by peers over gossipsub.
+	/// Announcements missed by a lagging subscriber are skipped.
+	pub async fn subscribe_block_announcements(
+		&self,
+	) -> Result<impl Stream<Item = BlockAnnouncement>> {
+		let receiver = self
+			.execute_sync(|response_sender| {
+				Box::new(SubscribeBlockAnnouncements {
+					response_sender: Some(response_sender),
+				})
+			})
+			.await?;
+		Ok(BroadcastStream::new(receiver).filter_map(Result::ok))
+	}
+
+	/// Announces block header, with the justification if it is finalized, to peers over gossipsub.
+	pub async fn publish_block_announcement(&self, announcement: &BlockAnnouncement) -> Result<()> {
+		let announcement = announcement.encode();
+		self.execute_sync(|response_sender| {
+			Box::new(PublishBlockAnnouncement {
+				announcement,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
+	}
+
 	/// Fetches rows from DHT.
 	/// Returns fetched rows and unfetched row indexes (so we can try RPC fetch).
 	///
@@ -853,7 +2657,7 @@ impl Client {
 		row_indexes: &[u32],
 	) -> Vec<Option<Vec<u8>>> {
 		let mut rows = vec![None; dimensions.extended_rows() as usize];
-		for row_indexes in row_indexes.chunks(self.dht_parallelization_limit) {
+		for row_indexes in row_indexes.chunks(self.dht_parallelization_limit()) {
 			let fetch = |row| self.fetch_row_from_dht(block_number, row);
 			let fetched_rows = join_all(row_indexes.iter().cloned().map(fetch)).await;
 			for (row_index, row) in fetched_rows.into_iter().flatten() {
@@ -863,13 +2667,60 @@ impl Client {
 		rows
 	}
 
-	async fn insert_into_dht(&self, records: Vec<(String, Record)>, block_num: u32) -> Result<()> {
-		if records.is_empty() {
+	/// Fetches rows from DHT until all GET queries resolve or the deadline is reached.
+	/// Rows not fetched before the deadline are `None`.
+	///
+	/// # Arguments
+	///
+	/// * `block_number` - Block number
+	/// * `dimensions` - Block matrix dimensions
+	/// * `row_indexes` - Indexes of the rows to fetch
+	/// * `deadline` - Time after which the remaining fetches are aborted
+	pub async fn fetch_rows_from_dht_with_deadline(
+		&self,
+		block_number: u32,
+		dimensions: Dimensions,
+		row_indexes: &[u32],
+		deadline: Instant,
+	) -> Vec<Option<Vec<u8>>> {
+		let fetches = row_indexes
+			.iter()
+			.map(|&row_index| self.fetch_row_from_dht(block_number, row_index));
+		let fetches = futures::StreamExt::buffer_unordered(
+			futures::stream::iter(fetches),
+			self.dht_parallelization_limit(),
+		);
+		let deadline = tokio::time::sleep_until(deadline.into());
+
+		let mut rows = vec![None; dimensions.extended_rows() as usize];
+		for (row_index, row) in self.collect_until(fetches, deadline).await {
+			rows[row_index as usize] = Some(row);
+		}
+		rows
+	}
+
+	fn check_insert_into_dht(&self, records_len: usize) -> Result<()> {
+		if records_len == 0 {
 			return Err(eyre!("Cant send empty record list."));
 		}
+		if self.is_publication_paused() {
+			return Err(eyre!(
+				"Publishing to the DHT is paused due to resource pressure."
+			));
+		}
+		Ok(())
+	}
+
+	async fn insert_into_dht(
+		&self,
+		records: Vec<(String, Record)>,
+		quorum: Quorum,
+		block_num: u32,
+	) -> Result<()> {
+		self.check_insert_into_dht(records.len())?;
 		self.put_kad_record(
 			records.into_iter().map(|e| e.1).collect(),
-			Quorum::One,
+			quorum,
 			block_num,
 		)
 		.await
@@ -885,13 +2736,101 @@ impl Client {
 	///
 	/// * `block` - Block number
 	/// * `cells` - Matrix cells to store into DHT
-	pub async fn insert_cells_into_dht(&self, block: u32, cells: Vec<Cell>) -> Result<()> {
+	/// * `quorum` - Number of peers that must store the cell, configured PUT quorum is used if not set
+	pub async fn insert_cells_into_dht(
+		&self,
+		block: u32,
+		cells: Vec<Cell>,
+		quorum: Option<Quorum>,
+	) -> Result<()> {
 		let records: Vec<_> = cells
 			.into_iter()
 			.map(DHTCell)
-			.map(|cell| (cell.reference(block), cell.dht_record(block, self.ttl)))
+			.map(|cell| {
+				let reference = cell.reference(block, self.network_prefix());
+				let record =
+					cell.dht_record(&reference, self.cell_ttl, self.signing_keypair.as_ref());
+				(reference, record)
+			})
 			.collect::<Vec<_>>();
-		self.insert_into_dht(records, block).await
+		let quorum = quorum.unwrap_or(self.put_quorum);
+		self.insert_into_dht(records, quorum, block).await
+	}
+
+	/// Inserts cells into the DHT and waits for the PUT results.
+	/// Returns result for each cell position, so the failed cells can be re-published.
+	///
+	/// # Arguments
+	///
+	/// * `block` - Block number
+	/// * `cells` - Matrix cells to store into DHT
+	pub async fn insert_cells_into_dht_with_results(
+		&self,
+		block: u32,
+		cells: Vec<Cell>,
+	) -> Result<Vec<(Position, std::result::Result<(), DHTPutError>)>> {
+		self.check_insert_into_dht(cells.len())?;
+
+		let mut positions = Vec::with_capacity(cells.len());
+		let mut records = Vec::with_capacity(cells.len());
+		let mut result_receivers = Vec::with_capacity(cells.len());
+		for cell in cells.into_iter().map(DHTCell) {
+			let (result_sender, result_receiver) = oneshot::channel();
+			positions.push(cell.0.position);
+			let reference = cell.reference(block, self.network_prefix());
+			records.push((
+				cell.dht_record(&reference, self.cell_ttl, self.signing_keypair.as_ref()),
+				Some(result_sender),
+			));
+			result_receivers.push(result_receiver);
+		}
+
+		self.command_sender
+			.send(TracedCommand::new(Box::new(PutKadRecord {
+				records,
+				quorum: self.put_quorum,
+				block_num: block,
+			})))
+			.context("receiver should not be dropped")?;
+
+		let results = join_all(result_receivers).await.into_iter().map(|result| {
+			// Result sender is dropped if the query is dropped before completion
+			result.unwrap_or(Err(DHTPutError::Dropped))
+		});
+
+		Ok(positions.into_iter().zip(results).collect())
+	}
+
+	/// Announces the local node as provider of the block cells, instead of storing cells into the DHT.
+	/// Cells are kept locally and served to peers over the cell fetch protocol.
+	///
+	/// # Arguments
+	///
+	/// * `block` - Block number
+	/// * `cells` - Matrix cells to provide
+	pub async fn provide_cells(&self, block: u32, cells: Vec<Cell>) -> Result<()> {
+		self.check_insert_into_dht(cells.len())?;
+		let cells = cells
+			.into_iter()
+			.map(|cell| {
+				let reference = prefixed_reference(self.network_prefix(), cell.reference(block));
+				let content = signed_value(
+					&reference,
+					cell.content.to_vec(),
+					self.signing_keypair.as_ref(),
+				);
+				(RecordKey::from(reference.into_bytes()), content)
+			})
+			.collect();
+
+		self.execute_sync(|response_sender| {
+			Box::new(ProvideCells {
+				key: cell_fetch::provider_key(block, self.network_prefix()),
+				cells,
+				response_sender: Some(response_sender),
+			})
+		})
+		.await
 	}
 
 	/// Inserts rows into the DHT.
@@ -912,9 +2851,14 @@ impl Client {
 		let records: Vec<_> = rows
 			.into_iter()
 			.map(DHTRow)
-			.map(|row| (row.reference(block), row.dht_record(block, self.ttl)))
+			.map(|row| {
+				let reference = row.reference(block, self.network_prefix());
+				let record =
+					row.dht_record(&reference, self.row_ttl, self.signing_keypair.as_ref());
+				(reference, record)
+			})
 			.collect::<Vec<_>>();
 
-		self.insert_into_dht(records, block).await
+		self.insert_into_dht(records, self.put_quorum, block).await
 	}
 }
@@ -0,0 +1,263 @@
+//! DHT crawler, which walks the network using the repeated closest peers lookups.
+//!
+//! Crawl starts with a lookup of a random key, and each discovered peer ID is used as the next lookup key,
+//! until no new peers are discovered or the lookup limit is reached.
+//! Agent versions and Kademlia modes are taken from the identify info of the peers contacted during the crawl.
+
+use super::{Client, PeerIdentity};
+use crate::{
+	telemetry::{metric, otlp::Record, MetricName, Metrics},
+	types::{KademliaMode, Origin, KADEMLIA_PROTOCOL_BASE},
+};
+use color_eyre::{eyre::WrapErr, Result};
+use libp2p::{kad::RecordKey, Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	fs,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+use tokio::time;
+use tracing::{debug, error, info};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrawledPeer {
+	pub addresses: Vec<Multiaddr>,
+	/// Agent version, if the peer was identified during the crawl
+	pub agent_version: Option<String>,
+	/// Peers advertising the Kademlia protocol are in the server mode
+	pub kad_mode: Option<KademliaMode>,
+}
+
+impl CrawledPeer {
+	fn identify(&mut self, identity: PeerIdentity) {
+		let is_server = identity
+			.protocols
+			.iter()
+			.any(|protocol| protocol.starts_with(KADEMLIA_PROTOCOL_BASE));
+		self.agent_version = Some(identity.agent_version);
+		self.kad_mode = Some(if is_server {
+			KademliaMode::Server
+		} else {
+			KademliaMode::Client
+		});
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Topology {
+	pub peers: HashMap<PeerId, CrawledPeer>,
+	/// Number of the closest peers lookups done during the crawl
+	pub lookups: usize,
+	pub duration: Duration,
+}
+
+impl Topology {
+	pub fn count_mode(&self, mode: KademliaMode) -> usize {
+		self.peers
+			.values()
+			.filter(|peer| peer.kad_mode == Some(mode))
+			.count()
+	}
+
+	pub fn agent_versions(&self) -> HashMap<String, usize> {
+		let mut versions = HashMap::new();
+		for agent_version in self
+			.peers
+			.values()
+			.filter_map(|peer| peer.agent_version.as_ref())
+		{
+			*versions.entry(agent_version.clone()).or_default() += 1;
+		}
+		versions
+	}
+
+	/// Writes the topology to the file as JSON
+	pub fn dump(&self, path: &str) -> Result<()> {
+		let json = serde_json::to_vec_pretty(self).wrap_err("error serializing DHT topology")?;
+		fs::write(path, json).wrap_err("error writing DHT topology")
+	}
+}
+
+/// Walks the DHT until no new peers are discovered, or `max_lookups` lookups are done
+pub async fn crawl(client: &Client, max_lookups: usize) -> Topology {
+	let start = Instant::now();
+	let mut topology = Topology::default();
+	let mut queried = HashSet::new();
+	let mut keys = VecDeque::from([PeerId::random()]);
+
+	while topology.lookups < max_lookups {
+		let Some(key) = keys.pop_front() else {
+			break;
+		};
+		topology.lookups += 1;
+
+		let peers = match client
+			.get_closest_peers(RecordKey::new(&key.to_bytes()))
+			.await
+		{
+			Ok(peers) => peers,
+			Err(error) => {
+				debug!("Closest peers lookup failed: {error}");
+				continue;
+			},
+		};
+
+		for (peer_id, addresses) in peers {
+			let peer = topology
+				.peers
+				.entry(peer_id)
+				.or_insert_with(|| CrawledPeer {
+					addresses: vec![],
+					agent_version: None,
+					kad_mode: None,
+				});
+			for address in addresses {
+				if !peer.addresses.contains(&address) {
+					peer.addresses.push(address);
+				}
+			}
+			if queried.insert(peer_id) {
+				keys.push_back(peer_id);
+			}
+		}
+	}
+
+	for (peer_id, peer) in topology.peers.iter_mut() {
+		match client.get_external_peer_info(*peer_id).await {
+			Ok(info) => {
+				if let Some(identity) = info.identity {
+					peer.identify(identity);
+				}
+			},
+			Err(error) => debug!("Cannot get info of peer {peer_id}: {error}"),
+		}
+	}
+
+	topology.duration = start.elapsed();
+	topology
+}
+
+#[derive(Clone)]
+enum CrawlerMetricValue {
+	DHTPeers(u64),
+	DHTServers(u64),
+	CrawlDuration(f64),
+}
+
+impl MetricName for CrawlerMetricValue {
+	fn name(&self) -> &'static str {
+		use CrawlerMetricValue::*;
+		match self {
+			DHTPeers(_) => "avail.light.crawl.dht_peers",
+			DHTServers(_) => "avail.light.crawl.dht_servers",
+			CrawlDuration(_) => "avail.light.crawl.dht_crawl_duration",
+		}
+	}
+}
+
+impl From<CrawlerMetricValue> for Record {
+	fn from(value: CrawlerMetricValue) -> Self {
+		use CrawlerMetricValue::*;
+		use Record::*;
+
+		let name = value.name();
+
+		match value {
+			DHTPeers(number) => MaxU64(name, number),
+			DHTServers(number) => MaxU64(name, number),
+			CrawlDuration(number) => AvgF64(name, number),
+		}
+	}
+}
+
+impl metric::Value for CrawlerMetricValue {
+	fn is_allowed(&self, origin: &Origin) -> bool {
+		matches!(origin, Origin::Internal)
+	}
+}
+
+/// Crawls the DHT periodically, records the topology metrics and optionally dumps the topology to the file
+pub async fn run(
+	client: Client,
+	metrics: Arc<impl Metrics>,
+	interval: Duration,
+	max_lookups: usize,
+	topology_path: Option<String>,
+) {
+	info!("Starting DHT crawler...");
+	let mut interval = time::interval(interval);
+	loop {
+		interval.tick().await;
+
+		let topology = crawl(&client, max_lookups).await;
+		let peers = topology.peers.len();
+		let servers = topology.count_mode(KademliaMode::Server);
+		info!(
+			peers,
+			servers,
+			lookups = topology.lookups,
+			"DHT crawl finished in {:?}",
+			topology.duration
+		);
+		debug!("Agent versions: {:?}", topology.agent_versions());
+
+		let _ = metrics
+			.record(CrawlerMetricValue::DHTPeers(peers as u64))
+			.await;
+		let _ = metrics
+			.record(CrawlerMetricValue::DHTServers(servers as u64))
+			.await;
+		let _ = metrics
+			.record(CrawlerMetricValue::CrawlDuration(
+				topology.duration.as_secs_f64(),
+			))
+			.await;
+
+		if let Some(path) = topology_path.as_deref() {
+			if let Err(error) = topology.dump(path) {
+				error!("Cannot dump DHT topology: {error:#}");
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn identify_kad_mode() {
+		let identity = |protocols: &[&str]| PeerIdentity {
+			agent_version: "avail-light-client/light-client/1.11.2/rust-client".to_string(),
+			protocol_version: "/avail/light/1.0.0".to_string(),
+			protocols: protocols.iter().map(|p| p.to_string()).collect(),
+			observed_address: Multiaddr::empty(),
+		};
+		let mut peer = CrawledPeer {
+			addresses: vec![],
+			agent_version: None,
+			kad_mode: None,
+		};
+
+		peer.identify(identity(&["/ipfs/ping/1.0.0"]));
+		assert_eq!(peer.kad_mode, Some(KademliaMode::Client));
+
+		peer.identify(identity(&[
+			"/ipfs/ping/1.0.0",
+			"/avail_kad/id/1.0.0-6f0a1b",
+		]));
+		assert_eq!(peer.kad_mode, Some(KademliaMode::Server));
+
+		let topology = Topology {
+			peers: HashMap::from([(PeerId::random(), peer)]),
+			..Default::default()
+		};
+		assert_eq!(topology.count_mode(KademliaMode::Server), 1);
+		assert_eq!(
+			topology.agent_versions()["avail-light-client/light-client/1.11.2/rust-client"],
+			1
+		);
+	}
+}
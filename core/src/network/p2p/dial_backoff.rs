@@ -0,0 +1,270 @@
+//! Exponential backoff of the dials to the repeatedly unreachable peers.
+//!
+//! Each failed dial doubles the period in which the outbound connections to the peer are denied,
+//! so the routing table peers which are gone are not re-dialed on every Kademlia query and bootstrap.
+//! Failures are forgotten once a connection with the peer is established, in either direction.
+//! Configured bootstrap and relay peers are never backed off, and explicit dials reset the backoff of the dialed peer.
+
+use libp2p::{
+	core::Endpoint,
+	swarm::{
+		dummy, ConnectionDenied, ConnectionId, DialError, DialFailure, FromSwarm, NetworkBehaviour,
+		THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+	},
+	Multiaddr, PeerId,
+};
+use std::{
+	collections::{HashMap, HashSet},
+	convert::Infallible,
+	fmt,
+	task::{Context, Poll},
+	time::Duration,
+};
+use tokio::time::Instant;
+
+/// Number of tracked peers above which the peers with expired backoff are pruned
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct DialBackoffConfig {
+	/// Backoff after the first failed dial
+	pub initial_backoff: Duration,
+	pub max_backoff: Duration,
+}
+
+struct DialFailures {
+	count: u32,
+	backoff_until: Instant,
+}
+
+/// Error of the outbound connections denied while the peer is backed off
+#[derive(Debug)]
+pub struct DialBackedOff {
+	pub peer_id: PeerId,
+	pub failures: u32,
+}
+
+impl fmt::Display for DialBackedOff {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Dial to peer {} is backed off after {} failures",
+			self.peer_id, self.failures
+		)
+	}
+}
+
+impl std::error::Error for DialBackedOff {}
+
+pub struct Behaviour {
+	config: DialBackoffConfig,
+	peers: HashMap<PeerId, DialFailures>,
+	/// Peers which are dialed regardless of the failures
+	exempt: HashSet<PeerId>,
+}
+
+impl Behaviour {
+	pub fn new(config: DialBackoffConfig, exempt: impl IntoIterator<Item = PeerId>) -> Self {
+		Self {
+			config,
+			peers: Default::default(),
+			exempt: exempt.into_iter().collect(),
+		}
+	}
+
+	/// Exempts the peer from the backoff, e.g. the bootstrap peer
+	pub fn exempt(&mut self, peer_id: PeerId) {
+		self.exempt.insert(peer_id);
+	}
+
+	pub fn is_exempt(&self, peer_id: &PeerId) -> bool {
+		self.exempt.contains(peer_id)
+	}
+
+	/// Forgets the failed dials to the peer, so the explicit dial is not denied
+	pub fn reset(&mut self, peer_id: &PeerId) {
+		self.peers.remove(peer_id);
+	}
+
+	/// Returns the number of consecutive failed dials to the peer
+	pub fn failures(&self, peer_id: &PeerId) -> u32 {
+		self.peers.get(peer_id).map_or(0, |peer| peer.count)
+	}
+
+	fn backoff(&self, failures: u32) -> Duration {
+		let multiplier = 2u32.saturating_pow(failures.saturating_sub(1));
+		self.config
+			.initial_backoff
+			.saturating_mul(multiplier)
+			.min(self.config.max_backoff)
+	}
+
+	fn record_failure(&mut self, peer_id: PeerId, now: Instant) {
+		if self.peers.len() > PRUNE_THRESHOLD {
+			let max_backoff = self.config.max_backoff;
+			self.peers
+				.retain(|_, peer| now.saturating_duration_since(peer.backoff_until) < max_backoff);
+		}
+
+		let count = self.failures(&peer_id) + 1;
+		let backoff_until = now + self.backoff(count);
+		self.peers.insert(
+			peer_id,
+			DialFailures {
+				count,
+				backoff_until,
+			},
+		);
+	}
+}
+
+impl NetworkBehaviour for Behaviour {
+	type ConnectionHandler = dummy::ConnectionHandler;
+	type ToSwarm = Infallible;
+
+	fn handle_pending_outbound_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		maybe_peer: Option<PeerId>,
+		_addresses: &[Multiaddr],
+		_effective_role: Endpoint,
+	) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+		let Some(peer_id) = maybe_peer.filter(|peer_id| !self.is_exempt(peer_id)) else {
+			return Ok(vec![]);
+		};
+		match self.peers.get(&peer_id) {
+			Some(peer) if Instant::now() < peer.backoff_until => {
+				Err(ConnectionDenied::new(DialBackedOff {
+					peer_id,
+					failures: peer.count,
+				}))
+			},
+			_ => Ok(vec![]),
+		}
+	}
+
+	fn handle_established_inbound_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		_peer: PeerId,
+		_local_addr: &Multiaddr,
+		_remote_addr: &Multiaddr,
+	) -> Result<THandler<Self>, ConnectionDenied> {
+		Ok(dummy::ConnectionHandler)
+	}
+
+	fn handle_established_outbound_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		_peer: PeerId,
+		_addr: &Multiaddr,
+		_role_override: Endpoint,
+	) -> Result<THandler<Self>, ConnectionDenied> {
+		Ok(dummy::ConnectionHandler)
+	}
+
+	fn on_swarm_event(&mut self, event: FromSwarm) {
+		match event {
+			FromSwarm::ConnectionEstablished(established) => {
+				self.peers.remove(&established.peer_id);
+			},
+			FromSwarm::DialFailure(DialFailure {
+				peer_id: Some(peer_id),
+				error,
+				..
+			}) => match error {
+				// Dials denied or cancelled locally are not counted as failures
+				DialError::Denied { .. }
+				| DialError::DialPeerConditionFalse(_)
+				| DialError::Aborted
+				| DialError::LocalPeerId { .. } => {},
+				_ => self.record_failure(peer_id, Instant::now()),
+			},
+			_ => {},
+		}
+	}
+
+	fn on_connection_handler_event(
+		&mut self,
+		_peer_id: PeerId,
+		_connection_id: ConnectionId,
+		event: THandlerOutEvent<Self>,
+	) {
+		match event {}
+	}
+
+	fn poll(&mut self, _: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+		Poll::Pending
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config() -> DialBackoffConfig {
+		DialBackoffConfig {
+			initial_backoff: Duration::from_secs(10),
+			max_backoff: Duration::from_secs(60),
+		}
+	}
+
+	fn is_denied(behaviour: &mut Behaviour, peer_id: PeerId) -> bool {
+		behaviour
+			.handle_pending_outbound_connection(
+				ConnectionId::new_unchecked(0),
+				Some(peer_id),
+				&[],
+				Endpoint::Dialer,
+			)
+			.is_err()
+	}
+
+	#[test]
+	fn exponential_backoff() {
+		let mut behaviour = Behaviour::new(config(), []);
+		let peer_id = PeerId::random();
+		let now = Instant::now();
+
+		assert_eq!(behaviour.failures(&peer_id), 0);
+		behaviour.record_failure(peer_id, now);
+		assert_eq!(behaviour.failures(&peer_id), 1);
+		assert_eq!(
+			behaviour.peers[&peer_id].backoff_until,
+			now + Duration::from_secs(10)
+		);
+
+		behaviour.record_failure(peer_id, now);
+		behaviour.record_failure(peer_id, now);
+		assert_eq!(behaviour.failures(&peer_id), 3);
+		assert_eq!(
+			behaviour.peers[&peer_id].backoff_until,
+			now + Duration::from_secs(40)
+		);
+
+		behaviour.record_failure(peer_id, now);
+		assert_eq!(
+			behaviour.peers[&peer_id].backoff_until,
+			now + Duration::from_secs(60)
+		);
+	}
+
+	#[test]
+	fn exempt_peers() {
+		let relay = PeerId::random();
+		let bootstrap = PeerId::random();
+		let peer_id = PeerId::random();
+		let mut behaviour = Behaviour::new(config(), [relay]);
+		behaviour.exempt(bootstrap);
+
+		for peer_id in [relay, bootstrap, peer_id] {
+			behaviour.record_failure(peer_id, Instant::now());
+		}
+		assert!(!is_denied(&mut behaviour, relay));
+		assert!(!is_denied(&mut behaviour, bootstrap));
+		assert!(is_denied(&mut behaviour, peer_id));
+
+		behaviour.reset(&peer_id);
+		assert!(!is_denied(&mut behaviour, peer_id));
+	}
+}
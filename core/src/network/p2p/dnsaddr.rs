@@ -0,0 +1,162 @@
+//! Resolution of the DNS bootstrap addresses.
+//!
+//! `/dns`, `/dns4` and `/dns6` addresses are resolved to the IP addresses of the host,
+//! and `/dnsaddr` addresses are resolved to the addresses in the `_dnsaddr` TXT records of the host.
+//! Resolved addresses without the peer ID are skipped, since the bootstrap peers are dialed by ID.
+
+use color_eyre::{eyre::eyre, Result};
+use hickory_resolver::TokioAsyncResolver;
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use std::{net::IpAddr, str::FromStr};
+use tracing::warn;
+
+/// Maximum depth of the nested `/dnsaddr` records
+const MAX_DNSADDR_DEPTH: usize = 4;
+
+/// Returns true if the address needs to be resolved before dialing
+pub fn is_dns_address(address: &Multiaddr) -> bool {
+	matches!(
+		address.iter().next(),
+		Some(Protocol::Dns(_) | Protocol::Dns4(_) | Protocol::Dns6(_) | Protocol::Dnsaddr(_))
+	)
+}
+
+/// Resolves DNS addresses to the concrete peer addresses, other addresses are returned as they are
+pub async fn resolve_bootstraps(addresses: &[Multiaddr]) -> Vec<(PeerId, Multiaddr)> {
+	let resolver = if addresses.iter().any(is_dns_address) {
+		match TokioAsyncResolver::tokio_from_system_conf() {
+			Ok(resolver) => Some(resolver),
+			Err(error) => {
+				warn!("Cannot create DNS resolver: {error}");
+				None
+			},
+		}
+	} else {
+		None
+	};
+
+	let mut pending = addresses
+		.iter()
+		.map(|address| (address.clone(), 0))
+		.collect::<Vec<_>>();
+	let mut resolved = vec![];
+
+	while let Some((address, depth)) = pending.pop() {
+		if !is_dns_address(&address) {
+			resolved.push(address);
+			continue;
+		}
+		let Some(resolver) = &resolver else {
+			continue;
+		};
+		let result =
+			match address.iter().next().map(|protocol| protocol.acquire()) {
+				Some(Protocol::Dnsaddr(_)) if depth >= MAX_DNSADDR_DEPTH => {
+					Err(eyre!("too many nested dnsaddr records"))
+				},
+				Some(Protocol::Dnsaddr(host)) => resolve_dnsaddr(resolver, &host, &address)
+					.await
+					.map(|addresses| {
+						pending.extend(addresses.into_iter().map(|address| (address, depth + 1)))
+					}),
+				Some(Protocol::Dns(host) | Protocol::Dns4(host) | Protocol::Dns6(host)) => {
+					resolve_dns(resolver, &host, &address)
+						.await
+						.map(|addresses| resolved.extend(addresses))
+				},
+				_ => Ok(()),
+			};
+		if let Err(error) = result {
+			warn!("Cannot resolve bootstrap address {address}: {error:#}");
+		}
+	}
+
+	resolved
+		.into_iter()
+		.filter_map(|address| match address.iter().last() {
+			Some(Protocol::P2p(peer_id)) => Some((peer_id, address)),
+			_ => {
+				warn!("Skipping bootstrap address {address} without peer ID");
+				None
+			},
+		})
+		.collect()
+}
+
+// Replaces the DNS host with its IP addresses
+async fn resolve_dns(
+	resolver: &TokioAsyncResolver,
+	host: &str,
+	address: &Multiaddr,
+) -> Result<Vec<Multiaddr>> {
+	let ips = resolver.lookup_ip(host).await?;
+	let addresses = ips
+		.iter()
+		.filter(|ip| match address.iter().next() {
+			Some(Protocol::Dns4(_)) => ip.is_ipv4(),
+			Some(Protocol::Dns6(_)) => ip.is_ipv6(),
+			_ => true,
+		})
+		.map(|ip| {
+			let protocol = match ip {
+				IpAddr::V4(ip) => Protocol::Ip4(ip),
+				IpAddr::V6(ip) => Protocol::Ip6(ip),
+			};
+			std::iter::once(protocol)
+				.chain(address.iter().skip(1))
+				.collect()
+		})
+		.collect::<Vec<_>>();
+	Ok(addresses)
+}
+
+// Reads the addresses from the `_dnsaddr` TXT records, keeping only the ones matching the peer ID, if set
+async fn resolve_dnsaddr(
+	resolver: &TokioAsyncResolver,
+	host: &str,
+	address: &Multiaddr,
+) -> Result<Vec<Multiaddr>> {
+	let peer_id = match address.iter().last() {
+		Some(Protocol::P2p(peer_id)) => Some(peer_id),
+		_ => None,
+	};
+	let records = resolver.txt_lookup(format!("_dnsaddr.{host}")).await?;
+	let addresses = records
+		.iter()
+		.filter_map(|record| {
+			let record = record
+				.txt_data()
+				.iter()
+				.map(|data| String::from_utf8_lossy(data))
+				.collect::<String>();
+			Multiaddr::from_str(record.strip_prefix("dnsaddr=")?).ok()
+		})
+		.filter(|address| {
+			peer_id.is_none()
+				|| matches!(address.iter().last(), Some(Protocol::P2p(id)) if Some(id) == peer_id)
+		})
+		.collect::<Vec<_>>();
+	Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn resolve_ip_addresses() {
+		let peer_id = PeerId::random();
+		let address =
+			Multiaddr::from_str(&format!("/ip4/127.0.0.1/tcp/37000/p2p/{peer_id}")).unwrap();
+		let without_peer_id = Multiaddr::from_str("/ip4/127.0.0.1/tcp/37001").unwrap();
+
+		assert!(!is_dns_address(&address));
+		assert!(is_dns_address(
+			&Multiaddr::from_str("/dnsaddr/bootstrap.example.com").unwrap()
+		));
+		assert_eq!(
+			resolve_bootstraps(&[address.clone(), without_peer_id]).await,
+			vec![(peer_id, address)]
+		);
+	}
+}
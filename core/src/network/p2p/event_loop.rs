@@ -1,28 +1,37 @@
+use codec::Decode;
 use color_eyre::{eyre::eyre, Result};
 use futures::StreamExt;
 use libp2p::{
 	autonat::{self, NatStatus},
-	core::ConnectedPoint,
-	dcutr,
+	core::{transport::ListenerId, ConnectedPoint},
+	dcutr, gossipsub,
 	identify::{self, Info},
 	identity::Keypair,
 	kad::{
-		self, store::RecordStore, BootstrapOk, GetRecordOk, InboundRequest, Mode, QueryId,
-		QueryResult, QueryStats, RecordKey,
+		self, store::RecordStore, BootstrapOk, GetClosestPeersOk, GetProvidersOk, GetRecordOk,
+		InboundRequest, Mode, QueryId, QueryResult, RecordKey,
 	},
 	mdns,
 	multiaddr::Protocol,
 	ping,
+	request_response::{self, OutboundRequestId},
 	swarm::{
 		dial_opts::{DialOpts, PeerCondition},
 		SwarmEvent,
 	},
 	upnp, Multiaddr, PeerId, Swarm,
 };
+use lru::LruCache;
 use rand::seq::SliceRandom;
-use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+use std::{
+	collections::{HashMap, HashSet},
+	num::NonZeroUsize,
+	str::FromStr,
+	sync::Arc,
+	time::Duration,
+};
 use tokio::{
-	sync::oneshot,
+	sync::{broadcast, mpsc, oneshot},
 	time::{interval_at, Instant, Interval},
 };
 use tracing::{debug, error, info, trace, warn};
@@ -31,12 +40,23 @@ use crate::{
 	network::p2p::is_multiaddr_global,
 	shutdown::Controller,
 	telemetry::{MetricCounter, MetricValue, Metrics},
-	types::{AgentVersion, KademliaMode, LibP2PConfig, TimeToLive},
+	types::{AgentPolicy, AgentVersion, KademliaMode, LibP2PConfig, TimeToLive},
 };
 
 use super::{
-	build_swarm, client::BlockStat, Behaviour, BehaviourEvent, CommandReceiver, EventLoopEntries,
-	QueryChannel, SendableCommand,
+	bandwidth::Bandwidth,
+	build_swarm, cell_fetch,
+	churn::PeerChurn,
+	client::BlockStat,
+	fetch_stats::QuorumNotReached,
+	kad_store::Store,
+	port_mapping::{Mapping, PortMapper, PortMapping, PortMappingProtocol},
+	prefixed_reference, quorum_size,
+	rate_limit::{InboundRateLimiter, RateLimit},
+	record_verification::{UnverifiedRecord, UNVERIFIED_RECORDS_CAPACITY},
+	routing_table, signed_record, Behaviour, BehaviourEvent, BlockAnnouncement, BlockPutStats,
+	CommandReceiver, DHTPutError, DHTQueryStats, EventLoopEntries, HolePunchStats, ListenerInfo,
+	ListenerStatus, P2pEvent, PeerIdentity, PeerLatency, PendingPuts, QueryChannel, TracedCommand,
 };
 
 // RelayState keeps track of all things relay related
@@ -69,12 +89,13 @@ impl RelayState {
 	}
 }
 
-// BootstrapState keeps track of all things bootstrap related
-struct BootstrapState {
-	// referring to the initial bootstrap process,
-	// one that runs when the Light Client node starts up
-	is_startup_done: bool,
-	// timer that is responsible for firing periodic bootstraps
+// RepublishState keeps track of the automatic republish of records nearing expiry
+struct RepublishState {
+	// records expiring within this window are republished
+	window: Duration,
+	// maximum number of records republished on each check, the rest are left for the next checks
+	limit: Option<usize>,
+	// timer that is responsible for firing periodic republish checks
 	timer: Interval,
 }
 
@@ -82,6 +103,22 @@ struct EventLoopConfig {
 	// Used for checking protocol version
 	is_fat_client: bool,
 	kad_record_ttl: TimeToLive,
+	kad_row_record_ttl: TimeToLive,
+	// Used for resolving DHT GET quorum
+	replication_factor: NonZeroUsize,
+	// Used for caching found records at the closest peers which did not have them
+	is_caching_enabled: bool,
+	network_prefix: Option<String>,
+	routing_table_snapshot_path: Option<String>,
+	// Maximum time to wait for pending queries on graceful shutdown
+	shutdown_timeout: Duration,
+}
+
+#[derive(Debug)]
+pub struct ShutdownRequest {
+	pub requested_at: Instant,
+	/// Senders notified once the event loop exits, empty if the shutdown is triggered by the controller
+	pub response_senders: Vec<oneshot::Sender<Result<()>>>,
 }
 
 #[derive(Debug)]
@@ -96,19 +133,86 @@ pub struct EventLoop {
 	swarm: Swarm<Behaviour>,
 	// Tracking Kademlia events
 	pending_kad_queries: HashMap<QueryId, QueryChannel>,
+	// DHT PUT records waiting for the in-flight PUT queries to finish
+	pending_kad_puts: PendingPuts,
+	// Records found by the successful GET queries, cached at the closest peers once the queries finish
+	found_records: HashMap<QueryId, kad::Record>,
 	// Tracking swarm events (i.e. peer dialing)
 	pending_swarm_events: HashMap<PeerId, oneshot::Sender<Result<ConnectionEstablishedInfo>>>,
+	// Tracking direct cell fetch requests
+	pending_cell_fetches: HashMap<OutboundRequestId, oneshot::Sender<Result<cell_fetch::Response>>>,
+	// Forwarding block headers announced over gossipsub
+	block_announcement_sender: broadcast::Sender<BlockAnnouncement>,
+	// Forwarding notable network events to the embedding application
+	event_sender: broadcast::Sender<P2pEvent>,
+	// Forwarding cell records received by DHT PUT to the proof verification, if enabled
+	unverified_cells_sender: Option<mpsc::Sender<UnverifiedRecord>>,
+	// Set when graceful shutdown is requested, new commands are rejected until pending queries are drained
+	shutdown_request: Option<ShutdownRequest>,
+	// Peers blocked at runtime, except the peers blocked temporarily by the rate limiter
+	blocked_peers: HashSet<PeerId>,
+	// Per-peer limit of the inbound requests, peers are blocked temporarily on repeated violations
+	inbound_rate_limiter: Option<InboundRateLimiter>,
+	// Peers blocked temporarily by the rate limiter, tracked apart from the blocked peers so only they are unblocked
+	rate_limited_peers: HashSet<PeerId>,
+	// Number of consecutive failed dials after which the peer is removed from the routing table (0 disables removal)
+	max_dial_failures: u32,
+	agent_policy: AgentPolicy,
+	// Bytes received and sent over the metered transport connections
+	bandwidth: Bandwidth,
+	// Ping round-trip times of the connected peers
+	peer_latencies: HashMap<PeerId, PeerLatency>,
+	// Identify info of the recently identified peers
+	peer_identities: LruCache<PeerId, PeerIdentity>,
+	holepunch_stats: HolePunchStats,
+	query_stats: DHTQueryStats,
+	// Sessions of the recently connected peers
+	peer_churn: PeerChurn,
+	// AutoNAT servers which confirmed the current NAT status
+	nat_servers: HashSet<PeerId>,
+	// NAT-PMP / PCP port mapping, started when no UPnP gateway is found
+	port_mapper: Option<PortMapper>,
+	// External address mapped on the gateway
+	port_mapping: Option<PortMapping>,
 	relay: RelayState,
-	bootstrap: BootstrapState,
+	republish: Option<RepublishState>,
 	/// Blocks we monitor for PUT success rate
 	active_blocks: HashMap<u32, BlockStat>,
+	// PUT statistics of the recently completed blocks
+	completed_blocks: LruCache<u32, BlockPutStats>,
+	// timer that is responsible for firing periodic checks of the timed out active blocks
+	active_blocks_timer: Interval,
 	shutdown: Controller<String>,
 	event_loop_config: EventLoopConfig,
 	kad_mode: Mode,
+	// Set when the Kademlia mode is set explicitly, automatic mode reconfiguration is skipped
+	is_kad_mode_pinned: bool,
+	// Listeners started with the listen commands, with their current status
+	listeners: HashMap<ListenerId, ListenerInfo>,
 }
 
+// Number of announced headers kept for lagging subscribers
+const BLOCK_ANNOUNCEMENTS_CAPACITY: usize = 128;
+// Number of network events kept for lagging subscribers
+const EVENTS_CAPACITY: usize = 1024;
+// Time after which the PUT stats of a block are completed, even if some PUT results are missing
+const ACTIVE_BLOCK_TIMEOUT: Duration = Duration::from_secs(600);
+const ACTIVE_BLOCKS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Number of completed blocks whose PUT stats are kept
+const COMPLETED_BLOCKS_CAPACITY: NonZeroUsize = match NonZeroUsize::new(256) {
+	Some(capacity) => capacity,
+	None => panic!("Capacity must not be zero"),
+};
+// Number of AutoNAT servers kept as the confirmation of the current NAT status
+const NAT_SERVERS_CAPACITY: usize = 32;
+// Number of identified peers whose identify info is kept
+const PEER_IDENTITIES_CAPACITY: NonZeroUsize = match NonZeroUsize::new(4096) {
+	Some(capacity) => capacity,
+	None => panic!("Capacity must not be zero"),
+};
+
 #[derive(PartialEq, Debug)]
-enum DHTKey {
+pub enum DHTKey {
 	Cell(u32, u32, u32),
 	Row(u32, u32),
 }
@@ -117,7 +221,12 @@ impl TryFrom<RecordKey> for DHTKey {
 	type Error = color_eyre::Report;
 
 	fn try_from(key: RecordKey) -> std::result::Result<Self, Self::Error> {
-		match *String::from_utf8(key.to_vec())?
+		let key = String::from_utf8(key.to_vec())?;
+		// Network prefix is not part of the reference
+		let reference = key
+			.rsplit_once('/')
+			.map_or(key.as_str(), |(_, reference)| reference);
+		match *reference
 			.split(':')
 			.map(str::parse::<u32>)
 			.collect::<std::result::Result<Vec<_>, _>>()?
@@ -130,10 +239,93 @@ impl TryFrom<RecordKey> for DHTKey {
 	}
 }
 
-#[cfg(not(feature = "kademlia-rocksdb"))]
-type Store = super::kad_mem_store::MemoryStore;
-#[cfg(feature = "kademlia-rocksdb")]
-type Store = super::kad_rocksdb_store::RocksDBStore;
+// Ticks the interval, or waits forever if there is no interval
+async fn tick(interval: Option<&mut Interval>) {
+	match interval {
+		Some(interval) => {
+			interval.tick().await;
+		},
+		None => std::future::pending().await,
+	}
+}
+
+/// Removes the active blocks whose PUT results didn't arrive within the timeout
+fn evict_timed_out_blocks(
+	active_blocks: &mut HashMap<u32, BlockStat>,
+	timeout: Duration,
+) -> Vec<(u32, BlockStat)> {
+	let timed_out = active_blocks
+		.iter()
+		.filter(|(_, block)| block.started_at.elapsed() >= timeout)
+		.map(|(&block_num, _)| block_num)
+		.collect::<Vec<_>>();
+
+	timed_out
+		.into_iter()
+		.filter_map(|block_num| Some((block_num, active_blocks.remove(&block_num)?)))
+		.collect()
+}
+
+/// Returns TTL for the record key, rows can be kept for a shorter window than the cells
+pub fn record_ttl<'a>(
+	key: &RecordKey,
+	cell_ttl: &'a TimeToLive,
+	row_ttl: &'a TimeToLive,
+) -> &'a TimeToLive {
+	match DHTKey::try_from(key.clone()) {
+		Ok(DHTKey::Row(..)) => row_ttl,
+		_ => cell_ttl,
+	}
+}
+
+// Keeps the records expiring first, up to the limit
+fn expiring_first(mut records: Vec<kad::Record>, limit: Option<usize>) -> Vec<kad::Record> {
+	if let Some(limit) = limit {
+		records.sort_by_key(|record| record.expires);
+		records.truncate(limit);
+	}
+	records
+}
+
+/// Returns locally held records matching the predicate, with refreshed expiry.
+/// If limited, the records expiring first are returned.
+/// Records are republished through the pending PUTs, so the in-flight PUT limit applies to them.
+pub fn records_to_republish(
+	kademlia: &mut kad::Behaviour<Store>,
+	cell_ttl: &TimeToLive,
+	row_ttl: &TimeToLive,
+	predicate: impl Fn(&kad::Record) -> bool,
+	limit: Option<usize>,
+) -> Vec<kad::Record> {
+	let records = kademlia
+		.store_mut()
+		.records()
+		.filter(|record| predicate(record))
+		.map(|record| record.into_owned())
+		.collect::<Vec<_>>();
+
+	let mut records = expiring_first(records, limit);
+	for record in &mut records {
+		record.expires = record_ttl(&record.key, cell_ttl, row_ttl).expires();
+	}
+	records
+}
+
+// Waits for the next port mapping result, or forever if there is no port mapper
+async fn next_port_mapping(port_mapper: Option<&mut PortMapper>) -> Result<Mapping> {
+	match port_mapper {
+		Some(port_mapper) => port_mapper.next().await,
+		None => std::future::pending().await,
+	}
+}
+
+// Sleeps until the deadline, or forever if there is no deadline
+async fn sleep_until(deadline: Option<Instant>) {
+	match deadline {
+		Some(deadline) => tokio::time::sleep_until(deadline).await,
+		None => std::future::pending().await,
+	}
+}
 
 impl EventLoop {
 	pub async fn new(
@@ -143,45 +335,114 @@ impl EventLoop {
 		is_ws_transport: bool,
 		shutdown: Controller<String>,
 		kad_mode: KademliaMode,
-		#[cfg(feature = "kademlia-rocksdb")] db: Arc<rocksdb::DB>,
+		db: Arc<rocksdb::DB>,
 	) -> Self {
-		let bootstrap_interval = cfg.bootstrap_interval;
 		let peer_id = id_keys.public().to_peer_id();
-		let store = Store::with_config(
-			peer_id,
-			(&cfg).into(),
-			#[cfg(feature = "kademlia-rocksdb")]
-			db,
-		);
+		let store = Store::with_config(peer_id, &cfg, db);
 
-		let swarm = build_swarm(&cfg, id_keys, store, is_ws_transport)
+		let bandwidth = Bandwidth::default();
+		let mut swarm = build_swarm(&cfg, id_keys, store, is_ws_transport, &bandwidth)
 			.await
 			.expect("Unable to build swarm.");
 
+		if let Some(path) = &cfg.routing_table_snapshot_path {
+			match routing_table::load(path) {
+				Ok(entries) => {
+					let imported =
+						routing_table::import(&mut swarm.behaviour_mut().kademlia, entries);
+					info!("Imported {imported} peers from the routing table snapshot");
+				},
+				Err(error) => warn!("Cannot load routing table snapshot: {error:#}"),
+			}
+		}
+
 		Self {
 			swarm,
 			pending_kad_queries: Default::default(),
+			pending_kad_puts: PendingPuts::new(
+				cfg.kademlia.put_in_flight_limit,
+				cfg.put_retry.clone(),
+			),
+			found_records: Default::default(),
 			pending_swarm_events: Default::default(),
+			pending_cell_fetches: Default::default(),
+			block_announcement_sender: broadcast::channel(BLOCK_ANNOUNCEMENTS_CAPACITY).0,
+			event_sender: broadcast::channel(EVENTS_CAPACITY).0,
+			unverified_cells_sender: None,
+			shutdown_request: None,
+			blocked_peers: Default::default(),
+			inbound_rate_limiter: cfg.inbound_rate_limit.map(InboundRateLimiter::new),
+			rate_limited_peers: Default::default(),
+			max_dial_failures: cfg.max_dial_failures,
+			agent_policy: cfg.agent_policy.clone(),
+			bandwidth,
+			peer_latencies: Default::default(),
+			peer_identities: LruCache::new(PEER_IDENTITIES_CAPACITY),
+			holepunch_stats: Default::default(),
+			query_stats: Default::default(),
+			peer_churn: Default::default(),
+			nat_servers: Default::default(),
+			port_mapper: cfg
+				.nat_pmp_fallback
+				.then(|| PortMapper::new(cfg.port, is_ws_transport)),
+			port_mapping: None,
 			relay: RelayState {
 				id: PeerId::random(),
 				address: Multiaddr::empty(),
 				is_circuit_established: false,
 				nodes: cfg.relays,
 			},
-			bootstrap: BootstrapState {
-				is_startup_done: false,
-				timer: interval_at(Instant::now() + bootstrap_interval, bootstrap_interval),
-			},
+			republish: cfg.kademlia.record_republish_window.map(|window| {
+				let period = window / 2;
+				RepublishState {
+					window,
+					limit: cfg.kademlia.record_republish_limit,
+					timer: interval_at(Instant::now() + period, period),
+				}
+			}),
 			active_blocks: Default::default(),
+			completed_blocks: LruCache::new(COMPLETED_BLOCKS_CAPACITY),
+			active_blocks_timer: interval_at(
+				Instant::now() + ACTIVE_BLOCKS_CHECK_INTERVAL,
+				ACTIVE_BLOCKS_CHECK_INTERVAL,
+			),
 			shutdown,
 			event_loop_config: EventLoopConfig {
 				is_fat_client,
 				kad_record_ttl: TimeToLive(cfg.kademlia.kad_record_ttl),
+				kad_row_record_ttl: TimeToLive(cfg.kademlia.kad_row_record_ttl),
+				replication_factor: cfg.kademlia.record_replication_factor,
+				is_caching_enabled: cfg.kademlia.caching_max_peers > 0,
+				network_prefix: cfg.kademlia.network_prefix.clone(),
+				routing_table_snapshot_path: cfg.routing_table_snapshot_path,
+				shutdown_timeout: cfg.kademlia.query_timeout,
 			},
 			kad_mode: kad_mode.into(),
+			is_kad_mode_pinned: false,
+			listeners: Default::default(),
 		}
 	}
 
+	/// Subscribes to the notable network events, should be called before the event loop is started.
+	/// Events missed by a lagging subscriber are skipped.
+	pub fn subscribe_events(&self) -> broadcast::Receiver<P2pEvent> {
+		self.event_sender.subscribe()
+	}
+
+	/// Forwards the cell records received by DHT PUT to the returned receiver, instead of storing them.
+	/// Should be called before the event loop is started, verified cells are stored using the client.
+	/// Records received while the verification queue is full are dropped.
+	pub fn verify_inbound_cells(&mut self) -> mpsc::Receiver<UnverifiedRecord> {
+		let (sender, receiver) = mpsc::channel(UNVERIFIED_RECORDS_CAPACITY);
+		self.unverified_cells_sender = Some(sender);
+		receiver
+	}
+
+	// Sends the event to the subscribers, if any
+	fn emit(&self, event: P2pEvent) {
+		_ = self.event_sender.send(event);
+	}
+
 	pub async fn run(mut self, metrics: Arc<impl Metrics>, mut command_receiver: CommandReceiver) {
 		// shutdown will wait as long as this token is not dropped
 		let _delay_token = self
@@ -190,26 +451,158 @@ impl EventLoop {
 			.expect("There should not be any shutdowns at the begging of the P2P Event Loop");
 
 		loop {
+			let shutdown_deadline = self
+				.shutdown_request
+				.as_ref()
+				.map(|request| request.requested_at + self.event_loop_config.shutdown_timeout);
+
 			tokio::select! {
 				event = self.swarm.next() => self.handle_event(event.expect("Swarm stream should be infinite"), metrics.clone()).await,
+				// high priority commands are received first
 				command = command_receiver.recv() => match command {
-					Some(c) => self.handle_command(c).await,
+					Some(c) => self.handle_command(c, metrics.clone()).await,
 					//
 					None => {
 						warn!("Command channel closed, exiting the network event loop");
 						break;
 					},
 				},
-				_ = self.bootstrap.timer.tick() => self.handle_periodic_bootstraps(),
-				// if the shutdown was triggered,
-				// break the loop immediately, proceed to the cleanup phase
-				_ = self.shutdown.triggered_shutdown() => {
-					info!("Shutdown triggered, exiting the network event loop");
+				_ = tick(self.republish.as_mut().map(|republish| &mut republish.timer)) => self.handle_periodic_republish(),
+				_ = self.active_blocks_timer.tick() => self.complete_timed_out_blocks(metrics.clone()).await,
+				_ = sleep_until(self.pending_kad_puts.next_retry()) => {
+					self.pending_kad_puts.queue_due_retries();
+					self.start_pending_puts();
+				},
+				_ = sleep_until(self.inbound_rate_limiter.as_ref().and_then(InboundRateLimiter::next_unblock)) => self.unblock_rate_limited_peers(),
+				result = next_port_mapping(self.port_mapper.as_mut()) => self.handle_port_mapping(result),
+				_ = sleep_until(shutdown_deadline) => {
+					warn!("Shutdown timeout elapsed with {} pending queries, exiting the network event loop", self.pending_kad_queries.len());
 					break;
+				},
+				// if the shutdown was triggered without the shutdown request,
+				// pending queries are drained the same way, until the shutdown timeout
+				_ = self.shutdown.triggered_shutdown(), if self.shutdown_request.is_none() => {
+					info!("Shutdown triggered, draining pending queries of the network event loop");
+					self.shutdown_request = Some(ShutdownRequest {
+						requested_at: Instant::now(),
+						response_senders: vec![],
+					});
 				}
 			}
+
+			// Records rejected by the local store are counted as failed PUTs
+			for key in self.pending_kad_puts.take_rejected() {
+				self.handle_put_result(key, None, true, metrics.clone())
+					.await;
+			}
+
+			if self.shutdown_request.is_some()
+				&& self.pending_kad_queries.is_empty()
+				&& self.pending_kad_puts.is_empty()
+			{
+				info!("Pending queries drained, exiting the network event loop");
+				break;
+			}
 		}
+		self.save_routing_table();
+		self.swarm.behaviour_mut().kademlia.store_mut().flush();
 		self.disconnect_peers();
+
+		if let Some(request) = self.shutdown_request.take() {
+			for response_sender in request.response_senders {
+				_ = response_sender.send(Ok(()));
+			}
+		}
+	}
+
+	fn save_routing_table(&mut self) {
+		let Some(path) = &self.event_loop_config.routing_table_snapshot_path else {
+			return;
+		};
+		let entries = routing_table::export(&mut self.swarm.behaviour_mut().kademlia);
+		match routing_table::save(path, &entries) {
+			Ok(()) => info!(
+				"Stored {} peers into the routing table snapshot",
+				entries.len()
+			),
+			Err(error) => warn!("Cannot store routing table snapshot: {error:#}"),
+		}
+	}
+
+	/// Checks the inbound request against the per-peer rate limit, and blocks the peer on repeated violations
+	fn is_inbound_request_allowed(&mut self, peer_id: PeerId) -> bool {
+		let Some(rate_limiter) = self.inbound_rate_limiter.as_mut() else {
+			return true;
+		};
+		match rate_limiter.check(peer_id, Instant::now()) {
+			RateLimit::Allowed => true,
+			RateLimit::Exceeded => {
+				trace!(%peer_id, "Inbound request rate limit exceeded");
+				false
+			},
+			RateLimit::Block => {
+				warn!(
+					%peer_id,
+					"Blocking peer for repeatedly exceeding the inbound request rate limit"
+				);
+				self.swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+				self.rate_limited_peers.insert(peer_id);
+				false
+			},
+			RateLimit::BlockPermanently => {
+				warn!(
+					%peer_id,
+					"Blocking peer permanently for being repeatedly blocked by the inbound request rate limit"
+				);
+				self.swarm.behaviour_mut().blocked_peers.block_peer(peer_id);
+				self.blocked_peers.insert(peer_id);
+				false
+			},
+		}
+	}
+
+	fn unblock_rate_limited_peers(&mut self) {
+		let Some(rate_limiter) = self.inbound_rate_limiter.as_mut() else {
+			return;
+		};
+		for peer_id in rate_limiter.expired_blocks(Instant::now()) {
+			// peers blocked meanwhile by the operator or for the invalid records stay blocked
+			if !self.rate_limited_peers.remove(&peer_id) || self.blocked_peers.contains(&peer_id) {
+				continue;
+			}
+			debug!(%peer_id, "Unblocking rate limited peer");
+			self.swarm
+				.behaviour_mut()
+				.blocked_peers
+				.unblock_peer(peer_id);
+		}
+	}
+
+	fn handle_port_mapping(&mut self, result: Result<Mapping>) {
+		let Some(port_mapper) = &self.port_mapper else {
+			return;
+		};
+		let port_mapping = match result {
+			Ok(mapping) => port_mapper.port_mapping(&mapping),
+			Err(error) => {
+				debug!("NAT-PMP / PCP port mapping failed: {error:#}");
+				return;
+			},
+		};
+
+		if let Some(previous) = self.port_mapping.take() {
+			if previous.external_address != port_mapping.external_address {
+				self.swarm
+					.remove_external_address(&previous.external_address);
+			}
+		}
+		info!(
+			"[{}] Mapped external address: {}",
+			port_mapping.protocol, port_mapping.external_address
+		);
+		self.swarm
+			.add_external_address(port_mapping.external_address.clone());
+		self.port_mapping = Some(port_mapping);
 	}
 
 	fn disconnect_peers(&mut self) {
@@ -253,14 +646,43 @@ impl EventLoop {
 						},
 						InboundRequest::PutRecord { source, record, .. } => {
 							metrics.count(MetricCounter::IncomingPutRecord).await;
+							if !self.is_inbound_request_allowed(source) {
+								metrics.count(MetricCounter::IncomingPutRecordLimited).await;
+								return;
+							}
 							match record {
 								Some(mut record) => {
-									let ttl = &self.event_loop_config.kad_record_ttl;
+									let ttl = record_ttl(
+										&record.key,
+										&self.event_loop_config.kad_record_ttl,
+										&self.event_loop_config.kad_row_record_ttl,
+									);
 
 									// Set TTL for all incoming records
 									// TTL will be set to a lower value between the local TTL and incoming record TTL
 									record.expires = record.expires.min(ttl.expires());
-									_ = self.swarm.behaviour_mut().kademlia.store_mut().put(record);
+
+									// Cells are stored once their proofs are verified
+									if let Some(sender) = self.unverified_cells_sender.as_ref() {
+										if let Ok(DHTKey::Cell(..)) =
+											DHTKey::try_from(record.key.clone())
+										{
+											if let Err(error) = sender.try_send((source, record)) {
+												trace!(
+													"Cell record from {source} is dropped: {error}"
+												);
+											}
+											return;
+										}
+									}
+									let key = record.key.clone();
+									if let Err(error) =
+										self.swarm.behaviour_mut().kademlia.store_mut().put(record)
+									{
+										debug!(
+											"Record {key:?} from {source:?} is not stored: {error}"
+										);
+									}
 								},
 								None => {
 									debug!("Received empty cell record from: {source:?}");
@@ -274,6 +696,7 @@ impl EventLoop {
 						trace!("Kademlia mode changed: {new_mode:?}");
 						// This event should not be automatically triggered because the mode changes are handled explicitly through the LC logic
 						self.kad_mode = new_mode;
+						self.emit(P2pEvent::KademliaModeChanged { mode: new_mode });
 						metrics.update_operating_mode(new_mode).await
 					},
 					kad::Event::OutboundQueryProgressed {
@@ -281,61 +704,167 @@ impl EventLoop {
 					} => match result {
 						QueryResult::GetRecord(result) => match result {
 							Ok(GetRecordOk::FoundRecord(record)) => {
-								if let Some(QueryChannel::GetRecord(ch)) =
-									self.pending_kad_queries.remove(&id)
+								let Some(QueryChannel::GetRecord {
+									quorum,
+									found,
+									remote_only,
+									..
+								}) = self.pending_kad_queries.get_mut(&id)
+								else {
+									return;
+								};
+								// Locally stored record is reported without the peer
+								if *remote_only && record.peer.is_none() {
+									return;
+								}
+								*found += 1;
+								let replication_factor = self.event_loop_config.replication_factor;
+								if *found < quorum_size(*quorum, replication_factor) {
+									return;
+								}
+
+								if let Some(QueryChannel::GetRecord {
+									response_sender, ..
+								}) = self.pending_kad_queries.remove(&id)
+								{
+									if self.event_loop_config.is_caching_enabled {
+										self.found_records.insert(id, record.record.clone());
+									}
+									response_sender.send(Ok(record));
+									self.query_stats.get.record(&stats, true);
+								}
+								self.emit(P2pEvent::GetCompleted {
+									is_success: true,
+									duration: stats.duration(),
+								});
+								// Quorum is reached, there is no need to query remaining peers
+								if let Some(mut query) =
+									self.swarm.behaviour_mut().kademlia.query_mut(&id)
 								{
-									_ = ch.send(Ok(record));
+									query.finish();
 								}
 							},
+							Ok(GetRecordOk::FinishedWithNoAdditionalRecord {
+								cache_candidates,
+							}) => {
+								// Caching is done by storing the record at the closest peers which did not return it
+								if let Some(record) = self.found_records.remove(&id) {
+									if !cache_candidates.is_empty() {
+										trace!(
+											"Caching record {:?} at {} peers",
+											record.key,
+											cache_candidates.len()
+										);
+										_ = self.swarm.behaviour_mut().kademlia.put_record_to(
+											record,
+											cache_candidates.into_values(),
+											kad::Quorum::One,
+										);
+									}
+									return;
+								}
+								if let Some(QueryChannel::GetRecord {
+									response_sender,
+									found,
+									..
+								}) = self.pending_kad_queries.remove(&id)
+								{
+									response_sender.send(Err(QuorumNotReached { found }.into()));
+									self.query_stats.get.record(&stats, false);
+								}
+								self.emit(P2pEvent::GetCompleted {
+									is_success: false,
+									duration: stats.duration(),
+								});
+							},
 							Err(err) => {
-								if let Some(QueryChannel::GetRecord(ch)) =
-									self.pending_kad_queries.remove(&id)
+								if self.found_records.remove(&id).is_some() {
+									return;
+								}
+								if let Some(QueryChannel::GetRecord {
+									response_sender, ..
+								}) = self.pending_kad_queries.remove(&id)
 								{
-									_ = ch.send(Err(err.into()));
+									response_sender.send(Err(err.into()));
+									self.query_stats.get.record(&stats, false);
 								}
+								self.emit(P2pEvent::GetCompleted {
+									is_success: false,
+									duration: stats.duration(),
+								});
 							},
-							_ => (),
 						},
 						QueryResult::PutRecord(Err(error)) => {
-							if self.pending_kad_queries.remove(&id).is_none() {
+							let Some(QueryChannel::PutRecord(query)) =
+								self.pending_kad_queries.remove(&id)
+							else {
 								return;
 							};
+							self.query_stats.put.record(&stats, false);
 
-							match error {
-								kad::PutRecordError::QuorumFailed { key, .. } => {
-									self.handle_put_result(key, stats, true, metrics).await;
-								},
-								kad::PutRecordError::Timeout { key, .. } => {
-									self.handle_put_result(key, stats, true, metrics).await;
+							let put_error = match error {
+								kad::PutRecordError::QuorumFailed { .. } => {
+									DHTPutError::QuorumFailed
 								},
+								kad::PutRecordError::Timeout { .. } => DHTPutError::Timeout,
+							};
+							// Failed query is counted as error only when there are no retries left
+							let Some(query) = self.pending_kad_puts.retry(query) else {
+								debug!(query_id = ?id, "Retrying failed DHT PUT query: {put_error:?}");
+								self.start_pending_puts();
+								return;
+							};
+							let key = query.key().clone();
+							let is_republish = query.is_republish();
+							query.send_result(Err(put_error));
+							self.start_pending_puts();
+							if !is_republish {
+								self.handle_put_result(key, stats.duration(), true, metrics)
+									.await;
 							}
 						},
 
 						QueryResult::PutRecord(Ok(record)) => {
-							if self.pending_kad_queries.remove(&id).is_none() {
+							let Some(QueryChannel::PutRecord(query)) =
+								self.pending_kad_queries.remove(&id)
+							else {
 								return;
 							};
-							self.handle_put_result(record.key.clone(), stats, false, metrics)
+							self.query_stats.put.record(&stats, true);
+							let is_republish = query.is_republish();
+							query.send_result(Ok(()));
+							self.start_pending_puts();
+							if !is_republish {
+								self.handle_put_result(
+									record.key.clone(),
+									stats.duration(),
+									false,
+									metrics,
+								)
 								.await;
+							}
 						},
 						QueryResult::Bootstrap(result) => match result {
 							Ok(BootstrapOk {
 								peer,
 								num_remaining,
 							}) => {
-								debug!("BootstrapOK event. PeerID: {peer:?}. Num remaining: {num_remaining:?}.");
+								debug!(
+									query_id = ?id,
+									peer_id = %peer,
+									num_remaining,
+									"BootstrapOK event"
+								);
 								if num_remaining == 0 {
 									if let Some(QueryChannel::Bootstrap(ch)) =
 										self.pending_kad_queries.remove(&id)
 									{
 										_ = ch.send(Ok(()));
-										// we can say that the startup bootstrap is done here
-										self.bootstrap.is_startup_done = true;
 									}
 								}
 							},
 							Err(err) => {
-								debug!("Bootstrap error event. Error: {err:?}.");
+								debug!(query_id = ?id, "Bootstrap error event. Error: {err:?}.");
 								if let Some(QueryChannel::Bootstrap(ch)) =
 									self.pending_kad_queries.remove(&id)
 								{
@@ -343,26 +872,90 @@ impl EventLoop {
 								}
 							},
 						},
+						QueryResult::GetProviders(result) => match result {
+							Ok(GetProvidersOk::FoundProviders { providers, .. }) => {
+								// Query is not finished, so the provider addresses are still known when dialing
+								if let Some(QueryChannel::GetProviders(ch)) =
+									self.pending_kad_queries.remove(&id)
+								{
+									_ = ch.send(Ok(providers));
+								}
+							},
+							Ok(GetProvidersOk::FinishedWithNoAdditionalRecord { .. }) => {
+								if let Some(QueryChannel::GetProviders(ch)) =
+									self.pending_kad_queries.remove(&id)
+								{
+									_ = ch.send(Ok(Default::default()));
+								}
+							},
+							Err(err) => {
+								if let Some(QueryChannel::GetProviders(ch)) =
+									self.pending_kad_queries.remove(&id)
+								{
+									_ = ch.send(Err(err.into()));
+								}
+							},
+						},
+						QueryResult::StartProviding(Err(error)) => {
+							debug!("Start providing failed: {error}");
+						},
+						QueryResult::GetClosestPeers(result) => {
+							let Some(QueryChannel::GetClosestPeers(ch)) =
+								self.pending_kad_queries.remove(&id)
+							else {
+								return;
+							};
+							match result {
+								Ok(GetClosestPeersOk { peers, .. }) => {
+									let mut addresses: HashMap<_, _> = routing_table::export(
+										&mut self.swarm.behaviour_mut().kademlia,
+									)
+									.into_iter()
+									.map(|entry| (entry.peer_id, entry.addresses))
+									.collect();
+									let peers = peers
+										.into_iter()
+										.map(|peer_id| {
+											(
+												peer_id,
+												addresses.remove(&peer_id).unwrap_or_default(),
+											)
+										})
+										.collect();
+									_ = ch.send(Ok(peers));
+								},
+								Err(err) => {
+									_ = ch.send(Err(err.into()));
+								},
+							}
+						},
 						_ => {},
 					},
 				}
 			},
 			SwarmEvent::Behaviour(BehaviourEvent::Identify(event)) => match event {
-				identify::Event::Received {
-					peer_id,
-					info:
-						Info {
-							listen_addrs,
-							agent_version,
-							protocol_version,
-							protocols,
-							..
-						},
-				} => {
+				identify::Event::Received { peer_id, info } => {
+					self.peer_identities.put(peer_id, PeerIdentity::from(&info));
+					let Info {
+						listen_addrs,
+						agent_version,
+						protocol_version,
+						protocols,
+						..
+					} = info;
 					trace!(
 						"Identity Received from: {peer_id:?} on listen address: {listen_addrs:?}"
 					);
 
+					if !self.agent_policy.allows(&agent_version) {
+						debug!(%peer_id, "Agent version {agent_version} is not allowed");
+						self.swarm.behaviour_mut().kademlia.remove_peer(&peer_id);
+						if self.agent_policy.disconnect {
+							_ = self.swarm.disconnect_peer_id(peer_id);
+						}
+						return;
+					}
+
 					let incoming_peer_agent_version = match AgentVersion::from_str(&agent_version) {
 						Ok(agent) => agent,
 						Err(e) => {
@@ -391,7 +984,7 @@ impl EventLoop {
 						}
 					} else {
 						// Block and remove non-compatible peers
-						debug!("Removing and blocking peer from routing table. Peer: {peer_id}. Agent: {agent_version}. Protocol: {protocol_version}");
+						debug!(%peer_id, "Removing and blocking peer from routing table. Agent: {agent_version}. Protocol: {protocol_version}");
 						self.swarm.behaviour_mut().kademlia.remove_peer(&peer_id);
 					}
 				},
@@ -448,9 +1041,15 @@ impl EventLoop {
 				},
 				autonat::Event::OutboundProbe(e) => {
 					trace!("[AutoNat] Outbound Probe: {:#?}", e);
+					if let autonat::OutboundProbeEvent::Response { peer, .. } = e {
+						if self.nat_servers.len() < NAT_SERVERS_CAPACITY {
+							self.nat_servers.insert(peer);
+						}
+					}
 				},
 				autonat::Event::StatusChanged { old, new } => {
 					debug!("[AutoNat] Old status: {:#?}. New status: {:#?}", old, new);
+					self.nat_servers.clear();
 					// check if went private or are private
 					// if so, create reservation request with relay
 					if new == NatStatus::Private || old == NatStatus::Private {
@@ -469,37 +1068,175 @@ impl EventLoop {
 			SwarmEvent::Behaviour(BehaviourEvent::Dcutr(dcutr::Event {
 				remote_peer_id,
 				result,
-			})) => match result {
-				Ok(_) => trace!("Hole punching succeeded with: {remote_peer_id:#?}"),
-				Err(err) => {
-					trace!("Hole punching failed with: {remote_peer_id:#?}. Error: {err:#?}")
-				},
+			})) => {
+				self.holepunch_stats.attempts += 1;
+				match result {
+					Ok(_) => {
+						self.holepunch_stats.successes += 1;
+						trace!("Hole punching succeeded with: {remote_peer_id:#?}")
+					},
+					Err(err) => {
+						if self.swarm.is_connected(&remote_peer_id) {
+							self.holepunch_stats.relay_fallbacks += 1;
+						}
+						trace!("Hole punching failed with: {remote_peer_id:#?}. Error: {err:#?}")
+					},
+				}
 			},
-			SwarmEvent::Behaviour(BehaviourEvent::Ping(ping::Event { result, .. })) => {
+			SwarmEvent::Behaviour(BehaviourEvent::Ping(ping::Event { peer, result, .. })) => {
 				if let Ok(rtt) = result {
+					self.peer_latencies
+						.entry(peer)
+						.and_modify(|latency| latency.update(rtt))
+						.or_insert_with(|| PeerLatency::new(rtt));
 					let _ = metrics
 						.record(MetricValue::DHTPingLatency(rtt.as_millis() as f64))
 						.await;
 				}
 			},
+			SwarmEvent::Behaviour(BehaviourEvent::CellFetch(event)) => match event {
+				request_response::Event::Message { peer, message } => match message {
+					request_response::Message::Request {
+						request, channel, ..
+					} => {
+						trace!(
+							peer_id = %peer,
+							block_number = request.block_number,
+							"[Cell Fetch] Request for {} cells",
+							request.positions.len()
+						);
+						let response = self.cell_fetch_response(&request);
+						if self
+							.swarm
+							.behaviour_mut()
+							.cell_fetch
+							.send_response(channel, response)
+							.is_err()
+						{
+							debug!(peer_id = %peer, "[Cell Fetch] Cannot send response");
+						}
+					},
+					request_response::Message::Response {
+						request_id,
+						response,
+					} => {
+						if let Some(ch) = self.pending_cell_fetches.remove(&request_id) {
+							_ = ch.send(Ok(response));
+						}
+					},
+				},
+				request_response::Event::OutboundFailure {
+					peer,
+					request_id,
+					error,
+				} => {
+					debug!(peer_id = %peer, "[Cell Fetch] Outbound request failed: {error}");
+					if let Some(ch) = self.pending_cell_fetches.remove(&request_id) {
+						_ = ch.send(Err(error.into()));
+					}
+				},
+				request_response::Event::InboundFailure { peer, error, .. } => {
+					trace!(peer_id = %peer, "[Cell Fetch] Inbound request failed: {error}");
+				},
+				request_response::Event::ResponseSent { peer, .. } => {
+					trace!(peer_id = %peer, "[Cell Fetch] Response sent");
+				},
+			},
+			SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(event)) => match event {
+				gossipsub::Event::Message {
+					propagation_source,
+					message,
+					..
+				} => match BlockAnnouncement::decode(&mut &message.data[..]) {
+					Ok(announcement) => {
+						trace!(
+							peer_id = %propagation_source,
+							block_number = announcement.header.number,
+							"[Gossipsub] Block announced"
+						);
+						// Sending fails only when there are no subscribers
+						_ = self.block_announcement_sender.send(announcement);
+					},
+					Err(error) => {
+						debug!(peer_id = %propagation_source, "[Gossipsub] Cannot decode block announcement: {error}");
+					},
+				},
+				gossipsub::Event::Subscribed { peer_id, topic } => {
+					trace!("[Gossipsub] Peer {peer_id} subscribed to {topic}");
+				},
+				gossipsub::Event::Unsubscribed { peer_id, topic } => {
+					trace!("[Gossipsub] Peer {peer_id} unsubscribed from {topic}");
+				},
+				gossipsub::Event::GossipsubNotSupported { peer_id } => {
+					trace!("[Gossipsub] Peer {peer_id} does not support gossipsub");
+				},
+			},
 			SwarmEvent::Behaviour(BehaviourEvent::Upnp(event)) => match event {
 				upnp::Event::NewExternalAddr(addr) => {
 					trace!("[UPnP] New external address: {addr}");
+					self.port_mapping = Some(PortMapping {
+						protocol: PortMappingProtocol::Upnp,
+						external_address: addr,
+					});
 				},
 				upnp::Event::GatewayNotFound => {
 					trace!("[UPnP] Gateway does not support UPnP");
+					if let Some(port_mapper) = self.port_mapper.as_mut() {
+						info!("Starting NAT-PMP / PCP port mapping");
+						port_mapper.start();
+					}
 				},
 				upnp::Event::NonRoutableGateway => {
 					trace!("[UPnP] Gateway is not exposed directly to the public Internet, i.e. it itself has a private IP address.");
 				},
 				upnp::Event::ExpiredExternalAddr(addr) => {
 					trace!("[UPnP] Gateway address expired: {addr}");
+					if self
+						.port_mapping
+						.as_ref()
+						.is_some_and(|mapping| mapping.external_address == addr)
+					{
+						self.port_mapping = None;
+					}
 				},
 			},
 			swarm_event => {
 				match swarm_event {
-					SwarmEvent::NewListenAddr { address, .. } => {
+					SwarmEvent::NewListenAddr {
+						listener_id,
+						address,
+					} => {
 						debug!("Local node is listening on {:?}", address);
+						if let Some(listener) = self.listeners.get_mut(&listener_id) {
+							listener.listen_addresses.push(address);
+							listener.status = ListenerStatus::Listening;
+						}
+					},
+					SwarmEvent::ExpiredListenAddr {
+						listener_id,
+						address,
+					} => {
+						debug!("Listen address expired: {address}");
+						if let Some(listener) = self.listeners.get_mut(&listener_id) {
+							listener
+								.listen_addresses
+								.retain(|listen_address| *listen_address != address);
+						}
+					},
+					SwarmEvent::ListenerClosed {
+						listener_id,
+						reason,
+						..
+					} => {
+						let error = reason.err().map(|error| error.to_string());
+						warn!("Listener {listener_id:?} closed. Error: {error:?}");
+						if let Some(listener) = self.listeners.get_mut(&listener_id) {
+							listener.listen_addresses.clear();
+							listener.status = ListenerStatus::Closed(error);
+						}
+					},
+					SwarmEvent::ListenerError { listener_id, error } => {
+						warn!("Listener {listener_id:?} error: {error}");
 					},
 					SwarmEvent::ConnectionClosed {
 						peer_id,
@@ -509,6 +1246,11 @@ impl EventLoop {
 						..
 					} => {
 						trace!("Connection closed. PeerID: {peer_id:?}. Address: {:?}. Num established: {num_established:?}. Cause: {cause:?}", endpoint.get_remote_address());
+						if num_established == 0 {
+							self.peer_latencies.remove(&peer_id);
+							self.peer_churn.disconnected(peer_id, Instant::now());
+							self.emit(P2pEvent::PeerDisconnected { peer_id });
+						}
 					},
 					SwarmEvent::IncomingConnection { .. } => {
 						metrics.count(MetricCounter::IncomingConnections).await;
@@ -527,6 +1269,9 @@ impl EventLoop {
 								address.to_string()
 							);
 						};
+						self.emit(P2pEvent::ExternalAddressConfirmed {
+							address: address.clone(),
+						});
 						metrics.update_multiaddress(address).await;
 					},
 					SwarmEvent::ConnectionEstablished {
@@ -537,6 +1282,13 @@ impl EventLoop {
 						..
 					} => {
 						metrics.count(MetricCounter::EstablishedConnections).await;
+						if num_established.get() == 1 {
+							self.peer_churn.connected(peer_id, Instant::now());
+							self.emit(P2pEvent::PeerConnected {
+								peer_id,
+								address: endpoint.get_remote_address().clone(),
+							});
+						}
 						// Notify the connections we're waiting on that we've connected successfully
 						if let Some(ch) = self.pending_swarm_events.remove(&peer_id) {
 							_ = ch.send(Ok(ConnectionEstablishedInfo {
@@ -561,6 +1313,7 @@ impl EventLoop {
 									debug!("Removed peer {removed_peer_id} from the routing table. Cause: {error}");
 								}
 							}
+							self.evict_unreachable_peer(peer_id);
 							if let Some(ch) = self.pending_swarm_events.remove(&peer_id) {
 								_ = ch.send(Err(error.into()));
 							}
@@ -585,23 +1338,144 @@ impl EventLoop {
 		}
 	}
 
-	async fn handle_command(&mut self, mut command: SendableCommand) {
+	async fn handle_command(&mut self, command: TracedCommand, metrics: Arc<impl Metrics>) {
+		let TracedCommand {
+			id,
+			sent_at,
+			mut command,
+		} = command;
+		if self.shutdown_request.is_some() && !command.is_allowed_on_shutdown() {
+			command.abort(eyre!("Network event loop is shutting down"));
+			return;
+		}
+
+		let name = command.name();
+		let started_at = Instant::now();
+		let queue_time = started_at.duration_since(sent_at);
 		if let Err(err) = command.run(EventLoopEntries::new(
 			&mut self.swarm,
 			&mut self.pending_kad_queries,
+			&mut self.pending_kad_puts,
 			&mut self.pending_swarm_events,
 			&mut self.active_blocks,
+			&self.completed_blocks,
 			&mut self.kad_mode,
+			&mut self.is_kad_mode_pinned,
+			&mut self.listeners,
+			&mut self.pending_cell_fetches,
+			&self.block_announcement_sender,
+			&mut self.blocked_peers,
+			&mut self.shutdown_request,
+			&self.bandwidth,
+			&self.peer_latencies,
+			&self.peer_identities,
+			&self.holepunch_stats,
+			&self.query_stats,
+			&self.peer_churn,
+			&self.nat_servers,
+			&self.port_mapping,
 		)) {
 			command.abort(eyre!(err));
 		}
+		let execution_time = started_at.elapsed();
+
+		trace!("Command {id} ({name}) waited {queue_time:?} and ran for {execution_time:?}");
+		metrics
+			.record(MetricValue::P2PCommandQueueTime(
+				name,
+				queue_time.as_secs_f64() * 1000.0,
+			))
+			.await;
+		metrics
+			.record(MetricValue::P2PCommandExecutionTime(
+				name,
+				execution_time.as_secs_f64() * 1000.0,
+			))
+			.await;
 	}
 
-	fn handle_periodic_bootstraps(&mut self) {
-		// commence with periodic bootstraps,
-		// only when the initial startup bootstrap is done
-		if self.bootstrap.is_startup_done {
-			_ = self.swarm.behaviour_mut().kademlia.bootstrap();
+	/// Collects requested cells available in the local Kademlia store or provided by the local node.
+	/// Signed records are sent with the envelope, so the requester can verify the publisher signature.
+	fn cell_fetch_response(&mut self, request: &cell_fetch::Request) -> cell_fetch::Response {
+		let store = self.swarm.behaviour_mut().kademlia.store_mut();
+		let network_prefix = self.event_loop_config.network_prefix.as_deref();
+		let provider_key = cell_fetch::provider_key(request.block_number, network_prefix);
+		let mut response = cell_fetch::Response::default();
+		for position in request.positions() {
+			let reference = position.reference(request.block_number);
+			let key = RecordKey::from(prefixed_reference(network_prefix, reference).into_bytes());
+			if let Some(record) = store.get(&key) {
+				let value = record.into_owned().value;
+				// Records with invalid signatures are not served
+				if let Err(error) = signed_record::open(key.as_ref(), value.clone(), false) {
+					debug!("Cell record {key:?} is not served: {error:#}");
+					continue;
+				}
+				response.push(position, value);
+			} else if let Some(content) = store.provided_content(&provider_key, &key) {
+				response.push(position, content.clone());
+			}
+		}
+		response
+	}
+
+	fn start_pending_puts(&mut self) {
+		self.pending_kad_puts.start(
+			&mut self.swarm.behaviour_mut().kademlia,
+			&mut self.pending_kad_queries,
+		);
+	}
+
+	fn handle_periodic_republish(&mut self) {
+		let Some(republish) = &self.republish else {
+			return;
+		};
+		let now = std::time::Instant::now();
+		let window_end = now + republish.window;
+		let limit = republish.limit;
+		let records = records_to_republish(
+			&mut self.swarm.behaviour_mut().kademlia,
+			&self.event_loop_config.kad_record_ttl,
+			&self.event_loop_config.kad_row_record_ttl,
+			|record| {
+				record
+					.expires
+					.is_some_and(|expires| now < expires && expires <= window_end)
+			},
+			limit,
+		);
+		if records.is_empty() {
+			return;
+		}
+		info!("Republishing {} records nearing expiry", records.len());
+		if limit.is_some_and(|limit| records.len() >= limit) {
+			debug!("Republish limit reached, remaining records are republished on the next check");
+		}
+		for record in records {
+			self.pending_kad_puts.push_republish(record);
+		}
+		self.start_pending_puts();
+	}
+
+	// Removes the peer from the routing table once the number of consecutive failed dials reaches the limit,
+	// bootstrap and relay peers are kept
+	fn evict_unreachable_peer(&mut self, peer_id: PeerId) {
+		let dial_backoff = &self.swarm.behaviour().dial_backoff;
+		let failures = dial_backoff.failures(&peer_id);
+		if self.max_dial_failures == 0
+			|| failures < self.max_dial_failures
+			|| dial_backoff.is_exempt(&peer_id)
+		{
+			return;
+		}
+		if self
+			.swarm
+			.behaviour_mut()
+			.kademlia
+			.remove_peer(&peer_id)
+			.is_some()
+		{
+			debug!("Removed unreachable peer {peer_id} from the routing table after {failures} failed dials");
 		}
 	}
 
@@ -657,7 +1531,7 @@ impl EventLoop {
 	async fn handle_put_result(
 		&mut self,
 		key: RecordKey,
-		stats: QueryStats,
+		duration: Option<Duration>,
 		is_error: bool,
 		metrics: Arc<impl Metrics>,
 	) {
@@ -669,52 +1543,145 @@ impl EventLoop {
 				return;
 			},
 		};
-		if let Some(block) = self.active_blocks.get_mut(&block_num) {
-			// Decrement record counter for this block
-			block.remaining_counter -= 1;
-			if is_error {
-				block.error_counter += 1;
-			} else {
-				block.success_counter += 1;
-			}
+		let Some(block) = self.active_blocks.get_mut(&block_num) else {
+			debug!("Can't find block in the active blocks list");
+			return;
+		};
 
-			block.time_stat = stats
-				.duration()
-				.as_ref()
-				.map(Duration::as_secs)
-				.unwrap_or_default();
-
-			if block.remaining_counter == 0 {
-				let success_rate = block.success_counter as f64 / block.total_count as f64;
-				info!(
-					"Cell upload success rate for block {block_num}: {}/{}. Duration: {}",
-					block.success_counter, block.total_count, block.time_stat
-				);
-				_ = metrics
-					.record(MetricValue::DHTPutSuccess(success_rate))
-					.await;
+		// Decrement record counter for this block
+		block.remaining_counter -= 1;
+		if is_error {
+			block.error_counter += 1;
+		} else {
+			block.success_counter += 1;
+		}
 
-				_ = metrics
-					.record(MetricValue::DHTPutDuration(block.time_stat as f64))
-					.await;
-			}
+		// Records rejected by the local store have no query duration
+		if let Some(duration) = duration {
+			block.time_stat = duration.as_secs();
+		}
+
+		let is_completed = block.remaining_counter == 0;
 
-			if self.event_loop_config.is_fat_client {
-				// Remove local records for fat clients (memory optimization)
-				debug!("Pruning local records on fat client");
-				self.swarm.behaviour_mut().kademlia.remove_record(&key);
+		if self.event_loop_config.is_fat_client {
+			// Remove local records for fat clients (memory optimization)
+			debug!("Pruning local records on fat client");
+			self.swarm.behaviour_mut().kademlia.remove_record(&key);
+		}
+
+		if is_completed {
+			if let Some(block) = self.active_blocks.remove(&block_num) {
+				self.complete_block(block_num, block, metrics).await;
 			}
-		} else {
-			debug!("Can't find block in the active blocks list")
 		}
 	}
+
+	/// Completes the stats of the blocks whose PUT results didn't arrive in time,
+	/// so the active blocks don't grow indefinitely
+	async fn complete_timed_out_blocks(&mut self, metrics: Arc<impl Metrics>) {
+		for (block_num, block) in
+			evict_timed_out_blocks(&mut self.active_blocks, ACTIVE_BLOCK_TIMEOUT)
+		{
+			warn!(
+				"PUT results of {} records of block {block_num} were not received in time",
+				block.remaining_counter
+			);
+			self.complete_block(block_num, block, metrics.clone()).await;
+		}
+	}
+
+	async fn complete_block(
+		&mut self,
+		block_num: u32,
+		block: BlockStat,
+		metrics: Arc<impl Metrics>,
+	) {
+		let stats = block.put_stats(true);
+		info!(
+			"Cell upload success rate for block {block_num}: {}/{}. Duration: {}",
+			block.success_counter, block.total_count, block.time_stat
+		);
+		_ = metrics
+			.record(MetricValue::DHTPutSuccess(stats.success_ratio()))
+			.await;
+
+		_ = metrics
+			.record(MetricValue::DHTPutDuration(block.time_stat as f64))
+			.await;
+		self.emit(P2pEvent::PutCompleted {
+			block_number: block_num,
+			success_count: block.success_counter,
+			total_count: block.total_count,
+			duration: Duration::from_secs(block.time_stat),
+		});
+		self.completed_blocks.put(block_num, stats);
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use crate::network::p2p::event_loop::DHTKey;
+	use crate::network::p2p::client::BlockStat;
+	use crate::network::p2p::event_loop::{
+		evict_timed_out_blocks, expiring_first, record_ttl, DHTKey,
+	};
+	use crate::types::TimeToLive;
 	use color_eyre::Result;
 	use libp2p::kad::RecordKey;
+	use std::{collections::HashMap, time::Duration};
+
+	#[test]
+	fn republish_limit_keeps_expiring_first() {
+		let now = std::time::Instant::now();
+		let record = |key: &str, expires_in: u64| {
+			let mut record = libp2p::kad::Record::new(RecordKey::new(&key), vec![]);
+			record.expires = Some(now + Duration::from_secs(expires_in));
+			record
+		};
+		let records = vec![
+			record("1:0:0", 30),
+			record("1:0:1", 10),
+			record("1:0:2", 20),
+		];
+
+		let keys = |records: Vec<libp2p::kad::Record>| {
+			records
+				.into_iter()
+				.map(|record| record.key)
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			keys(expiring_first(records.clone(), Some(2))),
+			vec![RecordKey::new(&"1:0:1"), RecordKey::new(&"1:0:2")]
+		);
+		assert_eq!(keys(expiring_first(records, None)).len(), 3);
+	}
+
+	#[test]
+	fn timed_out_blocks_evicted() {
+		let block = |started_at| BlockStat {
+			total_count: 2,
+			remaining_counter: 1,
+			success_counter: 1,
+			error_counter: 0,
+			time_stat: 0,
+			started_at,
+		};
+		let now = std::time::Instant::now();
+		let mut active_blocks =
+			HashMap::from([(1, block(now - Duration::from_secs(2))), (2, block(now))]);
+
+		let evicted = evict_timed_out_blocks(&mut active_blocks, Duration::from_secs(1));
+		let evicted = evicted
+			.iter()
+			.map(|(block_num, _)| *block_num)
+			.collect::<Vec<_>>();
+		assert_eq!(evicted, vec![1]);
+		assert!(active_blocks.contains_key(&2));
+		assert!(!active_blocks.contains_key(&1));
+
+		// Evicted blocks are not evicted again
+		assert!(evict_timed_out_blocks(&mut active_blocks, Duration::from_secs(1)).is_empty());
+	}
 
 	#[test]
 	fn dht_key_parse_record_key() {
@@ -729,5 +1696,19 @@ mod tests {
 
 		let result: Result<DHTKey> = RecordKey::new(&"123").try_into();
 		_ = result.unwrap_err();
+
+		let cell_key: DHTKey = RecordKey::new(&"turing/3:2:1").try_into().unwrap();
+		assert_eq!(cell_key, DHTKey::Cell(3, 2, 1));
+	}
+
+	#[test]
+	fn record_ttl_by_key() {
+		let cell_ttl = TimeToLive(Duration::from_secs(3600));
+		let row_ttl = TimeToLive(Duration::from_secs(600));
+		let ttl = |key| record_ttl(&RecordKey::new(&key), &cell_ttl, &row_ttl).0;
+
+		assert_eq!(ttl("1:2:3"), cell_ttl.0);
+		assert_eq!(ttl("turing/1:2"), row_ttl.0);
+		assert_eq!(ttl("cells:1"), cell_ttl.0);
 	}
 }
@@ -0,0 +1,224 @@
+//! Statistics of the cell fetches from the DHT.
+//!
+//! Each cell fetch is either served from the local cache, or by the DHT GET query.
+//! Stats of the queries are additive, so the stats of the single cells, query batches and sampling rounds
+//! can be summed up into the stats of the processed block.
+
+use codec::{Decode, Encode};
+use color_eyre::Report;
+use libp2p::kad;
+use serde::{Deserialize, Serialize};
+use std::{fmt, ops::AddAssign, time::Duration};
+
+/// DHT GET query which finished without reaching the quorum
+#[derive(Debug)]
+pub struct QuorumNotReached {
+	pub found: usize,
+}
+
+impl fmt::Display for QuorumNotReached {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"DHT GET quorum not reached, found {} records",
+			self.found
+		)
+	}
+}
+
+impl std::error::Error for QuorumNotReached {}
+
+/// Reason of the failed cell fetch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GetFailure {
+	NotFound,
+	Timeout,
+	QuorumFailed,
+	/// Record is not a valid cell, or it is not signed while the signatures are required
+	InvalidRecord,
+	/// Query is not executed, e.g. if the event loop is not responding
+	Other,
+}
+
+impl From<&Report> for GetFailure {
+	fn from(error: &Report) -> Self {
+		if error.downcast_ref::<QuorumNotReached>().is_some() {
+			return GetFailure::QuorumFailed;
+		}
+		match error.downcast_ref::<kad::GetRecordError>() {
+			Some(kad::GetRecordError::NotFound { .. }) => GetFailure::NotFound,
+			Some(kad::GetRecordError::Timeout { .. }) => GetFailure::Timeout,
+			Some(kad::GetRecordError::QuorumFailed { .. }) => GetFailure::QuorumFailed,
+			None => GetFailure::Other,
+		}
+	}
+}
+
+/// Number of the failed cell fetches by the failure reason
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Copy, Debug, Default, PartialEq)]
+pub struct GetFailures {
+	pub not_found: u32,
+	pub timeout: u32,
+	pub quorum_failed: u32,
+	pub invalid_record: u32,
+	pub other: u32,
+	/// Fetched cells with the invalid proofs, counted after the verification
+	pub invalid_proof: u32,
+	/// Queries aborted before they finished, when the fetch deadline is reached
+	pub cancelled: u32,
+}
+
+impl GetFailures {
+	/// Numbers of the failed fetches with the failure reasons
+	pub fn by_reason(&self) -> [(&'static str, u32); 7] {
+		[
+			("not_found", self.not_found),
+			("timeout", self.timeout),
+			("quorum_failed", self.quorum_failed),
+			("invalid_record", self.invalid_record),
+			("other", self.other),
+			("invalid_proof", self.invalid_proof),
+			("cancelled", self.cancelled),
+		]
+	}
+
+	pub fn total(&self) -> u32 {
+		self.not_found
+			+ self.timeout
+			+ self.quorum_failed
+			+ self.invalid_record
+			+ self.other
+			+ self.invalid_proof
+			+ self.cancelled
+	}
+}
+
+impl AddAssign for GetFailures {
+	fn add_assign(&mut self, other: Self) {
+		self.not_found += other.not_found;
+		self.timeout += other.timeout;
+		self.quorum_failed += other.quorum_failed;
+		self.invalid_record += other.invalid_record;
+		self.other += other.other;
+		self.invalid_proof += other.invalid_proof;
+		self.cancelled += other.cancelled;
+	}
+}
+
+/// Outcomes and durations of the cell fetches from the DHT
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Copy, Debug, Default, PartialEq)]
+pub struct DHTFetchStats {
+	/// Number of the cells found in the local cache, without the GET query
+	pub cached: u32,
+	/// Number of the cells fetched with the GET query
+	pub fetched: u32,
+	/// Number of the finished GET queries, successful or not
+	pub queries: u32,
+	/// Total duration of the finished GET queries (in milliseconds)
+	pub queries_duration: u64,
+	pub failures: GetFailures,
+}
+
+impl DHTFetchStats {
+	pub fn cached() -> Self {
+		DHTFetchStats {
+			cached: 1,
+			..Default::default()
+		}
+	}
+
+	/// Stats of the single finished GET query
+	pub fn query<T>(result: &Result<T, GetFailure>, duration: Duration) -> Self {
+		let mut stats = DHTFetchStats {
+			queries: 1,
+			queries_duration: duration.as_millis() as u64,
+			..Default::default()
+		};
+		let failures = &mut stats.failures;
+		match result {
+			Ok(_) => stats.fetched = 1,
+			Err(GetFailure::NotFound) => failures.not_found = 1,
+			Err(GetFailure::Timeout) => failures.timeout = 1,
+			Err(GetFailure::QuorumFailed) => failures.quorum_failed = 1,
+			Err(GetFailure::InvalidRecord) => failures.invalid_record = 1,
+			Err(GetFailure::Other) => failures.other = 1,
+		}
+		stats
+	}
+
+	pub fn cancelled(count: usize) -> Self {
+		let mut stats = DHTFetchStats::default();
+		stats.failures.cancelled = count as u32;
+		stats
+	}
+
+	/// Average duration of the finished GET queries, if any query is finished
+	pub fn average_latency(&self) -> Option<Duration> {
+		(self.queries > 0)
+			.then(|| Duration::from_millis(self.queries_duration / self.queries as u64))
+	}
+}
+
+impl AddAssign for DHTFetchStats {
+	fn add_assign(&mut self, other: Self) {
+		self.cached += other.cached;
+		self.fetched += other.fetched;
+		self.queries += other.queries;
+		self.queries_duration += other.queries_duration;
+		self.failures += other.failures;
+	}
+}
+
+impl std::iter::Sum for DHTFetchStats {
+	fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+		iter.fold(DHTFetchStats::default(), |mut sum, stats| {
+			sum += stats;
+			sum
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use color_eyre::eyre::eyre;
+	use test_case::test_case;
+
+	fn not_found() -> Report {
+		kad::GetRecordError::NotFound {
+			key: kad::RecordKey::new(&"1:0:0"),
+			closest_peers: vec![],
+		}
+		.into()
+	}
+
+	#[test_case(not_found() => GetFailure::NotFound ; "not found")]
+	#[test_case(QuorumNotReached { found: 1 }.into() => GetFailure::QuorumFailed ; "quorum not reached")]
+	#[test_case(eyre!("Event loop is not responding") => GetFailure::Other ; "other")]
+	fn get_failure(error: Report) -> GetFailure {
+		GetFailure::from(&error)
+	}
+
+	#[test]
+	fn stats_sum() {
+		let stats = [
+			DHTFetchStats::cached(),
+			DHTFetchStats::query(&Ok(()), Duration::from_millis(100)),
+			DHTFetchStats::query::<()>(&Err(GetFailure::Timeout), Duration::from_millis(300)),
+			DHTFetchStats::query::<()>(&Err(GetFailure::NotFound), Duration::from_millis(200)),
+			DHTFetchStats::cancelled(2),
+		]
+		.into_iter()
+		.sum::<DHTFetchStats>();
+
+		assert_eq!(stats.cached, 1);
+		assert_eq!(stats.fetched, 1);
+		assert_eq!(stats.queries, 3);
+		assert_eq!(stats.average_latency(), Some(Duration::from_millis(200)));
+		assert_eq!(stats.failures.timeout, 1);
+		assert_eq!(stats.failures.not_found, 1);
+		assert_eq!(stats.failures.cancelled, 2);
+		assert_eq!(stats.failures.total(), 4);
+		assert_eq!(DHTFetchStats::default().average_latency(), None);
+	}
+}
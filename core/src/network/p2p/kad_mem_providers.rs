@@ -5,6 +5,7 @@ use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::collections::{hash_map, hash_set, HashMap, HashSet};
 use std::iter;
+use std::time::Instant;
 
 #[derive(Clone, Debug)]
 pub struct ProvidersConfig {
@@ -35,6 +36,9 @@ pub struct Providers {
 	/// The set of all provider records for the node identified by `local_key`.
 	/// Must be kept in sync with `providers`.
 	provided: HashSet<ProviderRecord>,
+	/// Content served for the keys provided by the local node, mapped by the content key.
+	/// Removed together with the local provider record.
+	provided_content: HashMap<RecordKey, HashMap<RecordKey, Vec<u8>>>,
 }
 
 pub type ProviderIter<'a> = iter::Map<
@@ -48,6 +52,7 @@ impl Providers {
 			config,
 			providers: Default::default(),
 			provided: Default::default(),
+			provided_content: Default::default(),
 		}
 	}
 
@@ -72,6 +77,9 @@ impl Providers {
 
 		if let Some(i) = providers.iter().position(|p| p.provider == record.provider) {
 			// In-place update of an existing provider record.
+			if self.provided.remove(&providers[i]) {
+				self.provided.insert(record.clone());
+			}
 			providers.as_mut()[i] = record;
 		} else {
 			// It is a new provider record for that key.
@@ -89,7 +97,9 @@ impl Providers {
 				// Remove the excess provider, if any.
 				if providers.len() > self.config.max_providers_per_key {
 					if let Some(p) = providers.pop() {
-						self.provided.remove(&p);
+						if self.provided.remove(&p) {
+							self.provided_content.remove(&p.key);
+						}
 					}
 				}
 			} else if providers.len() < self.config.max_providers_per_key {
@@ -114,12 +124,69 @@ impl Providers {
 		self.provided.iter().map(Cow::Borrowed)
 	}
 
+	/// Adds content for the key provided by the local node.
+	/// Content is kept until the local provider record is removed.
+	pub fn add_provided_content(
+		&mut self,
+		key: &RecordKey,
+		content: impl IntoIterator<Item = (RecordKey, Vec<u8>)>,
+	) {
+		self.provided_content
+			.entry(key.clone())
+			.or_default()
+			.extend(content);
+	}
+
+	pub fn provided_content(&self, key: &RecordKey, content_key: &RecordKey) -> Option<&Vec<u8>> {
+		self.provided_content.get(key)?.get(content_key)
+	}
+
+	/// Returns the key provided by the local node which expires first
+	pub fn oldest_provided_key(&self) -> Option<RecordKey> {
+		self.provided
+			.iter()
+			// records without expiration are the last ones
+			.min_by_key(|record| (record.expires.is_none(), record.expires))
+			.map(|record| record.key.clone())
+	}
+
+	/// Removes all provider records of the key, together with the provided content
+	pub fn remove_key(&mut self, key: &RecordKey) {
+		if let Some(providers) = self.providers.remove(key) {
+			for provider in providers {
+				self.provided.remove(&provider);
+			}
+		}
+		self.provided_content.remove(key);
+	}
+
+	/// Removes expired provider records, returns number of removed records
+	pub fn prune_expired(&mut self, now: Instant) -> usize {
+		let mut pruned = 0;
+		self.providers.retain(|key, providers| {
+			providers.retain(|provider| {
+				if !provider.is_expired(now) {
+					return true;
+				}
+				if self.provided.remove(provider) {
+					self.provided_content.remove(key);
+				}
+				pruned += 1;
+				false
+			});
+			!providers.is_empty()
+		});
+		pruned
+	}
+
 	pub fn remove_provider(&mut self, key: &RecordKey, provider: &PeerId) {
 		if let hash_map::Entry::Occupied(mut e) = self.providers.entry(key.clone()) {
 			let providers = e.get_mut();
 			if let Some(i) = providers.iter().position(|p| &p.provider == provider) {
 				let p = providers.remove(i);
-				self.provided.remove(&p);
+				if self.provided.remove(&p) {
+					self.provided_content.remove(key);
+				}
 			}
 			if providers.is_empty() {
 				e.remove();
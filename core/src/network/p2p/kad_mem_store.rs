@@ -19,16 +19,17 @@
 // DEALINGS IN THE SOFTWARE.
 
 use super::kad_mem_providers::{ProviderIter, Providers, ProvidersConfig};
+use super::record_validation::{RecordValidationConfig, RecordValidator};
+use super::{cell_fetch, event_loop::DHTKey, has_network_prefix, StoreStats};
 use libp2p::identity::PeerId;
 use libp2p::kad::store::{Error, RecordStore, Result};
 use libp2p::kad::{KBucketKey, ProviderRecord, Record, RecordKey};
 use std::borrow::Cow;
-use std::collections::{hash_map, HashMap};
+use std::cmp::Ordering;
+use std::collections::{hash_map, BTreeSet, HashMap};
 use std::iter;
-use tracing::{instrument, Level};
-
-#[cfg(not(feature = "kademlia-rocksdb"))]
-use tracing::trace;
+use std::time::Instant;
+use tracing::{instrument, trace, Level};
 
 /// In-memory implementation of a `RecordStore`.
 pub struct MemoryStore {
@@ -38,8 +39,33 @@ pub struct MemoryStore {
 	config: MemoryStoreConfig,
 	/// The stored (regular) records.
 	records: HashMap<RecordKey, Record>,
+	/// Keys of the stored records ordered by expiration, used for eviction.
+	/// Must be kept in sync with `records`.
+	expirations: BTreeSet<(Instant, OrderedKey)>,
+	/// Total size of the stored record values, in bytes.
+	bytes: usize,
+	/// Number of records evicted since the store was created.
+	evicted: u64,
 	/// The stored provider records.
 	providers: Providers,
+	/// Validator of the stored records, records are not validated if not set.
+	validator: Option<RecordValidator>,
+}
+
+/// Record key ordered by its bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct OrderedKey(RecordKey);
+
+impl Ord for OrderedKey {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.0.as_ref().cmp(other.0.as_ref())
+	}
+}
+
+impl PartialOrd for OrderedKey {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
 }
 
 /// Configuration for a `MemoryStore`.
@@ -49,7 +75,16 @@ pub struct MemoryStoreConfig {
 	pub max_records: usize,
 	/// The maximum size of record values, in bytes.
 	pub max_value_bytes: usize,
+	/// The maximum total size of record values, in bytes.
+	pub max_bytes: Option<usize>,
+	/// Evict records closest to expiration when the store is full, instead of rejecting new records.
+	/// Records without expiration are never evicted.
+	pub evict_expiring: bool,
 	pub providers: ProvidersConfig,
+	/// Network prefix of the accepted record keys.
+	pub network_prefix: Option<String>,
+	/// Validation of the record keys and values, records are not validated if not set.
+	pub validation: Option<RecordValidationConfig>,
 }
 
 impl Default for MemoryStoreConfig {
@@ -58,12 +93,15 @@ impl Default for MemoryStoreConfig {
 		Self {
 			max_records: 1024,
 			max_value_bytes: 65 * 1024,
+			max_bytes: None,
+			evict_expiring: false,
 			providers: Default::default(),
+			network_prefix: None,
+			validation: None,
 		}
 	}
 }
 
-#[cfg(not(feature = "kademlia-rocksdb"))]
 impl MemoryStore {
 	/// Creates a new `MemoryRecordStore` with a default configuration.
 	pub fn new(local_id: PeerId) -> Self {
@@ -75,11 +113,22 @@ impl MemoryStore {
 		MemoryStore {
 			local_key: KBucketKey::from(local_id),
 			records: HashMap::default(),
+			expirations: BTreeSet::default(),
+			bytes: 0,
+			evicted: 0,
 			providers: Providers::with_config(config.providers.clone()),
+			validator: config.validation.clone().map(RecordValidator::new),
 			config,
 		}
 	}
 
+	/// Sets the latest block, which is used to check the recency of the records
+	pub fn set_latest_block(&mut self, block_num: u32) {
+		if let Some(validator) = self.validator.as_mut() {
+			validator.set_latest_block(block_num);
+		}
+	}
+
 	/// Retains the records satisfying a predicate.
 	#[instrument(level = Level::TRACE, skip(self, f))]
 	pub fn retain<F>(&mut self, f: F)
@@ -87,6 +136,76 @@ impl MemoryStore {
 		F: FnMut(&RecordKey, &mut Record) -> bool,
 	{
 		self.records.retain(f);
+		// Predicate can change the records, so the index is rebuilt
+		self.reindex();
+	}
+
+	/// Removes all records and provider records of the given block
+	pub fn prune_block(&mut self, block_num: u32) {
+		self.retain(|key, _| match DHTKey::try_from(key.clone()) {
+			Ok(DHTKey::Cell(block, _, _)) | Ok(DHTKey::Row(block, _)) => block != block_num,
+			Err(_) => true,
+		});
+		let network_prefix = self.config.network_prefix.as_deref();
+		let provider_key = cell_fetch::provider_key(block_num, network_prefix);
+		self.providers.remove_key(&provider_key);
+	}
+
+	/// Returns number of records, their total size and number of evicted records
+	pub fn stats(&self) -> StoreStats {
+		StoreStats {
+			records: self.records.len(),
+			bytes: self.bytes,
+			evicted: self.evicted,
+		}
+	}
+
+	fn index(&mut self, record: &Record) {
+		self.bytes += record.value.len();
+		if let Some(expires) = record.expires {
+			self.expirations
+				.insert((expires, OrderedKey(record.key.clone())));
+		}
+	}
+
+	fn unindex(&mut self, record: &Record) {
+		self.bytes -= record.value.len();
+		if let Some(expires) = record.expires {
+			self.expirations
+				.remove(&(expires, OrderedKey(record.key.clone())));
+		}
+	}
+
+	fn reindex(&mut self) {
+		self.bytes = self.records.values().map(|record| record.value.len()).sum();
+		self.expirations = self
+			.records
+			.values()
+			.filter_map(|record| Some((record.expires?, OrderedKey(record.key.clone()))))
+			.collect();
+	}
+
+	fn is_full(&self, records: usize, bytes: usize) -> bool {
+		records > self.config.max_records
+			|| self
+				.config
+				.max_bytes
+				.is_some_and(|max_bytes| bytes > max_bytes)
+	}
+
+	/// Evicts records closest to expiration until the new value fits into the store.
+	/// Returns [`false`] if there are no more records to evict.
+	fn evict(&mut self, value_bytes: usize) -> bool {
+		while self.is_full(self.records.len() + 1, self.bytes + value_bytes) {
+			let Some((_, OrderedKey(key))) = self.expirations.pop_first() else {
+				return false;
+			};
+			if let Some(record) = self.records.remove(&key) {
+				self.bytes -= record.value.len();
+				self.evicted += 1;
+			}
+		}
+		true
 	}
 
 	/// Shrinks the capacity of hashmap as much as possible
@@ -99,6 +218,33 @@ impl MemoryStore {
 			self.records.capacity()
 		);
 	}
+
+	/// Records are kept in memory only, so there is nothing to flush
+	pub fn flush(&mut self) {}
+
+	/// Adds content for the key provided by the local node
+	pub fn add_provided_content(
+		&mut self,
+		key: &RecordKey,
+		content: impl IntoIterator<Item = (RecordKey, Vec<u8>)>,
+	) {
+		self.providers.add_provided_content(key, content);
+	}
+
+	/// Returns content for the key provided by the local node
+	pub fn provided_content(&self, key: &RecordKey, content_key: &RecordKey) -> Option<&Vec<u8>> {
+		self.providers.provided_content(key, content_key)
+	}
+
+	/// Returns the key provided by the local node which expires first
+	pub fn oldest_provided_key(&self) -> Option<RecordKey> {
+		self.providers.oldest_provided_key()
+	}
+
+	/// Removes expired provider records, returns number of removed records
+	pub fn prune_expired_providers(&mut self, now: Instant) -> usize {
+		self.providers.prune_expired(now)
+	}
 }
 
 impl RecordStore for MemoryStore {
@@ -118,26 +264,35 @@ impl RecordStore for MemoryStore {
 			return Err(Error::ValueTooLarge);
 		}
 
-		let num_records = self.records.len();
-
-		match self.records.entry(r.key.clone()) {
-			hash_map::Entry::Occupied(mut e) => {
-				e.insert(r);
-			},
-			hash_map::Entry::Vacant(e) => {
-				if num_records >= self.config.max_records {
-					return Err(Error::MaxRecords);
-				}
-				e.insert(r);
-			},
+		// Store errors are not extensible, so records of other networks are rejected as invalid values
+		if !has_network_prefix(&r.key, self.config.network_prefix.as_deref()) {
+			return Err(Error::ValueTooLarge);
 		}
 
+		if let Some(Err(error)) = self.validator.as_ref().map(|v| v.validate(&r)) {
+			trace!("Record {:?} rejected: {error}", r.key);
+			return Err(Error::ValueTooLarge);
+		}
+
+		if let Some(replaced) = self.records.remove(&r.key) {
+			// Replaced record is reindexed, since both expiration and size can change
+			self.unindex(&replaced);
+		} else if self.is_full(self.records.len() + 1, self.bytes + r.value.len())
+			&& !(self.config.evict_expiring && self.evict(r.value.len()))
+		{
+			return Err(Error::MaxRecords);
+		}
+
+		self.index(&r);
+		self.records.insert(r.key.clone(), r);
 		Ok(())
 	}
 
 	#[instrument(level = Level::TRACE, skip(self))]
 	fn remove(&mut self, k: &RecordKey) {
-		self.records.remove(k);
+		if let Some(record) = self.records.remove(k) {
+			self.unindex(&record);
+		}
 	}
 
 	#[instrument(level = Level::TRACE, skip(self))]
@@ -162,7 +317,6 @@ impl RecordStore for MemoryStore {
 	}
 }
 
-#[cfg(not(feature = "kademlia-rocksdb"))]
 #[cfg(test)]
 mod tests {
 	use std::time::{Duration, Instant};
@@ -292,6 +446,133 @@ mod tests {
 		assert_eq!(store.provided().count(), 0);
 	}
 
+	#[test]
+	fn provided_content() {
+		let id = PeerId::random();
+		let mut store = MemoryStore::new(id);
+		let key = RecordKey::from(random_multihash());
+		let content_key = RecordKey::from(b"1:2:3".to_vec());
+		let mut rec = ProviderRecord::new(key.clone(), id, Vec::new());
+		assert!(store.add_provider(rec.clone()).is_ok());
+		store.add_provided_content(&key, [(content_key.clone(), vec![1, 2, 3])]);
+		assert_eq!(
+			Some(&vec![1, 2, 3]),
+			store.provided_content(&key, &content_key)
+		);
+
+		// Updated provider record keeps the content
+		rec.expires = Some(Instant::now());
+		assert!(store.add_provider(rec.clone()).is_ok());
+		assert_eq!(
+			vec![Cow::Borrowed(&rec)],
+			store.provided().collect::<Vec<_>>()
+		);
+		assert!(store.provided_content(&key, &content_key).is_some());
+
+		store.remove_provider(&key, &id);
+		assert!(store.provided_content(&key, &content_key).is_none());
+	}
+
+	#[test]
+	fn reject_record_of_other_network() {
+		let config = MemoryStoreConfig {
+			network_prefix: Some("turing".to_string()),
+			..Default::default()
+		};
+		let mut store = MemoryStore::with_config(PeerId::random(), config);
+		let record = |key: &str| Record::new(key.as_bytes().to_vec(), vec![1, 2, 3]);
+		assert!(store.put(record("turing/1:2:3")).is_ok());
+		assert!(store.put(record("mainnet/1:2:3")).is_err());
+		assert!(store.put(record("1:2:3")).is_err());
+	}
+
+	#[test]
+	fn evict_records_closest_to_expiration() {
+		let config = MemoryStoreConfig {
+			max_records: 2,
+			evict_expiring: true,
+			..Default::default()
+		};
+		let mut store = MemoryStore::with_config(PeerId::random(), config);
+		let now = Instant::now();
+		let record = |key: &str, expires: Duration| Record {
+			expires: Some(now + expires),
+			..Record::new(key.as_bytes().to_vec(), vec![1, 2, 3])
+		};
+
+		assert!(store.put(record("1:1:1", Duration::from_secs(20))).is_ok());
+		assert!(store.put(record("1:1:2", Duration::from_secs(10))).is_ok());
+		assert!(store.put(record("1:1:3", Duration::from_secs(30))).is_ok());
+
+		assert!(store.get(&RecordKey::new(&"1:1:2")).is_none());
+		assert_eq!(
+			store.stats(),
+			StoreStats {
+				records: 2,
+				bytes: 6,
+				evicted: 1
+			}
+		);
+	}
+
+	#[test]
+	fn max_bytes_without_eviction() {
+		let config = MemoryStoreConfig {
+			max_bytes: Some(5),
+			..Default::default()
+		};
+		let mut store = MemoryStore::with_config(PeerId::random(), config);
+		assert!(store
+			.put(Record::new(b"1:1:1".to_vec(), vec![1, 2, 3]))
+			.is_ok());
+		assert!(store
+			.put(Record::new(b"1:1:2".to_vec(), vec![1, 2, 3]))
+			.is_err());
+		assert_eq!(store.stats().bytes, 3);
+	}
+
+	#[test]
+	fn prune_block() {
+		let mut store = MemoryStore::new(PeerId::random());
+		for key in ["1:1:1", "1:2", "12:1:1", "2:1:1"] {
+			assert!(store
+				.put(Record::new(key.as_bytes().to_vec(), vec![1]))
+				.is_ok());
+		}
+		store.prune_block(1);
+		assert_eq!(store.records().count(), 2);
+		assert!(store.get(&RecordKey::new(&"12:1:1")).is_some());
+	}
+
+	#[test]
+	fn prune_provided_keys() {
+		let local_id = PeerId::random();
+		let mut store = MemoryStore::new(local_id);
+		let block_key = cell_fetch::provider_key(1, None);
+		let mut expired = ProviderRecord::new(random_multihash(), local_id, Vec::new());
+		expired.expires = Some(Instant::now());
+		for record in [
+			ProviderRecord::new(block_key.clone(), local_id, Vec::new()),
+			ProviderRecord::new(block_key.clone(), PeerId::random(), Vec::new()),
+			expired.clone(),
+		] {
+			assert!(store.add_provider(record).is_ok());
+		}
+		store.add_provided_content(&block_key, [(RecordKey::new(&"1:0:0"), vec![1])]);
+		assert_eq!(store.oldest_provided_key(), Some(expired.key.clone()));
+
+		assert_eq!(store.prune_expired_providers(Instant::now()), 1);
+		assert!(store.providers(&expired.key).is_empty());
+		assert_eq!(store.oldest_provided_key(), Some(block_key.clone()));
+
+		store.prune_block(1);
+		assert!(store.providers(&block_key).is_empty());
+		assert_eq!(store.provided().count(), 0);
+		assert!(store
+			.provided_content(&block_key, &RecordKey::new(&"1:0:0"))
+			.is_none());
+	}
+
 	#[test]
 	fn update_provider() {
 		let mut store = MemoryStore::new(PeerId::random());
@@ -1,19 +1,20 @@
 use super::kad_mem_providers::{Providers, ProvidersConfig};
+use super::record_validation::{RecordValidationConfig, RecordValidator};
+use super::{cell_fetch, has_network_prefix, prefixed_reference, StoreStats};
 use crate::data::KADEMLIA_STORE_CF;
-use codec::{Decode, Encode};
+use codec::{Decode, DecodeAll, Encode};
+use color_eyre::eyre::{eyre, WrapErr};
 use libp2p::identity::PeerId;
 use libp2p::kad::store::{Error, RecordStore, Result};
 use libp2p::kad::{self, KBucketKey, ProviderRecord, Record, RecordKey};
-use rocksdb::{BoundColumnFamily, IteratorMode};
+use rocksdb::{BoundColumnFamily, IteratorMode, WriteBatch};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::hash_set;
 use std::iter;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tracing::{error, instrument, Level};
-#[cfg(feature = "kademlia-rocksdb")]
-use {rocksdb::WriteBatch, tracing::info};
+use tracing::{error, info, instrument, trace, Level};
 
 #[derive(Serialize, Deserialize, Encode, Decode, Clone)]
 pub struct Entry(pub Vec<u8>, pub KadRecord);
@@ -52,8 +53,7 @@ impl From<Entry> for kad::Record {
 		kad::Record {
 			key: RecordKey::from(key),
 			value: record.value,
-			publisher: (!record.publisher.is_empty())
-				.then(|| PeerId::from_bytes(&record.publisher).expect("Invalid peer ID")),
+			publisher: PeerId::from_bytes(&record.publisher).ok(),
 			expires: (record.ttl > 0)
 				.then(|| Instant::now() + Duration::from_secs(record.ttl.into())),
 		}
@@ -71,6 +71,8 @@ pub struct RocksDBStore {
 	records: Arc<rocksdb::DB>,
 	/// The stored provider records.
 	providers: Providers,
+	/// Validator of the stored records, records are not validated if not set.
+	validator: Option<RecordValidator>,
 }
 
 /// Configuration for a `RocksDBStore`.
@@ -80,6 +82,13 @@ pub struct RocksDBStoreConfig {
 	/// The maximum size of record values, in bytes.
 	pub max_value_bytes: usize,
 	pub providers: ProvidersConfig,
+	/// Network prefix of the accepted record keys.
+	pub network_prefix: Option<String>,
+	/// Compress records with zstd before storing them.
+	/// Stored records are tagged with their format, so both can be read regardless of this flag.
+	pub compression: bool,
+	/// Validation of the record keys and values, records are not validated if not set.
+	pub validation: Option<RecordValidationConfig>,
 }
 
 impl Default for RocksDBStoreConfig {
@@ -88,11 +97,13 @@ impl Default for RocksDBStoreConfig {
 		Self {
 			max_value_bytes: 65 * 1024,
 			providers: Default::default(),
+			network_prefix: None,
+			compression: false,
+			validation: None,
 		}
 	}
 }
 
-#[cfg(feature = "kademlia-rocksdb")]
 impl RocksDBStore {
 	/// Creates a new `RocksDBRecordStore` with the given configuration.
 	pub fn with_config(local_id: PeerId, config: RocksDBStoreConfig, db: Arc<rocksdb::DB>) -> Self {
@@ -100,10 +111,18 @@ impl RocksDBStore {
 			local_key: KBucketKey::from(local_id),
 			records: db,
 			providers: Providers::with_config(config.providers.clone()),
+			validator: config.validation.clone().map(RecordValidator::new),
 			config,
 		}
 	}
 
+	/// Sets the latest block, which is used to check the recency of the records
+	pub fn set_latest_block(&mut self, block_num: u32) {
+		if let Some(validator) = self.validator.as_mut() {
+			validator.set_latest_block(block_num);
+		}
+	}
+
 	#[instrument(level = Level::TRACE, skip(self, f))]
 	/// Retains records that satisfy a given predicate.
 	/// NOTE: This is a suboptimal implementation for store size optimization,
@@ -132,6 +151,69 @@ impl RocksDBStore {
 
 	// Optimizations are not implemented currently
 	pub fn shrink_hashmap(&mut self) {}
+
+	/// Removes all records of the given block with a single range deletion.
+	/// Record keys start with the block number, so the block records are
+	/// in the `{block_num}:` (inclusive) to `{block_num};` (exclusive) key range.
+	/// Provider records of the block are removed as well.
+	pub fn prune_block(&mut self, block_num: u32) {
+		let network_prefix = self.config.network_prefix.as_deref();
+		self.providers
+			.remove_key(&cell_fetch::provider_key(block_num, network_prefix));
+		let Some(cf) = self.get_cf() else {
+			return;
+		};
+		let from = prefixed_reference(network_prefix, format!("{block_num}:"));
+		let to = prefixed_reference(network_prefix, format!("{block_num};"));
+		if let Err(error) = self.records.delete_range_cf(&cf, from, to) {
+			error!("Failed to prune block {block_num} records: {error}");
+		}
+	}
+
+	/// Returns number of records and their total size.
+	/// Records are not evicted from the RocksDB store, expired records are removed on compaction.
+	pub fn stats(&self) -> StoreStats {
+		self.records()
+			.fold(StoreStats::default(), |mut stats, record| {
+				stats.records += 1;
+				stats.bytes += record.value.len();
+				stats
+			})
+	}
+
+	/// Flushes records from memtables to disk
+	pub fn flush(&mut self) {
+		let Some(cf) = self.get_cf() else {
+			return;
+		};
+		if let Err(error) = self.records.flush_cf(&cf) {
+			error!("Failed to flush the RocksDB store: {error}");
+		}
+	}
+
+	/// Adds content for the key provided by the local node
+	pub fn add_provided_content(
+		&mut self,
+		key: &RecordKey,
+		content: impl IntoIterator<Item = (RecordKey, Vec<u8>)>,
+	) {
+		self.providers.add_provided_content(key, content);
+	}
+
+	/// Returns content for the key provided by the local node
+	pub fn provided_content(&self, key: &RecordKey, content_key: &RecordKey) -> Option<&Vec<u8>> {
+		self.providers.provided_content(key, content_key)
+	}
+
+	/// Returns the key provided by the local node which expires first
+	pub fn oldest_provided_key(&self) -> Option<RecordKey> {
+		self.providers.oldest_provided_key()
+	}
+
+	/// Removes expired provider records, returns number of removed records
+	pub fn prune_expired_providers(&mut self, now: Instant) -> usize {
+		self.providers.prune_expired(now)
+	}
 }
 
 impl RocksDBStore {
@@ -145,11 +227,48 @@ impl RocksDBStore {
 	}
 }
 
-pub fn into_kad_record(record: (Vec<u8>, Vec<u8>)) -> kad::Record {
+/// Compression level used for the records (zstd default)
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Format tag of the uncompressed records, prepended to the encoded record
+const FORMAT_ENCODED: u8 = 0;
+/// Format tag of the records compressed with zstd
+const FORMAT_COMPRESSED: u8 = 1;
+
+fn encode_kad_record(record: &KadRecord, compression: bool) -> std::io::Result<Vec<u8>> {
+	let encoded = record.encode();
+	if !compression {
+		return Ok([&[FORMAT_ENCODED][..], &encoded[..]].concat());
+	}
+	let compressed = zstd::encode_all(&encoded[..], COMPRESSION_LEVEL)?;
+	Ok([&[FORMAT_COMPRESSED][..], &compressed[..]].concat())
+}
+
+/// Decodes the stored record, records without a known format tag are not valid
+fn decode_kad_record(value: &[u8]) -> color_eyre::Result<KadRecord> {
+	let decompressed;
+	let value = match value.split_first() {
+		Some((&FORMAT_ENCODED, value)) => value,
+		Some((&FORMAT_COMPRESSED, value)) => {
+			decompressed = zstd::decode_all(value).wrap_err("Invalid compressed record")?;
+			&decompressed[..]
+		},
+		Some((format, _)) => return Err(eyre!("Unknown record format {format}")),
+		None => return Err(eyre!("Empty record")),
+	};
+	KadRecord::decode_all(&mut &value[..]).wrap_err("Invalid encoded record")
+}
+
+/// Decodes the stored key and value, returning `None` if the stored value is not valid
+pub fn into_kad_record(record: (Vec<u8>, Vec<u8>)) -> Option<kad::Record> {
 	let (key, value) = record;
-	KadRecord::decode(&mut &value[..])
-		.map(|record| Entry(key, record).into())
-		.expect("Expected valid encoded record, got invalid")
+	match decode_kad_record(&value) {
+		Ok(record) => Some(Entry(key, record).into()),
+		Err(error) => {
+			error!("Failed to decode record from database: {error:#}");
+			None
+		},
+	}
 }
 
 // NOTE: We are using `Error::ValueTooLarge` as default error for the RocksDB store
@@ -169,7 +288,7 @@ impl RecordStore for RocksDBStore {
 		match self.records.get_cf(&self.get_cf()?, key) {
 			Ok(record) => record
 				.map(|value| (key.to_vec(), value))
-				.map(into_kad_record)
+				.and_then(into_kad_record)
 				.map(Cow::Owned),
 			Err(error) => {
 				error!("Failed to get record from database: {error}");
@@ -186,14 +305,26 @@ impl RecordStore for RocksDBStore {
 			return Err(RocksDBStoreError);
 		}
 
+		if !has_network_prefix(&r.key, self.config.network_prefix.as_deref()) {
+			return Err(RocksDBStoreError);
+		}
+
+		if let Some(Err(error)) = self.validator.as_ref().map(|v| v.validate(&r)) {
+			trace!("Record {:?} rejected: {error}", r.key);
+			return Err(RocksDBStoreError);
+		}
+
 		let Entry(key, record) = r.into();
 
-		self.records
-			.put_cf(&cf, key, record.encode())
-			.map_err(|error| {
-				error!("Failed to put record into database: {error}");
-				RocksDBStoreError
-			})
+		let value = encode_kad_record(&record, self.config.compression).map_err(|error| {
+			error!("Failed to compress record: {error}");
+			RocksDBStoreError
+		})?;
+
+		self.records.put_cf(&cf, key, value).map_err(|error| {
+			error!("Failed to put record into database: {error}");
+			RocksDBStoreError
+		})
 	}
 
 	#[instrument(level = Level::TRACE, skip(self))]
@@ -223,7 +354,7 @@ impl RecordStore for RocksDBStore {
 					result.ok()
 				})
 				.map(|(key, value)| (key.to_vec(), value.to_vec()))
-				.map(into_kad_record)
+				.filter_map(into_kad_record)
 				.map(Cow::Owned),
 		)
 	}
@@ -248,7 +379,9 @@ impl RecordStore for RocksDBStore {
 pub use ttl::ExpirationCompactionFilterFactory;
 
 mod ttl {
-	use super::into_kad_record;
+	use super::{decode_kad_record, Entry};
+	use crate::network::p2p::event_loop::DHTKey;
+	use libp2p::kad::{self, RecordKey};
 	use rocksdb::{
 		compaction_filter::CompactionFilter,
 		compaction_filter_factory::{CompactionFilterContext, CompactionFilterFactory},
@@ -256,14 +389,39 @@ mod ttl {
 	};
 	use std::{ffi::CString, time::Instant};
 
+	/// Maximum TTLs (in seconds) of the cell and row records
+	#[derive(Clone, Copy)]
+	struct MaxTtl {
+		cell: u32,
+		row: u32,
+	}
+
+	impl MaxTtl {
+		fn of(&self, key: &[u8]) -> u32 {
+			match DHTKey::try_from(RecordKey::from(key.to_vec())) {
+				Ok(DHTKey::Row(..)) => self.row,
+				_ => self.cell,
+			}
+		}
+	}
+
 	pub struct ExpirationCompactionFilter {
 		now: Instant,
+		max_ttl: MaxTtl,
 		name: CString,
 	}
 
 	impl CompactionFilter for ExpirationCompactionFilter {
 		fn filter(&mut self, _level: u32, key: &[u8], value: &[u8]) -> CompactionDecision {
-			let record = into_kad_record((key.to_vec(), value.to_vec()));
+			// Records which cannot be decoded are never read, so they are removed
+			let Ok(record) = decode_kad_record(value) else {
+				return CompactionDecision::Remove;
+			};
+			// Records stored with longer TTL than configured for their kind are removed too
+			if record.ttl > self.max_ttl.of(key) {
+				return CompactionDecision::Remove;
+			}
+			let record: kad::Record = Entry(key.to_vec(), record).into();
 			match record.is_expired(self.now) {
 				true => CompactionDecision::Remove,
 				false => CompactionDecision::Keep,
@@ -276,15 +434,22 @@ mod ttl {
 	}
 
 	pub struct ExpirationCompactionFilterFactory {
+		max_ttl: MaxTtl,
 		name: CString,
 	}
 
-	impl Default for ExpirationCompactionFilterFactory {
-		fn default() -> Self {
+	impl ExpirationCompactionFilterFactory {
+		/// Creates a factory of filters which remove expired records,
+		/// and records with TTL longer than the given cell or row TTL (in seconds)
+		pub fn new(cell_ttl: u64, row_ttl: u64) -> Self {
 			let name = CString::new("kademlia_store_expiration_compaction_filter_factory")
 				.expect("CString::new failed");
+			let max_ttl = MaxTtl {
+				cell: u32::try_from(cell_ttl).unwrap_or(u32::MAX),
+				row: u32::try_from(row_ttl).unwrap_or(u32::MAX),
+			};
 
-			ExpirationCompactionFilterFactory { name }
+			ExpirationCompactionFilterFactory { max_ttl, name }
 		}
 	}
 
@@ -296,6 +461,7 @@ mod ttl {
 				CString::new("kademlia_store_expiration_compaction_filter").expect("valid CString");
 			ExpirationCompactionFilter {
 				now: Instant::now(),
+				max_ttl: self.max_ttl,
 				name,
 			}
 		}
@@ -305,3 +471,35 @@ mod ttl {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{decode_kad_record, encode_kad_record, KadRecord};
+	use test_case::test_case;
+
+	fn record(value: Vec<u8>) -> KadRecord {
+		KadRecord {
+			value,
+			publisher: vec![],
+			ttl: 3600,
+		}
+	}
+
+	#[test_case(false; "Uncompressed")]
+	#[test_case(true; "Compressed")]
+	fn record_round_trip(compression: bool) {
+		// Encoded record starts with the zstd magic number
+		let value = vec![0xb5, 0x2f, 0xfd, 0, 0, 0, 0, 0, 0, 0];
+		let encoded = encode_kad_record(&record(value.clone()), compression).unwrap();
+		let decoded = decode_kad_record(&encoded).unwrap();
+		assert_eq!((decoded.value, decoded.ttl), (value, 3600));
+	}
+
+	#[test_case(&[] ; "Empty")]
+	#[test_case(&[2, 0] ; "Unknown format")]
+	#[test_case(&[1, 0x28, 0xb5, 0x2f, 0xfd, 1] ; "Invalid compressed")]
+	#[test_case(&[0, 8, 1] ; "Invalid encoded")]
+	fn invalid_record(value: &[u8]) {
+		assert!(decode_kad_record(value).is_err());
+	}
+}
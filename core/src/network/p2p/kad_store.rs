@@ -0,0 +1,175 @@
+//! Kademlia record store, with backend selected from the configuration at startup.
+
+use super::kad_mem_providers::ProviderIter;
+use super::kad_mem_store::MemoryStore;
+use super::kad_rocksdb_store::RocksDBStore;
+use super::StoreStats;
+use crate::types::{KademliaStoreBackend, LibP2PConfig};
+use libp2p::identity::PeerId;
+use libp2p::kad::store::{RecordStore, Result};
+use libp2p::kad::{ProviderRecord, Record, RecordKey};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Kademlia record store, either in-memory or RocksDB backed.
+/// Providers are kept in memory in both cases.
+pub enum Store {
+	Memory(MemoryStore),
+	RocksDB(RocksDBStore),
+}
+
+impl Store {
+	/// Creates a new store with the backend configured in `LibP2PConfig`.
+	/// RocksDB backed store uses the Kademlia column family of the given database.
+	pub fn with_config(local_id: PeerId, cfg: &LibP2PConfig, db: Arc<rocksdb::DB>) -> Self {
+		match cfg.kademlia.store_backend {
+			KademliaStoreBackend::Memory => {
+				Store::Memory(MemoryStore::with_config(local_id, cfg.into()))
+			},
+			KademliaStoreBackend::RocksDB => {
+				Store::RocksDB(RocksDBStore::with_config(local_id, cfg.into(), db))
+			},
+		}
+	}
+
+	/// Removes expired records and provider records, returns number of removed records
+	pub fn prune_expired_records(&mut self, now: Instant) -> usize {
+		match self {
+			Store::Memory(store) => {
+				let before = store.records().count();
+				store.retain(|_, record| !record.is_expired(now));
+				before - store.records().count() + store.prune_expired_providers(now)
+			},
+			// Skip iterating all records from RocksDB, since TTL will be handled during compaction phase
+			Store::RocksDB(store) => store.prune_expired_providers(now),
+		}
+	}
+
+	/// Returns the key provided by the local node which expires first
+	pub fn oldest_provided_key(&self) -> Option<RecordKey> {
+		match self {
+			Store::Memory(store) => store.oldest_provided_key(),
+			Store::RocksDB(store) => store.oldest_provided_key(),
+		}
+	}
+
+	/// Removes all cell, row and provider records of the given block
+	pub fn prune_block(&mut self, block_num: u32) {
+		match self {
+			Store::Memory(store) => store.prune_block(block_num),
+			Store::RocksDB(store) => store.prune_block(block_num),
+		}
+	}
+
+	/// Sets the latest block, which is used to check the recency of the records
+	pub fn set_latest_block(&mut self, block_num: u32) {
+		match self {
+			Store::Memory(store) => store.set_latest_block(block_num),
+			Store::RocksDB(store) => store.set_latest_block(block_num),
+		}
+	}
+
+	pub fn stats(&self) -> StoreStats {
+		match self {
+			Store::Memory(store) => store.stats(),
+			Store::RocksDB(store) => store.stats(),
+		}
+	}
+
+	pub fn shrink_hashmap(&mut self) {
+		match self {
+			Store::Memory(store) => store.shrink_hashmap(),
+			Store::RocksDB(store) => store.shrink_hashmap(),
+		}
+	}
+
+	pub fn flush(&mut self) {
+		match self {
+			Store::Memory(store) => store.flush(),
+			Store::RocksDB(store) => store.flush(),
+		}
+	}
+
+	/// Adds content for the key provided by the local node
+	pub fn add_provided_content(
+		&mut self,
+		key: &RecordKey,
+		content: impl IntoIterator<Item = (RecordKey, Vec<u8>)>,
+	) {
+		match self {
+			Store::Memory(store) => store.add_provided_content(key, content),
+			Store::RocksDB(store) => store.add_provided_content(key, content),
+		}
+	}
+
+	/// Returns content for the key provided by the local node
+	pub fn provided_content(&self, key: &RecordKey, content_key: &RecordKey) -> Option<&Vec<u8>> {
+		match self {
+			Store::Memory(store) => store.provided_content(key, content_key),
+			Store::RocksDB(store) => store.provided_content(key, content_key),
+		}
+	}
+}
+
+impl RecordStore for Store {
+	type RecordsIter<'a> = Box<dyn Iterator<Item = Cow<'a, Record>> + 'a>;
+
+	type ProvidedIter<'a> = ProviderIter<'a>;
+
+	fn get(&self, k: &RecordKey) -> Option<Cow<'_, Record>> {
+		match self {
+			Store::Memory(store) => store.get(k),
+			Store::RocksDB(store) => store.get(k),
+		}
+	}
+
+	fn put(&mut self, r: Record) -> Result<()> {
+		match self {
+			Store::Memory(store) => store.put(r),
+			Store::RocksDB(store) => store.put(r),
+		}
+	}
+
+	fn remove(&mut self, k: &RecordKey) {
+		match self {
+			Store::Memory(store) => store.remove(k),
+			Store::RocksDB(store) => store.remove(k),
+		}
+	}
+
+	fn records(&self) -> Self::RecordsIter<'_> {
+		match self {
+			Store::Memory(store) => Box::new(store.records()),
+			Store::RocksDB(store) => store.records(),
+		}
+	}
+
+	fn add_provider(&mut self, record: ProviderRecord) -> Result<()> {
+		match self {
+			Store::Memory(store) => store.add_provider(record),
+			Store::RocksDB(store) => store.add_provider(record),
+		}
+	}
+
+	fn providers(&self, key: &RecordKey) -> Vec<ProviderRecord> {
+		match self {
+			Store::Memory(store) => store.providers(key),
+			Store::RocksDB(store) => store.providers(key),
+		}
+	}
+
+	fn provided(&self) -> Self::ProvidedIter<'_> {
+		match self {
+			Store::Memory(store) => store.provided(),
+			Store::RocksDB(store) => store.provided(),
+		}
+	}
+
+	fn remove_provider(&mut self, key: &RecordKey, provider: &PeerId) {
+		match self {
+			Store::Memory(store) => store.remove_provider(key, provider),
+			Store::RocksDB(store) => store.remove_provider(key, provider),
+		}
+	}
+}
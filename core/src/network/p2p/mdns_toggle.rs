@@ -0,0 +1,136 @@
+//! mDNS discovery which can be enabled and disabled at runtime.
+//!
+//! Disabled discovery drops the mDNS behaviour, which stops its interface tasks along with the multicast traffic.
+//! Enabled discovery creates a new behaviour, so the current listen addresses are replayed to it.
+
+use libp2p::{
+	core::{transport::ListenerId, Endpoint},
+	mdns,
+	swarm::{
+		dummy, ConnectionDenied, ConnectionId, FromSwarm, ListenAddresses, NetworkBehaviour,
+		NewListenAddr, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+	},
+	Multiaddr, PeerId,
+};
+use std::{
+	io,
+	task::{Context, Poll},
+};
+
+pub struct Behaviour {
+	config: mdns::Config,
+	local_peer_id: PeerId,
+	inner: Option<mdns::tokio::Behaviour>,
+	listen_addresses: ListenAddresses,
+}
+
+impl Behaviour {
+	pub fn new(config: mdns::Config, local_peer_id: PeerId, is_enabled: bool) -> io::Result<Self> {
+		let inner = is_enabled
+			.then(|| mdns::tokio::Behaviour::new(config.clone(), local_peer_id))
+			.transpose()?;
+		Ok(Self {
+			config,
+			local_peer_id,
+			inner,
+			listen_addresses: Default::default(),
+		})
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.inner.is_some()
+	}
+
+	pub fn set_enabled(&mut self, is_enabled: bool) -> io::Result<()> {
+		if is_enabled == self.is_enabled() {
+			return Ok(());
+		}
+		if !is_enabled {
+			self.inner = None;
+			return Ok(());
+		}
+
+		let mut inner = mdns::tokio::Behaviour::new(self.config.clone(), self.local_peer_id)?;
+		for addr in self.listen_addresses.iter() {
+			inner.on_swarm_event(FromSwarm::NewListenAddr(NewListenAddr {
+				listener_id: ListenerId::next(),
+				addr,
+			}));
+		}
+		self.inner = Some(inner);
+		Ok(())
+	}
+
+	pub fn discovered_nodes(&self) -> impl Iterator<Item = &PeerId> {
+		self.inner.iter().flat_map(|inner| inner.discovered_nodes())
+	}
+}
+
+impl NetworkBehaviour for Behaviour {
+	type ConnectionHandler = dummy::ConnectionHandler;
+	type ToSwarm = mdns::Event;
+
+	fn handle_pending_outbound_connection(
+		&mut self,
+		connection_id: ConnectionId,
+		maybe_peer: Option<PeerId>,
+		addresses: &[Multiaddr],
+		effective_role: Endpoint,
+	) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+		match self.inner.as_mut() {
+			Some(inner) => inner.handle_pending_outbound_connection(
+				connection_id,
+				maybe_peer,
+				addresses,
+				effective_role,
+			),
+			None => Ok(vec![]),
+		}
+	}
+
+	fn handle_established_inbound_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		_peer: PeerId,
+		_local_addr: &Multiaddr,
+		_remote_addr: &Multiaddr,
+	) -> Result<THandler<Self>, ConnectionDenied> {
+		Ok(dummy::ConnectionHandler)
+	}
+
+	fn handle_established_outbound_connection(
+		&mut self,
+		_connection_id: ConnectionId,
+		_peer: PeerId,
+		_addr: &Multiaddr,
+		_role_override: Endpoint,
+	) -> Result<THandler<Self>, ConnectionDenied> {
+		Ok(dummy::ConnectionHandler)
+	}
+
+	fn on_swarm_event(&mut self, event: FromSwarm) {
+		self.listen_addresses.on_swarm_event(&event);
+		if let Some(inner) = self.inner.as_mut() {
+			inner.on_swarm_event(event);
+		}
+	}
+
+	fn on_connection_handler_event(
+		&mut self,
+		_peer_id: PeerId,
+		_connection_id: ConnectionId,
+		event: THandlerOutEvent<Self>,
+	) {
+		match event {}
+	}
+
+	fn poll(
+		&mut self,
+		cx: &mut Context<'_>,
+	) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+		match self.inner.as_mut() {
+			Some(inner) => inner.poll(cx),
+			None => Poll::Pending,
+		}
+	}
+}
@@ -0,0 +1,129 @@
+//! Adaptive limit of the parallel DHT GET queries.
+//!
+//! Limit is adjusted AIMD-style after each batch of queries: it is increased by one after a healthy batch,
+//! and halved when the batch duration spikes above the average, or when too many queries in the batch fail.
+
+use std::time::Duration;
+
+/// Failure rate of the batch above which the limit is decreased
+const MAX_FAILURE_RATE: f64 = 0.5;
+/// Batch is considered slow when its duration is this many times longer than the average
+const LATENCY_SPIKE_FACTOR: f64 = 2.0;
+/// Weight of the latest batch duration in the average
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Current DHT GET parallelization limit with the average duration of the query batches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParallelizationStats {
+	pub limit: usize,
+	/// Not set until the first batch is finished
+	pub average_latency: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct ParallelizationLimit {
+	limit: usize,
+	/// Upper bound of the limit, static limit is used if not set
+	max_limit: Option<usize>,
+	average_latency: Option<Duration>,
+}
+
+impl ParallelizationLimit {
+	pub fn new(limit: usize, max_limit: Option<usize>) -> Self {
+		Self {
+			limit,
+			max_limit,
+			average_latency: None,
+		}
+	}
+
+	pub fn limit(&self) -> usize {
+		self.limit
+	}
+
+	/// Sets the current limit, raising the upper bound of the adaptive limit if needed
+	pub fn set_limit(&mut self, limit: usize) {
+		self.limit = limit.max(1);
+		self.max_limit = self.max_limit.map(|max_limit| max_limit.max(self.limit));
+	}
+
+	/// Adjusts the limit based on the batch duration and number of failed queries in the batch
+	pub fn update(&mut self, latency: Duration, queries: usize, failures: usize) {
+		let Some(max_limit) = self.max_limit else {
+			return;
+		};
+		if queries == 0 {
+			return;
+		}
+
+		let failure_rate = failures as f64 / queries as f64;
+		let is_latency_spike = self
+			.average_latency
+			.is_some_and(|average| latency > average.mul_f64(LATENCY_SPIKE_FACTOR));
+
+		self.limit = match failure_rate > MAX_FAILURE_RATE || is_latency_spike {
+			true => (self.limit / 2).max(1),
+			false => (self.limit + 1).min(max_limit),
+		};
+
+		self.average_latency = Some(match self.average_latency {
+			Some(average) => {
+				average.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING)
+			},
+			None => latency,
+		});
+	}
+
+	pub fn stats(&self) -> ParallelizationStats {
+		ParallelizationStats {
+			limit: self.limit,
+			average_latency: self.average_latency,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn static_limit() {
+		let mut limit = ParallelizationLimit::new(20, None);
+		limit.update(Duration::from_secs(1), 20, 20);
+		assert_eq!(limit.limit(), 20);
+		assert_eq!(limit.stats().average_latency, None);
+	}
+
+	#[test]
+	fn additive_increase_multiplicative_decrease() {
+		let mut limit = ParallelizationLimit::new(20, Some(21));
+		limit.update(Duration::from_secs(1), 20, 0);
+		assert_eq!(limit.limit(), 21);
+		limit.update(Duration::from_secs(1), 21, 0);
+		assert_eq!(limit.limit(), 21);
+
+		// Too many failures
+		limit.update(Duration::from_secs(1), 21, 11);
+		assert_eq!(limit.limit(), 10);
+
+		// Latency spike
+		limit.update(Duration::from_secs(3), 10, 0);
+		assert_eq!(limit.limit(), 5);
+
+		for _ in 0..10 {
+			limit.update(Duration::from_millis(1), 1, 1);
+		}
+		assert_eq!(limit.limit(), 1);
+	}
+
+	#[test]
+	fn set_limit_raises_max_limit() {
+		let mut limit = ParallelizationLimit::new(20, Some(21));
+		limit.set_limit(30);
+		limit.update(Duration::from_secs(1), 30, 0);
+		assert_eq!(limit.limit(), 30);
+
+		limit.set_limit(0);
+		assert_eq!(limit.limit(), 1);
+	}
+}
@@ -0,0 +1,258 @@
+//! NAT-PMP / PCP port mapping, used as a fallback when no UPnP gateway is found.
+//!
+//! Listening port is mapped on the default gateway with PCP (RFC 6887), falling back to NAT-PMP (RFC 6886)
+//! for gateways which support only the older protocol. Mapping is renewed by a background task in half of its lifetime.
+
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use std::{
+	fmt::{self, Display, Formatter},
+	net::{IpAddr, Ipv4Addr, Ipv6Addr},
+	time::Duration,
+};
+use tokio::{net::UdpSocket, sync::mpsc};
+use tracing::debug;
+
+const SERVER_PORT: u16 = 5351;
+/// Requested mapping lifetime (in seconds)
+const REQUESTED_LIFETIME: u32 = 7200;
+const REQUEST_RETRIES: u32 = 4;
+/// Response timeout of the first request, doubled on each retry
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(250);
+/// Interval between the mapping attempts after a failure
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+const MIN_RENEWAL_INTERVAL: Duration = Duration::from_secs(60);
+
+const PCP_VERSION: u8 = 2;
+const PCP_OPCODE_MAP: u8 = 1;
+const NAT_PMP_VERSION: u8 = 0;
+const NAT_PMP_OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+const NAT_PMP_OPCODE_MAP_TCP: u8 = 2;
+const RESPONSE_BIT: u8 = 0x80;
+const TCP_PROTOCOL: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortMappingProtocol {
+	Upnp,
+	Pcp,
+	NatPmp,
+}
+
+impl Display for PortMappingProtocol {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			PortMappingProtocol::Upnp => write!(f, "UPnP"),
+			PortMappingProtocol::Pcp => write!(f, "PCP"),
+			PortMappingProtocol::NatPmp => write!(f, "NAT-PMP"),
+		}
+	}
+}
+
+/// External address mapped on the gateway
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortMapping {
+	pub protocol: PortMappingProtocol,
+	pub external_address: Multiaddr,
+}
+
+#[derive(Debug)]
+pub struct Mapping {
+	protocol: PortMappingProtocol,
+	external_ip: Ipv4Addr,
+	external_port: u16,
+	lifetime: Duration,
+}
+
+/// Starts the port mapping task on demand and receives its results
+pub struct PortMapper {
+	port: u16,
+	is_ws_transport: bool,
+	results: Option<mpsc::UnboundedReceiver<Result<Mapping>>>,
+}
+
+impl PortMapper {
+	pub fn new(port: u16, is_ws_transport: bool) -> Self {
+		Self {
+			port,
+			is_ws_transport,
+			results: None,
+		}
+	}
+
+	/// Spawns the background task which maps the port and renews the mapping, if not already spawned
+	pub fn start(&mut self) {
+		if self.results.is_some() {
+			return;
+		}
+		let (sender, receiver) = mpsc::unbounded_channel();
+		tokio::spawn(run(self.port, sender));
+		self.results = Some(receiver);
+	}
+
+	/// Waits for the next mapping result, or forever if the task is not started
+	pub async fn next(&mut self) -> Result<Mapping> {
+		match self.results.as_mut() {
+			Some(results) => match results.recv().await {
+				Some(result) => result,
+				None => std::future::pending().await,
+			},
+			None => std::future::pending().await,
+		}
+	}
+
+	pub fn port_mapping(&self, mapping: &Mapping) -> PortMapping {
+		let mut external_address = Multiaddr::empty()
+			.with(Protocol::Ip4(mapping.external_ip))
+			.with(Protocol::Tcp(mapping.external_port));
+		if self.is_ws_transport {
+			external_address.push(Protocol::Ws("/".into()));
+		}
+		PortMapping {
+			protocol: mapping.protocol,
+			external_address,
+		}
+	}
+}
+
+// Maps the port and renews the mapping, until the results receiver is dropped
+async fn run(port: u16, results: mpsc::UnboundedSender<Result<Mapping>>) {
+	loop {
+		let result = map_port(port).await;
+		let renewal_interval = match &result {
+			Ok(mapping) => (mapping.lifetime / 2).max(MIN_RENEWAL_INTERVAL),
+			Err(_) => RETRY_INTERVAL,
+		};
+		if results.send(result).is_err() {
+			return;
+		}
+		tokio::time::sleep(renewal_interval).await;
+	}
+}
+
+async fn map_port(port: u16) -> Result<Mapping> {
+	let gateway = default_gateway()?;
+	let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+	socket.connect((gateway, SERVER_PORT)).await?;
+	let IpAddr::V4(client_ip) = socket.local_addr()?.ip() else {
+		return Err(eyre!("gateway is not reachable over IPv4"));
+	};
+
+	match map_pcp(&socket, client_ip, port).await {
+		Ok(mapping) => Ok(mapping),
+		Err(error) => {
+			debug!("PCP mapping failed, falling back to NAT-PMP: {error:#}");
+			map_nat_pmp(&socket, port).await
+		},
+	}
+}
+
+// Reads the default IPv4 gateway from the kernel routing table (Linux only)
+fn default_gateway() -> Result<Ipv4Addr> {
+	let routes = std::fs::read_to_string("/proc/net/route").wrap_err("error reading routes")?;
+	for line in routes.lines().skip(1) {
+		let fields = line.split_whitespace().collect::<Vec<_>>();
+		// Default route has zero destination
+		if let [_, "00000000", gateway, ..] = fields.as_slice() {
+			// Gateway is written as hex of the address bytes in the host (little-endian) order
+			let gateway = u32::from_str_radix(gateway, 16)?;
+			return Ok(Ipv4Addr::from(gateway.to_le_bytes()));
+		}
+	}
+	Err(eyre!("default gateway not found"))
+}
+
+// Sends the request with retries and exponential timeout, returns the response length
+async fn request(socket: &UdpSocket, request: &[u8], response: &mut [u8]) -> Result<usize> {
+	let mut timeout = REQUEST_TIMEOUT;
+	for _ in 0..REQUEST_RETRIES {
+		socket.send(request).await?;
+		match tokio::time::timeout(timeout, socket.recv(response)).await {
+			Ok(length) => return Ok(length?),
+			Err(_) => timeout *= 2,
+		}
+	}
+	Err(eyre!("no response from the gateway"))
+}
+
+async fn map_pcp(socket: &UdpSocket, client_ip: Ipv4Addr, port: u16) -> Result<Mapping> {
+	let nonce: [u8; 12] = rand::random();
+
+	let mut map_request = Vec::with_capacity(60);
+	map_request.extend_from_slice(&[PCP_VERSION, PCP_OPCODE_MAP, 0, 0]);
+	map_request.extend_from_slice(&REQUESTED_LIFETIME.to_be_bytes());
+	map_request.extend_from_slice(&client_ip.to_ipv6_mapped().octets());
+	map_request.extend_from_slice(&nonce);
+	map_request.extend_from_slice(&[TCP_PROTOCOL, 0, 0, 0]);
+	map_request.extend_from_slice(&port.to_be_bytes());
+	map_request.extend_from_slice(&port.to_be_bytes());
+	map_request.extend_from_slice(&Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+
+	let mut response = [0u8; 1100];
+	let length = request(socket, &map_request, &mut response).await?;
+	let response = &response[..length];
+
+	if length < 60 || response[0] != PCP_VERSION || response[1] != PCP_OPCODE_MAP | RESPONSE_BIT {
+		return Err(eyre!("invalid PCP response"));
+	}
+	if response[3] != 0 {
+		return Err(eyre!("PCP mapping failed with result code {}", response[3]));
+	}
+	if response[24..36] != nonce {
+		return Err(eyre!("PCP response nonce mismatch"));
+	}
+
+	let external_ip: [u8; 16] = response[44..60].try_into()?;
+	Ok(Mapping {
+		protocol: PortMappingProtocol::Pcp,
+		external_ip: Ipv6Addr::from(external_ip)
+			.to_ipv4_mapped()
+			.ok_or_else(|| eyre!("PCP mapped external address is not IPv4"))?,
+		external_port: u16::from_be_bytes(response[42..44].try_into()?),
+		lifetime: Duration::from_secs(u32::from_be_bytes(response[4..8].try_into()?).into()),
+	})
+}
+
+async fn map_nat_pmp(socket: &UdpSocket, port: u16) -> Result<Mapping> {
+	let mut response = [0u8; 16];
+
+	let length = request(
+		socket,
+		&[NAT_PMP_VERSION, NAT_PMP_OPCODE_EXTERNAL_ADDRESS],
+		&mut response,
+	)
+	.await?;
+	nat_pmp_result(&response[..length], NAT_PMP_OPCODE_EXTERNAL_ADDRESS, 12)?;
+	let external_ip = Ipv4Addr::new(response[8], response[9], response[10], response[11]);
+
+	let mut map_request = vec![NAT_PMP_VERSION, NAT_PMP_OPCODE_MAP_TCP, 0, 0];
+	map_request.extend_from_slice(&port.to_be_bytes());
+	map_request.extend_from_slice(&port.to_be_bytes());
+	map_request.extend_from_slice(&REQUESTED_LIFETIME.to_be_bytes());
+
+	let length = request(socket, &map_request, &mut response).await?;
+	nat_pmp_result(&response[..length], NAT_PMP_OPCODE_MAP_TCP, 16)?;
+
+	Ok(Mapping {
+		protocol: PortMappingProtocol::NatPmp,
+		external_ip,
+		external_port: u16::from_be_bytes(response[10..12].try_into()?),
+		lifetime: Duration::from_secs(u32::from_be_bytes(response[12..16].try_into()?).into()),
+	})
+}
+
+// Checks the NAT-PMP response header and result code
+fn nat_pmp_result(response: &[u8], opcode: u8, length: usize) -> Result<()> {
+	if response.len() < length
+		|| response[0] != NAT_PMP_VERSION
+		|| response[1] != opcode | RESPONSE_BIT
+	{
+		return Err(eyre!("invalid NAT-PMP response"));
+	}
+	match u16::from_be_bytes([response[2], response[3]]) {
+		0 => Ok(()),
+		code => Err(eyre!("NAT-PMP request failed with result code {code}")),
+	}
+}
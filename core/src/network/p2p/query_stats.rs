@@ -0,0 +1,112 @@
+//! Statistics of the completed DHT GET and PUT queries, aggregated into histograms.
+//!
+//! Kademlia does not expose the number of hops of a query, so the number of peers contacted
+//! by the query is tracked as a measure of the lookup depth instead.
+
+use libp2p::kad;
+
+/// Upper bounds of the query duration buckets, in milliseconds
+const DURATION_BOUNDS: &[u64] = &[50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+/// Upper bounds of the contacted peers buckets
+const PEERS_BOUNDS: &[u64] = &[1, 2, 5, 10, 20, 50, 100];
+
+/// Histogram with fixed bucket bounds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+	/// Inclusive upper bounds of the buckets
+	pub bounds: &'static [u64],
+	/// Number of values in each bucket, the last one counts the values above all bounds
+	pub counts: Vec<u64>,
+	/// Sum of all values
+	pub sum: u64,
+}
+
+impl Histogram {
+	pub fn new(bounds: &'static [u64]) -> Self {
+		Self {
+			bounds,
+			counts: vec![0; bounds.len() + 1],
+			sum: 0,
+		}
+	}
+
+	pub fn observe(&mut self, value: u64) {
+		let bucket = self
+			.bounds
+			.iter()
+			.position(|&bound| value <= bound)
+			.unwrap_or(self.bounds.len());
+		self.counts[bucket] += 1;
+		self.sum += value;
+	}
+
+	pub fn count(&self) -> u64 {
+		self.counts.iter().sum()
+	}
+
+	pub fn mean(&self) -> Option<f64> {
+		let count = self.count();
+		(count > 0).then(|| self.sum as f64 / count as f64)
+	}
+}
+
+/// Statistics of the completed queries of one kind
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryKindStats {
+	pub successes: u64,
+	pub failures: u64,
+	/// Query durations, in milliseconds
+	pub duration: Histogram,
+	/// Number of peers contacted by the query
+	pub contacted_peers: Histogram,
+}
+
+impl Default for QueryKindStats {
+	fn default() -> Self {
+		Self {
+			successes: 0,
+			failures: 0,
+			duration: Histogram::new(DURATION_BOUNDS),
+			contacted_peers: Histogram::new(PEERS_BOUNDS),
+		}
+	}
+}
+
+impl QueryKindStats {
+	pub fn record(&mut self, stats: &kad::QueryStats, is_success: bool) {
+		if is_success {
+			self.successes += 1;
+		} else {
+			self.failures += 1;
+		}
+		if let Some(duration) = stats.duration() {
+			self.duration.observe(duration.as_millis() as u64);
+		}
+		self.contacted_peers.observe(stats.num_requests() as u64);
+	}
+}
+
+/// Statistics of the completed DHT queries since the start
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DHTQueryStats {
+	pub get: QueryKindStats,
+	pub put: QueryKindStats,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn histogram_buckets() {
+		let mut histogram = Histogram::new(&[10, 100]);
+		assert_eq!(histogram.mean(), None);
+
+		for value in [1, 10, 11, 100, 1000] {
+			histogram.observe(value);
+		}
+		assert_eq!(histogram.counts, vec![2, 2, 1]);
+		assert_eq!(histogram.count(), 5);
+		assert_eq!(histogram.mean(), Some(224.4));
+	}
+}
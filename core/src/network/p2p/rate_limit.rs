@@ -0,0 +1,174 @@
+//! Per-peer rate limiting of the inbound Kademlia requests.
+//!
+//! Requests above the per-second limit are dropped, and each second in which the peer exceeded the limit is counted as a violation.
+//! Peers with too many violations are temporarily blocked, and peers blocked too many times are blocked permanently.
+//! Kademlia doesn't report the source of inbound GET requests, so only inbound PUT requests can be attributed to a peer and limited.
+
+use libp2p::PeerId;
+use std::{
+	collections::{BTreeMap, HashMap},
+	time::Duration,
+};
+use tokio::time::Instant;
+
+use crate::types::InboundRateLimitConfig;
+
+/// Length of the window in which the requests are counted
+const WINDOW: Duration = Duration::from_secs(1);
+/// Peers idle for longer than this are forgotten, along with their violations
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Number of tracked peers above which the idle peers are pruned
+const PRUNE_THRESHOLD: usize = 1024;
+/// Number of the temporary blocks after which the peer is blocked permanently
+const MAX_TEMPORARY_BLOCKS: u32 = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum RateLimit {
+	Allowed,
+	Exceeded,
+	/// Limit is exceeded too many times, peer should be blocked until the block expires
+	Block,
+	/// Peer is blocked temporarily too many times, and should be blocked permanently
+	BlockPermanently,
+}
+
+struct PeerRequests {
+	window_start: Instant,
+	requests: u32,
+	violations: u32,
+}
+
+pub struct InboundRateLimiter {
+	config: InboundRateLimitConfig,
+	peers: HashMap<PeerId, PeerRequests>,
+	/// Temporarily blocked peers, by the block expiry
+	blocked: BTreeMap<Instant, Vec<PeerId>>,
+	/// Number of the temporary blocks of each peer
+	offenses: HashMap<PeerId, u32>,
+}
+
+impl InboundRateLimiter {
+	pub fn new(config: InboundRateLimitConfig) -> Self {
+		Self {
+			config,
+			peers: Default::default(),
+			blocked: Default::default(),
+			offenses: Default::default(),
+		}
+	}
+
+	/// Counts the inbound request from the peer and checks it against the limit
+	pub fn check(&mut self, peer_id: PeerId, now: Instant) -> RateLimit {
+		if self.peers.len() > PRUNE_THRESHOLD {
+			self.peers
+				.retain(|_, peer| now.duration_since(peer.window_start) < IDLE_TIMEOUT);
+		}
+
+		let peer = self.peers.entry(peer_id).or_insert(PeerRequests {
+			window_start: now,
+			requests: 0,
+			violations: 0,
+		});
+
+		if now.duration_since(peer.window_start) >= WINDOW {
+			if now.duration_since(peer.window_start) >= IDLE_TIMEOUT {
+				peer.violations = 0;
+			}
+			peer.window_start = now;
+			peer.requests = 0;
+		}
+
+		peer.requests += 1;
+		if peer.requests <= self.config.requests_per_second {
+			return RateLimit::Allowed;
+		}
+		// Violation is counted once per window
+		if peer.requests == self.config.requests_per_second + 1 {
+			peer.violations += 1;
+		}
+		if peer.violations < self.config.max_violations {
+			return RateLimit::Exceeded;
+		}
+
+		self.peers.remove(&peer_id);
+		let offenses = self.offenses.entry(peer_id).or_default();
+		*offenses += 1;
+		if *offenses >= MAX_TEMPORARY_BLOCKS {
+			self.offenses.remove(&peer_id);
+			return RateLimit::BlockPermanently;
+		}
+		let expires = now + Duration::from_secs(self.config.block_duration);
+		self.blocked.entry(expires).or_default().push(peer_id);
+		RateLimit::Block
+	}
+
+	/// Returns the expiry of the earliest temporary block
+	pub fn next_unblock(&self) -> Option<Instant> {
+		self.blocked.keys().next().copied()
+	}
+
+	/// Removes and returns the peers whose temporary block has expired
+	pub fn expired_blocks(&mut self, now: Instant) -> Vec<PeerId> {
+		let pending = self.blocked.split_off(&(now + Duration::from_nanos(1)));
+		std::mem::replace(&mut self.blocked, pending)
+			.into_values()
+			.flatten()
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn block_after_violations() {
+		let mut limiter = InboundRateLimiter::new(InboundRateLimitConfig {
+			requests_per_second: 2,
+			max_violations: 2,
+			block_duration: 60,
+		});
+		let peer_id = PeerId::random();
+		let now = Instant::now();
+
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Allowed);
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Allowed);
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Exceeded);
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Exceeded);
+		assert_eq!(limiter.check(PeerId::random(), now), RateLimit::Allowed);
+
+		let now = now + WINDOW;
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Allowed);
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Allowed);
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Block);
+		assert_eq!(limiter.next_unblock(), Some(now + Duration::from_secs(60)));
+
+		assert!(limiter.expired_blocks(now).is_empty());
+		assert_eq!(
+			limiter.expired_blocks(now + Duration::from_secs(60)),
+			vec![peer_id]
+		);
+		assert_eq!(limiter.next_unblock(), None);
+	}
+
+	#[test]
+	fn block_permanently() {
+		let mut limiter = InboundRateLimiter::new(InboundRateLimitConfig {
+			requests_per_second: 1,
+			max_violations: 1,
+			block_duration: 60,
+		});
+		let peer_id = PeerId::random();
+		let mut now = Instant::now();
+
+		for _ in 1..MAX_TEMPORARY_BLOCKS {
+			assert_eq!(limiter.check(peer_id, now), RateLimit::Allowed);
+			assert_eq!(limiter.check(peer_id, now), RateLimit::Block);
+			now += Duration::from_secs(60);
+			assert_eq!(limiter.expired_blocks(now), vec![peer_id]);
+		}
+		assert_eq!(limiter.check(peer_id, now), RateLimit::Allowed);
+		assert_eq!(limiter.check(peer_id, now), RateLimit::BlockPermanently);
+		assert_eq!(limiter.next_unblock(), None);
+	}
+}
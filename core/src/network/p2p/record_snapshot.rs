@@ -0,0 +1,149 @@
+//! Kademlia record store snapshots.
+//!
+//! Snapshot is used to warm-start a new node, or to migrate records between the memory and RocksDB stores.
+//! File starts with a header (magic, version and export time), followed by the length prefixed SCALE encoded records.
+//! Record expiry is stored as TTL relative to the export time, so records keep their expiry after the import.
+
+use codec::{Decode, Encode};
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use libp2p::kad::{store::RecordStore, Record};
+use std::{
+	fs::File,
+	io::{BufRead, BufReader, BufWriter, Read, Write},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::kad_rocksdb_store::Entry;
+
+const MAGIC: [u8; 4] = *b"avlr";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Encode, Decode)]
+struct Header {
+	magic: [u8; 4],
+	version: u8,
+	/// Export time, in seconds since the Unix epoch
+	exported_at: u64,
+}
+
+fn unix_timestamp() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs()
+}
+
+/// Writes all non-expired records from the store to the snapshot file, returns number of exported records
+pub fn export(store: &impl RecordStore, path: &str) -> Result<usize> {
+	let file = File::create(path).wrap_err("error creating record snapshot")?;
+	let mut writer = BufWriter::new(file);
+
+	let header = Header {
+		magic: MAGIC,
+		version: VERSION,
+		exported_at: unix_timestamp(),
+	};
+	writer
+		.write_all(&header.encode())
+		.wrap_err("error writing record snapshot header")?;
+
+	let now = Instant::now();
+	let mut exported = 0;
+	for record in store.records() {
+		if record.expires.is_some_and(|expires| expires <= now) {
+			continue;
+		}
+		let entry = Entry::from(record.into_owned()).encode();
+		writer
+			.write_all(&(entry.len() as u32).to_le_bytes())
+			.and_then(|_| writer.write_all(&entry))
+			.wrap_err("error writing record snapshot")?;
+		exported += 1;
+	}
+
+	writer.flush().wrap_err("error writing record snapshot")?;
+	Ok(exported)
+}
+
+/// Reads the snapshot file and puts its records into the store, returns number of imported records.
+/// Records which expired since the export, or which are rejected by the store, are skipped.
+pub fn import(store: &mut impl RecordStore, path: &str) -> Result<usize> {
+	let file = File::open(path).wrap_err("error opening record snapshot")?;
+	let mut reader = BufReader::new(file);
+
+	let mut header = [0u8; 13];
+	reader
+		.read_exact(&mut header)
+		.wrap_err("error reading record snapshot header")?;
+	let header = Header::decode(&mut &header[..]).wrap_err("error decoding snapshot header")?;
+	if header.magic != MAGIC || header.version != VERSION {
+		return Err(eyre!("unsupported record snapshot format"));
+	}
+	let elapsed = Duration::from_secs(unix_timestamp().saturating_sub(header.exported_at));
+
+	let now = Instant::now();
+	let mut imported = 0;
+	while !reader
+		.fill_buf()
+		.wrap_err("error reading record snapshot")?
+		.is_empty()
+	{
+		let mut length = [0u8; 4];
+		reader
+			.read_exact(&mut length)
+			.wrap_err("error reading record snapshot")?;
+		let mut entry = vec![0u8; u32::from_le_bytes(length) as usize];
+		reader
+			.read_exact(&mut entry)
+			.wrap_err("error reading record snapshot")?;
+		let entry = Entry::decode(&mut &entry[..]).wrap_err("error decoding snapshot record")?;
+
+		let mut record = Record::from(entry);
+		if let Some(expires) = record.expires {
+			match expires.checked_sub(elapsed) {
+				Some(expires) if expires > now => record.expires = Some(expires),
+				_ => continue,
+			}
+		}
+		if store.put(record).is_ok() {
+			imported += 1;
+		}
+	}
+	Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use libp2p::{
+		kad::{store::MemoryStore, RecordKey},
+		PeerId,
+	};
+
+	#[test]
+	fn test_export_and_import() {
+		let path = std::env::temp_dir().join("avail_light_record_snapshot_test.bin");
+		let path = path.to_str().unwrap();
+
+		let mut store = MemoryStore::new(PeerId::random());
+		let mut record = Record::new(RecordKey::new(&"1:0:0"), vec![1; 80]);
+		record.expires = Some(Instant::now() + Duration::from_secs(3600));
+		store.put(record.clone()).unwrap();
+		let mut expired = Record::new(RecordKey::new(&"1:0:1"), vec![2; 80]);
+		expired.expires = Some(Instant::now() - Duration::from_secs(1));
+		store.put(expired).unwrap();
+
+		assert_eq!(export(&store, path).unwrap(), 1);
+
+		let mut imported_store = MemoryStore::new(PeerId::random());
+		assert_eq!(import(&mut imported_store, path).unwrap(), 1);
+		let imported = imported_store.get(&record.key).unwrap();
+		assert_eq!(imported.value, record.value);
+		assert!(imported.expires.is_some());
+
+		std::fs::remove_file(path).unwrap();
+	}
+}
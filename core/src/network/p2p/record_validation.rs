@@ -0,0 +1,136 @@
+//! Validation of the records before they are put into the Kademlia store.
+//!
+//! Cell records need to have `block:row:col` keys and values of the cell content size,
+//! while row records need to have `block:row` keys and values of a whole number of chunks.
+//! Block numbers are checked against the latest known block, if the recency window is configured.
+
+use super::{event_loop::DHTKey, signed_record};
+use kate_recovery::config;
+use libp2p::kad::Record;
+use std::fmt;
+
+#[derive(Debug, Clone, Default)]
+pub struct RecordValidationConfig {
+	/// Maximum distance in blocks between the record block and the latest block, not checked if not set
+	pub recency_window: Option<u32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InvalidRecord {
+	Key,
+	CellSize(usize),
+	RowSize(usize),
+	/// Record block is outside of the recency window
+	Block(u32),
+}
+
+impl fmt::Display for InvalidRecord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			InvalidRecord::Key => write!(f, "Invalid record key"),
+			InvalidRecord::CellSize(size) => write!(f, "Invalid cell record size {size}"),
+			InvalidRecord::RowSize(size) => write!(f, "Invalid row record size {size}"),
+			InvalidRecord::Block(block_num) => {
+				write!(
+					f,
+					"Record block {block_num} is outside of the recency window"
+				)
+			},
+		}
+	}
+}
+
+pub struct RecordValidator {
+	config: RecordValidationConfig,
+	latest_block: Option<u32>,
+}
+
+impl RecordValidator {
+	pub fn new(config: RecordValidationConfig) -> Self {
+		Self {
+			config,
+			latest_block: None,
+		}
+	}
+
+	pub fn set_latest_block(&mut self, block_num: u32) {
+		self.latest_block = self.latest_block.max(Some(block_num));
+	}
+
+	pub fn validate(&self, record: &Record) -> Result<(), InvalidRecord> {
+		let size = signed_record::unwrap_value(&record.value).len();
+		let block_num = match DHTKey::try_from(record.key.clone()) {
+			Ok(DHTKey::Cell(block_num, ..)) => {
+				if size != config::COMMITMENT_SIZE + config::CHUNK_SIZE {
+					return Err(InvalidRecord::CellSize(size));
+				}
+				block_num
+			},
+			Ok(DHTKey::Row(block_num, ..)) => {
+				if size == 0 || size % config::CHUNK_SIZE != 0 {
+					return Err(InvalidRecord::RowSize(size));
+				}
+				block_num
+			},
+			Err(_) => return Err(InvalidRecord::Key),
+		};
+
+		if let (Some(window), Some(latest_block)) = (self.config.recency_window, self.latest_block)
+		{
+			if block_num.abs_diff(latest_block) > window {
+				return Err(InvalidRecord::Block(block_num));
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record(key: &str, size: usize) -> Record {
+		Record::new(key.as_bytes().to_vec(), vec![0; size])
+	}
+
+	#[test]
+	fn validate_records() {
+		let mut validator = RecordValidator::new(RecordValidationConfig {
+			recency_window: Some(10),
+		});
+		let cell_size = config::COMMITMENT_SIZE + config::CHUNK_SIZE;
+
+		assert_eq!(validator.validate(&record("100:1:2", cell_size)), Ok(()));
+		assert_eq!(
+			validator.validate(&record("avail/100:1:2", cell_size)),
+			Ok(())
+		);
+		assert_eq!(
+			validator.validate(&record("100:1:2", cell_size - 1)),
+			Err(InvalidRecord::CellSize(cell_size - 1))
+		);
+		assert_eq!(
+			validator.validate(&record("100:1", config::CHUNK_SIZE * 4)),
+			Ok(())
+		);
+		assert_eq!(
+			validator.validate(&record("100:1", config::CHUNK_SIZE + 1)),
+			Err(InvalidRecord::RowSize(config::CHUNK_SIZE + 1))
+		);
+		assert_eq!(
+			validator.validate(&record("garbage", cell_size)),
+			Err(InvalidRecord::Key)
+		);
+
+		validator.set_latest_block(200);
+		assert_eq!(
+			validator.validate(&record("100:1:2", cell_size)),
+			Err(InvalidRecord::Block(100))
+		);
+		assert_eq!(validator.validate(&record("195:1:2", cell_size)), Ok(()));
+		assert_eq!(
+			validator.validate(&record("300:1:2", cell_size)),
+			Err(InvalidRecord::Block(300))
+		);
+	}
+}
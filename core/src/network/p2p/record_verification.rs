@@ -0,0 +1,303 @@
+//! Verification of the cell records received by DHT PUT, before they are stored.
+//!
+//! Cell proofs are verified against the commitments of the block header, taken from the finalized headers
+//! received since the start, or from the header store. Cells without a known header cannot be verified and are not stored.
+//! Peers are blocked once they send a number of the cells with invalid proofs or signatures within the counting window,
+//! counted together with the invalid records fetched from them.
+//! Cells are not counted as invalid if their proofs cannot be verified (e.g. when the verification is interrupted).
+//! Received records are queued in a bounded queue, and dropped when the verification cannot keep up.
+
+use super::{event_loop::DHTKey, signed_record, Client};
+use crate::{
+	data::{BlockHeaderKey, Database},
+	network::rpc::Event,
+	proof,
+	types::{BlockVerified, Extension},
+};
+use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
+use kate_recovery::{data::Cell, matrix::Position};
+use libp2p::{kad::Record, PeerId};
+use lru::LruCache;
+use std::{
+	collections::{HashMap, HashSet},
+	num::NonZeroUsize,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, trace, warn};
+
+const HEADERS_CAPACITY: NonZeroUsize = match NonZeroUsize::new(64) {
+	Some(capacity) => capacity,
+	None => panic!("Capacity must not be zero"),
+};
+
+/// Number of the invalid records after which the sending peer is blocked
+const MAX_INVALID_RECORDS: u32 = 3;
+
+/// Window in which the invalid records of the peer are counted, counting starts over after it
+const INVALID_RECORDS_WINDOW: Duration = Duration::from_secs(600);
+
+/// Maximum number of the received cell records waiting for the verification, further records are dropped
+pub const UNVERIFIED_RECORDS_CAPACITY: usize = 4096;
+
+/// Maximum number of the cell records verified together
+const BATCH_SIZE: usize = 256;
+
+/// Cell record received by DHT PUT, with the sending peer
+pub type UnverifiedRecord = (PeerId, Record);
+
+/// Number of the invalid records received from each peer, with the start of its counting window
+#[derive(Default)]
+pub(crate) struct InvalidRecords(HashMap<PeerId, (u32, Instant)>);
+
+impl InvalidRecords {
+	/// Counts the invalid record received from the peer, counts of the expired windows are dropped.
+	/// Returns the number of the invalid records once the peer should be blocked, and resets the count.
+	pub(crate) fn count(&mut self, peer_id: PeerId, now: Instant) -> Option<u32> {
+		self.0
+			.retain(|_, (_, started)| now.duration_since(*started) < INVALID_RECORDS_WINDOW);
+		let (count, _) = self.0.entry(peer_id).or_insert((0, now));
+		*count += 1;
+		if *count < MAX_INVALID_RECORDS {
+			return None;
+		}
+		self.0.remove(&peer_id).map(|(count, _)| count)
+	}
+}
+
+struct Headers<T: Database> {
+	db: T,
+	extensions: LruCache<u32, Option<Extension>>,
+}
+
+impl<T: Database> Headers<T> {
+	fn insert(&mut self, block: BlockVerified) {
+		self.extensions.put(block.block_num, block.extension);
+	}
+
+	fn extension(&mut self, block_num: u32) -> Option<Extension> {
+		if let Some(extension) = self.extensions.get(&block_num) {
+			return extension.clone();
+		}
+		let header = self.db.get(BlockHeaderKey(block_num))?;
+		let block = BlockVerified::try_from((header, None)).ok()?;
+		let extension = block.extension.clone();
+		self.insert(block);
+		extension
+	}
+}
+
+/// Cell record received by DHT PUT, with the opened cell
+struct ReceivedCell {
+	peer_id: PeerId,
+	record: Record,
+	cell: Cell,
+}
+
+/// Verifies the cell proofs of the block in batches, returns the valid and the invalid cells.
+/// Cells of the same position are verified in separate batches, since the results are reported by position.
+/// Batches which cannot be verified are dropped, without counting their cells as invalid.
+async fn verify(
+	extension: &Extension,
+	block_num: u32,
+	cells: Vec<ReceivedCell>,
+	public_parameters: Arc<PublicParameters>,
+) -> (Vec<ReceivedCell>, Vec<ReceivedCell>) {
+	let mut batches: Vec<Vec<ReceivedCell>> = vec![];
+	for received in cells {
+		let position = received.cell.position;
+		match batches
+			.iter_mut()
+			.find(|batch| batch.iter().all(|other| other.cell.position != position))
+		{
+			Some(batch) => batch.push(received),
+			None => batches.push(vec![received]),
+		}
+	}
+
+	let mut valid = vec![];
+	let mut invalid = vec![];
+	for batch in batches {
+		let cells = batch
+			.iter()
+			.map(|received| received.cell.clone())
+			.collect::<Vec<_>>();
+		let verified = match proof::verify(
+			block_num,
+			extension.dimensions,
+			&cells,
+			&extension.commitments,
+			public_parameters.clone(),
+		)
+		.await
+		{
+			Ok((verified, _)) => verified.into_iter().collect::<HashSet<_>>(),
+			Err(error) => {
+				debug!(block_num, "Cell proof verification failed: {error:#}");
+				continue;
+			},
+		};
+		let (batch_valid, batch_invalid): (Vec<_>, Vec<_>) = batch
+			.into_iter()
+			.partition(|received| verified.contains(&received.cell.position));
+		valid.extend(batch_valid);
+		invalid.extend(batch_invalid);
+	}
+	(valid, invalid)
+}
+
+/// Verifies the received cell records, stores the verified ones and blocks the peers sending the invalid ones.
+/// Records received while the previous ones are verified are verified together, in batches per block.
+pub async fn run(
+	client: Client,
+	db: impl Database,
+	public_parameters: Arc<PublicParameters>,
+	mut rpc_events: broadcast::Receiver<Event>,
+	mut records: mpsc::Receiver<UnverifiedRecord>,
+) {
+	info!("Starting DHT record verification...");
+	let mut headers = Headers {
+		db,
+		extensions: LruCache::new(HEADERS_CAPACITY),
+	};
+
+	loop {
+		let record = tokio::select! {
+			event = rpc_events.recv() => {
+				match event {
+					Ok(Event::HeaderUpdate { header, .. }) => match BlockVerified::try_from((header, None)) {
+						Ok(block) => headers.insert(block),
+						Err(error) => debug!("Header is not valid: {error}"),
+					},
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						warn!("DHT record verification skipped {skipped} headers");
+					},
+					Err(broadcast::error::RecvError::Closed) => return,
+				}
+				continue;
+			},
+			record = records.recv() => match record {
+				Some(record) => record,
+				None => return,
+			},
+		};
+
+		let mut batch = vec![record];
+		while batch.len() < BATCH_SIZE {
+			let Ok(record) = records.try_recv() else {
+				break;
+			};
+			batch.push(record);
+		}
+
+		let mut blocks: HashMap<u32, (Extension, Vec<ReceivedCell>)> = HashMap::new();
+		let mut invalid = vec![];
+		for (peer_id, record) in batch {
+			let Ok(DHTKey::Cell(block_num, row, col)) = DHTKey::try_from(record.key.clone()) else {
+				continue;
+			};
+			let Some(extension) = headers.extension(block_num) else {
+				trace!(
+					block_num,
+					"Cell record from {peer_id} cannot be verified without the block header"
+				);
+				continue;
+			};
+			let Ok(col) = u16::try_from(col) else {
+				continue;
+			};
+
+			let position = Position { row, col };
+			// Signatures are not required, but cells with invalid signatures are not stored
+			let content =
+				match signed_record::open(record.key.as_ref(), record.value.clone(), false) {
+					Ok(opened) => opened.value.as_slice().try_into().ok(),
+					Err(error) => {
+						debug!(block_num, "Cell record signature is not valid: {error:#}");
+						None
+					},
+				};
+			let Some(content) = content.filter(|_| (row as usize) < extension.commitments.len())
+			else {
+				invalid.push((peer_id, block_num, position));
+				continue;
+			};
+			blocks
+				.entry(block_num)
+				.or_insert_with(|| (extension, vec![]))
+				.1
+				.push(ReceivedCell {
+					peer_id,
+					record,
+					cell: Cell { position, content },
+				});
+		}
+
+		for (block_num, (extension, cells)) in blocks {
+			let (valid, rejected) =
+				verify(&extension, block_num, cells, public_parameters.clone()).await;
+			for ReceivedCell { record, .. } in valid {
+				if let Err(error) = client.store_record(record).await {
+					debug!("Verified cell record is not stored: {error:#}");
+				}
+			}
+			invalid.extend(
+				rejected
+					.into_iter()
+					.map(|ReceivedCell { peer_id, cell, .. }| (peer_id, block_num, cell.position)),
+			);
+		}
+
+		let mut invalid_senders = vec![];
+		for (peer_id, block_num, position) in invalid {
+			debug!(
+				block_num,
+				"Invalid cell record {position:?} received from {peer_id}"
+			);
+			invalid_senders.push(peer_id);
+		}
+		client.report_invalid_records(invalid_senders).await;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn invalid_records_count() {
+		let mut invalid_records = InvalidRecords::default();
+		let peer_id = PeerId::random();
+		let other_peer_id = PeerId::random();
+		let now = Instant::now();
+
+		assert_eq!(invalid_records.count(peer_id, now), None);
+		assert_eq!(invalid_records.count(other_peer_id, now), None);
+		assert_eq!(invalid_records.count(peer_id, now), None);
+		assert_eq!(
+			invalid_records.count(peer_id, now),
+			Some(MAX_INVALID_RECORDS)
+		);
+		// count is reset once the peer is blocked
+		assert_eq!(invalid_records.count(peer_id, now), None);
+	}
+
+	#[test]
+	fn invalid_records_window() {
+		let mut invalid_records = InvalidRecords::default();
+		let peer_id = PeerId::random();
+		let now = Instant::now();
+
+		assert_eq!(invalid_records.count(peer_id, now), None);
+		assert_eq!(invalid_records.count(peer_id, now), None);
+		// count starts over after the window
+		let now = now + INVALID_RECORDS_WINDOW;
+		assert_eq!(invalid_records.count(peer_id, now), None);
+		assert_eq!(invalid_records.count(peer_id, now), None);
+		assert_eq!(
+			invalid_records.count(peer_id, now),
+			Some(MAX_INVALID_RECORDS)
+		);
+	}
+}
@@ -0,0 +1,84 @@
+//! Kademlia routing table snapshots.
+//!
+//! Snapshot is stored on shutdown and loaded on startup, so restarted node can rejoin the DHT without rediscovering all the peers.
+
+use color_eyre::{eyre::WrapErr, Result};
+use libp2p::{kad, Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use super::Store;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingTableEntry {
+	pub peer_id: PeerId,
+	pub addresses: Vec<Multiaddr>,
+}
+
+/// Collects peers and their addresses from the Kademlia k-buckets
+pub fn export(kademlia: &mut kad::Behaviour<Store>) -> Vec<RoutingTableEntry> {
+	kademlia
+		.kbuckets()
+		.flat_map(|bucket| {
+			bucket
+				.iter()
+				.map(|entry| RoutingTableEntry {
+					peer_id: *entry.node.key.preimage(),
+					addresses: entry.node.value.iter().cloned().collect(),
+				})
+				.collect::<Vec<_>>()
+		})
+		.collect()
+}
+
+/// Adds peers to the Kademlia routing table, returns number of added peers
+pub fn import(kademlia: &mut kad::Behaviour<Store>, entries: Vec<RoutingTableEntry>) -> usize {
+	let mut imported = 0;
+	for RoutingTableEntry { peer_id, addresses } in entries {
+		let mut is_added = false;
+		for address in addresses {
+			if let kad::RoutingUpdate::Success = kademlia.add_address(&peer_id, address) {
+				is_added = true;
+			}
+		}
+		if is_added {
+			imported += 1;
+		}
+	}
+	imported
+}
+
+pub fn save(path: &str, entries: &[RoutingTableEntry]) -> Result<()> {
+	let snapshot = serde_json::to_vec(entries).wrap_err("error serializing routing table")?;
+	fs::write(path, snapshot).wrap_err("error writing routing table snapshot")
+}
+
+/// Loads routing table snapshot, missing snapshot results with empty routing table
+pub fn load(path: &str) -> Result<Vec<RoutingTableEntry>> {
+	if !Path::new(path).exists() {
+		return Ok(vec![]);
+	}
+	let snapshot = fs::read(path).wrap_err("error reading routing table snapshot")?;
+	serde_json::from_slice(&snapshot).wrap_err("error deserializing routing table")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_save_and_load() {
+		let path = std::env::temp_dir().join("avail_light_routing_table_test.json");
+		let path = path.to_str().unwrap();
+		let entries = vec![RoutingTableEntry {
+			peer_id: PeerId::random(),
+			addresses: vec!["/ip4/159.73.143.3/tcp/37000".parse().unwrap()],
+		}];
+
+		save(path, &entries).unwrap();
+		assert_eq!(load(path).unwrap(), entries);
+
+		fs::remove_file(path).unwrap();
+		assert!(load(path).unwrap().is_empty());
+	}
+}
@@ -0,0 +1,128 @@
+//! Security upgrade of the transport connections, offering Noise, TLS 1.3 or both.
+//!
+//! When both protocols are offered, the dialer proposes Noise first, so TLS is used only with the peers which don't support Noise.
+
+use futures::{
+	future::{BoxFuture, Either},
+	AsyncRead, AsyncWrite, FutureExt,
+};
+use libp2p::{
+	core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo},
+	identity, noise, tls, PeerId,
+};
+use std::io;
+
+use crate::types::TransportSecurity;
+
+type SecureOutput<C> = (PeerId, Either<noise::Output<C>, tls::TlsStream<C>>);
+
+pub struct SecurityUpgrade {
+	noise: Option<noise::Config>,
+	tls: Option<tls::Config>,
+}
+
+impl SecurityUpgrade {
+	pub fn new(
+		key: &identity::Keypair,
+		security: TransportSecurity,
+	) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+		let (is_noise, is_tls) = match security {
+			TransportSecurity::Noise => (true, false),
+			TransportSecurity::Tls => (false, true),
+			TransportSecurity::NoiseAndTls => (true, true),
+		};
+		Ok(Self {
+			noise: is_noise.then(|| noise::Config::new(key)).transpose()?,
+			tls: is_tls.then(|| tls::Config::new(key)).transpose()?,
+		})
+	}
+}
+
+fn is_protocol<U: UpgradeInfo<Info = &'static str>>(upgrade: &U, info: &str) -> bool {
+	upgrade
+		.protocol_info()
+		.into_iter()
+		.any(|protocol| protocol == info)
+}
+
+fn unsupported(info: &str) -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidInput,
+		format!("Unsupported security protocol {info}"),
+	)
+}
+
+impl UpgradeInfo for SecurityUpgrade {
+	type Info = &'static str;
+	type InfoIter = Vec<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		let noise = self.noise.iter().flat_map(UpgradeInfo::protocol_info);
+		let tls = self.tls.iter().flat_map(UpgradeInfo::protocol_info);
+		noise.chain(tls).collect()
+	}
+}
+
+impl<C> InboundConnectionUpgrade<C> for SecurityUpgrade
+where
+	C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+	type Output = SecureOutput<C>;
+	type Error = io::Error;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_inbound(self, socket: C, info: Self::Info) -> Self::Future {
+		async move {
+			match (self.noise, self.tls) {
+				(Some(noise), _) if is_protocol(&noise, info) => {
+					let (peer_id, output) = noise
+						.upgrade_inbound(socket, info)
+						.await
+						.map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+					Ok((peer_id, Either::Left(output)))
+				},
+				(_, Some(tls)) if is_protocol(&tls, info) => {
+					let (peer_id, output) = tls
+						.upgrade_inbound(socket, info)
+						.await
+						.map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+					Ok((peer_id, Either::Right(output)))
+				},
+				_ => Err(unsupported(info)),
+			}
+		}
+		.boxed()
+	}
+}
+
+impl<C> OutboundConnectionUpgrade<C> for SecurityUpgrade
+where
+	C: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+	type Output = SecureOutput<C>;
+	type Error = io::Error;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_outbound(self, socket: C, info: Self::Info) -> Self::Future {
+		async move {
+			match (self.noise, self.tls) {
+				(Some(noise), _) if is_protocol(&noise, info) => {
+					let (peer_id, output) = noise
+						.upgrade_outbound(socket, info)
+						.await
+						.map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+					Ok((peer_id, Either::Left(output)))
+				},
+				(_, Some(tls)) if is_protocol(&tls, info) => {
+					let (peer_id, output) = tls
+						.upgrade_outbound(socket, info)
+						.await
+						.map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+					Ok((peer_id, Either::Right(output)))
+				},
+				_ => Err(unsupported(info)),
+			}
+		}
+		.boxed()
+	}
+}
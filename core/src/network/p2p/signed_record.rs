@@ -0,0 +1,176 @@
+//! Signed envelope of the DHT record values, which authenticates the record publisher.
+//!
+//! Envelope holds the public key of the publisher, the record issue time and the publisher signature
+//! over the record key, value and issue time, so the fetched records can be traced back to their publishers.
+//! Expiration is not signed, since the records are republished and cached with the extended expiration,
+//! it is governed by the record expiration time instead.
+//! Values without the envelope are plain record values, published by the nodes which are not signing the records.
+
+use codec::{Decode, DecodeAll, Encode};
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Result,
+};
+use libp2p::{
+	identity::{Keypair, PublicKey},
+	PeerId,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prefix which distinguishes the signed values from the plain ones
+const MAGIC: &[u8; 4] = b"avsr";
+
+/// Tolerated clock difference between the publisher and the local node (in seconds)
+const MAX_CLOCK_SKEW: u64 = 300;
+
+#[derive(Encode, Decode)]
+struct Envelope {
+	/// Protobuf encoded public key of the publisher
+	public_key: Vec<u8>,
+	/// Record issue time, in seconds since the Unix epoch
+	issued_at: u64,
+	value: Vec<u8>,
+	signature: Vec<u8>,
+}
+
+/// Record value with the publisher which signed it
+#[derive(Debug, PartialEq)]
+pub struct OpenedValue {
+	pub value: Vec<u8>,
+	/// Publisher of the signed value, `None` if the value is not signed
+	pub publisher: Option<PeerId>,
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs())
+		.unwrap_or_default()
+}
+
+fn signed_message(key: &[u8], value: &[u8], issued_at: u64) -> Vec<u8> {
+	(key, value, issued_at).encode()
+}
+
+fn decode(value: &[u8]) -> Option<Envelope> {
+	let mut encoded = value.strip_prefix(MAGIC.as_slice())?;
+	Envelope::decode_all(&mut encoded).ok()
+}
+
+/// Wraps the record value into the envelope signed by the publisher
+pub fn sign(keypair: &Keypair, key: &[u8], value: Vec<u8>) -> Result<Vec<u8>> {
+	sign_at(keypair, key, value, now())
+}
+
+fn sign_at(keypair: &Keypair, key: &[u8], value: Vec<u8>, issued_at: u64) -> Result<Vec<u8>> {
+	let signature = keypair
+		.sign(&signed_message(key, &value, issued_at))
+		.wrap_err("error signing record")?;
+	let envelope = Envelope {
+		public_key: keypair.public().encode_protobuf(),
+		issued_at,
+		value,
+		signature,
+	};
+	Ok([MAGIC.as_slice(), &envelope.encode()].concat())
+}
+
+/// Returns the record value without the envelope, signature is not verified
+pub fn unwrap_value(value: &[u8]) -> Vec<u8> {
+	match decode(value) {
+		Some(envelope) => envelope.value,
+		None => value.to_vec(),
+	}
+}
+
+/// Verifies the signature and issue time of the signed record value, and returns the value with its publisher.
+/// Values which are not signed are returned as they are, unless signatures are required.
+pub fn open(key: &[u8], value: Vec<u8>, is_signature_required: bool) -> Result<OpenedValue> {
+	let Some(envelope) = decode(&value) else {
+		if is_signature_required {
+			return Err(eyre!("Record is not signed"));
+		}
+		return Ok(OpenedValue {
+			value,
+			publisher: None,
+		});
+	};
+
+	let public_key = PublicKey::try_decode_protobuf(&envelope.public_key)
+		.wrap_err("Invalid publisher public key")?;
+	let publisher = public_key.to_peer_id();
+	let message = signed_message(key, &envelope.value, envelope.issued_at);
+	if !public_key.verify(&message, &envelope.signature) {
+		return Err(eyre!("Invalid record signature of publisher {publisher}"));
+	}
+	if envelope.issued_at > now().saturating_add(MAX_CLOCK_SKEW) {
+		return Err(eyre!(
+			"Record signed by {publisher} is issued in the future"
+		));
+	}
+
+	Ok(OpenedValue {
+		value: envelope.value,
+		publisher: Some(publisher),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn signed_values() {
+		let keypair = Keypair::generate_ed25519();
+		let publisher = keypair.public().to_peer_id();
+		let key = b"avail/100:1:2";
+		let value = vec![7; 80];
+
+		let signed = sign(&keypair, key, value.clone()).unwrap();
+		assert_eq!(unwrap_value(&signed), value);
+		assert_eq!(
+			open(key, signed.clone(), true).unwrap(),
+			OpenedValue {
+				value: value.clone(),
+				publisher: Some(publisher),
+			}
+		);
+
+		// Signature does not cover a different key
+		assert!(open(b"avail/100:1:3", signed.clone(), false).is_err());
+
+		// Tampered value is rejected
+		let mut tampered = signed.clone();
+		let last = tampered.len() - 70;
+		tampered[last] ^= 1;
+		assert!(open(key, tampered, false).is_err());
+
+		// Plain values are accepted only if signatures are not required
+		assert_eq!(unwrap_value(&value), value);
+		assert_eq!(
+			open(key, value.clone(), false).unwrap(),
+			OpenedValue {
+				value: value.clone(),
+				publisher: None,
+			}
+		);
+		assert!(open(key, value, true).is_err());
+	}
+
+	#[test]
+	fn issue_time() {
+		let keypair = Keypair::generate_ed25519();
+		let key = b"avail/100:1:2";
+		let value = vec![7; 80];
+
+		// Republished records outlive the original time to live
+		let issued_long_ago = sign_at(&keypair, key, value.clone(), now() - 30 * 86400).unwrap();
+		assert!(open(key, issued_long_ago, true).is_ok());
+
+		let skewed = sign_at(&keypair, key, value.clone(), now() + MAX_CLOCK_SKEW / 2).unwrap();
+		assert!(open(key, skewed, true).is_ok());
+
+		let issued_in_future = sign_at(&keypair, key, value, now() + 2 * MAX_CLOCK_SKEW).unwrap();
+		assert!(open(key, issued_in_future, true).is_err());
+	}
+}
@@ -5,7 +5,12 @@ use kate_recovery::matrix::{Dimensions, Position};
 use rand::{seq::SliceRandom, thread_rng, Rng};
 use serde::{de, Deserialize, Serialize};
 use sp_core::bytes::from_hex;
-use std::{collections::HashSet, fmt::Display};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::Display,
+	sync::{Arc, RwLock},
+	time::Duration,
+};
 use tokio::{
 	sync::broadcast,
 	time::{self, timeout},
@@ -16,10 +21,12 @@ use crate::{
 	data::Database,
 	network::rpc,
 	shutdown::Controller,
-	types::{GrandpaJustification, RetryConfig},
+	types::{GrandpaJustification, RetryConfig, RpcRateLimitConfig},
+	utils::spawn_in_span,
 };
 
 mod client;
+mod kate_requests;
 mod subscriptions;
 
 use subscriptions::SubscriptionLoop;
@@ -120,27 +127,90 @@ impl Display for Node {
 	}
 }
 
+/// Health of a Node, measured by the periodic health check.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeHealth {
+	/// Round trip time of the health RPC call
+	pub latency: Duration,
+	/// Whether the Node is still syncing the chain
+	pub is_syncing: bool,
+	/// Number of peers connected to the Node
+	pub peers: usize,
+}
+
+impl NodeHealth {
+	/// Ranks Nodes that are in sync before the syncing ones, and faster before slower ones.
+	fn rank(&self) -> (bool, Duration) {
+		(self.is_syncing, self.latency)
+	}
+}
+
 #[derive(Clone)]
 pub struct Nodes {
-	list: Vec<Node>,
+	list: Arc<RwLock<Vec<Node>>>,
+	health: Arc<RwLock<HashMap<String, NodeHealth>>>,
 }
 
 impl Nodes {
+	fn candidates(hosts: &[String]) -> Vec<Node> {
+		hosts
+			.iter()
+			.map(|s| Node {
+				genesis_hash: Default::default(),
+				spec_version: Default::default(),
+				system_version: Default::default(),
+				host: s.to_string(),
+			})
+			.collect()
+	}
+
 	pub fn new(nodes: &[String]) -> Self {
-		let candidates = nodes.to_owned();
 		Self {
-			list: candidates
-				.iter()
-				.map(|s| Node {
-					genesis_hash: Default::default(),
-					spec_version: Default::default(),
-					system_version: Default::default(),
-					host: s.to_string(),
-				})
-				.collect(),
+			list: Arc::new(RwLock::new(Self::candidates(nodes))),
+			health: Default::default(),
 		}
 	}
 
+	/// Replaces the list of available Nodes, keeping the health of the Nodes which remain on the list.
+	pub fn set_hosts(&self, hosts: &[String]) {
+		*self.list.write().expect("Lock is not poisoned") = Self::candidates(hosts);
+		let mut health_scores = self.health.write().expect("Lock is not poisoned");
+		health_scores.retain(|host, _| hosts.contains(host));
+	}
+
+	/// Returns the list of available Nodes.
+	pub fn list(&self) -> Vec<Node> {
+		self.list.read().expect("Lock is not poisoned").clone()
+	}
+
+	/// Records the result of the Node health check, `None` marks the Node as unreachable.
+	pub fn set_health(&self, host: &str, health: Option<NodeHealth>) {
+		let mut health_scores = self.health.write().expect("Lock is not poisoned");
+		match health {
+			Some(health) => health_scores.insert(host.to_string(), health),
+			None => health_scores.remove(host),
+		};
+	}
+
+	/// Returns the last measured health of the Node, if it was reachable.
+	pub fn health(&self, host: &str) -> Option<NodeHealth> {
+		let health_scores = self.health.read().expect("Lock is not poisoned");
+		health_scores.get(host).cloned()
+	}
+
+	/// Orders the available Nodes by their health, excluding the current host.
+	///
+	/// Healthy Nodes come first, ordered by latency, followed by syncing Nodes,
+	/// while the Nodes which were not checked or were unreachable are left in random order at the end.
+	fn ranked(&self, current_host: String) -> Vec<Node> {
+		let mut list = self.shuffle(current_host);
+		list.sort_by_key(|Node { host, .. }| match self.health(host) {
+			Some(health) => (false, health.rank()),
+			None => (true, (true, Duration::MAX)),
+		});
+		list
+	}
+
 	/// Shuffles the list of available Nodes, excluding the host used for the current Subxt client creation.
 	///
 	/// This method returns a new shuffled list of Nodes from the original list, excluding the Node
@@ -148,48 +218,29 @@ impl Nodes {
 	/// The purpose of this exclusion is to prevent accidentally reconnecting to the same host in case of errors.
 	/// If there's a need to switch to a different host, the shuffled list provides a randomized order of available Nodes.
 	fn shuffle(&self, current_host: String) -> Vec<Node> {
-		if self.list.len() <= 1 {
-			return self.list.clone();
+		let list = self.list();
+		if list.len() <= 1 {
+			return list;
 		}
 
-		let mut list = self
-			.list
-			.iter()
-			.filter(|&Node { host, .. }| host != &current_host)
-			.cloned()
+		let mut list = list
+			.into_iter()
+			.filter(|Node { host, .. }| host != &current_host)
 			.collect::<Vec<Node>>();
 		list.shuffle(&mut thread_rng());
 		list
 	}
-
-	pub fn iter(&self) -> NodesIterator {
-		NodesIterator {
-			nodes: self,
-			current_index: 0,
-		}
-	}
-}
-
-pub struct NodesIterator<'a> {
-	nodes: &'a Nodes,
-	current_index: usize,
-}
-
-impl<'a> Iterator for NodesIterator<'a> {
-	type Item = &'a Node;
-
-	fn next(&mut self) -> Option<Self::Item> {
-		let res = self.nodes.list.get(self.current_index);
-		self.current_index += 1;
-		res
-	}
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn init<T: Database + Clone>(
 	db: T,
 	nodes: &[String],
 	genesis_hash: &str,
 	retry_config: RetryConfig,
+	health_check_interval: Duration,
+	cross_check: bool,
+	rate_limit: Option<RpcRateLimitConfig>,
 	shutdown: Controller<String>,
 ) -> Result<(Client<T>, broadcast::Sender<Event>, SubscriptionLoop<T>)> {
 	let rpc_client = Client::new(
@@ -197,9 +248,15 @@ pub async fn init<T: Database + Clone>(
 		Nodes::new(nodes),
 		genesis_hash,
 		retry_config,
-		shutdown,
+		cross_check,
+		rate_limit,
+		shutdown.clone(),
 	)
 	.await?;
+	// periodically check the health of the configured Nodes and switch away from unhealthy ones
+	spawn_in_span(
+		shutdown.with_cancel(rpc_client.clone().run_health_checks(health_check_interval)),
+	);
 	// create output channel for RPC Subscription Events
 	let (event_sender, _) = broadcast::channel(1000);
 	let subscriptions = SubscriptionLoop::new(db, rpc_client.clone(), event_sender.clone()).await?;
@@ -272,3 +329,46 @@ pub async fn wait_for_finalized_header(
 		Err(_) => Err(eyre!("Timeout on waiting for first finalized header")),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ranked_nodes_by_health() {
+		let nodes = Nodes::new(&[
+			"ws://a".to_string(),
+			"ws://b".to_string(),
+			"ws://c".to_string(),
+			"ws://d".to_string(),
+			"ws://e".to_string(),
+		]);
+		let health = |latency, is_syncing| NodeHealth {
+			latency: Duration::from_millis(latency),
+			is_syncing,
+			peers: 10,
+		};
+		nodes.set_health("ws://a", Some(health(300, false)));
+		nodes.set_health("ws://b", Some(health(10, true)));
+		nodes.set_health("ws://c", Some(health(100, false)));
+		nodes.set_health("ws://d", Some(health(50, false)));
+		nodes.set_health("ws://d", None);
+
+		let hosts = nodes
+			.ranked("ws://e".to_string())
+			.into_iter()
+			.map(|node| node.host)
+			.collect::<Vec<_>>();
+		assert_eq!(hosts, vec!["ws://c", "ws://a", "ws://b", "ws://d"]);
+
+		nodes.set_hosts(&["ws://a".to_string(), "ws://f".to_string()]);
+		assert!(nodes.health("ws://c").is_none());
+
+		let hosts = nodes
+			.ranked("ws://e".to_string())
+			.into_iter()
+			.map(|node| node.host)
+			.collect::<Vec<_>>();
+		assert_eq!(hosts, vec!["ws://a", "ws://f"]);
+	}
+}
@@ -9,14 +9,20 @@ use avail_subxt::{
 	utils::H256,
 	AvailClient, AvailConfig,
 };
+use codec::Encode;
 use color_eyre::{
 	eyre::{eyre, Context},
 	Report, Result,
 };
-use futures::{Stream, TryFutureExt, TryStreamExt};
+use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
+use futures::{Future, Stream, TryFutureExt, TryStreamExt};
 use kate_recovery::{data::Cell, matrix::Position};
-use sp_core::{bytes::from_hex, ed25519::Public, U256};
-use std::sync::Arc;
+use sp_core::{blake2_256, bytes::from_hex, ed25519::Public, U256};
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 use subxt::{
 	backend::{
 		legacy::rpc_methods::{BlockNumber, StorageKey},
@@ -27,20 +33,105 @@ use subxt::{
 	utils::AccountId32,
 };
 use subxt_signer::sr25519::Keypair;
-use tokio::sync::RwLock;
+use tokio::{
+	sync::{Notify, RwLock},
+	time::{self, timeout},
+};
 use tokio_retry::Retry;
 use tokio_stream::StreamExt;
-use tracing::{info, warn};
+use tracing::{debug, error, info, warn};
 
-use super::{Node, Nodes, Subscription, WrappedProof};
+use super::{kate_requests::KateRequests, Node, NodeHealth, Nodes, Subscription, WrappedProof};
 use crate::{
 	api::v2::types::Base64,
 	consts::ExpectedNodeVariant,
-	data::{Database, RpcNodeKey},
+	data::{BlockHashKey, BlockHeaderKey, Database, RpcNodeKey},
+	ledger::{self, NodeEvent},
+	proof::{self, BlockCommitments},
 	shutdown::Controller,
-	types::{RetryConfig, DEV_FLAG_GENHASH},
+	types::{RetryConfig, RpcRateLimitConfig, DEV_FLAG_GENHASH},
 };
 
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn concat_content(scalar: U256, proof: GProof) -> Result<[u8; 80]> {
+	let proof: Vec<u8> = proof.into();
+	if proof.len() != 48 {
+		return Err(eyre!("Invalid proof length"));
+	}
+
+	let mut result = [0u8; 80];
+	scalar.to_big_endian(&mut result[48..]);
+	result[..48].copy_from_slice(&proof);
+	Ok(result)
+}
+
+async fn query_proof(
+	client: Arc<AvailClient>,
+	block_hash: H256,
+	positions: Vec<Position>,
+) -> Result<Vec<Cell>> {
+	let cells: Cells = positions
+		.iter()
+		.map(|p| avail_subxt::Cell {
+			row: p.row,
+			col: p.col as u32,
+		})
+		.collect::<Vec<_>>()
+		.try_into()
+		.map_err(|_| eyre!("Failed to convert to cells"))?;
+
+	let proofs: Vec<(GRawScalar, GProof)> = client
+		.rpc_methods()
+		.query_proof(cells, block_hash)
+		.await
+		.map_err(|error| eyre!("{error}"))?;
+
+	positions
+		.into_iter()
+		.zip(proofs)
+		.map(|(position, (scalar, proof))| {
+			let content = concat_content(scalar, proof)?;
+			Ok(Cell { position, content })
+		})
+		.collect()
+}
+
+/// Resolves the block hash cross-checked with the secondary Node.
+/// On divergence, the hash stored for the block on its processing is preferred, if it matches any of the responses.
+/// Stored hash is not finality verified, but it is the hash the client already followed.
+/// Block is not cross-checked if the secondary Node cannot be queried, so an error is returned.
+fn resolve_block_hash(
+	block_number: u32,
+	hash: H256,
+	secondary: Result<(String, Option<H256>)>,
+	stored_hash: impl FnOnce() -> Option<H256>,
+) -> Result<H256> {
+	let (secondary_host, secondary_hash) =
+		secondary.wrap_err(format!("Cannot cross-check hash of block {block_number}"))?;
+
+	if secondary_hash == Some(hash) {
+		return Ok(hash);
+	}
+
+	error!(
+		block_number,
+		?hash,
+		?secondary_hash,
+		secondary_host,
+		"RPC responses diverge for the block hash"
+	);
+
+	match stored_hash() {
+		Some(stored_hash) if stored_hash == hash || Some(stored_hash) == secondary_hash => {
+			Ok(stored_hash)
+		},
+		_ => Err(eyre!(
+			"Divergent block hash for block {block_number}, without matching stored hash"
+		)),
+	}
+}
+
 #[derive(Clone)]
 pub struct Client<T: Database> {
 	subxt_client: Arc<RwLock<Arc<AvailClient>>>,
@@ -49,6 +140,13 @@ pub struct Client<T: Database> {
 	retry_config: RetryConfig,
 	expected_genesis_hash: String,
 	shutdown: Controller<String>,
+	node_switched: Arc<Notify>,
+	/// Public parameters for verifying the divergent cells, set if the responses are cross-checked
+	cross_check: Option<Arc<PublicParameters>>,
+	secondary_client: Arc<RwLock<Option<(String, Arc<AvailClient>)>>>,
+	/// Clients of the not connected Nodes, reused between the health checks
+	health_clients: Arc<RwLock<HashMap<String, Arc<AvailClient>>>>,
+	kate_requests: KateRequests,
 }
 
 impl<D: Database> Client<D> {
@@ -57,8 +155,14 @@ impl<D: Database> Client<D> {
 		nodes: Nodes,
 		expected_genesis_hash: &str,
 		retry_config: RetryConfig,
+		cross_check: bool,
+		rate_limit: Option<RpcRateLimitConfig>,
 		shutdown: Controller<String>,
 	) -> Result<Self> {
+		if cross_check && nodes.list().len() < 2 {
+			warn!("Cross-checking of RPC responses requires at least two full node endpoints");
+		}
+
 		// try and connect appropriate Node from the provided list
 		// will do retries with the provided Retry Config
 		let (client, node, _) = match shutdown
@@ -92,6 +196,11 @@ impl<D: Database> Client<D> {
 			retry_config,
 			expected_genesis_hash: expected_genesis_hash.to_string(),
 			shutdown,
+			node_switched: Arc::new(Notify::new()),
+			cross_check: cross_check.then(|| Arc::new(kate_recovery::couscous::public_params())),
+			secondary_client: Default::default(),
+			health_clients: Default::default(),
+			kate_requests: KateRequests::new(rate_limit),
 		})
 	}
 
@@ -212,8 +321,8 @@ impl<D: Database> Client<D> {
 				connected_node.host
 			);
 
-			// shuffle nodes, if possible
-			let nodes = self.nodes.shuffle(connected_node.host);
+			// order nodes by their health, trying the healthiest ones first
+			let nodes = self.nodes.ranked(connected_node.host);
 			// go through available Nodes, try to connect, Retry connecting if needed
 			let (client, node, result) = match self
 				.shutdown
@@ -237,10 +346,8 @@ impl<D: Database> Client<D> {
 				},
 			};
 
-			// retries gave results,
-			// update db with currently connected Node and keep a reference to the created Client
-			*self.subxt_client.write().await = client;
-			self.db.put(RpcNodeKey, node);
+			// retries gave results, switch to the newly connected Node
+			self.switch_node(client, node).await;
 
 			return Ok(result);
 		}
@@ -250,6 +357,180 @@ impl<D: Database> Client<D> {
 		))
 	}
 
+	/// Updates db with currently connected Node and keeps a reference to the created Client.
+	async fn switch_node(&self, client: Arc<AvailClient>, node: Node) {
+		*self.subxt_client.write().await = client;
+		let from = self.db.get(RpcNodeKey).map(|node| node.host);
+		// reconnects to the same node are not failovers
+		if from.as_ref() != Some(&node.host) {
+			ledger::record(
+				&self.db,
+				NodeEvent::RpcFailover {
+					from,
+					to: node.host.clone(),
+				},
+			);
+		}
+		self.db.put(RpcNodeKey, node);
+		// subscriptions are bound to the previous client, notify the stream to recreate them,
+		// permit is stored if the stream is not waiting for the notification at the moment
+		self.node_switched.notify_one();
+	}
+
+	async fn measure_health(client: &AvailClient) -> Result<NodeHealth> {
+		let start = Instant::now();
+		let health = client.legacy_rpc().system_health().await?;
+
+		Ok(NodeHealth {
+			latency: start.elapsed(),
+			is_syncing: health.is_syncing,
+			peers: health.peers,
+		})
+	}
+
+	/// Replaces the list of Nodes, switching to one of the new Nodes if the connected one is not on the list.
+	pub async fn set_nodes(&self, hosts: &[String]) -> Result<()> {
+		if hosts.is_empty() {
+			return Err(eyre!("List of the Nodes must not be empty"));
+		}
+		self.nodes.set_hosts(hosts);
+		*self.secondary_client.write().await = None;
+		self.health_clients
+			.write()
+			.await
+			.retain(|host, _| hosts.contains(host));
+
+		let current_host = self.db.get(RpcNodeKey).map(|node| node.host);
+		if current_host
+			.as_ref()
+			.is_some_and(|host| hosts.contains(host))
+		{
+			return Ok(());
+		}
+
+		info!("Connected Node is removed from the list, switching to another one");
+		let (client, node, _) = Self::try_connect_and_execute(
+			self.nodes.ranked(current_host.unwrap_or_default()),
+			ExpectedNodeVariant::default(),
+			&self.expected_genesis_hash,
+			|_| futures::future::ok(()),
+		)
+		.await?;
+		self.switch_node(client, node).await;
+
+		Ok(())
+	}
+
+	/// Checks the health of the currently connected Node.
+	pub async fn current_health(&self) -> Result<NodeHealth> {
+		Self::measure_health(&self.current_client().await).await
+	}
+
+	async fn node_health(&self, host: &str, current_host: Option<&str>) -> Result<NodeHealth> {
+		if current_host == Some(host) {
+			return Self::measure_health(&self.current_client().await).await;
+		}
+		let cached = self.health_clients.read().await.get(host).cloned();
+		let client = match cached {
+			Some(client) => client,
+			None => {
+				// connecting checks the genesis hash and system version of the Node
+				let (client, _) = Self::create_subxt_client(
+					host,
+					ExpectedNodeVariant::default(),
+					&self.expected_genesis_hash,
+				)
+				.await?;
+				let client = Arc::new(client);
+				self.health_clients
+					.write()
+					.await
+					.insert(host.to_string(), client.clone());
+				client
+			},
+		};
+		Self::measure_health(&client).await
+	}
+
+	/// Checks the health of all configured Nodes and switches to the healthiest one,
+	/// if the currently connected Node is unreachable or syncing.
+	async fn check_health(&self) -> Result<()> {
+		let current_host = self.db.get(RpcNodeKey).map(|node| node.host);
+
+		for Node { host, .. } in self.nodes.list() {
+			let health = timeout(
+				HEALTH_CHECK_TIMEOUT,
+				self.node_health(&host, current_host.as_deref()),
+			)
+			.await
+			.unwrap_or_else(|_| Err(eyre!("Health check timed out")));
+
+			match health {
+				Ok(health) => {
+					debug!(host, ?health, "Node health checked");
+					self.nodes.set_health(&host, Some(health));
+				},
+				Err(error) => {
+					warn!(host, %error, "Node health check failed");
+					self.nodes.set_health(&host, None);
+					// connection is recreated on the next health check
+					self.health_clients.write().await.remove(&host);
+				},
+			}
+		}
+
+		let Some(current_host) = current_host else {
+			return Ok(());
+		};
+
+		let is_healthy = |host: &str| self.nodes.health(host).is_some_and(|h| !h.is_syncing);
+		if is_healthy(&current_host) {
+			return Ok(());
+		}
+
+		let nodes = self.nodes.ranked(current_host.clone());
+		if !nodes.first().is_some_and(|node| is_healthy(&node.host)) {
+			debug!(
+				current_host,
+				"No healthier Node available, keeping the connection"
+			);
+			return Ok(());
+		}
+
+		warn!(
+			current_host,
+			"Connected Node is unhealthy, switching to another one"
+		);
+		let (client, node, _) = Self::try_connect_and_execute(
+			nodes,
+			ExpectedNodeVariant::default(),
+			&self.expected_genesis_hash,
+			|_| futures::future::ok(()),
+		)
+		.await?;
+		self.switch_node(client, node).await;
+
+		Ok(())
+	}
+
+	/// Periodically checks the health of the configured Nodes, failing over from the unhealthy ones.
+	pub async fn run_health_checks(self, interval: Duration) {
+		if interval.is_zero() {
+			info!("RPC health check interval is 0, health checks are disabled");
+			return;
+		}
+		let mut interval = time::interval(interval);
+		// first tick completes immediately, while the client has just connected
+		interval.tick().await;
+
+		loop {
+			interval.tick().await;
+			if let Err(error) = self.check_health().await {
+				warn!(%error, "Failed to switch to a healthy Node");
+			}
+		}
+	}
+
 	async fn create_subxt_subscriptions(
 		client: Arc<AvailClient>,
 	) -> Result<impl Stream<Item = Result<Subscription, subxt::error::Error>>> {
@@ -273,8 +554,18 @@ impl<D: Database> Client<D> {
 	}
 
 	pub async fn subscription_stream(self) -> impl Stream<Item = Result<Subscription>> {
+		// delays between reconnects, while the connection keeps dropping
+		let backoff = self.retry_config.clone().into_iter().collect::<Vec<_>>();
 		async_stream::stream! {
+			let mut reconnects = 0;
 			'outer: loop{
+				if reconnects > 0 {
+					if let Some(delay) = backoff.get(reconnects - 1).or(backoff.last()) {
+						info!("Reconnecting Subscriptions Stream in {delay:?}");
+						time::sleep(*delay).await;
+					}
+				}
+
 				let mut stream = match self.with_retries(|client| async move{
 					Self::create_subxt_subscriptions(client)
 						.await
@@ -287,16 +578,29 @@ impl<D: Database> Client<D> {
 				};
 
 				loop {
+					let result = tokio::select! {
+						result = stream.next() => result,
+						_ = self.node_switched.notified() => {
+							info!("Switched to another RPC Node. Recreating Subscriptions Stream.");
+							reconnects = 0;
+							continue 'outer
+						},
+					};
 					// no more subscriptions left on stream, we have to try and create a new stream
-					let Some(result) = stream.next().await else {
+					let Some(result) = result else {
 						warn!("No more items on Subscriptions Stream. Trying to create a new one.");
+						reconnects += 1;
 						continue 'outer
 					};
 					match result {
-						Ok(item) => yield Ok(item),
+						Ok(item) => {
+							reconnects = 0;
+							yield Ok(item)
+						},
 						// if Error was received, we need to switch to another RPC Client
 						Err(err)=> {
 							warn!(%err, "Received Error on stream. Trying to create a new one.");
+							reconnects += 1;
 							continue 'outer
 						}
 					}
@@ -309,6 +613,186 @@ impl<D: Database> Client<D> {
 		self.subxt_client.read().await.clone()
 	}
 
+	/// Returns client connected to a Node other than the current one, used to cross-check the responses.
+	async fn secondary_client(&self) -> Result<(String, Arc<AvailClient>)> {
+		let current_host = self
+			.db
+			.get(RpcNodeKey)
+			.map(|node| node.host)
+			.unwrap_or_default();
+
+		if let Some((host, client)) = self.secondary_client.read().await.clone() {
+			if host != current_host {
+				return Ok((host, client));
+			}
+		}
+
+		let nodes = self
+			.nodes
+			.ranked(current_host.clone())
+			.into_iter()
+			.filter(|node| node.host != current_host)
+			.collect::<Vec<_>>();
+
+		let (client, node, _) = Self::try_connect_and_execute(
+			nodes,
+			ExpectedNodeVariant::default(),
+			&self.expected_genesis_hash,
+			|_| futures::future::ok(()),
+		)
+		.await
+		.wrap_err("Cannot connect to the secondary Node")?;
+
+		*self.secondary_client.write().await = Some((node.host.clone(), client.clone()));
+		Ok((node.host, client))
+	}
+
+	/// Executes the query on the secondary Node, returning the Node host with the result.
+	/// Cached secondary client is dropped if the query fails, so the next query connects to another Node.
+	async fn query_secondary<T, Fut>(
+		&self,
+		query: impl FnOnce(Arc<AvailClient>) -> Fut,
+	) -> Result<(String, T)>
+	where
+		Fut: Future<Output = Result<T>>,
+	{
+		let (host, client) = self.secondary_client().await?;
+		match query(client).await {
+			Ok(result) => Ok((host, result)),
+			Err(error) => {
+				*self.secondary_client.write().await = None;
+				Err(error.wrap_err(format!("Query to the secondary Node {host} failed")))
+			},
+		}
+	}
+
+	/// Compares the block hash with the one returned by the secondary Node (see [`resolve_block_hash`]).
+	async fn cross_check_block_hash(&self, block_number: u32, hash: H256) -> Result<H256> {
+		let secondary = self
+			.query_secondary(|client| async move {
+				client
+					.legacy_rpc()
+					.chain_get_block_hash(Some(BlockNumber::from(block_number)))
+					.await
+					.map_err(Into::into)
+			})
+			.await;
+		let stored_hash = || {
+			self.db.get(BlockHashKey(block_number)).or_else(|| {
+				self.db
+					.get(BlockHeaderKey(block_number))
+					.map(|header| Encode::using_encoded(&header, blake2_256).into())
+			})
+		};
+		resolve_block_hash(block_number, hash, secondary, stored_hash)
+	}
+
+	/// Compares the cells with the ones returned by the secondary Node.
+	/// Proofs of both answers are verified for the divergent cells, and the cells with the valid proof are kept.
+	async fn cross_check_cells(
+		&self,
+		block_hash: H256,
+		cells: Vec<Cell>,
+		public_parameters: Arc<PublicParameters>,
+	) -> Vec<Cell> {
+		let positions = cells.iter().map(|cell| cell.position).collect::<Vec<_>>();
+		let secondary = self
+			.query_secondary(|client| query_proof(client, block_hash, positions))
+			.await;
+		let (secondary_host, secondary_cells) = match secondary {
+			Ok(result) => result,
+			Err(error) => {
+				warn!(?block_hash, "Cannot cross-check cells: {error:#}");
+				return cells;
+			},
+		};
+
+		let mut secondary_contents = secondary_cells
+			.into_iter()
+			.map(|cell| (cell.position, cell.content))
+			.collect::<HashMap<_, _>>();
+
+		let (mut cells, divergent): (Vec<_>, Vec<_>) = cells
+			.into_iter()
+			.partition(|cell| secondary_contents.get(&cell.position) == Some(&cell.content));
+		if divergent.is_empty() {
+			return cells;
+		}
+		error!(
+			?block_hash,
+			divergent = divergent.len(),
+			secondary_host,
+			"RPC responses diverge for the cell proofs"
+		);
+
+		let (block_number, block_commitments) = match self.block_commitments(block_hash).await {
+			Ok(result) => result,
+			Err(error) => {
+				warn!(?block_hash, "Cannot verify divergent cells: {error:#}");
+				return cells;
+			},
+		};
+		let verify = |cells: Vec<Cell>| {
+			let block_commitments = block_commitments.clone();
+			let public_parameters = public_parameters.clone();
+			async move {
+				let verified = proof::verify(
+					block_number,
+					block_commitments.dimensions,
+					&cells,
+					&block_commitments.commitments,
+					public_parameters,
+				)
+				.await
+				.map(|(verified, _)| verified)
+				.unwrap_or_else(|error| {
+					warn!(block_number, "Cannot verify divergent cells: {error:#}");
+					vec![]
+				});
+				cells
+					.into_iter()
+					.partition::<Vec<_>, _>(|cell| verified.contains(&cell.position))
+			}
+		};
+
+		let (verified, invalid) = verify(divergent).await;
+		let current = verified.len();
+		cells.extend(verified);
+		// cells without the valid proof from the current Node are taken from the secondary Node
+		let secondary_divergent = invalid
+			.iter()
+			.filter_map(|cell| {
+				let content = secondary_contents.remove(&cell.position)?;
+				Some(Cell {
+					position: cell.position,
+					content,
+				})
+			})
+			.collect::<Vec<_>>();
+		let (verified, invalid) = verify(secondary_divergent).await;
+		info!(
+			block_number,
+			current,
+			secondary = verified.len(),
+			dropped = invalid.len(),
+			"Divergent cells with the valid proof are kept"
+		);
+		cells.extend(verified);
+		cells
+	}
+
+	/// Returns the number and the commitments of the block, taken from the current Node
+	async fn block_commitments(&self, block_hash: H256) -> Result<(u32, Arc<BlockCommitments>)> {
+		let header = self.get_header_by_hash(block_hash).await?;
+		let header_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+		if header_hash != block_hash {
+			return Err(eyre!("Header does not match the block hash {block_hash:?}"));
+		}
+		let block_commitments = proof::block_commitments(block_hash, &header.extension)?
+			.ok_or_else(|| eyre!("Block {block_hash:?} has no commitments"))?;
+		Ok((header.number, block_commitments))
+	}
+
 	pub async fn get_block_hash(&self, block_number: u32) -> Result<H256> {
 		let hash = self
 			.with_retries(|client| async move {
@@ -417,52 +901,22 @@ impl<D: Database> Client<D> {
 		block_hash: H256,
 		positions: &[Position],
 	) -> Result<Vec<Cell>> {
-		fn concat_content(scalar: U256, proof: GProof) -> Result<[u8; 80]> {
-			let proof: Vec<u8> = proof.into();
-			if proof.len() != 48 {
-				return Err(eyre!("Invalid proof length"));
-			}
-
-			let mut result = [0u8; 80];
-			scalar.to_big_endian(&mut result[48..]);
-			result[..48].copy_from_slice(&proof);
-			Ok(result)
-		}
-
-		let cells: Cells = positions
-			.iter()
-			.map(|p| avail_subxt::Cell {
-				row: p.row,
-				col: p.col as u32,
-			})
-			.collect::<Vec<_>>()
-			.try_into()
-			.map_err(|_| eyre!("Failed to convert to cells"))?;
-
-		let proofs: Vec<(GRawScalar, GProof)> = self
+		// identical queries of the in-flight cells are coalesced
+		let cells = self
 			.with_retries(|client| {
-				let cells = cells.clone();
-				async move {
-					client
-						.rpc_methods()
-						.query_proof(cells, block_hash)
-						.await
-						.map_err(|error| subxt::Error::Other(format!("{error}")))
-						.map_err(Into::into)
-				}
+				self.kate_requests
+					.fetch(block_hash, positions, move |positions| {
+						query_proof(client, block_hash, positions)
+					})
 			})
-			.await
-			.map_err(Report::from)?;
-
-		let contents = proofs
-			.into_iter()
-			.map(|(scalar, proof)| concat_content(scalar, proof).expect("TODO"));
+			.await?;
 
-		Ok(positions
-			.iter()
-			.zip(contents)
-			.map(|(&position, content)| Cell { position, content })
-			.collect::<Vec<_>>())
+		let Some(public_parameters) = self.cross_check.clone() else {
+			return Ok(cells);
+		};
+		Ok(self
+			.cross_check_cells(block_hash, cells, public_parameters)
+			.await)
 	}
 
 	pub async fn get_system_version(&self) -> Result<String> {
@@ -514,10 +968,23 @@ impl<D: Database> Client<D> {
 	}
 
 	pub async fn get_header_by_block_number(&self, block_num: u32) -> Result<(Header, H256)> {
-		let hash = self.get_block_hash(block_num).await?;
-		self.get_header_by_hash(hash)
-			.await
-			.map(|header| (header, hash))
+		let mut hash = self.get_block_hash(block_num).await?;
+		if self.cross_check.is_none() {
+			return self
+				.get_header_by_hash(hash)
+				.await
+				.map(|header| (header, hash));
+		}
+
+		hash = self.cross_check_block_hash(block_num, hash).await?;
+		let header = self.get_header_by_hash(hash).await?;
+		let header_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+		if header_hash != hash {
+			return Err(eyre!(
+				"Header of block {block_num} does not match the block hash {hash:?}"
+			));
+		}
+		Ok((header, hash))
 	}
 
 	pub async fn get_validator_set_at(&self, block_hash: H256) -> Result<Option<Vec<AccountId32>>> {
@@ -647,3 +1114,49 @@ impl<D: Database> Client<D> {
 		Ok(gen_hash)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn secondary(hash: Option<H256>) -> Result<(String, Option<H256>)> {
+		Ok(("secondary".to_string(), hash))
+	}
+
+	#[test]
+	fn resolve_matching_block_hash() {
+		let hash = H256::repeat_byte(1);
+		let resolved = resolve_block_hash(1, hash, secondary(Some(hash)), || None).unwrap();
+		assert_eq!(resolved, hash);
+	}
+
+	#[test]
+	fn resolve_divergent_block_hash() {
+		let (hash, secondary_hash) = (H256::repeat_byte(1), H256::repeat_byte(2));
+
+		let resolved = resolve_block_hash(1, hash, secondary(Some(secondary_hash)), || {
+			Some(secondary_hash)
+		});
+		assert_eq!(resolved.unwrap(), secondary_hash);
+
+		let resolved = resolve_block_hash(1, hash, secondary(Some(secondary_hash)), || Some(hash));
+		assert_eq!(resolved.unwrap(), hash);
+
+		// divergent hashes are rejected without the stored hash, or if it matches neither
+		assert!(resolve_block_hash(1, hash, secondary(Some(secondary_hash)), || None).is_err());
+		assert!(resolve_block_hash(1, hash, secondary(None), || None).is_err());
+		assert!(
+			resolve_block_hash(1, hash, secondary(Some(secondary_hash)), || {
+				Some(H256::repeat_byte(3))
+			})
+			.is_err()
+		);
+	}
+
+	#[test]
+	fn resolve_block_hash_without_secondary() {
+		let hash = H256::repeat_byte(1);
+		let secondary = Err(eyre!("Cannot connect to the secondary Node"));
+		assert!(resolve_block_hash(1, hash, secondary, || Some(hash)).is_err());
+	}
+}
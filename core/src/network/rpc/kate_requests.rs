@@ -0,0 +1,187 @@
+use avail_subxt::utils::H256;
+use color_eyre::{eyre::eyre, Result};
+use futures::{
+	future::{BoxFuture, Shared},
+	FutureExt,
+};
+use kate_recovery::{data::Cell, matrix::Position};
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+use tokio::{
+	sync::{Mutex as AsyncMutex, Semaphore},
+	time::{interval, Interval, MissedTickBehavior},
+};
+use tracing::debug;
+
+use crate::types::RpcRateLimitConfig;
+
+type CellsResult = Result<Arc<Vec<Cell>>, Arc<String>>;
+type CellsRequest = Shared<BoxFuture<'static, CellsResult>>;
+
+struct RateLimit {
+	interval: AsyncMutex<Interval>,
+	in_flight: Semaphore,
+}
+
+/// Rate limits the kate proof queries, and coalesces the queries of the same cells.
+///
+/// Cells which are already requested by another in-flight query are not requested again,
+/// instead the response of the in-flight query is awaited.
+#[derive(Clone)]
+pub struct KateRequests {
+	in_flight: Arc<Mutex<HashMap<(H256, Position), CellsRequest>>>,
+	rate_limit: Option<Arc<RateLimit>>,
+}
+
+impl KateRequests {
+	pub fn new(rate_limit: Option<RpcRateLimitConfig>) -> Self {
+		let rate_limit = rate_limit.map(|config| {
+			let period = Duration::from_secs(1) / config.requests_per_second.max(1);
+			let mut interval = interval(period);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			Arc::new(RateLimit {
+				interval: AsyncMutex::new(interval),
+				in_flight: Semaphore::new(config.max_in_flight.max(1)),
+			})
+		});
+
+		Self {
+			in_flight: Default::default(),
+			rate_limit,
+		}
+	}
+
+	/// Fetches cells for the given positions, using `request` to query the ones which are not in-flight.
+	pub async fn fetch<F, Fut>(
+		&self,
+		block_hash: H256,
+		positions: &[Position],
+		request: F,
+	) -> Result<Vec<Cell>>
+	where
+		F: FnOnce(Vec<Position>) -> Fut,
+		Fut: Future<Output = Result<Vec<Cell>>> + Send + 'static,
+	{
+		let requests = {
+			let mut in_flight = self.in_flight.lock().expect("Lock is not poisoned");
+
+			let mut requests = vec![];
+			let mut unrequested = vec![];
+			for &position in positions {
+				match in_flight.get(&(block_hash, position)) {
+					Some(request) => requests.push(request.clone()),
+					None => unrequested.push(position),
+				}
+			}
+
+			debug!(
+				?block_hash,
+				coalesced = positions.len() - unrequested.len(),
+				requested = unrequested.len(),
+				"Requesting cells from RPC"
+			);
+
+			if !unrequested.is_empty() {
+				let keys = unrequested
+					.iter()
+					.map(|&position| (block_hash, position))
+					.collect::<Vec<_>>();
+				let request = self.limited(keys.clone(), request(unrequested));
+				for key in keys {
+					in_flight.insert(key, request.clone());
+				}
+				requests.push(request);
+			}
+			requests
+		};
+
+		let mut fetched = HashMap::new();
+		for request in requests {
+			let cells = request.await.map_err(|error| eyre!("{error}"))?;
+			fetched.extend(cells.iter().map(|cell| (cell.position, cell.clone())));
+		}
+
+		Ok(positions
+			.iter()
+			.filter_map(|position| fetched.remove(position))
+			.collect())
+	}
+
+	fn limited(
+		&self,
+		keys: Vec<(H256, Position)>,
+		request: impl Future<Output = Result<Vec<Cell>>> + Send + 'static,
+	) -> CellsRequest {
+		let in_flight = self.in_flight.clone();
+		let rate_limit = self.rate_limit.clone();
+
+		async move {
+			let result = match rate_limit {
+				Some(rate_limit) => {
+					let _permit = rate_limit.in_flight.acquire().await;
+					rate_limit.interval.lock().await.tick().await;
+					request.await
+				},
+				None => request.await,
+			};
+
+			let mut in_flight = in_flight.lock().expect("Lock is not poisoned");
+			for key in keys {
+				in_flight.remove(&key);
+			}
+
+			result
+				.map(Arc::new)
+				.map_err(|error| Arc::new(format!("{error:#}")))
+		}
+		.boxed()
+		.shared()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	fn cells(positions: Vec<Position>) -> Vec<Cell> {
+		positions
+			.into_iter()
+			.map(|position| Cell {
+				position,
+				content: [0u8; 80],
+			})
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn coalesce_requests_of_the_same_cells() {
+		let kate_requests = KateRequests::new(None);
+		let requested = Arc::new(AtomicUsize::new(0));
+		let positions = (0..4)
+			.map(|col| Position { row: 0, col })
+			.collect::<Vec<_>>();
+
+		let request = |requested: Arc<AtomicUsize>| {
+			move |positions: Vec<Position>| async move {
+				requested.fetch_add(positions.len(), Ordering::SeqCst);
+				tokio::time::sleep(Duration::from_millis(50)).await;
+				Ok(cells(positions))
+			}
+		};
+
+		let (first, second) = tokio::join!(
+			kate_requests.fetch(H256::zero(), &positions[..3], request(requested.clone())),
+			kate_requests.fetch(H256::zero(), &positions[1..], request(requested.clone())),
+		);
+
+		assert_eq!(first.unwrap().len(), 3);
+		assert_eq!(second.unwrap().len(), 3);
+		assert_eq!(requested.load(Ordering::SeqCst), 4);
+		assert!(kate_requests.in_flight.lock().unwrap().is_empty());
+	}
+}
@@ -1,14 +1,14 @@
-use avail_subxt::primitives::{grandpa::AuthorityId, Header};
+use avail_subxt::primitives::Header;
 use codec::Encode;
 use color_eyre::{eyre::eyre, Result};
-use sp_core::{
-	blake2_256,
-	ed25519::{self, Public},
+use sp_core::blake2_256;
+use std::{ops::Range, time::Instant};
+use tokio::sync::{
+	broadcast::Sender,
+	mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
 };
-use std::time::Instant;
-use tokio::sync::broadcast::Sender;
 use tokio_stream::StreamExt;
-use tracing::{debug, info, trace};
+use tracing::{debug, info, info_span, trace, warn, Span};
 
 use super::{Client, Subscription};
 use crate::{
@@ -16,9 +16,9 @@ use crate::{
 		Database, FinalitySyncCheckpoint, FinalitySyncCheckpointKey, IsFinalitySyncedKey,
 		LatestHeaderKey, VerifiedHeaderKey,
 	},
-	finality::{check_finality, ValidatorSet},
+	finality::{check_finality, verify_ancestry, ValidatorSet, ValidatorSetTracker},
+	telemetry::block_span,
 	types::{BlockRange, GrandpaJustification},
-	utils::filter_auth_set_changes,
 };
 
 #[derive(Clone, Debug)]
@@ -26,14 +26,17 @@ pub enum Event {
 	HeaderUpdate {
 		header: Header,
 		received_at: Instant,
+		/// Root span of the block processing trace
+		span: Span,
+		/// Justification of the finalized header, skipped headers are sent without it
+		justification: Option<GrandpaJustification>,
 	},
 }
 
 struct BlockData {
 	justifications: Vec<GrandpaJustification>,
-	unverified_headers: Vec<(Header, Instant, ValidatorSet)>,
-	current_valset: ValidatorSet,
-	next_valset: Option<ValidatorSet>,
+	unverified_headers: Vec<(Header, Instant, ValidatorSet, Span)>,
+	validator_sets: ValidatorSetTracker,
 	last_finalized_block_header: Option<Header>,
 }
 
@@ -42,6 +45,7 @@ pub struct SubscriptionLoop<T: Database> {
 	event_sender: Sender<Event>,
 	db: T,
 	block_data: BlockData,
+	historical_sync: Option<(u32, UnboundedSender<Range<u32>>)>,
 }
 
 impl<T: Database + Clone> SubscriptionLoop<T> {
@@ -71,16 +75,49 @@ impl<T: Database + Clone> SubscriptionLoop<T> {
 			block_data: BlockData {
 				justifications: Default::default(),
 				unverified_headers: Default::default(),
-				current_valset: ValidatorSet {
+				validator_sets: ValidatorSetTracker::new(ValidatorSet {
 					set_id,
 					validator_set,
-				},
-				next_valset: None,
+				}),
 				last_finalized_block_header: Some(last_finalized_block_header),
 			},
+			historical_sync: None,
 		})
 	}
 
+	/// Hands over the gaps of missed finalized blocks larger than `max_gap` to the historical sync,
+	/// instead of backfilling them from the subscription loop.
+	/// Returns receiver of the missed block ranges.
+	pub fn historical_sync(&mut self, max_gap: u32) -> UnboundedReceiver<Range<u32>> {
+		let (sender, receiver) = unbounded_channel();
+		self.historical_sync = Some((max_gap, sender));
+		receiver
+	}
+
+	/// Sends the missed blocks to the historical sync, if the gap is too large to be backfilled.
+	fn handover_to_historical_sync(&mut self, missed: Range<u32>) -> bool {
+		let Some((max_gap, sender)) = &self.historical_sync else {
+			return false;
+		};
+		if missed.len() <= *max_gap as usize {
+			return false;
+		}
+
+		warn!(
+			"Missed {} finalized blocks {missed:?}, switching to historical sync",
+			missed.len()
+		);
+		if let Err(error) = sender.send(missed.clone()) {
+			warn!("Cannot hand over missed blocks to historical sync: {error}");
+			return false;
+		}
+		// headers of the missed blocks will be fetched by the historical sync
+		self.block_data
+			.unverified_headers
+			.retain(|(header, ..)| !missed.contains(&header.number));
+		true
+	}
+
 	pub async fn run(mut self) -> Result<()> {
 		// create subscriptions stream
 		let subscriptions = self.rpc_client.clone().subscription_stream().await;
@@ -102,43 +139,27 @@ impl<T: Database + Clone> SubscriptionLoop<T> {
 		match subscription {
 			Subscription::Header(header) => {
 				let received_at = Instant::now();
+				let span = block_span(header.number);
 				self.db.put(LatestHeaderKey, header.clone().number);
 				info!("Header no.: {}", header.number);
 
-				// if new validator set becomes active, replace the current one
-				if self.block_data.next_valset.is_some() {
-					self.block_data.current_valset = self.block_data.next_valset.take().unwrap();
-				}
+				// validator set which finalizes the header, with the due changes enacted
+				let valset = self
+					.block_data
+					.validator_sets
+					.validator_set_at(header.number)
+					.clone();
 
 				// push new Unverified Header
 				self.block_data.unverified_headers.push((
 					header.clone(),
 					received_at,
-					self.block_data.current_valset.clone(),
+					valset,
+					span,
 				));
 
-				// search the header logs for validator set change
-				let mut new_auths = filter_auth_set_changes(&header);
-				// if the event exists, send the new auths over the message channel.
-				if !new_auths.is_empty() {
-					// TODO: Handle this in a proper fashion
-					assert!(
-						new_auths.len() == 1,
-						"There should be only one valset change!"
-					);
-					let auths: Vec<(AuthorityId, u64)> = new_auths.pop().unwrap();
-					let new_valset = auths
-						.into_iter()
-						.map(|(a, _)| ed25519::Public::from_raw(a.0 .0 .0))
-						.collect::<Vec<Public>>();
-
-					self.block_data.next_valset = Some(ValidatorSet {
-						set_id: self.block_data.current_valset.set_id + 1,
-						validator_set: new_valset,
-					});
-
-					debug!("Validator set change: {:?}", self.block_data.next_valset);
-				}
+				// schedule the validator set changes announced in the header
+				self.block_data.validator_sets.import(&header);
 			},
 			Subscription::Justification(justification) => {
 				info!(
@@ -160,26 +181,34 @@ impl<T: Database + Clone> SubscriptionLoop<T> {
 				.block_data
 				.unverified_headers
 				.iter()
-				.map(|(h, _, _)| Encode::using_encoded(h, blake2_256).into())
+				.map(|(h, ..)| Encode::using_encoded(h, blake2_256).into())
 				.position(|hash| justification.commit.target_hash == hash)
 			{
 				// basically, pop it out of the collection
-				let (header, received_at, valset) =
+				let (header, received_at, valset, span) =
 					self.block_data.unverified_headers.swap_remove(pos);
 
-				let is_final = check_finality(&valset, &justification);
-
-				is_final.expect("Finality check failed");
+				// only headers finalized by the validator set are sent to the sampling
+				let finality = info_span!(parent: &span, "finality_verification")
+					.in_scope(|| check_finality(&valset, &justification));
+				if let Err(error) = finality {
+					warn!(
+						block_number = header.number,
+						"Header finality check failed: {error:#}"
+					);
+					continue;
+				}
 
 				// store Finality Checkpoint if finality is synced
 				if finality_synced {
 					info!("Storing finality checkpoint at block {}", header.number);
+					let current_valset = self.block_data.validator_sets.current();
 					self.db.put(
 						FinalitySyncCheckpointKey,
 						FinalitySyncCheckpoint {
-							set_id: self.block_data.current_valset.set_id,
+							set_id: current_valset.set_id,
 							number: header.number,
-							validator_set: self.block_data.current_valset.validator_set.clone(),
+							validator_set: current_valset.validator_set.clone(),
 						},
 					);
 				} else {
@@ -189,39 +218,65 @@ impl<T: Database + Clone> SubscriptionLoop<T> {
 					finality_synced = self.db.get(IsFinalitySyncedKey).unwrap_or(false)
 				}
 
-				// try and get get all the skipped blocks, if they exist
-				if let Some(last_header) = self.block_data.last_finalized_block_header.as_ref() {
+				// try and get get all the skipped blocks, if they exist,
+				// unless there are too many of them, so they are handed over to the historical sync
+				if let Some(last_header) = self
+					.block_data
+					.last_finalized_block_header
+					.clone()
+					.filter(|last_header| {
+						let missed = (last_header.number + 1)..header.number;
+						!self.handover_to_historical_sync(missed)
+					}) {
+					let mut skipped = vec![];
 					for bl_num in (last_header.number + 1)..header.number {
-						info!("Sending skipped block {bl_num}");
-						let (header, received_at) = match self
+						let (header, received_at, span) = match self
 							.block_data
 							.unverified_headers
 							.iter()
-							.position(|(h, _, _)| h.number == bl_num)
+							.position(|(h, ..)| h.number == bl_num)
 						{
 							Some(pos) => {
 								info!("Fetching header from unverified headers");
 								let p = self.block_data.unverified_headers.swap_remove(pos);
-								(p.0, p.1)
+								(p.0, p.1, p.3)
 							},
 							None => {
 								info!("Fetching header from RPC");
-								let a = self
-									.rpc_client
-									.get_header_by_block_number(bl_num)
-									.await
-									.unwrap()
-									.0;
-								(a, Instant::now())
+								match self.rpc_client.get_header_by_block_number(bl_num).await {
+									Ok((header, _)) => {
+										let span = block_span(header.number);
+										(header, Instant::now(), span)
+									},
+									Err(error) => {
+										warn!("Cannot fetch skipped block {bl_num}: {error:#}");
+										break;
+									},
+								}
 							},
 						};
-						// send as output event
-						self.event_sender
-							.send(Event::HeaderUpdate {
-								header,
-								received_at,
-							})
-							.unwrap();
+						skipped.push((header, received_at, span));
+					}
+
+					// skipped headers are sent only if they link to the finalized header
+					let skipped_headers =
+						skipped.iter().map(|(h, ..)| h.clone()).collect::<Vec<_>>();
+					match verify_ancestry(&last_header, &skipped_headers, &header) {
+						Ok(()) => {
+							for (header, received_at, span) in skipped {
+								info!("Sending skipped block {}", header.number);
+								// send as output event
+								self.event_sender
+									.send(Event::HeaderUpdate {
+										header,
+										received_at,
+										span,
+										justification: None,
+									})
+									.unwrap();
+							}
+						},
+						Err(error) => warn!("Skipped blocks are not sent: {error:#}"),
 					}
 				}
 
@@ -244,6 +299,8 @@ impl<T: Database + Clone> SubscriptionLoop<T> {
 					.send(Event::HeaderUpdate {
 						header,
 						received_at,
+						span,
+						justification: Some(justification),
 					})
 					.unwrap();
 			} else {
@@ -1,28 +1,235 @@
 //! Parallelized proof verification
+//!
+//! Proofs are verified on the dedicated thread pool, which is sized by the configured number of threads,
+//! or by the number of available cores if the number of threads is not configured.
+//!
+//! Proofs of all cells are first verified in a batch, using the random linear combination of the proofs,
+//! so the pairings are computed only once. If the batch verification fails, proofs are verified one by one,
+//! to find the cells with invalid proofs.
+//!
+//! Commitments and dimensions extracted from the verified headers are cached by block hash,
+//! so the header extension is parsed only once per block.
 
-use color_eyre::eyre;
-use dusk_plonk::commitment_scheme::kzg10::PublicParameters;
+use crate::utils::extract_kate;
+use avail_subxt::{
+	api::runtime_types::avail_core::header::extension::HeaderExtension, utils::H256,
+};
+use color_eyre::{eyre, eyre::WrapErr};
+use dusk_bytes::{DeserializableSlice, Serializable};
+use dusk_plonk::{
+	bls12_381::{pairing, G1Affine, G1Projective, G2Affine},
+	commitment_scheme::kzg10::PublicParameters,
+	fft::EvaluationDomain,
+	prelude::BlsScalar,
+};
 use itertools::{Either, Itertools};
 use kate_recovery::{
+	commitments,
 	data::Cell,
 	matrix::{Dimensions, Position},
 	proof,
 };
-use std::sync::Arc;
-use tokio::{task::JoinSet, time::Instant};
-use tracing::{debug, Instrument};
+use lru::LruCache;
+use rand::{thread_rng, RngCore};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use std::{
+	num::NonZeroUsize,
+	sync::{Arc, Mutex, OnceLock},
+};
+use tokio::{sync::oneshot, time::Instant};
+use tracing::{debug, instrument};
 
-async fn verify_proof(
-	public_parameters: Arc<PublicParameters>,
+static THREAD_POOL: OnceLock<ThreadPool> = OnceLock::new();
+
+static COMMITMENT_CACHE: OnceLock<Mutex<CommitmentCache>> = OnceLock::new();
+
+/// Number of the cached blocks if the commitment cache is not initialized
+const DEFAULT_COMMITMENT_CACHE_SIZE: usize = 128;
+
+fn build_thread_pool(threads: Option<usize>) -> eyre::Result<ThreadPool> {
+	ThreadPoolBuilder::new()
+		.num_threads(threads.unwrap_or_default())
+		.thread_name(|index| format!("proof-verification-{index}"))
+		.build()
+		.wrap_err("Failed to build proof verification thread pool")
+}
+
+/// Initializes the proof verification thread pool with the given number of threads.
+/// Pool with one thread per available core is used if the pool is not initialized.
+pub fn init_thread_pool(threads: Option<usize>) -> eyre::Result<()> {
+	THREAD_POOL
+		.set(build_thread_pool(threads)?)
+		.map_err(|_| eyre::eyre!("Proof verification thread pool is already initialized"))
+}
+
+fn thread_pool() -> &'static ThreadPool {
+	THREAD_POOL.get_or_init(|| {
+		build_thread_pool(None).expect("Default proof verification thread pool must be built")
+	})
+}
+
+/// Commitments and dimensions of the block, extracted from the verified header
+#[derive(Debug)]
+pub struct BlockCommitments {
+	pub dimensions: Dimensions,
+	pub commitments: Vec<[u8; 48]>,
+}
+
+struct CommitmentCache {
+	/// Cached blocks, not set if the cache is disabled
+	blocks: Option<LruCache<H256, Option<Arc<BlockCommitments>>>>,
+	hits: u64,
+	misses: u64,
+}
+
+impl CommitmentCache {
+	fn new(size: usize) -> Self {
+		CommitmentCache {
+			blocks: NonZeroUsize::new(size).map(LruCache::new),
+			hits: 0,
+			misses: 0,
+		}
+	}
+}
+
+/// Initializes the commitment cache with the given number of blocks, cache is disabled if the size is 0.
+/// Cache of the default size is used if the cache is not initialized.
+pub fn init_commitment_cache(size: usize) -> eyre::Result<()> {
+	COMMITMENT_CACHE
+		.set(Mutex::new(CommitmentCache::new(size)))
+		.map_err(|_| eyre::eyre!("Commitment cache is already initialized"))
+}
+
+fn commitment_cache() -> &'static Mutex<CommitmentCache> {
+	COMMITMENT_CACHE.get_or_init(|| Mutex::new(CommitmentCache::new(DEFAULT_COMMITMENT_CACHE_SIZE)))
+}
+
+fn extract_commitments(extension: &HeaderExtension) -> eyre::Result<Option<BlockCommitments>> {
+	let Some((rows, cols, _, commitment)) = extract_kate(extension) else {
+		return Ok(None);
+	};
+	let dimensions =
+		Dimensions::new(rows, cols).ok_or_else(|| eyre::eyre!("Invalid dimensions"))?;
+	let commitments = commitments::from_slice(&commitment)?;
+	Ok(Some(BlockCommitments {
+		dimensions,
+		commitments,
+	}))
+}
+
+/// Commitments and dimensions of the block with the given hash, `None` if the block has no data.
+/// Commitments are extracted from the header extension on the first lookup, and cached afterwards.
+pub fn block_commitments(
+	block_hash: H256,
+	extension: &HeaderExtension,
+) -> eyre::Result<Option<Arc<BlockCommitments>>> {
+	let mut cache = commitment_cache()
+		.lock()
+		.map_err(|_| eyre::eyre!("Commitment cache lock is poisoned"))?;
+
+	let cached = cache
+		.blocks
+		.as_mut()
+		.and_then(|blocks| blocks.get(&block_hash).cloned());
+	if let Some(cached) = cached {
+		cache.hits += 1;
+		return Ok(cached);
+	}
+	cache.misses += 1;
+
+	let block_commitments = extract_commitments(extension)?.map(Arc::new);
+	if let Some(blocks) = cache.blocks.as_mut() {
+		blocks.put(block_hash, block_commitments.clone());
+	}
+	Ok(block_commitments)
+}
+
+/// Ratio of the commitment cache hits to all lookups since the previous call, `None` if there were no lookups
+pub fn commitment_cache_hit_rate() -> Option<f64> {
+	let mut cache = commitment_cache().lock().ok()?;
+	let lookups = cache.hits + cache.misses;
+	let hit_rate = (lookups > 0).then(|| cache.hits as f64 / lookups as f64);
+	cache.hits = 0;
+	cache.misses = 0;
+	hit_rate
+}
+
+/// Opening key points needed for the batch verification
+struct OpeningKey {
+	g: G1Affine,
+	h: G2Affine,
+	x_h: G2Affine,
+}
+
+impl OpeningKey {
+	fn new(public_parameters: &PublicParameters) -> Option<Self> {
+		let (_, opening_key) = public_parameters.trim(1).ok()?;
+		let bytes = opening_key.to_bytes();
+		let (g, rest) = bytes.split_at(G1Affine::SIZE);
+		let (h, x_h) = rest.split_at(G2Affine::SIZE);
+		Some(OpeningKey {
+			g: G1Affine::from_slice(g).ok()?,
+			h: G2Affine::from_slice(h).ok()?,
+			x_h: G2Affine::from_slice(x_h).ok()?,
+		})
+	}
+}
+
+fn random_scalar() -> BlsScalar {
+	let mut bytes = [0u8; 64];
+	thread_rng().fill_bytes(&mut bytes);
+	BlsScalar::from_bytes_wide(&bytes)
+}
+
+/// Verifies proofs of all cells with two pairings, by checking the random linear combination of the proofs:
+/// `e(sum(r * W), x * H) == e(sum(r * (C - y * G + z * W)), H)`.
+/// Returns `false` if any proof is invalid, or if any cell or commitment cannot be decoded.
+fn verify_batch(
+	public_parameters: &PublicParameters,
 	dimensions: Dimensions,
-	commitment: [u8; 48],
-	cell: Cell,
-) -> Result<(Position, bool), proof::Error> {
-	proof::verify(&public_parameters, dimensions, &commitment, &cell)
-		.map(|verified| (cell.position, verified))
+	cells: &[([u8; 48], Cell)],
+) -> bool {
+	let Some(opening_key) = OpeningKey::new(public_parameters) else {
+		return false;
+	};
+	let Ok(domain) = EvaluationDomain::new(usize::from(dimensions.cols().get())) else {
+		return false;
+	};
+	let points = domain.elements().collect::<Vec<_>>();
+
+	let terms = cells
+		.par_iter()
+		.map(|(commitment, cell)| {
+			let witness = G1Affine::from_slice(&cell.content[..48]).ok()?;
+			let value = BlsScalar::from_slice(&cell.content[48..]).ok()?;
+			let commitment = G1Affine::from_slice(commitment).ok()?;
+			let point = points.get(usize::from(cell.position.col))?;
+
+			let random = random_scalar();
+			let witness = G1Projective::from(witness);
+			let lhs = witness * random;
+			let rhs =
+				(G1Projective::from(commitment) - opening_key.g * value + witness * point) * random;
+			Some((lhs, rhs))
+		})
+		.collect::<Option<Vec<_>>>();
+	let Some(terms) = terms else {
+		return false;
+	};
+
+	let (lhs, rhs) = terms.into_iter().fold(
+		(G1Projective::identity(), G1Projective::identity()),
+		|(lhs, rhs), (cell_lhs, cell_rhs)| (lhs + cell_lhs, rhs + cell_rhs),
+	);
+	pairing(&G1Affine::from(lhs), &opening_key.x_h) == pairing(&G1Affine::from(rhs), &opening_key.h)
 }
 
 /// Verifies proofs for given block, cells and commitments
+#[instrument(
+	skip_all,
+	name = "proof_verification",
+	fields(block_number = block_num, cells = cells.len()),
+)]
 pub async fn verify(
 	block_num: u32,
 	dimensions: Dimensions,
@@ -36,26 +243,41 @@ pub async fn verify(
 
 	let start_time = Instant::now();
 
-	let mut tasks = JoinSet::new();
-
-	for cell in cells {
-		tasks.spawn(
-			verify_proof(
-				public_parameters.clone(),
-				dimensions,
-				commitments[cell.position.row as usize],
-				cell.clone(),
-			)
-			.in_current_span(),
-		);
-	}
+	let cells = cells
+		.iter()
+		.map(|cell| (commitments[cell.position.row as usize], cell.clone()))
+		.collect::<Vec<_>>();
 
-	let mut results = Vec::with_capacity(cells.len());
-	while let Some(result) = tasks.join_next().await {
-		results.push(result??)
-	}
+	let (results_sender, results_receiver) = oneshot::channel();
+	thread_pool().spawn(move || {
+		if cells.len() > 1 && verify_batch(&public_parameters, dimensions, &cells) {
+			let positions = cells.iter().map(|(_, cell)| (cell.position, true));
+			let _ = results_sender.send(Ok(positions.collect()));
+			return;
+		}
+
+		let results = cells
+			.par_iter()
+			.map(|(commitment, cell)| {
+				proof::verify(&public_parameters, dimensions, commitment, cell)
+					.map(|verified| (cell.position, verified))
+			})
+			.collect::<Result<Vec<_>, proof::Error>>();
+		// receiver is dropped if verification is cancelled
+		let _ = results_sender.send(results);
+	});
+
+	let results = results_receiver
+		.await
+		.wrap_err("Proof verification was interrupted")??;
 
-	debug!(block_num, duration = ?start_time.elapsed(), "Proof verification completed");
+	let elapsed = start_time.elapsed();
+	debug!(
+		block_num,
+		duration = ?elapsed,
+		cells_per_second = results.len() as f64 / elapsed.as_secs_f64(),
+		"Proof verification completed"
+	);
 
 	Ok(results
 		.into_iter()
@@ -64,3 +286,115 @@ pub async fn verify(
 			false => Either::Right(position),
 		}))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use dusk_plonk::fft::Evaluations;
+	use kate_recovery::testnet;
+
+	const ROWS: u16 = 2;
+	const COLS: u16 = 4;
+
+	/// Builds the commitments and the cells with valid proofs of the block with `ROWS` x `COLS` cells
+	fn block(public_parameters: &PublicParameters) -> (Dimensions, Vec<[u8; 48]>, Vec<Cell>) {
+		let dimensions = Dimensions::new(ROWS, COLS).unwrap();
+		let (commit_key, _) = public_parameters.trim(usize::from(COLS)).unwrap();
+		let domain = EvaluationDomain::new(usize::from(COLS)).unwrap();
+		let points = domain.elements().collect::<Vec<_>>();
+
+		let mut commitments = vec![];
+		let mut cells = vec![];
+		for row in 0..ROWS {
+			let values = (0..COLS)
+				.map(|col| BlsScalar::from(u64::from(row * COLS + col + 1)))
+				.collect::<Vec<_>>();
+			let polynomial = Evaluations::from_vec_and_domain(values.clone(), domain).interpolate();
+			commitments.push(commit_key.commit(&polynomial).unwrap().to_bytes());
+
+			for col in 0..COLS {
+				let witness =
+					commit_key.compute_single_witness(&polynomial, &points[usize::from(col)]);
+				let witness = commit_key.commit(&witness).unwrap().to_bytes();
+				let mut content = [0u8; 80];
+				content[..48].copy_from_slice(&witness);
+				content[48..].copy_from_slice(&values[usize::from(col)].to_bytes());
+				cells.push(Cell {
+					position: Position {
+						row: row.into(),
+						col,
+					},
+					content,
+				});
+			}
+		}
+		(dimensions, commitments, cells)
+	}
+
+	/// Replaces the value of the cell, so its proof is invalid
+	fn tamper(cell: &mut Cell) {
+		let value = BlsScalar::from_slice(&cell.content[48..]).unwrap();
+		cell.content[48..].copy_from_slice(&(value + BlsScalar::one()).to_bytes());
+	}
+
+	fn with_commitments(commitments: &[[u8; 48]], cells: &[Cell]) -> Vec<([u8; 48], Cell)> {
+		cells
+			.iter()
+			.map(|cell| (commitments[cell.position.row as usize], cell.clone()))
+			.collect()
+	}
+
+	#[test]
+	fn opening_key() {
+		let public_parameters = testnet::public_params(1024);
+		assert!(OpeningKey::new(&public_parameters).is_some());
+	}
+
+	#[test]
+	fn random_scalars() {
+		let scalars = (0..10).map(|_| random_scalar()).collect::<Vec<_>>();
+		assert!(scalars.iter().all(|scalar| *scalar != BlsScalar::zero()));
+		assert!(scalars.iter().map(|scalar| scalar.to_bytes()).all_unique());
+	}
+
+	#[test]
+	fn verify_valid_batch() {
+		let public_parameters = testnet::public_params(1024);
+		let (dimensions, commitments, cells) = block(&public_parameters);
+		let cells = with_commitments(&commitments, &cells);
+		assert!(verify_batch(&public_parameters, dimensions, &cells));
+	}
+
+	#[test]
+	fn verify_tampered_batch() {
+		let public_parameters = testnet::public_params(1024);
+		let (dimensions, commitments, mut cells) = block(&public_parameters);
+		tamper(&mut cells[5]);
+		let cells = with_commitments(&commitments, &cells);
+		assert!(!verify_batch(&public_parameters, dimensions, &cells));
+	}
+
+	#[tokio::test]
+	async fn verify_finds_tampered_cell() {
+		let public_parameters = Arc::new(testnet::public_params(1024));
+		let (dimensions, commitments, mut cells) = block(&public_parameters);
+		let (verified, unverified) = verify(
+			1,
+			dimensions,
+			&cells,
+			&commitments,
+			public_parameters.clone(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(verified.len(), cells.len());
+		assert!(unverified.is_empty());
+
+		tamper(&mut cells[5]);
+		let (verified, unverified) = verify(1, dimensions, &cells, &commitments, public_parameters)
+			.await
+			.unwrap();
+		assert_eq!(verified.len(), cells.len() - 1);
+		assert_eq!(unverified, vec![cells[5].position]);
+	}
+}
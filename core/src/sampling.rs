@@ -0,0 +1,405 @@
+//! Target driven sampling of the block cells.
+//!
+//! Cells are sampled in rounds of random positions, which are fetched from the DHT with the RPC fallback and verified.
+//! Each round samples only as many new positions as are still needed for the target confidence,
+//! so sampling stops as soon as the target is reached, or when the configured number of rounds is exhausted.
+//! Sampling results are persisted per block and broadcasted to the subscribers.
+//!
+//! Number of the sampled cells depends on the extended matrix size. Block is unavailable if at least the configured fraction
+//! of the cells is withheld, and since cells are sampled without replacement, smaller matrices need fewer samples.
+
+use crate::{
+	data::{Database, SamplingResultKey},
+	network::{self, FetchStats},
+	types::RuntimeConfig,
+};
+use avail_subxt::utils::H256;
+use codec::{Decode, Encode};
+use color_eyre::Result;
+use kate_recovery::{
+	config,
+	data::Cell,
+	matrix::{Dimensions, Position},
+};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::sync::{broadcast, watch};
+use tracing::{debug, info, info_span, instrument, Instrument};
+
+#[derive(Clone, Debug)]
+pub struct SamplingConfig {
+	/// Confidence which needs to be achieved, in percents
+	pub target_confidence: f64,
+	/// Maximum number of sampling rounds per block
+	pub max_rounds: u32,
+	/// Minimal fraction of the extended matrix cells which needs to be withheld to make the block unavailable
+	pub withheld_fraction: f64,
+	/// Minimum number of the cells to sample per block
+	pub min_cells: u32,
+	/// Maximum number of the cells to sample per block
+	pub max_cells: u32,
+}
+
+impl From<&RuntimeConfig> for SamplingConfig {
+	fn from(val: &RuntimeConfig) -> Self {
+		SamplingConfig {
+			target_confidence: val.confidence,
+			max_rounds: val.sampling_max_rounds,
+			withheld_fraction: val.sampling_withheld_fraction,
+			min_cells: val.sampling_min_cells,
+			max_cells: val.sampling_max_cells,
+		}
+	}
+}
+
+/// Minimal number of the withheld cells which makes the block unavailable
+pub fn withheld_cells(total: u32, withheld_fraction: f64) -> u32 {
+	((total as f64 * withheld_fraction).ceil() as u32).clamp(1, total.max(1))
+}
+
+/// Confidence (in percents) that the block is available, after the given number of the sampled cells is verified.
+/// Confidence is the probability that at least one of the sampled cells is withheld, if the minimal number of the cells is withheld.
+/// Every reported confidence is calculated with this model.
+pub fn confidence(total: u32, withheld: u32, verified: u32) -> f64 {
+	// probability that none of the sampled cells is withheld
+	let mut miss_probability = 1f64;
+	for sampled in 0..verified.min(total) {
+		miss_probability *= total.saturating_sub(withheld).saturating_sub(sampled) as f64;
+		miss_probability /= (total - sampled) as f64;
+	}
+	100f64 * (1f64 - miss_probability)
+}
+
+/// Number of the cells to sample for the target confidence.
+pub fn cell_count(dimensions: Dimensions, cfg: &SamplingConfig) -> u32 {
+	let total = dimensions.extended_size();
+	let withheld = withheld_cells(total, cfg.withheld_fraction);
+	let max_cells = cfg.max_cells.clamp(1, total);
+	let min_cells = cfg.min_cells.min(max_cells);
+
+	(1..=max_cells)
+		.find(|&count| confidence(total, withheld, count) >= cfg.target_confidence)
+		.map_or(max_cells, |count| count.max(min_cells))
+}
+
+#[derive(Serialize, Deserialize, Encode, Decode, Clone, Debug, PartialEq)]
+pub struct SamplingResult {
+	pub block_number: u32,
+	/// Number of the verified cells needed for the target confidence
+	pub required: u32,
+	/// Number of the sampled cell positions
+	pub sampled: u32,
+	pub verified: u32,
+	pub rounds: u32,
+	/// Number of the extended matrix cells
+	pub total_cells: u32,
+	/// Minimal number of the withheld cells which makes the block unavailable
+	pub withheld_cells: u32,
+}
+
+impl SamplingResult {
+	pub fn confidence(&self) -> f64 {
+		confidence(self.total_cells, self.withheld_cells, self.verified)
+	}
+
+	pub fn is_target_reached(&self) -> bool {
+		self.verified >= self.required
+	}
+}
+
+/// Confidence of the sampled block, if the target confidence was reached
+pub fn block_confidence(db: &impl Database, block_number: u32) -> Option<f64> {
+	db.get(SamplingResultKey(block_number))
+		.filter(SamplingResult::is_target_reached)
+		.map(|result| result.confidence())
+}
+
+/// Sampled block, with the verified cells and fetch stats of each round
+pub struct Sample {
+	pub result: SamplingResult,
+	pub cells: Vec<Cell>,
+	pub fetch_stats: Vec<FetchStats>,
+}
+
+/// Random positions of the extended matrix, which were not sampled yet
+fn random_positions(
+	dimensions: Dimensions,
+	count: u32,
+	sampled: &HashSet<Position>,
+) -> Vec<Position> {
+	let available = dimensions.extended_size() as usize - sampled.len();
+	let count = (count as usize).min(available);
+	let mut rng = thread_rng();
+	let mut positions = HashSet::new();
+	while positions.len() < count {
+		let col = rng.gen_range(0..dimensions.cols().into());
+		let row = rng.gen_range(0..dimensions.extended_rows());
+		let position = Position { row, col };
+		if !sampled.contains(&position) {
+			positions.insert(position);
+		}
+	}
+	positions.into_iter().collect()
+}
+
+pub struct Sampler {
+	cfg: SamplingConfig,
+	target_confidence: watch::Receiver<f64>,
+	results_sender: broadcast::Sender<SamplingResult>,
+}
+
+impl Sampler {
+	pub fn new(cfg: SamplingConfig) -> Self {
+		let (results_sender, _) = broadcast::channel(1 << 7);
+		let (_, target_confidence) = watch::channel(cfg.target_confidence);
+		Self {
+			cfg,
+			target_confidence,
+			results_sender,
+		}
+	}
+
+	/// Sets the channel of the target confidence, so it can be changed at runtime
+	pub fn with_target_confidence(mut self, target_confidence: watch::Receiver<f64>) -> Self {
+		self.target_confidence = target_confidence;
+		self
+	}
+
+	/// Subscribes to the sampling results of the blocks
+	pub fn subscribe(&self) -> broadcast::Receiver<SamplingResult> {
+		self.results_sender.subscribe()
+	}
+
+	/// Samples the block until the target confidence is reached, or the maximum number of rounds is exhausted
+	#[instrument(skip_all, name = "cell_sampling", fields(block_number = block_number))]
+	pub async fn sample(
+		&self,
+		db: &impl Database,
+		network_client: &impl network::Client,
+		block_number: u32,
+		block_hash: H256,
+		dimensions: Dimensions,
+		commitments: &[[u8; config::COMMITMENT_SIZE]],
+	) -> Result<Sample> {
+		let cfg = SamplingConfig {
+			target_confidence: *self.target_confidence.borrow(),
+			..self.cfg.clone()
+		};
+		let required = cell_count(dimensions, &cfg);
+		let total_cells = dimensions.extended_size();
+		let mut sampled = HashSet::new();
+		let mut cells = vec![];
+		let mut fetch_stats = vec![];
+		let mut rounds = 0;
+
+		while rounds < cfg.max_rounds.max(1) {
+			let missing = required.saturating_sub(cells.len() as u32);
+			let positions = random_positions(dimensions, missing, &sampled);
+			if positions.is_empty() {
+				break;
+			}
+			rounds += 1;
+			sampled.extend(positions.iter().cloned());
+
+			info!(
+				block_number,
+				round = rounds,
+				"cells_requested" = positions.len(),
+				"Random cells generated: {}",
+				positions.len()
+			);
+
+			let (mut fetched, unfetched, stats) = network_client
+				.fetch_verified(
+					block_number,
+					block_hash,
+					dimensions,
+					commitments,
+					&positions,
+				)
+				.instrument(info_span!("sampling_round", block_number, round = rounds))
+				.await?;
+			debug!(
+				block_number,
+				round = rounds,
+				"Sampling round verified {} cells, {} cells were not fetched",
+				fetched.len(),
+				unfetched.len()
+			);
+			cells.append(&mut fetched);
+			fetch_stats.push(stats);
+		}
+
+		let result = SamplingResult {
+			block_number,
+			required,
+			sampled: sampled.len() as u32,
+			verified: cells.len() as u32,
+			rounds,
+			total_cells,
+			withheld_cells: withheld_cells(total_cells, cfg.withheld_fraction),
+		};
+		db.put(SamplingResultKey(block_number), result.clone());
+		// there are no subscribers if sending fails
+		let _ = self.results_sender.send(result.clone());
+
+		Ok(Sample {
+			result,
+			cells,
+			fetch_stats,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data::MemoryDB;
+	use std::{
+		sync::{
+			atomic::{AtomicBool, Ordering},
+			Arc,
+		},
+		time::Duration,
+	};
+
+	fn config(target_confidence: f64) -> SamplingConfig {
+		SamplingConfig {
+			target_confidence,
+			max_rounds: 1,
+			withheld_fraction: 0.5,
+			min_cells: 1,
+			max_cells: 64,
+		}
+	}
+
+	#[test]
+	fn cell_count_for_dimensions() {
+		let small = Dimensions::new(1, 4).unwrap();
+		let large = Dimensions::new(256, 256).unwrap();
+
+		assert_eq!(cell_count(small, &config(99.0)), 5);
+		assert_eq!(cell_count(large, &config(99.0)), 7);
+		assert_eq!(cell_count(large, &config(99.99)), 14);
+		// Fewer withheld cells need more samples
+		let cfg = SamplingConfig {
+			withheld_fraction: 0.25,
+			..config(99.0)
+		};
+		assert_eq!(cell_count(large, &cfg), 17);
+		// Count is bounded by the configured limits and the matrix size
+		let cfg = SamplingConfig {
+			min_cells: 10,
+			..config(99.0)
+		};
+		assert_eq!(cell_count(small, &cfg), 8);
+		assert_eq!(cell_count(large, &cfg), 10);
+		let cfg = SamplingConfig {
+			max_cells: 4,
+			..config(99.0)
+		};
+		assert_eq!(cell_count(large, &cfg), 4);
+		assert_eq!(cell_count(small, &config(100.0)), 5);
+	}
+
+	#[test]
+	fn confidence_of_verified_cells() {
+		// 1 - 2^-n for the large matrix, with the half of the cells withheld
+		assert!((confidence(1 << 20, 1 << 19, 10) - 99.902).abs() < 0.001);
+		// Sampling without replacement, so the confidence is higher for the small matrix
+		assert_eq!(confidence(8, 4, 2), 100.0 * (1.0 - 4.0 / 8.0 * 3.0 / 7.0));
+		assert_eq!(confidence(8, 4, 5), 100.0);
+		assert_eq!(confidence(8, 4, 0), 0.0);
+	}
+
+	fn network_client() -> network::MockClient {
+		let mut network_client = network::MockClient::new();
+		let is_first_round = Arc::new(AtomicBool::new(true));
+		// First round fails to fetch one cell, next rounds fetch all cells
+		network_client
+			.expect_fetch_verified()
+			.returning(move |_, _, _, _, positions| {
+				let skip = is_first_round.swap(false, Ordering::Relaxed) as usize;
+				let fetched: Vec<_> = positions[skip..]
+					.iter()
+					.map(|&position| Cell {
+						position,
+						content: [0; 80],
+					})
+					.collect();
+				let unfetched = positions[..skip].to_vec();
+				let stats =
+					FetchStats::new(positions.len(), fetched.len(), Duration::from_secs(0), None);
+				Box::pin(async move { Ok((fetched, unfetched, stats)) })
+			});
+		network_client
+	}
+
+	#[tokio::test]
+	async fn sample_until_target_confidence() {
+		let db = MemoryDB::default();
+		let dimensions = Dimensions::new(1, 4).unwrap();
+		let sampler = Sampler::new(SamplingConfig {
+			max_rounds: 3,
+			..config(99.0)
+		});
+		let mut results = sampler.subscribe();
+
+		let sample = sampler
+			.sample(&db, &network_client(), 1, H256::zero(), dimensions, &[])
+			.await
+			.unwrap();
+		let expected = SamplingResult {
+			block_number: 1,
+			required: 5,
+			sampled: 6,
+			verified: 5,
+			rounds: 2,
+			total_cells: 8,
+			withheld_cells: 4,
+		};
+		assert_eq!(sample.result, expected);
+		assert!(sample.result.is_target_reached());
+		assert!(sample.result.confidence() >= 99.0);
+		assert_eq!(sample.fetch_stats.len(), 2);
+		assert_eq!(results.try_recv().unwrap(), expected);
+		assert_eq!(db.get(SamplingResultKey(1)), Some(expected));
+
+		let sampler = Sampler::new(SamplingConfig {
+			max_rounds: 1,
+			..config(99.0)
+		});
+		let sample = sampler
+			.sample(&db, &network_client(), 2, H256::zero(), dimensions, &[])
+			.await
+			.unwrap();
+		assert_eq!(sample.result.rounds, 1);
+		assert!(!sample.result.is_target_reached());
+	}
+
+	#[tokio::test]
+	async fn sample_with_changed_target_confidence() {
+		let db = MemoryDB::default();
+		let dimensions = Dimensions::new(256, 256).unwrap();
+		let (target_confidence, receiver) = watch::channel(99.0);
+		let sampler = Sampler::new(SamplingConfig {
+			max_rounds: 3,
+			..config(99.0)
+		})
+		.with_target_confidence(receiver);
+
+		let sample = sampler
+			.sample(&db, &network_client(), 1, H256::zero(), dimensions, &[])
+			.await
+			.unwrap();
+		assert_eq!(sample.result.required, 7);
+
+		target_confidence.send_replace(99.99);
+		let sample = sampler
+			.sample(&db, &network_client(), 2, H256::zero(), dimensions, &[])
+			.await
+			.unwrap();
+		assert_eq!(sample.result.required, 14);
+	}
+}
@@ -1,10 +1,14 @@
 //! Light (sync) client sampling and verification for blocks before latest finalized.
 //!
 //! Fetches and verifies previous blocks up to configured sync depth.
+//! Blocks are synced forward from the sync start block, or backward from the latest block.
+//! Progress is persisted, so the blocks with stored confidence are skipped after restart.
 //!
 //! # Flow
 //!
 //! * For each block, fetches block header from RPC and stores it into database
+//! * When syncing backward, waits for the finalized header of the sync range end to be verified by the light client,
+//!   and verifies that each header links to the header of the next block, starting from the verified one
 //! * Generate random cells for random data sampling
 //! * Retrieve cell proofs from a) DHT and/or b) via RPC call from the node, in that order
 //! * Verify proof using the received cells
@@ -17,37 +21,43 @@
 
 use crate::{
 	data::{
-		AchievedSyncConfidenceKey, BlockHeaderKey, Database, IsSyncedKey, LatestSyncKey,
-		VerifiedCellCountKey, VerifiedSyncHeaderKey,
+		AchievedSyncConfidenceKey, BlockHeaderKey, CanonicalHeaderKey, Database, IsSyncedKey,
+		LatestSyncKey, SamplingResultKey, VerifiedCellCountKey, VerifiedSyncHeaderKey,
 	},
 	network::{
 		self,
 		rpc::{self, Client as RpcClient},
 	},
-	types::{BlockRange, BlockVerified, SyncClientConfig},
-	utils::{calculate_confidence, extract_kate},
+	proof,
+	sampling::{self, SamplingResult},
+	types::{BlockRange, BlockVerified, SyncClientConfig, SyncDirection},
 };
 
 use async_trait::async_trait;
 use avail_subxt::{primitives::Header as DaHeader, utils::H256};
 use codec::Encode;
-use color_eyre::{
-	eyre::{eyre, WrapErr},
-	Result,
-};
-use kate_recovery::{commitments, matrix::Dimensions};
+use color_eyre::{eyre::WrapErr, Result};
 use mockall::automock;
 use sp_core::blake2_256;
-use std::{ops::Range, time::Instant};
-use tokio::sync::broadcast;
-use tracing::{error, info, warn};
+use std::{
+	ops::Range,
+	time::{Duration, Instant},
+};
+use tokio::{sync::broadcast, time};
+use tracing::{debug, error, info, warn};
+
+/// Maximum time to wait for the light client to verify the header of the sync range end
+const VERIFIED_HEADER_TIMEOUT: Duration = Duration::from_secs(120);
+const VERIFIED_HEADER_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[async_trait]
 #[automock]
 pub trait Client {
 	async fn get_header_by_block_number(&self, block_number: u32) -> Result<(DaHeader, H256)>;
+	/// Finalized header verified by the light client, `None` if it is not verified yet
+	fn get_verified_header(&self, block_number: u32) -> Option<(DaHeader, H256)>;
 	fn is_confidence_stored(&self, block_number: u32) -> bool;
-	fn store_verified_cell_count(&self, count: u32, block_number: u32);
+	fn store_sampling_result(&self, result: SamplingResult);
 	fn store_achieved_sync_confidence(&self, block_number: u32);
 	fn store_verified_sync_header(&self, block_number: u32);
 	fn store_latest_sync(&self, block_number: u32);
@@ -85,12 +95,20 @@ impl<T: Database + Sync> Client for SyncClient<T> {
 		Ok((header, hash))
 	}
 
+	fn get_verified_header(&self, block_number: u32) -> Option<(DaHeader, H256)> {
+		self.db
+			.get(CanonicalHeaderKey(block_number))
+			.map(|stored| (stored.header, stored.hash))
+	}
+
 	fn is_confidence_stored(&self, block_number: u32) -> bool {
 		self.db.get(VerifiedCellCountKey(block_number)).is_some()
 	}
 
-	fn store_verified_cell_count(&self, count: u32, block_number: u32) {
-		self.db.put(VerifiedCellCountKey(block_number), count)
+	fn store_sampling_result(&self, result: SamplingResult) {
+		self.db
+			.put(VerifiedCellCountKey(result.block_number), result.verified);
+		self.db.put(SamplingResultKey(result.block_number), result)
 	}
 
 	fn store_achieved_sync_confidence(&self, block_number: u32) {
@@ -99,8 +117,9 @@ impl<T: Database + Sync> Client for SyncClient<T> {
 			.db
 			.get(AchievedSyncConfidenceKey)
 			.unwrap_or_else(|| BlockRange::init(block_number));
-		// mutate the value
-		block_range.last = block_number;
+		// mutate the value, blocks can be synced in both directions
+		block_range.first = block_range.first.min(block_number);
+		block_range.last = block_range.last.max(block_number);
 		// store mutated value back in the DB
 		self.db.put(AchievedSyncConfidenceKey, block_range);
 	}
@@ -111,8 +130,9 @@ impl<T: Database + Sync> Client for SyncClient<T> {
 			.db
 			.get(VerifiedSyncHeaderKey)
 			.unwrap_or_else(|| BlockRange::init(block_number));
-		// mutate the value
-		block_range.last = block_number;
+		// mutate the value, blocks can be synced in both directions
+		block_range.first = block_range.first.min(block_number);
+		block_range.last = block_range.last.max(block_number);
 		// store mutated value back in the DB
 		self.db.put(VerifiedSyncHeaderKey, block_range);
 	}
@@ -139,20 +159,17 @@ async fn process_block(
 
 	info!(block_number, elapsed = ?begin.elapsed(), "Synced block header");
 
-	let (required, verified, unverified) = match extract_kate(&header.extension) {
+	let result = match proof::block_commitments(header_hash, &header.extension)? {
 		None => {
 			info!("Skipping block without header extension");
 
 			return Ok(());
 		},
-		Some((rows, cols, _, commitment)) => {
-			let dimensions =
-				Dimensions::new(rows, cols).ok_or_else(|| eyre!("Invalid dimensions"))?;
-
-			let commitments = commitments::from_slice(&commitment)?;
+		Some(block_commitments) => {
+			let dimensions = block_commitments.dimensions;
+			let commitments = &block_commitments.commitments;
 
-			// now this is in `u64`
-			let cell_count = rpc::cell_count_for_confidence(cfg.confidence);
+			let cell_count = sampling::cell_count(dimensions, &cfg.sampling);
 			let positions = rpc::generate_random_cells(dimensions, cell_count);
 
 			let (fetched, unfetched, _fetch_stats) = network_client
@@ -160,23 +177,34 @@ async fn process_block(
 					block_number,
 					header_hash,
 					dimensions,
-					&commitments,
+					commitments,
 					&positions,
 				)
 				.await?;
-			(positions.len(), fetched.len(), unfetched.len())
+			if fetched.len() < positions.len() {
+				error!(block_number, "Failed to fetch {} cells", unfetched.len());
+				return Ok(());
+			}
+			let total_cells = dimensions.extended_size();
+			SamplingResult {
+				block_number,
+				required: positions.len() as u32,
+				sampled: positions.len() as u32,
+				verified: fetched.len() as u32,
+				rounds: 1,
+				total_cells,
+				withheld_cells: sampling::withheld_cells(
+					total_cells,
+					cfg.sampling.withheld_fraction,
+				),
+			}
 		},
 	};
 
-	if required > verified {
-		error!(block_number, "Failed to fetch {} cells", unverified);
-		return Ok(());
-	}
-
-	// write verified cell count into on-disk database
-	client.store_verified_cell_count(verified.try_into()?, block_number);
+	let confidence = Some(result.confidence());
+	// write sampling result into on-disk database
+	client.store_sampling_result(result);
 
-	let confidence = Some(calculate_confidence(verified as u32));
 	let client_msg =
 		BlockVerified::try_from((header, confidence)).wrap_err("converting to message failed")?;
 
@@ -187,6 +215,23 @@ async fn process_block(
 	Ok(())
 }
 
+async fn wait_for_verified_header(
+	client: &impl Client,
+	block_number: u32,
+) -> Option<(DaHeader, H256)> {
+	let started = Instant::now();
+	loop {
+		if let Some(verified) = client.get_verified_header(block_number) {
+			return Some(verified);
+		}
+		if started.elapsed() >= VERIFIED_HEADER_TIMEOUT {
+			return None;
+		}
+		debug!(block_number, "Waiting for the block header to be verified");
+		time::sleep(VERIFIED_HEADER_POLL_INTERVAL).await;
+	}
+}
+
 /// Runs sync client.
 ///
 /// # Arguments
@@ -211,24 +256,66 @@ pub async fn run(
 		warn!("In order to process {sync_blocks_depth} blocks behind latest block, connected nodes needs to be archive nodes!");
 	}
 
-	info!("Syncing block headers for {sync_range:?}");
-	for block_number in sync_range {
+	info!(
+		direction = %cfg.direction,
+		"Syncing block headers for {sync_range:?}"
+	);
+
+	// parent hash of the next block header, which the synced header needs to match when syncing backward
+	let is_backward = cfg.direction == SyncDirection::Backward;
+	let mut next_parent_hash = None;
+	if is_backward {
+		// Header fetched from the RPC is not verified, so the chain is anchored to the finalized header verified by the light client
+		let Some((header, _)) = wait_for_verified_header(&client, sync_range.end).await else {
+			error!(
+				block_number = sync_range.end,
+				"Backward sync stopped, latest block header is not verified in {VERIFIED_HEADER_TIMEOUT:?}"
+			);
+			return;
+		};
+		next_parent_hash = Some(header.parent_hash);
+	}
+
+	let block_numbers: Box<dyn Iterator<Item = u32> + Send> = match cfg.direction {
+		SyncDirection::Forward => Box::new(sync_range),
+		SyncDirection::Backward => Box::new(sync_range.rev()),
+	};
+
+	for block_number in block_numbers {
 		// TODO: This is still an ambiguous check since data fetch can fail.
 		// We should write block status in DB explicitly.
-		if client.is_confidence_stored(block_number) {
+		// Headers of the processed blocks are still needed to verify the chain when syncing backward.
+		let is_confidence_stored = client.is_confidence_stored(block_number);
+		if is_confidence_stored && !is_backward {
 			continue;
 		};
 
 		let (header, header_hash) = match client.get_header_by_block_number(block_number).await {
 			Ok(value) => value,
 			Err(error) => {
+				if is_backward {
+					error!(block_number, "Backward sync stopped: {error:#}");
+					return;
+				}
 				error!(block_number, "Cannot process block: {error:#}");
 				continue;
 			},
 		};
 
+		if is_backward && next_parent_hash.replace(header.parent_hash) != Some(header_hash) {
+			error!(
+				block_number,
+				"Backward sync stopped, header does not link to the next block header"
+			);
+			return;
+		}
+
+		if is_confidence_stored {
+			continue;
+		}
+
 		client.store_latest_sync(block_number);
-		// TODO: Add proper header verification on sync
+		// TODO: Add proper header verification on forward sync
 		client.store_verified_sync_header(block_number);
 
 		// TODO: Should we handle unprocessed blocks differently?
@@ -256,9 +343,6 @@ pub async fn run(
 
 #[cfg(test)]
 mod tests {
-
-	use std::time::Duration;
-
 	use super::*;
 	use crate::types::{self, RuntimeConfig};
 	use avail_subxt::{
@@ -384,9 +468,9 @@ mod tests {
 			.with(eq(2))
 			.returning(|_| true);
 		mock_client
-			.expect_store_verified_cell_count()
-			.withf(move |_, block_number| *block_number == 2)
-			.returning(move |_, _| ());
+			.expect_store_sampling_result()
+			.withf(move |result| result.block_number == 2)
+			.returning(move |_| ());
 		process_block(
 			&mock_client,
 			&mock_network_client,
@@ -470,9 +554,9 @@ mod tests {
 			});
 
 		mock_client
-			.expect_store_verified_cell_count()
-			.withf(move |_, block_number| *block_number == 2)
-			.returning(move |_, _| ());
+			.expect_store_sampling_result()
+			.withf(move |result| result.block_number == 2)
+			.returning(move |_| ());
 		process_block(
 			&mock_client,
 			&mock_network_client,
@@ -484,4 +568,86 @@ mod tests {
 		.await
 		.unwrap();
 	}
+
+	#[tokio::test]
+	pub async fn test_backward_sync_stops_on_broken_chain() {
+		let (block_tx, _) = broadcast::channel::<types::BlockVerified>(10);
+		let mut cfg = SyncClientConfig::from(&RuntimeConfig::default());
+		cfg.direction = SyncDirection::Backward;
+		let mock_network_client = network::MockClient::new();
+		let mut mock_client = MockClient::new();
+
+		let latest_header = default_header();
+		let mut header = default_header();
+		header.number = 1;
+		let header_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+		// Header does not link to the latest header, so the block is not processed
+		assert_ne!(header_hash, latest_header.parent_hash);
+
+		mock_client
+			.expect_get_verified_header()
+			.with(eq(2))
+			.returning(move |_| {
+				let header = latest_header.clone();
+				let hash = Encode::using_encoded(&header, blake2_256).into();
+				Some((header, hash))
+			});
+		mock_client
+			.expect_get_header_by_block_number()
+			.with(eq(1))
+			.returning(move |_| {
+				let header = header.clone();
+				Box::pin(async move { Ok((header, header_hash)) })
+			});
+		mock_client
+			.expect_is_confidence_stored()
+			.with(eq(1))
+			.returning(|_| false);
+
+		run(mock_client, mock_network_client, cfg, 1..2, block_tx).await;
+	}
+
+	#[tokio::test]
+	pub async fn test_backward_sync_anchors_to_verified_header() {
+		let (block_tx, _) = broadcast::channel::<types::BlockVerified>(10);
+		let mut cfg = SyncClientConfig::from(&RuntimeConfig::default());
+		cfg.direction = SyncDirection::Backward;
+		let mock_network_client = network::MockClient::new();
+		let mut mock_client = MockClient::new();
+
+		let mut header = default_header();
+		header.number = 1;
+		let header_hash: H256 = Encode::using_encoded(&header, blake2_256).into();
+
+		// Synced header does not link to the verified header, regardless of the header served by the RPC
+		let verified_header = default_header();
+		assert_ne!(header_hash, verified_header.parent_hash);
+
+		mock_client
+			.expect_get_verified_header()
+			.with(eq(2))
+			.returning(move |_| {
+				let header = verified_header.clone();
+				let hash = Encode::using_encoded(&header, blake2_256).into();
+				Some((header, hash))
+			});
+		mock_client
+			.expect_get_header_by_block_number()
+			.with(eq(2))
+			.never();
+		mock_client
+			.expect_get_header_by_block_number()
+			.with(eq(1))
+			.returning(move |_| {
+				let header = header.clone();
+				Box::pin(async move { Ok((header, header_hash)) })
+			});
+		mock_client
+			.expect_is_confidence_stored()
+			.with(eq(1))
+			.returning(|_| false);
+
+		// Block is not processed, since no other calls are expected
+		run(mock_client, mock_network_client, cfg, 1..2, block_tx).await;
+	}
 }
@@ -20,10 +20,10 @@ use crate::{
 		BlockHeaderKey, Database, FinalitySyncCheckpoint, FinalitySyncCheckpointKey,
 		IsFinalitySyncedKey,
 	},
-	finality::{check_finality, ValidatorSet},
+	finality::{check_finality, validator_set_changes, ValidatorSet, ValidatorSetTracker},
 	network::rpc::{self, WrappedProof},
 	shutdown::Controller,
-	utils::filter_auth_set_changes,
+	types::FinalityCheckpointConfig,
 };
 
 #[async_trait]
@@ -208,22 +208,67 @@ async fn get_valset_at_genesis(
 	Ok(validator_set)
 }
 
-pub async fn run(client: impl Client, shutdown: Controller<String>, from_header: Header) {
-	if let Err(error) = sync(client, from_header).await {
+pub async fn run(
+	client: impl Client,
+	shutdown: Controller<String>,
+	from_header: Header,
+	trusted_checkpoint: Option<FinalityCheckpointConfig>,
+) {
+	if let Err(error) = sync(client, from_header, trusted_checkpoint).await {
 		error!("Cannot sync finality {error}");
 		let _ = shutdown.trigger_shutdown(format!("Cannot sync finality {error:#}"));
 	};
 }
 
-pub async fn sync(client: impl Client, mut from_header: Header) -> Result<()> {
+/// Returns the stored checkpoint, or the trusted one if finality is not synced past it.
+/// Hash of the trusted checkpoint block is checked against the block hash returned by the node.
+async fn checkpoint(
+	client: &impl Client,
+	trusted_checkpoint: Option<FinalityCheckpointConfig>,
+) -> Result<Option<FinalitySyncCheckpoint>> {
+	let stored_checkpoint = client.get_checkpoint();
+	let Some(trusted) = trusted_checkpoint else {
+		return Ok(stored_checkpoint);
+	};
+	// stored checkpoint number is the next block to verify
+	if let Some(stored) = stored_checkpoint.filter(|stored| stored.number > trusted.block_number) {
+		return Ok(Some(stored));
+	}
+
+	let hash = client
+		.get_block_hash(trusted.block_number)
+		.await
+		.wrap_err("Couldn't get hash of the checkpoint block")?;
+	if hash.0 != trusted.block_hash.0 {
+		return Err(eyre!(
+			"Checkpoint block {} hash {hash:?} doesn't match the trusted hash",
+			trusted.block_number
+		));
+	}
+	info!(
+		"Using trusted checkpoint at block no. {}",
+		trusted.block_number
+	);
+	Ok(Some(FinalitySyncCheckpoint {
+		number: trusted.block_number + 1,
+		set_id: trusted.set_id,
+		validator_set: trusted.validator_set,
+	}))
+}
+
+pub async fn sync(
+	client: impl Client,
+	mut from_header: Header,
+	trusted_checkpoint: Option<FinalityCheckpointConfig>,
+) -> Result<()> {
 	let gen_hash = client.get_genesis_hash().await?;
 
-	let checkpoint = client.get_checkpoint();
+	let checkpoint = checkpoint(&client, trusted_checkpoint).await?;
 
 	info!("Starting finality validation sync.");
 	let mut set_id: u64;
 	let mut curr_block_num = 1u32;
-	let mut validator_set: Vec<ed25519::Public>;
+	let validator_set: Vec<ed25519::Public>;
 	if let Some(ch) = checkpoint {
 		info!("Continuing from block no {}", ch.number);
 		set_id = ch.set_id;
@@ -248,8 +293,12 @@ pub async fn sync(client: impl Client, mut from_header: Header) -> Result<()> {
 		.get_block_hash(curr_block_num - 1)
 		.await
 		.wrap_err("Hash doesn't exist?")?;
+	let mut validator_sets = ValidatorSetTracker::new(ValidatorSet {
+		set_id,
+		validator_set,
+	});
 	loop {
-		if curr_block_num == last_block_num + 1 {
+		if curr_block_num > last_block_num {
 			info!("Finished verifying finality up to block no. {last_block_num}!");
 			break;
 		}
@@ -272,8 +321,24 @@ pub async fn sync(client: impl Client, mut from_header: Header) -> Result<()> {
 		);
 		prev_hash = from_header.using_encoded(blake2_256).into();
 
-		let next_validator_set = filter_auth_set_changes(&from_header);
-		if next_validator_set.is_empty() {
+		// Validator set which signs the block, with the pending change enacted once it is due
+		let valset = validator_sets.validator_set_at(curr_block_num).clone();
+		if valset.set_id != set_id {
+			set_id = valset.set_id;
+			client.store_checkpoint(FinalitySyncCheckpoint {
+				number: curr_block_num,
+				set_id,
+				validator_set: valset.validator_set.clone(),
+			});
+		}
+
+		let changes = validator_set_changes(&from_header);
+		// Forced changes are enacted without the finality of the announcing block,
+		// so only the blocks announcing the scheduled changes are finalized by the current set
+		if changes.iter().all(|change| change.is_forced) {
+			for change in changes {
+				validator_sets.schedule(change);
+			}
 			curr_block_num += 1;
 			continue;
 		}
@@ -291,25 +356,13 @@ pub async fn sync(client: impl Client, mut from_header: Header) -> Result<()> {
 			.await
 			.wrap_err(format!("Couldn't get header for {}", proof_block_hash))?;
 
-		let valset = ValidatorSet {
-			set_id,
-			validator_set,
-		};
 		check_finality(&valset, &proof.0.justification.0).context("Finality sync check failed")?;
 
 		trace!("Proof in block: {}", p_h.number);
+		for change in changes {
+			validator_sets.schedule(change);
+		}
 		curr_block_num += 1;
-
-		validator_set = next_validator_set[0]
-			.iter()
-			.map(|a| ed25519::Public::from_raw(a.0 .0 .0 .0))
-			.collect();
-		set_id += 1;
-		client.store_checkpoint(FinalitySyncCheckpoint {
-			number: curr_block_num,
-			set_id,
-			validator_set: validator_set.clone(),
-		});
 	}
 	// set finality synced flag in db
 	client.set_is_finality_synced(true);
@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use color_eyre::Result;
 use libp2p::{kad::Mode, Multiaddr};
 use otlp::Record;
+use tracing::{info_span, Span};
 
 pub mod metric;
 pub mod otlp;
@@ -18,6 +19,7 @@ pub enum MetricCounter {
 	EstablishedConnections,
 	IncomingPutRecord,
 	IncomingGetRecord,
+	IncomingPutRecordLimited,
 }
 
 pub trait MetricName {
@@ -37,6 +39,7 @@ impl MetricName for MetricCounter {
 			EstablishedConnections => "avail.light.established_connections",
 			IncomingPutRecord => "avail.light.incoming_put_record",
 			IncomingGetRecord => "avail.light.incoming_get_record",
+			IncomingPutRecordLimited => "avail.light.incoming_put_record_limited",
 		}
 	}
 }
@@ -66,6 +69,8 @@ pub enum MetricValue {
 	BlockConfidence(f64),
 	BlockConfidenceThreshold(f64),
 	BlockProcessingDelay(f64),
+	BlockHeadLag(u32),
+	ChainHeadHeight(u32),
 
 	DHTReplicationFactor(u16),
 
@@ -78,10 +83,24 @@ pub enum MetricValue {
 	DHTConnectedPeers(usize),
 	DHTQueryTimeout(u32),
 	DHTPingLatency(f64),
+	/// Average duration of the block DHT GET queries, in milliseconds
+	DHTGetLatency(f64),
+	/// Number of the block cells which are not fetched from the DHT, for the given failure reason
+	DHTGetFailures(&'static str, f64),
+
+	/// Time the command of the given type waited for the event loop, in milliseconds
+	P2PCommandQueueTime(&'static str, f64),
+	/// Time the event loop spent running the command of the given type, in milliseconds
+	P2PCommandExecutionTime(&'static str, f64),
 
 	RPCFetched(f64),
 	RPCFetchDuration(f64),
 	RPCCallDuration(f64),
+
+	/// Number of the cell proofs verified per second
+	ProofVerificationRate(f64),
+	/// Ratio of the commitment cache hits to all lookups
+	CommitmentCacheHitRate(f64),
 }
 
 impl MetricName for MetricValue {
@@ -93,6 +112,8 @@ impl MetricName for MetricValue {
 			BlockConfidence(_) => "avail.light.block.confidence",
 			BlockConfidenceThreshold(_) => "avail.light.block.confidence_threshold",
 			BlockProcessingDelay(_) => "avail.light.block.processing_delay",
+			BlockHeadLag(_) => "avail.light.block.head_lag",
+			ChainHeadHeight(_) => "avail.light.block.chain_head_height",
 
 			DHTReplicationFactor(_) => "avail.light.dht.replication_factor",
 			DHTFetched(_) => "avail.light.dht.fetched",
@@ -104,10 +125,18 @@ impl MetricName for MetricValue {
 			DHTConnectedPeers(_) => "avail.light.dht.connected_peers",
 			DHTQueryTimeout(_) => "avail.light.dht.query_timeout",
 			DHTPingLatency(_) => "avail.light.dht.ping_latency",
+			DHTGetLatency(_) => "avail.light.dht.get_latency",
+			DHTGetFailures(..) => "avail.light.dht.get_failures",
+
+			P2PCommandQueueTime(..) => "avail.light.p2p.command_queue_time",
+			P2PCommandExecutionTime(..) => "avail.light.p2p.command_execution_time",
 
 			RPCFetched(_) => "avail.light.rpc.fetched",
 			RPCFetchDuration(_) => "avail.light.rpc.fetch_duration",
 			RPCCallDuration(_) => "avail.light.rpc.call_duration",
+
+			ProofVerificationRate(_) => "avail.light.proof.verification_rate",
+			CommitmentCacheHitRate(_) => "avail.light.proof.commitment_cache_hit_rate",
 		}
 	}
 }
@@ -136,3 +165,9 @@ pub trait Metrics {
 	async fn update_operating_mode(&self, mode: Mode);
 	async fn update_multiaddress(&self, mode: Multiaddr);
 }
+
+/// Creates the root span of the block processing trace, starting at the header receipt.
+/// Spans of the processing stages are its children, so each block is exported as a separate trace.
+pub fn block_span(block_number: u32) -> Span {
+	info_span!(parent: None, "block", block_number)
+}
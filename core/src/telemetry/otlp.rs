@@ -12,6 +12,7 @@ use opentelemetry_api::{
 	KeyValue,
 };
 use opentelemetry_otlp::{ExportConfig, Protocol, WithExportConfig};
+use opentelemetry_sdk::{trace::Tracer, Resource};
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::sync::{Mutex, RwLock};
 
@@ -82,12 +83,33 @@ impl Metrics {
 			})?;
 		Ok(())
 	}
+
+	async fn record_attribute_f64(
+		&self,
+		name: &'static str,
+		(key, attribute): Attribute,
+		value: f64,
+	) -> Result<()> {
+		let instrument = self.meter.f64_observable_gauge(name).try_init()?;
+		let mut attributes = self.attributes().await.to_vec();
+		attributes.push(KeyValue::new(key, attribute));
+		self.meter
+			.register_callback(&[instrument.as_any()], move |observer| {
+				observer.observe_f64(&instrument, value, &attributes)
+			})?;
+		Ok(())
+	}
 }
 
+/// Key and value of the metric attribute
+type Attribute = (&'static str, &'static str);
+
 #[derive(Debug)]
 pub enum Record {
 	MaxU64(&'static str, u64),
 	AvgF64(&'static str, f64),
+	/// Average recorded separately for each value of the attribute
+	AvgF64PerAttribute(&'static str, Attribute, f64),
 }
 
 impl From<MetricValue> for Record {
@@ -102,6 +124,8 @@ impl From<MetricValue> for Record {
 			BlockConfidence(number) => AvgF64(name, number),
 			BlockConfidenceThreshold(number) => AvgF64(name, number),
 			BlockProcessingDelay(number) => AvgF64(name, number),
+			BlockHeadLag(number) => MaxU64(name, number as u64),
+			ChainHeadHeight(number) => MaxU64(name, number as u64),
 
 			DHTReplicationFactor(number) => AvgF64(name, number as f64),
 
@@ -114,10 +138,22 @@ impl From<MetricValue> for Record {
 			DHTConnectedPeers(number) => AvgF64(name, number as f64),
 			DHTQueryTimeout(number) => AvgF64(name, number as f64),
 			DHTPingLatency(number) => AvgF64(name, number),
+			DHTGetLatency(number) => AvgF64(name, number),
+			DHTGetFailures(reason, number) => AvgF64PerAttribute(name, ("reason", reason), number),
+
+			P2PCommandQueueTime(command, number) => {
+				AvgF64PerAttribute(name, ("command", command), number)
+			},
+			P2PCommandExecutionTime(command, number) => {
+				AvgF64PerAttribute(name, ("command", command), number)
+			},
 
 			RPCFetched(number) => AvgF64(name, number),
 			RPCFetchDuration(number) => AvgF64(name, number),
 			RPCCallDuration(number) => AvgF64(name, number),
+
+			ProofVerificationRate(number) => AvgF64(name, number),
+			CommitmentCacheHitRate(number) => AvgF64(name, number),
 		}
 	}
 }
@@ -151,6 +187,7 @@ fn flatten_metrics(buffer: &[Record]) -> (HashMap<&'static str, u64>, HashMap<&'
 		match value {
 			Record::MaxU64(name, number) => u64_maximums.entry(name).or_default().push(*number),
 			Record::AvgF64(name, number) => f64_averages.entry(name).or_default().push(*number),
+			Record::AvgF64PerAttribute(..) => {},
 		}
 	}
 
@@ -167,6 +204,27 @@ fn flatten_metrics(buffer: &[Record]) -> (HashMap<&'static str, u64>, HashMap<&'
 	(u64_metrics, f64_metrics)
 }
 
+/// Averages buffered per attribute metrics.
+/// Returned value is a `HashMap` where the keys are the metric names and attributes,
+/// and values are the averages of those metrics.
+fn flatten_attribute_metrics(buffer: &[Record]) -> HashMap<(&'static str, Attribute), f64> {
+	let mut averages: HashMap<(&'static str, Attribute), Vec<f64>> = HashMap::new();
+
+	for value in buffer {
+		if let Record::AvgF64PerAttribute(name, attribute, number) = value {
+			averages
+				.entry((name, *attribute))
+				.or_default()
+				.push(*number);
+		}
+	}
+
+	averages
+		.into_iter()
+		.map(|(key, v)| (key, v.iter().sum::<f64>() / v.len() as f64))
+		.collect()
+}
+
 #[async_trait]
 impl super::Metrics for Metrics {
 	/// Puts counter to the counter buffer if it is allowed.
@@ -206,6 +264,7 @@ impl super::Metrics for Metrics {
 
 		let mut metric_buffer = self.metric_buffer.lock().await;
 		let (metrics_u64, metrics_f64) = flatten_metrics(&metric_buffer);
+		let attribute_metrics = flatten_attribute_metrics(&metric_buffer);
 		metric_buffer.clear();
 
 		let attributes = self.attributes().await;
@@ -222,6 +281,10 @@ impl super::Metrics for Metrics {
 			self.record_f64(metric, value).await?;
 		}
 
+		for ((metric, attribute), value) in attribute_metrics.into_iter() {
+			self.record_attribute_f64(metric, attribute, value).await?;
+		}
+
 		Ok(())
 	}
 
@@ -247,6 +310,7 @@ fn init_counters(meter: Meter, origin: Origin) -> HashMap<&'static str, Counter<
 		MetricCounter::EstablishedConnections,
 		MetricCounter::IncomingPutRecord,
 		MetricCounter::IncomingGetRecord,
+		MetricCounter::IncomingPutRecordLimited,
 	]
 	.iter()
 	.filter(|counter| MetricCounter::is_allowed(counter, &origin))
@@ -290,6 +354,36 @@ pub fn initialize(
 	})
 }
 
+/// Initializes the tracer which exports the spans to the collector in batches.
+/// Tracer is installed as the global tracer provider, which needs to be shut down to export the remaining spans.
+pub fn tracer(endpoint: String, ot_config: OtelConfig) -> Result<Tracer> {
+	let export_config = ExportConfig {
+		endpoint,
+		timeout: Duration::from_secs(ot_config.ot_export_timeout),
+		protocol: Protocol::Grpc,
+	};
+	let trace_config =
+		opentelemetry_sdk::trace::config().with_resource(Resource::new(vec![KeyValue::new(
+			"service.name",
+			"avail_light_client",
+		)]));
+	let tracer = opentelemetry_otlp::new_pipeline()
+		.tracing()
+		.with_exporter(
+			opentelemetry_otlp::new_exporter()
+				.tonic()
+				.with_export_config(export_config),
+		)
+		.with_trace_config(trace_config)
+		.install_batch(opentelemetry_sdk::runtime::Tokio)?;
+	Ok(tracer)
+}
+
+/// Exports the remaining spans and shuts down the global tracer provider
+pub fn shutdown_tracer() {
+	global::shutdown_tracer_provider();
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -400,4 +494,30 @@ mod tests {
 		assert_eq!(m_f64.get("avail.light.block.confidence"), Some(&98.5));
 		assert_eq!(m_f64.get("avail.light.dht.connected_peers"), Some(&85.0));
 	}
+
+	#[test]
+	fn test_flatten_attribute_metrics() {
+		let buffer = vec![
+			MetricValue::P2PCommandQueueTime("PutKadRecord", 1.0),
+			MetricValue::P2PCommandQueueTime("PutKadRecord", 3.0),
+			MetricValue::P2PCommandQueueTime("GetKadRecord", 5.0),
+			MetricValue::P2PCommandExecutionTime("PutKadRecord", 0.5),
+			MetricValue::BlockConfidence(99.0),
+		];
+		let records = buffer.into_iter().map(Into::into).collect::<Vec<Record>>();
+
+		let (m_u64, m_f64) = super::flatten_metrics(&records);
+		assert!(m_u64.is_empty());
+		assert_eq!(m_f64.len(), 1);
+
+		let result = flatten_attribute_metrics(&records);
+		let queue_time = "avail.light.p2p.command_queue_time";
+		let execution_time = "avail.light.p2p.command_execution_time";
+		let put = ("command", "PutKadRecord");
+		let get = ("command", "GetKadRecord");
+		assert_eq!(result.len(), 3);
+		assert_eq!(result.get(&(queue_time, put)), Some(&2.0));
+		assert_eq!(result.get(&(queue_time, get)), Some(&5.0));
+		assert_eq!(result.get(&(execution_time, put)), Some(&0.5));
+	}
 }
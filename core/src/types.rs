@@ -1,23 +1,28 @@
 //! Shared light client structs and enums.
-#[cfg(not(feature = "kademlia-rocksdb"))]
-use crate::network::p2p::MemoryStoreConfig;
-use crate::network::p2p::{ProvidersConfig, RocksDBStoreConfig};
+use crate::network::p2p::{
+	DialBackoffConfig, MemoryStoreConfig, ProvidersConfig, RecordValidationConfig,
+	RocksDBStoreConfig,
+};
 use crate::network::rpc::Event;
-use crate::utils::{extract_app_lookup, extract_kate};
+use crate::network::FetchStrategy;
+use crate::proof;
+use crate::sampling::SamplingConfig;
+use crate::utils::extract_app_lookup;
 use avail_core::DataLookup;
 use avail_subxt::{primitives::Header as DaHeader, utils::H256};
 use codec::{Decode, Encode, Input};
-use color_eyre::{eyre::eyre, Report, Result};
-use kate_recovery::{
-	commitments,
-	matrix::{Dimensions, Partition},
+use color_eyre::{
+	eyre::{eyre, WrapErr},
+	Report, Result,
 };
-use libp2p::kad::Mode as KadMode;
+use kate_recovery::matrix::{Dimensions, Partition};
+use libp2p::kad::{Mode as KadMode, Quorum};
 use libp2p::{Multiaddr, PeerId};
-use semver::Version;
+use semver::{Version, VersionReq};
 use serde::{de::Error, Deserialize, Serialize};
 use sp_core::crypto::Ss58Codec;
 use sp_core::{blake2_256, bytes, ed25519};
+use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::num::{NonZeroU8, NonZeroUsize};
 use std::ops::Range;
@@ -31,6 +36,8 @@ use tokio_retry::strategy::{jitter, ExponentialBackoff, FibonacciBackoff};
 use tracing::{info, warn};
 
 const CELL_SIZE: usize = 32;
+/// Target block time, used to convert the deprecated block based intervals
+const BLOCK_TIME_SECS: u64 = 20;
 const PROOF_SIZE: usize = 48;
 pub const CELL_WITH_PROOF_SIZE: usize = CELL_SIZE + PROOF_SIZE;
 
@@ -71,9 +78,22 @@ pub struct BlockVerified {
 	pub confidence: Option<f64>,
 }
 
+/// Reorganization of the followed chain, detected when a block with a different hash is received for the already processed block number
+#[derive(Clone, Debug)]
+pub struct Reorg {
+	pub block_number: u32,
+	/// Hash of the orphaned block
+	pub orphaned_hash: H256,
+	/// Hash of the received block
+	pub block_hash: H256,
+	/// Numbers of the orphaned blocks, with the invalidated stored data
+	pub orphaned_blocks: Vec<u32>,
+}
+
 pub struct ClientChannels {
 	pub block_sender: broadcast::Sender<BlockVerified>,
 	pub rpc_event_receiver: broadcast::Receiver<Event>,
+	pub reorg_sender: broadcast::Sender<Reorg>,
 }
 
 impl TryFrom<(DaHeader, Option<f64>)> for BlockVerified {
@@ -87,7 +107,7 @@ impl TryFrom<(DaHeader, Option<f64>)> for BlockVerified {
 			confidence,
 		};
 
-		let Some((rows, cols, _, commitment)) = extract_kate(&header.extension) else {
+		let Some(block_commitments) = proof::block_commitments(hash, &header.extension)? else {
 			return Ok(block);
 		};
 
@@ -97,10 +117,9 @@ impl TryFrom<(DaHeader, Option<f64>)> for BlockVerified {
 
 		if !lookup.is_empty() {
 			block.extension = Some(Extension {
-				dimensions: Dimensions::new(rows, cols)
-					.ok_or_else(|| eyre!("Invalid dimensions"))?,
+				dimensions: block_commitments.dimensions,
 				lookup,
-				commitments: commitments::from_slice(&commitment)?,
+				commitments: block_commitments.commitments.clone(),
 			});
 		}
 
@@ -147,6 +166,188 @@ impl TryFrom<String> for KademliaMode {
 	}
 }
 
+/// Kademlia record store backend
+///
+/// * `Memory` - records are kept in memory and lost on restart
+/// * `RocksDB` - records are persisted in the RocksDB database
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum KademliaStoreBackend {
+	Memory,
+	RocksDB,
+}
+
+impl Display for KademliaStoreBackend {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			KademliaStoreBackend::Memory => write!(f, "memory"),
+			KademliaStoreBackend::RocksDB => write!(f, "rocksdb"),
+		}
+	}
+}
+
+impl From<KademliaStoreBackend> for String {
+	fn from(value: KademliaStoreBackend) -> Self {
+		value.to_string()
+	}
+}
+
+impl TryFrom<String> for KademliaStoreBackend {
+	type Error = color_eyre::Report;
+
+	fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+		match value.to_lowercase().as_str() {
+			"memory" => Ok(KademliaStoreBackend::Memory),
+			"rocksdb" => Ok(KademliaStoreBackend::RocksDB),
+			_ => Err(eyre!(
+				"Wrong Kademlia store backend. Expecting 'memory' or 'rocksdb'."
+			)),
+		}
+	}
+}
+
+/// Direction of the historical blocks sync
+///
+/// * `Forward` - from the sync start block up to the latest block
+/// * `Backward` - from the latest block down to the sync start block, verifying that each synced header links to the next one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum SyncDirection {
+	Forward,
+	Backward,
+}
+
+impl Display for SyncDirection {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			SyncDirection::Forward => write!(f, "forward"),
+			SyncDirection::Backward => write!(f, "backward"),
+		}
+	}
+}
+
+impl From<SyncDirection> for String {
+	fn from(value: SyncDirection) -> Self {
+		value.to_string()
+	}
+}
+
+impl TryFrom<String> for SyncDirection {
+	type Error = color_eyre::Report;
+
+	fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+		match value.to_lowercase().as_str() {
+			"forward" => Ok(SyncDirection::Forward),
+			"backward" => Ok(SyncDirection::Backward),
+			_ => Err(eyre!(
+				"Wrong sync direction. Expecting 'forward' or 'backward'."
+			)),
+		}
+	}
+}
+
+/// Security protocols offered on the transport connections, negotiated per connection
+///
+/// * `Noise` - Noise only
+/// * `Tls` - TLS 1.3 only
+/// * `NoiseAndTls` - both, with Noise preferred
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum TransportSecurity {
+	Noise,
+	Tls,
+	NoiseAndTls,
+}
+
+impl Display for TransportSecurity {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			TransportSecurity::Noise => write!(f, "noise"),
+			TransportSecurity::Tls => write!(f, "tls"),
+			TransportSecurity::NoiseAndTls => write!(f, "noise_and_tls"),
+		}
+	}
+}
+
+impl From<TransportSecurity> for String {
+	fn from(value: TransportSecurity) -> Self {
+		value.to_string()
+	}
+}
+
+impl TryFrom<String> for TransportSecurity {
+	type Error = color_eyre::Report;
+
+	fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+		match value.to_lowercase().as_str() {
+			"noise" => Ok(TransportSecurity::Noise),
+			"tls" => Ok(TransportSecurity::Tls),
+			"noise_and_tls" => Ok(TransportSecurity::NoiseAndTls),
+			_ => Err(eyre!(
+				"Wrong transport security. Expecting 'noise', 'tls' or 'noise_and_tls'."
+			)),
+		}
+	}
+}
+
+/// Number of peers required for a DHT operation to succeed
+///
+/// * `One` - single peer is sufficient
+/// * `Majority` - majority of the replication factor peers
+/// * `All` - all of the replication factor peers
+/// * `N` - given number of peers
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum DHTQuorum {
+	One,
+	Majority,
+	All,
+	N(NonZeroUsize),
+}
+
+impl From<DHTQuorum> for Quorum {
+	fn from(value: DHTQuorum) -> Self {
+		match value {
+			DHTQuorum::One => Quorum::One,
+			DHTQuorum::Majority => Quorum::Majority,
+			DHTQuorum::All => Quorum::All,
+			DHTQuorum::N(n) => Quorum::N(n),
+		}
+	}
+}
+
+impl Display for DHTQuorum {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			DHTQuorum::One => write!(f, "one"),
+			DHTQuorum::Majority => write!(f, "majority"),
+			DHTQuorum::All => write!(f, "all"),
+			DHTQuorum::N(n) => write!(f, "{n}"),
+		}
+	}
+}
+
+impl From<DHTQuorum> for String {
+	fn from(value: DHTQuorum) -> Self {
+		value.to_string()
+	}
+}
+
+impl TryFrom<String> for DHTQuorum {
+	type Error = color_eyre::Report;
+
+	fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+		match value.to_lowercase().as_str() {
+			"one" => Ok(DHTQuorum::One),
+			"majority" => Ok(DHTQuorum::Majority),
+			"all" => Ok(DHTQuorum::All),
+			n => n.parse::<NonZeroUsize>().map(DHTQuorum::N).map_err(|_| {
+				eyre!("Wrong DHT quorum. Expecting 'one', 'majority', 'all' or a positive number.")
+			}),
+		}
+	}
+}
+
 /// Client mode
 ///
 /// * `LightClient` - light client is running
@@ -167,7 +368,7 @@ impl From<Option<u32>> for Mode {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-#[serde(try_from = "String")]
+#[serde(try_from = "String", into = "String")]
 pub enum Origin {
 	Internal,
 	FatClient,
@@ -188,6 +389,12 @@ impl TryFrom<String> for Origin {
 	}
 }
 
+impl From<Origin> for String {
+	fn from(value: Origin) -> Self {
+		value.to_string()
+	}
+}
+
 impl Display for Origin {
 	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
 		f.write_str(match self {
@@ -236,7 +443,9 @@ pub mod block_matrix_partition_format {
 	where
 		D: Deserializer<'de>,
 	{
-		let value = &String::deserialize(deserializer)?;
+		let Some(value) = &Option::<String>::deserialize(deserializer)? else {
+			return Ok(None);
+		};
 		if value.is_empty() || value.to_ascii_lowercase().contains("none") {
 			return Ok(None);
 		}
@@ -280,6 +489,29 @@ impl From<&MultiaddrConfig> for (PeerId, Multiaddr) {
 	}
 }
 
+/// Bootstrap node, either with the peer ID or as a DNS address (e.g. `/dnsaddr/...`), which is resolved to the bootstrap peers
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(
+	untagged,
+	expecting = "Valid multiaddress/peer_id string, a tuple (peer_id, multiaddress) or a DNS multiaddress expected"
+)]
+pub enum BootstrapConfig {
+	Peer(MultiaddrConfig),
+	Address(Multiaddr),
+}
+
+impl From<&BootstrapConfig> for Multiaddr {
+	fn from(value: &BootstrapConfig) -> Self {
+		match value {
+			BootstrapConfig::Peer(peer) => {
+				let (peer_id, address) = peer.into();
+				address.with_p2p(peer_id).unwrap_or_else(|address| address)
+			},
+			BootstrapConfig::Address(address) => address.clone(),
+		}
+	}
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum SecretKey {
@@ -297,6 +529,59 @@ pub enum RetryConfig {
 	Fibonacci(FibonacciConfig),
 }
 
+/// Per-peer rate limit of the inbound Kademlia PUT requests
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InboundRateLimitConfig {
+	/// Maximum number of inbound requests per peer per second, requests above the limit are dropped
+	pub requests_per_second: u32,
+	/// Number of seconds in which the peer exceeded the limit, after which the peer is blocked
+	pub max_violations: u32,
+	/// Duration of the temporary peer block (in seconds)
+	pub block_duration: u64,
+}
+
+/// Rate limit of the kate RPC queries sent to the full node
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RpcRateLimitConfig {
+	/// Maximum number of queries per second
+	pub requests_per_second: u32,
+	/// Maximum number of concurrent queries
+	pub max_in_flight: usize,
+}
+
+/// Webhook alerts on the availability failures
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlertsConfig {
+	/// URL to which the alerts are posted as JSON
+	pub webhook_url: String,
+	/// Secret used to sign the alert payloads with HMAC-SHA256, payloads are not signed if not set
+	pub secret: Option<String>,
+	/// Confidence below which the processed block counts towards the low confidence alert
+	pub confidence_threshold: f64,
+	/// Number of the consecutive blocks below the confidence threshold which triggers the alert, 0 disables the alert
+	pub low_confidence_blocks: u32,
+	/// Number of the peers in the routing table below which the alert is triggered, 0 disables the alert
+	pub min_dht_peers: usize,
+	/// Duration for which the RPC node is unreachable before the alert is triggered (in seconds), 0 disables the alert
+	pub rpc_unreachable_timeout: u64,
+	/// Interval of the routing table and the RPC node checks (in seconds)
+	pub check_interval: u64,
+	/// Number of the retries of the failed webhook requests
+	pub retries: usize,
+}
+
+/// Trusted finality checkpoint, from which the finality is synced instead of the genesis
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FinalityCheckpointConfig {
+	pub block_number: u32,
+	/// Hash of the checkpoint block, the block hash returned by the node has to match it
+	pub block_hash: H256,
+	/// ID of the validator set which finalizes the blocks after the checkpoint
+	pub set_id: u64,
+	/// SS58 encoded GRANDPA keys of the validator set which finalizes the blocks after the checkpoint
+	pub validator_set: Vec<ed25519::Public>,
+}
+
 impl IntoIterator for RetryConfig {
 	type Item = Duration;
 	type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -335,6 +620,20 @@ pub struct FibonacciConfig {
 	pub retries: usize,
 }
 
+/// Role of the API client. Admin role is also allowed to use the read-only endpoints.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+	ReadOnly,
+	Admin,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ApiKey {
+	pub key: String,
+	pub role: Role,
+}
+
 /// Representation of a configuration used by this project.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(default)]
@@ -343,6 +642,11 @@ pub struct RuntimeConfig {
 	pub http_server_host: String,
 	/// Light client HTTP server port (default: 7007).
 	pub http_server_port: u16,
+	/// API keys with the `read_only` or `admin` role, sent as bearer tokens.
+	/// If any of the API keys or the JWT secret is set, all endpoints except `/health` and `/ready` require authentication (default: []).
+	pub api_keys: Vec<ApiKey>,
+	/// Secret for verifying HS256 signed JWTs with the `role` and optional `exp` claims, sent as bearer tokens (default: None).
+	pub api_jwt_secret: Option<String>,
 	/// Secret key for libp2p keypair. Can be either set to `seed` or to `key`.
 	/// If set to seed, keypair will be generated from that seed.
 	/// If set to key, a valid ed25519 private key must be provided, else the client will fail
@@ -351,6 +655,16 @@ pub struct RuntimeConfig {
 	/// P2P service port (default: 37000).
 	pub port: u16,
 	pub ws_transport_enable: bool,
+	/// Security protocols offered on the transport connections: noise, tls or noise_and_tls (default: noise).
+	/// Relayed connections are always secured with Noise.
+	pub transport_security: TransportSecurity,
+	/// Pre-shared key for running in a private network, only peers with the same key can connect.
+	/// Can be set either to a hex encoded 32 byte key or to a path of a PSK file (default: None).
+	/// Private network is supported only with TCP transport.
+	pub pnet_psk: Option<String>,
+	/// Path of the Kademlia routing table snapshot, stored on shutdown and loaded on startup (default: None).
+	/// If not set, routing table is rebuilt from bootstrap nodes on each start.
+	pub routing_table_snapshot_path: Option<String>,
 	/// Configures AutoNAT behaviour to reject probes as a server for clients that are observed at a non-global ip address (default: false)
 	pub autonat_only_global_ips: bool,
 	/// AutoNat throttle period for re-using a peer as server for a dial-request. (default: 1 sec)
@@ -362,20 +676,55 @@ pub struct RuntimeConfig {
 	/// AutoNat on init delay before starting the fist probe. (default: 5 sec)
 	pub autonat_boot_delay: u64,
 	/// Vector of Light Client bootstrap nodes, used to bootstrap DHT. If not set, light client acts as a bootstrap node, waiting for first peer to connect for DHT bootstrap (default: empty).
-	pub bootstraps: Vec<MultiaddrConfig>,
-	/// Defines a period of time in which periodic bootstraps will be repeated. (default: 300 sec)
+	/// Besides the peer addresses, `/dns`, `/dns4`, `/dns6` and `/dnsaddr` addresses are resolved to the bootstrap peers.
+	pub bootstraps: Vec<BootstrapConfig>,
+	/// Defines a period of time in which periodic bootstraps will be repeated. (default: 3600 sec)
 	pub bootstrap_period: u64,
+	/// Interval in which the number of peers in the routing table is checked. (default: 60 sec)
+	pub routing_table_check_interval: u64,
+	/// Number of peers in the routing table below which the bootstrap is repeated before the bootstrap period expires. (default: 10)
+	pub routing_table_min_peers: usize,
+	/// Interval in which the DNS bootstrap addresses are resolved again, 0 disables re-resolution (default: 3600 sec).
+	pub bootstrap_dns_refresh_interval: u64,
 	pub operation_mode: KademliaMode,
 	/// Sets the automatic Kademlia server mode switch (default: true)
 	pub automatic_server_mode: bool,
 	/// Vector of Relay nodes, which are used for hole punching
 	pub relays: Vec<MultiaddrConfig>,
+	/// Addresses to listen on, using the configured transport (e.g. TCP on multiple interfaces or ports).
+	/// If not set, node listens on all interfaces on the configured port (default: empty).
+	pub listen_addresses: Vec<Multiaddr>,
+	/// Known public addresses of the node (e.g. behind static NAT or port forwarding), advertised without AutoNAT or UPnP confirmation (default: empty).
+	pub external_addresses: Vec<Multiaddr>,
+	/// Maps the listening port on the gateway with PCP or NAT-PMP, when no UPnP gateway is found (default: false).
+	pub nat_pmp_fallback: bool,
+	/// Enables mDNS discovery of the peers in the local network, can be toggled at runtime (default: true).
+	pub mdns_enabled: bool,
 	/// WebSocket endpoint of full node for subscribing to latest header, etc (default: [ws://127.0.0.1:9944]).
 	pub full_node_ws: Vec<String>,
+	/// Runs without the full node, receiving block headers from peers over gossipsub and fetching data from DHT only (default: false).
+	/// Headers are finality verified from the stored or the trusted `finality_checkpoint`, which is required on the first start.
+	/// Historical sync, finality sync and transaction submission are disabled.
+	pub dht_only: bool,
+	/// Interval in seconds between health checks of the full nodes, used to fail over from the unhealthy ones.
+	/// Health checks are disabled if set to 0 (default: 60).
+	pub rpc_health_check_interval: u64,
+	/// Maximum number of finalized blocks missed during the RPC outage, which are backfilled by the subscriptions. Larger gaps are synced by the historical sync, if it is enabled (default: 100).
+	pub max_backfill_blocks: u32,
+	/// Fetches block hashes and cell proofs from two full nodes and compares them, useful without a trusted full node (default: false).
+	pub rpc_cross_check: bool,
+	/// Rate limit of the cell proof queries sent to the full node, e.g. when many cells fall back from DHT to RPC.
+	/// If not set, queries are not limited (default: None).
+	pub rpc_rate_limit: Option<RpcRateLimitConfig>,
+	/// Webhook alerts on the low confidence, the depleted routing table and the unreachable RPC node.
+	/// If not set, alerts are not sent (default: None).
+	pub alerts: Option<AlertsConfig>,
 	/// Genesis hash of the network to be connected to. Set to a string beginning with "DEV" to connect to any network.
 	pub genesis_hash: String,
 	/// If set, application client is started with given app_id (default: None).
 	pub app_id: Option<u32>,
+	/// Additional application IDs followed by the application client, along with the `app_id` (default: []).
+	pub app_ids: Vec<u32>,
 	/// Confidence threshold, used to calculate how many cells need to be sampled to achieve desired confidence (default: 92.0).
 	pub confidence: f64,
 	/// File system path where RocksDB used by light client, stores its data.
@@ -385,28 +734,82 @@ pub struct RuntimeConfig {
 	pub origin: Origin,
 	/// If set to true, logs are displayed in JSON format, which is used for structured logging. Otherwise, plain text format is used (default: false).
 	pub log_format_json: bool,
+	/// Log levels of the modules, overriding the log level, e.g. `{ "avail_light_core::network::p2p" = "debug" }`.
+	/// Modules are matched by the log target prefix, so the levels of the dependencies can be set as well (default: {}).
+	pub log_module_levels: HashMap<String, String>,
 	/// OpenTelemetry Collector endpoint (default: `http://otelcollector.avail.tools:4317`)
 	pub ot_collector_endpoint: String,
 	pub ot_export_period: u64,
 	pub ot_export_timeout: u64,
-	pub ot_flush_block_interval: u32,
+	/// Period in which the metrics are flushed to the collector, 0 disables flushing (default: 300 sec).
+	pub ot_flush_period: u64,
+	// TODO: Deprecated in favor of `ot_flush_period`, remove it once it is safe
+	pub ot_flush_block_interval: Option<u64>,
+	/// If set to true, traces of the block processing are exported to the OpenTelemetry Collector (default: false).
+	pub ot_traces: bool,
 	pub total_memory_gb_threshold: f64,
 	pub num_cpus_threshold: usize,
+	/// Percentage of used system memory above which publishing to the DHT is paused (default: 90.0).
+	pub memory_pressure_threshold: f64,
+	/// Percentage of used disk space on the `avail_path` disk above which publishing to the DHT is paused (default: 95.0).
+	pub disk_pressure_threshold: f64,
+	/// Percentage of `max_kad_record_number` records in the local store above which publishing to the DHT is paused (default: 95.0).
+	pub store_pressure_threshold: f64,
 	/// Disables fetching of cells from RPC, set to true if client expects cells to be available in DHT (default: false).
 	pub disable_rpc: bool,
+	/// Strategy of fetching the cells: `dht_first`, `rpc_first`, `race` or `dht_only`. Overridden by `disable_rpc` (default: dht_first).
+	pub fetch_strategy: FetchStrategy,
 	/// Maximum number of parallel tasks spawned for GET and PUT operations on DHT (default: 20).
 	pub dht_parallelization_limit: usize,
+	/// Upper bound of the adaptive DHT GET parallelization. If greater than `dht_parallelization_limit`, the number of parallel GET queries
+	/// starts from `dht_parallelization_limit`, and is increased on fast and successful fetches and halved on slow or failing ones.
+	/// If set to 0, the parallelization limit is static (default: 0).
+	pub dht_max_parallelization_limit: usize,
+	/// Maximum time spent fetching the cells of the latest block from DHT, cells not fetched in time are fetched from RPC.
+	/// If set to 0, fetching is not bounded (default: 20 sec).
+	pub dht_fetch_timeout: u64,
+	/// Number of verified cells kept in the local cache, used to answer repeated cell fetches without querying the DHT.
+	/// If set to 0, the cache is disabled (default: 1024).
+	pub dht_cell_cache_size: usize,
+	/// Number of the blocks with the commitments and dimensions kept in the verification cache.
+	/// If set to 0, the cache is disabled (default: 128).
+	pub commitment_cache_size: usize,
+	/// Maximum number of sampling rounds per block. Each round samples new random cells in place of the cells
+	/// which were not fetched or verified, until the `confidence` is reached (default: 3).
+	pub sampling_max_rounds: u32,
+	/// Minimal fraction of the extended matrix cells which needs to be withheld to make the block unavailable,
+	/// used to calculate the number of the sampled cells for the target confidence (default: 0.5).
+	pub sampling_withheld_fraction: f64,
+	/// Minimum number of the cells to sample per block (default: 1).
+	pub sampling_min_cells: u32,
+	/// Maximum number of the cells to sample per block (default: 64).
+	pub sampling_max_cells: u32,
 	/// Number of parallel queries for cell fetching via RPC from node (default: 8).
 	pub query_proof_rpc_parallel_tasks: usize,
+	/// Number of threads used for the parallel cell proof verification.
+	/// If not set, one thread per available core is used (default: None).
+	pub proof_verification_threads: Option<usize>,
 	/// Number of seconds to postpone block processing after block finalized message arrives (default: 20).
 	pub block_processing_delay: Option<u32>,
+	/// Number of blocks the latest verified block can lag behind the chain head before a warning is emitted (default: 5).
+	pub head_lag_threshold: u32,
+	/// Minimum number of peers in the routing table for the client to be reported as ready on `/ready` (default: 1).
+	pub ready_min_peers: usize,
+	/// If set to true, light client re-inserts verified cells into the DHT when in Kademlia server mode (default: false).
+	pub replication: bool,
 	/// Fraction and number of the block matrix part to fetch (e.g. 2/20 means second 1/20 part of a matrix) (default: None)
 	#[serde(with = "block_matrix_partition_format")]
 	pub block_matrix_partition: Option<Partition>,
 	/// Starting block of the syncing process. Omitting it will disable syncing. (default: None).
 	pub sync_start_block: Option<u32>,
+	/// Direction of the syncing process, `forward` from the starting block or `backward` from the latest block. (default: forward).
+	/// Backward sync verifies that each synced header links to the header of the next block, starting from the latest finalized block.
+	pub sync_direction: SyncDirection,
 	/// Enable or disable synchronizing finality. If disabled, finality is assumed to be verified until the starting block at the point the LC is started and is only checked for new blocks. (default: true)
 	pub sync_finality_enable: bool,
+	/// Trusted checkpoint from which the finality is synced, instead of the genesis.
+	/// Finality of the blocks before the checkpoint is not verified. If not set, finality is synced from the genesis (default: None).
+	pub finality_checkpoint: Option<FinalityCheckpointConfig>,
 	/// Maximum number of cells per request for proof queries (default: 30).
 	pub max_cells_per_rpc: Option<usize>,
 	/// Threshold for the number of cells fetched via DHT for the app client (default: 5000)
@@ -419,16 +822,47 @@ pub struct RuntimeConfig {
 	/// value - not greater than 1hr.
 	/// Record TTL, publication and replication intervals are co-dependent, meaning that TTL >> publication_interval >> replication_interval.
 	pub kad_record_ttl: u64,
+	/// Time-to-live for DHT row entries in seconds. If not set, `kad_record_ttl` is used (default: None).
+	/// Rows are only needed by app clients, so they can be kept for a shorter window than the cells.
+	pub kad_row_record_ttl: Option<u64>,
 	/// Sets the (re-)publication interval of stored records in seconds. (default: 12h).
 	/// Default value is set for light clients. Fat client value needs to be inferred from the TTL value.
 	/// This interval should be significantly shorter than the record TTL, to ensure records do not expire prematurely.
+	/// If set to 0, periodic publication is disabled.
 	pub publication_interval: u32,
 	/// Sets the (re-)replication interval for stored records in seconds. (default: 3h).
 	/// Default value is set for light clients. Fat client value needs to be inferred from the TTL and publication interval values.
 	/// This interval should be significantly shorter than the publication interval, to ensure persistence between re-publications.
 	pub replication_interval: u32,
-	/// The replication factor determines to how many closest peers a record is replicated. (default: 20).
+	/// Sets the window in seconds before record expiry, in which locally held records are republished to the DHT. (default: 0).
+	/// Records are checked every half of the window, so they are republished at least once before they expire.
+	/// If set to 0, automatic republish is disabled.
+	pub record_republish_window: u32,
+	/// Maximum number of records republished on each check, records expiring first are republished first,
+	/// so the republish is spread over the subsequent checks. If set to 0, the number is not limited (default: 10000).
+	pub record_republish_limit: usize,
+	/// The replication factor determines to how many closest peers a record is replicated. (default: 5).
 	pub replication_factor: u16,
+	/// Number of peers that must store a record for the DHT PUT to succeed: one, majority, all or a number of peers (default: one).
+	pub dht_put_quorum: DHTQuorum,
+	/// Number of peers that must return a record for the DHT GET to succeed: one, majority, all or a number of peers (default: one).
+	/// Majority and all are calculated from the replication factor.
+	pub dht_get_quorum: DHTQuorum,
+	/// Maximum number of DHT PUT queries in flight, the rest are queued until the running ones finish.
+	/// If set to 0, the number of PUT queries in flight is not limited (default: 1000).
+	pub dht_put_in_flight_limit: usize,
+	/// Retries of DHT PUT queries failed with quorum failure or timeout, with exponential or fibonacci backoff between the retries.
+	/// Failed query is counted as error only when there are no retries left. If not set, failed queries are not retried (default: None).
+	pub put_retry: Option<RetryConfig>,
+	/// Per-peer rate limit of the inbound DHT PUT requests. Peers repeatedly exceeding the limit are temporarily blocked.
+	/// If not set, inbound requests are not limited (default: None).
+	pub inbound_rate_limit: Option<InboundRateLimitConfig>,
+	/// Enables provider record mode, where fat clients provide block cells instead of storing them into the DHT,
+	/// and light clients fetch cells directly from the block providers (default: false).
+	pub dht_provider_mode: bool,
+	/// Network prefix of the DHT record keys, used to separate records of different networks (e.g. mainnet, turing) (default: None).
+	/// Records with mismatched prefix are rejected by the Kademlia store.
+	pub network_prefix: Option<String>,
 	/// Sets the amount of time to keep connections alive when they're idle. (default: 30s).
 	/// NOTE: libp2p default value is 10s, but because of Avail block time of 20s the value has been increased
 	pub connection_idle_timeout: u64,
@@ -436,23 +870,83 @@ pub struct RuntimeConfig {
 	pub task_command_buffer_size: usize,
 	pub per_connection_event_buffer_size: usize,
 	pub dial_concurrency_factor: u8,
-	/// Sets the timeout for a single Kademlia query. (default: 60s).
-	pub store_pruning_interval: u32,
-	/// Sets the allowed level of parallelism for iterative Kademlia queries. (default: 3).
+	/// Maximum number of established incoming connections. If not set, incoming connections are not limited (default: None).
+	pub max_established_incoming: Option<u32>,
+	/// Maximum number of established outgoing connections. If not set, outgoing connections are not limited (default: None).
+	pub max_established_outgoing: Option<u32>,
+	/// Maximum number of pending outgoing connections (dials). If not set, pending dials are not limited (default: None).
+	pub max_pending_outgoing: Option<u32>,
+	/// Yamux receive window size of a stream (in bytes). If not set, libp2p default is used (default: None).
+	pub yamux_receive_window_size: Option<u32>,
+	/// Maximum number of bytes buffered per yamux stream. If not set, libp2p default is used (default: None).
+	pub yamux_max_buffer_size: Option<usize>,
+	/// Maximum number of yamux streams per connection. If not set, libp2p default is used (default: None).
+	pub yamux_max_num_streams: Option<usize>,
+	/// Dials to a peer are backed off after a failed dial, starting with this backoff and doubling it on each consecutive failure. (default: 10 sec).
+	pub dial_backoff_initial: u64,
+	/// Maximum backoff of the dials to a repeatedly unreachable peer. (default: 3600 sec).
+	pub dial_backoff_max: u64,
+	/// Number of consecutive failed dials after which the peer is removed from the routing table. If set to 0, peers are not removed (default: 5).
+	pub max_dial_failures: u32,
+	/// Identify agent version prefixes of the peers allowed in the routing table, e.g. `avail-light-client/light-client`. If empty, all agents are allowed (default: []).
+	pub allowed_agent_prefixes: Vec<String>,
+	/// Semver requirement on the release version of the peers allowed in the routing table, e.g. `>=1.9.0, <2.0.0`. If not set, all releases are allowed (default: None).
+	pub allowed_agent_versions: Option<String>,
+	/// Disconnects the peers whose agent version is not allowed, instead of only keeping them out of the routing table (default: false).
+	pub disconnect_disallowed_agents: bool,
+	/// Period in which the expired records are pruned from the Kademlia store, 0 disables pruning (default: 3600 sec).
+	pub store_pruning_period: u64,
+	// TODO: Deprecated in favor of `store_pruning_period`, remove it once it is safe
+	pub store_pruning_interval: Option<u64>,
+	/// Period in which the unused capacity of the Kademlia store is released, 0 disables shrinking (default: 60 sec).
+	pub kademlia_shrink_period: u64,
+	/// Period in which the automatic Kademlia server mode switch is re-evaluated (default: 60 sec).
+	pub kademlia_mode_check_period: u64,
+	/// Maximum random delay added to the maintenance periods, as a fraction of the period (default: 0.1).
+	pub maintenance_jitter: f64,
+	/// Period for which the node events are kept in the ledger, 0 keeps them forever (default: 604800 sec).
+	pub ledger_retention: u64,
+	/// Number of the latest finalized headers and processed block hashes kept in the store, 0 keeps all of them (default: 14400).
+	pub header_retention: u32,
+	/// Sets the timeout for a single Kademlia query. (default: 10s).
 	pub query_timeout: u32,
-	/// Sets the Kademlia record store pruning interval in blocks (default: 180).
+	/// Sets the allowed level of parallelism (alpha) for iterative Kademlia queries. (default: 3).
 	pub query_parallelism: u16,
+	/// Sets the maximum size of a Kademlia packet, in bytes. (default: 16384).
+	/// Packets carrying the records need to fit the record values, so it should be larger than `max_kad_record_size`.
+	pub kad_max_packet_size: u64,
 	/// Sets the Kademlia caching strategy to use for successful lookups. (default: 1).
 	/// If set to 0, caching is disabled.
 	pub caching_max_peers: u16,
 	/// Require iterative queries to use disjoint paths for increased resiliency in the presence of potentially adversarial nodes. (default: false).
 	pub disjoint_query_paths: bool,
+	/// Kademlia record store backend, `memory` or `rocksdb`. (default: rocksdb).
+	pub kad_store_backend: KademliaStoreBackend,
 	/// The maximum number of records. (default: 2400000).
 	/// The default value has been calculated to sustain ~1hr worth of cells, in case of blocks with max sizes being produces in 20s block time for fat clients
 	/// (256x512) * 3 * 60
 	pub max_kad_record_number: u64,
 	/// The maximum size of record values, in bytes. (default: 8192).
 	pub max_kad_record_size: u64,
+	/// The maximum total size of record values in the in-memory store, in bytes. If set to 0, the total size is not limited. (default: 0).
+	pub max_kad_store_bytes: u64,
+	/// Evict records closest to expiration when the in-memory store is full, instead of rejecting new records. (default: false).
+	pub kad_store_eviction: bool,
+	/// Compress records stored in the RocksDB store with zstd. (default: false).
+	pub kad_store_compression: bool,
+	/// Validate keys and value sizes of the cell and row records before storing them. (default: true).
+	pub kad_record_validation: bool,
+	/// Maximum distance in blocks between the stored record block and the latest finalized block. (default: 0).
+	/// Checked only if record validation is enabled. If set to 0, block numbers are not checked.
+	pub kad_record_recency_window: u32,
+	/// Verify KZG proofs of the cells received by DHT PUT on fat clients, before storing them. (default: false).
+	/// Verification is CPU intensive, peers sending the cells with invalid proofs are blocked.
+	pub verify_inbound_cell_proofs: bool,
+	/// Sign the published DHT records with the node keypair, so the fetching clients can authenticate the publisher. (default: false).
+	pub sign_dht_records: bool,
+	/// Reject the fetched DHT records which are not signed by their publishers, or have invalid signatures. (default: false).
+	/// Records with invalid signatures are rejected regardless of this setting.
+	pub require_signed_dht_records: bool,
 	/// The maximum number of provider records for which the local node is the provider. (default: 1024).
 	pub max_kad_provided_keys: u64,
 	/// Set the configuration based on which the retries will be orchestrated, max duration [in seconds] between retries and number of tries.
@@ -466,6 +960,9 @@ pub struct RuntimeConfig {
 	#[cfg(feature = "crawl")]
 	#[serde(flatten)]
 	pub crawl: crate::crawl_client::CrawlConfig,
+	#[cfg(feature = "grpc")]
+	#[serde(flatten)]
+	pub grpc: crate::api::grpc::GrpcConfig,
 	/// Client alias for use in logs and metrics
 	pub client_alias: Option<String>,
 }
@@ -474,6 +971,40 @@ impl RuntimeConfig {
 	pub fn is_fat_client(&self) -> bool {
 		self.block_matrix_partition.is_some()
 	}
+
+	/// Upper bound of the adaptive DHT GET parallelization, not set if the parallelization limit is static
+	pub fn dht_max_parallelization_limit(&self) -> Option<usize> {
+		(self.dht_max_parallelization_limit > self.dht_parallelization_limit)
+			.then_some(self.dht_max_parallelization_limit)
+	}
+
+	/// Time-to-live for DHT row entries in seconds, falls back to the cell TTL
+	pub fn row_record_ttl(&self) -> u64 {
+		self.kad_row_record_ttl.unwrap_or(self.kad_record_ttl)
+	}
+
+	/// Converts the deprecated block based intervals into the periods in seconds, using the target block time
+	pub fn convert_deprecated_intervals(&mut self) {
+		if let Some(blocks) = self.ot_flush_block_interval.take() {
+			warn!("Using deprecated configuration parameter `ot_flush_block_interval`, use `ot_flush_period` instead.");
+			self.ot_flush_period = blocks * BLOCK_TIME_SECS;
+		}
+		if let Some(blocks) = self.store_pruning_interval.take() {
+			warn!("Using deprecated configuration parameter `store_pruning_interval`, use `store_pruning_period` instead.");
+			self.store_pruning_period = blocks * BLOCK_TIME_SECS;
+		}
+	}
+
+	/// Application IDs followed by the application client, starting with the `app_id`
+	pub fn app_ids(&self) -> Vec<u32> {
+		let mut app_ids = self.app_id.into_iter().collect::<Vec<_>>();
+		for &app_id in &self.app_ids {
+			if !app_ids.contains(&app_id) {
+				app_ids.push(app_id);
+			}
+		}
+		app_ids
+	}
 }
 
 pub struct Delay(pub Option<Duration>);
@@ -482,6 +1013,10 @@ pub struct Delay(pub Option<Duration>);
 pub struct LightClientConfig {
 	pub confidence: f64,
 	pub block_processing_delay: Delay,
+	pub head_lag_threshold: u32,
+	pub replication: bool,
+	pub app_ids: Vec<u32>,
+	pub header_retention: u32,
 }
 
 impl Delay {
@@ -501,6 +1036,10 @@ impl From<&RuntimeConfig> for LightClientConfig {
 		LightClientConfig {
 			confidence: val.confidence,
 			block_processing_delay: Delay(block_processing_delay),
+			head_lag_threshold: val.head_lag_threshold,
+			replication: val.replication,
+			app_ids: val.app_ids(),
+			header_retention: val.header_retention,
 		}
 	}
 }
@@ -515,6 +1054,8 @@ pub struct FatClientConfig {
 	pub block_processing_delay: Delay,
 	pub block_matrix_partition: Option<Partition>,
 	pub max_cells_per_rpc: usize,
+	pub dht_provider_mode: bool,
+	pub header_retention: u32,
 }
 
 impl From<&RuntimeConfig> for FatClientConfig {
@@ -532,6 +1073,8 @@ impl From<&RuntimeConfig> for FatClientConfig {
 			block_processing_delay: Delay(block_processing_delay),
 			block_matrix_partition: val.block_matrix_partition,
 			max_cells_per_rpc: val.max_cells_per_rpc.unwrap_or(30),
+			dht_provider_mode: val.dht_provider_mode,
+			header_retention: val.header_retention,
 		}
 	}
 }
@@ -540,16 +1083,32 @@ impl From<&RuntimeConfig> for FatClientConfig {
 pub struct LibP2PConfig {
 	pub secret_key: Option<SecretKey>,
 	pub port: u16,
+	pub transport_security: TransportSecurity,
+	pub pnet_psk: Option<String>,
+	pub routing_table_snapshot_path: Option<String>,
+	pub put_retry: Option<RetryConfig>,
+	pub inbound_rate_limit: Option<InboundRateLimitConfig>,
 	pub identify: IdentifyConfig,
 	pub autonat: AutoNATConfig,
 	pub kademlia: KademliaConfig,
 	pub relays: Vec<(PeerId, Multiaddr)>,
-	pub bootstrap_interval: Duration,
+	pub external_addresses: Vec<Multiaddr>,
+	pub nat_pmp_fallback: bool,
+	pub mdns_enabled: bool,
 	pub connection_idle_timeout: Duration,
 	pub max_negotiating_inbound_streams: usize,
 	pub task_command_buffer_size: NonZeroUsize,
 	pub per_connection_event_buffer_size: usize,
 	pub dial_concurrency_factor: NonZeroU8,
+	pub max_established_incoming: Option<u32>,
+	pub max_established_outgoing: Option<u32>,
+	pub max_pending_outgoing: Option<u32>,
+	pub yamux_receive_window_size: Option<u32>,
+	pub yamux_max_buffer_size: Option<usize>,
+	pub yamux_max_num_streams: Option<usize>,
+	pub dial_backoff: DialBackoffConfig,
+	pub max_dial_failures: u32,
+	pub agent_policy: AgentPolicy,
 	pub genesis_hash: String,
 }
 
@@ -569,30 +1128,40 @@ impl From<&LibP2PConfig> for libp2p::kad::Config {
 		let mut kad_cfg = libp2p::kad::Config::default();
 		kad_cfg
 			.set_publication_interval(cfg.kademlia.publication_interval)
+			// provided blocks expire together with the cell records
+			.set_provider_record_ttl(Some(cfg.kademlia.kad_record_ttl))
+			.set_provider_publication_interval(cfg.kademlia.publication_interval)
 			.set_replication_interval(cfg.kademlia.record_replication_interval)
 			.set_replication_factor(cfg.kademlia.record_replication_factor)
 			.set_query_timeout(cfg.kademlia.query_timeout)
 			.set_parallelism(cfg.kademlia.query_parallelism)
-			.set_caching(libp2p::kad::Caching::Enabled {
-				max_peers: cfg.kademlia.caching_max_peers,
+			.set_max_packet_size(cfg.kademlia.max_packet_size)
+			.set_caching(match cfg.kademlia.caching_max_peers {
+				0 => libp2p::kad::Caching::Disabled,
+				max_peers => libp2p::kad::Caching::Enabled { max_peers },
 			})
 			.disjoint_query_paths(cfg.kademlia.disjoint_query_paths)
 			.set_record_filtering(libp2p::kad::StoreInserts::FilterBoth)
+			// peers are inserted into the routing table only after the identify checks
+			.set_kbucket_inserts(libp2p::kad::BucketInserts::Manual)
 			.set_protocol_names(vec![kademlia_protocol_name]);
 		kad_cfg
 	}
 }
 
-#[cfg(not(feature = "kademlia-rocksdb"))]
 impl From<&LibP2PConfig> for MemoryStoreConfig {
 	fn from(cfg: &LibP2PConfig) -> Self {
 		MemoryStoreConfig {
 			max_records: cfg.kademlia.max_kad_record_number, // ~2hrs
 			max_value_bytes: cfg.kademlia.max_kad_record_size + 1,
+			max_bytes: cfg.kademlia.max_kad_store_bytes,
+			evict_expiring: cfg.kademlia.kad_store_eviction,
 			providers: ProvidersConfig {
 				max_providers_per_key: usize::from(cfg.kademlia.record_replication_factor), // Needs to match the replication factor, per libp2p docs
 				max_provided_keys: cfg.kademlia.max_kad_provided_keys,
 			},
+			network_prefix: cfg.kademlia.network_prefix.clone(),
+			validation: cfg.kademlia.record_validation.clone(),
 		}
 	}
 }
@@ -605,21 +1174,40 @@ impl From<&LibP2PConfig> for RocksDBStoreConfig {
 				max_providers_per_key: usize::from(cfg.kademlia.record_replication_factor), // Needs to match the replication factor, per libp2p docs
 				max_provided_keys: cfg.kademlia.max_kad_provided_keys,
 			},
+			network_prefix: cfg.kademlia.network_prefix.clone(),
+			compression: cfg.kademlia.kad_store_compression,
+			validation: cfg.kademlia.record_validation.clone(),
 		}
 	}
 }
 
-impl From<(&RuntimeConfig, IdentifyConfig)> for LibP2PConfig {
-	fn from(pair: (&RuntimeConfig, IdentifyConfig)) -> Self {
+impl TryFrom<(&RuntimeConfig, IdentifyConfig)> for LibP2PConfig {
+	type Error = Report;
+
+	fn try_from(pair: (&RuntimeConfig, IdentifyConfig)) -> Result<Self, Self::Error> {
 		let (val, identify) = pair;
-		Self {
+		let allowed_versions = val
+			.allowed_agent_versions
+			.as_deref()
+			.map(VersionReq::parse)
+			.transpose()
+			.wrap_err("Invalid allowed agent versions")?;
+
+		Ok(Self {
 			secret_key: val.secret_key.clone(),
 			port: val.port,
+			transport_security: val.transport_security,
+			pnet_psk: val.pnet_psk.clone(),
+			routing_table_snapshot_path: val.routing_table_snapshot_path.clone(),
+			put_retry: val.put_retry.clone(),
+			inbound_rate_limit: val.inbound_rate_limit.clone(),
 			identify,
 			autonat: val.into(),
 			kademlia: val.into(),
 			relays: val.relays.iter().map(Into::into).collect(),
-			bootstrap_interval: Duration::from_secs(val.bootstrap_period),
+			external_addresses: val.external_addresses.clone(),
+			nat_pmp_fallback: val.nat_pmp_fallback,
+			mdns_enabled: val.mdns_enabled,
 			connection_idle_timeout: Duration::from_secs(val.connection_idle_timeout),
 			max_negotiating_inbound_streams: val.max_negotiating_inbound_streams,
 			task_command_buffer_size: std::num::NonZeroUsize::new(val.task_command_buffer_size)
@@ -627,8 +1215,24 @@ impl From<(&RuntimeConfig, IdentifyConfig)> for LibP2PConfig {
 			per_connection_event_buffer_size: val.per_connection_event_buffer_size,
 			dial_concurrency_factor: std::num::NonZeroU8::new(val.dial_concurrency_factor)
 				.expect("Invalid dial concurrency factor"),
+			max_established_incoming: val.max_established_incoming,
+			max_established_outgoing: val.max_established_outgoing,
+			max_pending_outgoing: val.max_pending_outgoing,
+			yamux_receive_window_size: val.yamux_receive_window_size,
+			yamux_max_buffer_size: val.yamux_max_buffer_size,
+			yamux_max_num_streams: val.yamux_max_num_streams,
+			dial_backoff: DialBackoffConfig {
+				initial_backoff: Duration::from_secs(val.dial_backoff_initial),
+				max_backoff: Duration::from_secs(val.dial_backoff_max),
+			},
+			max_dial_failures: val.max_dial_failures,
+			agent_policy: AgentPolicy {
+				allowed_prefixes: val.allowed_agent_prefixes.clone(),
+				allowed_versions,
+				disconnect: val.disconnect_disallowed_agents,
+			},
 			genesis_hash: val.genesis_hash.clone(),
-		}
+		})
 	}
 }
 
@@ -636,15 +1240,26 @@ impl From<(&RuntimeConfig, IdentifyConfig)> for LibP2PConfig {
 #[derive(Clone)]
 pub struct KademliaConfig {
 	pub kad_record_ttl: Duration,
+	pub kad_row_record_ttl: Duration,
 	pub record_replication_factor: NonZeroUsize,
 	pub record_replication_interval: Option<Duration>,
 	pub publication_interval: Option<Duration>,
+	pub record_republish_window: Option<Duration>,
+	pub record_republish_limit: Option<usize>,
+	pub put_in_flight_limit: Option<usize>,
+	pub network_prefix: Option<String>,
 	pub query_timeout: Duration,
 	pub query_parallelism: NonZeroUsize,
+	pub max_packet_size: usize,
 	pub caching_max_peers: u16,
 	pub disjoint_query_paths: bool,
+	pub store_backend: KademliaStoreBackend,
 	pub max_kad_record_number: usize,
 	pub max_kad_record_size: usize,
+	pub max_kad_store_bytes: Option<usize>,
+	pub kad_store_eviction: bool,
+	pub kad_store_compression: bool,
+	pub record_validation: Option<RecordValidationConfig>,
 	pub max_kad_provided_keys: usize,
 	pub kademlia_mode: KademliaMode,
 	pub automatic_server_mode: bool,
@@ -654,17 +1269,36 @@ impl From<&RuntimeConfig> for KademliaConfig {
 	fn from(val: &RuntimeConfig) -> Self {
 		Self {
 			kad_record_ttl: Duration::from_secs(val.kad_record_ttl),
+			kad_row_record_ttl: Duration::from_secs(val.row_record_ttl()),
 			record_replication_factor: std::num::NonZeroUsize::new(val.replication_factor as usize)
 				.expect("Invalid replication factor"),
 			record_replication_interval: Some(Duration::from_secs(val.replication_interval.into())),
-			publication_interval: Some(Duration::from_secs(val.publication_interval.into())),
+			publication_interval: (val.publication_interval > 0)
+				.then(|| Duration::from_secs(val.publication_interval.into())),
+			record_republish_window: (val.record_republish_window > 0)
+				.then(|| Duration::from_secs(val.record_republish_window.into())),
+			record_republish_limit: (val.record_republish_limit > 0)
+				.then_some(val.record_republish_limit),
+			put_in_flight_limit: (val.dht_put_in_flight_limit > 0)
+				.then_some(val.dht_put_in_flight_limit),
+			network_prefix: val.network_prefix.clone(),
 			query_timeout: Duration::from_secs(val.query_timeout.into()),
 			query_parallelism: std::num::NonZeroUsize::new(val.query_parallelism as usize)
 				.expect("Invalid query parallelism value"),
+			max_packet_size: val.kad_max_packet_size as usize,
 			caching_max_peers: val.caching_max_peers,
 			disjoint_query_paths: val.disjoint_query_paths,
+			store_backend: val.kad_store_backend,
 			max_kad_record_number: val.max_kad_record_number as usize,
 			max_kad_record_size: val.max_kad_record_size as usize,
+			max_kad_store_bytes: (val.max_kad_store_bytes > 0)
+				.then_some(val.max_kad_store_bytes as usize),
+			kad_store_eviction: val.kad_store_eviction,
+			kad_store_compression: val.kad_store_compression,
+			record_validation: val.kad_record_validation.then(|| RecordValidationConfig {
+				recency_window: (val.kad_record_recency_window > 0)
+					.then_some(val.kad_record_recency_window),
+			}),
 			max_kad_provided_keys: val.max_kad_provided_keys as usize,
 			kademlia_mode: val.operation_mode,
 			automatic_server_mode: val.automatic_server_mode,
@@ -769,22 +1403,51 @@ impl AgentVersion {
 	}
 }
 
+/// Agent versions of the peers allowed in the routing table (see [RuntimeConfig] for details)
+#[derive(Clone, Default)]
+pub struct AgentPolicy {
+	pub allowed_prefixes: Vec<String>,
+	pub allowed_versions: Option<VersionReq>,
+	pub disconnect: bool,
+}
+
+impl AgentPolicy {
+	pub fn allows(&self, agent_version: &str) -> bool {
+		let is_prefix_allowed = self.allowed_prefixes.is_empty()
+			|| self
+				.allowed_prefixes
+				.iter()
+				.any(|prefix| agent_version.starts_with(prefix));
+
+		let is_version_allowed = self.allowed_versions.as_ref().map_or(true, |allowed| {
+			AgentVersion::from_str(agent_version)
+				.ok()
+				.and_then(|agent| Version::parse(&agent.release_version).ok())
+				.is_some_and(|version| allowed.matches(&version))
+		});
+
+		is_prefix_allowed && is_version_allowed
+	}
+}
+
 /// Sync client configuration (see [RuntimeConfig] for details)
 #[derive(Clone)]
 pub struct SyncClientConfig {
-	pub confidence: f64,
+	pub sampling: SamplingConfig,
 	pub disable_rpc: bool,
 	pub dht_parallelization_limit: usize,
 	pub is_last_step: bool,
+	pub direction: SyncDirection,
 }
 
 impl From<&RuntimeConfig> for SyncClientConfig {
 	fn from(val: &RuntimeConfig) -> Self {
 		SyncClientConfig {
-			confidence: val.confidence,
+			sampling: val.into(),
 			disable_rpc: val.disable_rpc,
 			dht_parallelization_limit: val.dht_parallelization_limit,
-			is_last_step: val.app_id.is_none(),
+			is_last_step: val.app_ids().is_empty(),
+			direction: val.sync_direction,
 		}
 	}
 }
@@ -823,16 +1486,28 @@ impl From<&RuntimeConfig> for OtelConfig {
 	}
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct MaintenanceConfig {
 	pub block_confidence_treshold: f64,
 	pub replication_factor: u16,
 	pub query_timeout: u32,
-	pub pruning_interval: u32,
-	pub telemetry_flush_interval: u32,
+	pub prune_interval: u64,
+	pub kademlia_shrink_interval: u64,
+	pub kademlia_mode_interval: u64,
+	pub telemetry_flush_interval: u64,
 	pub automatic_server_mode: bool,
 	pub total_memory_gb_threshold: f64,
 	pub num_cpus_threshold: usize,
+	pub avail_path: String,
+	pub max_kad_record_number: u64,
+	pub memory_pressure_threshold: f64,
+	pub disk_pressure_threshold: f64,
+	pub store_pressure_threshold: f64,
+	pub bootstrap_period: u64,
+	pub routing_table_check_interval: u64,
+	pub routing_table_min_peers: usize,
+	pub jitter: f64,
+	pub ledger_retention: u64,
 }
 
 impl From<&RuntimeConfig> for MaintenanceConfig {
@@ -841,11 +1516,23 @@ impl From<&RuntimeConfig> for MaintenanceConfig {
 			block_confidence_treshold: val.confidence,
 			replication_factor: val.replication_factor,
 			query_timeout: val.query_timeout,
-			pruning_interval: val.store_pruning_interval,
-			telemetry_flush_interval: val.ot_flush_block_interval,
+			prune_interval: val.store_pruning_period,
+			kademlia_shrink_interval: val.kademlia_shrink_period,
+			kademlia_mode_interval: val.kademlia_mode_check_period,
+			telemetry_flush_interval: val.ot_flush_period,
 			automatic_server_mode: val.automatic_server_mode,
 			total_memory_gb_threshold: val.total_memory_gb_threshold,
 			num_cpus_threshold: val.num_cpus_threshold,
+			avail_path: val.avail_path.clone(),
+			max_kad_record_number: val.max_kad_record_number,
+			memory_pressure_threshold: val.memory_pressure_threshold,
+			disk_pressure_threshold: val.disk_pressure_threshold,
+			store_pressure_threshold: val.store_pressure_threshold,
+			bootstrap_period: val.bootstrap_period,
+			routing_table_check_interval: val.routing_table_check_interval,
+			routing_table_min_peers: val.routing_table_min_peers,
+			jitter: val.maintenance_jitter,
+			ledger_retention: val.ledger_retention,
 		}
 	}
 }
@@ -855,8 +1542,13 @@ impl Default for RuntimeConfig {
 		RuntimeConfig {
 			http_server_host: "127.0.0.1".to_owned(),
 			http_server_port: 7007,
+			api_keys: vec![],
+			api_jwt_secret: None,
 			port: 37000,
 			ws_transport_enable: false,
+			transport_security: TransportSecurity::Noise,
+			pnet_psk: None,
+			routing_table_snapshot_path: None,
 			secret_key: None,
 			autonat_only_global_ips: false,
 			autonat_refresh_interval: 360,
@@ -865,48 +1557,123 @@ impl Default for RuntimeConfig {
 			autonat_boot_delay: 5,
 			bootstraps: vec![],
 			bootstrap_period: 3600,
+			routing_table_check_interval: 60,
+			routing_table_min_peers: 10,
+			bootstrap_dns_refresh_interval: 3600,
 			relays: Vec::new(),
+			listen_addresses: vec![],
+			external_addresses: Vec::new(),
+			nat_pmp_fallback: false,
+			mdns_enabled: true,
 			full_node_ws: vec!["ws://127.0.0.1:9944".to_owned()],
+			dht_only: false,
+			rpc_health_check_interval: 60,
+			max_backfill_blocks: 100,
+			rpc_cross_check: false,
+			rpc_rate_limit: None,
+			alerts: None,
 			genesis_hash: "DEV".to_owned(),
 			app_id: None,
+			app_ids: vec![],
 			confidence: 99.9,
 			avail_path: "avail_path".to_owned(),
 			log_level: "INFO".to_owned(),
 			log_format_json: false,
+			log_module_levels: HashMap::new(),
 			ot_collector_endpoint: "http://127.0.0.1:4317".to_string(),
 			ot_export_period: 300,
 			ot_export_timeout: 10,
-			ot_flush_block_interval: 15,
+			ot_flush_period: 300,
+			ot_flush_block_interval: None,
+			ot_traces: false,
 			total_memory_gb_threshold: 16.0,
 			num_cpus_threshold: 4,
+			memory_pressure_threshold: 90.0,
+			disk_pressure_threshold: 95.0,
+			store_pressure_threshold: 95.0,
 			disable_rpc: false,
+			fetch_strategy: FetchStrategy::DhtFirst,
 			dht_parallelization_limit: 20,
+			dht_max_parallelization_limit: 0,
+			dht_fetch_timeout: 20,
+			dht_cell_cache_size: 1024,
+			commitment_cache_size: 128,
+			sampling_max_rounds: 3,
+			sampling_withheld_fraction: 0.5,
+			sampling_min_cells: 1,
+			sampling_max_cells: 64,
 			query_proof_rpc_parallel_tasks: 8,
+			proof_verification_threads: None,
 			block_processing_delay: Some(20),
+			head_lag_threshold: 5,
+			ready_min_peers: 1,
+			replication: false,
 			block_matrix_partition: None,
 			sync_start_block: None,
+			sync_direction: SyncDirection::Forward,
 			sync_finality_enable: false,
+			finality_checkpoint: None,
 			max_cells_per_rpc: Some(30),
 			kad_record_ttl: 24 * 60 * 60,
+			kad_row_record_ttl: None,
 			threshold: 5000,
 			replication_factor: 5,
+			dht_put_quorum: DHTQuorum::One,
+			dht_get_quorum: DHTQuorum::One,
+			dht_put_in_flight_limit: 1000,
+			put_retry: None,
+			inbound_rate_limit: None,
+			dht_provider_mode: false,
+			network_prefix: None,
 			publication_interval: 12 * 60 * 60,
 			replication_interval: 3 * 60 * 60,
+			record_republish_window: 0,
+			record_republish_limit: 10000,
 			connection_idle_timeout: 30,
 			max_negotiating_inbound_streams: 128,
 			task_command_buffer_size: 32,
 			per_connection_event_buffer_size: 7,
 			dial_concurrency_factor: 8,
-			store_pruning_interval: 180,
+			max_established_incoming: None,
+			max_established_outgoing: None,
+			max_pending_outgoing: None,
+			yamux_receive_window_size: None,
+			yamux_max_buffer_size: None,
+			yamux_max_num_streams: None,
+			dial_backoff_initial: 10,
+			dial_backoff_max: 3600,
+			max_dial_failures: 5,
+			allowed_agent_prefixes: vec![],
+			allowed_agent_versions: None,
+			disconnect_disallowed_agents: false,
+			store_pruning_period: 3600,
+			store_pruning_interval: None,
+			kademlia_shrink_period: 60,
+			kademlia_mode_check_period: 60,
+			maintenance_jitter: 0.1,
+			ledger_retention: 604800,
+			header_retention: 14400,
 			query_timeout: 10,
 			query_parallelism: 3,
+			kad_max_packet_size: 16384,
 			caching_max_peers: 1,
 			disjoint_query_paths: false,
+			kad_store_backend: KademliaStoreBackend::RocksDB,
 			max_kad_record_number: 2400000,
 			max_kad_record_size: 8192,
+			max_kad_store_bytes: 0,
+			kad_store_eviction: false,
+			kad_store_compression: false,
+			kad_record_validation: true,
+			kad_record_recency_window: 0,
+			verify_inbound_cell_proofs: false,
+			sign_dht_records: false,
+			require_signed_dht_records: false,
 			max_kad_provided_keys: 1024,
 			#[cfg(feature = "crawl")]
 			crawl: crate::crawl_client::CrawlConfig::default(),
+			#[cfg(feature = "grpc")]
+			grpc: crate::api::grpc::GrpcConfig::default(),
 			origin: Origin::External,
 			operation_mode: KademliaMode::Client,
 			retry_config: RetryConfig::Fibonacci(FibonacciConfig {
@@ -920,12 +1687,114 @@ impl Default for RuntimeConfig {
 	}
 }
 
+/// Runtime parameters which can be changed without the restart, through the admin API
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RuntimeParameters {
+	pub log_level: Option<String>,
+	pub dht_parallelization_limit: Option<usize>,
+	pub confidence: Option<f64>,
+	pub full_node_ws: Option<Vec<String>>,
+	pub total_memory_gb_threshold: Option<f64>,
+	pub num_cpus_threshold: Option<usize>,
+}
+
+impl From<&RuntimeConfig> for RuntimeParameters {
+	fn from(val: &RuntimeConfig) -> Self {
+		RuntimeParameters {
+			log_level: Some(val.log_level.clone()),
+			dht_parallelization_limit: Some(val.dht_parallelization_limit),
+			confidence: Some(val.confidence),
+			full_node_ws: Some(val.full_node_ws.clone()),
+			total_memory_gb_threshold: Some(val.total_memory_gb_threshold),
+			num_cpus_threshold: Some(val.num_cpus_threshold),
+		}
+	}
+}
+
+impl RuntimeParameters {
+	/// Overrides the parameters with the ones set in the other parameters
+	pub fn merge(&mut self, other: RuntimeParameters) {
+		self.log_level = other.log_level.or(self.log_level.take());
+		self.dht_parallelization_limit = other
+			.dht_parallelization_limit
+			.or(self.dht_parallelization_limit);
+		self.confidence = other.confidence.or(self.confidence);
+		self.full_node_ws = other.full_node_ws.or(self.full_node_ws.take());
+		self.total_memory_gb_threshold = other
+			.total_memory_gb_threshold
+			.or(self.total_memory_gb_threshold);
+		self.num_cpus_threshold = other.num_cpus_threshold.or(self.num_cpus_threshold);
+	}
+
+	/// Returns the set parameters which differ from the current ones
+	pub fn changed(&self, current: &RuntimeParameters) -> RuntimeParameters {
+		fn changed<T: Clone + PartialEq>(value: &Option<T>, current: &Option<T>) -> Option<T> {
+			value
+				.clone()
+				.filter(|value| current.as_ref() != Some(value))
+		}
+		RuntimeParameters {
+			log_level: changed(&self.log_level, &current.log_level),
+			dht_parallelization_limit: changed(
+				&self.dht_parallelization_limit,
+				&current.dht_parallelization_limit,
+			),
+			confidence: changed(&self.confidence, &current.confidence),
+			full_node_ws: changed(&self.full_node_ws, &current.full_node_ws),
+			total_memory_gb_threshold: changed(
+				&self.total_memory_gb_threshold,
+				&current.total_memory_gb_threshold,
+			),
+			num_cpus_threshold: changed(&self.num_cpus_threshold, &current.num_cpus_threshold),
+		}
+	}
+
+	/// Overrides the configuration with the set parameters
+	pub fn apply(&self, cfg: &mut RuntimeConfig) {
+		if let Some(log_level) = &self.log_level {
+			cfg.log_level = log_level.clone();
+		}
+		if let Some(dht_parallelization_limit) = self.dht_parallelization_limit {
+			cfg.dht_parallelization_limit = dht_parallelization_limit;
+		}
+		if let Some(confidence) = self.confidence {
+			cfg.confidence = confidence;
+		}
+		if let Some(full_node_ws) = &self.full_node_ws {
+			cfg.full_node_ws = full_node_ws.clone();
+		}
+		if let Some(total_memory_gb_threshold) = self.total_memory_gb_threshold {
+			cfg.total_memory_gb_threshold = total_memory_gb_threshold;
+		}
+		if let Some(num_cpus_threshold) = self.num_cpus_threshold {
+			cfg.num_cpus_threshold = num_cpus_threshold;
+		}
+	}
+}
+
 impl RuntimeConfig {
 	/// A range bounded inclusively below and exclusively above
 	pub fn sync_range(&self, end: u32) -> Range<u32> {
 		let start = self.sync_start_block.unwrap_or(end);
 		Range { start, end }
 	}
+
+	/// Returns the keys of the reloaded configuration parameters which differ from this configuration,
+	/// and which cannot be changed without the restart
+	pub fn non_reloadable_changes(&self, reloaded: &RuntimeConfig) -> Result<Vec<String>> {
+		let mut reloaded = reloaded.clone();
+		RuntimeParameters::from(self).apply(&mut reloaded);
+		let (serde_json::Value::Object(current), serde_json::Value::Object(reloaded)) =
+			(serde_json::to_value(self)?, serde_json::to_value(reloaded)?)
+		else {
+			return Err(eyre!("Configuration is not serialized as a map"));
+		};
+		Ok(current
+			.into_iter()
+			.filter(|(key, value)| reloaded.get(key) != Some(value))
+			.map(|(key, _)| key)
+			.collect())
+	}
 }
 
 pub struct IdentityConfig {
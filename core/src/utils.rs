@@ -7,9 +7,7 @@ use avail_subxt::{
 		da_control::pallet::Call,
 		da_runtime::RuntimeCall,
 	},
-	primitives::{
-		grandpa::AuthorityId, grandpa::ConsensusLog, AppUncheckedExtrinsic, Header as DaHeader,
-	},
+	primitives::AppUncheckedExtrinsic,
 	utils::H256,
 };
 use codec::Decode;
@@ -22,6 +20,8 @@ use kate_recovery::{
 	data::Cell,
 	matrix::{Dimensions, Position},
 };
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
 use tracing::Instrument;
 
 pub fn spawn_in_span<F>(future: F) -> tokio::task::JoinHandle<F::Output>
@@ -42,11 +42,6 @@ pub fn decode_app_data(data: &[u8]) -> Result<Option<Vec<u8>>> {
 	}
 }
 
-/// Calculates confidence from given number of verified cells
-pub fn calculate_confidence(count: u32) -> f64 {
-	100f64 * (1f64 - 1f64 / 2u32.pow(count) as f64)
-}
-
 pub trait OptionalExtension {
 	fn option(&self) -> Option<&Self>;
 }
@@ -58,6 +53,15 @@ impl OptionalExtension for HeaderExtension {
 	}
 }
 
+/// Extension of the header which may not be stored
+impl OptionalExtension for Option<HeaderExtension> {
+	fn option(&self) -> Option<&Self> {
+		self.as_ref()
+			.and_then(|extension| extension.option())
+			.map(|_| self)
+	}
+}
+
 /// Extract fields from extension header
 pub(crate) fn extract_kate(extension: &HeaderExtension) -> Option<(u16, u16, H256, Vec<u8>)> {
 	match &extension.option()? {
@@ -94,27 +98,6 @@ pub(crate) fn extract_app_lookup(extension: &HeaderExtension) -> eyre::Result<Op
 		.map_err(|e| eyre!("Invalid DataLookup: {}", e))
 }
 
-pub fn filter_auth_set_changes(header: &DaHeader) -> Vec<Vec<(AuthorityId, u64)>> {
-	let new_auths = header
-		.digest
-		.logs
-		.iter()
-		.filter_map(|e| match &e {
-			// UGHHH, why won't the b"FRNK" just work
-			avail_subxt::config::substrate::DigestItem::Consensus(
-				[b'F', b'R', b'N', b'K'],
-				data,
-			) => match ConsensusLog::<u32>::decode(&mut data.as_slice()) {
-				Ok(ConsensusLog::ScheduledChange(x)) => Some(x.next_authorities),
-				Ok(ConsensusLog::ForcedChange(_, x)) => Some(x.next_authorities),
-				_ => None,
-			},
-			_ => None,
-		})
-		.collect::<Vec<_>>();
-	new_auths
-}
-
 // TODO: Remove unused functions if not needed after next iteration
 
 #[allow(dead_code)]
@@ -137,14 +120,86 @@ fn diff_positions(positions: &[Position], cells: &[Cell]) -> Vec<Position> {
 		.collect::<Vec<_>>()
 }
 
+/// Overrides the configuration fields with the variables whose names start with the `prefix`.
+/// Rest of the variable name is the upper case field name, with `__` separating the nested fields,
+/// e.g. `AVAIL_LC_ALERTS__WEBHOOK_URL` overrides `webhook_url` in the `alerts` table.
+/// Field names are the configuration keys, so the top level keys are not grouped (e.g. `AVAIL_LC_PORT`).
+/// Values are parsed as JSON, except for the string fields, which are taken as is.
+/// Configuration is returned unchanged if none of the variables is prefixed.
+pub fn override_with_vars<T: Serialize + DeserializeOwned>(
+	config: T,
+	prefix: &str,
+	vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<T> {
+	let vars = vars
+		.into_iter()
+		.filter(|(name, _)| name.starts_with(prefix))
+		.collect::<Vec<_>>();
+	if vars.is_empty() {
+		return Ok(config);
+	}
+	let mut config = serde_json::to_value(config)?;
+	for (name, value) in vars {
+		let path = name[prefix.len()..].to_lowercase();
+		let mut keys = path.split("__").peekable();
+		let mut table = &mut config;
+		while let Some(key) = keys.next() {
+			if table.is_null() {
+				// unset optional tables are created by overriding their fields
+				*table = Value::Object(Map::new());
+			}
+			let Value::Object(fields) = table else {
+				return Err(eyre!(
+					"Cannot override {name}, parent of `{key}` is not a table"
+				));
+			};
+			if !fields.contains_key(key) && !fields.is_empty() {
+				return Err(eyre!(
+					"Cannot override {name}, field `{key}` does not exist"
+				));
+			}
+			let field = fields.entry(key.to_string()).or_insert(Value::Null);
+			if keys.peek().is_none() {
+				*field = match field {
+					Value::String(_) => Value::String(value),
+					_ => serde_json::from_str(&value).unwrap_or(Value::String(value)),
+				};
+				break;
+			}
+			table = field;
+		}
+	}
+	serde_path_to_error::deserialize(config).map_err(|error| {
+		eyre!(
+			"Invalid configuration override of `{}`: {}",
+			error.path(),
+			error.inner()
+		)
+	})
+}
+
+/// Deserializes the TOML configuration, the error contains the path of the invalid field
+pub fn parse_config<T: DeserializeOwned>(content: &str) -> Result<T> {
+	let mut deserializer = toml::Deserializer::new(content);
+	serde_path_to_error::deserialize(&mut deserializer)
+		.map_err(|error| eyre!("`{}`: {}", error.path(), error.inner()))
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{can_reconstruct, diff_positions};
+	use super::{can_reconstruct, diff_positions, override_with_vars, parse_config};
+	use crate::types::{block_matrix_partition_format, Origin, RuntimeConfig, SecretKey};
 	use kate_recovery::{
 		data::Cell,
 		matrix::{Dimensions, Position},
 	};
 
+	fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+		vars.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect()
+	}
+
 	fn position(row: u32, col: u16) -> Position {
 		Position { row, col }
 	}
@@ -203,4 +258,79 @@ mod tests {
 		assert_eq!(diff_positions(&positions, &cells)[0], position(0, 0));
 		assert_eq!(diff_positions(&positions, &cells)[1], position(1, 1));
 	}
+
+	#[test]
+	fn test_config_round_trip() {
+		let mut cfg = RuntimeConfig::default();
+		let json = serde_json::to_value(&cfg).unwrap();
+		assert!(serde_json::from_value::<RuntimeConfig>(json).is_ok());
+
+		cfg.block_matrix_partition = Some(block_matrix_partition_format::parse("1/2").unwrap());
+		cfg.origin = Origin::Other("test".to_string());
+		let json = serde_json::to_value(&cfg).unwrap();
+		let cfg = serde_json::from_value::<RuntimeConfig>(json).unwrap();
+		assert!(cfg.block_matrix_partition.is_some());
+		assert_eq!(cfg.origin, Origin::Other("test".to_string()));
+	}
+
+	#[test]
+	fn test_override_with_vars() {
+		let cfg = override_with_vars(
+			RuntimeConfig::default(),
+			"AVAIL_LC_",
+			vars(&[
+				("AVAIL_LC_PORT", "37001"),
+				("AVAIL_LC_HTTP_SERVER_HOST", "0.0.0.0"),
+				("AVAIL_LC_FULL_NODE_WS", r#"["ws://127.0.0.1:9944"]"#),
+				("AVAIL_LC_APP_ID", "1"),
+				("AVAIL_LC_SECRET_KEY__SEED", "avail"),
+				("OTHER_PORT", "1"),
+			]),
+		)
+		.unwrap();
+
+		assert_eq!(cfg.port, 37001);
+		assert_eq!(cfg.http_server_host, "0.0.0.0");
+		assert_eq!(cfg.full_node_ws, vec!["ws://127.0.0.1:9944"]);
+		assert_eq!(cfg.app_id, Some(1));
+		assert!(matches!(cfg.secret_key, Some(SecretKey::Seed { seed }) if seed == "avail"));
+	}
+
+	#[test]
+	fn test_parse_config() {
+		let cfg: RuntimeConfig = parse_config("port = 37001\nfull_node_ws = []").unwrap();
+		assert_eq!(cfg.port, 37001);
+		assert!(cfg.full_node_ws.is_empty());
+
+		// deprecated block based intervals are converted into the periods
+		let mut cfg: RuntimeConfig = parse_config(
+			"store_pruning_interval = 180
+ot_flush_block_interval = 15",
+		)
+		.unwrap();
+		cfg.convert_deprecated_intervals();
+		assert_eq!(cfg.store_pruning_period, 3600);
+		assert_eq!(cfg.ot_flush_period, 300);
+		assert_eq!(cfg.store_pruning_interval, None);
+
+		let error = parse_config::<RuntimeConfig>("port = \"port\"")
+			.unwrap_err()
+			.to_string();
+		assert!(error.starts_with("`port`: invalid type"), "{error}");
+	}
+
+	#[test]
+	fn test_invalid_override_with_vars() {
+		let override_var = |name, value| {
+			override_with_vars(
+				RuntimeConfig::default(),
+				"AVAIL_LC_",
+				vars(&[(name, value)]),
+			)
+		};
+		assert!(override_var("AVAIL_LC_UNKNOWN", "1").is_err());
+		assert!(override_var("AVAIL_LC_PORT", "port").is_err());
+		assert!(override_var("AVAIL_LC_PORT__NUMBER", "1").is_err());
+		assert!(override_var("AVAIL_LC_P2P__PORT", "37001").is_err());
+	}
 }
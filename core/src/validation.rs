@@ -0,0 +1,370 @@
+//! Validation of the configuration on startup.
+//!
+//! Cross-field constraints which cannot be checked while deserializing are checked before
+//! any of the services is started, and all violations are reported at once,
+//! each with the path of the violating field and the suggested fix.
+
+use color_eyre::{eyre::eyre, Result};
+use libp2p::multiaddr::Protocol;
+use semver::VersionReq;
+use std::{fmt, fs, path::Path};
+
+use crate::{
+	network::p2p::{is_dns_address, MIN_YAMUX_RECEIVE_WINDOW_SIZE},
+	types::{BootstrapConfig, RuntimeConfig},
+};
+
+/// Configuration constraint which is not satisfied
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+	/// Path of the violating field, e.g. `alerts.confidence_threshold`
+	pub field: String,
+	pub problem: String,
+	pub fix: String,
+}
+
+impl Violation {
+	fn new(field: impl Into<String>, problem: impl Into<String>, fix: impl Into<String>) -> Self {
+		Violation {
+			field: field.into(),
+			problem: problem.into(),
+			fix: fix.into(),
+		}
+	}
+}
+
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "`{}`: {}. {}.", self.field, self.problem, self.fix)
+	}
+}
+
+fn is_percentage(value: f64) -> bool {
+	value > 0.0 && value <= 100.0
+}
+
+fn kademlia_violations(cfg: &RuntimeConfig) -> Vec<Violation> {
+	let mut violations = vec![];
+	let ttl = cfg.kad_record_ttl;
+	let publication = cfg.publication_interval as u64;
+	let replication = cfg.replication_interval as u64;
+
+	if ttl == 0 {
+		violations.push(Violation::new(
+			"kad_record_ttl",
+			"record TTL is 0",
+			"Set `kad_record_ttl` to the number of seconds the records are kept (e.g. 86400)",
+		));
+	}
+	if cfg.kad_row_record_ttl == Some(0) {
+		violations.push(Violation::new(
+			"kad_row_record_ttl",
+			"row record TTL is 0",
+			"Remove `kad_row_record_ttl` to use `kad_record_ttl`, or set it to the number of seconds the rows are kept",
+		));
+	}
+	if publication > 0 && publication >= ttl {
+		violations.push(Violation::new(
+			"publication_interval",
+			format!("publication interval ({publication} sec) is not shorter than the record TTL ({ttl} sec)"),
+			"Set `publication_interval` significantly shorter than `kad_record_ttl`, or to 0 to disable the publication",
+		));
+	}
+	if publication > 0 && replication >= publication {
+		violations.push(Violation::new(
+			"replication_interval",
+			format!("replication interval ({replication} sec) is not shorter than the publication interval ({publication} sec)"),
+			"Set `replication_interval` significantly shorter than `publication_interval`",
+		));
+	} else if replication >= ttl {
+		violations.push(Violation::new(
+			"replication_interval",
+			format!("replication interval ({replication} sec) is not shorter than the record TTL ({ttl} sec)"),
+			"Set `replication_interval` significantly shorter than `kad_record_ttl`",
+		));
+	}
+	let shortest_ttl = ttl.min(cfg.row_record_ttl());
+	let republish_window = cfg.record_republish_window as u64;
+	if republish_window > 0 && republish_window >= shortest_ttl {
+		violations.push(Violation::new(
+			"record_republish_window",
+			format!("republish window ({republish_window} sec) is not shorter than the record TTL ({shortest_ttl} sec)"),
+			"Set `record_republish_window` shorter than `kad_record_ttl` and `kad_row_record_ttl`, or to 0 to disable the republish",
+		));
+	}
+	if cfg.replication_factor == 0 {
+		violations.push(Violation::new(
+			"replication_factor",
+			"replication factor is 0",
+			"Set `replication_factor` to the number of peers the records are stored on (e.g. 5)",
+		));
+	}
+	if cfg.query_parallelism == 0 {
+		violations.push(Violation::new(
+			"query_parallelism",
+			"query parallelism is 0",
+			"Set `query_parallelism` to the number of parallel requests per query (e.g. 3)",
+		));
+	}
+	violations
+}
+
+fn p2p_violations(cfg: &RuntimeConfig) -> Vec<Violation> {
+	let mut violations = vec![];
+	for (index, bootstrap) in cfg.bootstraps.iter().enumerate() {
+		let BootstrapConfig::Address(address) = bootstrap else {
+			continue;
+		};
+		let has_peer_id = matches!(address.iter().last(), Some(Protocol::P2p(_)));
+		if !has_peer_id && !is_dns_address(address) {
+			violations.push(Violation::new(
+				format!("bootstraps[{index}]"),
+				format!("bootstrap address {address} has no peer ID"),
+				"Append `/p2p/<peer-id>` of the bootstrap node to the address, or use a `/dnsaddr` address",
+			));
+		}
+	}
+	if cfg.task_command_buffer_size == 0 {
+		violations.push(Violation::new(
+			"task_command_buffer_size",
+			"task command buffer size is 0",
+			"Set `task_command_buffer_size` greater than 0 (e.g. 32)",
+		));
+	}
+	if cfg.dial_concurrency_factor == 0 {
+		violations.push(Violation::new(
+			"dial_concurrency_factor",
+			"dial concurrency factor is 0",
+			"Set `dial_concurrency_factor` greater than 0 (e.g. 8)",
+		));
+	}
+	if let Some(window_size) = cfg
+		.yamux_receive_window_size
+		.filter(|&window_size| window_size < MIN_YAMUX_RECEIVE_WINDOW_SIZE)
+	{
+		violations.push(Violation::new(
+			"yamux_receive_window_size",
+			format!("yamux receive window size {window_size} is smaller than {MIN_YAMUX_RECEIVE_WINDOW_SIZE} bytes"),
+			format!("Set `yamux_receive_window_size` at least {MIN_YAMUX_RECEIVE_WINDOW_SIZE}, or remove it to use the default"),
+		));
+	}
+	if let Some(Err(error)) = cfg.allowed_agent_versions.as_deref().map(VersionReq::parse) {
+		violations.push(Violation::new(
+			"allowed_agent_versions",
+			format!("version requirement is not valid: {error}"),
+			"Set `allowed_agent_versions` to a semver requirement (e.g. \">=1.10.0\")",
+		));
+	}
+	violations
+}
+
+fn threshold_violations(cfg: &RuntimeConfig) -> Vec<Violation> {
+	let mut violations = vec![];
+	if !(cfg.confidence > 0.0 && cfg.confidence < 100.0) {
+		violations.push(Violation::new(
+			"confidence",
+			format!("confidence {} is not between 0 and 100", cfg.confidence),
+			"Set `confidence` between 0 and 100, exclusive (e.g. 99.9)",
+		));
+	}
+	if !(cfg.total_memory_gb_threshold > 0.0 && cfg.total_memory_gb_threshold.is_finite()) {
+		violations.push(Violation::new(
+			"total_memory_gb_threshold",
+			"total memory threshold is not positive",
+			"Set `total_memory_gb_threshold` greater than 0 (e.g. 16.0)",
+		));
+	}
+	if cfg.num_cpus_threshold == 0 {
+		violations.push(Violation::new(
+			"num_cpus_threshold",
+			"number of CPUs threshold is 0",
+			"Set `num_cpus_threshold` greater than 0 (e.g. 4)",
+		));
+	}
+	for (field, value) in [
+		("memory_pressure_threshold", cfg.memory_pressure_threshold),
+		("disk_pressure_threshold", cfg.disk_pressure_threshold),
+		("store_pressure_threshold", cfg.store_pressure_threshold),
+	] {
+		if !is_percentage(value) {
+			violations.push(Violation::new(
+				field,
+				format!("pressure threshold {value} is not a percentage"),
+				format!("Set `{field}` greater than 0 and at most 100"),
+			));
+		}
+	}
+	if !(cfg.sampling_withheld_fraction > 0.0 && cfg.sampling_withheld_fraction < 1.0) {
+		violations.push(Violation::new(
+			"sampling_withheld_fraction",
+			format!(
+				"withheld fraction {} is not between 0 and 1",
+				cfg.sampling_withheld_fraction
+			),
+			"Set `sampling_withheld_fraction` between 0 and 1, exclusive (e.g. 0.5)",
+		));
+	}
+	if cfg.sampling_min_cells > cfg.sampling_max_cells {
+		violations.push(Violation::new(
+			"sampling_min_cells",
+			format!(
+				"minimum number of cells ({}) is greater than the maximum ({})",
+				cfg.sampling_min_cells, cfg.sampling_max_cells
+			),
+			"Set `sampling_min_cells` at most `sampling_max_cells`",
+		));
+	}
+	if cfg.dht_parallelization_limit == 0 {
+		violations.push(Violation::new(
+			"dht_parallelization_limit",
+			"DHT parallelization limit is 0",
+			"Set `dht_parallelization_limit` greater than 0 (e.g. 20)",
+		));
+	}
+	if let Some(alerts) = &cfg.alerts {
+		if !is_percentage(alerts.confidence_threshold) {
+			violations.push(Violation::new(
+				"alerts.confidence_threshold",
+				format!(
+					"confidence threshold {} is not a percentage",
+					alerts.confidence_threshold
+				),
+				"Set `confidence_threshold` in the `alerts` table greater than 0 and at most 100",
+			));
+		}
+	}
+	violations
+}
+
+fn period_violations(cfg: &RuntimeConfig) -> Vec<Violation> {
+	let mut violations = vec![];
+	if !(0.0..=1.0).contains(&cfg.maintenance_jitter) {
+		violations.push(Violation::new(
+			"maintenance_jitter",
+			format!(
+				"maintenance jitter {} is not between 0 and 1",
+				cfg.maintenance_jitter
+			),
+			"Set `maintenance_jitter` to a fraction of the period between 0 and 1 (e.g. 0.1)",
+		));
+	}
+	violations
+}
+
+/// Returns the violated constraints which can be checked without accessing the file system
+pub fn violations(cfg: &RuntimeConfig) -> Vec<Violation> {
+	[
+		kademlia_violations(cfg),
+		p2p_violations(cfg),
+		threshold_violations(cfg),
+		period_violations(cfg),
+	]
+	.concat()
+}
+
+fn check_writable(path: &Path) -> std::io::Result<()> {
+	fs::create_dir_all(path)?;
+	let probe = path.join(".write_check");
+	fs::write(&probe, [])?;
+	fs::remove_file(probe)
+}
+
+/// Checks the configuration constraints, and that the database path is writable.
+/// All violations are reported in the returned error.
+pub fn validate(cfg: &RuntimeConfig) -> Result<()> {
+	let mut violations = violations(cfg);
+	if let Err(error) = check_writable(Path::new(&cfg.avail_path)) {
+		violations.push(Violation::new(
+			"avail_path",
+			format!("database path {} is not writable: {error}", cfg.avail_path),
+			"Set `avail_path` to a directory writable by the light client, or fix its permissions",
+		));
+	}
+	if violations.is_empty() {
+		return Ok(());
+	}
+	let violations = violations
+		.iter()
+		.map(|violation| format!("\n  - {violation}"))
+		.collect::<String>();
+	Err(eyre!("Configuration is not valid:{violations}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::MultiaddrConfig;
+	use libp2p::Multiaddr;
+	use std::str::FromStr;
+
+	fn fields(cfg: &RuntimeConfig) -> Vec<String> {
+		violations(cfg)
+			.into_iter()
+			.map(|violation| violation.field)
+			.collect()
+	}
+
+	#[test]
+	fn default_is_valid() {
+		assert!(violations(&RuntimeConfig::default()).is_empty());
+	}
+
+	#[test]
+	fn kademlia_intervals() {
+		let mut cfg = RuntimeConfig::default();
+		cfg.kad_record_ttl = 3600;
+		assert_eq!(fields(&cfg), vec!["publication_interval"]);
+
+		cfg.publication_interval = 0;
+		assert_eq!(fields(&cfg), vec!["replication_interval"]);
+
+		cfg.replication_interval = 600;
+		cfg.record_republish_window = 1800;
+		cfg.kad_row_record_ttl = Some(1200);
+		assert_eq!(fields(&cfg), vec!["record_republish_window"]);
+	}
+
+	#[test]
+	fn bootstrap_without_peer_id() {
+		let address = |address| BootstrapConfig::Address(Multiaddr::from_str(address).unwrap());
+		let mut cfg = RuntimeConfig::default();
+		cfg.bootstraps = vec![
+			address("/dnsaddr/bootstrap.avail.tools"),
+			address("/ip4/127.0.0.1/tcp/39000"),
+			BootstrapConfig::Peer(MultiaddrConfig::Compact(
+				"/ip4/127.0.0.1/tcp/39000/p2p/12D3KooWStAKPADXqJ7cngPYXd2mSANpdgh1xQ34aouufHA2xShz"
+					.to_string()
+					.try_into()
+					.unwrap(),
+			)),
+		];
+		assert_eq!(fields(&cfg), vec!["bootstraps[1]"]);
+	}
+
+	#[test]
+	fn all_violations_reported() {
+		let mut cfg = RuntimeConfig::default();
+		cfg.confidence = 100.0;
+		cfg.num_cpus_threshold = 0;
+		cfg.disk_pressure_threshold = 0.0;
+		cfg.sampling_min_cells = 100;
+		cfg.yamux_receive_window_size = Some(1024);
+		cfg.maintenance_jitter = f64::NAN;
+		cfg.avail_path = std::env::temp_dir().to_string_lossy().to_string();
+		assert_eq!(
+			fields(&cfg),
+			vec![
+				"yamux_receive_window_size",
+				"confidence",
+				"num_cpus_threshold",
+				"disk_pressure_threshold",
+				"sampling_min_cells",
+				"maintenance_jitter"
+			]
+		);
+
+		let error = validate(&cfg).unwrap_err().to_string();
+		assert!(error.contains("`num_cpus_threshold`: number of CPUs threshold is 0."));
+		assert_eq!(error.matches("\n  - ").count(), 6);
+	}
+}